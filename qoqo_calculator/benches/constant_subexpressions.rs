@@ -0,0 +1,53 @@
+// Copyright © 2020-2021 HQS Quantum Simulations GmbH. All Rights Reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except
+// in compliance with the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the
+// License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either
+// express or implied. See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Measures Calculator::parse_str_assign for a generated script repeating the same
+//! parenthesized literal subexpressions in every statement.
+//!
+//! Run with `cargo bench --bench constant_subexpressions`.
+
+use qoqo_calculator::Calculator;
+use std::hint::black_box;
+use std::time::Instant;
+
+const ITERATIONS: usize = 200;
+const STATEMENTS: usize = 512;
+
+/// Script assigning rotation angles on a grid of 1024 steps, as generated for a circuit.
+fn generated_script() -> String {
+    (0..STATEMENTS)
+        .map(|index| {
+            format!(
+                "phi_{index}={index} * (2 * 3.141592653589793 / 1024) + theta \
+                 * (sqrt(2) / 2 * cos(2 * 3.141592653589793 / 1024) \
+                 - sqrt(2) / 2 * sin(2 * 3.141592653589793 / 1024))"
+            )
+        })
+        .collect::<Vec<String>>()
+        .join("; ")
+}
+
+fn main() {
+    let script = generated_script();
+    let mut calculator = Calculator::new();
+    calculator.set_variable("theta", 0.25).unwrap();
+    let start = Instant::now();
+    let mut sum = 0.0;
+    for _ in 0..ITERATIONS {
+        sum += calculator.parse_str_assign(black_box(&script)).unwrap();
+    }
+    let elapsed = start.elapsed();
+    println!(
+        "parse_str_assign({STATEMENTS} statements): {elapsed:?}, {:?} per call (checksum {sum})",
+        elapsed / ITERATIONS as u32
+    );
+}
@@ -0,0 +1,67 @@
+// Copyright © 2020-2021 HQS Quantum Simulations GmbH. All Rights Reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except
+// in compliance with the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the
+// License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either
+// express or implied. See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Compares CompiledExpression::evaluate_over for a time series to setting the variable
+//! and calling Calculator::parse_str for every point.
+//!
+//! Run with `cargo bench --bench evaluate_over`.
+
+use qoqo_calculator::{Calculator, CompiledExpression};
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+const POINTS: usize = 100_000;
+
+const REPETITIONS: usize = 20;
+
+/// Envelope with arithmetic, a power and a function call
+const EXPRESSION: &str = "amp * exp(-((t-t0)/w)^2)";
+
+fn run(name: &str, mut evaluate: impl FnMut() -> f64) -> Duration {
+    let start = Instant::now();
+    let mut sum = 0.0;
+    for _ in 0..REPETITIONS {
+        sum += black_box(evaluate());
+    }
+    let elapsed = start.elapsed() / REPETITIONS as u32;
+    println!(
+        "{name}: {elapsed:?} per {POINTS} points, {:?} per point (checksum {sum})",
+        elapsed / POINTS as u32
+    );
+    elapsed
+}
+
+fn main() {
+    let mut calculator = Calculator::new();
+    calculator.set_variable("amp", 0.5).unwrap();
+    calculator.set_variable("t0", 50.0).unwrap();
+    calculator.set_variable("w", 10.0).unwrap();
+    let times: Vec<f64> = (0..POINTS).map(|i| i as f64 * 1e-3).collect();
+    let mut out = vec![0.0; POINTS];
+
+    let naive = run("parse_str loop", || {
+        let mut calculator = calculator.clone();
+        for (time, value) in times.iter().zip(out.iter_mut()) {
+            calculator.set_variable("t", *time).unwrap();
+            *value = calculator.parse_str(black_box(EXPRESSION)).unwrap();
+        }
+        out.iter().sum()
+    });
+    let compiled = CompiledExpression::new(EXPRESSION);
+    let kernel = run("evaluate_over", || {
+        compiled
+            .evaluate_over("t", black_box(&times), &calculator, &mut out)
+            .unwrap();
+        out.iter().sum()
+    });
+    println!("speedup: {:.1}", naive.as_secs_f64() / kernel.as_secs_f64());
+}
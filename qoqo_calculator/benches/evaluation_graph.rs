@@ -0,0 +1,78 @@
+// Copyright © 2020-2021 HQS Quantum Simulations GmbH. All Rights Reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except
+// in compliance with the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the
+// License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either
+// express or implied. See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Compares EvaluationGraph::update after changing one variable to EvaluationGraph::eval_all
+//! for 1000 expressions of which 10 depend on the changed variable.
+//!
+//! Run with `cargo bench --bench evaluation_graph`.
+
+use qoqo_calculator::{Calculator, EvaluationGraph};
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+const EXPRESSIONS: usize = 1000;
+
+const DEPENDENT: usize = 10;
+
+const REPETITIONS: usize = 200;
+
+fn run(name: &str, mut evaluate: impl FnMut(usize) -> f64) -> Duration {
+    let start = Instant::now();
+    let mut sum = 0.0;
+    for repetition in 0..REPETITIONS {
+        sum += black_box(evaluate(repetition));
+    }
+    let elapsed = start.elapsed() / REPETITIONS as u32;
+    println!("{name}: {elapsed:?} per change (checksum {sum})");
+    elapsed
+}
+
+fn main() {
+    let mut calculator = Calculator::new();
+    let mut graph = EvaluationGraph::new();
+    for index in 0..EXPRESSIONS {
+        calculator
+            .set_variable(&format!("theta_{index}"), index as f64 * 1e-3)
+            .unwrap();
+        // Every hundredth expression depends on the changed variable
+        let shift = if index % (EXPRESSIONS / DEPENDENT) == 0 {
+            "theta_changed"
+        } else {
+            "1"
+        };
+        graph
+            .register(&format!("amp * cos(theta_{index} - {shift}) ^ 2"))
+            .unwrap();
+    }
+    calculator.set_variable("amp", 0.5).unwrap();
+    calculator.set_variable("theta_changed", 0.0).unwrap();
+    graph.eval_all(&calculator).unwrap();
+
+    let full = run("eval_all", |repetition| {
+        calculator
+            .set_variable("theta_changed", repetition as f64 * 1e-2)
+            .unwrap();
+        graph.eval_all(&calculator).unwrap().iter().sum()
+    });
+    let incremental = run("update", |repetition| {
+        calculator
+            .set_variable("theta_changed", repetition as f64 * 1e-2 + 1.0)
+            .unwrap();
+        let updated = graph.update(&calculator, &["theta_changed"]).unwrap();
+        assert_eq!(updated.len(), DEPENDENT);
+        updated.iter().map(|(_, value)| value).sum()
+    });
+    println!(
+        "speedup: {:.1}",
+        full.as_secs_f64() / incremental.as_secs_f64()
+    );
+}
@@ -0,0 +1,43 @@
+// Copyright © 2020-2021 HQS Quantum Simulations GmbH. All Rights Reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except
+// in compliance with the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the
+// License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either
+// express or implied. See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Measures Calculator::parse_str for a function-call-dense expression,
+//! dominated by the lookup of function names.
+//!
+//! Run with `cargo bench --bench function_calls`.
+
+use qoqo_calculator::Calculator;
+use std::hint::black_box;
+use std::time::Instant;
+
+const ITERATIONS: usize = 200_000;
+
+/// Expression using short and long function names, from both ends of the function table.
+const EXPRESSION: &str = "abs(x) + theta(x) + delta(x) + sin(cos(tan(x))) \
+    + atan2(sinh(x), cosh(x)) + hypot(expm1(x), log10(x)) + artanh(fract(x)) \
+    + floor(ceil(round(x))) + max(min(x, y), pow(y, 2)) + sqrt(cbrt(exp2(x)))";
+
+fn main() {
+    let mut calculator = Calculator::new();
+    calculator.set_variable("x", 0.3).unwrap();
+    calculator.set_variable("y", 0.7).unwrap();
+    let start = Instant::now();
+    let mut sum = 0.0;
+    for _ in 0..ITERATIONS {
+        sum += calculator.parse_str(black_box(EXPRESSION)).unwrap();
+    }
+    let elapsed = start.elapsed();
+    println!(
+        "parse_str(function calls): {elapsed:?}, {:?} per call (checksum {sum})",
+        elapsed / ITERATIONS as u32
+    );
+}
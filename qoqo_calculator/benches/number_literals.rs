@@ -0,0 +1,72 @@
+// Copyright © 2020-2021 HQS Quantum Simulations GmbH. All Rights Reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except
+// in compliance with the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the
+// License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either
+// express or implied. See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Measures lexing a number-dense expression, as generated for the parameters of a circuit,
+//! and converting the same literals with `f64::from_str` for comparison.
+//!
+//! The conversion with `f64::from_str` is a small part of lexing, the remaining time is spent
+//! isolating the literals and lexing the operators.
+//!
+//! Run with `cargo bench --bench number_literals`.
+
+use qoqo_calculator::{tokenize, Token};
+use std::hint::black_box;
+use std::str::FromStr;
+use std::time::Instant;
+
+const ITERATIONS: usize = 200;
+const LITERALS: usize = 4096;
+
+/// Literals with up to 17 significant digits and small exponents.
+fn generated_literals() -> Vec<String> {
+    (0..LITERALS)
+        .map(|index| match index % 4 {
+            0 => format!("{index}"),
+            1 => format!("{}", index as f64 / 1024.0),
+            2 => format!("{:e}", (index as f64).sqrt() * 1e-6),
+            _ => format!("{}", 1.0 / (index as f64 + 1.0)),
+        })
+        .collect()
+}
+
+fn main() {
+    let literals = generated_literals();
+    let expression = literals.join(" + ");
+
+    let start = Instant::now();
+    let mut sum = 0.0;
+    for _ in 0..ITERATIONS {
+        for token in tokenize(black_box(&expression)) {
+            if let Token::Number(x) = token {
+                sum += x;
+            }
+        }
+    }
+    let elapsed = start.elapsed();
+    println!(
+        "tokenize({LITERALS} literals): {elapsed:?}, {:?} per call (checksum {sum})",
+        elapsed / ITERATIONS as u32
+    );
+
+    let start = Instant::now();
+    let mut sum = 0.0;
+    for _ in 0..ITERATIONS {
+        for literal in black_box(&literals) {
+            sum += f64::from_str(literal).unwrap();
+        }
+    }
+    let elapsed = start.elapsed();
+    println!(
+        "f64::from_str({LITERALS} literals): {elapsed:?}, {:?} per call (checksum {sum})",
+        elapsed / ITERATIONS as u32
+    );
+}
@@ -0,0 +1,97 @@
+// Copyright © 2020-2021 HQS Quantum Simulations GmbH. All Rights Reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except
+// in compliance with the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the
+// License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either
+// express or implied. See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Compares Calculator::parse_get with a cloned value to Calculator::parse_get_ref
+//! for a symbolic parameter held in a struct, and to evaluating the expression
+//! compiled once with Calculator::compile. The last run sets an observer with
+//! Calculator::set_observer, the other runs show that unobserved evaluations are not slowed down.
+//!
+//! Run with `cargo bench --bench parse_get`.
+
+use qoqo_calculator::{Calculator, CalculatorFloat, EvaluationEvent};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::hint::black_box;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Instant;
+
+/// Allocator counting the number of allocations.
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+/// Operation holding a symbolic parameter, as done in roqoqo.
+struct RotateX {
+    theta: CalculatorFloat,
+}
+
+const ITERATIONS: usize = 1_000_000;
+
+fn run(name: &str, evaluate: impl Fn() -> f64) {
+    let allocations_start = ALLOCATIONS.load(Ordering::Relaxed);
+    let start = Instant::now();
+    let mut sum = 0.0;
+    for _ in 0..ITERATIONS {
+        sum += black_box(evaluate());
+    }
+    let elapsed = start.elapsed();
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed) - allocations_start;
+    println!(
+        "{name}: {elapsed:?}, {:.2} allocations per call (checksum {sum})",
+        allocations as f64 / ITERATIONS as f64
+    );
+}
+
+fn main() {
+    let mut calculator = Calculator::new();
+    calculator.set_variable("theta", 0.3).unwrap();
+    let operation = RotateX {
+        theta: CalculatorFloat::from("theta * 2"),
+    };
+    run("parse_get(clone)", || {
+        calculator
+            .parse_get(black_box(&operation).theta.clone())
+            .unwrap()
+    });
+    run("parse_get_ref", || {
+        calculator
+            .parse_get_ref(&black_box(&operation).theta)
+            .unwrap()
+    });
+    let compiled = calculator.compile("theta * 2").unwrap();
+    run("CompiledExpression::evaluate", || {
+        black_box(&compiled).evaluate(&calculator).unwrap()
+    });
+    let mut observed = calculator.clone();
+    observed.set_observer(Arc::new(|event: &EvaluationEvent| {
+        black_box(event);
+    }));
+    run("parse_get_ref (observer set)", || {
+        observed
+            .parse_get_ref(&black_box(&operation).theta)
+            .unwrap()
+    });
+}
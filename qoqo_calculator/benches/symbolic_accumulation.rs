@@ -0,0 +1,126 @@
+// Copyright © 2020-2021 HQS Quantum Simulations GmbH. All Rights Reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except
+// in compliance with the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the
+// License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either
+// express or implied. See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Measures accumulating a symbolic sum `c_0 * x_0 + c_1 * x_1 + ...` as done for Hamiltonians.
+//!
+//! The first run formats a new string for every term like the operators did before they
+//! extended the buffer of the left operand, the cost grows quadratically with the number of
+//! terms. `+=` and `*=` extend the accumulated expression in place but move it once per term
+//! to insert the opening bracket, so their cost grows quadratically as well, with a smaller
+//! factor. `SymbolicAccumulator` and `Sum` count the opening brackets and write them once,
+//! their cost grows linearly.
+//!
+//! Run with `cargo bench --bench symbolic_accumulation`.
+
+use qoqo_calculator::{CalculatorFloat, SymbolicAccumulator};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::hint::black_box;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Instant;
+
+/// Allocator counting the number of allocations.
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+const TERMS: [usize; 3] = [1_000, 10_000, 30_000];
+
+/// Return the symbolic terms `c_i * x_i`.
+fn terms(number: usize) -> Vec<CalculatorFloat> {
+    (0..number)
+        .map(|index| {
+            CalculatorFloat::from(format!("c_{index}"))
+                * CalculatorFloat::from(format!("x_{index}"))
+        })
+        .collect()
+}
+
+/// Add two symbolic values by formatting a new string, as `+` did before.
+fn formatted_add(left: CalculatorFloat, right: CalculatorFloat) -> CalculatorFloat {
+    match (left, right) {
+        (CalculatorFloat::Str(x), CalculatorFloat::Str(y)) => {
+            CalculatorFloat::Str(format!("({x} + {y})"))
+        }
+        (left, right) => left + right,
+    }
+}
+
+fn run(name: &str, number: usize, accumulate: impl Fn(Vec<CalculatorFloat>) -> CalculatorFloat) {
+    let terms = terms(number);
+    let allocations_start = ALLOCATIONS.load(Ordering::Relaxed);
+    let start = Instant::now();
+    let sum = black_box(accumulate(black_box(terms)));
+    let elapsed = start.elapsed();
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed) - allocations_start;
+    println!(
+        "{name} ({number} terms): {elapsed:?}, {allocations} allocations (length {})",
+        sum.to_string().len()
+    );
+}
+
+fn main() {
+    for number in TERMS {
+        run("formatted +", number, |terms| {
+            terms
+                .into_iter()
+                .fold(CalculatorFloat::Float(0.0), formatted_add)
+        });
+        run("+=", number, |terms| {
+            let mut sum = CalculatorFloat::Float(0.0);
+            for term in terms {
+                sum += term;
+            }
+            sum
+        });
+        run("SymbolicAccumulator +=", number, |terms| {
+            let mut sum = SymbolicAccumulator::default();
+            for term in terms {
+                sum += term;
+            }
+            sum.finish()
+        });
+        run("Sum", number, |terms| terms.into_iter().sum());
+        run("*=", number, |terms| {
+            let mut product = CalculatorFloat::Float(2.0);
+            for term in terms {
+                product *= term;
+            }
+            product
+        });
+        run("SymbolicAccumulator *=", number, |terms| {
+            let mut product = SymbolicAccumulator::from(CalculatorFloat::Float(2.0));
+            for term in terms {
+                product *= term;
+            }
+            product.finish()
+        });
+    }
+}
@@ -0,0 +1,137 @@
+// Copyright © 2020-2021 HQS Quantum Simulations GmbH. All Rights Reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except
+// in compliance with the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the
+// License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either
+// express or implied. See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! arbitrary module
+//!
+//! Provides proptest strategies for CalculatorFloat and CalculatorComplex and implements
+//! [proptest::arbitrary::Arbitrary] for both types, available with the `proptest` feature.
+//!
+//! Numeric values are finite floats. Symbolic values are expressions composed of variables,
+//! numbers, the arithmetic operators and known functions. Every subexpression is written in
+//! brackets, so all generated expressions are accepted by `CalculatorFloat::from_str`.
+//!
+//! # Example
+//!
+//! ```
+//! use proptest::prelude::*;
+//! use proptest::test_runner::TestRunner;
+//! use qoqo_calculator::CalculatorFloat;
+//!
+//! let mut runner = TestRunner::default();
+//! runner
+//!     .run(&any::<CalculatorFloat>(), |x| {
+//!         prop_assert_eq!(x.clone() + 0.0, x);
+//!         Ok(())
+//!     })
+//!     .unwrap();
+//! ```
+
+use crate::calculator_float::format_float;
+use crate::{CalculatorComplex, CalculatorFloat};
+use proptest::prelude::*;
+
+/// Variable names used in generated expressions.
+const VARIABLES: [&str; 6] = ["x", "y", "theta", "phi", "a_1", "t0"];
+
+/// Functions with one argument used in generated expressions.
+const UNARY_FUNCTIONS: [&str; 8] = ["sin", "cos", "tan", "exp", "sqrt", "abs", "tanh", "log"];
+
+/// Functions with two arguments used in generated expressions.
+const BINARY_FUNCTIONS: [&str; 4] = ["atan2", "max", "min", "hypot"];
+
+/// Binary operators used in generated expressions.
+const OPERATORS: [&str; 5] = ["+", "-", "*", "/", "^"];
+
+/// Return a strategy generating finite floats, including zero and subnormal values.
+pub fn finite_float() -> impl Strategy<Value = f64> {
+    prop::num::f64::POSITIVE
+        | prop::num::f64::NEGATIVE
+        | prop::num::f64::NORMAL
+        | prop::num::f64::SUBNORMAL
+        | prop::num::f64::ZERO
+}
+
+/// Return a strategy generating the names of variables used in symbolic expressions.
+pub fn variable_name() -> impl Strategy<Value = String> {
+    prop::sample::select(&VARIABLES[..]).prop_map(String::from)
+}
+
+/// Return a strategy generating symbolic expressions accepted by `CalculatorFloat::from_str`.
+///
+/// The expressions contain at least one variable and are nested at most `depth` levels.
+///
+/// # Arguments
+///
+/// * `depth` - Maximal nesting depth of operators and functions
+///
+pub fn symbolic_expression(depth: u32) -> impl Strategy<Value = String> {
+    let number = (0.0..1e6_f64).prop_map(format_float);
+    let leaf = prop_oneof![2 => variable_name(), 1 => number];
+    leaf.prop_recursive(depth, 64, 2, |inner| {
+        prop_oneof![
+            (
+                inner.clone(),
+                prop::sample::select(&OPERATORS[..]),
+                inner.clone()
+            )
+                .prop_map(|(left, operator, right)| format!("({left} {operator} {right})")),
+            inner.clone().prop_map(|operand| format!("(-{operand})")),
+            (prop::sample::select(&UNARY_FUNCTIONS[..]), inner.clone())
+                .prop_map(|(name, argument)| format!("{name}({argument})")),
+            (
+                prop::sample::select(&BINARY_FUNCTIONS[..]),
+                inner.clone(),
+                inner
+            )
+                .prop_map(|(name, first, second)| format!("{name}({first}, {second})")),
+        ]
+    })
+    .prop_flat_map(|expression| {
+        // Expressions made only of numbers are combined with a variable
+        let has_variable = crate::variables_in_expression(&expression)
+            .map(|variables| !variables.is_empty())
+            .unwrap_or(false);
+        if has_variable {
+            Just(expression).boxed()
+        } else {
+            variable_name()
+                .prop_map(move |variable| format!("({expression} * {variable})"))
+                .boxed()
+        }
+    })
+}
+
+impl Arbitrary for CalculatorFloat {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<CalculatorFloat>;
+
+    /// Generate finite floats and symbolic expressions nested at most three levels.
+    fn arbitrary_with(_args: ()) -> Self::Strategy {
+        prop_oneof![
+            finite_float().prop_map(CalculatorFloat::Float),
+            symbolic_expression(3).prop_map(CalculatorFloat::Str),
+        ]
+        .boxed()
+    }
+}
+
+impl Arbitrary for CalculatorComplex {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<CalculatorComplex>;
+
+    /// Generate the real and imaginary part independently as CalculatorFloat.
+    fn arbitrary_with(_args: ()) -> Self::Strategy {
+        (any::<CalculatorFloat>(), any::<CalculatorFloat>())
+            .prop_map(|(re, im)| CalculatorComplex::new(re, im))
+            .boxed()
+    }
+}
@@ -18,125 +18,513 @@
 //!
 //! Provides Calculator struct for parsing string expressions to floats.
 
-use crate::{CalculatorError, CalculatorFloat};
-use std::collections::HashMap;
+use crate::calculator_float::format_float;
+use crate::dual::{Dual, Scalar, Unevaluated};
+use crate::expression_cache::RepeatedConstant;
+use crate::math;
+use crate::special_functions;
+use crate::{
+    CalculatorComplex, CalculatorError, CalculatorFloat, CalculatorWarning, CompiledExpression,
+    ExpressionCache, InterpolationTable, OutOfRange, Truncated,
+};
+use num_complex::Complex;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fmt;
+use std::marker::PhantomData;
+use std::ops::Range;
 use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 use std::vec::Vec;
 static ATOL: f64 = f64::EPSILON;
 
-/// Match name of function to number of arguments.
-/// Returns result with CalculatorError when function name is not known.
-fn function_argument_numbers(input: &str) -> Result<usize, CalculatorError> {
-    match input {
-        "sin" => Ok(1),
-        "cos" => Ok(1),
-        "abs" => Ok(1),
-        "tan" => Ok(1),
-        "acos" => Ok(1),
-        "asin" => Ok(1),
-        "atan" => Ok(1),
-        "cosh" => Ok(1),
-        "sinh" => Ok(1),
-        "tanh" => Ok(1),
-        "acosh" => Ok(1),
-        "asinh" => Ok(1),
-        "atanh" => Ok(1),
-        "arcosh" => Ok(1),
-        "arsinh" => Ok(1),
-        "artanh" => Ok(1),
-        "exp" => Ok(1),
-        "exp2" => Ok(1),
-        "expm1" => Ok(1), //< exponential minus Ok(1)
-        "log" => Ok(1),
-        "log10" => Ok(1),
-        "sqrt" => Ok(1),
-        "cbrt" => Ok(1), //< cubic root
-        "ceil" => Ok(1),
-        "floor" => Ok(1),
-        "fract" => Ok(1),
-        "round" => Ok(1),
-        "erf" => Ok(1),
-        "tgamma" => Ok(1),
-        "lgamma" => Ok(1),
-        "sign" => Ok(1),
-        "delta" => Ok(1),
-        "theta" => Ok(1),
-        "parity" => Ok(1),
-        "atan2" => Ok(2),
-        "hypot" => Ok(2),
-        "pow" => Ok(2),
-        "max" => Ok(2),
-        "min" => Ok(2),
-        _ => Err(CalculatorError::FunctionNotFound {
-            fct: input.to_string(),
-        }),
-    }
+/// Handler evaluating functions that are not known to the Calculator,
+/// see [Calculator::set_unknown_function_handler].
+///
+/// Called with the name of the function and the values of its arguments.
+pub type UnknownFunctionHandler = Arc<dyn Fn(&str, &[f64]) -> Option<f64> + Send + Sync>;
+
+/// Observer notified of every evaluated expression, see [Calculator::set_observer].
+pub type EvaluationObserver = Arc<dyn Fn(&EvaluationEvent) + Send + Sync>;
+
+/// Record of an evaluated expression passed to an [EvaluationObserver].
+#[derive(Debug, Clone, PartialEq)]
+pub struct EvaluationEvent {
+    /// Evaluated expression, long expressions are truncated like in error messages, see [Truncated]
+    pub expression: String,
+    /// Value of the expression or the kind of the error, see [CalculatorError::kind]
+    pub outcome: Result<f64, &'static str>,
+    /// Time spent evaluating the expression
+    pub duration: Duration,
+    /// Number of the event, starting at 0 and increasing by one for every event of the observer
+    pub sequence: u64,
 }
 
-/// Match name of function with one argument to Rust function and return Result.
-fn function_1_argument(input: &str, arg0: f64) -> Result<f64, CalculatorError> {
-    match input {
-        "sin" => Ok(arg0.sin()),
-        "cos" => Ok(arg0.cos()),
-        "abs" => Ok(arg0.abs()),
-        "tan" => Ok(arg0.tan()),
-        "acos" => Ok(arg0.acos()),
-        "asin" => Ok(arg0.asin()),
-        "atan" => Ok(arg0.atan()),
-        "cosh" => Ok(arg0.cosh()),
-        "sinh" => Ok(arg0.sinh()),
-        "tanh" => Ok(arg0.tanh()),
-        "acosh" => Ok(arg0.acosh()),
-        "asinh" => Ok(arg0.asinh()),
-        "atanh" => Ok(arg0.atanh()),
-        "arcosh" => Ok(arg0.acosh()),
-        "arsinh" => Ok(arg0.asinh()),
-        "artanh" => Ok(arg0.atanh()),
-        "exp" => Ok(arg0.exp()),
-        "exp2" => Ok(arg0.exp2()),
-        "expm1" => Ok(arg0.exp_m1()), //< exponential minus 1
-        "log" => Ok(arg0.ln()),
-        "log10" => Ok(arg0.log10()),
-        "sqrt" => Ok(arg0.sqrt()),
-        "cbrt" => Ok(arg0.cbrt()), //< cubic root
-        "ceil" => Ok(arg0.ceil()),
-        "floor" => Ok(arg0.floor()),
-        "fract" => Ok(arg0.fract()),
-        "round" => Ok(arg0.round()),
-        "sign" => Ok(arg0.signum()),
-        "delta" => {
-            if (arg0 - 0.0).abs() < ATOL {
-                Ok(1.0)
-            } else {
-                Ok(0.0)
+/// Observer of a Calculator with the sequence number of its next event, shared between clones.
+#[derive(Clone)]
+struct ObserverSlot {
+    observer: EvaluationObserver,
+    sequence: Arc<AtomicU64>,
+}
+
+/// Evaluate an expression and notify the observer of the outcome when one is set.
+///
+/// Without observer the expression is only evaluated, neither the time is measured nor
+/// the expression is copied.
+fn observe(
+    slot: Option<&ObserverSlot>,
+    expression: &str,
+    evaluate: impl FnOnce() -> Result<f64, CalculatorError>,
+) -> Result<f64, CalculatorError> {
+    let Some(slot) = slot else {
+        return evaluate();
+    };
+    let start = Instant::now();
+    let result = evaluate();
+    let event = EvaluationEvent {
+        expression: Truncated(expression).to_string(),
+        outcome: result.as_ref().copied().map_err(|error| error.kind()),
+        duration: start.elapsed(),
+        sequence: slot.sequence.fetch_add(1, Ordering::Relaxed),
+    };
+    (slot.observer)(&event);
+    result
+}
+
+/// Maximal nesting depth of variables defined by expressions, see [Calculator::set_expression_variable].
+pub const MAX_EXPRESSION_VARIABLE_DEPTH: usize = 32;
+
+/// Functions taking a single-quoted prefix and aggregating all numeric variables whose names start with it.
+///
+/// `sum_vars('a_')` sums the variables in lexicographic order of their names and
+/// `count_vars('a_')` counts them, so both are deterministic whatever the order the variables were set in.
+/// Variables defined by expressions are not included.
+pub(crate) const VARIABLE_AGGREGATES: [&str; 2] = ["count_vars", "sum_vars"];
+
+/// Reductions over a comma separated list of values in square brackets, e.g. `max[a, b, c]`.
+///
+/// The square brackets take any number of values, while functions in round brackets take a
+/// fixed number of arguments or, like `max(a, b, c)`, at least two. `sum[]` is zero, the other
+/// reductions of an empty list return `CalculatorError::NotEnoughFunctionArguments`.
+/// See [crate::expr] to compose them.
+pub(crate) const REDUCTIONS: [&str; 4] = ["max", "mean", "min", "sum"];
+
+/// Statement validating a condition inside an expression, `assert(condition, 'message')`.
+///
+/// Evaluates to 1.0 when the condition is not zero. Otherwise, or when the condition is NaN,
+/// the evaluation stops with `CalculatorError::AssertionFailed`. The message is optional.
+const ASSERT: &str = "assert";
+
+/// Message of a failed assert without message.
+const DEFAULT_ASSERT_MESSAGE: &str = "assertion failed";
+
+/// Statement executing the statements of a registered library, `include('name')`,
+/// see [Calculator::register_library].
+const INCLUDE: &str = "include";
+
+/// Maximal nesting depth of included libraries, see [Calculator::register_library].
+pub const MAX_INCLUDE_DEPTH: usize = 16;
+
+/// Number of arguments of a function.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ArgumentCount {
+    /// Exactly the given number of arguments
+    Fixed(usize),
+    /// Two or more arguments
+    Variadic,
+}
+
+/// Implementation of a function known to the Calculator.
+#[derive(Clone, Copy)]
+pub(crate) enum FunctionImplementation {
+    /// Function with one argument
+    Unary(fn(f64) -> f64),
+    /// Function with two arguments
+    Binary(fn(f64, f64) -> f64),
+    /// Function with one argument that is not defined for all arguments
+    FallibleUnary(fn(f64) -> Result<f64, CalculatorError>),
+    /// Function with two arguments that is not defined for all arguments
+    FallibleBinary(fn(f64, f64) -> Result<f64, CalculatorError>),
+    /// Function with two or more arguments, combined from left to right with the binary function
+    Variadic(fn(f64, f64) -> f64),
+}
+
+impl FunctionImplementation {
+    /// Return the number of arguments of the function.
+    fn argument_count(&self) -> ArgumentCount {
+        match self {
+            FunctionImplementation::Unary(_) | FunctionImplementation::FallibleUnary(_) => {
+                ArgumentCount::Fixed(1)
             }
-        }
-        "theta" => {
-            if (arg0 - 0.0).abs() < ATOL {
-                Ok(0.5)
-            } else if arg0 < 0.0 {
-                Ok(0.0)
-            } else {
-                Ok(1.0)
+            FunctionImplementation::Binary(_) | FunctionImplementation::FallibleBinary(_) => {
+                ArgumentCount::Fixed(2)
             }
+            FunctionImplementation::Variadic(_) => ArgumentCount::Variadic,
         }
-        //"parity" => {let m = i64::from((arg0+0.5).floor());
-        //     if m.overflowing_rem(2) {Ok(-1.0)} else {Ok(1.0)}},
+    }
+}
+
+/// Functions known to the Calculator with a short description, sorted by name for binary search.
+///
+/// All function name lookups of the parser go through this table,
+/// [crate::introspection::functions] lists its entries.
+static FUNCTIONS: [(&str, FunctionImplementation, &str); 46] = [
+    (
+        "abs",
+        FunctionImplementation::Unary(f64::abs),
+        "Absolute value",
+    ),
+    (
+        "acos",
+        FunctionImplementation::Unary(math::acos),
+        "Arccosine",
+    ),
+    (
+        "acosh",
+        FunctionImplementation::Unary(math::acosh),
+        "Inverse hyperbolic cosine",
+    ),
+    (
+        "arcosh",
+        FunctionImplementation::Unary(math::acosh),
+        "Inverse hyperbolic cosine, alias of acosh",
+    ),
+    (
+        "arsinh",
+        FunctionImplementation::Unary(math::asinh),
+        "Inverse hyperbolic sine, alias of asinh",
+    ),
+    (
+        "artanh",
+        FunctionImplementation::Unary(math::atanh),
+        "Inverse hyperbolic tangent, alias of atanh",
+    ),
+    ("asin", FunctionImplementation::Unary(math::asin), "Arcsine"),
+    (
+        "asinh",
+        FunctionImplementation::Unary(math::asinh),
+        "Inverse hyperbolic sine",
+    ),
+    (
+        "atan",
+        FunctionImplementation::Unary(math::atan),
+        "Arctangent",
+    ),
+    (
+        "atan2",
+        FunctionImplementation::Binary(math::atan2),
+        "Arctangent of x / y using the signs of both arguments",
+    ),
+    (
+        "atanh",
+        FunctionImplementation::Unary(math::atanh),
+        "Inverse hyperbolic tangent",
+    ),
+    (
+        "binom",
+        FunctionImplementation::FallibleBinary(special_functions::binom),
+        "Binomial coefficient",
+    ),
+    (
+        "cbrt",
+        FunctionImplementation::Unary(math::cbrt),
+        "Cubic root",
+    ),
+    (
+        "ceil",
+        FunctionImplementation::Unary(f64::ceil),
+        "Smallest integer greater than or equal to x",
+    ),
+    ("cos", FunctionImplementation::Unary(math::cos), "Cosine"),
+    (
+        "cosh",
+        FunctionImplementation::Unary(math::cosh),
+        "Hyperbolic cosine",
+    ),
+    (
+        "delta",
+        FunctionImplementation::Unary(delta),
+        "Kronecker delta, 1 for zero and 0 otherwise",
+    ),
+    (
+        "erf",
+        FunctionImplementation::Unary(special_functions::erf),
+        "Error function",
+    ),
+    (
+        "exp",
+        FunctionImplementation::Unary(math::exp),
+        "Exponential function",
+    ),
+    (
+        "exp2",
+        FunctionImplementation::Unary(math::exp2),
+        "Power of two",
+    ),
+    (
+        "expm1",
+        FunctionImplementation::Unary(math::exp_m1),
+        "Exponential function minus one",
+    ),
+    (
+        "factorial",
+        FunctionImplementation::FallibleUnary(special_functions::factorial),
+        "Factorial, gamma(x + 1)",
+    ),
+    (
+        "floor",
+        FunctionImplementation::Unary(f64::floor),
+        "Largest integer smaller than or equal to x",
+    ),
+    (
+        "fract",
+        FunctionImplementation::Unary(f64::fract),
+        "Fractional part",
+    ),
+    (
+        "gamma",
+        FunctionImplementation::FallibleUnary(special_functions::gamma),
+        "Gamma function",
+    ),
+    (
+        "hypot",
+        FunctionImplementation::Binary(math::hypot),
+        "Length of the hypotenuse",
+    ),
+    (
+        "lgamma",
+        FunctionImplementation::FallibleUnary(special_functions::lgamma),
+        "Logarithm of the absolute value of the gamma function",
+    ),
+    (
+        "log",
+        FunctionImplementation::Unary(math::ln),
+        "Natural logarithm",
+    ),
+    (
+        "log10",
+        FunctionImplementation::Unary(math::log10),
+        "Logarithm to base 10",
+    ),
+    (
+        "log2",
+        FunctionImplementation::Unary(math::log2),
+        "Logarithm to base 2",
+    ),
+    (
+        "max",
+        FunctionImplementation::Variadic(f64::max),
+        "Maximum of two or more values",
+    ),
+    (
+        "min",
+        FunctionImplementation::Variadic(f64::min),
+        "Minimum of two or more values",
+    ),
+    (
+        "parity",
+        FunctionImplementation::FallibleUnary(special_functions::parity),
+        "Parity of an integer, 1 for even and -1 for odd",
+    ),
+    (
+        "pow",
+        FunctionImplementation::Binary(math::powf),
+        "Power x^y",
+    ),
+    (
+        "round",
+        FunctionImplementation::Unary(f64::round),
+        "Round half away from zero",
+    ),
+    (
+        "round_digits",
+        FunctionImplementation::FallibleBinary(special_functions::round_digits),
+        "Round to a number of decimal digits",
+    ),
+    (
+        "round_step",
+        FunctionImplementation::FallibleBinary(special_functions::round_step),
+        "Round to a multiple of a step",
+    ),
+    (
+        "sign",
+        FunctionImplementation::Unary(f64::signum),
+        "Sign of x",
+    ),
+    ("sin", FunctionImplementation::Unary(math::sin), "Sine"),
+    (
+        "sinh",
+        FunctionImplementation::Unary(math::sinh),
+        "Hyperbolic sine",
+    ),
+    (
+        "sqrt",
+        FunctionImplementation::Unary(f64::sqrt),
+        "Square root",
+    ),
+    (
+        "sum",
+        FunctionImplementation::Variadic(|x, y| x + y),
+        "Sum of two or more values",
+    ),
+    ("tan", FunctionImplementation::Unary(math::tan), "Tangent"),
+    (
+        "tanh",
+        FunctionImplementation::Unary(math::tanh),
+        "Hyperbolic tangent",
+    ),
+    (
+        "tgamma",
+        FunctionImplementation::FallibleUnary(special_functions::tgamma),
+        "Gamma function, same as gamma",
+    ),
+    (
+        "theta",
+        FunctionImplementation::Unary(heaviside),
+        "Heaviside step function, 0.5 for zero",
+    ),
+];
+
+/// Functions evaluated by the parser itself with their number of arguments and a short description.
+const SPECIAL_FUNCTIONS: [(&str, usize, &str); 4] = [
+    (
+        ASSERT,
+        2,
+        "Stop the evaluation when the condition is zero, the quoted message is optional",
+    ),
+    (
+        INCLUDE,
+        1,
+        "Execute the statements of the library registered with the quoted name",
+    ),
+    (
+        "count_vars",
+        1,
+        "Number of variables whose names start with the quoted prefix",
+    ),
+    (
+        "sum_vars",
+        1,
+        "Sum of the variables whose names start with the quoted prefix",
+    ),
+];
+
+/// Return name, number of arguments and description of all functions the parser evaluates.
+///
+/// Lists the entries of the function table followed by the special functions.
+pub(crate) fn function_descriptions(
+) -> impl Iterator<Item = (&'static str, ArgumentCount, &'static str)> {
+    FUNCTIONS
+        .iter()
+        .map(|(name, function, description)| (*name, function.argument_count(), *description))
+        .chain(SPECIAL_FUNCTIONS.iter().map(|(name, number, description)| {
+            (*name, ArgumentCount::Fixed(*number), *description)
+        }))
+}
+
+/// Constants the parser uses for identifiers that are neither variables nor expression variables.
+///
+/// Variables set in the Calculator take precedence, so setting e.g. `e` hides the constant.
+/// [crate::introspection::constants] lists the entries.
+static CONSTANTS: [(&str, f64, &str); 5] = [
+    ("E", std::f64::consts::E, "Euler's number, alias of e"),
+    (
+        "PI",
+        std::f64::consts::PI,
+        "Ratio of circumference to diameter, alias of pi",
+    ),
+    ("e", std::f64::consts::E, "Euler's number"),
+    (
+        "pi",
+        std::f64::consts::PI,
+        "Ratio of circumference to diameter",
+    ),
+    ("tau", std::f64::consts::TAU, "Full turn, 2 * pi"),
+];
+
+/// Return name, value and description of all constants known to the parser.
+pub(crate) fn constant_descriptions() -> &'static [(&'static str, f64, &'static str)] {
+    &CONSTANTS
+}
+
+/// Look up a constant known to the parser, None when the name is not a constant.
+pub(crate) fn lookup_constant(name: &str) -> Option<f64> {
+    CONSTANTS
+        .iter()
+        .find(|(constant, _, _)| *constant == name)
+        .map(|(_, value, _)| *value)
+}
+
+/// Look up a function in the function table.
+/// Returns result with CalculatorError when function name is not known.
+pub(crate) fn lookup_function(input: &str) -> Result<FunctionImplementation, CalculatorError> {
+    FUNCTIONS
+        .binary_search_by(|(name, _, _)| (*name).cmp(input))
+        .map(|index| FUNCTIONS[index].1)
+        .map_err(|_| CalculatorError::FunctionNotFound {
+            fct: input.to_string(),
+        })
+}
+
+/// Match name of function to number of arguments.
+/// Returns result with CalculatorError when function name is not known.
+pub(crate) fn function_argument_numbers(input: &str) -> Result<ArgumentCount, CalculatorError> {
+    lookup_function(input).map(|function| function.argument_count())
+}
+
+/// Match name of function with one argument to Rust function and return Result.
+pub(crate) fn function_1_argument(input: &str, arg0: f64) -> Result<f64, CalculatorError> {
+    match lookup_function(input)? {
+        FunctionImplementation::Unary(function) => Ok(function(arg0)),
+        FunctionImplementation::FallibleUnary(function) => function(arg0),
         _ => Err(CalculatorError::FunctionNotFound {
             fct: input.to_string(),
         }),
     }
 }
 
+/// Kronecker delta of a float, as evaluated by the `delta` function.
+///
+/// Returns 1.0 when `|x| < ATOL` and 0.0 otherwise.
+/// ATOL is `f64::EPSILON`, so only zero and values smaller than about 2.2e-16 count as zero.
+pub(crate) fn delta(x: f64) -> f64 {
+    if x.abs() < ATOL {
+        1.0
+    } else {
+        0.0
+    }
+}
+
+/// Heaviside step function of a float, as evaluated by the `theta` function.
+///
+/// Returns 0.5 when `|x| < ATOL` (see [delta]), 0.0 for negative x and 1.0 for positive x.
+pub(crate) fn heaviside(x: f64) -> f64 {
+    if x.abs() < ATOL {
+        0.5
+    } else if x < 0.0 {
+        0.0
+    } else {
+        1.0
+    }
+}
+
 /// Match name of function with two arguments to Rust function and return Result.
-fn function_2_arguments(input: &str, arg0: f64, arg1: f64) -> Result<f64, CalculatorError> {
-    match input {
-        "atan2" => Ok(arg0.atan2(arg1)),
-        "hypot" => Ok(arg0.hypot(arg1)),
-        "pow" => Ok(arg0.powf(arg1)),
-        "max" => Ok(arg0.max(arg1)),
-        "min" => Ok(arg0.min(arg1)),
+///
+/// Variadic functions combine the two arguments.
+pub(crate) fn function_2_arguments(
+    input: &str,
+    arg0: f64,
+    arg1: f64,
+) -> Result<f64, CalculatorError> {
+    match lookup_function(input)? {
+        FunctionImplementation::Binary(function) | FunctionImplementation::Variadic(function) => {
+            Ok(function(arg0, arg1))
+        }
+        FunctionImplementation::FallibleBinary(function) => function(arg0, arg1),
         _ => Err(CalculatorError::FunctionNotFound {
             fct: input.to_string(),
         }),
@@ -144,10 +532,41 @@ fn function_2_arguments(input: &str, arg0: f64, arg1: f64) -> Result<f64, Calcul
 }
 
 /// Struct for parsing string expressions to floats.
-#[derive(Debug, Clone)]
+///
+/// Parsed expressions are compiled once and stored in an [ExpressionCache] of bounded size,
+/// see [ExpressionCache::set_capacity].
+/// Cloning a Calculator shares the cache with the clone while the variable
+/// values are copied and independent for each instance.
+/// A clone of a frozen Calculator is frozen as well, see [Calculator::freeze].
+#[derive(Clone)]
 pub struct Calculator {
     ///  HashMap of variables in current Calculator
     pub variables: HashMap<String, f64>,
+    /// Variables defined by expressions, evaluated when the variable is used
+    expression_variables: HashMap<String, String>,
+    /// Scripts executed by `include('name')`, keyed by the library name
+    libraries: HashMap<String, String>,
+    /// Interpolation tables called like functions, shared between clones
+    interpolation_tables: HashMap<String, Arc<InterpolationTable>>,
+    /// Handler evaluating functions that are neither built-in nor interpolation tables
+    unknown_function_handler: Option<UnknownFunctionHandler>,
+    /// Cache of compiled expressions shared between clones
+    expression_cache: ExpressionCache,
+    /// Report number literals that do not round-trip through f64 as warnings
+    warn_on_precision_loss: bool,
+    /// Reject all changes of variables, functions and libraries, see [Calculator::freeze]
+    frozen: bool,
+    /// Observer notified of every evaluated expression, shared between clones
+    observer: Option<ObserverSlot>,
+}
+
+/// Implement Debug for Calculator showing only the variables.
+impl fmt::Debug for Calculator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Calculator")
+            .field("variables", &self.variables)
+            .finish()
+    }
 }
 
 /// Define the default value of Calculator.
@@ -157,13 +576,165 @@ impl Default for Calculator {
     }
 }
 
+/// Serialized form of the variables of a Calculator, sorted by name.
+#[derive(Serialize)]
+#[serde(rename = "Calculator")]
+struct CalculatorVariablesRef<'a> {
+    variables: BTreeMap<&'a str, f64>,
+    expression_variables: BTreeMap<&'a str, &'a str>,
+}
+
+/// Deserialized form of the variables of a Calculator.
+#[derive(Deserialize)]
+#[serde(rename = "Calculator", deny_unknown_fields)]
+struct CalculatorVariables {
+    #[serde(default)]
+    variables: HashMap<String, f64>,
+    #[serde(default)]
+    expression_variables: HashMap<String, String>,
+}
+
+/// Serialize the variables and the variables defined by expressions of a Calculator.
+///
+/// Interpolation tables, libraries, handlers, the observer and the settings are not serialized.
+impl Serialize for Calculator {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        CalculatorVariablesRef {
+            variables: self
+                .variables
+                .iter()
+                .map(|(name, value)| (name.as_str(), *value))
+                .collect(),
+            expression_variables: self
+                .expression_variables
+                .iter()
+                .map(|(name, expression)| (name.as_str(), expression.as_str()))
+                .collect(),
+        }
+        .serialize(serializer)
+    }
+}
+
+/// Deserialize a Calculator with the serialized variables and variables defined by expressions.
+///
+/// The expressions of the variables are validated like in [Calculator::set_expression_variable].
+impl<'de> Deserialize<'de> for Calculator {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let data = CalculatorVariables::deserialize(deserializer)?;
+        let mut calculator = Calculator::new();
+        calculator.variables = data.variables;
+        for (name, expression) in data.expression_variables {
+            calculator
+                .set_expression_variable(&name, &expression)
+                .map_err(serde::de::Error::custom)?;
+        }
+        Ok(calculator)
+    }
+}
+
 impl Calculator {
     /// Create new Calculator.
     pub fn new() -> Self {
         Calculator {
             variables: HashMap::new(),
+            expression_variables: HashMap::new(),
+            libraries: HashMap::new(),
+            interpolation_tables: HashMap::new(),
+            unknown_function_handler: None,
+            expression_cache: ExpressionCache::new(),
+            warn_on_precision_loss: false,
+            frozen: false,
+            observer: None,
+        }
+    }
+
+    /// Create new Calculator using an existing cache of compiled expressions.
+    ///
+    /// Allows independently created Calculators to share compiled expressions.
+    ///
+    /// # Arguments
+    ///
+    /// * `cache` - Cache of compiled expressions that is shared
+    ///
+    pub fn with_shared_cache(cache: ExpressionCache) -> Self {
+        Calculator {
+            variables: HashMap::new(),
+            expression_variables: HashMap::new(),
+            libraries: HashMap::new(),
+            interpolation_tables: HashMap::new(),
+            unknown_function_handler: None,
+            expression_cache: cache,
+            warn_on_precision_loss: false,
+            frozen: false,
+            observer: None,
+        }
+    }
+
+    /// Return the cache of compiled expressions used by the Calculator.
+    pub fn expression_cache(&self) -> &ExpressionCache {
+        &self.expression_cache
+    }
+
+    /// Report number literals that snap to a different value when parsed.
+    ///
+    /// When enabled, [Calculator::parse_str_with_warnings] and
+    /// [Calculator::parse_str_assign_with_warnings] return a [CalculatorWarning::PrecisionLoss]
+    /// for every number literal that is neither the shortest representation nor the exact
+    /// decimal value of the float it is parsed to, e.g. `0.1000000000000000001`.
+    /// Literals with additional zeros, e.g. `0.50000000000000000000`, do not warn.
+    ///
+    /// # Arguments
+    ///
+    /// * `enabled` - Whether the warnings are reported, disabled by default
+    ///
+    pub fn warn_on_precision_loss(&mut self, enabled: bool) {
+        self.warn_on_precision_loss = enabled;
+    }
+
+    /// Freeze the Calculator, rejecting all further changes.
+    ///
+    /// Guarantees that the configuration of the Calculator is not changed by accident,
+    /// e.g. by a callback during a run. While frozen, [Calculator::set_variable],
+    /// [Calculator::set_variables], [Calculator::remove_variable], [Calculator::parse_str_assign],
+    /// [Calculator::set_expression_variable], the registration of interpolation tables and
+    /// libraries and setting or removing the unknown function handler return
+    /// `CalculatorError::CalculatorFrozen`. Parsing without assignments, e.g.
+    /// [Calculator::parse_str], is not affected. The public `variables` field is not guarded.
+    /// Clones of a frozen Calculator are frozen as well.
+    pub fn freeze(&mut self) {
+        self.frozen = true;
+    }
+
+    /// Allow changes of a frozen Calculator again, see [Calculator::freeze].
+    pub fn unfreeze(&mut self) {
+        self.frozen = false;
+    }
+
+    /// Return true when the Calculator is frozen, see [Calculator::freeze].
+    pub fn is_frozen(&self) -> bool {
+        self.frozen
+    }
+
+    /// Return an error when the Calculator is frozen.
+    ///
+    /// # Arguments
+    ///
+    /// * `operation` - Name of the method changing the Calculator, reported in the error
+    ///
+    fn check_not_frozen(&self, operation: &'static str) -> Result<(), CalculatorError> {
+        if self.frozen {
+            Err(CalculatorError::CalculatorFrozen { operation })
+        } else {
+            Ok(())
         }
     }
+
     /// Set variable for Calculator.
     ///
     /// # Arguments
@@ -171,8 +742,56 @@ impl Calculator {
     /// * `name` - Name of the variable
     /// * `value` - Float value of the variable
     ///
-    pub fn set_variable(&mut self, name: &str, value: f64) {
+    /// # Returns
+    ///
+    /// * `Ok(())` - The variable has been set
+    /// * `Err(CalculatorError::CalculatorFrozen)` - The Calculator is frozen
+    ///
+    pub fn set_variable(&mut self, name: &str, value: f64) -> Result<(), CalculatorError> {
+        self.check_not_frozen("set_variable")?;
         self.variables.insert(name.to_string(), value);
+        Ok(())
+    }
+
+    /// Remove a variable from the Calculator.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - Name of the variable
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Option<f64>)` - The value of the removed variable, None when it was not set
+    /// * `Err(CalculatorError::CalculatorFrozen)` - The Calculator is frozen
+    ///
+    pub fn remove_variable(&mut self, name: &str) -> Result<Option<f64>, CalculatorError> {
+        self.check_not_frozen("remove_variable")?;
+        Ok(self.variables.remove(name))
+    }
+
+    /// Set the physical constants as variables of the Calculator.
+    ///
+    /// Makes hbar, kb, qe, c, mu0 and eps0 with CODATA 2018 values available in expressions,
+    /// see [crate::constants]. Variables that are already set keep their value.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Vec<String>)` - Names of the constants that were not set because a variable with the
+    ///   same name was already set, can be used to warn about the collision
+    /// * `Err(CalculatorError::CalculatorFrozen)` - The Calculator is frozen
+    ///
+    #[cfg(feature = "physical-constants")]
+    pub fn load_physical_constants(&mut self) -> Result<Vec<String>, CalculatorError> {
+        self.check_not_frozen("load_physical_constants")?;
+        let mut collisions: Vec<String> = Vec::new();
+        for (name, value) in crate::constants::PHYSICAL_CONSTANTS {
+            if self.variables.contains_key(*name) {
+                collisions.push(name.to_string());
+            } else {
+                self.variables.insert(name.to_string(), *value);
+            }
+        }
+        Ok(collisions)
     }
 
     /// Get variable for Calculator.
@@ -189,1166 +808,6023 @@ impl Calculator {
         Ok(*self
             .variables
             .get(name)
-            .ok_or(CalculatorError::VariableNotSet {
+            .ok_or_else(|| CalculatorError::VariableNotSet {
                 name: name.to_string(),
             })?)
     }
 
-    ///  Parse a string expression.
+    /// Set several variables for Calculator.
     ///
     /// # Arguments
     ///
-    /// * `expression` - Expression that is parsed
+    /// * `variables` - Names and float values of the variables, later values of the same name win
     ///
-    pub fn parse_str(&self, expression: &str) -> Result<f64, CalculatorError> {
-        let mut parser = ParserEnum::new_immutable(expression, self);
-        let end_value = parser.evaluate_all_tokens()?;
-        match end_value {
-            None => Err(CalculatorError::NoValueReturnedParsing),
-            Some(x) => Ok(x),
-        }
+    /// # Returns
+    ///
+    /// * `Ok(())` - The variables have been set
+    /// * `Err(CalculatorError::CalculatorFrozen)` - The Calculator is frozen
+    ///
+    pub fn set_variables(
+        &mut self,
+        variables: impl IntoIterator<Item = (String, f64)>,
+    ) -> Result<(), CalculatorError> {
+        self.check_not_frozen("set_variables")?;
+        self.variables.extend(variables);
+        Ok(())
     }
 
-    ///  Parse a string expression allowing variable assignments.
+    /// Get the values of several variables.
     ///
+    /// # Arguments
     ///
+    /// * `names` - Names of the variables
     ///
-    /// # Arguments
+    /// # Returns
     ///
-    /// * `expression` - Expression that is parsed
+    /// * `Ok(Vec<f64>)` - Values of the variables in the order of the names
+    /// * `Err(CalculatorError::VariablesNotSet)` - All names of variables that are not set, in the order of the names
     ///
-    pub fn parse_str_assign(&mut self, expression: &str) -> Result<f64, CalculatorError> {
-        let mut parser = ParserEnum::new_mutable(expression, self);
-        let end_value = parser.evaluate_all_tokens()?;
-        match end_value {
-            None => Err(CalculatorError::NoValueReturnedParsing),
-            Some(x) => Ok(x),
+    pub fn get_variables(&self, names: &[&str]) -> Result<Vec<f64>, CalculatorError> {
+        let mut values: Vec<f64> = Vec::with_capacity(names.len());
+        let mut missing: Vec<String> = Vec::new();
+        for name in names {
+            match self.variables.get(*name) {
+                Some(value) => values.push(*value),
+                None => missing.push(name.to_string()),
+            }
+        }
+        if missing.is_empty() {
+            Ok(values)
+        } else {
+            Err(CalculatorError::VariablesNotSet { names: missing })
         }
     }
 
-    /// Parse a CalculatorFloat to float.
+    /// Return a copy of all variables sorted by name, e.g. for a stable display.
+    pub fn variables_snapshot(&self) -> BTreeMap<String, f64> {
+        self.variables
+            .iter()
+            .map(|(name, value)| (name.clone(), *value))
+            .collect()
+    }
+
+    /// Serialize the variables of the Calculator to JSON.
     ///
-    /// # Arguments
+    /// The variables and the variables defined by expressions are written sorted by name.
+    /// Interpolation tables, libraries, handlers, the observer and the settings are not serialized.
     ///
-    /// * `parse_variable` - Parsed string CalculatorFloat or returns float value
+    /// # Returns
     ///
-    pub fn parse_get(&self, parse_variable: CalculatorFloat) -> Result<f64, CalculatorError> {
-        match parse_variable {
-            CalculatorFloat::Float(x) => Ok(x),
-            CalculatorFloat::Str(expression) => self.parse_str(&expression),
+    /// * `Ok(String)` - JSON of the variables
+    /// * `Err(CalculatorError::SerializationError)` - A variable is not finite and can not be written to JSON
+    ///
+    pub fn to_json(&self) -> Result<String, CalculatorError> {
+        if let Some(name) = self
+            .variables_snapshot()
+            .into_iter()
+            .find_map(|(name, value)| (!value.is_finite()).then_some(name))
+        {
+            return Err(CalculatorError::SerializationError {
+                msg: format!(
+                    "Variable {} is not finite and can not be written to JSON",
+                    Truncated(format_args!("{name:?}"))
+                ),
+            });
         }
+        serde_json::to_string(self).map_err(|error| CalculatorError::SerializationError {
+            msg: error.to_string(),
+        })
     }
-}
 
-/// Enum combining different types of Tokens in an Expression.
-#[derive(Debug, Clone, PartialEq)]
-pub enum Token {
-    /// A float or integer
-    Number(f64),
-    /// A variable
-    Variable(String),
-    /// A  known function
-    Function(String),
-    /// Plus
-    Plus,
-    /// Minus
-    Minus,
-    /// Multiply
-    Multiply,
-    /// Divice
-    Divide,
-    /// Poser
-    Power,
-    /// Factorial
-    Factorial,
-    /// DoubleFactorial
-    DoubleFactorial,
-    /// A bracket opening
-    BracketOpen,
-    /// A bracket closing
-    BracketClose,
-    /// Assign operator
-    Assign,
-    /// Assignment of a variable
-    VariableAssign(String),
-    /// Comma
-    Comma,
-    /// End of Expression
-    EndOfExpression,
-    /// End of parsed string
-    EndOfString,
-    /// No Token has been recognized in string
-    Unrecognized,
-}
+    /// Create a Calculator with the variables serialized by [Calculator::to_json].
+    ///
+    /// # Arguments
+    ///
+    /// * `json` - JSON of the variables
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Calculator)` - New Calculator with the variables
+    /// * `Err(CalculatorError::SerializationError)` - The JSON can not be read or a variable
+    ///   defined by an expression is not valid
+    ///
+    pub fn from_json(json: &str) -> Result<Calculator, CalculatorError> {
+        serde_json::from_str(json).map_err(|error| CalculatorError::SerializationError {
+            msg: error.to_string(),
+        })
+    }
 
-/// Standard print implementation for Rust.
-impl fmt::Display for Token {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self {
-            Token::Number(x) => write!(f, "Token::Number({x:e})"),
-            Token::VariableAssign(y) => write!(f, "Token::VariableAssign({y})"),
-            Token::Variable(y) => write!(f, "Token::Variable({y})"),
-            Token::Function(y) => write!(f, "Token::Function({y})"),
-            Token::Plus => write!(f, "Token::Plus"),
-            Token::Minus => write!(f, "Token::Minus"),
-            Token::Multiply => write!(f, "Token::Multiply"),
-            Token::Divide => write!(f, "Token::Divide"),
-            Token::Power => write!(f, "Token::Power"),
-            Token::Factorial => write!(f, "Token::Factorial"),
-            Token::DoubleFactorial => write!(f, "Token::DoubleFactorial"),
-            Token::BracketOpen => write!(f, "Token::BracketOpen"),
-            Token::BracketClose => write!(f, "Token::BracketClose"),
-            Token::Assign => write!(f, "Token::Assign"),
-            Token::Comma => write!(f, "Token::Comma"),
-            Token::EndOfExpression => write!(f, "Token::EndOfExpression"),
-            Token::EndOfString => write!(f, "Token::EndOfString"),
-            Token::Unrecognized => write!(f, "Token::Unrecognized"),
-        }
+    /// Define a variable by an expression that is evaluated whenever the variable is used.
+    ///
+    /// The expression can use numeric variables and other variables defined by
+    /// expressions, e.g. `beta` defined as `2 * alpha`. It is evaluated lazily with the
+    /// variable values at the time of parsing. A numeric variable with the same name
+    /// takes precedence over the expression. Cyclic definitions and definitions nested
+    /// deeper than [MAX_EXPRESSION_VARIABLE_DEPTH] return an error when the variable is used.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - Name of the variable
+    /// * `expression` - Expression defining the variable, must not contain assignments
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` - The variable has been defined
+    /// * `Err(CalculatorError::InvalidExpression)` - The expression is not valid, see [validate_expression]
+    /// * `Err(CalculatorError::CalculatorFrozen)` - The Calculator is frozen
+    ///
+    pub fn set_expression_variable(
+        &mut self,
+        name: &str,
+        expression: &str,
+    ) -> Result<(), CalculatorError> {
+        self.check_not_frozen("set_expression_variable")?;
+        validate_expression(expression)?;
+        self.expression_variables
+            .insert(name.to_string(), expression.to_string());
+        Ok(())
     }
-}
 
-/// Struct implementing Iterator trait to lex string
-/// to computational Tokens.
-pub struct TokenIterator<'a> {
-    // Save current expression as a slice of a string so we do not
-    // need to copy but only modify (shorten) the slice.
-    //
-    /// Current str expression being lexed
-    pub current_expression: &'a str,
-}
-
-// Implement the Iterator Trait for TokenIterator so it can be used as standard rust iterator.
-impl<'a, 'b> Iterator for TokenIterator<'a>
-where
-    'a: 'b,
-{
-    type Item = Token;
+    /// Get the expression defining a variable.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - Name of the variable
+    ///
+    /// # Returns
+    ///
+    /// `Option<&str>` - The expression, None when the variable is not defined by an expression
+    ///
+    pub fn get_expression_variable(&self, name: &str) -> Option<&str> {
+        self.expression_variables.get(name).map(|x| x.as_str())
+    }
 
-    // Define next method for Token iterator
-    fn next(&mut self) -> Option<Token> {
-        if self.current_expression.is_empty() {
-            None
-        } else {
-            // Loop to remove whitespace and comments
-            loop {
-                if self.current_expression.starts_with(' ') {
-                    let end = self
-                        .current_expression
-                        .char_indices()
-                        .find_map(|(ind, c)| if c.is_whitespace() { None } else { Some(ind) })
-                        .unwrap_or(self.current_expression.len());
-                    self.cut_current_expression(end);
-                    if self.current_expression.is_empty() {
-                        return Some(Token::EndOfString);
-                    }
-                    continue;
-                } else if self.current_expression.starts_with('#') {
-                    let end = self
-                        .current_expression
-                        .char_indices()
-                        .find_map(|(ind, c)| if c != '\u{000A}' { None } else { Some(ind + 1) })
-                        .unwrap_or(self.current_expression.len());
-                    self.cut_current_expression(end);
-                    if self.current_expression.is_empty() {
-                        return Some(Token::EndOfString);
-                    }
-                    continue;
-                }
-                break;
-            }
-            // Test if head of current_expression is a letter char
-            if self
-                .current_expression
-                .chars()
-                .next()
-                .unwrap()
-                .is_alphabetic()
-            {
-                // Find end of symbolic expression (not alphanumeric or '_')
-                let end = self
-                    .current_expression
-                    .char_indices()
-                    .find_map(|(ind, c)| {
-                        if c.is_alphanumeric() || c == '_' {
-                            None
-                        } else {
-                            Some(ind)
-                        }
-                    })
-                    .unwrap_or(self.current_expression.len());
-                // Get next token from TokenIterator with shortened expression
-                let next_token = if end >= self.current_expression.len() {
-                    TokenIterator {
-                        current_expression: "",
-                    }
-                    .next()
-                } else {
-                    TokenIterator {
-                        current_expression: &self.current_expression[end..],
-                    }
-                    .next()
-                };
-                // Depending on next token currently lexed string current_expression[..end] creates different tokens
-                // Token contains current_expression[..end] for later processing
-                return Some(match next_token {
-                    Some(Token::Assign) => {
-                        let vs = self.current_expression[..end].to_owned();
-                        self.cut_current_expression(end + 1);
-                        Token::VariableAssign(vs)
-                    }
-                    Some(Token::BracketOpen) => {
-                        let vs = self.current_expression[..end].to_owned();
-                        self.cut_current_expression(end + 1);
-                        Token::Function(vs)
-                    }
-                    _ => {
-                        let vs = self.current_expression[..end].to_owned();
-                        self.cut_current_expression(end);
-                        Token::Variable(vs)
-                    }
-                });
-            }
-            // Lex string that contains a number.
-            // Test if current expression starts with ascii number
-            if self
-                .current_expression
-                .chars()
-                .next()
-                .unwrap()
-                .is_ascii_digit()
-                || self.current_expression.starts_with('.')
-            {
-                // find end of number expression
-                let (end, next_char) = self
-                    .current_expression
-                    .char_indices()
-                    .find(|(_, c)| !c.is_ascii_digit() && c != &'.')
-                    .unwrap_or((self.current_expression.len(), ' '));
-                let mut end_offset = 0;
-                let mut start: usize = 0;
-                // Handle scientific notation.
-                // Starts with e or E for scientific notation
-                if next_char == 'e' || next_char == 'E' {
-                    // offset for just 'e' or 'E'
-                    start = 1;
-                    if self
-                        .current_expression
-                        .chars()
-                        .nth(end + start)
-                        .unwrap_or(' ')
-                        == '+'
-                        || self
-                            .current_expression
-                            .chars()
-                            .nth(end + start)
-                            .unwrap_or(' ')
-                            == '-'
-                    {
-                        // offset if exponent has sign
-                        start = 2;
-                    }
-                    // Find end of exponent
-                    end_offset = self.current_expression[end + start..]
-                        .char_indices()
-                        .find_map(|(ind, c)| if c.is_ascii_digit() { None } else { Some(ind) })
-                        .unwrap_or(self.current_expression.len() - (end + start));
-                }
-                let end_total = end + start + end_offset;
-                let number_expression = &self.current_expression[..end_total];
-                // Use inbuilt rust string -> number conversion to get number and handle errors
-                self.cut_current_expression(end_total);
-                return Some(match f64::from_str(number_expression) {
-                    Err(_) => Token::Unrecognized,
-                    Ok(f) => Token::Number(f.to_owned()),
-                });
-            };
-            // Create symbol tokens
-            let symbol = self.current_expression.chars().next().unwrap();
-            self.current_expression = &self.current_expression[1..];
-            return Some(match symbol {
-                '+' => Token::Plus,
-                '-' => Token::Minus,
-                '*' => match self.current_expression.chars().next().unwrap_or(' ') {
-                    '*' => {
-                        self.current_expression = &self.current_expression[1..];
-                        Token::Power
-                    }
-                    _ => Token::Multiply,
-                },
-                '/' => Token::Divide,
-                '^' => Token::Power,
-                '(' => Token::BracketOpen,
-                ')' => Token::BracketClose,
-                '=' => Token::Assign,
-                ',' => Token::Comma,
-                ';' => Token::EndOfExpression,
-                '!' => match self.current_expression.chars().next().unwrap_or(' ') {
-                    '!' => {
-                        self.current_expression = &self.current_expression[1..];
-                        Token::DoubleFactorial
-                    }
-                    _ => Token::Factorial,
-                },
-                _ => Token::Unrecognized,
-            });
-        }
+    /// Return all variables defined by expressions, keyed by the variable name.
+    pub fn expression_variables(&self) -> &HashMap<String, String> {
+        &self.expression_variables
     }
-}
 
-// Helper methods not in standard iterator trait.
-impl<'a> TokenIterator<'a> {
-    // Return the next token and the current token (in string form).
-    fn next_token_and_str(&mut self) -> (Option<Token>, &'a str) {
-        let next_token = self.next();
-        let next_str = self.current_expression;
-        (next_token, next_str)
+    /// Register a table of sampled values that can be called like a function, e.g. `flux(x)`.
+    ///
+    /// The table is evaluated by piecewise linear interpolation and returns the y value of
+    /// the closest end of the table for arguments outside of the x values.
+    /// Registering a table with the name of an existing table replaces it.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - Name of the table used in expressions, must not be a built-in function
+    /// * `xs` - Strictly increasing finite x values
+    /// * `ys` - Finite y values at the x values, same length as xs and at least two values
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` - The table has been registered
+    /// * `Err(CalculatorError::InvalidInterpolationTable)` - The name or the values are not valid
+    /// * `Err(CalculatorError::CalculatorFrozen)` - The Calculator is frozen
+    ///
+    pub fn register_interpolation_table(
+        &mut self,
+        name: &str,
+        xs: Vec<f64>,
+        ys: Vec<f64>,
+    ) -> Result<(), CalculatorError> {
+        self.register_interpolation_table_with_out_of_range(name, xs, ys, OutOfRange::Clamp)
     }
 
-    // Modify the current expression to be a slice of the current expression.
-    fn cut_current_expression(&mut self, end: usize) {
-        if end == self.current_expression.len() {
-            self.current_expression = "";
-        } else {
-            self.current_expression = &self.current_expression[end..];
+    /// Register a table of sampled values choosing the behavior outside of the table.
+    ///
+    /// Same as [Calculator::register_interpolation_table] with [OutOfRange::Error]
+    /// returning `CalculatorError::InterpolationOutOfRange` for arguments outside of the x values.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - Name of the table used in expressions, must not be a built-in function
+    /// * `xs` - Strictly increasing finite x values
+    /// * `ys` - Finite y values at the x values, same length as xs and at least two values
+    /// * `out_of_range` - Behavior for arguments outside of the x values
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` - The table has been registered
+    /// * `Err(CalculatorError::InvalidInterpolationTable)` - The name or the values are not valid
+    /// * `Err(CalculatorError::CalculatorFrozen)` - The Calculator is frozen
+    ///
+    pub fn register_interpolation_table_with_out_of_range(
+        &mut self,
+        name: &str,
+        xs: Vec<f64>,
+        ys: Vec<f64>,
+        out_of_range: OutOfRange,
+    ) -> Result<(), CalculatorError> {
+        self.check_not_frozen("register_interpolation_table")?;
+        let mut characters = name.chars();
+        if !characters.next().is_some_and(char::is_alphabetic)
+            || !characters.all(|c| c.is_alphanumeric() || c == '_')
+        {
+            return Err(CalculatorError::InvalidInterpolationTable {
+                msg: "name is not a valid function name",
+            });
+        }
+        if lookup_function(name).is_ok()
+            || VARIABLE_AGGREGATES.contains(&name)
+            || name == ASSERT
+            || name == INCLUDE
+        {
+            return Err(CalculatorError::InvalidInterpolationTable {
+                msg: "name is a built-in function",
+            });
         }
+        let table = InterpolationTable::new(xs, ys, out_of_range)?;
+        self.interpolation_tables
+            .insert(name.to_string(), Arc::new(table));
+        Ok(())
     }
-}
 
-// /// Parser from &str to f64 using TokenIterator lexer.
-// struct Parser<'a> {
-//     /// Expression that has not been parsed yet
-//     remaining_expression: &'a str,
-//     /// Token that is currently parsed
-//     current_token: Token,
-//     /// Calculator that contains set variables
-//     calculator: &'a mut Calculator,
-// }
-
-/// Parser from &str to f64 using TokenIterator lexer.
-enum ParserEnum<'a> {
-    MutableCalculator {
-        /// Expression that has not been parsed yet
-        remaining_expression: &'a str,
-        /// Token that is currently parsed
-        current_token: Token,
-        /// Calculator that contains set variables
-        calculator: &'a mut Calculator,
-    },
-    ImmutableCalculator {
-        /// Expression that has not been parsed yet
-        remaining_expression: &'a str,
-        /// Token that is currently parsed
-        current_token: Token,
-        /// Calculator that contains set variables
-        calculator: &'a Calculator,
-    },
-}
-
-impl<'a, 'b> ParserEnum<'a>
-where
-    'b: 'a,
-{
-    /// Get variable for Calculator.
+    /// Get a registered interpolation table.
     ///
     /// # Arguments
     ///
-    /// * `name` - Name of the variable
+    /// * `name` - Name of the table
     ///
     /// # Returns
     ///
-    /// `value` - Result
+    /// `Option<&InterpolationTable>` - The table, None when no table is registered with the name
     ///
-    #[inline]
-    pub fn get_variable(&self, name: &str) -> Result<f64, CalculatorError> {
-        match self {
-            Self::MutableCalculator { calculator, .. } => calculator.get_variable(name),
-            Self::ImmutableCalculator { calculator, .. } => calculator.get_variable(name),
-        }
+    pub fn get_interpolation_table(&self, name: &str) -> Option<&InterpolationTable> {
+        self.interpolation_tables
+            .get(name)
+            .map(|table| table.as_ref())
     }
 
-    /// Set variable for Calculator.
+    /// Set the handler evaluating functions that are not known to the Calculator.
+    ///
+    /// The handler is only called for function names that are neither built-in functions
+    /// nor registered interpolation tables. It receives the name of the function and
+    /// the values of all arguments. Returning None reports the function as not found.
+    /// Replaces a previously set handler.
     ///
     /// # Arguments
     ///
-    /// * `name` - Name of the variable
-    /// * `value` - Float value of the variable
-    #[inline]
-    pub fn set_variable(&mut self, name: &str, value: f64) -> Result<(), CalculatorError> {
-        match self {
-            Self::MutableCalculator { calculator, .. } => calculator.set_variable(name, value),
-            Self::ImmutableCalculator { .. } => {
-                return Err(CalculatorError::ParsingError {
-                    msg: "Assign operation not allowed when using immutable Calculator",
-                })
-            }
-        }
+    /// * `handler` - Handler returning the value of a function or None when it does not know the function
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` - The handler has been set
+    /// * `Err(CalculatorError::CalculatorFrozen)` - The Calculator is frozen
+    ///
+    pub fn set_unknown_function_handler(
+        &mut self,
+        handler: UnknownFunctionHandler,
+    ) -> Result<(), CalculatorError> {
+        self.check_not_frozen("set_unknown_function_handler")?;
+        self.unknown_function_handler = Some(handler);
         Ok(())
     }
 
-    fn new_mutable(expression: &'a str, calculator: &'b mut Calculator) -> Self {
-        let (next_token, next_str) = (TokenIterator {
-            current_expression: expression,
-        })
-        .next_token_and_str();
-        ParserEnum::MutableCalculator {
-            remaining_expression: next_str,
-            current_token: next_token.unwrap(),
-            calculator,
-        }
+    /// Set the observer notified of every expression evaluated by [Calculator::parse_str],
+    /// [Calculator::parse_get] and [Calculator::parse_str_assign].
+    ///
+    /// The observer is called after the evaluation with an [EvaluationEvent] carrying the
+    /// expression, the value or error kind, the duration and a sequence number. Float values
+    /// passed to [Calculator::parse_get] are not evaluated and not reported. Clones of the
+    /// Calculator share the observer and its sequence numbers. Replaces a previously set
+    /// observer and restarts the sequence numbers at 0. Without observer no time is measured.
+    ///
+    /// Setting an observer does not change any result, so it is also allowed for a frozen
+    /// Calculator. The observer must not panic: a panic is not caught and propagates to the
+    /// caller after the evaluation, variables assigned by [Calculator::parse_str_assign]
+    /// are set nonetheless.
+    ///
+    /// # Arguments
+    ///
+    /// * `observer` - Observer called with the record of every evaluated expression
+    ///
+    pub fn set_observer(&mut self, observer: EvaluationObserver) {
+        self.observer = Some(ObserverSlot {
+            observer,
+            sequence: Arc::new(AtomicU64::new(0)),
+        });
     }
 
-    fn new_immutable(expression: &'a str, calculator: &'b Calculator) -> Self {
-        let (next_token, next_str) = (TokenIterator {
-            current_expression: expression,
-        })
-        .next_token_and_str();
-        ParserEnum::ImmutableCalculator {
-            remaining_expression: next_str,
-            current_token: next_token.unwrap(),
-            calculator,
-        }
+    /// Remove the observer of evaluated expressions, see [Calculator::set_observer].
+    pub fn remove_observer(&mut self) {
+        self.observer = None;
     }
 
-    fn remaining_expression(&mut self) -> &'a str {
-        match self {
-            ParserEnum::MutableCalculator {
-                remaining_expression,
-                ..
-            } => remaining_expression,
-            ParserEnum::ImmutableCalculator {
-                remaining_expression,
-                ..
-            } => remaining_expression,
-        }
+    /// Remove the handler evaluating functions that are not known, see [Calculator::set_unknown_function_handler].
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` - The handler has been removed
+    /// * `Err(CalculatorError::CalculatorFrozen)` - The Calculator is frozen
+    ///
+    pub fn remove_unknown_function_handler(&mut self) -> Result<(), CalculatorError> {
+        self.check_not_frozen("remove_unknown_function_handler")?;
+        self.unknown_function_handler = None;
+        Ok(())
     }
 
-    fn current_token(&self) -> &Token {
-        match self {
-            ParserEnum::MutableCalculator { current_token, .. } => current_token,
-            ParserEnum::ImmutableCalculator { current_token, .. } => current_token,
+    /// Register a library of statements executed by `include('name')`.
+    ///
+    /// Libraries compose scripts from shared definitions, e.g. of derived parameters.
+    /// `include('name');` executes the statements of the library at that point of a script
+    /// parsed with [Calculator::parse_str_assign], the variables assigned in the library are
+    /// assigned like in the including script. Libraries can include other libraries,
+    /// including a library that is currently included returns
+    /// `CalculatorError::RecursiveInclude` and libraries nested deeper than [MAX_INCLUDE_DEPTH]
+    /// return `CalculatorError::IncludeTooDeep`. Other errors in a library are returned as
+    /// `CalculatorError::LibraryError` with the name of the library and the position in it.
+    ///
+    /// The script is checked like [Calculator::check_str] without being executed, variables
+    /// read by the library do not have to be set and included libraries do not have to be
+    /// registered yet. Registering a library with the name of an existing library replaces it.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - Name of the library used in `include('name')`
+    /// * `script` - Statements of the library
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` - The library has been registered
+    /// * `Err(CalculatorError::LibraryError)` - The script can not be parsed, contains the reason
+    ///   and the position in the script
+    /// * `Err(CalculatorError::CalculatorFrozen)` - The Calculator is frozen
+    ///
+    pub fn register_library(&mut self, name: &str, script: &str) -> Result<(), CalculatorError> {
+        self.check_not_frozen("register_library")?;
+        let compiled = self.expression_cache.get_or_compile(script);
+        let declared = HashSet::new();
+        let mut parser: Parser<_, Unevaluated> = Parser::with_seed(
+            &compiled,
+            CheckingCalculator {
+                calculator: self,
+                declared: &declared,
+                assigned: HashSet::new(),
+                undeclared: RefCell::new(Vec::new()),
+                missing_libraries_allowed: true,
+            },
+            None,
+        );
+        parser.including.push(name.to_string());
+        match parser.evaluate_all_tokens() {
+            Ok(_) => (),
+            Err(
+                error @ (CalculatorError::RecursiveInclude { .. }
+                | CalculatorError::IncludeTooDeep { .. }),
+            ) => return Err(error),
+            Err(error) => {
+                return Err(CalculatorError::LibraryError {
+                    library: name.to_string(),
+                    position: parser.current_position(),
+                    error: Box::new(error),
+                })
+            }
         }
+        self.libraries.insert(name.to_string(), script.to_string());
+        Ok(())
     }
 
-    // impl<'a, 'b> Parser<'a>
-    // where
-    //     'b: 'a,
-    // {
-
-    /// Get Variable form internal
+    /// Get the script of a registered library.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - Name of the library
+    ///
+    /// # Returns
+    ///
+    /// `Option<&str>` - The script, None when no library is registered with the name
+    ///
+    pub fn get_library(&self, name: &str) -> Option<&str> {
+        self.libraries.get(name).map(|script| script.as_str())
+    }
 
-    // /// Initialize a new instance of Parser.
-    // fn new(expression: &'a str, calculator: &'b mut Calculator) -> Self {
-    //     let (next_token, next_str) = (TokenIterator {
-    //         current_expression: expression,
-    //     })
-    //     .next_token_and_str();
-    //     Parser {
-    //         remaining_expression: next_str,
-    //         current_token: next_token.unwrap(),
-    //         calculator,
-    //     }
-    // }
+    /// Return the compiled script of a library, using the expression cache.
+    fn compiled_library(&self, name: &str) -> Result<Arc<CompiledExpression>, CalculatorError> {
+        self.libraries
+            .get(name)
+            .map(|script| self.expression_cache.get_or_compile(script))
+            .ok_or_else(|| CalculatorError::LibraryNotFound {
+                name: name.to_string(),
+            })
+    }
 
-    /// Get next token via TokenIterator.
-    fn next_token(&mut self) {
-        let (next_token, next_str) = (TokenIterator {
-            current_expression: self.remaining_expression(),
-        })
-        .next_token_and_str();
-        match next_token {
-            None => match self {
-                ParserEnum::MutableCalculator {
-                    remaining_expression,
-                    current_token,
-                    ..
-                } => {
-                    *current_token = Token::EndOfString;
-                    *remaining_expression = "";
-                }
-                ParserEnum::ImmutableCalculator {
-                    remaining_expression,
-                    current_token,
-                    ..
-                } => {
-                    *current_token = Token::EndOfString;
-                    *remaining_expression = "";
-                }
-            },
-            Some(t) => match self {
-                ParserEnum::MutableCalculator {
-                    remaining_expression,
-                    current_token,
-                    ..
-                } => {
-                    *current_token = t;
-                    *remaining_expression = next_str;
-                }
-                ParserEnum::ImmutableCalculator {
-                    remaining_expression,
-                    current_token,
-                    ..
-                } => {
-                    *current_token = t;
-                    *remaining_expression = next_str;
-                }
-            },
-        }
+    /// Return the compiled expression defining a variable, using the expression cache.
+    fn compiled_expression_variable(&self, name: &str) -> Option<Arc<CompiledExpression>> {
+        self.expression_variables
+            .get(name)
+            .map(|expression| self.expression_cache.get_or_compile(expression))
     }
 
-    /// Evaluate all Tokens to real value, None (for not returning expressions)
-    /// or return error.
-    fn evaluate_all_tokens(&mut self) -> Result<Option<f64>, CalculatorError> {
-        let mut current_value: Option<f64> = None;
-        while self.current_token() != &Token::EndOfString {
-            current_value = self.evaluate_init()?;
-            while self.current_token() == &Token::EndOfExpression {
-                self.next_token();
+    ///  Parse a string expression.
+    ///
+    /// Identifiers are looked up in the variables set with [Calculator::set_variable],
+    /// then in the variables defined by expressions ([Calculator::set_expression_variable])
+    /// and last in the built-in constants `pi`, `PI`, `e`, `E` and `tau`,
+    /// see [crate::introspection::constants]. Setting a variable with the name of a
+    /// constant therefore overrides the constant.
+    ///
+    /// # Arguments
+    ///
+    /// * `expression` - Expression that is parsed
+    ///
+    pub fn parse_str(&self, expression: &str) -> Result<f64, CalculatorError> {
+        observe(self.observer.as_ref(), expression, || {
+            let compiled = self.expression_cache.get_or_compile(expression);
+            self.evaluate_compiled(&compiled)
+        })
+    }
+
+    /// Compile an expression once for repeated evaluation with [CompiledExpression::evaluate].
+    ///
+    /// The expression is lexed and checked with [validate_expression] when it is compiled.
+    /// Variables and functions are resolved when it is evaluated, so the compiled expression
+    /// can be evaluated with any Calculator and sees changed variable values.
+    ///
+    /// # Arguments
+    ///
+    /// * `expression` - Expression that is compiled, must not contain assignments
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(CompiledExpression)` - The compiled expression
+    /// * `Err(CalculatorError::InvalidExpression)` - Byte position of the first invalid element and the reason
+    ///
+    pub fn compile(&self, expression: &str) -> Result<CompiledExpression, CalculatorError> {
+        validate_expression(expression)?;
+        Ok(CompiledExpression::new(expression))
+    }
+
+    /// Evaluate a compiled expression like [Calculator::parse_str].
+    pub(crate) fn evaluate_compiled(
+        &self,
+        compiled: &CompiledExpression,
+    ) -> Result<f64, CalculatorError> {
+        let mut parser = Parser::new(compiled, self);
+        let end_value = parser.evaluate_all_tokens()?;
+        match end_value {
+            None => Err(CalculatorError::NoValueReturnedParsing),
+            Some(x) => Ok(x),
+        }
+    }
+
+    /// Parse a string expression and return its value and its derivative with respect to a variable.
+    ///
+    /// The derivative is computed exactly by evaluating the expression with dual numbers
+    /// (forward-mode automatic differentiation), the value is identical to [Calculator::parse_str].
+    /// Variables defined by expressions and interpolation tables are differentiated as well.
+    /// Piecewise constant functions have a vanishing derivative also at their discontinuities,
+    /// functions without derivative rule return an error. See [CalculatorFloat::derivative]
+    /// for the symbolic derivative.
+    ///
+    /// # Arguments
+    ///
+    /// * `expression` - Expression that is parsed
+    /// * `wrt` - Name of the variable the derivative is taken with respect to
+    ///
+    /// # Returns
+    ///
+    /// * `Ok((f64, f64))` - Value of the expression and its derivative
+    /// * `Err(CalculatorError)` - Parsing failed or a function has no derivative rule
+    ///
+    pub fn parse_str_derivative(
+        &self,
+        expression: &str,
+        wrt: &str,
+    ) -> Result<(f64, f64), CalculatorError> {
+        let compiled = self.expression_cache.get_or_compile(expression);
+        let mut parser: Parser<_, Dual> = Parser::with_seed(&compiled, self, Some(wrt));
+        let result = parser
+            .evaluate_all_tokens()?
+            .ok_or(CalculatorError::NoValueReturnedParsing)?;
+        Ok((result.value, result.derivative))
+    }
+
+    /// Parse a string expression with imaginary numbers to a complex number.
+    ///
+    /// Numbers directly followed by `i` or `j` are imaginary, e.g. `1 + 2i` or `exp(0.5j * pi)`.
+    /// Variables are real and looked up like in [Calculator::parse_str], expressions without
+    /// imaginary numbers evaluate to the same real value. The functions `abs`, `exp`, `log`,
+    /// `sqrt`, `sin`, `cos`, `tan`, `sinh`, `cosh`, `tanh` and `pow` accept complex arguments
+    /// and use the principal branch, also for `sqrt` and `log` of negative numbers. All other
+    /// functions, comparisons and conditions require real arguments.
+    ///
+    /// # Arguments
+    ///
+    /// * `expression` - Expression that is parsed
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Complex<f64>)` - Value of the expression
+    /// * `Err(CalculatorError::ComplexCanNotBeConvertedToFloat)` - A value that has to be real
+    ///   has a nonzero imaginary part
+    /// * `Err(CalculatorError)` - Parsing failed
+    ///
+    pub fn parse_str_complex(&self, expression: &str) -> Result<Complex<f64>, CalculatorError> {
+        let compiled = self.expression_cache.get_or_compile(expression);
+        let mut parser: Parser<_, Complex<f64>> = Parser::with_seed(&compiled, self, None);
+        parser
+            .evaluate_all_tokens()?
+            .ok_or(CalculatorError::NoValueReturnedParsing)
+    }
+
+    ///  Parse a string expression and report suspicious patterns.
+    ///
+    /// Same as [Calculator::parse_str], additionally returning non-fatal warnings for
+    /// patterns that are valid but most likely not intended, see [CalculatorWarning].
+    /// Warnings are only reported when parsing succeeds.
+    ///
+    /// # Arguments
+    ///
+    /// * `expression` - Expression that is parsed
+    ///
+    /// # Returns
+    ///
+    /// `(f64, Vec<CalculatorWarning>)` - Value of the expression and the warnings ordered by position
+    ///
+    pub fn parse_str_with_warnings(
+        &self,
+        expression: &str,
+    ) -> Result<(f64, Vec<CalculatorWarning>), CalculatorError> {
+        let value = self.parse_str(expression)?;
+        Ok((
+            value,
+            expression_warnings(
+                expression,
+                &self.expression_cache.get_or_compile(expression),
+                self.variables
+                    .keys()
+                    .chain(self.expression_variables.keys()),
+                self.warn_on_precision_loss,
+            ),
+        ))
+    }
+
+    ///  Parse a string expression allowing variable assignments.
+    ///
+    /// Parsing is atomic: assignments are staged in a temporary map that is
+    /// consulted before the variables of the Calculator while the expression is
+    /// evaluated. The staged assignments are only written to the Calculator when
+    /// the whole expression has been parsed successfully. When an error occurs,
+    /// the variables of the Calculator are left unchanged.
+    /// Assigning the same variable several times in one expression is allowed,
+    /// later statements see the most recent assignment.
+    /// A frozen Calculator returns `CalculatorError::CalculatorFrozen` without parsing,
+    /// also for expressions without assignments.
+    ///
+    /// # Arguments
+    ///
+    /// * `expression` - Expression that is parsed
+    ///
+    pub fn parse_str_assign(&mut self, expression: &str) -> Result<f64, CalculatorError> {
+        let observer = self.observer.clone();
+        observe(observer.as_ref(), expression, || {
+            self.parse_str_assign_unobserved(expression)
+        })
+    }
+
+    /// Parse a string expression allowing variable assignments without notifying the observer.
+    fn parse_str_assign_unobserved(&mut self, expression: &str) -> Result<f64, CalculatorError> {
+        self.check_not_frozen("parse_str_assign")?;
+        let compiled = self.expression_cache.get_or_compile(expression);
+        let mut parser = Parser::new(
+            &compiled,
+            StagedCalculator {
+                calculator: self,
+                staged_variables: HashMap::new(),
+            },
+        );
+        let end_value = parser.evaluate_all_tokens()?;
+        let staged_variables = parser.into_calculator().staged_variables;
+        match end_value {
+            None => Err(CalculatorError::NoValueReturnedParsing),
+            Some(x) => {
+                self.variables.extend(staged_variables);
+                Ok(x)
             }
         }
-        Ok(current_value)
     }
 
-    /// Initialize the evaluation of an expression.
-    fn evaluate_init(&mut self) -> Result<Option<f64>, CalculatorError> {
-        if self.current_token() == &Token::EndOfExpression
-            || self.current_token() == &Token::EndOfString
-        {
-            Err(CalculatorError::UnexpectedEndOfExpression)
-        } else {
-            if let Token::VariableAssign(ref vs) = (*self).current_token() {
-                match self {
-                    ParserEnum::MutableCalculator { .. } => (),
-                    ParserEnum::ImmutableCalculator { .. } => {
-                        return Err(CalculatorError::ForbiddenAssign {
-                            variable_name: vs.to_owned(),
-                        })
+    ///  Parse a string expression allowing variable assignments and report suspicious patterns.
+    ///
+    /// Same as [Calculator::parse_str_assign], additionally returning non-fatal warnings for
+    /// patterns that are valid but most likely not intended, see [CalculatorWarning].
+    /// Warnings are only reported when parsing succeeds.
+    ///
+    /// # Arguments
+    ///
+    /// * `expression` - Expression that is parsed
+    ///
+    /// # Returns
+    ///
+    /// `(f64, Vec<CalculatorWarning>)` - Value of the expression and the warnings ordered by position
+    ///
+    pub fn parse_str_assign_with_warnings(
+        &mut self,
+        expression: &str,
+    ) -> Result<(f64, Vec<CalculatorWarning>), CalculatorError> {
+        // Warnings are based on the variables set before the expression is parsed
+        let warnings = expression_warnings(
+            expression,
+            &self.expression_cache.get_or_compile(expression),
+            self.variables
+                .keys()
+                .chain(self.expression_variables.keys()),
+            self.warn_on_precision_loss,
+        );
+        let value = self.parse_str_assign(expression)?;
+        Ok((value, warnings))
+    }
+
+    /// Check an expression against declared variables without computing it.
+    ///
+    /// The expression is parsed completely: function names, numbers of arguments and
+    /// brackets are checked and every read variable has to be declared or assigned before
+    /// in the expression. No arithmetic is performed, so domain errors and divisions by zero
+    /// are not reported, asserts always pass, and the variables and expression variables
+    /// of the Calculator are not used. Functions that are not known are passed to the unknown
+    /// function handler with dummy arguments to check that the handler knows the name.
+    /// Assignments do not change the Calculator.
+    ///
+    /// # Arguments
+    ///
+    /// * `expression` - Expression that is checked, may contain assignments
+    /// * `declared` - Names of the variables that are set when the expression is evaluated
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(CheckReport)` - The variables, assignments, function calls and warnings of the expression
+    /// * `Err(CalculatorError::VariablesNotSet)` - Names of all read variables that are not declared
+    /// * `Err(CalculatorError)` - The expression can not be parsed
+    ///
+    pub fn check_str(
+        &self,
+        expression: &str,
+        declared: &HashSet<String>,
+    ) -> Result<CheckReport, CalculatorError> {
+        let compiled = self.expression_cache.get_or_compile(expression);
+        let undeclared = self.undeclared_variables(&compiled, declared)?;
+        if !undeclared.is_empty() {
+            return Err(CalculatorError::VariablesNotSet { names: undeclared });
+        }
+        let mut report = CheckReport::default();
+        let add = |names: &mut Vec<String>, name: &str| {
+            if !names.iter().any(|known| known == name) {
+                names.push(name.to_string());
+            }
+        };
+        let tokens = compiled.tokens();
+        for (index, token) in tokens.iter().enumerate() {
+            match token {
+                Token::Variable(name) => add(&mut report.variables, name),
+                Token::VariableAssign(name) => add(&mut report.assigned, name),
+                Token::CompoundAssign(name, _) => {
+                    add(&mut report.variables, name);
+                    add(&mut report.assigned, name);
+                }
+                Token::Function(name) if name == INCLUDE => (),
+                Token::Function(name) | Token::Reduction(name) => {
+                    let call = (
+                        name.to_string(),
+                        number_call_arguments(&tokens[index + 1..]),
+                    );
+                    if !report.functions.contains(&call) {
+                        report.functions.push(call);
                     }
                 }
-                let vsnew = vs.to_owned();
-                self.next_token();
-                let res = self.evaluate_binary_1()?;
-                self.set_variable(&vsnew, res)?;
-                return Ok(Some(res));
+                _ => (),
             }
-            Ok(Some(self.evaluate_binary_1()?))
         }
+        report.warnings = expression_warnings(
+            expression,
+            &compiled,
+            declared.iter(),
+            self.warn_on_precision_loss,
+        );
+        Ok(report)
     }
 
-    /// Evaluate least preference binary expression (+, -).
-    fn evaluate_binary_1(&mut self) -> Result<f64, CalculatorError> {
-        let mut res = self.evaluate_binary_2()?;
-        while self.current_token() == &Token::Plus || self.current_token() == &Token::Minus {
-            let bsum: bool = self.current_token() == &Token::Plus;
-            self.next_token();
-            let val = self.evaluate_binary_2()?;
-            if bsum {
-                res += val;
-            } else {
-                res -= val;
-            }
+    /// Validate an expression before any variables are set, without computing it.
+    ///
+    /// The expression is first checked with the free function [validate_expression] and returns
+    /// the same errors for invalid expressions. It is then parsed completely like in
+    /// [Calculator::check_str] to check function names and numbers of arguments.
+    /// Variables do not have to be set, their names are returned instead.
+    ///
+    /// # Arguments
+    ///
+    /// * `expression` - Expression that is validated
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Vec<String>)` - Names of the variables of the expression in the order of their first use
+    /// * `Err(CalculatorError::InvalidExpression)` - Position of an unrecognized element, an assignment
+    ///   or an unbalanced bracket, see [validate_expression]
+    /// * `Err(CalculatorError)` - The expression can not be parsed
+    ///
+    pub fn validate_expression(&self, expression: &str) -> Result<Vec<String>, CalculatorError> {
+        validate_expression(expression)?;
+        let compiled = self.expression_cache.get_or_compile(expression);
+        self.undeclared_variables(&compiled, &HashSet::new())
+    }
+
+    /// Parse a compiled expression without computing it and return the read variables that
+    /// are neither declared nor assigned before.
+    fn undeclared_variables(
+        &self,
+        compiled: &CompiledExpression,
+        declared: &HashSet<String>,
+    ) -> Result<Vec<String>, CalculatorError> {
+        let mut parser: Parser<_, Unevaluated> = Parser::with_seed(
+            compiled,
+            CheckingCalculator {
+                calculator: self,
+                declared,
+                assigned: HashSet::new(),
+                undeclared: RefCell::new(Vec::new()),
+                missing_libraries_allowed: false,
+            },
+            None,
+        );
+        parser
+            .evaluate_all_tokens()?
+            .ok_or(CalculatorError::NoValueReturnedParsing)?;
+        Ok(parser.into_calculator().undeclared.into_inner())
+    }
+
+    /// Parse a CalculatorFloat to float.
+    ///
+    /// # Arguments
+    ///
+    /// * `parse_variable` - Parsed string CalculatorFloat or returns float value
+    ///
+    pub fn parse_get(&self, parse_variable: CalculatorFloat) -> Result<f64, CalculatorError> {
+        self.parse_get_ref(&parse_variable)
+    }
+
+    /// Parse a borrowed CalculatorFloat to float.
+    ///
+    /// Same as [Calculator::parse_get] without requiring ownership,
+    /// so values held in other structs do not need to be cloned.
+    ///
+    /// # Arguments
+    ///
+    /// * `parse_variable` - Parsed string CalculatorFloat or returns float value
+    ///
+    pub fn parse_get_ref(&self, parse_variable: &CalculatorFloat) -> Result<f64, CalculatorError> {
+        match parse_variable {
+            CalculatorFloat::Float(x) => Ok(*x),
+            CalculatorFloat::Str(expression) => self.parse_str(expression),
         }
-        Ok(res)
     }
 
-    /// Evaluate middle preference binary expression (*, /).
-    fn evaluate_binary_2(&mut self) -> Result<f64, CalculatorError> {
-        let mut res = self.evaluate_binary_3()?;
-        while self.current_token() == &Token::Multiply || self.current_token() == &Token::Divide {
-            let bmul: bool = self.current_token() == &Token::Multiply;
-            self.next_token();
-            let val = self.evaluate_binary_3()?;
-            if bmul {
-                res *= val;
-            } else {
-                if val == 0.0 {
-                    return Err(CalculatorError::DivisionByZero);
+    /// Parse a CalculatorComplex to a complex number.
+    ///
+    /// The real and imaginary part are parsed like [Calculator::parse_get],
+    /// numeric parts are returned unchanged.
+    ///
+    /// # Arguments
+    ///
+    /// * `parse_variable` - CalculatorComplex with numeric or symbolic parts
+    ///
+    pub fn parse_get_complex(
+        &self,
+        parse_variable: CalculatorComplex,
+    ) -> Result<Complex<f64>, CalculatorError> {
+        Ok(Complex::new(
+            self.parse_get_ref(&parse_variable.re)?,
+            self.parse_get_ref(&parse_variable.im)?,
+        ))
+    }
+
+    /// Rename the variables of a script to `v0`, `v1`, ... to share it without the original names.
+    ///
+    /// Read variables and assignment targets are renamed consistently in the order of their
+    /// first occurrence, so the renamed script evaluates like the original when the variables
+    /// are set according to the mapping. Numbers, functions, placeholders, string literals
+    /// (e.g. the prefixes of `sum_vars`) and comments are not changed. The script is not validated.
+    ///
+    /// # Arguments
+    ///
+    /// * `script` - Script with the original names
+    ///
+    /// # Returns
+    ///
+    /// * `(String, HashMap<String, String>)` - The renamed script and the original names with their replacements
+    ///
+    pub fn anonymize_script(script: &str) -> (String, HashMap<String, String>) {
+        let mut mapping: HashMap<String, String> = HashMap::new();
+        let anonymized = anonymize_variables(script, &mut mapping);
+        (anonymized, mapping)
+    }
+
+    /// Evaluate an expression on the Cartesian product of variable values.
+    ///
+    /// The expression is compiled once. The swept variables are set in a local overlay
+    /// that is consulted before the variables of the Calculator, the Calculator itself is not changed.
+    ///
+    /// # Arguments
+    ///
+    /// * `expression` - Expression that is evaluated, assignments are not allowed
+    /// * `axes` - Names of the swept variables and the values of each variable
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(SweepResult)` - The axes and the values in row-major order, the last axis changes fastest
+    /// * `Err(CalculatorError::SweepPointError)` - Multi-index of the first failing point and the reason
+    ///
+    pub fn sweep(
+        &self,
+        expression: &str,
+        axes: &[(&str, Vec<f64>)],
+    ) -> Result<SweepResult, CalculatorError> {
+        let compiled = self.expression_cache.get_or_compile(expression);
+        let names: Vec<&str> = axes.iter().map(|(name, _)| *name).collect();
+        let number_points: usize = axes.iter().map(|(_, values)| values.len()).product();
+        let mut values: Vec<f64> = Vec::with_capacity(number_points);
+        let mut index: Vec<usize> = vec![0; axes.len()];
+        let mut point: Vec<f64> = Vec::with_capacity(axes.len());
+        for _ in 0..number_points {
+            point.clear();
+            point.extend(axes.iter().zip(index.iter()).map(|((_, axis), i)| axis[*i]));
+            let mut parser = Parser::new(
+                &compiled,
+                SweepCalculator {
+                    calculator: self,
+                    names: &names,
+                    values: &point,
+                },
+            );
+            let value = parser
+                .evaluate_all_tokens()
+                .and_then(|value| value.ok_or(CalculatorError::NoValueReturnedParsing))
+                .map_err(|error| CalculatorError::SweepPointError {
+                    index: index.clone(),
+                    error: Box::new(error),
+                })?;
+            values.push(value);
+            // Advance the multi-index, the last axis changes fastest
+            for (i, (_, axis)) in index.iter_mut().zip(axes.iter()).rev() {
+                *i += 1;
+                if *i < axis.len() {
+                    break;
                 }
-                res /= val;
+                *i = 0;
             }
         }
-        Ok(res)
+        Ok(SweepResult {
+            axes: axes
+                .iter()
+                .map(|(name, axis)| (name.to_string(), axis.clone()))
+                .collect(),
+            values,
+        })
     }
 
-    /// Evaluate least preference binary expression (^, !).
-    fn evaluate_binary_3(&mut self) -> Result<f64, CalculatorError> {
-        let mut res = self.evaluate_unary()?;
-        match self.current_token() {
-            Token::DoubleFactorial => {
-                return Err(CalculatorError::NotImplementedError {
-                    fct: "DoubleFactorial",
-                })
+    /// Check numerically whether two expressions are probably equal.
+    ///
+    /// The variables of both expressions that are not set in the Calculator are sampled
+    /// uniformly from `[-π, π]` by a ChaCha8 random number generator seeded with `seed`,
+    /// so the same seed gives the same samples on every platform. Both expressions are
+    /// evaluated at every sample, the Calculator itself is not changed.
+    /// Two values agree when `|a - b| <= 1e-12 + 1e-9 * max(|a|, |b|)`, when they are equal
+    /// infinities or when both are NaN.
+    ///
+    /// Expressions that agree on all samples are not proven equal, e.g. when they differ only
+    /// outside of `[-π, π]`.
+    ///
+    /// # Arguments
+    ///
+    /// * `a` - First expression
+    /// * `b` - Second expression
+    /// * `samples` - Number of samples
+    /// * `seed` - Seed of the random number generator
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(false)` - The expressions disagree on a sample
+    /// * `Ok(true)` - The expressions agree on all samples
+    /// * `Err(CalculatorError::SamplePointError)` - Sampled values and the reason the evaluation failed
+    ///
+    #[cfg(feature = "rand")]
+    pub fn probably_equal(
+        &self,
+        a: &CalculatorFloat,
+        b: &CalculatorFloat,
+        samples: usize,
+        seed: u64,
+    ) -> Result<bool, CalculatorError> {
+        use rand::distributions::{Distribution, Uniform};
+        use rand::SeedableRng;
+        use std::collections::BTreeSet;
+
+        // Float values are used directly, symbolic values are compiled once
+        let mut free_variables: BTreeSet<String> = BTreeSet::new();
+        let mut sides: Vec<(f64, Option<Arc<CompiledExpression>>)> = Vec::with_capacity(2);
+        for expression in [a, b] {
+            match expression {
+                CalculatorFloat::Float(x) => sides.push((*x, None)),
+                CalculatorFloat::Str(expression) => {
+                    free_variables.extend(variables_in_expression(expression)?.into_iter().filter(
+                        |name| {
+                            !self.variables.contains_key(name)
+                                && !self.expression_variables.contains_key(name)
+                                && lookup_constant(name).is_none()
+                        },
+                    ));
+                    sides.push((0.0, Some(self.expression_cache.get_or_compile(expression))));
+                }
             }
-            Token::Factorial => {
-                return Err(CalculatorError::NotImplementedError { fct: "Factorial" })
+        }
+        let names: Vec<&str> = free_variables.iter().map(|name| name.as_str()).collect();
+        let distribution = Uniform::new_inclusive(-std::f64::consts::PI, std::f64::consts::PI);
+        let mut rng = rand_chacha::ChaCha8Rng::seed_from_u64(seed);
+        let mut point: Vec<f64> = Vec::with_capacity(names.len());
+        for _ in 0..samples {
+            point.clear();
+            point.extend(names.iter().map(|_| distribution.sample(&mut rng)));
+            let mut values = [0.0; 2];
+            for (value, (float, compiled)) in values.iter_mut().zip(&sides) {
+                *value = match compiled {
+                    None => *float,
+                    Some(compiled) => {
+                        let mut parser = Parser::new(
+                            compiled,
+                            SweepCalculator {
+                                calculator: self,
+                                names: &names,
+                                values: &point,
+                            },
+                        );
+                        parser
+                            .evaluate_all_tokens()
+                            .and_then(|value| value.ok_or(CalculatorError::NoValueReturnedParsing))
+                            .map_err(|error| CalculatorError::SamplePointError {
+                                assignments: free_variables
+                                    .iter()
+                                    .cloned()
+                                    .zip(point.clone())
+                                    .collect(),
+                                error: Box::new(error),
+                            })?
+                    }
+                };
             }
-            Token::Power => {
-                self.next_token();
-                res = res.powf(self.evaluate_unary()?);
+            let [x, y] = values;
+            let agree = x == y
+                || (x.is_nan() && y.is_nan())
+                || (x - y).abs() <= 1e-12 + 1e-9 * x.abs().max(y.abs());
+            if !agree {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    /// Evaluate a compiled expression with one variable set to a value.
+    ///
+    /// The variable overlays a set variable of the same name, the Calculator is not changed.
+    pub(crate) fn evaluate_compiled_at(
+        &self,
+        compiled: &CompiledExpression,
+        variable: &str,
+        value: f64,
+    ) -> Result<f64, CalculatorError> {
+        let names = [variable];
+        let values = [value];
+        let mut parser = Parser::new(
+            compiled,
+            SweepCalculator {
+                calculator: self,
+                names: &names,
+                values: &values,
+            },
+        );
+        parser
+            .evaluate_all_tokens()?
+            .ok_or(CalculatorError::NoValueReturnedParsing)
+    }
+}
+
+/// Result of evaluating an expression on the Cartesian product of variable values.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SweepResult {
+    /// Names of the swept variables and the values of each variable
+    pub axes: Vec<(String, Vec<f64>)>,
+    /// Values of the expression in row-major order, the last axis changes fastest
+    pub values: Vec<f64>,
+}
+
+impl SweepResult {
+    /// Return the number of values along each axis.
+    pub fn shape(&self) -> Vec<usize> {
+        self.axes.iter().map(|(_, values)| values.len()).collect()
+    }
+}
+
+/// Result of checking an expression with [Calculator::check_str].
+///
+/// Names are listed once, in the order of their first occurrence in the expression.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct CheckReport {
+    /// Variables that are read, including variables read by compound assignments
+    pub variables: Vec<String>,
+    /// Variables that are assigned
+    pub assigned: Vec<String>,
+    /// Called functions with their number of arguments
+    pub functions: Vec<(String, usize)>,
+    /// Non-fatal warnings ordered by position, see [CalculatorWarning]
+    pub warnings: Vec<CalculatorWarning>,
+}
+
+/// Return the number of arguments of a function call or the number of values of a reduction.
+///
+/// # Arguments
+///
+/// * `tokens` - Tokens following the function or reduction token, which includes the opening bracket
+///
+pub(crate) fn number_call_arguments(tokens: &[Token]) -> usize {
+    let mut depth: usize = 0;
+    let mut commas: usize = 0;
+    let mut empty = true;
+    for token in tokens {
+        match token {
+            Token::BracketClose | Token::ListClose if depth == 0 => break,
+            Token::BracketClose | Token::ListClose => depth -= 1,
+            Token::BracketOpen | Token::Function(_) | Token::Reduction(_) | Token::ListOpen => {
+                depth += 1
             }
+            Token::Comma if depth == 0 => commas += 1,
+            Token::EndOfExpression | Token::EndOfString => break,
             _ => (),
         }
-        Ok(res)
+        empty = false;
+    }
+    if empty {
+        0
+    } else {
+        commas + 1
     }
+}
 
-    /// Handle any unary + or - signs.
-    fn evaluate_unary(&mut self) -> Result<f64, CalculatorError> {
-        let mut prefactor: f64 = 1.0;
-        match self.current_token() {
-            Token::Minus => {
-                self.next_token();
-                prefactor = -1.0;
+/// Check that an expression only contains elements that can be parsed.
+///
+/// Rejects unrecognized elements, variable assignments and unbalanced brackets.
+/// Does not evaluate the expression, so unset variables are not reported.
+///
+/// This is the validity check of symbolic expressions used by the whole crate: constructing a
+/// [crate::CalculatorFloat::Str], compiling expressions and [Calculator::validate_expression]
+/// all call it before any further checks.
+///
+/// # Arguments
+///
+/// * `expression` - Expression that is checked
+///
+/// # Returns
+///
+/// * `Ok(())` - The expression is valid
+/// * `Err(CalculatorError::InvalidExpression)` - Byte position of the first invalid element and the reason
+///
+pub fn validate_expression(expression: &str) -> Result<(), CalculatorError> {
+    let invalid = |position: usize, error: CalculatorError| CalculatorError::InvalidExpression {
+        position,
+        error: Box::new(error),
+    };
+    // Positions of the open brackets and whether they are square brackets
+    let mut open_brackets: Vec<(usize, bool)> = Vec::new();
+    for (position, token) in positioned_tokens(expression) {
+        match token {
+            Token::BracketOpen | Token::Function(_) => open_brackets.push((position, false)),
+            Token::Reduction(_) => open_brackets.push((position, true)),
+            Token::BracketClose | Token::ListClose => {
+                let square = token == Token::ListClose;
+                if open_brackets.pop().map(|(_, open_square)| open_square) != Some(square) {
+                    return Err(invalid(
+                        position,
+                        CalculatorError::ParsingError {
+                            msg: "Unexpected closing bracket",
+                            position: None,
+                        },
+                    ));
+                }
             }
-            Token::Plus => {
-                self.next_token();
+            Token::VariableAssign(variable_name) | Token::CompoundAssign(variable_name, _) => {
+                return Err(invalid(
+                    position,
+                    CalculatorError::NotParsableAssign {
+                        variable_name: variable_name.into_owned(),
+                    },
+                ))
+            }
+            Token::Assign => {
+                return Err(invalid(position, CalculatorError::NotParsableSingleAssign))
+            }
+            Token::Unrecognized | Token::ListOpen => {
+                return Err(invalid(position, CalculatorError::NotParsableUnrecognized))
             }
             _ => (),
         }
-        Ok(prefactor * self.evaluate()?)
     }
+    match open_brackets.first() {
+        Some((position, _)) => Err(invalid(
+            *position,
+            CalculatorError::ParsingError {
+                msg: "Expected Braket close",
+                position: None,
+            },
+        )),
+        None => Ok(()),
+    }
+}
 
-    /// Handle numbers, variables, functions and parentheses.
-    fn evaluate(&mut self) -> Result<f64, CalculatorError> {
-        match self.current_token().clone() {
-            Token::BracketOpen => {
-                self.next_token();
-                let res_init = self.evaluate_init()?.ok_or(CalculatorError::ParsingError {
-                    msg: "Unexpected None return",
-                })?;
-                //self.next_token()?;
-                if self.current_token() != &Token::BracketClose {
-                    Err(CalculatorError::ParsingError {
-                        msg: "Expected Braket close",
-                    })
+/// Add the position of the token where parsing failed to a parsing error without position.
+///
+/// A token that can not be parsed at its position is returned as
+/// [CalculatorError::UnexpectedToken] or [CalculatorError::UnrecognizedToken]
+/// with its source text, the end of the expression as
+/// [CalculatorError::UnexpectedEndOfExpressionAt]. Other errors are returned unchanged.
+///
+/// # Arguments
+///
+/// * `error` - Error returned by the parser
+/// * `source` - Parsed expression
+/// * `token` - Token where parsing failed
+/// * `position` - Byte position of the token, replaced by the length of the expression
+///   for the end of the expression
+///
+pub(crate) fn locate_parsing_error(
+    error: CalculatorError,
+    source: &str,
+    token: &Token,
+    position: usize,
+) -> CalculatorError {
+    let position = match token {
+        Token::EndOfString => source.len(),
+        _ => position,
+    };
+    match error {
+        CalculatorError::ParsingError {
+            msg: "Bad_Position",
+            position: None,
+        } => match token {
+            Token::EndOfExpression | Token::EndOfString => {
+                CalculatorError::UnexpectedEndOfExpressionAt { position }
+            }
+            _ => {
+                let remaining = source.get(position..).unwrap_or_default();
+                let text = SpannedTokenIterator::new(remaining)
+                    .next()
+                    .map(|(span, _)| remaining[span].to_string())
+                    .unwrap_or_default();
+                if token == &Token::Unrecognized {
+                    CalculatorError::UnrecognizedToken {
+                        token: text,
+                        position,
+                    }
                 } else {
-                    self.next_token();
-                    Ok(res_init)
+                    CalculatorError::UnexpectedToken {
+                        token: text,
+                        position,
+                    }
                 }
             }
-            Token::Number(vf) => {
-                self.next_token();
-                Ok(vf)
+        },
+        CalculatorError::ParsingError {
+            msg,
+            position: None,
+        } => CalculatorError::ParsingError {
+            msg,
+            position: Some(position),
+        },
+        CalculatorError::UnexpectedEndOfExpression => {
+            CalculatorError::UnexpectedEndOfExpressionAt { position }
+        }
+        error => error,
+    }
+}
+
+/// Lex an expression and return each token with its byte position in the expression.
+pub(crate) fn positioned_tokens(expression: &str) -> Vec<(usize, Token<'_>)> {
+    spanned_tokens(expression)
+        .into_iter()
+        .map(|(span, token)| (span.start, token))
+        .collect()
+}
+
+/// Lex an expression and return each token with its byte range in the expression.
+///
+/// Functions and reductions include their opening bracket and assignments their
+/// assignment operator, as they are lexed as a single token.
+pub(crate) fn spanned_tokens(expression: &str) -> Vec<(Range<usize>, Token<'_>)> {
+    SpannedTokenIterator::new(expression).collect()
+}
+
+/// Return the number of single character insertions, deletions or substitutions
+/// needed to change one string into the other.
+fn edit_distance(first: &str, second: &str) -> usize {
+    let second: Vec<char> = second.chars().collect();
+    let mut previous: Vec<usize> = (0..=second.len()).collect();
+    for (i, first_char) in first.chars().enumerate() {
+        let mut current: Vec<usize> = vec![i + 1];
+        for (j, second_char) in second.iter().enumerate() {
+            let substitution = previous[j] + usize::from(first_char != *second_char);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[second.len()]
+}
+
+/// Split a decimal number into its significant digits and the exponent of the last digit.
+///
+/// Leading and trailing zeros are removed, so equal decimal values give equal results,
+/// e.g. `0.50` and `5e-1` both give `("5", -1)`. Zero gives `("", 0)`.
+fn normalized_decimal(number: &str) -> Option<(String, i64)> {
+    let (mantissa, exponent) = match number.find(['e', 'E']) {
+        Some(split) => (&number[..split], number[split + 1..].parse::<i64>().ok()?),
+        None => (number, 0),
+    };
+    let (integer, fraction) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+    let digits = format!("{integer}{fraction}");
+    let digits = digits.trim_start_matches('0');
+    let significant = digits.trim_end_matches('0');
+    if significant.is_empty() {
+        return Some((String::new(), 0));
+    }
+    let exponent = exponent
+        .checked_sub(fraction.len() as i64)?
+        .checked_add((digits.len() - significant.len()) as i64)?;
+    Some((significant.to_string(), exponent))
+}
+
+/// Check that a number literal is parsed to a float without changing its value.
+///
+/// A literal round-trips when it is the shortest representation of the parsed float,
+/// which is the formatting used for floats in symbolic expressions,
+/// or the exact decimal value of the parsed float, up to leading and trailing zeros.
+fn literal_round_trips(literal: &str, parsed: f64) -> bool {
+    if !parsed.is_finite() {
+        return false;
+    }
+    let literal = normalized_decimal(literal);
+    literal.is_some()
+        && (literal == normalized_decimal(&format!("{parsed:e}"))
+            // 1100 digits hold the exact decimal value of every float
+            || literal == normalized_decimal(&format!("{parsed:.1100e}")))
+}
+
+/// Find suspicious but valid patterns in an expression.
+///
+/// # Arguments
+///
+/// * `expression` - Expression that is checked
+/// * `compiled` - Compiled expression
+/// * `set_variables` - Names of the variables set before the expression is evaluated
+/// * `precision_loss` - Whether number literals that do not round-trip through f64 are reported
+///
+/// # Returns
+///
+/// `Vec<CalculatorWarning>` - Warnings ordered by their position in the expression
+///
+fn expression_warnings<'b>(
+    expression: &str,
+    compiled: &CompiledExpression,
+    set_variables: impl Iterator<Item = &'b String>,
+    precision_loss: bool,
+) -> Vec<CalculatorWarning> {
+    let tokens: Vec<(usize, &Token)> = compiled
+        .positions()
+        .iter()
+        .copied()
+        .zip(compiled.tokens())
+        .collect();
+    let mut warnings: Vec<CalculatorWarning> = Vec::new();
+    let read = |name: &str| {
+        tokens
+            .iter()
+            .filter(|(_, token)| matches!(token, Token::Variable(read) if read == name))
+            .count()
+    };
+    let mut candidates: Vec<&str> = set_variables.map(|name| name.as_str()).collect();
+    candidates.extend(tokens.iter().filter_map(|(_, token)| match token {
+        Token::VariableAssign(name) => Some(&**name),
+        _ => None,
+    }));
+    // The value of the last statement is returned, so its assignments are not unused
+    let last_statement = tokens
+        .iter()
+        .rposition(|(_, token)| !matches!(token, Token::EndOfExpression | Token::EndOfString))
+        .and_then(|last| {
+            tokens[..last]
+                .iter()
+                .rposition(|(_, token)| *token == &Token::EndOfExpression)
+        })
+        .map_or(0, |end| end + 1);
+    for (index, (position, token)) in tokens.iter().enumerate() {
+        match token {
+            Token::Comma => {
+                let digit_before = expression[..*position].ends_with(|c: char| c.is_ascii_digit());
+                let digit_after =
+                    expression[position + 1..].starts_with(|c: char| c.is_ascii_digit());
+                if digit_before && digit_after {
+                    warnings.push(CalculatorWarning::DecimalComma {
+                        position: *position,
+                    });
+                }
             }
-            Token::Variable(ref vs) => {
-                let vsnew = vs.to_owned();
-                self.next_token();
-                self.get_variable(&vsnew)
+            // Short names like x and y differ by one character without being typos
+            Token::Variable(name) if name.chars().count() >= 3 && read(name) == 1 => {
+                if let Some(similar) = candidates
+                    .iter()
+                    .find(|candidate| edit_distance(name, candidate) == 1 && read(candidate) == 0)
+                {
+                    warnings.push(CalculatorWarning::SimilarVariable {
+                        position: *position,
+                        name: name.to_string(),
+                        similar: similar.to_string(),
+                    });
+                }
             }
-            Token::Function(ref vs) => {
-                let vsnew = vs.to_owned();
-                self.next_token();
-                let mut heap = Vec::new();
-                let number_arguments = function_argument_numbers(&vsnew)?;
-                for argument_number in 0..number_arguments {
-                    heap.push(
-                        self.evaluate_init()?
-                            .ok_or(CalculatorError::NoValueReturnedParsing)?,
-                    );
-                    // Swallow commas in function arguments
-                    if argument_number < number_arguments - 1 {
-                        if self.current_token() != &Token::Comma {
-                            return Err(CalculatorError::ParsingError {
-                                msg: "expected comma in function arguments",
-                            });
-                        } else {
-                            self.next_token();
+            Token::Number(parsed) if precision_loss => {
+                // Lexing the number again gives the source text of the literal
+                let mut lexer = TokenIterator {
+                    current_expression: &expression[*position..],
+                };
+                lexer.next();
+                let literal =
+                    &expression[*position..expression.len() - lexer.current_expression.len()];
+                if !literal_round_trips(literal, *parsed) {
+                    warnings.push(CalculatorWarning::PrecisionLoss {
+                        position: *position,
+                        literal: literal.to_string(),
+                        parsed: *parsed,
+                    });
+                }
+            }
+            Token::VariableAssign(name) if index < last_statement => {
+                // Reads are searched up to the end of the statement reassigning the variable
+                let mut reassigned = false;
+                let mut is_read = false;
+                for (_, later) in tokens[index + 1..].iter() {
+                    match later {
+                        Token::Variable(later_name) | Token::CompoundAssign(later_name, _)
+                            if later_name == name =>
+                        {
+                            is_read = true;
+                            break;
                         }
+                        Token::VariableAssign(later_name) if later_name == name => {
+                            reassigned = true
+                        }
+                        Token::EndOfExpression if reassigned => break,
+                        _ => (),
                     }
-                    //self.next_token()?;
                 }
-                if self.current_token() != &Token::BracketClose {
-                    return Err(CalculatorError::ParsingError {
-                        msg: "Expected braket close.",
+                if !is_read {
+                    warnings.push(CalculatorWarning::UnusedAssignment {
+                        position: *position,
+                        name: name.to_string(),
                     });
                 }
-                self.next_token();
-                match number_arguments {
-                    1 => function_1_argument(
-                        &vsnew,
-                        *(heap
-                            .first()
-                            .ok_or(CalculatorError::NotEnoughFunctionArguments)?),
-                    ),
-                    2 => function_2_arguments(
-                        &vsnew,
-                        *(heap
-                            .first()
-                            .ok_or(CalculatorError::NotEnoughFunctionArguments)?),
-                        *(heap
-                            .get(1)
-                            .ok_or(CalculatorError::NotEnoughFunctionArguments)?),
-                    ),
-                    _ => Err(CalculatorError::ParsingError {
-                        msg: "Unsupported number of arguments.",
-                    }),
-                }
             }
-            _ => Err(CalculatorError::ParsingError {
-                msg: "Bad_Position",
-            }),
+            _ => (),
         }
     }
+    warnings
 }
 
-#[cfg(test)]
-mod tests {
-    use super::function_1_argument;
-    use super::function_2_arguments;
-    use super::function_argument_numbers;
-    use super::Calculator;
-    use super::CalculatorFloat;
-    use super::Token;
-    use super::TokenIterator;
+/// Return the names of all variables referenced in an expression.
+///
+/// Names are returned once, in the order of their first occurrence.
+///
+/// # Arguments
+///
+/// * `expression` - Expression that is searched for variables
+///
+/// # Returns
+///
+/// * `Ok(Vec<String>)` - Names of the referenced variables
+/// * `Err(CalculatorError::InvalidExpression)` - The expression is not valid, see [validate_expression]
+///
+pub fn variables_in_expression(expression: &str) -> Result<Vec<String>, CalculatorError> {
+    validate_expression(expression)?;
+    let token_iterator = TokenIterator {
+        current_expression: expression,
+    };
+    let mut variables: Vec<String> = Vec::new();
+    for token in token_iterator {
+        if let Token::Variable(name) = token {
+            if !variables.iter().any(|known| known == &name) {
+                variables.push(name.into_owned());
+            }
+        }
+    }
+    Ok(variables)
+}
 
-    // Test the next function of the TokenIterator for an end of string Token
-    #[test]
-    fn test_end_of_string() {
-        let mut t_iterator = TokenIterator {
-            current_expression: " ",
-        };
-        assert_eq!(t_iterator.next().unwrap(), Token::EndOfString);
-        let mut t_iterator2 = TokenIterator {
-            current_expression: "#aaabbb+.3+.4e-10*5-1; ",
+/// Evaluate an expression without variables in a temporary Calculator.
+///
+/// Shorthand for [Calculator::parse_str] of a new Calculator, assignments are not allowed.
+///
+/// # Arguments
+///
+/// * `expression` - Expression that is parsed
+///
+/// # Returns
+///
+/// * `Ok(f64)` - Value of the expression
+/// * `Err(CalculatorError::ForbiddenAssign)` - The expression assigns a variable
+/// * `Err(CalculatorError)` - The expression can not be parsed
+///
+pub fn parse_str(expression: &str) -> Result<f64, CalculatorError> {
+    Calculator::new().parse_str(expression)
+}
+
+/// Evaluate an expression with imaginary numbers without variables in a temporary Calculator.
+///
+/// Shorthand for [Calculator::parse_str_complex] of a new Calculator, e.g. `(1 + 2i) * 3j`.
+///
+/// # Arguments
+///
+/// * `expression` - Expression that is parsed
+///
+/// # Returns
+///
+/// * `Ok(Complex<f64>)` - Value of the expression
+/// * `Err(CalculatorError)` - The expression can not be parsed
+///
+pub fn parse_str_complex(expression: &str) -> Result<Complex<f64>, CalculatorError> {
+    Calculator::new().parse_str_complex(expression)
+}
+
+/// Evaluate an expression allowing variable assignments in a temporary Calculator.
+///
+/// Shorthand for [Calculator::parse_str_assign] of a new Calculator, e.g. `a = 3; a * 2`.
+/// The assigned variables are dropped with the Calculator.
+///
+/// # Arguments
+///
+/// * `expression` - Expression that is parsed
+///
+/// # Returns
+///
+/// * `Ok(f64)` - Value of the last statement of the expression
+/// * `Err(CalculatorError)` - The expression can not be parsed
+///
+pub fn parse_str_assign(expression: &str) -> Result<f64, CalculatorError> {
+    Calculator::new().parse_str_assign(expression)
+}
+
+/// Rename the variables of an expression or script to `v0`, `v1`, ...
+///
+/// Read variables and assignment targets are renamed in the order of their first occurrence,
+/// names that are already in the mapping keep their replacement and new names continue
+/// the numbering. Numbers, functions, placeholders, string literals and comments are not changed.
+///
+/// # Arguments
+///
+/// * `expression` - Expression or script with the original names
+/// * `mapping` - Original names and their replacements, extended by the new names
+///
+pub(crate) fn anonymize_variables(
+    expression: &str,
+    mapping: &mut HashMap<String, String>,
+) -> String {
+    let mut output = String::with_capacity(expression.len());
+    let mut copied = 0;
+    for (span, token) in spanned_tokens(expression) {
+        let name = match token {
+            Token::Variable(name)
+            | Token::VariableAssign(name)
+            | Token::CompoundAssign(name, _) => name,
+            _ => continue,
         };
-        assert_eq!(t_iterator2.next().unwrap(), Token::EndOfString);
+        let number_names = mapping.len();
+        let replacement = mapping
+            .entry(name.to_string())
+            .or_insert_with(|| format!("v{number_names}"));
+        // The names of assignments are followed by their operator in the same token
+        output.push_str(&expression[copied..span.start]);
+        output.push_str(replacement);
+        copied = span.start + name.len();
     }
+    output.push_str(&expression[copied..]);
+    output
+}
 
-    // Test the next function of the TokenIterator for a plus/minus Token
-    #[test]
-    fn test_plus_minus() {
-        let mut t_iterator = TokenIterator {
-            current_expression: " +",
-        };
-        assert_eq!(t_iterator.next().unwrap(), Token::Plus);
-        let mut t_iterator2 = TokenIterator {
-            current_expression: "#a \n +",
-        };
-        assert_eq!(t_iterator2.next().unwrap(), Token::Plus);
-        let mut t_iterator3 = TokenIterator {
-            current_expression: "-",
-        };
-        assert_eq!(t_iterator3.next().unwrap(), Token::Minus);
+/// Check that a placeholder name is a valid variable name.
+fn is_placeholder_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars.next().is_some_and(|first| first.is_alphabetic())
+        && chars.all(|c| c.is_alphanumeric() || c == '_')
+}
+
+/// Return the names of the placeholders in tokens once, in the order of their first occurrence.
+pub(crate) fn placeholders_in<'t>(tokens: impl Iterator<Item = &'t Token<'t>>) -> Vec<String> {
+    let mut names: Vec<String> = Vec::new();
+    for token in tokens {
+        if let Token::Placeholder(name) = token {
+            if !names.iter().any(|known| known == name) {
+                names.push(name.to_string());
+            }
+        }
     }
+    names
+}
 
-    // Test the next function of the TokenIterator for a number Token
-    #[test]
-    fn test_number() {
-        let mut t_iterator = TokenIterator {
-            current_expression: "1e0",
-        };
-        assert_eq!(t_iterator.next().unwrap(), Token::Number(1.0));
-        let mut t_iterator2 = TokenIterator {
-            current_expression: "(1+2e0)",
-        };
-        assert_eq!(t_iterator2.nth(3).unwrap(), Token::Number(2.0));
-        let mut t_iterator3 = TokenIterator {
-            current_expression: "2e+10",
-        };
-        assert_eq!(t_iterator3.next().unwrap(), Token::Number(2.0e+10));
-        let mut t_iterator4 = TokenIterator {
-            current_expression: "1.74E-10",
-        };
-        assert_eq!(t_iterator4.next().unwrap(), Token::Number(1.74E-10));
+/// Enum combining different types of Tokens in an Expression.
+///
+/// Names of variables and functions borrow from the lexed expression where possible,
+/// so lexing and evaluating an expression does not need to allocate them.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Token<'a> {
+    /// A float or integer
+    Number(f64),
+    /// An imaginary number, a float or integer directly followed by `i` or `j`, e.g. `2i`.
+    /// Only expressions evaluated with [Calculator::parse_str_complex] can contain it
+    ImaginaryNumber(f64),
+    /// A variable
+    Variable(Cow<'a, str>),
+    /// A  known function
+    Function(Cow<'a, str>),
+    /// A reduction over a list of values in square brackets, e.g. `max[a, b, c]`
+    Reduction(Cow<'a, str>),
+    /// Plus
+    Plus,
+    /// Minus
+    Minus,
+    /// Multiply
+    Multiply,
+    /// Divice
+    Divide,
+    /// Remainder of a division `%`, with the sign of the dividend like `%` for f64
+    Modulo,
+    /// Poser
+    Power,
+    /// Factorial
+    Factorial,
+    /// DoubleFactorial
+    DoubleFactorial,
+    /// A bracket opening
+    BracketOpen,
+    /// A bracket closing
+    BracketClose,
+    /// A square bracket opening without reduction name
+    ListOpen,
+    /// A square bracket closing a reduction
+    ListClose,
+    /// Assign operator
+    Assign,
+    /// Comparison operator, e.g. `<=` or `!=`
+    Comparison(ComparisonOperator),
+    /// Assignment of a variable
+    VariableAssign(Cow<'a, str>),
+    /// Compound assignment of a variable, e.g. `a += 1`
+    CompoundAssign(Cow<'a, str>, CompoundOperator),
+    /// Comma
+    Comma,
+    /// Single-quoted string literal without escapes, e.g. the prefix in `sum_vars('a_')`
+    StringLiteral(Cow<'a, str>),
+    /// Placeholder in braces that is replaced before evaluation, e.g. `{qubit}`,
+    /// see [crate::CalculatorFloat::bind_placeholders]
+    Placeholder(Cow<'a, str>),
+    /// End of Expression
+    EndOfExpression,
+    /// End of parsed string
+    EndOfString,
+    /// No Token has been recognized in string
+    Unrecognized,
+}
+
+/// Operator of a compound assignment.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompoundOperator {
+    /// Add assign `+=`
+    Plus,
+    /// Subtract assign `-=`
+    Minus,
+    /// Multiply assign `*=`
+    Multiply,
+    /// Divide assign `/=`
+    Divide,
+}
+
+/// Operator of a comparison, a comparison evaluates to 1.0 when it holds and to 0.0 otherwise.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ComparisonOperator {
+    /// Less than `<`
+    Less,
+    /// Greater than `>`
+    Greater,
+    /// Less than or equal `<=`
+    LessEqual,
+    /// Greater than or equal `>=`
+    GreaterEqual,
+    /// Equal `==`
+    Equal,
+    /// Not equal `!=`
+    NotEqual,
+}
+
+impl ComparisonOperator {
+    /// Return the symbol of the operator in expressions.
+    pub fn symbol(self) -> &'static str {
+        match self {
+            ComparisonOperator::Less => "<",
+            ComparisonOperator::Greater => ">",
+            ComparisonOperator::LessEqual => "<=",
+            ComparisonOperator::GreaterEqual => ">=",
+            ComparisonOperator::Equal => "==",
+            ComparisonOperator::NotEqual => "!=",
+        }
     }
 
-    // Test the next function of the TokenIterator for a multiply Token
-    #[test]
-    fn test_multiply() {
-        let mut t_iterator = TokenIterator {
-            current_expression: " *",
+    /// Compare two floats, 1.0 when the comparison holds and 0.0 otherwise.
+    ///
+    /// Follows the comparisons of f64, all comparisons with NaN except `!=` give 0.0.
+    pub fn compare(self, left: f64, right: f64) -> f64 {
+        let holds = match self {
+            ComparisonOperator::Less => left < right,
+            ComparisonOperator::Greater => left > right,
+            ComparisonOperator::LessEqual => left <= right,
+            ComparisonOperator::GreaterEqual => left >= right,
+            ComparisonOperator::Equal => left == right,
+            ComparisonOperator::NotEqual => left != right,
         };
-        assert_eq!(t_iterator.next().unwrap(), Token::Multiply);
+        if holds {
+            1.0
+        } else {
+            0.0
+        }
     }
+}
 
-    // Test the next function of the TokenIterator for a divide Token
-    #[test]
-    fn test_divide() {
-        let mut t_iterator = TokenIterator {
-            current_expression: " /",
-        };
-        assert_eq!(t_iterator.next().unwrap(), Token::Divide);
+impl Token<'_> {
+    /// Return a Token that does not borrow from the lexed expression.
+    pub fn into_owned(self) -> Token<'static> {
+        match self {
+            Token::Number(x) => Token::Number(x),
+            Token::ImaginaryNumber(x) => Token::ImaginaryNumber(x),
+            Token::Variable(name) => Token::Variable(Cow::Owned(name.into_owned())),
+            Token::Function(name) => Token::Function(Cow::Owned(name.into_owned())),
+            Token::Reduction(name) => Token::Reduction(Cow::Owned(name.into_owned())),
+            Token::Plus => Token::Plus,
+            Token::Minus => Token::Minus,
+            Token::Multiply => Token::Multiply,
+            Token::Divide => Token::Divide,
+            Token::Modulo => Token::Modulo,
+            Token::Power => Token::Power,
+            Token::Factorial => Token::Factorial,
+            Token::DoubleFactorial => Token::DoubleFactorial,
+            Token::BracketOpen => Token::BracketOpen,
+            Token::BracketClose => Token::BracketClose,
+            Token::ListOpen => Token::ListOpen,
+            Token::ListClose => Token::ListClose,
+            Token::Assign => Token::Assign,
+            Token::Comparison(operator) => Token::Comparison(operator),
+            Token::VariableAssign(name) => Token::VariableAssign(Cow::Owned(name.into_owned())),
+            Token::CompoundAssign(name, operator) => {
+                Token::CompoundAssign(Cow::Owned(name.into_owned()), operator)
+            }
+            Token::Comma => Token::Comma,
+            Token::StringLiteral(literal) => Token::StringLiteral(Cow::Owned(literal.into_owned())),
+            Token::Placeholder(name) => Token::Placeholder(Cow::Owned(name.into_owned())),
+            Token::EndOfExpression => Token::EndOfExpression,
+            Token::EndOfString => Token::EndOfString,
+            Token::Unrecognized => Token::Unrecognized,
+        }
     }
+}
 
-    // Test the next function of the TokenIterator for a power (^ and **) Token
-    #[test]
-    fn test_power() {
-        let mut t_iterator = TokenIterator {
-            current_expression: " **",
-        };
-        assert_eq!(t_iterator.next().unwrap(), Token::Power);
-        let mut t_iterator2 = TokenIterator {
-            current_expression: " ^",
-        };
-        assert_eq!(t_iterator2.next().unwrap(), Token::Power);
+/// Standard print implementation for Rust.
+impl fmt::Display for Token<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::Number(x) => write!(f, "Token::Number({})", format_float(*x)),
+            Token::ImaginaryNumber(x) => write!(f, "Token::ImaginaryNumber({})", format_float(*x)),
+            Token::VariableAssign(y) => write!(f, "Token::VariableAssign({y})"),
+            Token::CompoundAssign(y, op) => write!(f, "Token::CompoundAssign({y}, {op:?})"),
+            Token::Variable(y) => write!(f, "Token::Variable({y})"),
+            Token::Function(y) => write!(f, "Token::Function({y})"),
+            Token::Reduction(y) => write!(f, "Token::Reduction({y})"),
+            Token::Plus => write!(f, "Token::Plus"),
+            Token::Minus => write!(f, "Token::Minus"),
+            Token::Multiply => write!(f, "Token::Multiply"),
+            Token::Divide => write!(f, "Token::Divide"),
+            Token::Modulo => write!(f, "Token::Modulo"),
+            Token::Power => write!(f, "Token::Power"),
+            Token::Factorial => write!(f, "Token::Factorial"),
+            Token::DoubleFactorial => write!(f, "Token::DoubleFactorial"),
+            Token::BracketOpen => write!(f, "Token::BracketOpen"),
+            Token::BracketClose => write!(f, "Token::BracketClose"),
+            Token::ListOpen => write!(f, "Token::ListOpen"),
+            Token::ListClose => write!(f, "Token::ListClose"),
+            Token::Assign => write!(f, "Token::Assign"),
+            Token::Comparison(op) => write!(f, "Token::Comparison({op:?})"),
+            Token::Comma => write!(f, "Token::Comma"),
+            Token::StringLiteral(y) => write!(f, "Token::StringLiteral({y})"),
+            Token::Placeholder(y) => write!(f, "Token::Placeholder({y})"),
+            Token::EndOfExpression => write!(f, "Token::EndOfExpression"),
+            Token::EndOfString => write!(f, "Token::EndOfString"),
+            Token::Unrecognized => write!(f, "Token::Unrecognized"),
+        }
+    }
+}
+
+/// Struct implementing Iterator trait to lex string
+/// to computational Tokens.
+///
+/// See [SpannedTokenIterator] for the byte ranges of the Tokens in the string.
+#[derive(Debug, Clone)]
+pub struct TokenIterator<'a> {
+    // Save current expression as a slice of a string so we do not
+    // need to copy but only modify (shorten) the slice.
+    //
+    /// Current str expression being lexed
+    pub current_expression: &'a str,
+}
+
+// Implement the Iterator Trait for TokenIterator so it can be used as standard rust iterator.
+impl<'a> Iterator for TokenIterator<'a> {
+    type Item = Token<'a>;
+
+    // Define next method for Token iterator
+    fn next(&mut self) -> Option<Token<'a>> {
+        if self.current_expression.is_empty() {
+            None
+        } else {
+            // Loop to remove whitespace and comments
+            loop {
+                if self.current_expression.starts_with(' ') {
+                    let end = self
+                        .current_expression
+                        .char_indices()
+                        .find_map(|(ind, c)| if c.is_whitespace() { None } else { Some(ind) })
+                        .unwrap_or(self.current_expression.len());
+                    self.cut_current_expression(end);
+                    if self.current_expression.is_empty() {
+                        return Some(Token::EndOfString);
+                    }
+                    continue;
+                } else if self.current_expression.starts_with('#') {
+                    let end = self
+                        .current_expression
+                        .char_indices()
+                        .find_map(|(ind, c)| if c != '\u{000A}' { None } else { Some(ind + 1) })
+                        .unwrap_or(self.current_expression.len());
+                    self.cut_current_expression(end);
+                    if self.current_expression.is_empty() {
+                        return Some(Token::EndOfString);
+                    }
+                    continue;
+                }
+                break;
+            }
+            // Test if head of current_expression is a letter char
+            if self
+                .current_expression
+                .chars()
+                .next()
+                .unwrap()
+                .is_alphabetic()
+            {
+                // Copy the slice so the names of tokens borrow from the expression and not from self
+                let expression: &'a str = self.current_expression;
+                // Find end of symbolic expression (not alphanumeric or '_')
+                let end = self
+                    .current_expression
+                    .char_indices()
+                    .find_map(|(ind, c)| {
+                        if c.is_alphanumeric() || c == '_' {
+                            None
+                        } else {
+                            Some(ind)
+                        }
+                    })
+                    .unwrap_or(self.current_expression.len());
+                // Compound assignment operators directly follow the name, separated by at most spaces
+                let rest = self.current_expression[end..].trim_start_matches(' ');
+                let compound_operator = match rest.get(..2) {
+                    Some("+=") => Some(CompoundOperator::Plus),
+                    Some("-=") => Some(CompoundOperator::Minus),
+                    Some("*=") => Some(CompoundOperator::Multiply),
+                    Some("/=") => Some(CompoundOperator::Divide),
+                    _ => None,
+                };
+                if let Some(operator) = compound_operator {
+                    let vs = Cow::Borrowed(&expression[..end]);
+                    let operator_end = self.current_expression.len() - rest.len() + 2;
+                    self.cut_current_expression(operator_end);
+                    return Some(Token::CompoundAssign(vs, operator));
+                }
+                // Get next token from TokenIterator with shortened expression
+                let next_token = if end >= self.current_expression.len() {
+                    TokenIterator {
+                        current_expression: "",
+                    }
+                    .next()
+                } else {
+                    TokenIterator {
+                        current_expression: &self.current_expression[end..],
+                    }
+                    .next()
+                };
+                // Depending on next token currently lexed string current_expression[..end] creates different tokens
+                // Token contains current_expression[..end] for later processing
+                return Some(match next_token {
+                    Some(Token::Assign) => {
+                        let vs = Cow::Borrowed(&expression[..end]);
+                        self.cut_current_expression(end + 1);
+                        Token::VariableAssign(vs)
+                    }
+                    Some(Token::BracketOpen) => {
+                        let vs = Cow::Borrowed(&expression[..end]);
+                        self.cut_current_expression(end + 1);
+                        Token::Function(vs)
+                    }
+                    Some(Token::ListOpen) => {
+                        let vs = Cow::Borrowed(&expression[..end]);
+                        self.cut_current_expression(end + 1);
+                        Token::Reduction(vs)
+                    }
+                    _ => {
+                        let vs = Cow::Borrowed(&expression[..end]);
+                        self.cut_current_expression(end);
+                        Token::Variable(vs)
+                    }
+                });
+            }
+            // Lex string that contains a number.
+            // The number is isolated on bytes, all bytes of a number are ascii
+            let bytes = self.current_expression.as_bytes();
+            if bytes[0].is_ascii_digit() || bytes[0] == b'.' {
+                // find end of number expression
+                let mut end = bytes
+                    .iter()
+                    .position(|byte| !byte.is_ascii_digit() && byte != &b'.')
+                    .unwrap_or(bytes.len());
+                // Handle scientific notation.
+                // Starts with e or E for scientific notation, optionally followed by a sign
+                if let Some(b'e' | b'E') = bytes.get(end) {
+                    let sign = usize::from(matches!(bytes.get(end + 1), Some(b'+' | b'-')));
+                    let exponent_start = end + 1 + sign;
+                    // Find end of exponent
+                    end = bytes[exponent_start..]
+                        .iter()
+                        .position(|byte| !byte.is_ascii_digit())
+                        .map_or(bytes.len(), |offset| exponent_start + offset);
+                }
+                let number_expression = &self.current_expression[..end];
+                // A directly following `i` or `j` that does not start a name marks an imaginary number
+                let mut suffix = self.current_expression[end..].chars();
+                let imaginary = matches!(suffix.next(), Some('i' | 'j'))
+                    && !suffix
+                        .next()
+                        .is_some_and(|c| c.is_alphanumeric() || c == '_');
+                self.cut_current_expression(end + usize::from(imaginary));
+                // f64::from_str is locale independent and does not allocate, it converts most
+                // literals with the exact fast path of Clinger and the others with Eisel-Lemire
+                return Some(match f64::from_str(number_expression) {
+                    Err(_) => Token::Unrecognized,
+                    Ok(f) if imaginary => Token::ImaginaryNumber(f),
+                    Ok(f) => Token::Number(f),
+                });
+            };
+            // Lex single-quoted string literal, an unterminated literal consumes the rest of the expression
+            if let Some(rest) = self.current_expression.strip_prefix('\'') {
+                let expression: &'a str = self.current_expression;
+                return Some(match rest.find('\'') {
+                    Some(end) => {
+                        self.cut_current_expression(end + 2);
+                        Token::StringLiteral(Cow::Borrowed(&expression[1..end + 1]))
+                    }
+                    None => {
+                        self.current_expression = "";
+                        Token::Unrecognized
+                    }
+                });
+            }
+            // Lex placeholder, the name follows the rules of variable names without spaces in the braces
+            if let Some(rest) = self.current_expression.strip_prefix('{') {
+                let expression: &'a str = self.current_expression;
+                return Some(match rest.find('}') {
+                    Some(end) => {
+                        self.cut_current_expression(end + 2);
+                        let name = &expression[1..end + 1];
+                        if is_placeholder_name(name) {
+                            Token::Placeholder(Cow::Borrowed(name))
+                        } else {
+                            Token::Unrecognized
+                        }
+                    }
+                    None => {
+                        self.current_expression = "";
+                        Token::Unrecognized
+                    }
+                });
+            }
+            // Create symbol tokens
+            let symbol = self.current_expression.chars().next().unwrap();
+            self.current_expression = &self.current_expression[1..];
+            Some(match symbol {
+                '+' => Token::Plus,
+                '-' => Token::Minus,
+                '*' => match self.current_expression.chars().next().unwrap_or(' ') {
+                    '*' => {
+                        self.current_expression = &self.current_expression[1..];
+                        Token::Power
+                    }
+                    _ => Token::Multiply,
+                },
+                '/' => Token::Divide,
+                '%' => Token::Modulo,
+                '^' => Token::Power,
+                '(' => Token::BracketOpen,
+                ')' => Token::BracketClose,
+                '[' => Token::ListOpen,
+                ']' => Token::ListClose,
+                // A single `=` is an assignment, `==` the comparison
+                '=' => match self.current_expression.chars().next().unwrap_or(' ') {
+                    '=' => {
+                        self.current_expression = &self.current_expression[1..];
+                        Token::Comparison(ComparisonOperator::Equal)
+                    }
+                    _ => Token::Assign,
+                },
+                '<' => match self.current_expression.chars().next().unwrap_or(' ') {
+                    '=' => {
+                        self.current_expression = &self.current_expression[1..];
+                        Token::Comparison(ComparisonOperator::LessEqual)
+                    }
+                    _ => Token::Comparison(ComparisonOperator::Less),
+                },
+                '>' => match self.current_expression.chars().next().unwrap_or(' ') {
+                    '=' => {
+                        self.current_expression = &self.current_expression[1..];
+                        Token::Comparison(ComparisonOperator::GreaterEqual)
+                    }
+                    _ => Token::Comparison(ComparisonOperator::Greater),
+                },
+                ',' => Token::Comma,
+                ';' => Token::EndOfExpression,
+                '!' => match self.current_expression.chars().next().unwrap_or(' ') {
+                    '!' => {
+                        self.current_expression = &self.current_expression[1..];
+                        Token::DoubleFactorial
+                    }
+                    // `!=` is the comparison and not a factorial followed by an assignment
+                    '=' => {
+                        self.current_expression = &self.current_expression[1..];
+                        Token::Comparison(ComparisonOperator::NotEqual)
+                    }
+                    _ => Token::Factorial,
+                },
+                _ => Token::Unrecognized,
+            })
+        }
+    }
+}
+
+// Helper methods not in standard iterator trait.
+impl<'a> TokenIterator<'a> {
+    // Modify the current expression to be a slice of the current expression.
+    fn cut_current_expression(&mut self, end: usize) {
+        if end == self.current_expression.len() {
+            self.current_expression = "";
+        } else {
+            self.current_expression = &self.current_expression[end..];
+        }
+    }
+}
+
+/// Struct implementing Iterator trait to lex a string to Tokens together with their span.
+///
+/// The span is the byte range of the token in the lexed string, whitespace and comments
+/// before the token are not included. Functions and reductions include their opening bracket
+/// and assignments their assignment operator, as they are lexed as a single token.
+///
+/// # Example
+///
+/// ```
+/// use qoqo_calculator::{SpannedTokenIterator, Token};
+///
+/// let tokens: Vec<_> = SpannedTokenIterator::new("a # comment\n + sin(b)").collect();
+/// assert_eq!(tokens[0], (0..1, Token::Variable("a".into())));
+/// assert_eq!(tokens[1], (13..14, Token::Plus));
+/// assert_eq!(tokens[2], (15..19, Token::Function("sin".into())));
+/// ```
+#[derive(Debug, Clone)]
+pub struct SpannedTokenIterator<'a> {
+    /// Complete str expression being lexed
+    expression: &'a str,
+    /// Lexer of the remaining expression
+    tokens: TokenIterator<'a>,
+}
+
+impl<'a> SpannedTokenIterator<'a> {
+    /// Create a new SpannedTokenIterator lexing an expression.
+    ///
+    /// # Arguments
+    ///
+    /// * `expression` - Expression that is lexed
+    ///
+    pub fn new(expression: &'a str) -> Self {
+        SpannedTokenIterator {
+            expression,
+            tokens: TokenIterator {
+                current_expression: expression,
+            },
+        }
+    }
+
+    /// Return the byte position of the remaining expression in the lexed expression.
+    pub fn offset(&self) -> usize {
+        self.expression.len() - self.tokens.current_expression.len()
+    }
+}
+
+impl<'a> Iterator for SpannedTokenIterator<'a> {
+    type Item = (Range<usize>, Token<'a>);
+
+    fn next(&mut self) -> Option<(Range<usize>, Token<'a>)> {
+        // Skip whitespace and comments the same way the lexer does
+        let mut remaining = self.tokens.current_expression;
+        loop {
+            if remaining.starts_with(' ') {
+                remaining = remaining.trim_start();
+            } else if remaining.starts_with('#') {
+                remaining = remaining
+                    .find('\u{000A}')
+                    .map_or("", |end| &remaining[end + 1..]);
+            } else {
+                break;
+            }
+        }
+        let position = self.expression.len() - remaining.len();
+        let token = self.tokens.next()?;
+        Some((position..self.offset(), token))
+    }
+}
+
+mod private {
+    /// Prevents implementing CalculatorAccess outside of qoqo_calculator.
+    pub trait Sealed {}
+}
+
+/// Access to the variables of a Calculator while an expression is parsed.
+///
+/// Implemented for `&Calculator`, which only allows reading variables,
+/// and for the staged assignments used by [Calculator::parse_str_assign].
+/// The trait is sealed and can not be implemented outside of qoqo_calculator.
+///
+/// ```compile_fail
+/// use qoqo_calculator::{CalculatorAccess, CalculatorError};
+///
+/// struct External;
+///
+/// impl CalculatorAccess for External {
+///     fn get_variable(&self, name: &str) -> Result<f64, CalculatorError> {
+///         Ok(0.0)
+///     }
+///     fn set_variable(&mut self, name: &str, value: f64) -> Result<(), CalculatorError> {
+///         Ok(())
+///     }
+/// }
+/// ```
+pub trait CalculatorAccess: private::Sealed {
+    /// Get variable for Calculator.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - Name of the variable
+    ///
+    /// # Returns
+    ///
+    /// `value` - Result
+    ///
+    fn get_variable(&self, name: &str) -> Result<f64, CalculatorError>;
+
+    /// Set variable for Calculator.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - Name of the variable
+    /// * `value` - Float value of the variable
+    ///
+    fn set_variable(&mut self, name: &str, value: f64) -> Result<(), CalculatorError>;
+
+    /// Check that a variable can be assigned before its value is evaluated.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - Name of the variable
+    ///
+    fn check_assign(&self, _name: &str) -> Result<(), CalculatorError> {
+        Ok(())
+    }
+
+    /// Get the compiled expression defining a variable.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - Name of the variable
+    ///
+    /// # Returns
+    ///
+    /// `Option<Arc<CompiledExpression>>` - None when the variable is not defined by an expression
+    ///
+    fn get_expression_variable(&self, _name: &str) -> Option<Arc<CompiledExpression>> {
+        None
+    }
+
+    /// Get an interpolation table called like a function.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - Name of the table
+    ///
+    /// # Returns
+    ///
+    /// `Option<&InterpolationTable>` - None when no table is registered with the name
+    ///
+    fn get_interpolation_table(&self, _name: &str) -> Option<&InterpolationTable> {
+        None
+    }
+
+    /// Get the handler evaluating functions that are not known.
+    ///
+    /// # Returns
+    ///
+    /// `Option<&UnknownFunctionHandler>` - None when no handler is set
+    ///
+    fn get_unknown_function_handler(&self) -> Option<&UnknownFunctionHandler> {
+        None
+    }
+
+    /// Get all numeric variables whose names start with a prefix.
+    ///
+    /// # Arguments
+    ///
+    /// * `prefix` - Prefix of the variable names
+    ///
+    /// # Returns
+    ///
+    /// `BTreeMap<String, f64>` - Names and values of the variables sorted by name
+    ///
+    fn variables_with_prefix(&self, prefix: &str) -> BTreeMap<String, f64>;
+
+    /// Get the compiled script of a library executed by `include('name')`.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - Name of the library
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Some(Arc<CompiledExpression>))` - The compiled script of the library
+    /// * `Ok(None)` - The library is skipped
+    /// * `Err(CalculatorError)` - The library is not registered or can not be included
+    ///
+    fn get_library(&self, _name: &str) -> Result<Option<Arc<CompiledExpression>>, CalculatorError> {
+        Err(CalculatorError::ParsingError {
+            msg: "include is only allowed where variables can be assigned, see parse_str_assign",
+            position: None,
+        })
+    }
+}
+
+impl private::Sealed for &Calculator {}
+
+/// Read-only access, assigning a variable returns ForbiddenAssign.
+impl CalculatorAccess for &Calculator {
+    #[inline]
+    fn get_variable(&self, name: &str) -> Result<f64, CalculatorError> {
+        Calculator::get_variable(self, name)
+    }
+
+    #[inline]
+    fn get_expression_variable(&self, name: &str) -> Option<Arc<CompiledExpression>> {
+        self.compiled_expression_variable(name)
+    }
+
+    #[inline]
+    fn get_interpolation_table(&self, name: &str) -> Option<&InterpolationTable> {
+        Calculator::get_interpolation_table(self, name)
+    }
+
+    #[inline]
+    fn get_unknown_function_handler(&self) -> Option<&UnknownFunctionHandler> {
+        self.unknown_function_handler.as_ref()
+    }
+
+    fn variables_with_prefix(&self, prefix: &str) -> BTreeMap<String, f64> {
+        self.variables
+            .iter()
+            .filter(|(name, _)| name.starts_with(prefix))
+            .map(|(name, value)| (name.clone(), *value))
+            .collect()
+    }
+
+    #[inline]
+    fn set_variable(&mut self, name: &str, _value: f64) -> Result<(), CalculatorError> {
+        Err(CalculatorError::ForbiddenAssign {
+            variable_name: name.to_string(),
+        })
+    }
+
+    #[inline]
+    fn check_assign(&self, name: &str) -> Result<(), CalculatorError> {
+        Err(CalculatorError::ForbiddenAssign {
+            variable_name: name.to_string(),
+        })
+    }
+}
+
+/// Calculator access checking an expression against declared variables.
+struct CheckingCalculator<'a> {
+    /// Calculator providing interpolation tables and the unknown function handler
+    calculator: &'a Calculator,
+    /// Variables that are set when the expression is evaluated
+    declared: &'a HashSet<String>,
+    /// Variables assigned in the expression
+    assigned: HashSet<String>,
+    /// Read variables that are neither declared nor assigned before, in the order of their first use
+    undeclared: RefCell<Vec<String>>,
+    /// Skip included libraries that are not registered instead of returning an error
+    missing_libraries_allowed: bool,
+}
+
+impl private::Sealed for CheckingCalculator<'_> {}
+
+/// Variables that are not declared are recorded instead of returning an error,
+/// so all of them are reported.
+impl CalculatorAccess for CheckingCalculator<'_> {
+    fn get_variable(&self, name: &str) -> Result<f64, CalculatorError> {
+        if !self.declared.contains(name) && !self.assigned.contains(name) {
+            let mut undeclared = self.undeclared.borrow_mut();
+            if !undeclared.iter().any(|known| known == name) {
+                undeclared.push(name.to_string());
+            }
+        }
+        Ok(1.0)
+    }
+
+    fn set_variable(&mut self, name: &str, _value: f64) -> Result<(), CalculatorError> {
+        self.assigned.insert(name.to_string());
+        Ok(())
+    }
+
+    fn variables_with_prefix(&self, prefix: &str) -> BTreeMap<String, f64> {
+        self.declared
+            .iter()
+            .chain(self.assigned.iter())
+            .filter(|name| name.starts_with(prefix))
+            .map(|name| (name.clone(), 1.0))
+            .collect()
+    }
+
+    #[inline]
+    fn get_interpolation_table(&self, name: &str) -> Option<&InterpolationTable> {
+        self.calculator.get_interpolation_table(name)
+    }
+
+    #[inline]
+    fn get_unknown_function_handler(&self) -> Option<&UnknownFunctionHandler> {
+        self.calculator.get_unknown_function_handler()
+    }
+
+    fn get_library(&self, name: &str) -> Result<Option<Arc<CompiledExpression>>, CalculatorError> {
+        match self.calculator.compiled_library(name) {
+            Ok(compiled) => Ok(Some(compiled)),
+            Err(_) if self.missing_libraries_allowed => Ok(None),
+            Err(error) => Err(error),
+        }
+    }
+}
+
+/// Calculator access staging assignments until parsing succeeded.
+struct StagedCalculator<'a> {
+    /// Calculator that contains set variables
+    calculator: &'a Calculator,
+    /// Variables assigned while parsing, only written to the Calculator on success
+    staged_variables: HashMap<String, f64>,
+}
+
+impl private::Sealed for StagedCalculator<'_> {}
+
+/// Read and write access, staged variables are consulted before the Calculator.
+impl CalculatorAccess for StagedCalculator<'_> {
+    #[inline]
+    fn get_variable(&self, name: &str) -> Result<f64, CalculatorError> {
+        match self.staged_variables.get(name) {
+            Some(value) => Ok(*value),
+            None => self.calculator.get_variable(name),
+        }
+    }
+
+    #[inline]
+    fn set_variable(&mut self, name: &str, value: f64) -> Result<(), CalculatorError> {
+        self.staged_variables.insert(name.to_string(), value);
+        Ok(())
+    }
+
+    fn variables_with_prefix(&self, prefix: &str) -> BTreeMap<String, f64> {
+        let mut variables = self.calculator.variables_with_prefix(prefix);
+        variables.extend(
+            self.staged_variables
+                .iter()
+                .filter(|(name, _)| name.starts_with(prefix))
+                .map(|(name, value)| (name.clone(), *value)),
+        );
+        variables
+    }
+
+    #[inline]
+    fn get_expression_variable(&self, name: &str) -> Option<Arc<CompiledExpression>> {
+        self.calculator.compiled_expression_variable(name)
+    }
+
+    #[inline]
+    fn get_interpolation_table(&self, name: &str) -> Option<&InterpolationTable> {
+        self.calculator.get_interpolation_table(name)
+    }
+
+    #[inline]
+    fn get_unknown_function_handler(&self) -> Option<&UnknownFunctionHandler> {
+        self.calculator.get_unknown_function_handler()
+    }
+
+    fn get_library(&self, name: &str) -> Result<Option<Arc<CompiledExpression>>, CalculatorError> {
+        self.calculator.compiled_library(name).map(Some)
+    }
+}
+
+/// Calculator access overlaying the variable values of one point of a sweep.
+struct SweepCalculator<'a> {
+    /// Calculator that contains set variables
+    calculator: &'a Calculator,
+    /// Names of the swept variables
+    names: &'a [&'a str],
+    /// Values of the swept variables at the current point
+    values: &'a [f64],
+}
+
+impl private::Sealed for SweepCalculator<'_> {}
+
+/// Read-only access, swept variables are consulted before the Calculator.
+impl CalculatorAccess for SweepCalculator<'_> {
+    #[inline]
+    fn get_variable(&self, name: &str) -> Result<f64, CalculatorError> {
+        match self.names.iter().rposition(|swept| *swept == name) {
+            Some(position) => Ok(self.values[position]),
+            None => self.calculator.get_variable(name),
+        }
+    }
+
+    fn variables_with_prefix(&self, prefix: &str) -> BTreeMap<String, f64> {
+        let mut variables = self.calculator.variables_with_prefix(prefix);
+        // Later swept values of the same name win, as in get_variable
+        variables.extend(
+            self.names
+                .iter()
+                .zip(self.values.iter())
+                .filter(|(name, _)| name.starts_with(prefix))
+                .map(|(name, value)| (name.to_string(), *value)),
+        );
+        variables
+    }
+
+    #[inline]
+    fn set_variable(&mut self, name: &str, _value: f64) -> Result<(), CalculatorError> {
+        self.check_assign(name)
+    }
+
+    #[inline]
+    fn check_assign(&self, name: &str) -> Result<(), CalculatorError> {
+        Err(CalculatorError::ForbiddenAssign {
+            variable_name: name.to_string(),
+        })
+    }
+
+    #[inline]
+    fn get_expression_variable(&self, name: &str) -> Option<Arc<CompiledExpression>> {
+        self.calculator.compiled_expression_variable(name)
+    }
+
+    #[inline]
+    fn get_interpolation_table(&self, name: &str) -> Option<&InterpolationTable> {
+        self.calculator.get_interpolation_table(name)
+    }
+
+    #[inline]
+    fn get_unknown_function_handler(&self) -> Option<&UnknownFunctionHandler> {
+        self.calculator.get_unknown_function_handler()
+    }
+}
+
+/// Read-only access used while evaluating the expression defining a variable.
+struct ExpressionVariableAccess<'a> {
+    /// Access of the parser using the variable
+    calculator: &'a dyn CalculatorAccess,
+}
+
+impl private::Sealed for ExpressionVariableAccess<'_> {}
+
+/// Forwards to the access of the parser using the variable, assigning returns ForbiddenAssign.
+impl CalculatorAccess for ExpressionVariableAccess<'_> {
+    #[inline]
+    fn get_variable(&self, name: &str) -> Result<f64, CalculatorError> {
+        self.calculator.get_variable(name)
+    }
+
+    fn variables_with_prefix(&self, prefix: &str) -> BTreeMap<String, f64> {
+        self.calculator.variables_with_prefix(prefix)
+    }
+
+    #[inline]
+    fn set_variable(&mut self, name: &str, _value: f64) -> Result<(), CalculatorError> {
+        self.check_assign(name)
+    }
+
+    #[inline]
+    fn check_assign(&self, name: &str) -> Result<(), CalculatorError> {
+        Err(CalculatorError::ForbiddenAssign {
+            variable_name: name.to_string(),
+        })
+    }
+
+    #[inline]
+    fn get_expression_variable(&self, name: &str) -> Option<Arc<CompiledExpression>> {
+        self.calculator.get_expression_variable(name)
+    }
+
+    #[inline]
+    fn get_interpolation_table(&self, name: &str) -> Option<&InterpolationTable> {
+        self.calculator.get_interpolation_table(name)
+    }
+
+    #[inline]
+    fn get_unknown_function_handler(&self) -> Option<&UnknownFunctionHandler> {
+        self.calculator.get_unknown_function_handler()
+    }
+}
+
+/// Read and write access used while executing the statements of an included library.
+struct IncludeAccess<'a> {
+    /// Access of the parser executing the include
+    calculator: &'a mut dyn CalculatorAccess,
+}
+
+impl private::Sealed for IncludeAccess<'_> {}
+
+/// Forwards to the access of the parser executing the include.
+impl CalculatorAccess for IncludeAccess<'_> {
+    #[inline]
+    fn get_variable(&self, name: &str) -> Result<f64, CalculatorError> {
+        self.calculator.get_variable(name)
+    }
+
+    #[inline]
+    fn set_variable(&mut self, name: &str, value: f64) -> Result<(), CalculatorError> {
+        self.calculator.set_variable(name, value)
+    }
+
+    #[inline]
+    fn check_assign(&self, name: &str) -> Result<(), CalculatorError> {
+        self.calculator.check_assign(name)
+    }
+
+    fn variables_with_prefix(&self, prefix: &str) -> BTreeMap<String, f64> {
+        self.calculator.variables_with_prefix(prefix)
+    }
+
+    #[inline]
+    fn get_expression_variable(&self, name: &str) -> Option<Arc<CompiledExpression>> {
+        self.calculator.get_expression_variable(name)
+    }
+
+    #[inline]
+    fn get_interpolation_table(&self, name: &str) -> Option<&InterpolationTable> {
+        self.calculator.get_interpolation_table(name)
+    }
+
+    #[inline]
+    fn get_unknown_function_handler(&self) -> Option<&UnknownFunctionHandler> {
+        self.calculator.get_unknown_function_handler()
+    }
+
+    fn get_library(&self, name: &str) -> Result<Option<Arc<CompiledExpression>>, CalculatorError> {
+        self.calculator.get_library(name)
+    }
+}
+
+/// Token returned by the Parser once all tokens have been parsed.
+static END_OF_STRING: Token<'static> = Token::EndOfString;
+
+/// Parser from compiled expression to f64, or to a Dual number holding the derivative
+/// with respect to the seeded variable.
+///
+/// The Parser borrows the tokens of the compiled expression,
+/// so evaluating an expression does not allocate.
+struct Parser<'a, C: CalculatorAccess, N: Scalar = f64> {
+    /// Tokens that have not been parsed yet
+    remaining_tokens: std::slice::Iter<'a, Token<'static>>,
+    /// Parenthesized subexpressions without variables that occur several times
+    repeated_constants: &'a [RepeatedConstant],
+    /// Byte positions of all tokens of the compiled expression
+    positions: &'a [usize],
+    /// Expression the tokens are lexed from
+    source: &'a str,
+    /// Token that is currently parsed
+    current_token: &'a Token<'static>,
+    /// Access to the variables of the Calculator
+    calculator: C,
+    /// Variables defined by expressions that are currently evaluated, outermost first
+    resolving: Vec<String>,
+    /// Libraries that are currently included, outermost first
+    including: Vec<String>,
+    /// Variable with derivative one
+    seed: Option<&'a str>,
+    /// Values of the repeated constant subexpressions evaluated in this parse by slot,
+    /// only allocated when the expression has repeated constant subexpressions
+    constants: Vec<Option<N>>,
+    /// Number type the expression is evaluated with
+    number: PhantomData<N>,
+}
+
+impl<'a, C: CalculatorAccess> Parser<'a, C> {
+    /// Initialize a new instance of Parser evaluating to f64.
+    fn new(compiled: &'a CompiledExpression, calculator: C) -> Self {
+        Parser::with_seed(compiled, calculator, None)
+    }
+}
+
+impl<'a, C: CalculatorAccess, N: Scalar> Parser<'a, C, N> {
+    /// Initialize a new instance of Parser, the seeded variable has the derivative one.
+    fn with_seed(compiled: &'a CompiledExpression, calculator: C, seed: Option<&'a str>) -> Self {
+        let mut remaining_tokens = compiled.tokens().iter();
+        Parser {
+            current_token: remaining_tokens.next().unwrap_or(&END_OF_STRING),
+            remaining_tokens,
+            repeated_constants: compiled.repeated_constants(),
+            positions: compiled.positions(),
+            source: compiled.source(),
+            calculator,
+            resolving: Vec::new(),
+            including: Vec::new(),
+            seed,
+            constants: Vec::new(),
+            number: PhantomData,
+        }
+    }
+
+    /// Consume the parser and return the access to the Calculator.
+    fn into_calculator(self) -> C {
+        self.calculator
+    }
+
+    fn current_token(&self) -> &'a Token<'static> {
+        self.current_token
+    }
+
+    /// Get next token from the compiled expression.
+    fn next_token(&mut self) {
+        self.current_token = self.remaining_tokens.next().unwrap_or(&END_OF_STRING);
+    }
+
+    /// Return the index of the current token in the compiled expression.
+    fn current_index(&self) -> usize {
+        self.positions.len() - self.remaining_tokens.len() - 1
+    }
+
+    /// Return the byte position of the current token in the expression.
+    fn current_position(&self) -> usize {
+        let index = self.positions.len() - self.remaining_tokens.len();
+        index
+            .checked_sub(1)
+            .and_then(|index| self.positions.get(index))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Get the value of a numeric variable or evaluate the expression defining the variable.
+    ///
+    /// Names that are neither variables nor expression variables are looked up in the
+    /// built-in constants, e.g. `pi`.
+    fn variable_value(&self, name: &str) -> Result<N, CalculatorError> {
+        let error = match self.calculator.get_variable(name) {
+            Ok(value) => return Ok(N::variable(value, self.seed == Some(name))),
+            Err(error @ CalculatorError::VariableNotSet { .. }) => error,
+            Err(error) => return Err(error),
+        };
+        let compiled = match self.calculator.get_expression_variable(name) {
+            Some(compiled) => compiled,
+            None => return lookup_constant(name).map(N::constant).ok_or(error),
+        };
+        let mut chain = self.resolving.clone();
+        chain.push(name.to_string());
+        if self.resolving.iter().any(|resolving| resolving == name) {
+            return Err(CalculatorError::RecursiveExpressionVariable { chain });
+        }
+        if self.resolving.len() >= MAX_EXPRESSION_VARIABLE_DEPTH {
+            return Err(CalculatorError::ExpressionVariableTooDeep {
+                name: name.to_string(),
+                limit: MAX_EXPRESSION_VARIABLE_DEPTH,
+            });
+        }
+        let mut parser: Parser<_, N> = Parser::with_seed(
+            &compiled,
+            ExpressionVariableAccess {
+                calculator: &self.calculator,
+            },
+            self.seed,
+        );
+        parser.resolving = chain;
+        parser
+            .evaluate_all_tokens()?
+            .ok_or(CalculatorError::NoValueReturnedParsing)
+    }
+
+    /// Evaluate all Tokens to real value, None (for not returning expressions)
+    /// or return error.
+    ///
+    /// Parsing errors are returned with the position of the token where parsing failed.
+    fn evaluate_all_tokens(&mut self) -> Result<Option<N>, CalculatorError> {
+        self.evaluate_statements()
+            .map_err(|error| self.locate_error(error))
+    }
+
+    /// Add the position of the current token to a parsing error without position.
+    fn locate_error(&self, error: CalculatorError) -> CalculatorError {
+        locate_parsing_error(
+            error,
+            self.source,
+            self.current_token(),
+            self.current_position(),
+        )
+    }
+
+    /// Evaluate the statements of the expression separated by `;`.
+    fn evaluate_statements(&mut self) -> Result<Option<N>, CalculatorError> {
+        let mut current_value: Option<N> = None;
+        while self.current_token() != &Token::EndOfString {
+            current_value = self.evaluate_init()?;
+            while self.current_token() == &Token::EndOfExpression {
+                self.next_token();
+            }
+        }
+        Ok(current_value)
+    }
+
+    /// Initialize the evaluation of an expression.
+    fn evaluate_init(&mut self) -> Result<Option<N>, CalculatorError> {
+        if self.current_token() == &Token::EndOfExpression
+            || self.current_token() == &Token::EndOfString
+        {
+            Err(CalculatorError::UnexpectedEndOfExpression)
+        } else {
+            if let Token::VariableAssign(vs) = self.current_token() {
+                self.calculator.check_assign(vs)?;
+                self.next_token();
+                let res = self.evaluate_binary_0()?;
+                self.calculator.set_variable(vs, res.real()?)?;
+                return Ok(Some(res));
+            }
+            if matches!(self.current_token(), Token::Function(name) if name == INCLUDE) {
+                self.next_token();
+                return self.evaluate_include();
+            }
+            if let Token::CompoundAssign(vs, operator) = self.current_token() {
+                self.calculator.check_assign(vs)?;
+                let current = N::variable(self.calculator.get_variable(vs)?, self.seed == Some(vs));
+                self.next_token();
+                let rhs = self.evaluate_binary_0()?;
+                let res = match operator {
+                    CompoundOperator::Plus => current + rhs,
+                    CompoundOperator::Minus => current - rhs,
+                    CompoundOperator::Multiply => current * rhs,
+                    CompoundOperator::Divide => {
+                        if rhs.is_zero() {
+                            return Err(CalculatorError::DivisionByZero);
+                        }
+                        current / rhs
+                    }
+                };
+                self.calculator.set_variable(vs, res.real()?)?;
+                return Ok(Some(res));
+            }
+            Ok(Some(self.evaluate_binary_0()?))
+        }
+    }
+
+    /// Evaluate least preference binary expression (<, >, <=, >=, ==, !=).
+    ///
+    /// Comparisons evaluate to 1.0 or 0.0 and are piecewise constant, the derivative is zero.
+    fn evaluate_binary_0(&mut self) -> Result<N, CalculatorError> {
+        let mut res = self.evaluate_binary_1()?;
+        while let Token::Comparison(operator) = self.current_token() {
+            let operator = *operator;
+            self.next_token();
+            let val = self.evaluate_binary_1()?;
+            res = N::constant(operator.compare(res.real()?, val.real()?));
+        }
+        Ok(res)
+    }
+
+    /// Evaluate low preference binary expression (+, -).
+    fn evaluate_binary_1(&mut self) -> Result<N, CalculatorError> {
+        let mut res = self.evaluate_binary_2()?;
+        while self.current_token() == &Token::Plus || self.current_token() == &Token::Minus {
+            let bsum: bool = self.current_token() == &Token::Plus;
+            self.next_token();
+            let val = self.evaluate_binary_2()?;
+            if bsum {
+                res = res + val;
+            } else {
+                res = res - val;
+            }
+        }
+        Ok(res)
+    }
+
+    /// Evaluate middle preference binary expression (*, /, %).
+    fn evaluate_binary_2(&mut self) -> Result<N, CalculatorError> {
+        let mut res = self.evaluate_binary_3()?;
+        loop {
+            let operator = match self.current_token() {
+                Token::Multiply => '*',
+                Token::Divide => '/',
+                Token::Modulo => '%',
+                _ => return Ok(res),
+            };
+            self.next_token();
+            let val = self.evaluate_binary_3()?;
+            if operator == '*' {
+                res = res * val;
+            } else {
+                if val.is_zero() {
+                    return Err(CalculatorError::DivisionByZero);
+                }
+                res = if operator == '/' {
+                    res / val
+                } else {
+                    res % val
+                };
+            }
+        }
+    }
+
+    /// Evaluate least preference binary expression (^, !).
+    fn evaluate_binary_3(&mut self) -> Result<N, CalculatorError> {
+        let mut res = self.evaluate_unary()?;
+        match self.current_token() {
+            Token::DoubleFactorial => {
+                return Err(CalculatorError::NotImplementedError {
+                    fct: "DoubleFactorial",
+                })
+            }
+            Token::Factorial => {
+                return Err(CalculatorError::NotImplementedError { fct: "Factorial" })
+            }
+            Token::Power => {
+                self.next_token();
+                res = res.powf(self.evaluate_unary()?);
+            }
+            _ => (),
+        }
+        Ok(res)
+    }
+
+    /// Handle any unary + or - signs.
+    fn evaluate_unary(&mut self) -> Result<N, CalculatorError> {
+        let mut prefactor: f64 = 1.0;
+        match self.current_token() {
+            Token::Minus => {
+                self.next_token();
+                prefactor = -1.0;
+            }
+            Token::Plus => {
+                self.next_token();
+            }
+            _ => (),
+        }
+        Ok(N::constant(prefactor) * self.evaluate()?)
+    }
+
+    /// Handle numbers, variables, functions and parentheses.
+    fn evaluate(&mut self) -> Result<N, CalculatorError> {
+        match self.current_token() {
+            Token::BracketOpen => {
+                let index = self.current_index();
+                let repeated = self
+                    .repeated_constants
+                    .binary_search_by_key(&index, |constant| constant.open)
+                    .ok()
+                    .map(|found| self.repeated_constants[found]);
+                if let Some(constant) = repeated {
+                    if let Some(Some(value)) = self.constants.get(constant.slot) {
+                        // Skip the subexpression including the closing bracket
+                        self.current_token = self
+                            .remaining_tokens
+                            .nth(constant.close - constant.open)
+                            .unwrap_or(&END_OF_STRING);
+                        return Ok(*value);
+                    }
+                }
+                self.next_token();
+                let res_init = self.evaluate_init()?.ok_or(CalculatorError::ParsingError {
+                    msg: "Unexpected None return",
+                    position: None,
+                })?;
+                //self.next_token()?;
+                if self.current_token() != &Token::BracketClose {
+                    Err(CalculatorError::ParsingError {
+                        msg: "Expected Braket close",
+                        position: None,
+                    })
+                } else {
+                    if let Some(constant) = repeated {
+                        // Only values of subexpressions ending at the matching bracket are reused
+                        if self.current_index() == constant.close {
+                            if self.constants.is_empty() {
+                                let slots = self
+                                    .repeated_constants
+                                    .iter()
+                                    .map(|constant| constant.slot + 1)
+                                    .max()
+                                    .unwrap_or_default();
+                                self.constants = vec![None; slots];
+                            }
+                            self.constants[constant.slot] = Some(res_init);
+                        }
+                    }
+                    self.next_token();
+                    Ok(res_init)
+                }
+            }
+            Token::Number(vf) => {
+                self.next_token();
+                Ok(N::constant(*vf))
+            }
+            Token::ImaginaryNumber(vf) => {
+                let value = N::imaginary(*vf)?;
+                self.next_token();
+                Ok(value)
+            }
+            Token::Variable(vs) => {
+                self.next_token();
+                self.variable_value(vs)
+            }
+            // The placeholders are not evaluated, all placeholders that follow are reported
+            Token::Placeholder(_) => Err(CalculatorError::UnboundPlaceholder {
+                names: placeholders_in(
+                    std::iter::once(self.current_token).chain(self.remaining_tokens.clone()),
+                ),
+            }),
+            Token::Reduction(vs) => {
+                self.next_token();
+                self.evaluate_reduction(vs)
+            }
+            Token::Function(vs) => {
+                let position = self.current_position();
+                self.next_token();
+                if VARIABLE_AGGREGATES.contains(&&**vs) {
+                    return self.evaluate_variable_aggregate(vs);
+                }
+                if vs == ASSERT {
+                    return self.evaluate_assert(position);
+                }
+                if vs == INCLUDE {
+                    return Err(CalculatorError::ParsingError {
+                        msg: "include is a statement and can not be used in an expression",
+                        position: None,
+                    });
+                }
+                if self.calculator.get_interpolation_table(vs).is_some() {
+                    let argument = self
+                        .evaluate_init()?
+                        .ok_or(CalculatorError::NoValueReturnedParsing)?;
+                    if self.current_token() != &Token::BracketClose {
+                        return Err(CalculatorError::ParsingError {
+                            msg: "Expected braket close.",
+                            position: None,
+                        });
+                    }
+                    self.next_token();
+                    // The argument can not change the registered tables, the lookup is repeated
+                    // since the parser is borrowed mutably while the argument is evaluated
+                    return N::interpolate(
+                        self.calculator
+                            .get_interpolation_table(vs)
+                            .ok_or(CalculatorError::NoValueReturnedParsing)?,
+                        argument,
+                    );
+                }
+                let number_arguments = match function_argument_numbers(vs) {
+                    Ok(ArgumentCount::Fixed(number_arguments)) => number_arguments,
+                    Ok(ArgumentCount::Variadic) => return self.evaluate_variadic(vs),
+                    Err(error) if self.calculator.get_unknown_function_handler().is_some() => {
+                        return self.evaluate_unknown_function(vs, error);
+                    }
+                    Err(error) => return Err(error),
+                };
+                // Functions take at most two arguments, which are kept on the stack
+                let mut arguments = [N::constant(0.0); 2];
+                for argument_number in 0..number_arguments {
+                    *arguments
+                        .get_mut(argument_number)
+                        .ok_or(CalculatorError::ParsingError {
+                            msg: "Unsupported number of arguments.",
+                            position: None,
+                        })? = self
+                        .evaluate_init()?
+                        .ok_or(CalculatorError::NoValueReturnedParsing)?;
+                    // Swallow commas in function arguments
+                    if argument_number < number_arguments - 1 {
+                        if self.current_token() != &Token::Comma {
+                            return Err(CalculatorError::ParsingError {
+                                msg: "expected comma in function arguments",
+                                position: None,
+                            });
+                        } else {
+                            self.next_token();
+                        }
+                    }
+                    //self.next_token()?;
+                }
+                if self.current_token() != &Token::BracketClose {
+                    return Err(CalculatorError::ParsingError {
+                        msg: "Expected braket close.",
+                        position: None,
+                    });
+                }
+                self.next_token();
+                match number_arguments {
+                    1 => N::function_1(vs, arguments[0]),
+                    2 => N::function_2(vs, arguments[0], arguments[1]),
+                    _ => Err(CalculatorError::ParsingError {
+                        msg: "Unsupported number of arguments.",
+                        position: None,
+                    }),
+                }
+            }
+            _ => Err(CalculatorError::ParsingError {
+                msg: "Bad_Position",
+                position: None,
+            }),
+        }
+    }
+
+    /// Evaluate `sum_vars('prefix')` or `count_vars('prefix')` after the opening bracket.
+    ///
+    /// The variables are summed in lexicographic order of their names,
+    /// so the result does not depend on the order in which the variables were set.
+    fn evaluate_variable_aggregate(&mut self, name: &str) -> Result<N, CalculatorError> {
+        let prefix = match self.current_token() {
+            Token::StringLiteral(prefix) => prefix,
+            _ => {
+                return Err(CalculatorError::ParsingError {
+                    msg: "Expected string literal argument.",
+                    position: None,
+                })
+            }
+        };
+        self.next_token();
+        if self.current_token() != &Token::BracketClose {
+            return Err(CalculatorError::ParsingError {
+                msg: "Expected braket close.",
+                position: None,
+            });
+        }
+        self.next_token();
+        let variables = self.calculator.variables_with_prefix(prefix);
+        Ok(match name {
+            "count_vars" => N::constant(variables.len() as f64),
+            _ => variables
+                .iter()
+                .fold(N::constant(0.0), |sum, (name, value)| {
+                    sum + N::variable(*value, self.seed == Some(name.as_str()))
+                }),
+        })
+    }
+
+    /// Evaluate a variadic function like `max(a, b, c)` after the opening bracket.
+    ///
+    /// The arguments are combined from left to right like the values of a reduction,
+    /// see [Parser::evaluate_reduction]. Fewer than two arguments return
+    /// `CalculatorError::NotEnoughFunctionArguments`.
+    fn evaluate_variadic(&mut self, name: &str) -> Result<N, CalculatorError> {
+        if self.current_token() == &Token::BracketClose {
+            return Err(CalculatorError::NotEnoughFunctionArguments);
+        }
+        let mut result = self
+            .evaluate_init()?
+            .ok_or(CalculatorError::NoValueReturnedParsing)?;
+        let mut number_arguments: usize = 1;
+        loop {
+            match self.current_token() {
+                Token::Comma => self.next_token(),
+                Token::BracketClose => break,
+                _ => {
+                    return Err(CalculatorError::ParsingError {
+                        msg: "Expected braket close.",
+                        position: None,
+                    })
+                }
+            }
+            let value = self
+                .evaluate_init()?
+                .ok_or(CalculatorError::NoValueReturnedParsing)?;
+            number_arguments += 1;
+            result = match name {
+                "sum" => result + value,
+                _ => N::function_2(name, result, value)?,
+            };
+        }
+        self.next_token();
+        if number_arguments < 2 {
+            return Err(CalculatorError::NotEnoughFunctionArguments);
+        }
+        Ok(result)
+    }
+
+    /// Evaluate a reduction like `max[a, b, c]` after the opening square bracket.
+    ///
+    /// The values are combined from left to right, see [REDUCTIONS].
+    fn evaluate_reduction(&mut self, name: &str) -> Result<N, CalculatorError> {
+        if !REDUCTIONS.contains(&name) {
+            return Err(CalculatorError::FunctionNotFound {
+                fct: format!("{name}[]"),
+            });
+        }
+        let mut result: Option<N> = None;
+        let mut number_values: usize = 0;
+        if self.current_token() != &Token::ListClose {
+            loop {
+                let value = self
+                    .evaluate_init()?
+                    .ok_or(CalculatorError::NoValueReturnedParsing)?;
+                number_values += 1;
+                result = Some(match result {
+                    None => value,
+                    Some(previous) if name == "max" || name == "min" => {
+                        N::function_2(name, previous, value)?
+                    }
+                    Some(previous) => previous + value,
+                });
+                match self.current_token() {
+                    Token::Comma => self.next_token(),
+                    Token::ListClose => break,
+                    _ => {
+                        return Err(CalculatorError::ParsingError {
+                            msg: "Expected square braket close.",
+                            position: None,
+                        })
+                    }
+                }
+            }
+        }
+        self.next_token();
+        match (result, name) {
+            (Some(sum), "mean") => Ok(sum / N::constant(number_values as f64)),
+            (Some(result), _) => Ok(result),
+            (None, "sum") => Ok(N::constant(0.0)),
+            (None, _) => Err(CalculatorError::NotEnoughFunctionArguments),
+        }
+    }
+
+    /// Evaluate `assert(condition, 'message')` after the opening bracket.
+    ///
+    /// Returns 1.0 when the condition is not zero and AssertionFailed with the position
+    /// of the assert otherwise.
+    fn evaluate_assert(&mut self, position: usize) -> Result<N, CalculatorError> {
+        let condition = self
+            .evaluate_init()?
+            .ok_or(CalculatorError::NoValueReturnedParsing)?;
+        let mut message = None;
+        if self.current_token() == &Token::Comma {
+            self.next_token();
+            match self.current_token() {
+                Token::StringLiteral(literal) => message = Some(literal),
+                _ => {
+                    return Err(CalculatorError::ParsingError {
+                        msg: "Expected string literal argument.",
+                        position: None,
+                    })
+                }
+            }
+            self.next_token();
+        }
+        if self.current_token() != &Token::BracketClose {
+            return Err(CalculatorError::ParsingError {
+                msg: "Expected braket close.",
+                position: None,
+            });
+        }
+        self.next_token();
+        let condition = condition.real()?;
+        if condition != 0.0 && !condition.is_nan() {
+            Ok(N::constant(1.0))
+        } else {
+            Err(CalculatorError::AssertionFailed {
+                message: message
+                    .map_or(DEFAULT_ASSERT_MESSAGE, |message| message)
+                    .to_string(),
+                position,
+            })
+        }
+    }
+
+    /// Execute `include('name')` after the opening bracket.
+    ///
+    /// Returns the value of the last statement of the library, None for an empty library.
+    fn evaluate_include(&mut self) -> Result<Option<N>, CalculatorError> {
+        let name = match self.current_token() {
+            Token::StringLiteral(name) => name,
+            _ => {
+                return Err(CalculatorError::ParsingError {
+                    msg: "Expected string literal argument.",
+                    position: None,
+                })
+            }
+        };
+        self.next_token();
+        if self.current_token() != &Token::BracketClose {
+            return Err(CalculatorError::ParsingError {
+                msg: "Expected braket close.",
+                position: None,
+            });
+        }
+        self.next_token();
+        let mut chain = self.including.clone();
+        chain.push(name.to_string());
+        if self.including.iter().any(|including| including == name) {
+            return Err(CalculatorError::RecursiveInclude { chain });
+        }
+        if self.including.len() >= MAX_INCLUDE_DEPTH {
+            return Err(CalculatorError::IncludeTooDeep {
+                name: name.to_string(),
+                limit: MAX_INCLUDE_DEPTH,
+            });
+        }
+        let compiled = match self.calculator.get_library(name)? {
+            Some(compiled) => compiled,
+            None => return Ok(None),
+        };
+        let mut parser: Parser<_, N> = Parser::with_seed(
+            &compiled,
+            IncludeAccess {
+                calculator: &mut self.calculator,
+            },
+            self.seed,
+        );
+        parser.including = chain;
+        match parser.evaluate_all_tokens() {
+            Ok(value) => Ok(value),
+            // Errors of the include chain are returned once and not for every library
+            Err(
+                error @ (CalculatorError::RecursiveInclude { .. }
+                | CalculatorError::IncludeTooDeep { .. }),
+            ) => Err(error),
+            Err(error) => Err(CalculatorError::LibraryError {
+                library: name.to_string(),
+                position: parser.current_position(),
+                error: Box::new(error),
+            }),
+        }
+    }
+
+    /// Evaluate a function that is not known by passing its arguments to the unknown function handler.
+    ///
+    /// The arguments are collected until the closing bracket.
+    /// When the handler returns None, the error of the function lookup is returned.
+    /// The handler only provides values, so arguments depending on the seeded variable
+    /// return an error.
+    fn evaluate_unknown_function(
+        &mut self,
+        name: &str,
+        error: CalculatorError,
+    ) -> Result<N, CalculatorError> {
+        let mut arguments: Vec<N> = Vec::new();
+        if self.current_token() != &Token::BracketClose {
+            loop {
+                arguments.push(
+                    self.evaluate_init()?
+                        .ok_or(CalculatorError::NoValueReturnedParsing)?,
+                );
+                match self.current_token() {
+                    Token::Comma => self.next_token(),
+                    Token::BracketClose => break,
+                    _ => {
+                        return Err(CalculatorError::ParsingError {
+                            msg: "expected comma in function arguments",
+                            position: None,
+                        })
+                    }
+                }
+            }
+        }
+        self.next_token();
+        let values = arguments
+            .iter()
+            .map(|argument| argument.real())
+            .collect::<Result<Vec<f64>, CalculatorError>>()?;
+        // The arguments can not change the handler, the lookup is repeated
+        // since the parser is borrowed mutably while the arguments are evaluated
+        let value = self
+            .calculator
+            .get_unknown_function_handler()
+            .and_then(|handler| handler(name, &values))
+            .ok_or(error)?;
+        if arguments
+            .iter()
+            .any(|argument| argument.derivative() != 0.0)
+        {
+            return Err(CalculatorError::NotImplementedError {
+                fct: "derivative of unknown function",
+            });
+        }
+        Ok(N::constant(value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::function_1_argument;
+    use super::function_2_arguments;
+    use super::function_argument_numbers;
+    use super::ArgumentCount;
+    use super::Calculator;
+    use super::CalculatorComplex;
+    use super::CalculatorError;
+    use super::CalculatorFloat;
+    use super::CompoundOperator;
+    use super::EvaluationEvent;
+    use super::Token;
+    use super::MAX_EXPRESSION_VARIABLE_DEPTH;
+    use super::MAX_INCLUDE_DEPTH;
+    use super::{edit_distance, validate_expression, variables_in_expression};
+    use super::{FunctionImplementation, FUNCTIONS};
+    use super::{Parser, StagedCalculator};
+    use super::{SpannedTokenIterator, TokenIterator};
+    use crate::{tokenize, CalculatorWarning, CompiledExpression, ExpressionCache, OutOfRange};
+    use num_complex::Complex;
+    use std::borrow::Cow;
+    use std::collections::HashMap;
+    use std::collections::HashSet;
+    use std::sync::{Arc, Mutex};
+
+    // Test the next function of the TokenIterator for an end of string Token
+    #[test]
+    fn test_end_of_string() {
+        let mut t_iterator = TokenIterator {
+            current_expression: " ",
+        };
+        assert_eq!(t_iterator.next().unwrap(), Token::EndOfString);
+        let mut t_iterator2 = TokenIterator {
+            current_expression: "#aaabbb+.3+.4e-10*5-1; ",
+        };
+        assert_eq!(t_iterator2.next().unwrap(), Token::EndOfString);
+    }
+
+    // Test the next function of the TokenIterator for a plus/minus Token
+    #[test]
+    fn test_plus_minus() {
+        let mut t_iterator = TokenIterator {
+            current_expression: " +",
+        };
+        assert_eq!(t_iterator.next().unwrap(), Token::Plus);
+        let mut t_iterator2 = TokenIterator {
+            current_expression: "#a \n +",
+        };
+        assert_eq!(t_iterator2.next().unwrap(), Token::Plus);
+        let mut t_iterator3 = TokenIterator {
+            current_expression: "-",
+        };
+        assert_eq!(t_iterator3.next().unwrap(), Token::Minus);
+    }
+
+    // Test the next function of the TokenIterator for a number Token
+    #[test]
+    fn test_number() {
+        let mut t_iterator = TokenIterator {
+            current_expression: "1e0",
+        };
+        assert_eq!(t_iterator.next().unwrap(), Token::Number(1.0));
+        let mut t_iterator2 = TokenIterator {
+            current_expression: "(1+2e0)",
+        };
+        assert_eq!(t_iterator2.nth(3).unwrap(), Token::Number(2.0));
+        let mut t_iterator3 = TokenIterator {
+            current_expression: "2e+10",
+        };
+        assert_eq!(t_iterator3.next().unwrap(), Token::Number(2.0e+10));
+        let mut t_iterator4 = TokenIterator {
+            current_expression: "1.74E-10",
+        };
+        assert_eq!(t_iterator4.next().unwrap(), Token::Number(1.74E-10));
+    }
+
+    // Test the next function of the TokenIterator for a multiply Token
+    #[test]
+    fn test_multiply() {
+        let mut t_iterator = TokenIterator {
+            current_expression: " *",
+        };
+        assert_eq!(t_iterator.next().unwrap(), Token::Multiply);
+    }
+
+    // Test the next function of the TokenIterator for a divide Token
+    #[test]
+    fn test_divide() {
+        let mut t_iterator = TokenIterator {
+            current_expression: " /",
+        };
+        assert_eq!(t_iterator.next().unwrap(), Token::Divide);
+    }
+
+    // Test the next function of the TokenIterator for a modulo Token
+    #[test]
+    fn test_modulo() {
+        let mut t_iterator = TokenIterator {
+            current_expression: " %2",
+        };
+        assert_eq!(t_iterator.next().unwrap(), Token::Modulo);
+        assert_eq!(t_iterator.next().unwrap(), Token::Number(2.0));
+    }
+
+    // Test the next function of the TokenIterator for the comparison Tokens
+    #[test]
+    fn test_comparison() {
+        use super::ComparisonOperator::*;
+        let tokens: Vec<Token> = TokenIterator {
+            current_expression: "a<b>c<=d>=e==f!=g=h!",
+        }
+        .collect();
+        assert_eq!(
+            tokens,
+            vec![
+                Token::Variable("a".into()),
+                Token::Comparison(Less),
+                Token::Variable("b".into()),
+                Token::Comparison(Greater),
+                Token::Variable("c".into()),
+                Token::Comparison(LessEqual),
+                Token::Variable("d".into()),
+                Token::Comparison(GreaterEqual),
+                Token::Variable("e".into()),
+                Token::Comparison(Equal),
+                Token::Variable("f".into()),
+                Token::Comparison(NotEqual),
+                Token::VariableAssign("g".into()),
+                Token::Variable("h".into()),
+                Token::Factorial,
+            ]
+        );
+        assert_eq!(
+            format!("{}", Token::Comparison(NotEqual)),
+            "Token::Comparison(NotEqual)"
+        );
+    }
+
+    // Test that comparisons evaluate to 1 or 0 with the lowest precedence
+    #[test]
+    fn test_parse_comparison() {
+        let mut calculator = Calculator::new();
+        calculator.set_variable("x", 0.5).unwrap();
+        calculator.set_variable("y", 3.0).unwrap();
+        for (expression, expected) in [
+            ("x > 0", 1.0),
+            ("x < 0", 0.0),
+            ("abs(x - 1) < 1e-6", 0.0),
+            ("abs(x - 0.5) < 1e-6", 1.0),
+            ("max(x, (x > 0) * y)", 3.0),
+            ("max(x, (x < 0) * y)", 0.5),
+            ("x + 1 >= y - 1.5", 1.0),
+            ("theta(x) * (y != 3) + (y == 3)", 1.0),
+            ("(x > 0) * sin(x) + (x <= 0) * x", 0.5_f64.sin()),
+        ] {
+            assert_eq!(
+                calculator.parse_str(expression),
+                Ok(expected),
+                "{expression}"
+            );
+        }
+        // An equality is not an assignment, an assignment can store a comparison
+        assert_eq!(calculator.parse_str_assign("x == y"), Ok(0.0));
+        assert_eq!(calculator.get_variable("x"), Ok(0.5));
+        assert_eq!(calculator.parse_str_assign("z=x == 0.5"), Ok(1.0));
+        assert_eq!(calculator.get_variable("z"), Ok(1.0));
+        assert_eq!(calculator.parse_str_assign("z += y > x"), Ok(2.0));
+        assert_eq!(calculator.get_variable("z"), Ok(2.0));
+        // Comparisons are piecewise constant
+        assert_eq!(
+            calculator.parse_str_derivative("(x > 0) * x", "x"),
+            Ok((0.5, 1.0))
+        );
+    }
+
+    // Test that numbers directly followed by i or j are lexed as imaginary numbers
+    #[test]
+    fn test_imaginary_number_lexing() {
+        assert_eq!(
+            tokenize("2i + 1.5e-1j*x - 3 i + 2in"),
+            vec![
+                Token::ImaginaryNumber(2.0),
+                Token::Plus,
+                Token::ImaginaryNumber(0.15),
+                Token::Multiply,
+                Token::Variable("x".into()),
+                Token::Minus,
+                Token::Number(3.0),
+                Token::Variable("i".into()),
+                Token::Plus,
+                Token::Number(2.0),
+                Token::Variable("in".into()),
+            ]
+        );
+        assert_eq!(
+            format!("{}", Token::ImaginaryNumber(0.5)),
+            "Token::ImaginaryNumber(5e-1)"
+        );
+        // Imaginary numbers are rejected by the real parser
+        assert_eq!(
+            Calculator::new().parse_str("1 + 2i"),
+            Err(CalculatorError::ParsingError {
+                msg: "Imaginary number in real expression, use parse_str_complex",
+                position: Some(4),
+            })
+        );
+    }
+
+    // Test parsing expressions with imaginary numbers to complex numbers
+    #[test]
+    fn test_parse_str_complex() {
+        let mut calculator = Calculator::new();
+        calculator.set_variable("x", 2.0).unwrap();
+        calculator.set_expression_variable("y", "x^2 - 1").unwrap();
+        for (expression, expected) in [
+            ("1.5", Complex::new(1.5, 0.0)),
+            ("x * y", Complex::new(6.0, 0.0)),
+            ("3j", Complex::new(0.0, 3.0)),
+            ("-2.5i", Complex::new(0.0, -2.5)),
+            ("1 + 2i", Complex::new(1.0, 2.0)),
+            ("(1 + 2i) * (3 - 1i)", Complex::new(5.0, 5.0)),
+            ("x - y * 1i", Complex::new(2.0, -3.0)),
+            ("1 / 2i", Complex::new(0.0, -0.5)),
+            ("(2i)^2", Complex::new(-4.0, 0.0)),
+            ("sqrt(-4)", Complex::new(0.0, 2.0)),
+            ("abs(3 + 4i)", Complex::new(5.0, 0.0)),
+            ("max(x, y) + 1i", Complex::new(3.0, 1.0)),
+        ] {
+            assert_eq!(
+                calculator.parse_str_complex(expression),
+                Ok(expected),
+                "{expression}"
+            );
+        }
+        // Expressions without imaginary numbers give the real value
+        for expression in ["sin(x) / 3", "x^0.5 + log(y)", "(x > 1) * 2"] {
+            assert_eq!(
+                calculator.parse_str_complex(expression),
+                Ok(Complex::new(calculator.parse_str(expression).unwrap(), 0.0)),
+                "{expression}"
+            );
+        }
+        let rotation = calculator.parse_str_complex("exp(0.5i * pi)").unwrap();
+        assert!((rotation - Complex::new(0.0, 1.0)).norm() < 1e-15);
+        assert_eq!(
+            calculator.parse_str_complex("1 / (x - 2 + 0i)"),
+            Err(CalculatorError::DivisionByZero)
+        );
+        assert_eq!(
+            calculator.parse_str_complex("1i < 2"),
+            Err(CalculatorError::ComplexCanNotBeConvertedToFloat {
+                val: CalculatorComplex::new(0.0, 1.0)
+            })
+        );
+        assert_eq!(
+            calculator.parse_str_complex("floor(2 + 1i)"),
+            Err(CalculatorError::ComplexCanNotBeConvertedToFloat {
+                val: CalculatorComplex::new(2.0, 1.0)
+            })
+        );
+        assert!(matches!(
+            calculator.parse_str_complex("z = 1i"),
+            Err(CalculatorError::ForbiddenAssign { .. })
+        ));
+        assert_eq!(
+            super::parse_str_complex("(1 + 2i) * 3j"),
+            Ok(Complex::new(-6.0, 3.0))
+        );
+    }
+
+    // Test evaluating the numeric and symbolic parts of a CalculatorComplex
+    #[test]
+    fn test_parse_get_complex() {
+        let mut calculator = Calculator::new();
+        calculator.set_variable("a", 0.5).unwrap();
+        assert_eq!(
+            calculator.parse_get_complex(CalculatorComplex::new(1.0, -2.0)),
+            Ok(Complex::new(1.0, -2.0))
+        );
+        assert_eq!(
+            calculator.parse_get_complex(CalculatorComplex::new("2 * a", 0.0)),
+            Ok(Complex::new(1.0, 0.0))
+        );
+        assert_eq!(
+            calculator.parse_get_complex(CalculatorComplex::new(3.0, "a + 1")),
+            Ok(Complex::new(3.0, 1.5))
+        );
+        assert_eq!(
+            calculator.parse_get_complex(CalculatorComplex::new("a", "a^2")),
+            Ok(Complex::new(0.5, 0.25))
+        );
+        assert_eq!(
+            calculator.parse_get_complex(CalculatorComplex::new(1.0, "b")),
+            Err(CalculatorError::VariableNotSet {
+                name: "b".to_string()
+            })
+        );
+    }
+
+    // Test that % has the precedence of * and / and the semantics of % for f64
+    #[test]
+    fn test_parse_modulo() {
+        let mut calculator = Calculator::new();
+        for (expression, expected) in [
+            ("7 % 3", 7.0 % 3.0),
+            ("-7 % 3", -7.0 % 3.0),
+            ("7 % -3", 7.0 % -3.0),
+            ("-7 % -3", -7.0 % -3.0),
+            ("-7.5 % 2", -1.5),
+            ("1 + 7 % 3 * 2", 3.0),
+            ("12 / 4 % 2", 1.0),
+            ("2 * 7 % 4", 2.0),
+            ("2 ^ 3 % 5", 3.0),
+        ] {
+            assert_eq!(
+                calculator.parse_str(expression),
+                Ok(expected),
+                "{expression}"
+            );
+        }
+        calculator.set_variable("phi", 7.0).unwrap();
+        calculator.set_variable("pi", std::f64::consts::PI).unwrap();
+        let wrapped = calculator.parse_str("phi % (2*pi)").unwrap();
+        assert!((wrapped - (7.0 - 2.0 * std::f64::consts::PI)).abs() < 1e-15);
+        assert_eq!(
+            calculator.parse_str("phi % 0"),
+            Err(CalculatorError::DivisionByZero)
+        );
+        assert_eq!(
+            calculator.parse_str("phi % (phi - 7)"),
+            Err(CalculatorError::DivisionByZero)
+        );
+        assert_eq!(
+            calculator.parse_str_derivative("phi % 2", "phi"),
+            Ok((1.0, 1.0))
+        );
+    }
+
+    // Test the next function of the TokenIterator for a power (^ and **) Token
+    #[test]
+    fn test_power() {
+        let mut t_iterator = TokenIterator {
+            current_expression: " **",
+        };
+        assert_eq!(t_iterator.next().unwrap(), Token::Power);
+        let mut t_iterator2 = TokenIterator {
+            current_expression: " ^",
+        };
+        assert_eq!(t_iterator2.next().unwrap(), Token::Power);
+    }
+
+    // Test the next function of the TokenIterator for a bracket (open and close) Token
+    #[test]
+    fn test_brackets() {
+        let mut t_iterator = TokenIterator {
+            current_expression: " (",
+        };
+        assert_eq!(t_iterator.next().unwrap(), Token::BracketOpen);
+        let mut t_iterator = TokenIterator {
+            current_expression: ") ",
+        };
+        assert_eq!(t_iterator.next().unwrap(), Token::BracketClose);
+    }
+
+    // Test the next function of the TokenIterator for an assign Token
+    #[test]
+    fn test_assign() {
+        let mut t_iterator = TokenIterator {
+            current_expression: " =",
+        };
+        assert_eq!(t_iterator.next().unwrap(), Token::Assign);
+    }
+
+    // Test the next function of the TokenIterator for a comma Token
+    #[test]
+    fn test_comma() {
+        let mut t_iterator = TokenIterator {
+            current_expression: ", ",
+        };
+        assert_eq!(t_iterator.next().unwrap(), Token::Comma);
+    }
+
+    // Test the next function of the TokenIterator for a semi-colon Token
+    #[test]
+    fn test_semi_colon() {
+        let mut t_iterator = TokenIterator {
+            current_expression: ";",
+        };
+        assert_eq!(t_iterator.next().unwrap(), Token::EndOfExpression);
+    }
+
+    // Test the next function of the TokenIterator for a (double) factorial Token
+    #[test]
+    fn test_factorial() {
+        let mut t_iterator = TokenIterator {
+            current_expression: "!",
+        };
+        assert_eq!(t_iterator.next().unwrap(), Token::Factorial);
+        let mut t_iterator = TokenIterator {
+            current_expression: "!!",
+        };
+        assert_eq!(t_iterator.next().unwrap(), Token::DoubleFactorial);
+    }
+
+    // Test the next function of the TokenIterator for an unrecognised Token
+    #[test]
+    fn test_unrecognized() {
+        let mut t_iterator = TokenIterator {
+            current_expression: "|",
+        };
+        assert_eq!(t_iterator.next().unwrap(), Token::Unrecognized);
+    }
+
+    // Test the next function of the TokenIterator for single-quoted string literals
+    #[test]
+    fn test_string_literal() {
+        let tokens: Vec<Token> = TokenIterator {
+            current_expression: "sum_vars('a_ 1')+'' ",
+        }
+        .collect();
+        assert_eq!(
+            tokens,
+            vec![
+                Token::Function("sum_vars".into()),
+                Token::StringLiteral("a_ 1".into()),
+                Token::BracketClose,
+                Token::Plus,
+                Token::StringLiteral("".into()),
+                Token::EndOfString,
+            ]
+        );
+        assert!(matches!(tokens[1], Token::StringLiteral(Cow::Borrowed(_))));
+        // An unterminated literal is unrecognized and consumes the rest of the expression
+        let tokens: Vec<Token> = TokenIterator {
+            current_expression: "1 + 'a_) + 2",
+        }
+        .collect();
+        assert_eq!(
+            tokens,
+            vec![Token::Number(1.0), Token::Plus, Token::Unrecognized]
+        );
+        assert_eq!(
+            validate_expression("sum_vars('a_)"),
+            Err(CalculatorError::InvalidExpression {
+                position: 9,
+                error: Box::new(CalculatorError::NotParsableUnrecognized)
+            })
+        );
+        assert!(Calculator::new().parse_str("sum_vars('a_)").is_err());
+        assert_eq!(validate_expression("count_vars('a_')"), Ok(()));
+        assert_eq!(
+            format!("{}", Token::StringLiteral("a_".into())),
+            "Token::StringLiteral(a_)"
+        );
+    }
+
+    // Test the next function of the TokenIterator for placeholders in braces
+    #[test]
+    fn test_placeholder() {
+        let tokens: Vec<Token> = TokenIterator {
+            current_expression: "{qubit_1}*{n}+{ n }+{2}",
+        }
+        .collect();
+        assert_eq!(
+            tokens,
+            vec![
+                Token::Placeholder("qubit_1".into()),
+                Token::Multiply,
+                Token::Placeholder("n".into()),
+                Token::Plus,
+                Token::Unrecognized,
+                Token::Plus,
+                Token::Unrecognized,
+            ]
+        );
+        assert!(matches!(tokens[0], Token::Placeholder(Cow::Borrowed(_))));
+        // An unterminated placeholder is unrecognized and consumes the rest of the expression
+        let tokens: Vec<Token> = TokenIterator {
+            current_expression: "1 + {n + 2",
+        }
+        .collect();
+        assert_eq!(
+            tokens,
+            vec![Token::Number(1.0), Token::Plus, Token::Unrecognized]
+        );
+        assert_eq!(validate_expression("{n} * sin({m})"), Ok(()));
+        assert_eq!(
+            variables_in_expression("{n} * x"),
+            Ok(vec!["x".to_string()])
+        );
+        assert_eq!(
+            Calculator::new().parse_str("2 * {n} + {m}"),
+            Err(CalculatorError::UnboundPlaceholder {
+                names: vec!["n".to_string(), "m".to_string()]
+            })
+        );
+        assert_eq!(
+            format!("{}", Token::Placeholder("n".into())),
+            "Token::Placeholder(n)"
+        );
+    }
+
+    // Test the next function of the TokenIterator for a variable Token
+    #[test]
+    fn test_variable() {
+        let mut t_iterator = TokenIterator {
+            current_expression: "test",
+        };
+        let next_token = t_iterator.next().expect("next token throws error");
+        assert_eq!(next_token, Token::Variable("test".into()));
+        let mut t_iterator = TokenIterator {
+            current_expression: "test;",
+        };
+        let next_token = t_iterator.next().expect("next token throws error");
+        assert_eq!(next_token, Token::Variable("test".into()));
+        let mut t_iterator = TokenIterator {
+            current_expression: "test+",
+        };
+        let next_token = t_iterator.next().expect("next token throws error");
+        assert_eq!(next_token, Token::Variable("test".into()));
+    }
+
+    // Test the next function of the TokenIterator for a variable assign Token
+    #[test]
+    fn test_variable_assign() {
+        let mut t_iterator = TokenIterator {
+            current_expression: "test=",
+        };
+        let next_token = t_iterator.next().expect("next token throws error");
+        assert_eq!(next_token, Token::VariableAssign("test".into()));
+    }
+
+    // Test that compound assignments are only lexed when the operator is written without a gap
+    #[test]
+    fn test_compound_assign() {
+        let tokens: Vec<Token> = TokenIterator {
+            current_expression: "a+=1",
+        }
+        .collect();
+        assert_eq!(
+            tokens,
+            vec![
+                Token::CompoundAssign("a".into(), CompoundOperator::Plus),
+                Token::Number(1.0)
+            ]
+        );
+        for (expression, operator) in [
+            ("a -= 1", CompoundOperator::Minus),
+            ("a *=1", CompoundOperator::Multiply),
+            ("a/= 1", CompoundOperator::Divide),
+        ] {
+            let mut t_iterator = TokenIterator {
+                current_expression: expression,
+            };
+            assert_eq!(
+                t_iterator.next().unwrap(),
+                Token::CompoundAssign("a".into(), operator)
+            );
+        }
+        let tokens: Vec<Token> = TokenIterator {
+            current_expression: "a+ =1",
+        }
+        .collect();
+        assert_eq!(
+            tokens,
+            vec![
+                Token::Variable("a".into()),
+                Token::Plus,
+                Token::Assign,
+                Token::Number(1.0)
+            ]
+        );
+        let tokens: Vec<Token> = TokenIterator {
+            current_expression: "a + = 1",
+        }
+        .collect();
+        assert_eq!(
+            tokens,
+            vec![
+                Token::Variable("a".into()),
+                Token::Plus,
+                Token::Assign,
+                Token::Number(1.0)
+            ]
+        );
+        let tokens: Vec<Token> = TokenIterator {
+            current_expression: "a**=2",
+        }
+        .collect();
+        assert_eq!(
+            tokens,
+            vec![
+                Token::Variable("a".into()),
+                Token::Power,
+                Token::Assign,
+                Token::Number(2.0)
+            ]
+        );
+    }
+
+    // Test the next function of the TokenIterator for a function Token
+    #[test]
+    fn test_functions() {
+        let mut t_iterator = TokenIterator {
+            current_expression: "test(",
+        };
+        let next_token = t_iterator.next().expect("next token throws error");
+        assert_eq!(next_token, Token::Function("test".into()));
+    }
+
+    // Test the default function of Calculator
+    #[test]
+    fn test_calculator_new() {
+        let _calculator = Calculator::new();
+    }
+
+    #[test]
+    fn test_calculator_default() {
+        let _calculator = Calculator::default();
+        assert_eq!(_calculator.variables, Calculator::new().variables);
+    }
+
+    // Test the Debug macro for Calculator
+    #[test]
+    fn test_calculator_debug() {
+        let mut calculator = Calculator::new();
+        calculator.set_variable("x", 0.1).unwrap();
+        assert_eq!(
+            format!("{calculator:?}"),
+            "Calculator { variables: {\"x\": 0.1} }"
+        );
+    }
+
+    // Test the Clone macro for Calculator
+    #[test]
+    fn test_calculator_clone() {
+        let mut calculator = Calculator::new();
+        calculator.set_variable("x", 0.1).unwrap();
+        let c_clone = calculator.clone();
+        assert_eq!(c_clone.get_variable("x").unwrap(), 0.1);
+        assert_eq!(calculator.variables, c_clone.variables);
+    }
+
+    // Test set_variable function
+    #[test]
+    fn test_set_value() {
+        let mut calculator = Calculator::new();
+        calculator.set_variable("test", 0.1).unwrap();
+        assert_eq!(*calculator.variables.get("test").unwrap(), 0.1);
+    }
+
+    // Test get_variable function (float and error return)
+    #[test]
+    fn test_get_value() {
+        let mut calculator = Calculator::new();
+        calculator.set_variable("test", 0.1).unwrap();
+        assert_eq!(calculator.get_variable("test").unwrap(), 0.1);
+        assert!(calculator.get_variable("test2").is_err());
+    }
+
+    // Test parse_string for a variable Token
+    #[test]
+    fn test_parse_variable() {
+        let calculator = Calculator::new();
+        let value = calculator.parse_str("a=3; 2*(a+1);");
+        assert!(value.is_err());
+        let value = calculator.parse_str("2*(3+1);");
+        assert_eq!(value.unwrap(), 8.0);
+    }
+
+    // Test parse_string for a variable Token
+    #[test]
+    fn test_parse_assign_variable() {
+        let mut calculator = Calculator::new();
+        let value = calculator.parse_str_assign("a=3; 2*(a+1);");
+        assert_eq!(value.unwrap(), 8.0);
+        assert_eq!(calculator.get_variable("a").unwrap(), 3.0);
+    }
+
+    // Test reassigning the same variable within one parse_str_assign call
+    #[test]
+    fn test_parse_assign_reassignment() {
+        let mut calculator = Calculator::new();
+        let value = calculator.parse_str_assign("a=1; a=a+1; a*2");
+        assert_eq!(value.unwrap(), 4.0);
+        assert_eq!(calculator.get_variable("a").unwrap(), 2.0);
+    }
+
+    // Test parse_str_assign for all compound assignment operators
+    #[test]
+    fn test_parse_assign_compound() {
+        let mut calculator = Calculator::new();
+        let value = calculator.parse_str_assign("a=0; a+=1; a*=3;");
+        assert_eq!(value.unwrap(), 3.0);
+        assert_eq!(calculator.get_variable("a").unwrap(), 3.0);
+        let value = calculator.parse_str_assign("a -= 5; a /= 4");
+        assert_eq!(value.unwrap(), -0.5);
+        let value = calculator.parse_str_assign("b=2; b += b * 2; b^2");
+        assert_eq!(value.unwrap(), 36.0);
+        assert_eq!(calculator.get_variable("b").unwrap(), 6.0);
+        assert_eq!(
+            calculator.parse_str_assign("a+ =1"),
+            Err(CalculatorError::UnexpectedToken {
+                token: "=".to_string(),
+                position: 3
+            })
+        );
+    }
+
+    // Test that failing compound assignments do not change the variables
+    #[test]
+    fn test_parse_assign_compound_failure() {
+        let mut calculator = Calculator::new();
+        calculator.set_variable("a", 2.0).unwrap();
+        assert_eq!(
+            calculator.parse_str_assign("c+=1"),
+            Err(CalculatorError::VariableNotSet {
+                name: "c".to_string()
+            })
+        );
+        assert_eq!(
+            calculator.parse_str_assign("a+=1; a/=a-3"),
+            Err(CalculatorError::DivisionByZero)
+        );
+        assert_eq!(calculator.get_variable("a").unwrap(), 2.0);
+        assert_eq!(
+            calculator.parse_str("a+=1"),
+            Err(CalculatorError::ForbiddenAssign {
+                variable_name: "a".to_string()
+            })
+        );
+        assert_eq!(calculator.get_variable("a").unwrap(), 2.0);
+    }
+
+    // Test that no assignment is kept when parsing fails after a successful assignment
+    #[test]
+    fn test_parse_assign_failure_after_assignment() {
+        let mut calculator = Calculator::new();
+        calculator.set_variable("a", 10.0).unwrap();
+        let value = calculator.parse_str_assign("a=1; b=2; c=a/0; a");
+        assert_eq!(value, Err(CalculatorError::DivisionByZero));
+        assert_eq!(calculator.get_variable("a").unwrap(), 10.0);
+        assert!(calculator.get_variable("b").is_err());
+        assert!(calculator.get_variable("c").is_err());
+    }
+
+    // Test that a failure in the first assignment leaves the Calculator unchanged
+    #[test]
+    fn test_parse_assign_failure_in_first_assignment() {
+        let mut calculator = Calculator::new();
+        calculator.set_variable("a", 10.0).unwrap();
+        let value = calculator.parse_str_assign("a=undefined+1; b=2");
+        assert_eq!(
+            value,
+            Err(CalculatorError::VariableNotSet {
+                name: "undefined".to_string()
+            })
+        );
+        assert_eq!(calculator.variables.len(), 1);
+        assert_eq!(calculator.get_variable("a").unwrap(), 10.0);
+    }
+
+    // Test that clones share compiled expressions but not variable values
+    #[test]
+    fn test_shared_expression_cache_clone() {
+        let mut calculator = Calculator::new();
+        calculator.set_variable("a", 1.0).unwrap();
+        let mut clone = calculator.clone();
+        clone.set_variable("a", 2.0).unwrap();
+
+        assert_eq!(calculator.parse_str("a + 1").unwrap(), 2.0);
+        assert_eq!(calculator.expression_cache().misses(), 1);
+        assert_eq!(clone.parse_str("a + 1").unwrap(), 3.0);
+        assert_eq!(clone.expression_cache().hits(), 1);
+        assert_eq!(calculator.expression_cache().hits(), 1);
+        assert_eq!(clone.expression_cache().len(), 1);
+    }
+
+    // Test sharing a cache between independently created Calculators
+    #[test]
+    fn test_with_shared_cache() {
+        let cache = ExpressionCache::new();
+        let mut first = Calculator::with_shared_cache(cache.clone());
+        let second = Calculator::with_shared_cache(cache.clone());
+        assert_eq!(first.parse_str_assign("b=3; b * 2").unwrap(), 6.0);
+        assert_eq!(cache.misses(), 1);
+        assert!(second.parse_str("b=3; b * 2").is_err());
+        assert_eq!(cache.hits(), 1);
+        assert!(Calculator::new().expression_cache().is_empty());
+        assert_eq!(
+            second.parse_str(""),
+            Err(CalculatorError::NoValueReturnedParsing)
+        );
+    }
+
+    // Test parsing the same expressions from several threads with a shared cache
+    #[test]
+    fn test_shared_expression_cache_threads() {
+        let calculator = Calculator::new();
+        let expressions: Vec<String> = (0..20).map(|i| format!("{i} * x + sin(x)")).collect();
+        let handles: Vec<_> = (0..8)
+            .map(|thread| {
+                let mut calculator = calculator.clone();
+                let expressions = expressions.clone();
+                std::thread::spawn(move || {
+                    calculator.set_variable("x", thread as f64).unwrap();
+                    for (i, expression) in expressions.iter().enumerate() {
+                        let value = calculator.parse_str(expression).unwrap();
+                        let expected = i as f64 * thread as f64 + (thread as f64).sin();
+                        assert!((value - expected).abs() < 1e-12);
+                    }
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+        let cache = calculator.expression_cache();
+        assert_eq!(cache.len(), 20);
+        assert_eq!(cache.misses(), 20);
+        assert_eq!(cache.hits(), 8 * 20 - 20);
+    }
+
+    // Test that parse_str rejects assignments before evaluating the assigned value
+    #[test]
+    fn test_parse_str_forbidden_assign() {
+        let calculator = Calculator::new();
+        assert_eq!(
+            calculator.parse_str("a=undefined + 1"),
+            Err(CalculatorError::ForbiddenAssign {
+                variable_name: "a".to_string()
+            })
+        );
+        assert_eq!(
+            calculator.parse_str("1; b=2"),
+            Err(CalculatorError::ForbiddenAssign {
+                variable_name: "b".to_string()
+            })
+        );
+    }
+
+    // Test a two axis sweep against manually computed values
+    #[test]
+    fn test_sweep() {
+        let mut calculator = Calculator::new();
+        calculator.set_variable("c", 0.5).unwrap();
+        calculator.set_variable("x", 100.0).unwrap();
+        let result = calculator
+            .sweep(
+                "x * c + sin(y)",
+                &[("x", vec![1.0, 2.0, 3.0]), ("y", vec![0.0, 0.25])],
+            )
+            .unwrap();
+        assert_eq!(result.shape(), vec![3, 2]);
+        let mut expected = Vec::new();
+        for x in [1.0_f64, 2.0, 3.0] {
+            for y in [0.0_f64, 0.25] {
+                expected.push(x * 0.5 + y.sin());
+            }
+        }
+        assert_eq!(result.values, expected);
+        assert_eq!(result.axes[1], ("y".to_string(), vec![0.0, 0.25]));
+        assert_eq!(calculator.get_variable("x").unwrap(), 100.0);
+        assert!(calculator.get_variable("y").is_err());
+        assert_eq!(calculator.expression_cache().misses(), 1);
+
+        let empty = calculator.sweep("x", &[("x", vec![])]).unwrap();
+        assert!(empty.values.is_empty());
+        let single = calculator.sweep("x", &[]).unwrap();
+        assert_eq!(single.values, vec![100.0]);
+    }
+
+    // Test that a failing sweep point reports its multi-index
+    #[test]
+    fn test_sweep_error_index() {
+        let calculator = Calculator::new();
+        let result = calculator.sweep(
+            "1 / (x - y)",
+            &[("x", vec![0.0, 1.0]), ("y", vec![2.0, 1.0])],
+        );
+        assert_eq!(
+            result,
+            Err(CalculatorError::SweepPointError {
+                index: vec![1, 1],
+                error: Box::new(CalculatorError::DivisionByZero)
+            })
+        );
+        assert_eq!(
+            calculator.sweep("z=x", &[("x", vec![1.0])]),
+            Err(CalculatorError::SweepPointError {
+                index: vec![0],
+                error: Box::new(CalculatorError::ForbiddenAssign {
+                    variable_name: "z".to_string()
+                })
+            })
+        );
+    }
+
+    // Test renaming the variables and assignment targets of a script
+    #[test]
+    fn test_anonymize_script() {
+        let script = "t_rise=2e-9; t_total=t_rise + 3 * t_gate; t_total*=scale; # t_total\nt_total";
+        let (anonymized, mapping) = Calculator::anonymize_script(script);
+        assert_eq!(anonymized, "v0=2e-9; v1=v0 + 3 * v2; v1*=v3; # t_total\nv1");
+        let names: Vec<(&str, &str)> = ["t_rise", "t_total", "t_gate", "scale"]
+            .iter()
+            .map(|name| (*name, mapping[*name].as_str()))
+            .collect();
+        assert_eq!(
+            names,
+            vec![
+                ("t_rise", "v0"),
+                ("t_total", "v1"),
+                ("t_gate", "v2"),
+                ("scale", "v3")
+            ]
+        );
+        assert_eq!(mapping.len(), 4);
+        let mut original = Calculator::new();
+        let mut renamed = Calculator::new();
+        for (name, value) in [("t_gate", 1e-8), ("scale", 1.5)] {
+            original.set_variable(name, value).unwrap();
+            renamed.set_variable(&mapping[name], value).unwrap();
+        }
+        assert_eq!(
+            original.parse_str_assign(script).unwrap(),
+            renamed.parse_str_assign(&anonymized).unwrap()
+        );
+        assert_eq!(original.get_variable("t_total"), renamed.get_variable("v1"));
+        // The mapping only depends on the script
+        assert_eq!(Calculator::anonymize_script(script), (anonymized, mapping));
+        // String literals are not changed
+        assert_eq!(
+            Calculator::anonymize_script("a_1=1; a_2=b; sum_vars('a_')").0,
+            "v0=1; v1=v2; sum_vars('a_')"
+        );
+    }
+
+    // Test that algebraically equal expressions agree on random samples
+    #[cfg(feature = "rand")]
+    #[test]
+    fn test_probably_equal() {
+        let mut calculator = Calculator::new();
+        for (a, b) in [
+            ("a * (b + c)", "a * b + a * c"),
+            ("(x + 1)^2", "x^2 + 2 * x + 1"),
+            ("sin(2 * x)", "2 * sin(x) * cos(x)"),
+            ("exp(x + y)", "exp(x) * exp(y)"),
+        ] {
+            let equal = calculator
+                .probably_equal(&CalculatorFloat::from(a), &CalculatorFloat::from(b), 100, 7)
+                .unwrap();
+            assert!(equal, "{a} == {b}");
+        }
+        let trigonometric = CalculatorFloat::from("sin(x)^2 + cos(x)^2");
+        assert!(calculator
+            .probably_equal(&trigonometric, &CalculatorFloat::from(1.0), 100, 7)
+            .unwrap());
+        // Set variables are not sampled
+        calculator.set_variable("a", 2.0).unwrap();
+        assert!(calculator
+            .probably_equal(
+                &CalculatorFloat::from("a * x"),
+                &CalculatorFloat::from("2 * x"),
+                100,
+                7
+            )
+            .unwrap());
+        // Small differences are found within few samples
+        assert!(!calculator
+            .probably_equal(
+                &CalculatorFloat::from("sin(2 * x)"),
+                &CalculatorFloat::from("2 * sin(x) * cos(x) + 1e-6 * x"),
+                3,
+                7
+            )
+            .unwrap());
+        assert!(!calculator
+            .probably_equal(
+                &CalculatorFloat::from("a * x"),
+                &CalculatorFloat::from("2.000001 * x"),
+                3,
+                7
+            )
+            .unwrap());
+    }
+
+    // Test that samples only depend on the seed and failing samples are reported
+    #[cfg(feature = "rand")]
+    #[test]
+    fn test_probably_equal_seed() {
+        let calculator = Calculator::new();
+        let x = CalculatorFloat::from("x");
+        let root = CalculatorFloat::from("sqrt(x^2)");
+        // With a single sample the result depends on the sign of the sampled x
+        let results: Vec<bool> = (0..32)
+            .map(|seed| calculator.probably_equal(&x, &root, 1, seed).unwrap())
+            .collect();
+        let repeated: Vec<bool> = (0..32)
+            .map(|seed| calculator.probably_equal(&x, &root, 1, seed).unwrap())
+            .collect();
+        assert_eq!(results, repeated);
+        assert!(results.contains(&true) && results.contains(&false));
+
+        let error = calculator
+            .probably_equal(&CalculatorFloat::from("1 / (x - x)"), &x, 10, 3)
+            .unwrap_err();
+        match error {
+            CalculatorError::SamplePointError { assignments, error } => {
+                assert_eq!(assignments.len(), 1);
+                assert_eq!(assignments[0].0, "x");
+                assert!(assignments[0].1.abs() <= std::f64::consts::PI);
+                assert_eq!(*error, CalculatorError::DivisionByZero);
+            }
+            error => panic!("{error:?}"),
+        }
+        assert!(calculator
+            .probably_equal(&CalculatorFloat::from("x +"), &x, 10, 3)
+            .is_err());
+    }
+
+    // Test the built-in constants and that variables take precedence over them
+    #[test]
+    fn test_built_in_constants() {
+        let mut calculator = Calculator::new();
+        assert_eq!(
+            calculator.parse_str("2*pi").unwrap(),
+            2.0 * std::f64::consts::PI
+        );
+        assert_eq!(
+            CalculatorFloat::from(calculator.parse_str("pi").unwrap()),
+            CalculatorFloat::PI
+        );
+        assert_eq!(
+            CalculatorFloat::from(calculator.parse_str("E").unwrap()),
+            CalculatorFloat::E
+        );
+        assert_eq!(
+            calculator.parse_str("tau / 2").unwrap(),
+            std::f64::consts::PI
+        );
+        calculator.set_variable("x", 2.0).unwrap();
+        assert_eq!(
+            calculator.parse_str_derivative("pi * x", "x"),
+            Ok((2.0 * std::f64::consts::PI, std::f64::consts::PI))
+        );
+        // Variables and expression variables override the constants
+        calculator.set_variable("pi", 3.0).unwrap();
+        assert_eq!(calculator.parse_str("2*pi").unwrap(), 6.0);
+        calculator.set_expression_variable("e", "pi + 1").unwrap();
+        assert_eq!(calculator.parse_str("e").unwrap(), 4.0);
+        calculator.remove_variable("pi").unwrap();
+        assert_eq!(
+            calculator.parse_str("e").unwrap(),
+            std::f64::consts::PI + 1.0
+        );
+        // Constants are not variables of the Calculator
+        assert_eq!(
+            calculator.get_variable("tau"),
+            Err(CalculatorError::VariableNotSet {
+                name: "tau".to_string()
+            })
+        );
+        assert!(calculator.parse_str_assign("tau += 1").is_err());
+        assert_eq!(super::parse_str("cos(pi)").unwrap(), -1.0);
+    }
+
+    // Test loading physical constants keeps user variables
+    #[cfg(feature = "physical-constants")]
+    #[test]
+    fn test_load_physical_constants() {
+        let mut calculator = Calculator::new();
+        calculator.set_variable("c", 1.0).unwrap();
+        let collisions = calculator.load_physical_constants().unwrap();
+        assert_eq!(collisions, vec!["c".to_string()]);
+        assert_eq!(calculator.get_variable("c").unwrap(), 1.0);
+        assert_eq!(
+            calculator.parse_str("hbar").unwrap(),
+            crate::constants::HBAR
+        );
+        assert_eq!(
+            calculator.parse_str("kb * 2").unwrap(),
+            2.0 * crate::constants::KB
+        );
+        assert_eq!(calculator.parse_str("qe").unwrap(), 1.602176634e-19);
+        assert!(calculator.load_physical_constants().unwrap().len() == 6);
+    }
+
+    // Test validate_expression for valid and invalid expressions
+    #[test]
+    fn test_free_parse_functions() {
+        assert_eq!(super::parse_str("2 * (3 + 1)"), Ok(8.0));
+        assert_eq!(super::parse_str("1 + 2 # comment"), Ok(3.0));
+        assert_eq!(
+            super::parse_str("a=3; a*2"),
+            Err(CalculatorError::ForbiddenAssign {
+                variable_name: "a".to_string()
+            })
+        );
+        assert_eq!(
+            super::parse_str("a"),
+            Err(CalculatorError::VariableNotSet {
+                name: "a".to_string()
+            })
+        );
+        assert_eq!(super::parse_str_assign("a=3; a*2"), Ok(6.0));
+        assert_eq!(
+            super::parse_str_assign("a=3; # first statement\nb=a+1; a*b;"),
+            Ok(12.0)
+        );
+        assert_eq!(super::parse_str_assign("2^3"), Ok(8.0));
+        assert_eq!(
+            super::parse_str_assign("a=1/0; a"),
+            Err(CalculatorError::DivisionByZero)
+        );
+        // Variables are not kept between calls
+        assert!(super::parse_str_assign("a").is_err());
+    }
+
+    #[test]
+    fn test_validate_expression() {
+        assert_eq!(validate_expression("sin(a) + (b * 2) ^ c"), Ok(()));
+        assert_eq!(validate_expression(""), Ok(()));
+        assert_eq!(
+            validate_expression("1 + b=2"),
+            Err(CalculatorError::InvalidExpression {
+                position: 4,
+                error: Box::new(CalculatorError::NotParsableAssign {
+                    variable_name: "b".to_string()
+                })
+            })
+        );
+        assert_eq!(
+            validate_expression("1 + = 2"),
+            Err(CalculatorError::InvalidExpression {
+                position: 4,
+                error: Box::new(CalculatorError::NotParsableSingleAssign)
+            })
+        );
+        assert_eq!(
+            validate_expression("a + $"),
+            Err(CalculatorError::InvalidExpression {
+                position: 4,
+                error: Box::new(CalculatorError::NotParsableUnrecognized)
+            })
+        );
+        assert_eq!(
+            validate_expression("(a + 1))"),
+            Err(CalculatorError::InvalidExpression {
+                position: 7,
+                error: Box::new(CalculatorError::ParsingError {
+                    msg: "Unexpected closing bracket",
+                    position: None,
+                })
+            })
+        );
+        assert_eq!(
+            validate_expression("1 + sin(a"),
+            Err(CalculatorError::InvalidExpression {
+                position: 4,
+                error: Box::new(CalculatorError::ParsingError {
+                    msg: "Expected Braket close",
+                    position: None,
+                })
+            })
+        );
+        assert_eq!(validate_expression("max[a, (b + 1), c] * 2"), Ok(()));
+        assert_eq!(
+            validate_expression("max[a, b)"),
+            Err(CalculatorError::InvalidExpression {
+                position: 8,
+                error: Box::new(CalculatorError::ParsingError {
+                    msg: "Unexpected closing bracket",
+                    position: None,
+                })
+            })
+        );
+        assert_eq!(
+            validate_expression("(a + b]"),
+            Err(CalculatorError::InvalidExpression {
+                position: 6,
+                error: Box::new(CalculatorError::ParsingError {
+                    msg: "Unexpected closing bracket",
+                    position: None,
+                })
+            })
+        );
+    }
+
+    // Test variables_in_expression returns each variable once in order of appearance
+    #[test]
+    fn test_variables_in_expression() {
+        assert_eq!(
+            variables_in_expression("b * sin(a) + b / atan2(c, 2)").unwrap(),
+            vec!["b".to_string(), "a".to_string(), "c".to_string()]
+        );
+        assert!(variables_in_expression("1.0 + 2").unwrap().is_empty());
+        assert!(variables_in_expression("a = 1").is_err());
+    }
+
+    // Test edit_distance counts insertions, deletions and substitutions
+    #[test]
+    fn test_edit_distance() {
+        assert_eq!(edit_distance("theta", "theta"), 0);
+        assert_eq!(edit_distance("theta", "thet"), 1);
+        assert_eq!(edit_distance("theta", "thetta"), 1);
+        assert_eq!(edit_distance("theta", "thxta"), 1);
+        assert_eq!(edit_distance("theta", "phi"), 4);
+        assert_eq!(edit_distance("", "abc"), 3);
+    }
+
+    // Test that clean expressions do not produce warnings
+    #[test]
+    fn test_parse_str_with_warnings_clean() {
+        let mut calculator = Calculator::new();
+        calculator.set_variable("theta", 0.5).unwrap();
+        calculator.set_variable("phi", 0.25).unwrap();
+        calculator.set_variable("a", 1.0).unwrap();
+        calculator.set_variable("b", 2.0).unwrap();
+        for expression in [
+            "sin(theta) + cos(phi)",
+            "atan2(2, 5) + atan2(theta,phi) + atan2(2.5, 1)",
+            "a + 1 # comment with 2,5",
+        ] {
+            let (value, warnings) = calculator.parse_str_with_warnings(expression).unwrap();
+            assert_eq!(value, calculator.parse_str(expression).unwrap());
+            assert!(warnings.is_empty(), "{expression}: {warnings:?}");
+        }
+        let (value, warnings) = calculator
+            .parse_str_assign_with_warnings("c=theta * 2; d=c + 1; d ^ 2")
+            .unwrap();
+        assert_eq!(value, 4.0);
+        assert!(warnings.is_empty());
+        let (_, warnings) = calculator.parse_str_assign_with_warnings("e=3;").unwrap();
+        assert!(warnings.is_empty());
+    }
+
+    // Test the warning for a comma between two digits
+    #[test]
+    fn test_parse_str_with_warnings_decimal_comma() {
+        let calculator = Calculator::new();
+        let (value, warnings) = calculator.parse_str_with_warnings("1 + max(2,5)").unwrap();
+        assert_eq!(value, 6.0);
+        assert_eq!(
+            warnings,
+            vec![CalculatorWarning::DecimalComma { position: 9 }]
+        );
+    }
+
+    // Test the warning for a variable that is similar to another set variable
+    #[test]
+    fn test_parse_str_with_warnings_similar_variable() {
+        let mut calculator = Calculator::new();
+        calculator.set_variable("theta", 0.5).unwrap();
+        calculator.set_variable("thetta", 0.25).unwrap();
+        let (value, warnings) = calculator.parse_str_with_warnings("2 * thetta").unwrap();
+        assert_eq!(value, 0.5);
+        assert_eq!(
+            warnings,
+            vec![CalculatorWarning::SimilarVariable {
+                position: 4,
+                name: "thetta".to_string(),
+                similar: "theta".to_string(),
+            }]
+        );
+        // Variables used more than once or similar variables that are used are no typo
+        let (_, warnings) = calculator
+            .parse_str_with_warnings("thetta * thetta")
+            .unwrap();
+        assert!(warnings.is_empty());
+        let (_, warnings) = calculator
+            .parse_str_with_warnings("theta + thetta")
+            .unwrap();
+        assert!(warnings.is_empty());
+    }
+
+    // Test checking an expression against declared variables without computing it
+    #[test]
+    fn test_check_str() {
+        let calculator = Calculator::new();
+        let declared: HashSet<String> = ["amp", "t", "q_1", "q_2"]
+            .iter()
+            .map(|name| name.to_string())
+            .collect();
+        // Division by zero and domain errors do not fire with unknown values
+        let script = "scale=amp / (t - t); scale += sqrt(-1); max(scale * log(0), sum_vars('q_'))";
+        let report = calculator.check_str(script, &declared).unwrap();
+        assert_eq!(report.variables, vec!["amp", "t", "scale"]);
+        assert_eq!(report.assigned, vec!["scale"]);
+        assert_eq!(
+            report.functions,
+            vec![
+                ("sqrt".to_string(), 1),
+                ("max".to_string(), 2),
+                ("log".to_string(), 1),
+                ("sum_vars".to_string(), 1),
+            ]
+        );
+        assert!(report.warnings.is_empty());
+        // The Calculator is not changed by assignments
+        assert!(calculator.get_variable("scale").is_err());
+        // All variables that are not declared are listed
+        assert_eq!(
+            calculator.check_str("a=1; a + b * c + b + amp", &declared),
+            Err(CalculatorError::VariablesNotSet {
+                names: vec!["b".to_string(), "c".to_string()]
+            })
+        );
+        assert_eq!(
+            calculator.check_str("x += 1", &declared),
+            Err(CalculatorError::VariablesNotSet {
+                names: vec!["x".to_string()]
+            })
+        );
+        // Wrong numbers of arguments and unknown functions are found without evaluation
+        assert_eq!(
+            calculator.check_str("atan2(amp) / 0", &declared),
+            Err(CalculatorError::ParsingError {
+                msg: "expected comma in function arguments",
+                position: Some(9),
+            })
+        );
+        assert_eq!(
+            calculator.check_str("sin(amp, t)", &declared),
+            Err(CalculatorError::ParsingError {
+                msg: "Expected braket close.",
+                position: Some(7),
+            })
+        );
+        assert_eq!(
+            calculator.check_str("sine(t)", &declared),
+            Err(CalculatorError::FunctionNotFound {
+                fct: "sine".to_string()
+            })
+        );
+        assert!(calculator.check_str("assert(0) + (t", &declared).is_err());
+        let report = calculator
+            .check_str("assert(t - t, 'never checked'); 2 * tt", &declared)
+            .unwrap_err();
+        assert_eq!(
+            report,
+            CalculatorError::VariablesNotSet {
+                names: vec!["tt".to_string()]
+            }
+        );
+        let report = calculator
+            .check_str("mean[amp, t, max[q_1, q_2], 1]", &declared)
+            .unwrap();
+        assert_eq!(
+            report.functions,
+            vec![("mean".to_string(), 4), ("max".to_string(), 2)]
+        );
+        let (_, warnings) = calculator.parse_str_with_warnings("max(2,5)").unwrap();
+        assert_eq!(
+            calculator
+                .check_str("max(2,5)", &declared)
+                .unwrap()
+                .warnings,
+            warnings
+        );
+    }
+
+    // Test saving and restoring the variables of a Calculator as JSON
+    #[test]
+    fn test_calculator_json_round_trip() {
+        // Empty Calculator
+        let calculator = Calculator::new();
+        let json = calculator.to_json().unwrap();
+        assert_eq!(json, r#"{"variables":{},"expression_variables":{}}"#);
+        assert!(Calculator::from_json(&json).unwrap().variables.is_empty());
+        assert!(Calculator::from_json("{}").unwrap().variables.is_empty());
+
+        // Variables and variables defined by expressions are written sorted by name
+        let mut calculator = Calculator::new();
+        calculator.set_variable("b", 2.5).unwrap();
+        calculator.set_variable("a", -1e-300).unwrap();
+        calculator
+            .set_expression_variable("c", "2 * a + b")
+            .unwrap();
+        let json = calculator.to_json().unwrap();
+        assert_eq!(
+            json,
+            r#"{"variables":{"a":-1e-300,"b":2.5},"expression_variables":{"c":"2 * a + b"}}"#
+        );
+        let restored = Calculator::from_json(&json).unwrap();
+        assert_eq!(restored.variables, calculator.variables);
+        assert_eq!(restored.get_expression_variable("c"), Some("2 * a + b"));
+        assert_eq!(restored.parse_str("c"), Ok(2.5 - 2e-300));
+
+        // Many variables keep their exact values
+        let mut calculator = Calculator::new();
+        for index in 0..10_000 {
+            calculator
+                .set_variable(&format!("v_{index}"), (index as f64).sqrt() / 7.0)
+                .unwrap();
+        }
+        let restored = Calculator::from_json(&calculator.to_json().unwrap()).unwrap();
+        assert_eq!(restored.variables, calculator.variables);
+
+        // Binary formats
+        let encoded = bincode::serialize(&calculator).unwrap();
+        let restored: Calculator = bincode::deserialize(&encoded).unwrap();
+        assert_eq!(restored.variables, calculator.variables);
+    }
+
+    // Test the errors of saving and restoring a Calculator as JSON
+    #[test]
+    fn test_calculator_json_errors() {
+        let mut calculator = Calculator::new();
+        calculator.set_variable("x", f64::NAN).unwrap();
+        assert_eq!(
+            calculator.to_json(),
+            Err(CalculatorError::SerializationError {
+                msg: "Variable \"x\" is not finite and can not be written to JSON".to_string()
+            })
+        );
+        for json in [
+            "",
+            "[1, 2]",
+            r#"{"variables": {"a": "1"}}"#,
+            r#"{"variables": {}, "tables": {}}"#,
+            r#"{"expression_variables": {"a": "b=1"}}"#,
+        ] {
+            assert!(
+                matches!(
+                    Calculator::from_json(json),
+                    Err(CalculatorError::SerializationError { .. })
+                ),
+                "{json}"
+            );
+        }
+    }
+
+    // Test that parsing errors report the byte position of the token where parsing failed
+    #[test]
+    fn test_parse_error_positions() {
+        let mut calculator = Calculator::new();
+        assert_eq!(
+            calculator.parse_str("1 + 2)"),
+            Err(CalculatorError::UnexpectedToken {
+                token: ")".to_string(),
+                position: 5
+            })
+        );
+        // Whitespace and comments before the error are counted
+        let script = "  # scale of the pulse\n  2 * (3 + ) ";
+        assert_eq!(
+            calculator.parse_str(script),
+            Err(CalculatorError::UnexpectedToken {
+                token: ")".to_string(),
+                position: 34
+            })
+        );
+        assert_eq!(
+            calculator.parse_str_assign("a=1; # unit\nb=a * 1e; b"),
+            Err(CalculatorError::UnrecognizedToken {
+                token: "1e".to_string(),
+                position: 18
+            })
+        );
+        assert_eq!(
+            calculator.parse_str("max(1,   "),
+            Err(CalculatorError::UnexpectedEndOfExpressionAt { position: 9 })
+        );
+        assert_eq!(
+            calculator.parse_str("1; max(2,; 3"),
+            Err(CalculatorError::UnexpectedEndOfExpressionAt { position: 9 })
+        );
+        assert_eq!(
+            calculator.parse_str("(1 + 2 # not closed"),
+            Err(CalculatorError::ParsingError {
+                msg: "Expected Braket close",
+                position: Some(19)
+            })
+        );
+        assert_eq!(
+            calculator.parse_str("1 + atan2(1)"),
+            Err(CalculatorError::ParsingError {
+                msg: "expected comma in function arguments",
+                position: Some(11)
+            })
+        );
+        assert_eq!(
+            calculator.parse_str("   (1 + 2))").unwrap_err().to_string(),
+            "Parsing error: Unexpected token ')' at position 10"
+        );
+    }
+
+    // Test lexing tokens together with their byte ranges
+    #[test]
+    fn test_spanned_token_iterator() {
+        let expression = "a=2 # set a\n; sin(a) *  $";
+        let spans: Vec<(std::ops::Range<usize>, Token)> =
+            SpannedTokenIterator::new(expression).collect();
+        assert_eq!(
+            spans,
+            vec![
+                (0..2, Token::VariableAssign(Cow::Borrowed("a"))),
+                (2..3, Token::Number(2.0)),
+                (12..13, Token::EndOfExpression),
+                (14..18, Token::Function(Cow::Borrowed("sin"))),
+                (18..19, Token::Variable(Cow::Borrowed("a"))),
+                (19..20, Token::BracketClose),
+                (21..22, Token::Multiply),
+                (24..25, Token::Unrecognized),
+            ]
+        );
+        for (span, token) in spans {
+            assert_eq!(
+                TokenIterator {
+                    current_expression: &expression[span]
+                }
+                .next(),
+                Some(token)
+            );
+        }
+    }
+
+    // Test validating an expression before any variables are set
+    #[test]
+    fn test_calculator_validate_expression() {
+        let calculator = Calculator::new();
+        // Unknown variables are valid and returned in the order of their first use
+        assert_eq!(
+            calculator.validate_expression("a * sin(b) + max(a, c, 2)"),
+            Ok(vec!["a".to_string(), "b".to_string(), "c".to_string()])
+        );
+        assert_eq!(
+            calculator.validate_expression("x"),
+            Ok(vec!["x".to_string()])
+        );
+        assert_eq!(calculator.validate_expression("2 * 3"), Ok(vec![]));
+        assert_eq!(
+            calculator.validate_expression("sum_vars('a_') + x"),
+            Ok(vec!["x".to_string()])
+        );
+        // Unbalanced brackets
+        assert_eq!(
+            calculator.validate_expression("a + b)"),
+            Err(CalculatorError::InvalidExpression {
+                position: 5,
+                error: Box::new(CalculatorError::ParsingError {
+                    msg: "Unexpected closing bracket",
+                    position: None,
+                })
+            })
+        );
+        assert!(matches!(
+            calculator.validate_expression("(a + b"),
+            Err(CalculatorError::InvalidExpression { position: 0, .. })
+        ));
+        // Unknown functions and wrong numbers of arguments
+        assert_eq!(
+            calculator.validate_expression("foo(a)"),
+            Err(CalculatorError::FunctionNotFound {
+                fct: "foo".to_string()
+            })
+        );
+        assert_eq!(
+            calculator.validate_expression("sin(a, b)"),
+            Err(CalculatorError::ParsingError {
+                msg: "Expected braket close.",
+                position: Some(5),
+            })
+        );
+        assert_eq!(
+            calculator.validate_expression("atan2(a)"),
+            Err(CalculatorError::ParsingError {
+                msg: "expected comma in function arguments",
+                position: Some(7),
+            })
+        );
+        // Assignments and unrecognized elements are rejected
+        assert!(matches!(
+            calculator.validate_expression("a=2; a"),
+            Err(CalculatorError::InvalidExpression { position: 0, .. })
+        ));
+        assert!(matches!(
+            calculator.validate_expression("a $ b"),
+            Err(CalculatorError::InvalidExpression { position: 2, .. })
+        ));
+        // Invalid expressions give the errors of the free function and of construction
+        for expression in ["a + b)", "(a + b", "max[a, b)", "a=2; a", "a $ b"] {
+            assert_eq!(
+                calculator.validate_expression(expression),
+                Err(validate_expression(expression).unwrap_err())
+            );
+            assert_eq!(
+                expression.parse::<CalculatorFloat>(),
+                Err(validate_expression(expression).unwrap_err())
+            );
+        }
+    }
+
+    // Test that checking an expression is not slower than evaluating it
+    #[test]
+    fn test_check_str_performance() {
+        let mut calculator = Calculator::new();
+        let names = ["a", "b", "c"];
+        for name in names {
+            calculator.set_variable(name, 0.5).unwrap();
+        }
+        let declared: HashSet<String> = names.iter().map(|name| name.to_string()).collect();
+        let expression = "exp(a + 1) * sin(b) + cosh(c + 2) ^ 2 / sqrt(a) + atan2(b, c) * log(a)";
+        let time = |run: &dyn Fn()| {
+            (0..5)
+                .map(|_| {
+                    let start = std::time::Instant::now();
+                    for _ in 0..200 {
+                        run();
+                    }
+                    start.elapsed()
+                })
+                .min()
+                .unwrap()
+        };
+        let parsing = time(&|| {
+            calculator.parse_str(expression).unwrap();
+        });
+        let checking = time(&|| {
+            calculator.check_str(expression, &declared).unwrap();
+        });
+        // Generous bound, the check only has to be in the same range as parsing
+        assert!(checking <= parsing * 2, "{checking:?} {parsing:?}");
+    }
+
+    // Test the warning for number literals that change their value when parsed
+    #[test]
+    fn test_parse_str_with_warnings_precision_loss() {
+        let mut calculator = Calculator::new();
+        let expression = "(0.1000000000000000001 + x)";
+        calculator.set_variable("x", 1.0).unwrap();
+        let (_, warnings) = calculator.parse_str_with_warnings(expression).unwrap();
+        assert!(warnings.is_empty());
+        calculator.warn_on_precision_loss(true);
+        let (value, warnings) = calculator.parse_str_with_warnings(expression).unwrap();
+        assert_eq!(value, 1.1);
+        assert_eq!(
+            warnings,
+            vec![CalculatorWarning::PrecisionLoss {
+                position: 1,
+                literal: "0.1000000000000000001".to_string(),
+                parsed: 0.1,
+            }]
+        );
+        // Shortest representations, exact decimal values and additional zeros round-trip
+        for literal in [
+            "0.1",
+            "1e-1",
+            "1E-1",
+            "0.50000000000000000000",
+            "00.5",
+            "5000e-4",
+            "0.30000000000000004",
+            "0.1000000000000000055511151231257827021181583404541015625",
+            "9007199254740993e0",
+            "0",
+            "0.000e5",
+            "1e308",
+        ] {
+            let (_, warnings) = calculator.parse_str_with_warnings(literal).unwrap();
+            // 2^53 + 1 is not representable, it is the only literal of the list that warns
+            assert_eq!(
+                warnings.is_empty(),
+                literal != "9007199254740993e0",
+                "{literal}: {warnings:?}"
+            );
+        }
+        // Overflow to infinity and underflow to zero change the value
+        for literal in ["1e400", "1e-400", "0.10000000000000001"] {
+            let (_, warnings) = calculator.parse_str_with_warnings(literal).unwrap();
+            assert_eq!(warnings.len(), 1, "{literal}");
+        }
+        let (value, warnings) = calculator
+            .parse_str_assign_with_warnings(
+                "a=2.00000000000000000001; a * 3 #1.00000000000000000001",
+            )
+            .unwrap();
+        assert_eq!(value, 6.0);
+        assert_eq!(
+            warnings,
+            vec![CalculatorWarning::PrecisionLoss {
+                position: 2,
+                literal: "2.00000000000000000001".to_string(),
+                parsed: 2.0,
+            }]
+        );
+    }
+
+    // Test the warning for an assignment that is not read afterwards
+    #[test]
+    fn test_parse_str_assign_with_warnings_unused_assignment() {
+        let mut calculator = Calculator::new();
+        let (value, warnings) = calculator
+            .parse_str_assign_with_warnings("a=1; b=2; a + 1")
+            .unwrap();
+        assert_eq!(value, 2.0);
+        assert_eq!(
+            warnings,
+            vec![CalculatorWarning::UnusedAssignment {
+                position: 5,
+                name: "b".to_string(),
+            }]
+        );
+        // The first assignment is overwritten before it is read
+        let (_, warnings) = calculator
+            .parse_str_assign_with_warnings("a=1; a=2; a")
+            .unwrap();
+        assert_eq!(
+            warnings,
+            vec![CalculatorWarning::UnusedAssignment {
+                position: 0,
+                name: "a".to_string(),
+            }]
+        );
+        let (_, warnings) = calculator
+            .parse_str_assign_with_warnings("a=1; a=a + 1; a")
+            .unwrap();
+        assert!(warnings.is_empty());
+    }
+
+    // Test that failing expressions return the error instead of warnings
+    #[test]
+    fn test_parse_str_with_warnings_error() {
+        let mut calculator = Calculator::new();
+        assert_eq!(
+            calculator.parse_str_with_warnings("max(2,5) / 0"),
+            Err(CalculatorError::DivisionByZero)
+        );
+        assert_eq!(
+            calculator.parse_str_assign_with_warnings("a=1; b=2; 1 / 0"),
+            Err(CalculatorError::DivisionByZero)
+        );
+        assert!(calculator.get_variable("a").is_err());
+    }
+
+    // Test parse_string for a variable Token with an underscore in it
+    #[test]
+    fn test_parse_variable_underscore() {
+        let mut calculator = Calculator::new();
+        let value = calculator.parse_str_assign("a_1=3; 2*(a_1+1);");
+        assert_eq!(value.unwrap(), 8.0);
+        assert_eq!(calculator.get_variable("a_1").unwrap(), 3.0);
+    }
+
+    // Test parse_string for a function Token
+    #[test]
+    fn test_parse_assing_function() {
+        let f: f64 = 4.0;
+        let mut calculator = Calculator::new();
+        let value = calculator.parse_str_assign("a=3; sin(a+1);");
+        assert_eq!(value.unwrap(), f.sin());
+        assert_eq!(calculator.get_variable("a").unwrap(), 3.0);
+
+        let value = calculator.parse_str_assign("atan2(a+1,2e0);");
+        assert_eq!(value.unwrap(), f.atan2(2e0));
+        assert_eq!(calculator.get_variable("a").unwrap(), 3.0);
+    }
+
+    // Test parse_get function
+    #[test]
+    fn test_parse_get() {
+        let calculator = Calculator::new();
+
+        let cf_float = CalculatorFloat::from(3.0);
+        let value_cf_float = calculator.parse_get(cf_float);
+        assert_eq!(value_cf_float.unwrap(), 3.0);
+
+        let cf_string = CalculatorFloat::from("3+0");
+        let value_cf_string = calculator.parse_get(cf_string);
+        assert_eq!(value_cf_string.unwrap(), 3.0);
+    }
+
+    // Test parse_get_ref gives the same results as parse_get
+    #[test]
+    fn test_parse_get_ref() {
+        let mut calculator = Calculator::new();
+        calculator.set_variable("theta", 0.3).unwrap();
+        for value in [
+            CalculatorFloat::from(3.0),
+            CalculatorFloat::from("3+0"),
+            CalculatorFloat::from("sin(theta) / 2"),
+            CalculatorFloat::from("undefined"),
+        ] {
+            assert_eq!(
+                calculator.parse_get_ref(&value),
+                calculator.parse_get(value.clone())
+            );
+        }
+    }
+
+    // Test that all evaluate functions match statements return the expected float/error
+    #[test]
+    fn test_evaluate_functions() {
+        let calculator = Calculator::new();
+
+        // Evaluate unary function: + and -
+        let value = calculator.parse_str("-2");
+        assert_eq!(value.unwrap(), -2.0);
+        let value = calculator.parse_str("+2");
+        assert_eq!(value.unwrap(), 2.0);
+
+        // Evaluate binary3 function: **/^, ! and !!
+        let value = calculator.parse_str("2^3");
+        assert_eq!(value.unwrap(), 8.0);
+        let value = calculator.parse_str("2**3");
+        assert_eq!(value.unwrap(), 8.0);
+        let value = calculator.parse_str("3!");
+        assert!(value.is_err());
+        let value = calculator.parse_str("3!!");
+        assert!(value.is_err());
+
+        // Evaluate binary2 function: * and /
+        let value = calculator.parse_str("2*3");
+        assert_eq!(value.unwrap(), 6.0);
+        let value = calculator.parse_str("3/2");
+        assert_eq!(value.unwrap(), 1.5);
+        let value = calculator.parse_str("3/0");
+        assert!(value.is_err());
+
+        // Evaluate binary1 function: + and -
+        let value = calculator.parse_str("1+1");
+        assert_eq!(value.unwrap(), 2.0);
+        let value = calculator.parse_str("1-1");
+        assert_eq!(value.unwrap(), 0.0);
+
+        // Evaluate initialization function
+        let value = calculator.parse_str(";3");
+        assert!(value.is_err());
+
+        // Evaluate function
+        let value = calculator.parse_str("(3");
+        assert!(value.is_err());
+        let value = calculator.parse_str("(3)");
+        assert_eq!(value.unwrap(), 3.0);
+        let value = calculator.parse_str("3");
+        assert_eq!(value.unwrap(), 3.0);
+        let value = calculator.parse_str("x=3; x+2");
+        assert!(value.is_err());
+        let value = calculator.parse_str("max(3, 2)");
+        assert_eq!(value.unwrap(), 3.0);
+        let value = calculator.parse_str("max(3 2)");
+        assert!(value.is_err());
+        let value = calculator.parse_str("max(3, 2");
+        assert!(value.is_err());
+        let value = calculator.parse_str(")");
+        assert!(value.is_err());
+    }
+
+    // Testing that all functions get matched with the correct nummber of arguments (1 or 2)
+    #[test]
+    fn test_function_argument_numbers() {
+        assert_eq!(
+            function_argument_numbers("sin").unwrap(),
+            ArgumentCount::Fixed(1)
+        );
+        assert_eq!(
+            function_argument_numbers("cos").unwrap(),
+            ArgumentCount::Fixed(1)
+        );
+        assert_eq!(
+            function_argument_numbers("abs").unwrap(),
+            ArgumentCount::Fixed(1)
+        );
+        assert_eq!(
+            function_argument_numbers("tan").unwrap(),
+            ArgumentCount::Fixed(1)
+        );
+        assert_eq!(
+            function_argument_numbers("acos").unwrap(),
+            ArgumentCount::Fixed(1)
+        );
+        assert_eq!(
+            function_argument_numbers("asin").unwrap(),
+            ArgumentCount::Fixed(1)
+        );
+        assert_eq!(
+            function_argument_numbers("atan").unwrap(),
+            ArgumentCount::Fixed(1)
+        );
+        assert_eq!(
+            function_argument_numbers("cosh").unwrap(),
+            ArgumentCount::Fixed(1)
+        );
+        assert_eq!(
+            function_argument_numbers("sinh").unwrap(),
+            ArgumentCount::Fixed(1)
+        );
+        assert_eq!(
+            function_argument_numbers("tanh").unwrap(),
+            ArgumentCount::Fixed(1)
+        );
+        assert_eq!(
+            function_argument_numbers("acosh").unwrap(),
+            ArgumentCount::Fixed(1)
+        );
+        assert_eq!(
+            function_argument_numbers("asinh").unwrap(),
+            ArgumentCount::Fixed(1)
+        );
+        assert_eq!(
+            function_argument_numbers("atanh").unwrap(),
+            ArgumentCount::Fixed(1)
+        );
+        assert_eq!(
+            function_argument_numbers("arcosh").unwrap(),
+            ArgumentCount::Fixed(1)
+        );
+        assert_eq!(
+            function_argument_numbers("arsinh").unwrap(),
+            ArgumentCount::Fixed(1)
+        );
+        assert_eq!(
+            function_argument_numbers("artanh").unwrap(),
+            ArgumentCount::Fixed(1)
+        );
+        assert_eq!(
+            function_argument_numbers("exp").unwrap(),
+            ArgumentCount::Fixed(1)
+        );
+        assert_eq!(
+            function_argument_numbers("exp2").unwrap(),
+            ArgumentCount::Fixed(1)
+        );
+        assert_eq!(
+            function_argument_numbers("expm1").unwrap(),
+            ArgumentCount::Fixed(1)
+        );
+        assert_eq!(
+            function_argument_numbers("log").unwrap(),
+            ArgumentCount::Fixed(1)
+        );
+        assert_eq!(
+            function_argument_numbers("log10").unwrap(),
+            ArgumentCount::Fixed(1)
+        );
+        assert_eq!(
+            function_argument_numbers("log2").unwrap(),
+            ArgumentCount::Fixed(1)
+        );
+        assert_eq!(
+            function_argument_numbers("sqrt").unwrap(),
+            ArgumentCount::Fixed(1)
+        );
+        assert_eq!(
+            function_argument_numbers("cbrt").unwrap(),
+            ArgumentCount::Fixed(1)
+        );
+        assert_eq!(
+            function_argument_numbers("ceil").unwrap(),
+            ArgumentCount::Fixed(1)
+        );
+        assert_eq!(
+            function_argument_numbers("floor").unwrap(),
+            ArgumentCount::Fixed(1)
+        );
+        assert_eq!(
+            function_argument_numbers("fract").unwrap(),
+            ArgumentCount::Fixed(1)
+        );
+        assert_eq!(
+            function_argument_numbers("round").unwrap(),
+            ArgumentCount::Fixed(1)
+        );
+        assert_eq!(
+            function_argument_numbers("erf").unwrap(),
+            ArgumentCount::Fixed(1)
+        );
+        assert_eq!(
+            function_argument_numbers("tgamma").unwrap(),
+            ArgumentCount::Fixed(1)
+        );
+        assert_eq!(
+            function_argument_numbers("lgamma").unwrap(),
+            ArgumentCount::Fixed(1)
+        );
+        assert_eq!(
+            function_argument_numbers("sign").unwrap(),
+            ArgumentCount::Fixed(1)
+        );
+        assert_eq!(
+            function_argument_numbers("delta").unwrap(),
+            ArgumentCount::Fixed(1)
+        );
+        assert_eq!(
+            function_argument_numbers("theta").unwrap(),
+            ArgumentCount::Fixed(1)
+        );
+        assert_eq!(
+            function_argument_numbers("parity").unwrap(),
+            ArgumentCount::Fixed(1)
+        );
+        assert_eq!(
+            function_argument_numbers("factorial").unwrap(),
+            ArgumentCount::Fixed(1)
+        );
+        assert_eq!(
+            function_argument_numbers("gamma").unwrap(),
+            ArgumentCount::Fixed(1)
+        );
+        assert_eq!(
+            function_argument_numbers("binom").unwrap(),
+            ArgumentCount::Fixed(2)
+        );
+        assert_eq!(
+            function_argument_numbers("round_digits").unwrap(),
+            ArgumentCount::Fixed(2)
+        );
+        assert_eq!(
+            function_argument_numbers("round_step").unwrap(),
+            ArgumentCount::Fixed(2)
+        );
+        assert_eq!(
+            function_argument_numbers("atan2").unwrap(),
+            ArgumentCount::Fixed(2)
+        );
+        assert_eq!(
+            function_argument_numbers("hypot").unwrap(),
+            ArgumentCount::Fixed(2)
+        );
+        assert_eq!(
+            function_argument_numbers("pow").unwrap(),
+            ArgumentCount::Fixed(2)
+        );
+        assert_eq!(
+            function_argument_numbers("max").unwrap(),
+            ArgumentCount::Variadic
+        );
+        assert_eq!(
+            function_argument_numbers("min").unwrap(),
+            ArgumentCount::Variadic
+        );
+        assert_eq!(
+            function_argument_numbers("sum").unwrap(),
+            ArgumentCount::Variadic
+        );
+        assert!(function_argument_numbers("test").is_err());
     }
 
-    // Test the next function of the TokenIterator for a bracket (open and close) Token
+    // Testing that all functions with 1 argument get matched with the correct Rust function
     #[test]
-    fn test_brackets() {
-        let mut t_iterator = TokenIterator {
-            current_expression: " (",
-        };
-        assert_eq!(t_iterator.next().unwrap(), Token::BracketOpen);
-        let mut t_iterator = TokenIterator {
-            current_expression: ") ",
-        };
-        assert_eq!(t_iterator.next().unwrap(), Token::BracketClose);
+    fn test_function_1_argument() {
+        let f: f64 = 0.1;
+        // The transcendental functions of std are used unless deterministic-math is enabled
+        #[cfg(not(feature = "deterministic-math"))]
+        {
+            let f1: f64 = 1.5;
+            assert_eq!(function_1_argument("sin", 0.1).unwrap(), f.sin());
+            assert_eq!(function_1_argument("cos", 0.1).unwrap(), f.cos());
+            assert_eq!(function_1_argument("tan", 0.1).unwrap(), f.tan());
+            assert_eq!(function_1_argument("acos", 0.1).unwrap(), f.acos());
+            assert_eq!(function_1_argument("asin", 0.1).unwrap(), f.asin());
+            assert_eq!(function_1_argument("atan", 0.1).unwrap(), f.atan());
+            assert_eq!(function_1_argument("cosh", 0.1).unwrap(), f.cosh());
+            assert_eq!(function_1_argument("sinh", 0.1).unwrap(), f.sinh());
+            assert_eq!(function_1_argument("tanh", 0.1).unwrap(), f.tanh());
+            assert_eq!(function_1_argument("acosh", 1.5).unwrap(), f1.acosh());
+            assert_eq!(function_1_argument("asinh", 0.1).unwrap(), f.asinh());
+            assert_eq!(function_1_argument("atanh", 0.1).unwrap(), f.atanh());
+            assert_eq!(function_1_argument("arcosh", 1.5).unwrap(), f1.acosh());
+            assert_eq!(function_1_argument("arsinh", 0.1).unwrap(), f.asinh());
+            assert_eq!(function_1_argument("artanh", 0.1).unwrap(), f.atanh());
+            assert_eq!(function_1_argument("exp", 0.1).unwrap(), f.exp());
+            assert_eq!(function_1_argument("exp2", 0.1).unwrap(), f.exp2());
+            assert_eq!(function_1_argument("expm1", 0.1).unwrap(), f.exp_m1());
+            assert_eq!(function_1_argument("log", 0.1).unwrap(), f.ln());
+            assert_eq!(function_1_argument("log10", 0.1).unwrap(), f.log10());
+            assert_eq!(function_1_argument("log2", 0.1).unwrap(), f.log2());
+            assert_eq!(function_1_argument("cbrt", 0.1).unwrap(), f.cbrt());
+        }
+        assert_eq!(function_1_argument("abs", 0.1).unwrap(), f.abs());
+        assert_eq!(function_1_argument("sqrt", 0.1).unwrap(), f.sqrt());
+        assert_eq!(function_1_argument("ceil", 0.1).unwrap(), f.ceil());
+        assert_eq!(function_1_argument("floor", 0.1).unwrap(), f.floor());
+        assert_eq!(function_1_argument("fract", 0.1).unwrap(), f.fract());
+        assert_eq!(function_1_argument("round", 0.1).unwrap(), f.round());
+        assert_eq!(function_1_argument("sign", 0.1).unwrap(), f.signum());
+        assert_eq!(function_1_argument("delta", 0.0).unwrap(), 1.0);
+        assert_eq!(function_1_argument("delta", 0.1).unwrap(), 0.0);
+        assert_eq!(function_1_argument("theta", 0.0).unwrap(), 0.5);
+        assert_eq!(function_1_argument("theta", -0.1).unwrap(), 0.0);
+        assert_eq!(function_1_argument("theta", 0.1).unwrap(), 1.0);
+        assert!(function_1_argument("test", 1.0).is_err());
     }
 
-    // Test the next function of the TokenIterator for an assign Token
+    // Testing that all functions with 2 arguments get matched with the correct Rust function
     #[test]
-    fn test_assign() {
-        let mut t_iterator = TokenIterator {
-            current_expression: " =",
-        };
-        assert_eq!(t_iterator.next().unwrap(), Token::Assign);
+    fn test_function_2_argument() {
+        let f: f64 = 0.1;
+        // The transcendental functions of std are used unless deterministic-math is enabled
+        #[cfg(not(feature = "deterministic-math"))]
+        {
+            assert_eq!(
+                function_2_arguments("atan2", 0.1, 0.2).unwrap(),
+                f.atan2(0.2)
+            );
+            assert_eq!(
+                function_2_arguments("hypot", 0.1, 0.2).unwrap(),
+                f.hypot(0.2)
+            );
+            assert_eq!(function_2_arguments("pow", 0.1, 0.2).unwrap(), f.powf(0.2));
+        }
+        assert_eq!(function_2_arguments("max", 0.1, 0.2).unwrap(), f.max(0.2));
+        assert_eq!(function_2_arguments("min", 0.1, 0.2).unwrap(), f.min(0.2));
+        assert!(function_2_arguments("test", 1.0, 1.0).is_err());
     }
 
-    // Test the next function of the TokenIterator for a comma Token
+    // Test chained variables defined by expressions
     #[test]
-    fn test_comma() {
-        let mut t_iterator = TokenIterator {
-            current_expression: ", ",
-        };
-        assert_eq!(t_iterator.next().unwrap(), Token::Comma);
+    fn test_expression_variable_chain() {
+        let mut calculator = Calculator::new();
+        calculator.set_variable("alpha", 2.0).unwrap();
+        calculator
+            .set_expression_variable("beta", "2*alpha")
+            .unwrap();
+        calculator
+            .set_expression_variable("gamma", "beta + alpha")
+            .unwrap();
+        assert_eq!(calculator.parse_str("gamma * 10").unwrap(), 60.0);
+        assert_eq!(
+            calculator.parse_get(CalculatorFloat::from("beta")).unwrap(),
+            4.0
+        );
+        // Definitions are evaluated lazily with the current values
+        calculator.set_variable("alpha", 1.0).unwrap();
+        assert_eq!(calculator.parse_str("gamma").unwrap(), 3.0);
+        // Assignments staged in the same expression are visible in definitions
+        assert_eq!(calculator.parse_str_assign("alpha=3; gamma").unwrap(), 9.0);
+        // get_variable only returns numeric variables
+        assert_eq!(
+            calculator.get_variable("beta"),
+            Err(CalculatorError::VariableNotSet {
+                name: "beta".to_string()
+            })
+        );
+        assert_eq!(calculator.get_expression_variable("beta"), Some("2*alpha"));
+        assert_eq!(calculator.get_expression_variable("alpha"), None);
+        assert_eq!(calculator.expression_variables().len(), 2);
+        let sweep = calculator
+            .sweep("gamma", &[("alpha", vec![1.0, 2.0])])
+            .unwrap();
+        assert_eq!(sweep.values, vec![3.0, 6.0]);
     }
 
-    // Test the next function of the TokenIterator for a semi-colon Token
+    // Test that numeric variables take precedence over variables defined by expressions
     #[test]
-    fn test_semi_colon() {
-        let mut t_iterator = TokenIterator {
-            current_expression: ";",
-        };
-        assert_eq!(t_iterator.next().unwrap(), Token::EndOfExpression);
+    fn test_expression_variable_shadowing() {
+        let mut calculator = Calculator::new();
+        calculator.set_expression_variable("beta", "1 + 1").unwrap();
+        assert_eq!(calculator.parse_str("beta").unwrap(), 2.0);
+        calculator.set_variable("beta", 5.0).unwrap();
+        assert_eq!(calculator.parse_str("beta").unwrap(), 5.0);
+        calculator.variables.remove("beta");
+        assert_eq!(calculator.parse_str_assign("beta=7; beta").unwrap(), 7.0);
+        assert_eq!(calculator.get_expression_variable("beta"), Some("1 + 1"));
     }
 
-    // Test the next function of the TokenIterator for a (double) factorial Token
+    // Test setting and getting several variables at once
     #[test]
-    fn test_factorial() {
-        let mut t_iterator = TokenIterator {
-            current_expression: "!",
-        };
-        assert_eq!(t_iterator.next().unwrap(), Token::Factorial);
-        let mut t_iterator = TokenIterator {
-            current_expression: "!!",
-        };
-        assert_eq!(t_iterator.next().unwrap(), Token::DoubleFactorial);
+    fn test_bulk_variables() {
+        let mut calculator = Calculator::new();
+        calculator
+            .set_variables((0..200).map(|index| (format!("v{index}"), index as f64)))
+            .unwrap();
+        calculator
+            .set_variables(vec![("b".to_string(), 2.0), ("a".to_string(), 1.0)])
+            .unwrap();
+        assert_eq!(calculator.variables.len(), 202);
+        assert_eq!(
+            calculator.get_variables(&["a", "v199", "b"]).unwrap(),
+            vec![1.0, 199.0, 2.0]
+        );
+        assert_eq!(calculator.get_variables(&[]).unwrap(), Vec::<f64>::new());
+        assert_eq!(
+            calculator.get_variables(&["x", "a", "v200", "y"]),
+            Err(CalculatorError::VariablesNotSet {
+                names: vec!["x".to_string(), "v200".to_string(), "y".to_string()]
+            })
+        );
+        let snapshot = calculator.variables_snapshot();
+        assert_eq!(snapshot.len(), 202);
+        let names: Vec<&String> = snapshot.keys().take(4).collect();
+        assert_eq!(names, vec!["a", "b", "v0", "v1"]);
+        assert_eq!(snapshot.get("v42"), Some(&42.0));
     }
 
-    // Test the next function of the TokenIterator for an unrecognised Token
+    // Test calling interpolation tables in expressions
     #[test]
-    fn test_unrecognized() {
-        let mut t_iterator = TokenIterator {
-            current_expression: "|",
-        };
-        assert_eq!(t_iterator.next().unwrap(), Token::Unrecognized);
+    fn test_interpolation_table() {
+        let mut calculator = Calculator::new();
+        calculator
+            .register_interpolation_table("flux", vec![0.0, 1.0, 2.0], vec![0.0, 10.0, 30.0])
+            .unwrap();
+        calculator
+            .register_interpolation_table_with_out_of_range(
+                "strict",
+                vec![0.0, 1.0],
+                vec![1.0, 2.0],
+                OutOfRange::Error,
+            )
+            .unwrap();
+        calculator.set_variable("x", 1.5).unwrap();
+        assert_eq!(calculator.parse_str("flux(x)").unwrap(), 20.0);
+        assert_eq!(calculator.parse_str("flux(1)").unwrap(), 10.0);
+        assert_eq!(calculator.parse_str("2 * flux(0.5) + 1").unwrap(), 11.0);
+        assert_eq!(calculator.parse_str("flux(sin(0) - 1)").unwrap(), 0.0);
+        assert_eq!(calculator.parse_str("flux(5)").unwrap(), 30.0);
+        assert_eq!(calculator.parse_str("strict(0.25)").unwrap(), 1.25);
+        assert_eq!(
+            calculator.parse_str("strict(x)"),
+            Err(CalculatorError::InterpolationOutOfRange {
+                value: 1.5,
+                lower: 0.0,
+                upper: 1.0
+            })
+        );
+        assert!(calculator.parse_str("flux(1, 2)").is_err());
+        assert_eq!(calculator.parse_str_assign("y=flux(2); y").unwrap(), 30.0);
+        calculator
+            .set_expression_variable("z", "flux(x) / 2")
+            .unwrap();
+        assert_eq!(calculator.parse_str("z").unwrap(), 10.0);
+        let sweep = calculator
+            .sweep("flux(t)", &[("t", vec![0.0, 0.5])])
+            .unwrap();
+        assert_eq!(sweep.values, vec![0.0, 5.0]);
+        let clone = calculator.clone();
+        assert_eq!(clone.parse_str("flux(0.1)").unwrap(), 1.0);
+        assert_eq!(
+            Calculator::new().parse_str("flux(1)"),
+            Err(CalculatorError::FunctionNotFound {
+                fct: "flux".to_string()
+            })
+        );
+        let table = calculator.get_interpolation_table("strict").unwrap();
+        assert_eq!(table.xs(), &[0.0, 1.0]);
+        assert_eq!(table.out_of_range(), OutOfRange::Error);
+        assert!(calculator.get_interpolation_table("x").is_none());
     }
 
-    // Test the next function of the TokenIterator for a variable Token
-    #[test]
-    fn test_variable() {
-        let mut t_iterator = TokenIterator {
-            current_expression: "test",
-        };
-        let next_token = t_iterator.next().expect("next token throws error");
-        assert_eq!(next_token, Token::Variable("test".to_owned()));
-        let mut t_iterator = TokenIterator {
-            current_expression: "test;",
-        };
-        let next_token = t_iterator.next().expect("next token throws error");
-        assert_eq!(next_token, Token::Variable("test".to_owned()));
-        let mut t_iterator = TokenIterator {
-            current_expression: "test+",
-        };
-        let next_token = t_iterator.next().expect("next token throws error");
-        assert_eq!(next_token, Token::Variable("test".to_owned()));
+    /// Bessel function of the first kind of integer order n by its power series.
+    fn bessel_j(n: i32, x: f64) -> f64 {
+        let mut term = (x / 2.0).powi(n) / (1..=n).map(f64::from).product::<f64>();
+        let mut sum = term;
+        for k in 1..40 {
+            term *= -(x / 2.0).powi(2) / (f64::from(k) * f64::from(k + n));
+            sum += term;
+        }
+        sum
     }
 
-    // Test the next function of the TokenIterator for a variable assign Token
+    // Test that sum_vars and count_vars do not depend on the order the variables were set in
     #[test]
-    fn test_variable_assign() {
-        let mut t_iterator = TokenIterator {
-            current_expression: "test=",
-        };
-        let next_token = t_iterator.next().expect("next token throws error");
-        assert_eq!(next_token, Token::VariableAssign("test".to_owned()));
-    }
+    fn test_variable_aggregates() {
+        let names = ["a_3", "a_1", "b_1", "a_10", "a_2", "a", "ab"];
+        let values = [0.1, 1e16, 7.0, -1e16, 0.2, 100.0, 1000.0];
+        let mut results: Vec<(f64, f64)> = Vec::new();
+        // Insert the same variables in rotated and reversed orders
+        for rotation in 0..names.len() {
+            for reverse in [false, true] {
+                let mut order: Vec<usize> = (0..names.len()).collect();
+                order.rotate_left(rotation);
+                if reverse {
+                    order.reverse();
+                }
+                let mut calculator = Calculator::new();
+                for index in order {
+                    calculator
+                        .set_variable(names[index], values[index])
+                        .unwrap();
+                }
+                results.push((
+                    calculator.parse_str("sum_vars('a_')").unwrap(),
+                    calculator.parse_str("count_vars('a_')").unwrap(),
+                ));
+            }
+        }
+        // Sum in the order a_1, a_10, a_2, a_3
+        let expected: f64 = ((1e16 + -1e16) + 0.2) + 0.1;
+        for result in results {
+            assert_eq!(result.0.to_bits(), expected.to_bits());
+            assert_eq!(result.1, 4.0);
+        }
 
-    // Test the next function of the TokenIterator for a function Token
-    #[test]
-    fn test_functions() {
-        let mut t_iterator = TokenIterator {
-            current_expression: "test(",
-        };
-        let next_token = t_iterator.next().expect("next token throws error");
-        assert_eq!(next_token, Token::Function("test".to_owned()));
+        let mut calculator = Calculator::new();
+        calculator.set_variable("a_1", 1.0).unwrap();
+        calculator.set_variable("a", 2.0).unwrap();
+        assert_eq!(calculator.parse_str("sum_vars('')").unwrap(), 3.0);
+        assert_eq!(calculator.parse_str("sum_vars('z')").unwrap(), 0.0);
+        assert_eq!(
+            calculator.parse_str("2 * count_vars( 'a' ) + 1").unwrap(),
+            5.0
+        );
+        // Assignments staged in the same script are included
+        assert_eq!(
+            calculator
+                .parse_str_assign("a_2=5; a_1=3; sum_vars('a_')")
+                .unwrap(),
+            8.0
+        );
+        let sweep = calculator
+            .sweep("sum_vars('a_')", &[("a_0", vec![1.0, 2.0])])
+            .unwrap();
+        assert_eq!(sweep.values, vec![9.0, 10.0]);
+        // Variables defined by expressions are not included
+        calculator.set_expression_variable("a_3", "a_1").unwrap();
+        assert_eq!(calculator.parse_str("count_vars('a_')").unwrap(), 2.0);
+        assert_eq!(
+            calculator.parse_str("sum_vars(a_1)"),
+            Err(CalculatorError::ParsingError {
+                msg: "Expected string literal argument.",
+                position: Some(9),
+            })
+        );
+        assert_eq!(
+            calculator.parse_str("sum_vars('a_', 'b_')"),
+            Err(CalculatorError::ParsingError {
+                msg: "Expected braket close.",
+                position: Some(13),
+            })
+        );
+        assert!(calculator.parse_str("'a_'").is_err());
+        assert_eq!(
+            calculator.register_interpolation_table("sum_vars", vec![0.0, 1.0], vec![0.0, 1.0]),
+            Err(CalculatorError::InvalidInterpolationTable {
+                msg: "name is a built-in function"
+            })
+        );
     }
 
-    // Test the default function of Calculator
+    // Test assert statements in immutable and mutable parsing
     #[test]
-    fn test_calculator_new() {
-        let _calculator = Calculator::new();
-    }
+    fn test_assert() {
+        let mut calculator = Calculator::new();
+        calculator.set_variable("t_rise", 1.0).unwrap();
+        calculator.set_variable("t_fall", 2.0).unwrap();
+        calculator.set_variable("t_total", 5.0).unwrap();
+        let script = "assert(theta(t_total - t_rise - t_fall), 'pulse too long'); t_total";
+        assert_eq!(calculator.parse_str(script), Ok(5.0));
+        assert_eq!(calculator.parse_str("assert(-0.5)"), Ok(1.0));
+        calculator.set_variable("t_total", 2.5).unwrap();
+        assert_eq!(
+            calculator.parse_str(script),
+            Err(CalculatorError::AssertionFailed {
+                message: "pulse too long".to_string(),
+                position: 0
+            })
+        );
+        assert_eq!(
+            calculator.parse_str("1;  assert(0)"),
+            Err(CalculatorError::AssertionFailed {
+                message: "assertion failed".to_string(),
+                position: 4
+            })
+        );
+        assert_eq!(
+            calculator
+                .parse_str("assert(0/0 + 0 * t_total)")
+                .unwrap_err(),
+            CalculatorError::DivisionByZero
+        );
+        calculator.set_variable("nan", f64::NAN).unwrap();
+        assert!(matches!(
+            calculator.parse_str("assert(nan)"),
+            Err(CalculatorError::AssertionFailed { .. })
+        ));
 
-    #[test]
-    fn test_calculator_default() {
-        let _calculator = Calculator::default();
-        assert_eq!(_calculator.variables, Calculator::new().variables);
-    }
+        // A failing assert aborts the script and discards all assignments of the script
+        let result = calculator.parse_str_assign("a=1; assert(a - 1, 'a is one'); b=2");
+        assert_eq!(
+            result,
+            Err(CalculatorError::AssertionFailed {
+                message: "a is one".to_string(),
+                position: 5
+            })
+        );
+        assert!(calculator.get_variable("a").is_err());
+        assert!(calculator.get_variable("b").is_err());
+        assert_eq!(
+            calculator.parse_str_assign("a=2; assert(a - 1, 'a is one'); b=2"),
+            Ok(2.0)
+        );
+        assert_eq!(calculator.get_variables(&["a", "b"]), Ok(vec![2.0, 2.0]));
 
-    // Test the Debug macro for Calculator
-    #[test]
-    fn test_calculator_debug() {
-        let mut calculator = Calculator::new();
-        calculator.set_variable("x", 0.1);
+        // Positions of asserts in expression variables refer to the defining expression
+        calculator
+            .set_expression_variable("checked", "a * assert(b - 2)")
+            .unwrap();
         assert_eq!(
-            format!("{calculator:?}"),
-            "Calculator { variables: {\"x\": 0.1} }"
+            calculator.parse_str("1 + checked"),
+            Err(CalculatorError::AssertionFailed {
+                message: "assertion failed".to_string(),
+                position: 4
+            })
+        );
+        assert_eq!(
+            calculator.register_interpolation_table("assert", vec![0.0, 1.0], vec![0.0, 1.0]),
+            Err(CalculatorError::InvalidInterpolationTable {
+                msg: "name is a built-in function"
+            })
         );
     }
 
-    // Test the Clone macro for Calculator
+    // Test including registered libraries in scripts
     #[test]
-    fn test_calculator_clone() {
+    fn test_include_library() {
         let mut calculator = Calculator::new();
-        calculator.set_variable("x", 0.1);
-        let c_clone = calculator.clone();
-        assert_eq!(c_clone.get_variable("x").unwrap(), 0.1);
-        assert_eq!(calculator.variables, c_clone.variables);
-    }
+        calculator
+            .register_library("timing", "t_total=t_rise + t_plateau + t_rise")
+            .unwrap();
+        calculator
+            .register_library("pulse", "include('timing'); area=amp * (t_total - t_rise)")
+            .unwrap();
+        assert_eq!(
+            calculator.get_library("timing"),
+            Some("t_total=t_rise + t_plateau + t_rise")
+        );
 
-    // Test set_variable function
-    #[test]
-    fn test_set_value() {
-        let mut calculator = Calculator::new();
-        calculator.set_variable("test", 0.1);
-        assert_eq!(*calculator.variables.get("test").unwrap(), 0.1);
-    }
+        // Later includes see the assignments of the script and of earlier includes
+        let script = "t_rise=1; t_plateau=3; amp=0.5; include('pulse'); area * 2";
+        assert_eq!(calculator.parse_str_assign(script), Ok(4.0));
+        assert_eq!(
+            calculator.get_variables(&["t_total", "area"]),
+            Ok(vec![5.0, 2.0])
+        );
+        assert_eq!(
+            calculator.parse_str_assign("t_rise=2; include('timing')"),
+            Ok(7.0)
+        );
+        // Variables read before the include are not set yet
+        let mut fresh = Calculator::new();
+        fresh
+            .register_library("timing", "t_total=t_rise + t_plateau + t_rise")
+            .unwrap();
+        assert_eq!(
+            fresh.parse_str_assign("include('timing'); t_rise=1; t_plateau=3"),
+            Err(CalculatorError::LibraryError {
+                library: "timing".to_string(),
+                position: 15,
+                error: Box::new(CalculatorError::VariableNotSet {
+                    name: "t_rise".to_string()
+                })
+            })
+        );
+        assert!(fresh.get_variable("t_plateau").is_err());
 
-    // Test get_variable function (float and error return)
-    #[test]
-    fn test_get_value() {
-        let mut calculator = Calculator::new();
-        calculator.set_variable("test", 0.1);
-        assert_eq!(calculator.get_variable("test").unwrap(), 0.1);
-        assert!(calculator.get_variable("test2").is_err());
-    }
+        // Errors in nested libraries report every library with the position in it
+        calculator
+            .register_library("ratio", "ratio=1 / (t_rise - 2)")
+            .unwrap();
+        calculator
+            .register_library("uses_ratio", "x=1; include('ratio')")
+            .unwrap();
+        assert_eq!(
+            calculator.parse_str_assign("include('uses_ratio')"),
+            Err(CalculatorError::LibraryError {
+                library: "uses_ratio".to_string(),
+                position: 20,
+                error: Box::new(CalculatorError::LibraryError {
+                    library: "ratio".to_string(),
+                    position: 21,
+                    error: Box::new(CalculatorError::DivisionByZero)
+                })
+            })
+        );
+        assert_eq!(
+            calculator.parse_str_assign("include('missing')"),
+            Err(CalculatorError::LibraryNotFound {
+                name: "missing".to_string()
+            })
+        );
 
-    // Test parse_string for a variable Token
-    #[test]
-    fn test_parse_variable() {
-        let calculator = Calculator::new();
-        let value = calculator.parse_str("a=3; 2*(a+1);");
-        assert!(value.is_err());
-        let value = calculator.parse_str("2*(3+1);");
-        assert_eq!(value.unwrap(), 8.0);
+        // Includes are only allowed as statements of mutable scripts
+        assert!(calculator.parse_str("include('timing')").is_err());
+        assert!(calculator
+            .parse_str_assign("1 + include('timing')")
+            .is_err());
+        assert!(calculator.parse_str_assign("include(timing)").is_err());
+        assert_eq!(
+            calculator.register_interpolation_table("include", vec![0.0, 1.0], vec![0.0, 1.0]),
+            Err(CalculatorError::InvalidInterpolationTable {
+                msg: "name is a built-in function"
+            })
+        );
     }
 
-    // Test parse_string for a variable Token
+    // Test cycles, depth and validation of libraries
     #[test]
-    fn test_parse_assign_variable() {
+    fn test_include_cycles() {
         let mut calculator = Calculator::new();
-        let value = calculator.parse_str_assign("a=3; 2*(a+1);");
-        assert_eq!(value.unwrap(), 8.0);
-        assert_eq!(calculator.get_variable("a").unwrap(), 3.0);
-    }
+        // Libraries are checked but not executed, included libraries can be registered later
+        calculator
+            .register_library("a", "x=y + 1; include('b')")
+            .unwrap();
+        assert_eq!(
+            calculator.parse_str_assign("y=1; include('a')"),
+            Err(CalculatorError::LibraryError {
+                library: "a".to_string(),
+                position: 20,
+                error: Box::new(CalculatorError::LibraryNotFound {
+                    name: "b".to_string()
+                })
+            })
+        );
+        assert!(calculator.get_variable("x").is_err());
+        // Cycles through registered libraries are found when registering
+        assert_eq!(
+            calculator.register_library("b", "include('a')"),
+            Err(CalculatorError::RecursiveInclude {
+                chain: vec!["b".to_string(), "a".to_string(), "b".to_string()]
+            })
+        );
+        assert_eq!(
+            calculator.register_library("c", "include('c')"),
+            Err(CalculatorError::RecursiveInclude {
+                chain: vec!["c".to_string(), "c".to_string()]
+            })
+        );
+        assert_eq!(
+            calculator.register_library("d", "x=1; sine(x)"),
+            Err(CalculatorError::LibraryError {
+                library: "d".to_string(),
+                position: 10,
+                error: Box::new(CalculatorError::FunctionNotFound {
+                    fct: "sine".to_string()
+                })
+            })
+        );
+        assert!(calculator.get_library("b").is_none());
+        assert!(calculator.get_library("c").is_none());
+
+        // Nesting up to the maximal depth
+        for level in (1..=MAX_INCLUDE_DEPTH).rev() {
+            let script = if level < MAX_INCLUDE_DEPTH {
+                format!("depth={level}; include('level_{}')", level + 1)
+            } else {
+                format!("depth={level}")
+            };
+            calculator
+                .register_library(&format!("level_{level}"), &script)
+                .unwrap();
+        }
+        assert_eq!(
+            calculator.parse_str_assign("include('level_1')"),
+            Ok(MAX_INCLUDE_DEPTH as f64)
+        );
+        assert_eq!(
+            calculator.register_library("level_0", "include('level_1')"),
+            Err(CalculatorError::IncludeTooDeep {
+                name: format!("level_{MAX_INCLUDE_DEPTH}"),
+                limit: MAX_INCLUDE_DEPTH
+            })
+        );
 
-    // Test parse_string for a variable Token with an underscore in it
-    #[test]
-    fn test_parse_variable_underscore() {
-        let mut calculator = Calculator::new();
-        let value = calculator.parse_str_assign("a_1=3; 2*(a_1+1);");
-        assert_eq!(value.unwrap(), 8.0);
-        assert_eq!(calculator.get_variable("a_1").unwrap(), 3.0);
+        // Checking a script follows the includes
+        let declared: HashSet<String> = ["y".to_string()].into_iter().collect();
+        calculator.register_library("b", "z=x").unwrap();
+        let report = calculator
+            .check_str("include('a'); x + z", &declared)
+            .unwrap();
+        assert_eq!(report.assigned, Vec::<String>::new());
+        assert_eq!(report.variables, vec!["x", "z"]);
+        assert_eq!(
+            calculator.check_str("include('missing')", &declared),
+            Err(CalculatorError::LibraryNotFound {
+                name: "missing".to_string()
+            })
+        );
     }
 
-    // Test parse_string for a function Token
+    // Test evaluating unknown functions with a handler
     #[test]
-    fn test_parse_assing_function() {
-        let f: f64 = 4.0;
+    fn test_unknown_function_handler() {
         let mut calculator = Calculator::new();
-        let value = calculator.parse_str_assign("a=3; sin(a+1);");
-        assert_eq!(value.unwrap(), f.sin());
-        assert_eq!(calculator.get_variable("a").unwrap(), 3.0);
-
-        let value = calculator.parse_str_assign("atan2(a+1,2e0);");
-        assert_eq!(value.unwrap(), f.atan2(2e0));
-        assert_eq!(calculator.get_variable("a").unwrap(), 3.0);
+        calculator
+            .set_unknown_function_handler(Arc::new(|name, arguments| match name {
+                "besselj0" => arguments.first().map(|x| bessel_j(0, *x)),
+                "besselj" => match arguments {
+                    [n, x] => Some(bessel_j(*n as i32, *x)),
+                    _ => None,
+                },
+                "count" => Some(arguments.len() as f64),
+                _ => None,
+            }))
+            .unwrap();
+        calculator.set_variable("x", 2.5).unwrap();
+        let j0 = calculator.parse_str("besselj0(x)").unwrap();
+        assert!((j0 - (-0.048_383_776_468_197_98)).abs() < 1e-12);
+        let j1 = calculator.parse_str("besselj(1, x)").unwrap();
+        assert!((j1 - 0.497_094_102_464_274_2).abs() < 1e-12);
+        assert_eq!(calculator.parse_str("count()").unwrap(), 0.0);
+        assert_eq!(calculator.parse_str("count(1, (2), sin(3))").unwrap(), 3.0);
+        assert_eq!(
+            calculator
+                .parse_str("count(count(1, 2), count(), besselj0(0)) * 2")
+                .unwrap(),
+            6.0
+        );
+        // Built-in functions are evaluated before the handler
+        assert_eq!(calculator.parse_str("max(1, count(1, 2))").unwrap(), 2.0);
+        assert_eq!(
+            calculator.parse_str("besselk(x)"),
+            Err(CalculatorError::FunctionNotFound {
+                fct: "besselk".to_string()
+            })
+        );
+        assert_eq!(
+            calculator.parse_str("besselj(x)"),
+            Err(CalculatorError::FunctionNotFound {
+                fct: "besselj".to_string()
+            })
+        );
+        assert!(calculator.parse_str("count(1 2)").is_err());
+        assert!(calculator.parse_str("count(1,)").is_err());
+        assert!(calculator.parse_str("count(1").is_err());
+        assert_eq!(calculator.parse_str_assign("y=count(1); y").unwrap(), 1.0);
+        calculator.remove_unknown_function_handler().unwrap();
+        assert_eq!(
+            calculator.parse_str("count(1)"),
+            Err(CalculatorError::FunctionNotFound {
+                fct: "count".to_string()
+            })
+        );
     }
 
-    // Test parse_get function
+    // Test errors when registering interpolation tables
     #[test]
-    fn test_parse_get() {
-        let calculator = Calculator::new();
-
-        let cf_float = CalculatorFloat::from(3.0);
-        let value_cf_float = calculator.parse_get(cf_float);
-        assert_eq!(value_cf_float.unwrap(), 3.0);
-
-        let cf_string = CalculatorFloat::from("3+0");
-        let value_cf_string = calculator.parse_get(cf_string);
-        assert_eq!(value_cf_string.unwrap(), 3.0);
+    fn test_interpolation_table_errors() {
+        let mut calculator = Calculator::new();
+        let register = |calculator: &mut Calculator, name: &str, xs: Vec<f64>, ys: Vec<f64>| {
+            calculator.register_interpolation_table(name, xs, ys)
+        };
+        for (name, msg) in [
+            ("sin", "name is a built-in function"),
+            ("2x", "name is not a valid function name"),
+            ("a b", "name is not a valid function name"),
+            ("", "name is not a valid function name"),
+        ] {
+            assert_eq!(
+                register(&mut calculator, name, vec![0.0, 1.0], vec![0.0, 1.0]),
+                Err(CalculatorError::InvalidInterpolationTable { msg })
+            );
+        }
+        assert_eq!(
+            register(&mut calculator, "t", vec![1.0, 0.0], vec![0.0, 1.0]),
+            Err(CalculatorError::InvalidInterpolationTable {
+                msg: "x values must be strictly increasing"
+            })
+        );
+        assert_eq!(
+            register(&mut calculator, "t", vec![0.0], vec![0.0]),
+            Err(CalculatorError::InvalidInterpolationTable {
+                msg: "at least two points are needed"
+            })
+        );
+        assert_eq!(
+            register(&mut calculator, "t", vec![0.0, 1.0], vec![0.0]),
+            Err(CalculatorError::InvalidInterpolationTable {
+                msg: "x and y values have different lengths"
+            })
+        );
+        assert!(calculator.get_interpolation_table("t").is_none());
     }
 
-    // Test that all evaluate functions match statements return the expected float/error
+    // Test errors of variables defined by expressions
     #[test]
-    fn test_evaluate_functions() {
-        let calculator = Calculator::new();
-
-        // Evaluate unary function: + and -
-        let value = calculator.parse_str("-2");
-        assert_eq!(value.unwrap(), -2.0);
-        let value = calculator.parse_str("+2");
-        assert_eq!(value.unwrap(), 2.0);
-
-        // Evaluate binary3 function: **/^, ! and !!
-        let value = calculator.parse_str("2^3");
-        assert_eq!(value.unwrap(), 8.0);
-        let value = calculator.parse_str("2**3");
-        assert_eq!(value.unwrap(), 8.0);
-        let value = calculator.parse_str("3!");
-        assert!(value.is_err());
-        let value = calculator.parse_str("3!!");
-        assert!(value.is_err());
-
-        // Evaluate binary2 function: * and /
-        let value = calculator.parse_str("2*3");
-        assert_eq!(value.unwrap(), 6.0);
-        let value = calculator.parse_str("3/2");
-        assert_eq!(value.unwrap(), 1.5);
-        let value = calculator.parse_str("3/0");
-        assert!(value.is_err());
-
-        // Evaluate binary1 function: + and -
-        let value = calculator.parse_str("1+1");
-        assert_eq!(value.unwrap(), 2.0);
-        let value = calculator.parse_str("1-1");
-        assert_eq!(value.unwrap(), 0.0);
-
-        // Evaluate initialization function
-        let value = calculator.parse_str(";3");
-        assert!(value.is_err());
-
-        // Evaluate function
-        let value = calculator.parse_str("(3");
-        assert!(value.is_err());
-        let value = calculator.parse_str("(3)");
-        assert_eq!(value.unwrap(), 3.0);
-        let value = calculator.parse_str("3");
-        assert_eq!(value.unwrap(), 3.0);
-        let value = calculator.parse_str("x=3; x+2");
-        assert!(value.is_err());
-        let value = calculator.parse_str("max(3, 2)");
-        assert_eq!(value.unwrap(), 3.0);
-        let value = calculator.parse_str("max(3 2)");
-        assert!(value.is_err());
-        let value = calculator.parse_str("max(3, 2");
-        assert!(value.is_err());
-        let value = calculator.parse_str(")");
-        assert!(value.is_err());
+    fn test_expression_variable_errors() {
+        let mut calculator = Calculator::new();
+        assert!(calculator.set_expression_variable("a", "b=1").is_err());
+        assert!(calculator.set_expression_variable("a", "(b").is_err());
+        assert_eq!(calculator.get_expression_variable("a"), None);
+        calculator.set_expression_variable("a", "b + 1").unwrap();
+        assert_eq!(
+            calculator.parse_str("a"),
+            Err(CalculatorError::VariableNotSet {
+                name: "b".to_string()
+            })
+        );
+        calculator.set_expression_variable("b", "2 * c").unwrap();
+        calculator.set_expression_variable("c", "a").unwrap();
+        assert_eq!(
+            calculator.parse_str("1 + a"),
+            Err(CalculatorError::RecursiveExpressionVariable {
+                chain: vec![
+                    "a".to_string(),
+                    "b".to_string(),
+                    "c".to_string(),
+                    "a".to_string()
+                ]
+            })
+        );
+        calculator.set_expression_variable("d", "d").unwrap();
+        assert_eq!(
+            calculator.parse_str("d").unwrap_err().to_string(),
+            "Variable defined by expression depends on itself: d -> d"
+        );
+        for level in 0..MAX_EXPRESSION_VARIABLE_DEPTH + 1 {
+            calculator
+                .set_expression_variable(&format!("x{level}"), &format!("x{} + 1", level + 1))
+                .unwrap();
+        }
+        calculator
+            .set_variable(&format!("x{}", MAX_EXPRESSION_VARIABLE_DEPTH + 1), 0.0)
+            .unwrap();
+        assert_eq!(
+            calculator.parse_str("x1").unwrap(),
+            MAX_EXPRESSION_VARIABLE_DEPTH as f64
+        );
+        assert_eq!(
+            calculator.parse_str("x0"),
+            Err(CalculatorError::ExpressionVariableTooDeep {
+                name: format!("x{MAX_EXPRESSION_VARIABLE_DEPTH}"),
+                limit: MAX_EXPRESSION_VARIABLE_DEPTH
+            })
+        );
     }
 
-    // Testing that all functions get matched with the correct nummber of arguments (1 or 2)
+    // Test that the function table is sorted, as required by the binary search
     #[test]
-    fn test_function_argument_numbers() {
-        assert_eq!(function_argument_numbers("sin").unwrap(), 1);
-        assert_eq!(function_argument_numbers("cos").unwrap(), 1);
-        assert_eq!(function_argument_numbers("abs").unwrap(), 1);
-        assert_eq!(function_argument_numbers("tan").unwrap(), 1);
-        assert_eq!(function_argument_numbers("acos").unwrap(), 1);
-        assert_eq!(function_argument_numbers("asin").unwrap(), 1);
-        assert_eq!(function_argument_numbers("atan").unwrap(), 1);
-        assert_eq!(function_argument_numbers("cosh").unwrap(), 1);
-        assert_eq!(function_argument_numbers("sinh").unwrap(), 1);
-        assert_eq!(function_argument_numbers("tanh").unwrap(), 1);
-        assert_eq!(function_argument_numbers("acosh").unwrap(), 1);
-        assert_eq!(function_argument_numbers("asinh").unwrap(), 1);
-        assert_eq!(function_argument_numbers("atanh").unwrap(), 1);
-        assert_eq!(function_argument_numbers("arcosh").unwrap(), 1);
-        assert_eq!(function_argument_numbers("arsinh").unwrap(), 1);
-        assert_eq!(function_argument_numbers("artanh").unwrap(), 1);
-        assert_eq!(function_argument_numbers("exp").unwrap(), 1);
-        assert_eq!(function_argument_numbers("exp2").unwrap(), 1);
-        assert_eq!(function_argument_numbers("expm1").unwrap(), 1);
-        assert_eq!(function_argument_numbers("log").unwrap(), 1);
-        assert_eq!(function_argument_numbers("log10").unwrap(), 1);
-        assert_eq!(function_argument_numbers("sqrt").unwrap(), 1);
-        assert_eq!(function_argument_numbers("cbrt").unwrap(), 1);
-        assert_eq!(function_argument_numbers("ceil").unwrap(), 1);
-        assert_eq!(function_argument_numbers("floor").unwrap(), 1);
-        assert_eq!(function_argument_numbers("fract").unwrap(), 1);
-        assert_eq!(function_argument_numbers("round").unwrap(), 1);
-        assert_eq!(function_argument_numbers("erf").unwrap(), 1);
-        assert_eq!(function_argument_numbers("tgamma").unwrap(), 1);
-        assert_eq!(function_argument_numbers("lgamma").unwrap(), 1);
-        assert_eq!(function_argument_numbers("sign").unwrap(), 1);
-        assert_eq!(function_argument_numbers("delta").unwrap(), 1);
-        assert_eq!(function_argument_numbers("theta").unwrap(), 1);
-        assert_eq!(function_argument_numbers("parity").unwrap(), 1);
-        assert_eq!(function_argument_numbers("atan2").unwrap(), 2);
-        assert_eq!(function_argument_numbers("hypot").unwrap(), 2);
-        assert_eq!(function_argument_numbers("pow").unwrap(), 2);
-        assert_eq!(function_argument_numbers("max").unwrap(), 2);
-        assert_eq!(function_argument_numbers("min").unwrap(), 2);
-        assert!(function_argument_numbers("test").is_err());
+    fn test_function_table_sorted() {
+        for window in FUNCTIONS.windows(2) {
+            assert!(
+                window[0].0 < window[1].0,
+                "{} >= {}",
+                window[0].0,
+                window[1].0
+            );
+        }
     }
 
-    // Testing that all functions with 1 argument get matched with the correct Rust function
+    // Test that every entry of the function table is reachable through parse_str
     #[test]
-    fn test_function_1_argument() {
-        let f: f64 = 0.1;
-        let f1: f64 = 1.5;
-        assert_eq!(function_1_argument("sin", 0.1).unwrap(), f.sin());
-        assert_eq!(function_1_argument("cos", 0.1).unwrap(), f.cos());
-        assert_eq!(function_1_argument("abs", 0.1).unwrap(), f.abs());
-        assert_eq!(function_1_argument("tan", 0.1).unwrap(), f.tan());
-        assert_eq!(function_1_argument("acos", 0.1).unwrap(), f.acos());
-        assert_eq!(function_1_argument("asin", 0.1).unwrap(), f.asin());
-        assert_eq!(function_1_argument("atan", 0.1).unwrap(), f.atan());
-        assert_eq!(function_1_argument("cosh", 0.1).unwrap(), f.cosh());
-        assert_eq!(function_1_argument("sinh", 0.1).unwrap(), f.sinh());
-        assert_eq!(function_1_argument("tanh", 0.1).unwrap(), f.tanh());
-        assert_eq!(function_1_argument("acosh", 1.5).unwrap(), f1.acosh());
-        assert_eq!(function_1_argument("asinh", 0.1).unwrap(), f.asinh());
-        assert_eq!(function_1_argument("atanh", 0.1).unwrap(), f.atanh());
-        assert_eq!(function_1_argument("arcosh", 1.5).unwrap(), f1.acosh());
-        assert_eq!(function_1_argument("arsinh", 0.1).unwrap(), f.asinh());
-        assert_eq!(function_1_argument("artanh", 0.1).unwrap(), f.atanh());
-        assert_eq!(function_1_argument("exp", 0.1).unwrap(), f.exp());
-        assert_eq!(function_1_argument("exp2", 0.1).unwrap(), f.exp2());
-        assert_eq!(function_1_argument("expm1", 0.1).unwrap(), f.exp_m1());
-        assert_eq!(function_1_argument("log", 0.1).unwrap(), f.ln());
-        assert_eq!(function_1_argument("log10", 0.1).unwrap(), f.log10());
-        assert_eq!(function_1_argument("sqrt", 0.1).unwrap(), f.sqrt());
-        assert_eq!(function_1_argument("cbrt", 0.1).unwrap(), f.cbrt());
-        assert_eq!(function_1_argument("ceil", 0.1).unwrap(), f.ceil());
-        assert_eq!(function_1_argument("floor", 0.1).unwrap(), f.floor());
-        assert_eq!(function_1_argument("fract", 0.1).unwrap(), f.fract());
-        assert_eq!(function_1_argument("round", 0.1).unwrap(), f.round());
-        assert_eq!(function_1_argument("sign", 0.1).unwrap(), f.signum());
-        assert_eq!(function_1_argument("delta", 0.0).unwrap(), 1.0);
-        assert_eq!(function_1_argument("delta", 0.1).unwrap(), 0.0);
-        assert_eq!(function_1_argument("theta", 0.0).unwrap(), 0.5);
-        assert_eq!(function_1_argument("theta", -0.1).unwrap(), 0.0);
-        assert_eq!(function_1_argument("theta", 0.1).unwrap(), 1.0);
-        assert!(function_1_argument("test", 1.0).is_err());
+    fn test_function_table_reachable() {
+        let calculator = Calculator::new();
+        for (name, function, _) in FUNCTIONS.iter() {
+            let (expression, expected) = match function {
+                FunctionImplementation::Unary(f) => (format!("{name}(0.5)"), Ok(f(0.5))),
+                FunctionImplementation::Binary(f) => {
+                    (format!("{name}(0.5, 0.25)"), Ok(f(0.5, 0.25)))
+                }
+                FunctionImplementation::FallibleUnary(f) => (format!("{name}(0.5)"), f(0.5)),
+                FunctionImplementation::FallibleBinary(f) => {
+                    (format!("{name}(0.5, 0.25)"), f(0.5, 0.25))
+                }
+                FunctionImplementation::Variadic(f) => {
+                    (format!("{name}(0.5, 0.25, 2)"), Ok(f(f(0.5, 0.25), 2.0)))
+                }
+            };
+            match (calculator.parse_str(&expression), expected) {
+                (Ok(value), Ok(expected)) => assert!(
+                    value == expected || (value.is_nan() && expected.is_nan()),
+                    "{expression}: {value} != {expected}"
+                ),
+                (Err(error), Err(expected)) => assert_eq!(error, expected),
+                (result, expected) => panic!("{expression}: {result:?} != {expected:?}"),
+            }
+        }
     }
 
-    // Testing that all functions with 2 arguments get matched with the correct Rust function
+    // Test that the lexer borrows names from the expression and into_owned copies them
     #[test]
-    fn test_function_2_argument() {
-        let f: f64 = 0.1;
-        assert_eq!(
-            function_2_arguments("atan2", 0.1, 0.2).unwrap(),
-            f.atan2(0.2)
-        );
-        assert_eq!(
-            function_2_arguments("hypot", 0.1, 0.2).unwrap(),
-            f.hypot(0.2)
-        );
-        assert_eq!(function_2_arguments("pow", 0.1, 0.2).unwrap(), f.powf(0.2));
-        assert_eq!(function_2_arguments("max", 0.1, 0.2).unwrap(), f.max(0.2));
-        assert_eq!(function_2_arguments("min", 0.1, 0.2).unwrap(), f.min(0.2));
-        assert!(function_2_arguments("test", 1.0, 1.0).is_err());
+    fn token_borrows_expression() {
+        let mut t_iterator = TokenIterator {
+            current_expression: "alpha=sin(beta)",
+        };
+        let tokens: Vec<Token> = t_iterator.by_ref().collect();
+        for token in tokens.iter() {
+            if let Token::VariableAssign(name) | Token::Function(name) | Token::Variable(name) =
+                token
+            {
+                assert!(matches!(name, Cow::Borrowed(_)));
+            }
+        }
+        let owned: Vec<Token<'static>> = tokens.iter().cloned().map(Token::into_owned).collect();
+        assert_eq!(owned, tokens);
+        assert!(matches!(owned[1], Token::Function(Cow::Owned(_))));
     }
 
     // Testing display function for all possible inputs
@@ -1358,15 +6834,19 @@ mod tests {
         let f_formatted = format!("{f}");
         assert_eq!(f_formatted, "Token::Number(1e-1)");
 
-        let f = Token::VariableAssign(String::from("x"));
+        let f = Token::VariableAssign("x".into());
         let f_formatted = format!("{f}");
         assert_eq!(f_formatted, "Token::VariableAssign(x)");
 
-        let f = Token::Variable(String::from("3t"));
+        let f = Token::CompoundAssign("x".into(), CompoundOperator::Divide);
+        let f_formatted = format!("{f}");
+        assert_eq!(f_formatted, "Token::CompoundAssign(x, Divide)");
+
+        let f = Token::Variable("3t".into());
         let f_formatted = format!("{f}");
         assert_eq!(f_formatted, "Token::Variable(3t)");
 
-        let f = Token::Function(String::from("2s"));
+        let f = Token::Function("2s".into());
         let f_formatted = format!("{f}");
         assert_eq!(f_formatted, "Token::Function(2s)");
 
@@ -1386,6 +6866,10 @@ mod tests {
         let f_formatted = format!("{f}");
         assert_eq!(f_formatted, "Token::Divide");
 
+        let f = Token::Modulo;
+        let f_formatted = format!("{f}");
+        assert_eq!(f_formatted, "Token::Modulo");
+
         let f = Token::Power;
         let f_formatted = format!("{f}");
         assert_eq!(f_formatted, "Token::Power");
@@ -1426,5 +6910,394 @@ mod tests {
         let f_formatted = format!("{f}");
         assert_eq!(f_formatted, "Token::Unrecognized");
     }
+
+    // Test that repeated parenthesized subexpressions without variables are reused within one parse
+    #[test]
+    fn constant_subexpressions_reused() {
+        let script =
+            "a=(2 * 3) + 1; b=((2 * 3) + sin(0.5)) * (2 * 3); c=(sin(0.5)) + (2 * 3) ^ 2 + (sin(0.5)); a + b + c";
+        let compiled = CompiledExpression::new(script);
+        let repeated: Vec<(&str, usize)> = compiled
+            .repeated_constants()
+            .iter()
+            .map(|constant| {
+                let start = compiled.positions()[constant.open];
+                let end = compiled.positions()[constant.close] + 1;
+                (&script[start..end], constant.slot)
+            })
+            .collect();
+        assert_eq!(
+            repeated,
+            vec![
+                ("(2 * 3)", 0),
+                ("(2 * 3)", 0),
+                ("(2 * 3)", 0),
+                ("(sin(0.5))", 1),
+                ("(2 * 3)", 0),
+                ("(sin(0.5))", 1)
+            ]
+        );
+        let mut calculator = Calculator::new();
+        let mut parser = Parser::new(
+            &compiled,
+            StagedCalculator {
+                calculator: &mut calculator,
+                staged_variables: HashMap::new(),
+            },
+        );
+        let value = parser.evaluate_all_tokens().unwrap().unwrap();
+        assert_eq!(parser.constants, vec![Some(6.0), Some(0.5_f64.sin())]);
+        let sine = 0.5_f64.sin();
+        let expected = 7.0 + (6.0 + sine) * 6.0 + 2.0 * sine + 36.0;
+        assert!((value - expected).abs() < 1e-12);
+        assert_eq!(calculator.parse_str_assign(script).unwrap(), value);
+    }
+
+    // Test that subexpressions depending on variables are never reused
+    #[test]
+    fn variable_subexpressions_not_reused() {
+        let mut calculator = Calculator::new();
+        calculator
+            .register_interpolation_table("profile", vec![0.0, 1.0], vec![0.0, 2.0])
+            .unwrap();
+        for (script, expected) in [
+            ("a=1; b=(a + 1); a=5; b + (a + 1)", 8.0),
+            (
+                "a_1=1; s=(sum_vars('a_') + 0); a_2=2; s + (sum_vars('a_') + 0)",
+                4.0,
+            ),
+            ("x=2; (x * 3) + (x * 3)", 12.0),
+            ("y=1; y += (y + 1); (y + 1)", 4.0),
+            ("(profile(0.5)) + (profile(0.5))", 2.0),
+        ] {
+            assert!(
+                CompiledExpression::new(script)
+                    .repeated_constants()
+                    .is_empty(),
+                "{script}"
+            );
+            assert_eq!(calculator.parse_str_assign(script).unwrap(), expected);
+        }
+        // Assignments in brackets are evaluated every time
+        assert_eq!(
+            calculator
+                .parse_str_assign("z=(2) + 1; (z=3) + (2) + (z=3)")
+                .unwrap(),
+            8.0
+        );
+        // Failed evaluations are not reused
+        assert_eq!(
+            calculator.parse_str("(1 / 0) + (1 / 0)"),
+            Err(CalculatorError::DivisionByZero)
+        );
+        // Derivatives of reused subexpressions are zero
+        calculator.set_variable("w", 2.0).unwrap();
+        assert_eq!(
+            calculator
+                .parse_str_derivative("(2 * 3) * w + (2 * 3) * w", "w")
+                .unwrap(),
+            (24.0, 12.0)
+        );
+    }
+
+    // Test max, min and sum with two or more arguments in round brackets
+    #[test]
+    fn variadic_functions() {
+        let mut calculator = Calculator::new();
+        calculator.set_variable("x", 2.0).unwrap();
+        for (expression, expected) in [
+            ("max(1, 2, 3)", 3.0),
+            ("max(3, 2, 1)", 3.0),
+            ("min(4, x, 3)", 2.0),
+            ("sum(1, 2, 3, 4)", 10.0),
+            ("sum(0.1, 0.2, 0.3)", 0.1 + 0.2 + 0.3),
+            ("max(1, min(2, 3), 4)", 4.0),
+            ("max(1, min(5, 3, x + 2), -4)", 3.0),
+            ("sum(max(1, 2), min(3, 4, 5), x) * 2", 14.0),
+            ("max(1, 2)", 2.0),
+        ] {
+            assert_eq!(
+                calculator.parse_str(expression),
+                Ok(expected),
+                "{expression}"
+            );
+        }
+        for expression in ["max()", "min(1)", "sum(x)", "max((1 + 2))"] {
+            assert_eq!(
+                calculator.parse_str(expression),
+                Err(CalculatorError::NotEnoughFunctionArguments),
+                "{expression}"
+            );
+        }
+        assert_eq!(
+            calculator.parse_str("max(1, 2 3)"),
+            Err(CalculatorError::ParsingError {
+                msg: "Expected braket close.",
+                position: Some(9),
+            })
+        );
+        // Derivatives follow the combined two-argument functions
+        assert_eq!(
+            calculator.parse_str_derivative("sum(x, x^2, 1) + max(0, x, 1)", "x"),
+            Ok((9.0, 6.0))
+        );
+        let report = calculator
+            .check_str(
+                "max(a, b, c) + sum(a, b)",
+                &HashSet::from(["a", "b", "c"].map(String::from)),
+            )
+            .unwrap();
+        assert_eq!(
+            report.functions,
+            vec![("max".to_string(), 3), ("sum".to_string(), 2)]
+        );
+        assert_eq!(
+            CalculatorFloat::from("sum(x, x^2, 3)")
+                .derivative("x")
+                .and_then(|derivative| calculator.parse_get(derivative)),
+            Ok(5.0)
+        );
+    }
+
+    // Test that a frozen Calculator rejects every change and keeps evaluating
+    #[test]
+    fn freeze_rejects_changes() {
+        let mut calculator = Calculator::new();
+        calculator.set_variable("a", 2.0).unwrap();
+        calculator.set_expression_variable("b", "a * 3").unwrap();
+        calculator
+            .register_interpolation_table("flux", vec![0.0, 1.0], vec![0.0, 2.0])
+            .unwrap();
+        calculator.register_library("lib", "c=a + 1").unwrap();
+        assert!(!calculator.is_frozen());
+        calculator.freeze();
+        assert!(calculator.is_frozen());
+
+        let frozen = |operation| Err(CalculatorError::CalculatorFrozen { operation });
+        assert_eq!(calculator.set_variable("a", 5.0), frozen("set_variable"));
+        assert_eq!(
+            calculator.set_variables([("d".to_string(), 1.0)]),
+            frozen("set_variables")
+        );
+        assert_eq!(
+            calculator.remove_variable("a"),
+            Err(CalculatorError::CalculatorFrozen {
+                operation: "remove_variable"
+            })
+        );
+        assert_eq!(
+            calculator.parse_str_assign("a=5"),
+            Err(CalculatorError::CalculatorFrozen {
+                operation: "parse_str_assign"
+            })
+        );
+        assert_eq!(
+            calculator.parse_str_assign("a + 1"),
+            Err(CalculatorError::CalculatorFrozen {
+                operation: "parse_str_assign"
+            })
+        );
+        assert!(matches!(
+            calculator.parse_str_assign_with_warnings("a=5"),
+            Err(CalculatorError::CalculatorFrozen { .. })
+        ));
+        assert_eq!(
+            calculator.set_expression_variable("b", "a"),
+            frozen("set_expression_variable")
+        );
+        assert_eq!(
+            calculator.register_interpolation_table("flux", vec![0.0, 1.0], vec![0.0, 1.0]),
+            frozen("register_interpolation_table")
+        );
+        assert_eq!(
+            calculator.register_interpolation_table_with_out_of_range(
+                "flux",
+                vec![0.0, 1.0],
+                vec![0.0, 1.0],
+                OutOfRange::Error
+            ),
+            frozen("register_interpolation_table")
+        );
+        assert_eq!(
+            calculator.register_library("lib", "c=0"),
+            frozen("register_library")
+        );
+        assert_eq!(
+            calculator.set_unknown_function_handler(Arc::new(|_, _| Some(1.0))),
+            frozen("set_unknown_function_handler")
+        );
+        assert_eq!(
+            calculator.remove_unknown_function_handler(),
+            frozen("remove_unknown_function_handler")
+        );
+        #[cfg(feature = "physical-constants")]
+        assert!(matches!(
+            calculator.load_physical_constants(),
+            Err(CalculatorError::CalculatorFrozen { .. })
+        ));
+
+        // Nothing has been changed
+        assert_eq!(calculator.variables_snapshot().len(), 1);
+        assert_eq!(calculator.get_variable("a").unwrap(), 2.0);
+        assert_eq!(calculator.get_expression_variable("b"), Some("a * 3"));
+        assert_eq!(calculator.get_library("lib"), Some("c=a + 1"));
+        assert_eq!(calculator.parse_str("flux(1)").unwrap(), 2.0);
+        assert_eq!(
+            calculator.parse_str("unknown(1)"),
+            Err(CalculatorError::FunctionNotFound {
+                fct: "unknown".to_string()
+            })
+        );
+
+        // Evaluating without assignments is not affected
+        assert_eq!(calculator.parse_str("a + b + flux(0.5)").unwrap(), 9.0);
+        assert_eq!(
+            calculator
+                .parse_get(CalculatorFloat::from("a * 2"))
+                .unwrap(),
+            4.0
+        );
+        assert_eq!(
+            calculator.parse_str_derivative("a * x", "a"),
+            Err(CalculatorError::VariableNotSet {
+                name: "x".to_string()
+            })
+        );
+        assert_eq!(
+            calculator.parse_str_derivative("a^2", "a").unwrap(),
+            (4.0, 4.0)
+        );
+        assert_eq!(calculator.parse_str_with_warnings("a").unwrap().0, 2.0);
+        assert!(calculator
+            .check_str("a=1; a + e", &HashSet::from(["e".to_string()]))
+            .is_ok());
+        assert_eq!(
+            calculator
+                .sweep("a * x", &[("x", vec![1.0, 2.0])])
+                .unwrap()
+                .values,
+            vec![2.0, 4.0]
+        );
+
+        calculator.unfreeze();
+        assert!(!calculator.is_frozen());
+        calculator.set_variable("a", 5.0).unwrap();
+        assert_eq!(calculator.parse_str_assign("c=a; c").unwrap(), 5.0);
+        assert_eq!(calculator.remove_variable("c"), Ok(Some(5.0)));
+        assert_eq!(calculator.remove_variable("c"), Ok(None));
+    }
+
+    // Test that clones of a frozen Calculator are frozen and can be unfrozen independently
+    #[test]
+    fn freeze_clone() {
+        let mut calculator = Calculator::new();
+        calculator.set_variable("a", 1.0).unwrap();
+        let mut unfrozen_clone = calculator.clone();
+        calculator.freeze();
+        assert!(!unfrozen_clone.is_frozen());
+        unfrozen_clone.set_variable("a", 2.0).unwrap();
+
+        let mut frozen_clone = calculator.clone();
+        assert!(frozen_clone.is_frozen());
+        assert!(frozen_clone.set_variable("a", 3.0).is_err());
+        frozen_clone.unfreeze();
+        frozen_clone.set_variable("a", 3.0).unwrap();
+        assert!(calculator.is_frozen());
+        assert_eq!(calculator.get_variable("a").unwrap(), 1.0);
+        assert_eq!(frozen_clone.get_variable("a").unwrap(), 3.0);
+    }
+
+    /// Return a Calculator with an observer recording all events.
+    fn recorded_calculator() -> (Calculator, Arc<Mutex<Vec<EvaluationEvent>>>) {
+        let events: Arc<Mutex<Vec<EvaluationEvent>>> = Arc::new(Mutex::new(Vec::new()));
+        let recorder = events.clone();
+        let mut calculator = Calculator::new();
+        calculator.set_observer(Arc::new(move |event: &EvaluationEvent| {
+            recorder.lock().unwrap().push(event.clone())
+        }));
+        (calculator, events)
+    }
+
+    // Test that the observer records successful and failing evaluations
+    #[test]
+    fn observer_records_events() {
+        let (mut calculator, events) = recorded_calculator();
+        calculator.set_variable("a", 2.0).unwrap();
+        assert_eq!(calculator.parse_str("a * 3"), Ok(6.0));
+        assert!(calculator.parse_str("a / 0").is_err());
+        assert_eq!(
+            calculator.parse_get(CalculatorFloat::from("a + 1")),
+            Ok(3.0)
+        );
+        assert_eq!(calculator.parse_get(CalculatorFloat::from(1.5)), Ok(1.5));
+        assert_eq!(calculator.parse_str_assign("b=a; b+1"), Ok(3.0));
+        assert!(calculator.parse_str("unknown").is_err());
+        let recorded = events.lock().unwrap().clone();
+        let summary: Vec<(&str, Result<f64, &str>, u64)> = recorded
+            .iter()
+            .map(|event| (event.expression.as_str(), event.outcome, event.sequence))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("a * 3", Ok(6.0), 0),
+                ("a / 0", Err("DivisionByZero"), 1),
+                ("a + 1", Ok(3.0), 2),
+                ("b=a; b+1", Ok(3.0), 3),
+                ("unknown", Err("VariableNotSet"), 4),
+            ]
+        );
+        // Long expressions are truncated like in error messages
+        let long = "a + ".repeat(1000) + "a";
+        calculator.parse_str(&long).unwrap();
+        let expression = events.lock().unwrap().last().unwrap().expression.clone();
+        assert!(expression.len() < long.len());
+        // Without observer no events are recorded
+        calculator.remove_observer();
+        calculator.parse_str("a").unwrap();
+        assert_eq!(events.lock().unwrap().len(), 6);
+    }
+
+    // Test that clones share the observer and its sequence numbers
+    #[test]
+    fn observer_shared_by_clones() {
+        let (calculator, events) = recorded_calculator();
+        let clone = calculator.clone();
+        calculator.parse_str("1").unwrap();
+        clone.parse_str("2").unwrap();
+        calculator.parse_str("3").unwrap();
+        let sequences: Vec<u64> = events
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|event| event.sequence)
+            .collect();
+        assert_eq!(sequences, vec![0, 1, 2]);
+        // A frozen Calculator can be observed, a new observer restarts the sequence
+        let mut frozen = clone.clone();
+        frozen.freeze();
+        let (_, new_events) = recorded_calculator();
+        let recorder = new_events.clone();
+        frozen.set_observer(Arc::new(move |event: &EvaluationEvent| {
+            recorder.lock().unwrap().push(event.clone())
+        }));
+        assert!(frozen.parse_str_assign("a=1; a").is_err());
+        let event = new_events.lock().unwrap()[0].clone();
+        assert_eq!(event.outcome, Err("CalculatorFrozen"));
+        assert_eq!(event.sequence, 0);
+        assert_eq!(events.lock().unwrap().len(), 3);
+    }
+
+    // Test that a panic of the observer propagates after the variables have been assigned
+    #[test]
+    fn observer_panic_propagates() {
+        let mut calculator = Calculator::new();
+        calculator.set_observer(Arc::new(|_: &EvaluationEvent| panic!("observer failed")));
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            calculator.parse_str_assign("a=2; a").unwrap()
+        }));
+        assert!(result.is_err());
+        assert_eq!(calculator.get_variable("a"), Ok(2.0));
+    }
 }
 // End of tests
@@ -15,22 +15,49 @@
 //! Provides CalculatorComplex struct and methods for parsing and evaluating
 //! mathematical expressions in string form to complex.
 
+use crate::canonical_hash;
+use crate::math;
+use crate::write_truncated;
 use crate::CalculatorError;
 use crate::CalculatorFloat;
+use crate::SymbolicAccumulator;
 use num_complex::Complex;
 #[cfg(feature = "json_schema")]
 use schemars::schema::*;
 use serde::de::Deserialize;
 use serde::de::Error;
-use serde::de::{SeqAccess, Visitor};
+use serde::de::{IgnoredAny, MapAccess, SeqAccess, Visitor};
 use serde::ser::SerializeTuple;
 use serde::Serialize;
+use std::cmp;
+use std::collections::HashMap;
 use std::convert::TryFrom;
 use std::fmt;
 use std::ops;
 /// Struct CalculatorComplex.
 ///
+/// # Zero value
 ///
+/// The zero value, where both parts are `CalculatorFloat::Float` zeros, has no defined phase
+/// and no reciprocal:
+///
+/// * [CalculatorComplex::arg] returns `atan2(0, 0) = 0` for compatibility,
+///   [CalculatorComplex::arg_checked] returns `CalculatorError::PhaseOfZero`
+/// * [CalculatorComplex::norm] and [CalculatorComplex::abs] return zero
+/// * [CalculatorComplex::recip] panics, [CalculatorComplex::checked_recip] returns
+///   `CalculatorError::DivisionByZero`
+///
+/// A value with small but nonzero parts is not treated as zero. Since the reciprocal divides
+/// by the square norm, `checked_recip` also returns an error when the square norm underflows
+/// to zero, which happens for parts below about `2e-162`.
+/// Symbolic parts are never treated as zero, even when they evaluate to zero.
+///
+/// # Real values
+///
+/// Reading the `re` field to pass a complex value on as CalculatorFloat silently drops a
+/// nonzero imaginary part. Use [CalculatorComplex::demote], which returns an error unless the
+/// imaginary part is the float zero, or [CalculatorComplex::demote_lossy], which reports
+/// whether the imaginary part was dropped. [CalculatorFloat::promote] converts in the other direction.
 #[derive(Debug, Clone, PartialEq)]
 pub struct CalculatorComplex {
     /// CalculatorFloat value of real part of CalculatorComplex
@@ -50,6 +77,48 @@ impl schemars::JsonSchema for CalculatorComplex {
     }
 }
 
+/// Compare CalculatorComplex with [approx::assert_abs_diff_eq] componentwise.
+///
+/// The real and imaginary parts are compared like [CalculatorFloat].
+#[cfg(feature = "approx")]
+impl approx::AbsDiffEq for CalculatorComplex {
+    type Epsilon = f64;
+
+    fn default_epsilon() -> f64 {
+        CalculatorFloat::default_epsilon()
+    }
+
+    fn abs_diff_eq(&self, other: &Self, epsilon: f64) -> bool {
+        self.re.abs_diff_eq(&other.re, epsilon) && self.im.abs_diff_eq(&other.im, epsilon)
+    }
+}
+
+/// Compare CalculatorComplex with [approx::assert_relative_eq] componentwise.
+#[cfg(feature = "approx")]
+impl approx::RelativeEq for CalculatorComplex {
+    fn default_max_relative() -> f64 {
+        CalculatorFloat::default_max_relative()
+    }
+
+    fn relative_eq(&self, other: &Self, epsilon: f64, max_relative: f64) -> bool {
+        self.re.relative_eq(&other.re, epsilon, max_relative)
+            && self.im.relative_eq(&other.im, epsilon, max_relative)
+    }
+}
+
+/// Compare CalculatorComplex with [approx::assert_ulps_eq] componentwise.
+#[cfg(feature = "approx")]
+impl approx::UlpsEq for CalculatorComplex {
+    fn default_max_ulps() -> u32 {
+        CalculatorFloat::default_max_ulps()
+    }
+
+    fn ulps_eq(&self, other: &Self, epsilon: f64, max_ulps: u32) -> bool {
+        self.re.ulps_eq(&other.re, epsilon, max_ulps)
+            && self.im.ulps_eq(&other.im, epsilon, max_ulps)
+    }
+}
+
 impl Serialize for CalculatorComplex {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
@@ -62,6 +131,13 @@ impl Serialize for CalculatorComplex {
     }
 }
 
+/// Deserialize CalculatorComplex from the tuple `(re, im)` or from the map `{"re": ..., "im": ...}`.
+///
+/// Self-describing human readable formats like JSON accept both forms, other formats only
+/// the tuple form that is serialized. The map also accepts the keys `real` and `imag` and the
+/// marker `is_calculator_complex` written by `to_dict` of the Python CalculatorComplex.
+/// Errors of the real or imaginary part name the part, the position in the document is added
+/// by the deserializer.
 impl<'de> Deserialize<'de> for CalculatorComplex {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
@@ -73,7 +149,7 @@ impl<'de> Deserialize<'de> for CalculatorComplex {
             fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
                 std::fmt::Formatter::write_str(
                     formatter,
-                    "Tuple of two CalculatorFloat values (float or string)",
+                    "Tuple of two CalculatorFloat values (float or string) or map with keys re and im",
                 )
             }
             // when variants are marked by String values
@@ -81,14 +157,19 @@ impl<'de> Deserialize<'de> for CalculatorComplex {
             where
                 M: SeqAccess<'de>,
             {
-                // let visitor = TupleVisitor;
-                let real: CalculatorFloat = match access.next_element()? {
+                let real: CalculatorFloat = match access
+                    .next_element()
+                    .map_err(|err| M::Error::custom(format!("Invalid real part: {err}")))?
+                {
                     Some(x) => x,
                     None => {
                         return Err(M::Error::custom("Missing real part".to_string()));
                     }
                 };
-                let imaginary: CalculatorFloat = match access.next_element()? {
+                let imaginary: CalculatorFloat = match access
+                    .next_element()
+                    .map_err(|err| M::Error::custom(format!("Invalid imaginary part: {err}")))?
+                {
                     Some(x) => x,
                     None => {
                         return Err(M::Error::custom("Missing imaginary part".to_string()));
@@ -97,10 +178,50 @@ impl<'de> Deserialize<'de> for CalculatorComplex {
 
                 Ok(CalculatorComplex::new(real, imaginary))
             }
+            // map form with fields re and im, or real and imag
+            fn visit_map<M>(self, mut access: M) -> Result<Self::Value, M::Error>
+            where
+                M: MapAccess<'de>,
+            {
+                let mut real: Option<CalculatorFloat> = None;
+                let mut imaginary: Option<CalculatorFloat> = None;
+                while let Some(key) = access.next_key::<String>()? {
+                    let (part, name) = match key.as_str() {
+                        "re" | "real" => (&mut real, "real"),
+                        "im" | "imag" => (&mut imaginary, "imaginary"),
+                        "is_calculator_complex" => {
+                            access.next_value::<IgnoredAny>()?;
+                            continue;
+                        }
+                        _ => {
+                            return Err(M::Error::unknown_field(
+                                &key,
+                                &["re", "im", "real", "imag"],
+                            ))
+                        }
+                    };
+                    if part.is_some() {
+                        return Err(M::Error::custom(format!("Duplicate {name} part")));
+                    }
+                    *part =
+                        Some(access.next_value().map_err(|err| {
+                            M::Error::custom(format!("Invalid {name} part: {err}"))
+                        })?);
+                }
+                match (real, imaginary) {
+                    (Some(real), Some(imaginary)) => Ok(CalculatorComplex::new(real, imaginary)),
+                    (None, _) => Err(M::Error::custom("Missing real part".to_string())),
+                    (_, None) => Err(M::Error::custom("Missing imaginary part".to_string())),
+                }
+            }
         }
         let pp_visitor = ComplexVisitor;
 
-        deserializer.deserialize_tuple(2, pp_visitor)
+        if deserializer.is_human_readable() {
+            deserializer.deserialize_any(pp_visitor)
+        } else {
+            deserializer.deserialize_tuple(2, pp_visitor)
+        }
     }
 }
 
@@ -243,6 +364,27 @@ impl fmt::Display for CalculatorComplex {
 }
 
 impl CalculatorComplex {
+    /// Stream the Display of the CalculatorComplex into a writer, optionally truncated.
+    ///
+    /// The real and imaginary part are written one after the other, see
+    /// [CalculatorFloat::write_display]. The maximal length applies to the complete output.
+    ///
+    /// # Arguments
+    ///
+    /// * `writer` - Writer receiving the displayed value
+    /// * `max_len` - Maximum number of bytes of the value before the `...`, None for the complete value
+    ///
+    pub fn write_display<W: fmt::Write>(
+        &self,
+        writer: &mut W,
+        max_len: Option<usize>,
+    ) -> fmt::Result {
+        match max_len {
+            None => write!(writer, "{self}"),
+            Some(limit) => write_truncated(writer, self, limit),
+        }
+    }
+
     /// Constant zero for CalculatorComplex
     pub const ZERO: CalculatorComplex = CalculatorComplex {
         re: CalculatorFloat::Float(0.0),
@@ -280,14 +422,36 @@ impl CalculatorComplex {
     }
 
     /// Return phase of complex number x: arg(x).
+    ///
+    /// Returns 0 for the zero value, see [the zero value](CalculatorComplex#zero-value).
     pub fn arg(&self) -> CalculatorFloat {
         self.im.atan2(&self.re)
     }
+
+    /// Return phase of complex number x: arg(x), returning an error for the zero value.
+    ///
+    /// See [the zero value](CalculatorComplex#zero-value).
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(CalculatorFloat)` - The phase
+    /// * `Err(CalculatorError::PhaseOfZero)` - Both parts are the float zero
+    ///
+    pub fn arg_checked(&self) -> Result<CalculatorFloat, CalculatorError> {
+        if self.is_zero() {
+            Err(CalculatorError::PhaseOfZero)
+        } else {
+            Ok(self.arg())
+        }
+    }
+
     /// Return square norm of complex number x: |x|^2=x.re^2+x.im^2.
     pub fn norm_sqr(&self) -> CalculatorFloat {
         (self.re.clone() * &self.re) + (self.im.clone() * &self.im)
     }
     /// Return norm of complex number x: |x|=(x.re^2+x.im^2)^1/2.
+    ///
+    /// Returns 0 for the zero value, see [the zero value](CalculatorComplex#zero-value).
     pub fn norm(&self) -> CalculatorFloat {
         ((self.re.clone() * &self.re) + (self.im.clone() * &self.im)).sqrt()
     }
@@ -304,6 +468,244 @@ impl CalculatorComplex {
             im: -self.im.clone(),
         }
     }
+
+    /// Return the phase factor e^(i*theta) = cos(theta) + i*sin(theta).
+    ///
+    /// This is the canonical spelling of a phase factor: a symbolic theta gives the
+    /// parts `cos(theta)` and `sin(theta)`, so the expression of theta is contained twice.
+    ///
+    /// # Arguments
+    ///
+    /// * `theta` - Phase given as type that can be converted to CalculatorFloat
+    ///
+    pub fn exp_i<T>(theta: T) -> Self
+    where
+        T: Into<CalculatorFloat>,
+    {
+        let theta: CalculatorFloat = theta.into();
+        Self {
+            re: theta.cos(),
+            im: theta.sin(),
+        }
+    }
+
+    /// Return the complex number r * e^(i*theta) given in polar form.
+    ///
+    /// Numeric arguments give float parts like `Complex::from_polar`, symbolic arguments give
+    /// the parts `(r * cos(theta))` and `(r * sin(theta))`, a factor of one is dropped.
+    /// See [CalculatorComplex::to_polar] for the inverse.
+    ///
+    /// # Arguments
+    ///
+    /// * `r` - Norm given as type that can be converted to CalculatorFloat
+    /// * `theta` - Phase given as type that can be converted to CalculatorFloat
+    ///
+    pub fn from_polar<T1, T2>(r: T1, theta: T2) -> Self
+    where
+        T1: Into<CalculatorFloat>,
+        T2: Into<CalculatorFloat>,
+    {
+        let r: CalculatorFloat = r.into();
+        let theta: CalculatorFloat = theta.into();
+        Self {
+            re: r.clone() * theta.cos(),
+            im: r * theta.sin(),
+        }
+    }
+
+    /// Return the polar form (norm, arg) of the complex number.
+    ///
+    /// The phase of the zero value is 0, see [the zero value](CalculatorComplex#zero-value).
+    pub fn to_polar(&self) -> (CalculatorFloat, CalculatorFloat) {
+        (self.norm(), self.arg())
+    }
+
+    /// Return true when x is a number with |x| = 1 up to the tolerance.
+    ///
+    /// Returns false when a part is symbolic.
+    ///
+    /// # Arguments
+    ///
+    /// * `tolerance` - Absolute tolerance of the norm
+    ///
+    pub fn is_on_unit_circle(&self, tolerance: f64) -> bool {
+        match (&self.re, &self.im) {
+            (CalculatorFloat::Float(re), CalculatorFloat::Float(im)) => {
+                (math::hypot(*re, *im) - 1.0).abs() <= tolerance
+            }
+            _ => false,
+        }
+    }
+
+    /// Return True when both parts are the float zero, see [CalculatorFloat::is_zero].
+    pub fn is_zero(&self) -> bool {
+        self.re.is_zero() && self.im.is_zero()
+    }
+
+    /// Return True when the imaginary part is the float zero, the real part can be symbolic.
+    pub fn is_real(&self) -> bool {
+        self.im.is_zero()
+    }
+
+    /// Return hyperbolic sine of x: sinh(x) = sinh(x.re)*cos(x.im) + i*cosh(x.re)*sin(x.im).
+    pub fn sinh(&self) -> CalculatorComplex {
+        Self {
+            re: real_function(&self.re, "sinh", math::sinh) * self.im.cos(),
+            im: real_function(&self.re, "cosh", math::cosh) * self.im.sin(),
+        }
+    }
+
+    /// Return hyperbolic cosine of x: cosh(x) = cosh(x.re)*cos(x.im) + i*sinh(x.re)*sin(x.im).
+    pub fn cosh(&self) -> CalculatorComplex {
+        Self {
+            re: real_function(&self.re, "cosh", math::cosh) * self.im.cos(),
+            im: real_function(&self.re, "sinh", math::sinh) * self.im.sin(),
+        }
+    }
+
+    /// Return tangent of x: tan(x) = (sin(2*x.re) + i*sinh(2*x.im)) / (cos(2*x.re) + cosh(2*x.im)).
+    ///
+    /// For numbers the denominator is not checked, like for f64 the result is infinite or NaN
+    /// at the poles.
+    pub fn tan(&self) -> CalculatorComplex {
+        if let (CalculatorFloat::Float(re), CalculatorFloat::Float(im)) = (&self.re, &self.im) {
+            let denominator = math::cos(2.0 * re) + math::cosh(2.0 * im);
+            return Self::new(
+                math::sin(2.0 * re) / denominator,
+                math::sinh(2.0 * im) / denominator,
+            );
+        }
+        let two_re = self.re.clone() * 2.0;
+        let two_im = self.im.clone() * 2.0;
+        let denominator = two_re.cos() + real_function(&two_im, "cosh", math::cosh);
+        Self {
+            re: two_re.sin() / &denominator,
+            im: real_function(&two_im, "sinh", math::sinh) / denominator,
+        }
+    }
+
+    /// Return exponential function of x: exp(x) = exp(x.re)*cos(x.im) + i*exp(x.re)*sin(x.im).
+    pub fn exp(&self) -> CalculatorComplex {
+        let modulus = self.re.exp();
+        Self {
+            re: modulus.clone() * self.im.cos(),
+            im: modulus * self.im.sin(),
+        }
+    }
+
+    /// Return sine of x: sin(x) = sin(x.re)*cosh(x.im) + i*cos(x.re)*sinh(x.im).
+    pub fn sin(&self) -> CalculatorComplex {
+        Self {
+            re: self.re.sin() * real_function(&self.im, "cosh", math::cosh),
+            im: self.re.cos() * real_function(&self.im, "sinh", math::sinh),
+        }
+    }
+
+    /// Return cosine of x: cos(x) = cos(x.re)*cosh(x.im) - i*sin(x.re)*sinh(x.im).
+    pub fn cos(&self) -> CalculatorComplex {
+        Self {
+            re: self.re.cos() * real_function(&self.im, "cosh", math::cosh),
+            im: -(self.re.sin() * real_function(&self.im, "sinh", math::sinh)),
+        }
+    }
+
+    /// Return principal square root of x: sqrt(x) = sqrt(|x|)*(cos(arg(x)/2) + i*sin(arg(x)/2)).
+    ///
+    /// The branch cut is the negative real axis, like for num_complex the phase is in (-pi, pi].
+    pub fn sqrt(&self) -> CalculatorComplex {
+        self.powf(0.5)
+    }
+
+    /// Return x to the power of a real exponent: x^y = |x|^y*(cos(y*arg(x)) + i*sin(y*arg(x))).
+    ///
+    /// Uses the principal branch like num_complex, the zero value to a positive power is zero.
+    ///
+    /// # Arguments
+    ///
+    /// * `exponent` - Exponent given as type that can be converted to CalculatorFloat
+    ///
+    pub fn powf<T>(&self, exponent: T) -> CalculatorComplex
+    where
+        T: Into<CalculatorFloat>,
+    {
+        let exponent: CalculatorFloat = exponent.into();
+        let modulus = self.norm().powf(&exponent);
+        let phase = self.arg() * exponent;
+        Self {
+            re: modulus.clone() * phase.cos(),
+            im: modulus * phase.sin(),
+        }
+    }
+
+    /// Return the real part, returning an error unless the imaginary part is zero.
+    ///
+    /// The imaginary part has to be exactly the float zero, also tiny values like `1e-20`
+    /// and symbolic expressions that evaluate to zero return an error.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(CalculatorFloat)` - The real part
+    /// * `Err(CalculatorError::ComplexCanNotBeConvertedToFloat)` - The imaginary part is a nonzero float
+    /// * `Err(CalculatorError::ComplexSymbolicNotConvertable)` - The imaginary part is symbolic
+    ///
+    pub fn demote(&self) -> Result<CalculatorFloat, CalculatorError> {
+        match self.im {
+            CalculatorFloat::Float(x) => {
+                if x == 0.0 {
+                    Ok(self.re.clone())
+                } else {
+                    Err(CalculatorError::ComplexCanNotBeConvertedToFloat { val: self.clone() })
+                }
+            }
+            CalculatorFloat::Str(_) => {
+                Err(CalculatorError::ComplexSymbolicNotConvertable { val: self.clone() })
+            }
+        }
+    }
+
+    /// Return the real part and whether a nonzero or symbolic imaginary part was dropped.
+    ///
+    /// See [CalculatorComplex::demote] for the conversion that returns an error instead.
+    pub fn demote_lossy(&self) -> (CalculatorFloat, bool) {
+        let dropped = !self.is_real();
+        (self.re.clone(), dropped)
+    }
+
+    /// Return the derivative of the real and imaginary part with respect to a variable.
+    ///
+    /// # Arguments
+    ///
+    /// * `variable` - Name of the variable
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(CalculatorComplex)` - The derivative, see [CalculatorFloat::derivative]
+    /// * `Err(CalculatorError)` - One of the parts can not be differentiated
+    ///
+    pub fn derivative(&self, variable: &str) -> Result<CalculatorComplex, CalculatorError> {
+        Ok(Self {
+            re: self.re.derivative(variable)?,
+            im: self.im.derivative(variable)?,
+        })
+    }
+    /// Replace a variable in the real and imaginary part by a value, see [CalculatorFloat::substitute].
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - Name of the replaced variable
+    /// * `value` - Value inserted for the variable, a number, variable or expression
+    ///
+    pub fn substitute(
+        &self,
+        name: &str,
+        value: &CalculatorFloat,
+    ) -> Result<CalculatorComplex, CalculatorError> {
+        Ok(Self {
+            re: self.re.substitute(name, value)?,
+            im: self.im.substitute(name, value)?,
+        })
+    }
+
     /// Return true when x is close to y.
     pub fn isclose<T>(&self, other: T) -> bool
     where
@@ -312,6 +714,61 @@ impl CalculatorComplex {
         let other_from: CalculatorComplex = other.into();
         self.re.isclose(other_from.re) && self.im.isclose(other_from.im)
     }
+
+    /// Rename the variables of the real and imaginary part to `v0`, `v1`, ...
+    ///
+    /// The real part is renamed first, names that occur in both parts get the same replacement.
+    /// See [CalculatorFloat::anonymized].
+    ///
+    /// # Returns
+    ///
+    /// * `(CalculatorComplex, HashMap<String, String>)` - The renamed CalculatorComplex and the original names with their replacements
+    ///
+    pub fn anonymized(&self) -> (CalculatorComplex, HashMap<String, String>) {
+        let mut mapping: HashMap<String, String> = HashMap::new();
+        let re = self.re.anonymized_with(&mut mapping);
+        let im = self.im.anonymized_with(&mut mapping);
+        (CalculatorComplex { re, im }, mapping)
+    }
+
+    /// Compare two CalculatorComplex values in a total order.
+    ///
+    /// Compares the real parts and then the imaginary parts with [CalculatorFloat::total_cmp].
+    ///
+    /// # Arguments
+    ///
+    /// * `other` - CalculatorComplex compared to self
+    ///
+    pub fn total_cmp(&self, other: &Self) -> cmp::Ordering {
+        self.re
+            .total_cmp(&other.re)
+            .then_with(|| self.im.total_cmp(&other.im))
+    }
+
+    /// Return the CalculatorComplex as a string that can be parsed by SymPy.
+    ///
+    /// See [CalculatorFloat::to_sympy_string] for the conversion of the real and imaginary parts.
+    pub fn to_sympy_string(&self) -> Result<String, CalculatorError> {
+        Ok(format!(
+            "({}) + I*({})",
+            self.re.to_sympy_string()?,
+            self.im.to_sympy_string()?
+        ))
+    }
+
+    /// Return a stable 64 bit hash of the CalculatorComplex.
+    ///
+    /// Combines the normalized real and imaginary parts, see [CalculatorFloat::canonical_hash].
+    pub fn canonical_hash(&self) -> u64 {
+        canonical_hash::fnv1a_64(&canonical_hash::encode_complex(self))
+    }
+
+    /// Return a stable 128 bit hash of the CalculatorComplex.
+    ///
+    /// See [CalculatorComplex::canonical_hash].
+    pub fn canonical_hash_128(&self) -> u128 {
+        canonical_hash::fnv1a_128(&canonical_hash::encode_complex(self))
+    }
 }
 
 /// Implement `+` for CalculatorComplex and generic type `T`.
@@ -336,17 +793,25 @@ where
 
 /// Implements summing over an iterator of CalculatorComplex
 ///
+/// The real and imaginary parts are summed like for [CalculatorFloat],
+/// so the cost grows linearly with the number of summands.
+///
 /// # Arguments
 ///
 /// * `iter` - Any iterator over CalculatorComplex items
 ///
 impl std::iter::Sum for CalculatorComplex {
     fn sum<I: Iterator<Item = CalculatorComplex>>(iter: I) -> Self {
-        let mut sum = CalculatorComplex::new(0, 0);
+        let mut re = SymbolicAccumulator::default();
+        let mut im = SymbolicAccumulator::default();
         for i in iter {
-            sum += i;
+            re += i.re;
+            im += i.im;
+        }
+        CalculatorComplex {
+            re: re.finish(),
+            im: im.finish(),
         }
-        sum
     }
 }
 
@@ -362,10 +827,8 @@ where
 {
     fn add_assign(&mut self, other: T) {
         let other_from: CalculatorComplex = other.into();
-        *self = CalculatorComplex {
-            re: &self.re + other_from.re,
-            im: &self.im + other_from.im,
-        }
+        self.re += other_from.re;
+        self.im += other_from.im;
     }
 }
 
@@ -400,10 +863,8 @@ where
 {
     fn sub_assign(&mut self, other: T) {
         let other_from: CalculatorComplex = other.into();
-        *self = CalculatorComplex {
-            re: self.re.clone() - other_from.re,
-            im: self.im.clone() - other_from.im,
-        }
+        self.re -= other_from.re;
+        self.im -= other_from.im;
     }
 }
 
@@ -497,22 +958,199 @@ where
     }
 }
 
+/// Implement arithmetic operators with a scalar as left operand and CalculatorComplex as right operand.
+///
+/// The scalar is converted to CalculatorComplex and the operator of CalculatorComplex is applied,
+/// so symbolic results are the same as for `CalculatorComplex * T`.
+macro_rules! scalar_first_operations {
+    ($scalar:ty => $(($trait:ident, $method:ident)),*) => {
+        $(
+            impl ops::$trait<CalculatorComplex> for $scalar {
+                type Output = CalculatorComplex;
+                fn $method(self, other: CalculatorComplex) -> CalculatorComplex {
+                    ops::$trait::$method(CalculatorComplex::from(self), other)
+                }
+            }
+
+            impl ops::$trait<&CalculatorComplex> for $scalar {
+                type Output = CalculatorComplex;
+                fn $method(self, other: &CalculatorComplex) -> CalculatorComplex {
+                    ops::$trait::$method(CalculatorComplex::from(self), other)
+                }
+            }
+        )*
+    };
+}
+
+scalar_first_operations!(f64 => (Add, add), (Sub, sub), (Mul, mul), (Div, div));
+scalar_first_operations!(Complex<f64> => (Add, add), (Sub, sub), (Mul, mul), (Div, div));
+
+/// Apply a real function to a part, writing the function call for symbolic parts.
+fn real_function(x: &CalculatorFloat, name: &str, function: fn(f64) -> f64) -> CalculatorFloat {
+    match x {
+        CalculatorFloat::Float(x) => CalculatorFloat::Float(function(*x)),
+        CalculatorFloat::Str(x) => CalculatorFloat::Str(format!("{name}({x})")),
+    }
+}
+
 /// Implement Inverse `1/x` for CalculatorFloat.
 impl CalculatorComplex {
     /// Returns Inverse `1/x` for CalculatorFloat.
+    ///
+    /// # Panics
+    ///
+    /// Panics on the zero value, see [the zero value](CalculatorComplex#zero-value)
+    /// and [CalculatorComplex::checked_recip].
     pub fn recip(&self) -> CalculatorComplex {
-        let norm = self.norm_sqr();
-        CalculatorComplex {
-            re: self.re.clone() / &norm,
-            im: -self.im.clone() / &norm,
+        match self.checked_recip() {
+            Ok(inverse) => inverse,
+            Err(error) => panic!("{error}"),
         }
     }
+
+    /// Returns Inverse `1/x` for CalculatorFloat, returning an error instead of panicking.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(CalculatorComplex)` - The inverse
+    /// * `Err(CalculatorError::DivisionByZero)` - The square norm is the float zero,
+    ///   see [the zero value](CalculatorComplex#zero-value)
+    /// * `Err(CalculatorError::SymbolicExpressionTooLarge)` - The symbolic expressions are too long
+    ///
+    pub fn checked_recip(&self) -> Result<CalculatorComplex, CalculatorError> {
+        let norm = self
+            .re
+            .clone()
+            .checked_mul(&self.re)?
+            .checked_add(self.im.clone().checked_mul(&self.im)?)?;
+        Ok(CalculatorComplex {
+            re: self.re.clone().checked_div(&norm)?,
+            im: (-self.im.clone()).checked_div(&norm)?,
+        })
+    }
+}
+
+/// Arithmetic returning errors instead of panicking.
+///
+/// The operator traits panic on a division by zero and when symbolic expressions exceed
+/// [crate::max_symbolic_length]. The checked methods return the same results as the operators
+/// and a CalculatorError instead, so callers do not depend on catching panics.
+impl CalculatorComplex {
+    /// Add a value, returning an error instead of panicking.
+    ///
+    /// # Arguments
+    ///
+    /// * `other` - Any type T for which CalculatorComplex::From<T> trait is implemented
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(CalculatorComplex)` - The sum
+    /// * `Err(CalculatorError::SymbolicExpressionTooLarge)` - The symbolic expressions are too long
+    ///
+    pub fn checked_add<T>(self, other: T) -> Result<CalculatorComplex, CalculatorError>
+    where
+        T: Into<CalculatorComplex>,
+    {
+        let other_from: CalculatorComplex = other.into();
+        Ok(CalculatorComplex {
+            re: self.re.checked_add(other_from.re)?,
+            im: self.im.checked_add(other_from.im)?,
+        })
+    }
+
+    /// Subtract a value, returning an error instead of panicking.
+    ///
+    /// # Arguments
+    ///
+    /// * `other` - Any type T for which CalculatorComplex::From<T> trait is implemented
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(CalculatorComplex)` - The difference
+    /// * `Err(CalculatorError::SymbolicExpressionTooLarge)` - The symbolic expressions are too long
+    ///
+    pub fn checked_sub<T>(self, other: T) -> Result<CalculatorComplex, CalculatorError>
+    where
+        T: Into<CalculatorComplex>,
+    {
+        let other_from: CalculatorComplex = other.into();
+        Ok(CalculatorComplex {
+            re: self.re.checked_sub(other_from.re)?,
+            im: self.im.checked_sub(other_from.im)?,
+        })
+    }
+
+    /// Multiply with a value, returning an error instead of panicking.
+    ///
+    /// # Arguments
+    ///
+    /// * `other` - Any type T for which CalculatorComplex::From<T> trait is implemented
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(CalculatorComplex)` - The product
+    /// * `Err(CalculatorError::SymbolicExpressionTooLarge)` - The symbolic expressions are too long
+    ///
+    pub fn checked_mul<T>(self, other: T) -> Result<CalculatorComplex, CalculatorError>
+    where
+        T: Into<CalculatorComplex>,
+    {
+        let other_from: CalculatorComplex = other.into();
+        Ok(CalculatorComplex {
+            re: self
+                .re
+                .clone()
+                .checked_mul(&other_from.re)?
+                .checked_sub(self.im.clone().checked_mul(&other_from.im)?)?,
+            im: self
+                .re
+                .checked_mul(&other_from.im)?
+                .checked_add(self.im.checked_mul(&other_from.re)?)?,
+        })
+    }
+
+    /// Divide by a value, returning an error instead of panicking.
+    ///
+    /// # Arguments
+    ///
+    /// * `other` - Any type T for which CalculatorComplex::From<T> trait is implemented
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(CalculatorComplex)` - The quotient
+    /// * `Err(CalculatorError::DivisionByZero)` - The norm of the divisor is the float zero
+    /// * `Err(CalculatorError::SymbolicExpressionTooLarge)` - The symbolic expressions are too long
+    ///
+    pub fn checked_div<T>(self, other: T) -> Result<CalculatorComplex, CalculatorError>
+    where
+        T: Into<CalculatorComplex>,
+    {
+        let other_from: CalculatorComplex = other.into();
+        let norm = other_from
+            .re
+            .clone()
+            .checked_mul(&other_from.re)?
+            .checked_add(other_from.im.clone().checked_mul(&other_from.im)?)?;
+        Ok(CalculatorComplex {
+            re: self
+                .re
+                .clone()
+                .checked_mul(&other_from.re)?
+                .checked_add(self.im.clone().checked_mul(&other_from.im)?)?
+                .checked_div(&norm)?,
+            im: (-self.re)
+                .checked_mul(&other_from.im)?
+                .checked_add(self.im.checked_mul(&other_from.re)?)?
+                .checked_div(&norm)?,
+        })
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::CalculatorComplex;
     use super::CalculatorFloat;
+    use crate::{Calculator, CalculatorError};
     use num_complex::Complex;
     #[cfg(feature = "json_schema")]
     use schemars::schema_for;
@@ -588,6 +1226,63 @@ mod tests {
         );
     }
 
+    // Test streaming both parts into a writer with truncation of the complete output
+    #[test]
+    fn write_display() {
+        let re = "a * ".repeat(1 << 18) + "a";
+        let value = CalculatorComplex::new(re.as_str(), re.as_str());
+        let mut written = String::new();
+        value.write_display(&mut written, Some(10)).unwrap();
+        assert_eq!(written, "(a * a * a... (2097163 bytes)");
+        let mut written = String::new();
+        value.write_display(&mut written, None).unwrap();
+        assert_eq!(written.len(), 2097163);
+        assert_eq!(written, value.to_string());
+        let mut written = String::new();
+        CalculatorComplex::new(1, 2)
+            .write_display(&mut written, Some(100))
+            .unwrap();
+        assert_eq!(written, "(1e0 + i * 2e0)");
+    }
+
+    // Test that names in the real and imaginary part are renamed consistently
+    #[test]
+    fn anonymized() {
+        let value = CalculatorComplex::new("amp * cos(phase)", "amp * sin(phase) + offset");
+        let (anonymized, mapping) = value.anonymized();
+        assert_eq!(
+            anonymized,
+            CalculatorComplex::new("v0 * cos(v1)", "v0 * sin(v1) + v2")
+        );
+        assert_eq!(mapping.len(), 3);
+        assert_eq!(mapping["offset"], "v2");
+        let (anonymized, mapping) = CalculatorComplex::new(1.0, "b").anonymized();
+        assert_eq!(anonymized, CalculatorComplex::new(1.0, "v0"));
+        assert_eq!(mapping["b"], "v0");
+    }
+
+    // Test the componentwise approx comparisons
+    #[cfg(feature = "approx")]
+    #[test]
+    fn test_approx() {
+        use approx::{assert_abs_diff_eq, assert_relative_eq, assert_relative_ne, assert_ulps_eq};
+        let x = CalculatorComplex::new(1.0, 2.0);
+        assert_relative_eq!(x, CalculatorComplex::new(1.0 + 1e-9, 2.0));
+        assert_relative_ne!(x, CalculatorComplex::new(1.0, 2.0 + 1e-6));
+        assert_abs_diff_eq!(x, CalculatorComplex::new(1.01, 1.99), epsilon = 0.1);
+        assert_ulps_eq!(x, CalculatorComplex::new(1.0, 2.0));
+        let symbolic = CalculatorComplex::new("a", 2.0);
+        assert_relative_eq!(symbolic, CalculatorComplex::new("a", 2.0 + 1e-9));
+        assert_relative_ne!(symbolic, CalculatorComplex::new("b", 2.0));
+        assert_relative_ne!(symbolic, x, max_relative = 1.0);
+        let message = std::panic::catch_unwind(|| assert_abs_diff_eq!(x, symbolic.clone()))
+            .unwrap_err()
+            .downcast::<String>()
+            .unwrap();
+        assert!(message.contains("re: Float(1.0)"), "{message}");
+        assert!(message.contains("re: Str(\"a\")"), "{message}");
+    }
+
     #[cfg(feature = "json_schema")]
     #[test]
     fn test_json_schema_support() {
@@ -762,6 +1457,81 @@ mod tests {
         assert_eq!(x, CalculatorComplex::new(7.0 / 25.0, -1.0 / 25.0));
     }
 
+    // Test the operators with f64 and Complex<f64> as left operand
+    #[test]
+    fn scalar_first_operations() {
+        let x = CalculatorComplex::new("x", 1);
+        let y = CalculatorComplex::new(3, 4);
+        // f64
+        assert_eq!(2.0 + y.clone(), CalculatorComplex::new(5, 4));
+        assert_eq!(2.0 - &y, CalculatorComplex::new(-1, -4));
+        assert_eq!(2.0 * &y, CalculatorComplex::new(6, 8));
+        assert_eq!(25.0 / y.clone(), CalculatorComplex::new(3, -4));
+        assert_eq!(2.0 + &x, CalculatorComplex::new("(2e0 + x)", 1));
+        assert_eq!(2.0 - x.clone(), CalculatorComplex::new("(2e0 - x)", -1));
+        assert_eq!(2.0 * x.clone(), CalculatorComplex::from(2.0) * x.clone());
+        assert_eq!(2.0 / &x, CalculatorComplex::from(2.0) / &x);
+        // Complex<f64>
+        let z = Complex::new(1.0, 2.0);
+        assert_eq!(z + y.clone(), CalculatorComplex::new(4, 6));
+        assert_eq!(z - &y, CalculatorComplex::new(-2, -2));
+        assert_eq!(z * &y, CalculatorComplex::new(-5, 10));
+        assert_eq!(
+            z / y.clone(),
+            CalculatorComplex::new(11.0 / 25.0, 2.0 / 25.0)
+        );
+        assert_eq!(z + &x, CalculatorComplex::new("(1e0 + x)", 3));
+        assert_eq!(z * x.clone(), CalculatorComplex::from(z) * x.clone());
+        assert_eq!(z / &x, CalculatorComplex::from(z) / &x);
+    }
+
+    // Test that checked operations agree with the operators and report errors instead of panicking
+    #[test]
+    fn checked_operations() {
+        let x = CalculatorComplex::new("a", 1);
+        let y = CalculatorComplex::new(3, "b");
+        assert_eq!(
+            x.clone().checked_add(y.clone()).unwrap(),
+            x.clone() + y.clone()
+        );
+        assert_eq!(
+            x.clone().checked_sub(y.clone()).unwrap(),
+            x.clone() - y.clone()
+        );
+        assert_eq!(
+            x.clone().checked_mul(y.clone()).unwrap(),
+            x.clone() * y.clone()
+        );
+        assert_eq!(
+            x.clone().checked_div(y.clone()).unwrap(),
+            x.clone() / y.clone()
+        );
+        assert_eq!(
+            CalculatorComplex::new(1, 1).checked_div(CalculatorComplex::new(3, 4)),
+            Ok(CalculatorComplex::new(7.0 / 25.0, -1.0 / 25.0))
+        );
+        assert_eq!(
+            x.clone().checked_div(0.0),
+            Err(CalculatorError::DivisionByZero)
+        );
+        let mut large = CalculatorComplex::new("x", "y");
+        let error = loop {
+            match large.clone().checked_mul(large.clone()) {
+                Ok(product) => large = product,
+                Err(error) => break error,
+            }
+        };
+        assert!(matches!(
+            error,
+            CalculatorError::SymbolicExpressionTooLarge { .. }
+        ));
+        assert!(large.clone().checked_div(large.clone()).is_err());
+        let name = "x".repeat(crate::max_symbolic_length() / 2 + 1);
+        let long = CalculatorComplex::new(CalculatorFloat::Str(name), 0);
+        assert!(long.clone().checked_add(long.clone()).is_err());
+        assert!(long.clone().checked_sub(long).is_err());
+    }
+
     // Test the arg(x) functionality of CalculatorComplex with all possible input types
     #[test]
     fn arg() {
@@ -779,6 +1549,43 @@ mod tests {
         assert_eq!(x.arg(), CalculatorFloat::from("atan2(2t, x)"));
     }
 
+    // Test that arg_checked and checked_recip report the zero value
+    #[test]
+    fn zero_value() {
+        let zero = CalculatorComplex::new(0.0, -0.0);
+        assert_eq!(zero.arg(), CalculatorFloat::from(0.0));
+        assert_eq!(zero.arg_checked(), Err(CalculatorError::PhaseOfZero));
+        assert_eq!(zero.norm(), CalculatorFloat::from(0.0));
+        assert_eq!(zero.checked_recip(), Err(CalculatorError::DivisionByZero));
+
+        let small = CalculatorComplex::new(1e-100, 0.0);
+        assert_eq!(small.arg_checked(), Ok(CalculatorFloat::from(0.0)));
+        assert_eq!(
+            small.checked_recip(),
+            Ok(CalculatorComplex::new(1e100, -0.0))
+        );
+        assert_eq!(small.recip(), small.checked_recip().unwrap());
+        let tiny = CalculatorComplex::new(0.0, 1e-300);
+        assert_eq!(
+            tiny.arg_checked(),
+            Ok(CalculatorFloat::from(std::f64::consts::FRAC_PI_2))
+        );
+        assert_eq!(tiny.checked_recip(), Err(CalculatorError::DivisionByZero));
+
+        let symbolic = CalculatorComplex::new("x", 0.0);
+        assert_eq!(symbolic.arg_checked(), Ok(symbolic.arg()));
+        assert_eq!(symbolic.checked_recip(), Ok(symbolic.recip()));
+        let symbolic = CalculatorComplex::new(0.0, "x - x");
+        assert_eq!(symbolic.arg_checked(), Ok(symbolic.arg()));
+    }
+
+    // Test that the reciprocal of the zero value panics
+    #[test]
+    #[should_panic(expected = "Division by zero")]
+    fn recip_zero() {
+        let _ = CalculatorComplex::new(0.0, 0.0).recip();
+    }
+
     // Test the square norm functionality of CalculatorComplex
     #[test]
     fn norm_sqr() {
@@ -810,6 +1617,184 @@ mod tests {
         assert_eq!(x.conj(), CalculatorComplex::new(y.conj().re, y.conj().im));
     }
 
+    // Test the phase factor exp_i and is_on_unit_circle
+    #[test]
+    fn exp_i() {
+        for theta in [0.0, 0.3, -1.2, std::f64::consts::PI, 10.0] {
+            let x = CalculatorComplex::exp_i(theta);
+            assert!(x.isclose(Complex::new(0.0, theta).exp()));
+            assert!(x.is_on_unit_circle(1e-12));
+        }
+        // The symbolic phase is contained in both parts
+        let x = CalculatorComplex::exp_i("theta + 1");
+        assert_eq!(x.re, CalculatorFloat::from("cos(theta + 1)"));
+        assert_eq!(x.im, CalculatorFloat::from("sin(theta + 1)"));
+        assert_eq!(x.to_string(), "(cos(theta + 1) + i * sin(theta + 1))");
+        assert!(!x.is_on_unit_circle(1e-12));
+        assert!(!CalculatorComplex::new(1.1, 0.0).is_on_unit_circle(1e-3));
+        assert!(CalculatorComplex::new(1.1, 0.0).is_on_unit_circle(0.2));
+    }
+
+    // Test the round trip of from_polar and to_polar and the symbolic parts of from_polar
+    #[test]
+    fn polar_form() {
+        for (r, theta) in [
+            (1.0, 0.0),
+            (2.0, 0.7),
+            (0.5, -2.5),
+            (3.0, std::f64::consts::PI),
+        ] {
+            let x = CalculatorComplex::from_polar(r, theta);
+            assert!(x.isclose(Complex::from_polar(r, theta)));
+            let (norm, arg) = x.to_polar();
+            assert!(norm.isclose(r));
+            assert!(arg.isclose(theta));
+        }
+        assert_eq!(
+            CalculatorComplex::ZERO.to_polar(),
+            (CalculatorFloat::ZERO, CalculatorFloat::ZERO)
+        );
+        let x = CalculatorComplex::from_polar("r", "theta");
+        assert_eq!(x.re, CalculatorFloat::from("(r * cos(theta))"));
+        assert_eq!(x.im, CalculatorFloat::from("(r * sin(theta))"));
+        assert_eq!(
+            CalculatorComplex::from_polar(1.0, "theta"),
+            CalculatorComplex::exp_i("theta")
+        );
+        assert_eq!(
+            CalculatorComplex::from_polar("r", 0.0),
+            CalculatorComplex::new("r", 0.0)
+        );
+        let mut calculator = Calculator::new();
+        calculator.set_variable("r", 1.5).unwrap();
+        calculator.set_variable("theta", 0.4).unwrap();
+        let expected = Complex::from_polar(1.5, 0.4);
+        assert!((calculator.parse_get(x.re.clone()).unwrap() - expected.re).abs() < 1e-15);
+        assert!((calculator.parse_get(x.im.clone()).unwrap() - expected.im).abs() < 1e-15);
+        let (norm, arg) = x.to_polar();
+        assert!((calculator.parse_get(norm).unwrap() - 1.5).abs() < 1e-15);
+        assert!((calculator.parse_get(arg).unwrap() - 0.4).abs() < 1e-15);
+    }
+
+    // Test the exact predicates is_zero and is_real
+    #[test]
+    fn predicates() {
+        assert!(CalculatorComplex::new(0.0, -0.0).is_zero());
+        assert!(!CalculatorComplex::new(0.0, 1e-300).is_zero());
+        assert!(!CalculatorComplex::new("x", 0.0).is_zero());
+        assert!(CalculatorComplex::new("x", 0.0).is_real());
+        assert!(CalculatorComplex::from(2.5).is_real());
+        assert!(!CalculatorComplex::new(1.0, 1e-300).is_real());
+        assert!(!CalculatorComplex::new(1.0, "y").is_real());
+        assert_eq!(
+            CalculatorComplex::new(0.0, 1e-300).arg_checked(),
+            Ok(CalculatorFloat::from(std::f64::consts::FRAC_PI_2))
+        );
+        assert_eq!(
+            CalculatorComplex::new(1.0, 1e-300).demote_lossy(),
+            (CalculatorFloat::from(1.0), true)
+        );
+    }
+
+    // Test sinh, cosh and tan against num_complex
+    #[test]
+    fn hyperbolic_and_tan() {
+        for (re, im) in [(0.0, 0.0), (0.5, -1.5), (-2.0, 0.3), (1.0, 4.0)] {
+            let x = CalculatorComplex::new(re, im);
+            let y = Complex::new(re, im);
+            assert!(x.sinh().isclose(y.sinh()));
+            assert!(x.cosh().isclose(y.cosh()));
+            assert!(x.tan().isclose(y.tan()));
+        }
+        let x = CalculatorComplex::new("a", 2.0);
+        assert_eq!(
+            x.sinh(),
+            CalculatorComplex::new(
+                CalculatorFloat::from("sinh(a)") * 2.0_f64.cos(),
+                CalculatorFloat::from("cosh(a)") * 2.0_f64.sin()
+            )
+        );
+        let x = CalculatorComplex::new(0.0, "b");
+        assert_eq!(
+            x.cosh(),
+            CalculatorComplex::new("cos(b)", CalculatorFloat::Float(0.0))
+        );
+        let tan = CalculatorComplex::new("a", "b").tan();
+        assert_eq!(
+            tan.re,
+            CalculatorFloat::from("(sin((a * 2e0)) / (cos((a * 2e0)) + cosh((b * 2e0))))")
+        );
+        let mut calculator = crate::Calculator::new();
+        calculator.set_variable("a", 0.5).unwrap();
+        calculator.set_variable("b", -1.5).unwrap();
+        let evaluated = calculator.parse_get(tan.im).unwrap();
+        assert!((evaluated - Complex::new(0.5, -1.5).tan().im).abs() < 1e-12);
+    }
+
+    // Test exp, sin, cos, sqrt and powf against num_complex
+    #[test]
+    fn elementary_functions() {
+        for (re, im) in [
+            (0.0, 0.0),
+            (0.5, -1.5),
+            (-2.0, 0.3),
+            (1.0, 4.0),
+            (-4.0, 0.0),
+            (0.0, 2.0),
+        ] {
+            let x = CalculatorComplex::new(re, im);
+            let y = Complex::new(re, im);
+            assert!(x.exp().isclose(y.exp()));
+            assert!(x.sin().isclose(y.sin()));
+            assert!(x.cos().isclose(y.cos()));
+            assert!(x.sqrt().isclose(y.sqrt()));
+            for exponent in [0.0, 1.0, 2.0, -0.5, 2.5] {
+                if re != 0.0 || im != 0.0 || exponent >= 0.0 {
+                    assert!(x.powf(exponent).isclose(y.powf(exponent)));
+                }
+            }
+        }
+        let x = CalculatorComplex::new("a", "b");
+        assert_eq!(x.exp().re, CalculatorFloat::from("(exp(a) * cos(b))"));
+        assert_eq!(x.exp().im, CalculatorFloat::from("(exp(a) * sin(b))"));
+        assert_eq!(x.sin().im, CalculatorFloat::from("(cos(a) * sinh(b))"));
+        assert_eq!(x.cos().im, CalculatorFloat::from("(-(sin(a) * sinh(b)))"));
+    }
+
+    // Test that symbolic results evaluate to the numeric results once variables are set
+    #[test]
+    fn elementary_functions_symbolic() {
+        let mut calculator = crate::Calculator::new();
+        for (re, im) in [(0.5, -1.5), (-2.0, 0.3), (-4.0, 0.0), (0.0, 2.0)] {
+            calculator.set_variable("a", re).unwrap();
+            calculator.set_variable("b", im).unwrap();
+            calculator.set_variable("p", 2.5).unwrap();
+            let symbolic = CalculatorComplex::new("a", "b");
+            let numeric = CalculatorComplex::new(re, im);
+            let evaluate = |x: CalculatorComplex| {
+                Complex::new(
+                    calculator.parse_get(x.re).unwrap(),
+                    calculator.parse_get(x.im).unwrap(),
+                )
+            };
+            let functions: [fn(&CalculatorComplex) -> CalculatorComplex; 5] = [
+                CalculatorComplex::exp,
+                CalculatorComplex::sin,
+                CalculatorComplex::cos,
+                CalculatorComplex::sqrt,
+                |x| x.powf(2.5),
+            ];
+            for function in functions {
+                let expected = function(&numeric);
+                assert!(!function(&symbolic).re.is_float());
+                assert!(expected.isclose(evaluate(function(&symbolic))));
+            }
+            // A symbolic exponent
+            assert!(numeric.powf(2.5).isclose(evaluate(numeric.powf("p"))));
+            assert!(numeric.powf(2.5).isclose(evaluate(symbolic.powf("p"))));
+        }
+    }
+
     // Test the isclose functionality of CalculatorComplex
     #[test]
     fn is_close() {
@@ -821,6 +1806,15 @@ mod tests {
         assert!(!x.isclose(y));
     }
 
+    // Test the conversion of CalculatorComplex to a SymPy compatible string
+    #[test]
+    fn to_sympy_string() {
+        let x = CalculatorComplex::new("a^2", 0.5);
+        assert_eq!(x.to_sympy_string().unwrap(), "(Symbol('a')**2.0) + I*(0.5)");
+        let x = CalculatorComplex::new(1, CalculatorFloat::Str("a=2".to_string()));
+        assert!(x.to_sympy_string().is_err());
+    }
+
     // // Test the negative sign (*-1) functionality of CalculatorComplex
     #[test]
     fn neg() {
@@ -874,5 +1868,64 @@ mod tests {
         assert!(x1s == x2s);
         assert!(x2s == x1s);
     }
+
+    // Test the derivative of the real and imaginary part
+    #[test]
+    fn derivative() {
+        let x = CalculatorComplex::new("x * y", "sin(x)");
+        assert_eq!(
+            x.derivative("x").unwrap(),
+            CalculatorComplex::new("y", "cos(x)")
+        );
+        assert_eq!(x.derivative("y").unwrap(), CalculatorComplex::new("x", 0.0));
+        assert_eq!(x.derivative("z").unwrap(), CalculatorComplex::new(0.0, 0.0));
+        assert!(CalculatorComplex::new(0.0, "tgamma(x)")
+            .derivative("x")
+            .is_err());
+    }
+
+    // Test replacing a variable in both parts
+    #[test]
+    fn substitute() {
+        let x = CalculatorComplex::new("x * y", "sin(x)");
+        assert_eq!(
+            x.substitute("x", &CalculatorFloat::from("z")).unwrap(),
+            CalculatorComplex::new("z * y", "sin(z)")
+        );
+        assert_eq!(
+            x.substitute("x", &CalculatorFloat::ZERO).unwrap(),
+            CalculatorComplex::new("0e0 * y", 0.0)
+        );
+    }
+
+    // Test the explicit conversions between CalculatorComplex and CalculatorFloat
+    #[test]
+    fn promote_demote() {
+        let real = CalculatorComplex::new("x", 0.0);
+        assert_eq!(real.demote(), Ok(CalculatorFloat::from("x")));
+        assert_eq!(real.demote_lossy(), (CalculatorFloat::from("x"), false));
+        assert_eq!(
+            CalculatorComplex::new(2.0, -0.0).demote(),
+            Ok(CalculatorFloat::from(2.0))
+        );
+        // A tiny imaginary part is not dropped silently
+        let tiny = CalculatorComplex::new(1.0, 1e-20);
+        assert_eq!(
+            tiny.demote(),
+            Err(CalculatorError::ComplexCanNotBeConvertedToFloat { val: tiny.clone() })
+        );
+        assert_eq!(tiny.demote_lossy(), (CalculatorFloat::from(1.0), true));
+        let symbolic = CalculatorComplex::new(1.0, "x - x");
+        assert_eq!(
+            symbolic.demote(),
+            Err(CalculatorError::ComplexSymbolicNotConvertable {
+                val: symbolic.clone()
+            })
+        );
+        assert_eq!(symbolic.demote_lossy(), (CalculatorFloat::from(1.0), true));
+        let promoted = CalculatorFloat::from("2 * y").promote();
+        assert_eq!(promoted, CalculatorComplex::new("2 * y", 0.0));
+        assert_eq!(promoted.demote(), Ok(CalculatorFloat::from("2 * y")));
+    }
 }
 // End of tests
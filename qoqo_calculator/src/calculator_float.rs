@@ -15,17 +15,29 @@
 //! Provides CalculatorFloat enum and methods for parsing and evaluating
 //! mathematical expressions in string form to float.
 
-use crate::calculator::{Token, TokenIterator};
-use crate::CalculatorError;
+use crate::calculator::{
+    anonymize_variables, delta, heaviside, number_call_arguments, parse_str, placeholders_in,
+    positioned_tokens, spanned_tokens, validate_expression, variables_in_expression, Token,
+    TokenIterator, REDUCTIONS,
+};
+use crate::canonical_hash;
+use crate::derivative::ExpressionTree;
+use crate::math;
+use crate::special_functions;
+use crate::{write_truncated, CalculatorComplex, CalculatorError};
 #[cfg(feature = "json_schema")]
 use schemars::schema::*;
 use serde::de::{Deserializer, Error, Visitor};
 use serde::ser::Serializer;
 use serde::{Deserialize, Serialize};
+use std::cmp;
+use std::collections::HashMap;
 use std::convert::TryFrom;
 use std::fmt;
 use std::ops;
+use std::ops::Range;
 use std::str::FromStr;
+use std::sync::atomic::{AtomicUsize, Ordering};
 
 static ATOL: f64 = f64::EPSILON;
 static RTOL: f64 = 1e-8;
@@ -37,6 +49,22 @@ static RTOL: f64 = 1e-8;
 /// * `Float` - f64 value
 /// * `Str` - String instance
 ///
+/// The expression grammar of the [crate::Calculator] is the only grammar of `Str` values.
+/// Every expression emitted by an operation on symbolic values can be parsed by the Calculator
+/// and evaluates to the value the operation returns for the numeric values,
+/// which is enforced by the `emitter_roundtrip` tests.
+///
+/// # Serialization
+///
+/// The serialized form depends on the format:
+///
+/// * Human-readable formats (e.g. JSON, YAML) use the bare value: `0.5` or `"theta"`.
+/// * Other formats (e.g. bincode) use the enum-tagged form, which reads `{"Float": 0.5}`
+///   or `{"Str": "theta"}` in JSON.
+///
+/// The helper modules [crate::serialize::always_tagged] and [crate::serialize::always_untagged]
+/// select one form for a field with `#[serde(with = "...")]` independent of the format.
+///
 #[derive(Debug, Clone, PartialEq)]
 // #[cfg_attr(feature = "json_schema", derive(schemars::JsonSchema))]
 pub enum CalculatorFloat {
@@ -60,6 +88,62 @@ impl schemars::JsonSchema for CalculatorFloat {
     }
 }
 
+/// Compare CalculatorFloat with [approx::assert_abs_diff_eq].
+///
+/// Two floats are compared numerically. Symbolic values are only equal to identical symbolic
+/// values and never to a float. The default epsilon is the absolute tolerance of
+/// [CalculatorFloat::isclose].
+#[cfg(feature = "approx")]
+impl approx::AbsDiffEq for CalculatorFloat {
+    type Epsilon = f64;
+
+    fn default_epsilon() -> f64 {
+        ATOL
+    }
+
+    fn abs_diff_eq(&self, other: &Self, epsilon: f64) -> bool {
+        match (self, other) {
+            (Self::Float(x), Self::Float(y)) => x.abs_diff_eq(y, epsilon),
+            (Self::Str(x), Self::Str(y)) => x == y,
+            _ => false,
+        }
+    }
+}
+
+/// Compare CalculatorFloat with [approx::assert_relative_eq], see [approx::AbsDiffEq].
+///
+/// The default maximal relative difference is the relative tolerance of [CalculatorFloat::isclose].
+#[cfg(feature = "approx")]
+impl approx::RelativeEq for CalculatorFloat {
+    fn default_max_relative() -> f64 {
+        RTOL
+    }
+
+    fn relative_eq(&self, other: &Self, epsilon: f64, max_relative: f64) -> bool {
+        match (self, other) {
+            (Self::Float(x), Self::Float(y)) => x.relative_eq(y, epsilon, max_relative),
+            (Self::Str(x), Self::Str(y)) => x == y,
+            _ => false,
+        }
+    }
+}
+
+/// Compare CalculatorFloat with [approx::assert_ulps_eq], see [approx::AbsDiffEq].
+#[cfg(feature = "approx")]
+impl approx::UlpsEq for CalculatorFloat {
+    fn default_max_ulps() -> u32 {
+        f64::default_max_ulps()
+    }
+
+    fn ulps_eq(&self, other: &Self, epsilon: f64, max_ulps: u32) -> bool {
+        match (self, other) {
+            (Self::Float(x), Self::Float(y)) => x.ulps_eq(y, epsilon, max_ulps),
+            (Self::Str(x), Self::Str(y)) => x == y,
+            _ => false,
+        }
+    }
+}
+
 /// Implement Default value 0 for CalculatorFloat.
 impl Default for CalculatorFloat {
     fn default() -> Self {
@@ -68,7 +152,13 @@ impl Default for CalculatorFloat {
 }
 
 // Implementing serde serialization
-// writing directly to string or f64.
+// writing directly to string or f64 in human-readable formats (e.g. JSON: `0.5` or `"theta"`)
+// and as enum variant otherwise (e.g. bincode). In formats like JSON the enum variant
+// has the form `{"Float": 0.5}` or `{"Str": "theta"}`.
+// See `serialize::always_tagged` and `serialize::always_untagged` to use one form
+// independent of the format.
+// Human-readable formats like JSON can not represent infinities and NaN, in the untagged
+// form they are written as the strings `"inf"`, `"-inf"` and `"NaN"`, see `non_finite_from_str`.
 impl Serialize for CalculatorFloat {
     // Serialization function for CalculatorFloat according to float or string type.
     //
@@ -86,21 +176,45 @@ impl Serialize for CalculatorFloat {
     where
         S: Serializer,
     {
-        let human_readable = serializer.is_human_readable();
-        if human_readable {
-            match self {
-                CalculatorFloat::Float(x) => serializer.serialize_f64(*x),
-                CalculatorFloat::Str(x) => serializer.serialize_str(x),
-            }
+        if serializer.is_human_readable() {
+            serialize_untagged(self, serializer)
         } else {
-            match self {
-                CalculatorFloat::Float(x) => {
-                    serializer.serialize_newtype_variant("CalculatorFloat", 0, "Float", x)
-                }
-                CalculatorFloat::Str(x) => {
-                    serializer.serialize_newtype_variant("CalculatorFloat", 1, "Str", x)
-                }
-            }
+            serialize_tagged(self, serializer)
+        }
+    }
+}
+
+/// Serialize a CalculatorFloat as a bare float or string.
+///
+/// In human-readable formats non-finite floats are written as the strings `"inf"`, `"-inf"`
+/// and `"NaN"`, which are deserialized to the same Float values.
+pub(crate) fn serialize_untagged<S>(
+    value: &CalculatorFloat,
+    serializer: S,
+) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    match value {
+        CalculatorFloat::Float(x) if !x.is_finite() && serializer.is_human_readable() => {
+            serializer.serialize_str(non_finite_to_str(*x))
+        }
+        CalculatorFloat::Float(x) => serializer.serialize_f64(*x),
+        CalculatorFloat::Str(x) => serializer.serialize_str(x),
+    }
+}
+
+/// Serialize a CalculatorFloat in the enum-tagged form `Float(f64)` or `Str(String)`.
+pub(crate) fn serialize_tagged<S>(value: &CalculatorFloat, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    match value {
+        CalculatorFloat::Float(x) => {
+            serializer.serialize_newtype_variant("CalculatorFloat", 0, "Float", x)
+        }
+        CalculatorFloat::Str(x) => {
+            serializer.serialize_newtype_variant("CalculatorFloat", 1, "Str", x)
         }
     }
 }
@@ -123,235 +237,346 @@ impl<'de> Deserialize<'de> for CalculatorFloat {
     where
         D: Deserializer<'de>,
     {
-        let human_readable = deserializer.is_human_readable();
-        if human_readable {
-            struct TemporaryVisitor;
-            impl<'de> Visitor<'de> for TemporaryVisitor {
-                type Value = CalculatorFloat;
-
-                // Visit expectation for CalculatorFloatVisitor.
-                //
-                // # Arguments
-                //
-                // * `self` - Error
-                // * `formatter` - Configuration for formatting
-                //
-                // # Returns
-                //
-                // `str` - What TemporaryVisitor should expect
-                //
-                fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
-                    formatter.write_str("float or string")
-                }
+        if deserializer.is_human_readable() {
+            deserialize_untagged(deserializer)
+        } else {
+            deserialize_tagged(deserializer)
+        }
+    }
+}
 
-                // Visit function for string value.
-                //
-                // # Arguments
-                //
-                // * `self` - Error
-                // * `value` - value to be deserialized
-                //
-                // # Returns
-                //
-                // `Result<CalculatorFloat, E>` - CalculatorFloat of value or corresponding error
-                //
-                fn visit_str<E>(self, value: &str) -> Result<CalculatorFloat, E>
-                where
-                    E: Error,
-                {
-                    Ok(CalculatorFloat::from(value))
-                }
+/// Deserialize a CalculatorFloat from a bare float or string.
+///
+/// Strings that can be parsed as float are deserialized to CalculatorFloat::Float,
+/// including the spellings of infinities and NaN accepted by [non_finite_from_str].
+/// Plain decimal literals like `"0.3"` are kept as CalculatorFloat::Str, see [CalculatorFloat::decimal].
+/// Requires a self-describing format.
+pub(crate) fn deserialize_untagged<'de, D>(deserializer: D) -> Result<CalculatorFloat, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    struct TemporaryVisitor;
+    impl<'de> Visitor<'de> for TemporaryVisitor {
+        type Value = CalculatorFloat;
+
+        // Visit expectation for CalculatorFloatVisitor.
+        //
+        // # Arguments
+        //
+        // * `self` - Error
+        // * `formatter` - Configuration for formatting
+        //
+        // # Returns
+        //
+        // `str` - What TemporaryVisitor should expect
+        //
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            formatter.write_str("float or string")
+        }
 
-                // Visit function for f64 value.
-                //
-                // # Arguments
-                //
-                // * `self` - Error
-                // * `value` - value to be deserialized
-                //
-                // # Returns
-                //
-                // `Result<CalculatorFloat, E>` - CalculatorFloat of value or corresponding error
-                //
-                fn visit_f64<E>(self, value: f64) -> Result<CalculatorFloat, E>
-                where
-                    E: Error,
-                {
-                    Ok(CalculatorFloat::from(value))
-                }
+        // Visit function for string value.
+        //
+        // # Arguments
+        //
+        // * `self` - Error
+        // * `value` - value to be deserialized
+        //
+        // # Returns
+        //
+        // `Result<CalculatorFloat, E>` - CalculatorFloat of value or corresponding error
+        //
+        fn visit_str<E>(self, value: &str) -> Result<CalculatorFloat, E>
+        where
+            E: Error,
+        {
+            check_deserialized_length(value.len())?;
+            if let Some(x) = non_finite_from_str(value) {
+                return Ok(CalculatorFloat::Float(x));
+            }
+            if parse_decimal_literal(value).is_some() {
+                return Ok(CalculatorFloat::Str(value.to_string()));
+            }
+            match f64::from_str(value) {
+                Ok(x) => Ok(CalculatorFloat::Float(x)),
+                Err(_) => deserialized_str(value.to_string()),
+            }
+        }
 
-                // Visit function for u64 value.
-                //
-                // # Arguments
-                //
-                // * `self` - Error
-                // * `value` - value to be deserialized
-                //
-                // # Returns
-                //
-                // `Result<CalculatorFloat, E>` - CalculatorFloat of value or corresponding error
-                //
-                fn visit_u64<E>(self, value: u64) -> Result<CalculatorFloat, E>
-                where
-                    E: Error,
-                {
-                    Ok(CalculatorFloat::from(value))
-                }
+        // Visit function for f64 value.
+        //
+        // # Arguments
+        //
+        // * `self` - Error
+        // * `value` - value to be deserialized
+        //
+        // # Returns
+        //
+        // `Result<CalculatorFloat, E>` - CalculatorFloat of value or corresponding error
+        //
+        fn visit_f64<E>(self, value: f64) -> Result<CalculatorFloat, E>
+        where
+            E: Error,
+        {
+            Ok(CalculatorFloat::from(value))
+        }
 
-                // Visit function for i32 value.
-                //
-                // # Arguments
-                //
-                // * `self` - Error
-                // * `value` - value to be deserialized
-                //
-                // # Returns
-                //
-                // `Result<CalculatorFloat, E>` - CalculatorFloat of value or corresponding error
-                //
-                fn visit_i32<E>(self, value: i32) -> Result<CalculatorFloat, E>
-                where
-                    E: Error,
-                {
-                    Ok(CalculatorFloat::from(value))
-                }
+        // Visit function for u64 value.
+        //
+        // # Arguments
+        //
+        // * `self` - Error
+        // * `value` - value to be deserialized
+        //
+        // # Returns
+        //
+        // `Result<CalculatorFloat, E>` - CalculatorFloat of value or corresponding error
+        //
+        fn visit_u64<E>(self, value: u64) -> Result<CalculatorFloat, E>
+        where
+            E: Error,
+        {
+            Ok(CalculatorFloat::from(value))
+        }
 
-                // Visit function for i64 value.
-                //
-                // # Arguments
-                //
-                // * `self` - Error
-                // * `value` - value to be deserialized
-                //
-                // # Returns
-                //
-                // `Result<CalculatorFloat, E>` - CalculatorFloat of value or corresponding error
-                //
-                fn visit_i64<E>(self, value: i64) -> Result<CalculatorFloat, E>
-                where
-                    E: Error,
-                {
-                    Ok(CalculatorFloat::from(value))
-                }
+        // Visit function for i32 value.
+        //
+        // # Arguments
+        //
+        // * `self` - Error
+        // * `value` - value to be deserialized
+        //
+        // # Returns
+        //
+        // `Result<CalculatorFloat, E>` - CalculatorFloat of value or corresponding error
+        //
+        fn visit_i32<E>(self, value: i32) -> Result<CalculatorFloat, E>
+        where
+            E: Error,
+        {
+            Ok(CalculatorFloat::from(value))
+        }
 
-                // Visit function for u32 value.
-                //
-                // # Arguments
-                //
-                // * `self` - Error
-                // * `value` - value to be deserialized
-                //
-                // # Returns
-                //
-                // `Result<CalculatorFloat, E>` - CalculatorFloat of value or corresponding error
-                //
-                fn visit_u32<E>(self, value: u32) -> Result<CalculatorFloat, E>
-                where
-                    E: Error,
-                {
-                    Ok(CalculatorFloat::from(value))
-                }
-            }
+        // Visit function for i64 value.
+        //
+        // # Arguments
+        //
+        // * `self` - Error
+        // * `value` - value to be deserialized
+        //
+        // # Returns
+        //
+        // `Result<CalculatorFloat, E>` - CalculatorFloat of value or corresponding error
+        //
+        fn visit_i64<E>(self, value: i64) -> Result<CalculatorFloat, E>
+        where
+            E: Error,
+        {
+            Ok(CalculatorFloat::from(value))
+        }
 
-            deserializer.deserialize_any(TemporaryVisitor)
-        } else {
-            // Marker struct for the Variants of CalculatorFlot
-            enum Variant {
-                Float,
-                Str,
+        // Visit function for u32 value.
+        //
+        // # Arguments
+        //
+        // * `self` - Error
+        // * `value` - value to be deserialized
+        //
+        // # Returns
+        //
+        // `Result<CalculatorFloat, E>` - CalculatorFloat of value or corresponding error
+        //
+        fn visit_u32<E>(self, value: u32) -> Result<CalculatorFloat, E>
+        where
+            E: Error,
+        {
+            Ok(CalculatorFloat::from(value))
+        }
+    }
+
+    deserializer.deserialize_any(TemporaryVisitor)
+}
+
+/// Deserialize a CalculatorFloat from the enum-tagged form `Float(f64)` or `Str(String)`.
+pub(crate) fn deserialize_tagged<'de, D>(deserializer: D) -> Result<CalculatorFloat, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    // Marker struct for the Variants of CalculatorFlot
+    enum Variant {
+        Float,
+        Str,
+    }
+    // Visitor extracting the Variant of the serialized CalculatorFloat enum
+    struct VariantVisitor;
+    impl<'de> serde::de::Visitor<'de> for VariantVisitor {
+        type Value = Variant;
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            fmt::Formatter::write_str(formatter, "Identifier of CalculatorFloat variant")
+        }
+        // when variants are marked by u64 values
+        fn visit_u64<E>(self, value: u64) -> Result<Self::Value, E>
+        where
+            E: Error,
+        {
+            match value {
+                0u64 => Ok(Variant::Float),
+                1u64 => Ok(Variant::Str),
+                _ => Err(Error::invalid_value(
+                    serde::de::Unexpected::Unsigned(value),
+                    &"CalculatorFloat has two variants, expecting field identifier 0 or 1",
+                )),
             }
-            // Visitor extracting the Variant of the serialized CalculatorFloat enum
-            struct VariantVisitor;
-            impl<'de> serde::de::Visitor<'de> for VariantVisitor {
-                type Value = Variant;
-                fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
-                    fmt::Formatter::write_str(formatter, "Identifier of CalculatorFloat variant")
-                }
-                // when variants are marked by u64 values
-                fn visit_u64<E>(self, value: u64) -> Result<Self::Value, E>
-                where
-                    E: Error,
-                {
-                    match value {
-                        0u64 => Ok(Variant::Float),
-                        1u64 => Ok(Variant::Str),
-                        _ => Err(Error::invalid_value(
-                            serde::de::Unexpected::Unsigned(value),
-                            &"CalculatorFloat has two variants, expecting field identifier 0 or 1",
-                        )),
-                    }
-                }
-                // when variants are marked by String values
-                fn visit_str<E>(self, value: &str) -> Result<Self::Value, E>
-                where
-                    E: Error,
-                {
-                    match value {
-                        "Float" => Ok(Variant::Float),
-                        "Str" => Ok(Variant::Str),
-                        _ => Err(Error::unknown_variant(value, VARIANTS)),
-                    }
-                }
-                // when variants are marked by Strings as byte fields
-                fn visit_bytes<E>(self, value: &[u8]) -> Result<Self::Value, E>
-                where
-                    E: Error,
-                {
-                    match value {
-                        b"Float" => Ok(Variant::Float),
-                        b"Str" => Ok(Variant::Str),
-                        _ => {
-                            let unknown_variant_string =
-                                &std::string::String::from_utf8_lossy(value);
-                            Err(Error::unknown_variant(unknown_variant_string, VARIANTS))
-                        }
-                    }
-                }
+        }
+        // when variants are marked by String values
+        fn visit_str<E>(self, value: &str) -> Result<Self::Value, E>
+        where
+            E: Error,
+        {
+            match value {
+                "Float" => Ok(Variant::Float),
+                "Str" => Ok(Variant::Str),
+                _ => Err(Error::unknown_variant(value, VARIANTS)),
             }
-            impl<'de> serde::Deserialize<'de> for Variant {
-                #[inline]
-                fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
-                where
-                    D: serde::Deserializer<'de>,
-                {
-                    serde::Deserializer::deserialize_identifier(deserializer, VariantVisitor)
+        }
+        // when variants are marked by Strings as byte fields
+        fn visit_bytes<E>(self, value: &[u8]) -> Result<Self::Value, E>
+        where
+            E: Error,
+        {
+            match value {
+                b"Float" => Ok(Variant::Float),
+                b"Str" => Ok(Variant::Str),
+                _ => {
+                    let unknown_variant_string = &std::string::String::from_utf8_lossy(value);
+                    Err(Error::unknown_variant(unknown_variant_string, VARIANTS))
                 }
             }
-            struct Visitor {}
-            impl<'de> serde::de::Visitor<'de> for Visitor {
-                type Value = CalculatorFloat;
-                fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
-                    fmt::Formatter::write_str(formatter, "enum CalculatorFloat")
-                }
-                fn visit_enum<A>(self, data: A) -> Result<Self::Value, A::Error>
-                where
-                    A: serde::de::EnumAccess<'de>,
-                {
-                    match match serde::de::EnumAccess::variant(data) {
-                        Ok(extracted_data) => extracted_data,
-                        Err(error) => {
-                            return Err(error);
-                        }
-                    } {
-                        (Variant::Float, variant) => Result::map(
-                            serde::de::VariantAccess::newtype_variant::<f64>(variant),
-                            CalculatorFloat::Float,
-                        ),
-                        (Variant::Str, variant) => Result::map(
-                            serde::de::VariantAccess::newtype_variant::<String>(variant),
-                            CalculatorFloat::Str,
-                        ),
-                    }
+        }
+    }
+    impl<'de> serde::Deserialize<'de> for Variant {
+        #[inline]
+        fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where
+            D: serde::Deserializer<'de>,
+        {
+            serde::Deserializer::deserialize_identifier(deserializer, VariantVisitor)
+        }
+    }
+    struct Visitor {}
+    impl<'de> serde::de::Visitor<'de> for Visitor {
+        type Value = CalculatorFloat;
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            fmt::Formatter::write_str(formatter, "enum CalculatorFloat")
+        }
+        fn visit_enum<A>(self, data: A) -> Result<Self::Value, A::Error>
+        where
+            A: serde::de::EnumAccess<'de>,
+        {
+            match match serde::de::EnumAccess::variant(data) {
+                Ok(extracted_data) => extracted_data,
+                Err(error) => {
+                    return Err(error);
                 }
+            } {
+                (Variant::Float, variant) => Result::map(
+                    serde::de::VariantAccess::newtype_variant::<f64>(variant),
+                    CalculatorFloat::Float,
+                ),
+                (Variant::Str, variant) => Result::and_then(
+                    serde::de::VariantAccess::newtype_variant::<BoundedExpression>(variant),
+                    |expression| deserialized_str(expression.0),
+                ),
+            }
+        }
+    }
+    const VARIANTS: &[&str] = &["Float", "Str"];
+    serde::Deserializer::deserialize_enum(deserializer, "CalculatorFloat", VARIANTS, Visitor {})
+}
+
+/// Default maximum length of deserialized symbolic expressions, unlimited.
+pub const DEFAULT_MAX_DESERIALIZED_EXPRESSION_LENGTH: usize = usize::MAX;
+
+/// Maximum length of deserialized symbolic expressions.
+static MAX_DESERIALIZED_EXPRESSION_LENGTH: AtomicUsize =
+    AtomicUsize::new(DEFAULT_MAX_DESERIALIZED_EXPRESSION_LENGTH);
+
+/// Set the maximum length of strings accepted when deserializing a CalculatorFloat.
+///
+/// Deserialization accepts symbolic expressions of any length by default. Services storing
+/// untrusted data before evaluating it can set a limit, so that overlong expressions are
+/// rejected when they are deserialized instead of exhausting memory or the stack of the
+/// parser when they are evaluated later. Longer strings fail with a custom error of the
+/// deserializer holding [CalculatorError::DeserializedExpressionTooLong].
+/// In the enum-tagged form the length is checked before the string is copied.
+///
+/// The limit is global for the process and applies to CalculatorComplex and all other
+/// types containing a CalculatorFloat.
+///
+/// # Arguments
+///
+/// * `limit` - Maximum length in bytes, [DEFAULT_MAX_DESERIALIZED_EXPRESSION_LENGTH] for no limit
+///
+pub fn set_max_deserialized_expression_length(limit: usize) {
+    MAX_DESERIALIZED_EXPRESSION_LENGTH.store(limit, Ordering::Relaxed);
+}
+
+/// Return the maximum length of strings accepted when deserializing a CalculatorFloat.
+///
+/// See [set_max_deserialized_expression_length].
+pub fn max_deserialized_expression_length() -> usize {
+    MAX_DESERIALIZED_EXPRESSION_LENGTH.load(Ordering::Relaxed)
+}
+
+/// Check the length of a deserialized string against [max_deserialized_expression_length].
+///
+/// # Arguments
+///
+/// * `length` - Length of the string in bytes
+///
+fn check_deserialized_length<E>(length: usize) -> Result<(), E>
+where
+    E: Error,
+{
+    let limit = max_deserialized_expression_length();
+    if length > limit {
+        return Err(E::custom(CalculatorError::DeserializedExpressionTooLong {
+            length,
+            limit,
+        }));
+    }
+    Ok(())
+}
+
+/// Symbolic expression whose length is checked before it is copied.
+struct BoundedExpression(String);
+
+impl<'de> Deserialize<'de> for BoundedExpression {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct ExpressionVisitor;
+        impl<'de> Visitor<'de> for ExpressionVisitor {
+            type Value = BoundedExpression;
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("string")
+            }
+            fn visit_str<E>(self, value: &str) -> Result<Self::Value, E>
+            where
+                E: Error,
+            {
+                check_deserialized_length(value.len())?;
+                Ok(BoundedExpression(value.to_string()))
+            }
+            fn visit_string<E>(self, value: String) -> Result<Self::Value, E>
+            where
+                E: Error,
+            {
+                check_deserialized_length(value.len())?;
+                Ok(BoundedExpression(value))
             }
-            const VARIANTS: &[&str] = &["Float", "Str"];
-            serde::Deserializer::deserialize_enum(
-                deserializer,
-                "CalculatorFloat",
-                VARIANTS,
-                Visitor {},
-            )
         }
+        deserializer.deserialize_str(ExpressionVisitor)
     }
 }
 
@@ -481,11 +706,19 @@ impl<'a> From<&'a f64> for CalculatorFloat {
 ///
 /// * `CalculatorFloat::Str`
 ///
+/// # Panics
+///
+/// Panics when the string is not a valid expression in debug builds or with the
+/// `strict-construction` feature. Use `CalculatorFloat::from_str` to get an error instead.
+///
 impl From<String> for CalculatorFloat {
     fn from(item: String) -> Self {
         let f = f64::from_str(item.as_str());
         match f {
-            Err(_) => CalculatorFloat::Str(item),
+            Err(_) => {
+                assert_valid_expression(&item);
+                CalculatorFloat::Str(item)
+            }
             Ok(x) => CalculatorFloat::Float(x),
         }
     }
@@ -497,11 +730,19 @@ impl From<String> for CalculatorFloat {
 ///
 /// * `CalculatorFloat::Float`
 ///
+/// # Panics
+///
+/// Panics when the string is not a valid expression in debug builds or with the
+/// `strict-construction` feature. Use `CalculatorFloat::from_str` to get an error instead.
+///
 impl From<&String> for CalculatorFloat {
     fn from(item: &String) -> Self {
         let f = f64::from_str(item.as_str());
         match f {
-            Err(_) => CalculatorFloat::Str(item.clone()),
+            Err(_) => {
+                assert_valid_expression(item);
+                CalculatorFloat::Str(item.clone())
+            }
             Ok(x) => CalculatorFloat::Float(x),
         }
     }
@@ -513,11 +754,19 @@ impl From<&String> for CalculatorFloat {
 ///
 /// * `CalculatorFloat::Float`
 ///
+/// # Panics
+///
+/// Panics when the string is not a valid expression in debug builds or with the
+/// `strict-construction` feature. Use `CalculatorFloat::from_str` to get an error instead.
+///
 impl From<&str> for CalculatorFloat {
     fn from(item: &str) -> Self {
         let f = f64::from_str(item);
         match f {
-            Err(_) => CalculatorFloat::Str(String::from(item)),
+            Err(_) => {
+                assert_valid_expression(item);
+                CalculatorFloat::Str(String::from(item))
+            }
             Ok(x) => CalculatorFloat::Float(x),
         }
     }
@@ -530,31 +779,96 @@ impl FromStr for CalculatorFloat {
         let f = f64::from_str(s);
         match f {
             Err(_) => {
-                let mut tokeniter = TokenIterator {
-                    current_expression: s,
-                };
-                match tokeniter.find(|t| {
-                    matches!(
-                        t,
-                        Token::VariableAssign(_) | Token::Assign | Token::Unrecognized
-                    )
-                }) {
-                    None => Ok(CalculatorFloat::Str(s.to_string())),
-                    Some(t) => match t {
-                        Token::VariableAssign(vs) => {
-                            Err(CalculatorError::NotParsableAssign { variable_name: vs })
-                        }
-                        Token::Assign => Err(CalculatorError::NotParsableSingleAssign),
-                        Token::Unrecognized => Err(CalculatorError::NotParsableUnrecognized),
-                        _ => panic!(""),
-                    },
-                }
+                validate_expression(s)?;
+                Ok(CalculatorFloat::Str(s.to_string()))
             }
             Ok(x) => Ok(CalculatorFloat::Float(x)),
         }
     }
 }
 
+/// Check a symbolic expression at the construction site of a CalculatorFloat::Str.
+///
+/// Uses the full check of [crate::validate_expression], including balanced brackets.
+/// The check only runs in debug builds or when the `strict-construction` feature is enabled,
+/// so the default release behaviour of the infallible constructors is unchanged.
+///
+/// # Panics
+///
+/// Panics with the offending expression when it is not valid.
+///
+fn assert_valid_expression(expression: &str) {
+    if cfg!(any(debug_assertions, feature = "strict-construction")) {
+        if let Err(err) = validate_expression(expression) {
+            panic!(
+                "Invalid expression {expression:?} used to construct CalculatorFloat::Str: {err}"
+            );
+        }
+    }
+}
+
+/// Create a CalculatorFloat::Str from a deserialized expression.
+///
+/// With the `strict-construction` feature an invalid expression is returned as a
+/// deserialization error containing the offending expression instead of panicking.
+///
+/// # Arguments
+///
+/// * `expression` - The deserialized symbolic expression
+///
+fn deserialized_str<E>(expression: String) -> Result<CalculatorFloat, E>
+where
+    E: Error,
+{
+    if cfg!(feature = "strict-construction") {
+        validate_expression(&expression).map_err(|err| {
+            E::custom(format!(
+                "Invalid expression {expression:?} used to construct CalculatorFloat::Str: {err}"
+            ))
+        })?;
+    } else {
+        assert_valid_expression(&expression);
+    }
+    Ok(CalculatorFloat::Str(expression))
+}
+
+/// Return true when x is close to y with the tolerances of [CalculatorFloat::isclose].
+#[inline]
+fn floats_close(x: f64, y: f64) -> bool {
+    (x - y).abs() <= (ATOL + RTOL * y.abs())
+}
+
+/// Return the string written for a non-finite float in human-readable formats.
+fn non_finite_to_str(x: f64) -> &'static str {
+    if x.is_nan() {
+        "NaN"
+    } else if x > 0.0 {
+        "inf"
+    } else {
+        "-inf"
+    }
+}
+
+/// Return the non-finite float encoded by a string.
+///
+/// Accepts `inf` and `infinity` with an optional sign and `nan`, all case-insensitive
+/// (e.g. `"inf"`, `"+Inf"`, `"-Infinity"`, `"NaN"`).
+/// Returns None for all other strings.
+pub(crate) fn non_finite_from_str(value: &str) -> Option<f64> {
+    let (sign, unsigned) = match value.as_bytes().first() {
+        Some(b'-') => (-1.0, &value[1..]),
+        Some(b'+') => (1.0, &value[1..]),
+        _ => (1.0, value),
+    };
+    if unsigned.eq_ignore_ascii_case("inf") || unsigned.eq_ignore_ascii_case("infinity") {
+        Some(sign * f64::INFINITY)
+    } else if unsigned.eq_ignore_ascii_case("nan") {
+        Some(f64::NAN)
+    } else {
+        None
+    }
+}
+
 /// Try turning CalculatorFloat into f64 float.
 ///
 /// # Returns
@@ -600,187 +914,2077 @@ impl<'a> From<&'a CalculatorFloat> for CalculatorFloat {
     }
 }
 
-/// Implement Display trait for CalculatorFloat.
+/// Format a float value embedded in a symbolic expression.
 ///
-/// Allows use of simple text formating
+/// All numeric values written into composed symbolic strings and the Display of
+/// CalculatorFloat::Float use this format, so equal values give identical strings.
+/// The format is the shortest exponential notation that parses back to the
+/// bit-identical f64, including subnormals, -0.0 and values that need 17 significant
+/// digits like `1/3`. Symbolic expressions therefore evaluate to the same value
+/// as the operations on floats they were composed of.
 ///
-impl fmt::Display for CalculatorFloat {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self {
-            CalculatorFloat::Float(x) => write!(f, "{x:e}"),
-            CalculatorFloat::Str(y) => write!(f, "{y}"),
-        }
-    }
+/// # Arguments
+///
+/// * `value` - Float value that is formatted
+///
+pub(crate) fn format_float(value: f64) -> String {
+    format!("{value:e}")
 }
 
-impl CalculatorFloat {
-    /// Constant zero for CalculatorFloat
-    pub const ZERO: CalculatorFloat = CalculatorFloat::Float(0.0);
+/// Return true when a float operand is exactly zero, see [CalculatorFloat::is_zero].
+#[inline]
+fn is_zero_value(value: f64) -> bool {
+    value == 0.0
+}
 
-    /// Constant one for CalculatorFloat
-    pub const ONE: CalculatorFloat = CalculatorFloat::Float(1.0);
+/// Return true when a float operand is exactly one, see [CalculatorFloat::is_one].
+#[inline]
+fn is_one_value(value: f64) -> bool {
+    value == 1.0
+}
 
-    /// Constant pi for CalculatorFloat
-    pub const PI: CalculatorFloat = CalculatorFloat::Float(std::f64::consts::PI);
+/// Largest integer below which all integers are exactly representable by f64 (2^53).
+const MAX_EXACT_INTEGER: f64 = 9007199254740992.0;
 
-    /// Constant Euler's number e for CalculatorFloat
-    pub const E: CalculatorFloat = CalculatorFloat::Float(std::f64::consts::E);
+/// Numerator and positive denominator of a fraction.
+type Fraction = (i128, i128);
 
-    /// Constant 1/sqrt(2) e for CalculatorFloat
-    pub const FRAC_1_SQRT_2: CalculatorFloat =
-        CalculatorFloat::Float(std::f64::consts::FRAC_1_SQRT_2);
+/// Return the greatest common divisor of two integers, gcd(0, 0) is 0.
+fn gcd(a: i128, b: i128) -> i128 {
+    let (mut a, mut b) = (a.abs(), b.abs());
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    a
+}
 
-    /// Constant pi / 2 e for CalculatorFloat
-    pub const FRAC_PI_2: CalculatorFloat = CalculatorFloat::Float(std::f64::consts::FRAC_PI_2);
+/// Reduce a fraction and move its sign to the numerator, None when the denominator is zero.
+fn reduce_fraction(numerator: i128, denominator: i128) -> Option<Fraction> {
+    if denominator == 0 {
+        return None;
+    }
+    let divisor = gcd(numerator, denominator) * denominator.signum();
+    Some((numerator / divisor, denominator / divisor))
+}
 
-    /// Constant pi / 4 e for CalculatorFloat
-    pub const FRAC_PI_4: CalculatorFloat = CalculatorFloat::Float(std::f64::consts::FRAC_PI_4);
+/// Return the expression `(numerator / denominator)` of a reduced fraction.
+///
+/// Returns None when the numerator or denominator does not fit into i64.
+fn rational_expression((numerator, denominator): Fraction) -> Option<String> {
+    let numerator = i64::try_from(numerator).ok()?;
+    let denominator = i64::try_from(denominator).ok()?;
+    Some(format!("({numerator} / {denominator})"))
+}
 
-    /// Constant sqrt(2) e for CalculatorFloat
-    pub const SQRT_2: CalculatorFloat = CalculatorFloat::Float(std::f64::consts::SQRT_2);
+/// Return the rational literal of a reduced fraction, None when it does not fit into i64.
+fn rational_literal(fraction: Fraction) -> Option<CalculatorFloat> {
+    rational_expression(fraction).map(CalculatorFloat::Str)
+}
 
-    /// Return True when CalculatorFloat does not contain symbolic expression.
-    pub fn is_float(&self) -> bool {
-        match self {
-            CalculatorFloat::Float(_) => true,
-            CalculatorFloat::Str(_) => false,
+/// Parse a rational literal in the canonical form created by [CalculatorFloat::rational].
+///
+/// # Arguments
+///
+/// * `expression` - Symbolic expression
+///
+fn parse_rational_literal(expression: &str) -> Option<Fraction> {
+    let (numerator, denominator) = expression
+        .strip_prefix('(')?
+        .strip_suffix(')')?
+        .split_once(" / ")?;
+    let fraction = (
+        i128::from(numerator.parse::<i64>().ok()?),
+        i128::from(denominator.parse::<i64>().ok()?),
+    );
+    // Only the reduced form with positive denominator without signs or zeros in front is canonical
+    let canonical = reduce_fraction(fraction.0, fraction.1)
+        .and_then(rational_expression)
+        .filter(|literal| literal == expression);
+    canonical.map(|_| fraction)
+}
+
+/// Largest number of digits of decimal literals, their digits always fit into i64.
+const MAX_DECIMAL_DIGITS: usize = 18;
+
+/// Parse a plain decimal literal like `-0.25` as created by [CalculatorFloat::decimal].
+///
+/// Returns the unreduced fraction `digits / 10^decimals`, None for other expressions.
+///
+/// # Arguments
+///
+/// * `expression` - Symbolic expression
+///
+fn parse_decimal_literal(expression: &str) -> Option<Fraction> {
+    let unsigned = expression.strip_prefix('-').unwrap_or(expression);
+    let (integer, decimals) = unsigned.split_once('.')?;
+    let is_digits = |part: &str| !part.is_empty() && part.bytes().all(|b| b.is_ascii_digit());
+    if !is_digits(integer)
+        || !is_digits(decimals)
+        || integer.len() + decimals.len() > MAX_DECIMAL_DIGITS
+    {
+        return None;
+    }
+    let digits: i128 = format!("{integer}{decimals}").parse().ok()?;
+    let sign = if unsigned.len() < expression.len() {
+        -1
+    } else {
+        1
+    };
+    Some((sign * digits, 10_i128.pow(decimals.len() as u32)))
+}
+
+/// Return the decimal literal of a reduced fraction, e.g. `"0.25"` for 1 / 4.
+///
+/// Integers are written with one decimal like `"2.0"`. Returns None when the denominator has
+/// prime factors other than 2 and 5 or the literal has more than [MAX_DECIMAL_DIGITS] digits.
+fn decimal_literal((numerator, denominator): Fraction) -> Option<CalculatorFloat> {
+    let (mut digits, mut remaining, mut decimals) = (numerator, denominator, 0);
+    while remaining != 1 {
+        if remaining % 10 == 0 {
+            remaining /= 10;
+        } else if remaining % 2 == 0 {
+            remaining /= 2;
+            digits = digits.checked_mul(5)?;
+        } else if remaining % 5 == 0 {
+            remaining /= 5;
+            digits = digits.checked_mul(2)?;
+        } else {
+            return None;
         }
+        decimals += 1;
     }
-    /// Return square root of CalculatorFloat.
-    pub fn sqrt(&self) -> CalculatorFloat {
-        match self {
-            CalculatorFloat::Float(f) => CalculatorFloat::Float(f.sqrt()),
-            CalculatorFloat::Str(s) => CalculatorFloat::Str(format!("sqrt({s})")),
+    if decimals == 0 {
+        digits *= 10;
+        decimals = 1;
+    }
+    let unsigned = format!("{:0>width$}", digits.unsigned_abs(), width = decimals + 1);
+    let (integer, fraction) = unsigned.split_at(unsigned.len() - decimals);
+    let sign = if digits < 0 { "-" } else { "" };
+    let literal = format!("{sign}{integer}.{fraction}");
+    parse_decimal_literal(&literal).map(|_| CalculatorFloat::Str(literal))
+}
+
+/// Return true when the bracket opened at `open` is closed by the last character of the expression.
+///
+/// # Arguments
+///
+/// * `expression` - Symbolic expression
+/// * `open` - Byte index of an opening bracket in the expression
+///
+fn bracket_closes_at_end(expression: &str, open: usize) -> bool {
+    let bytes = expression.as_bytes();
+    if bytes.get(open) != Some(&b'(') {
+        return false;
+    }
+    let mut depth: usize = 0;
+    for (index, byte) in bytes.iter().enumerate().skip(open) {
+        match byte {
+            b'(' => depth += 1,
+            b')' => {
+                depth -= 1;
+                if depth == 0 {
+                    return index == bytes.len() - 1;
+                }
+            }
+            _ => (),
         }
     }
-    /// Return atan2 for CalculatorFloat and generic type `T`.
-    ///
-    /// # Arguments
-    ///
-    /// * `other` - Any type T for which CalculatorFloat::From<T> trait is implemented
+    false
+}
+
+/// Return the argument when the whole expression is a single call of the function `name`.
+///
+/// Declines (returns None) for expressions like `abs(x) * abs(y)` where the
+/// bracket of the call closes before the end of the expression.
+///
+/// # Arguments
+///
+/// * `expression` - Symbolic expression
+/// * `name` - Name of the function
+///
+fn strip_call<'a>(expression: &'a str, name: &str) -> Option<&'a str> {
+    if expression.starts_with(name) && bracket_closes_at_end(expression, name.len()) {
+        Some(&expression[name.len() + 1..expression.len() - 1])
+    } else {
+        None
+    }
+}
+
+/// Return true when the expression is a single operand the unary minus applies to as a whole.
+///
+/// Accepts names and numbers without signs, fully bracketed expressions and single function calls.
+fn is_single_operand(expression: &str) -> bool {
+    let name_length = expression
+        .bytes()
+        .take_while(|byte| byte.is_ascii_alphanumeric() || *byte == b'_')
+        .count();
+    name_length == expression.len() && name_length > 0
+        || bracket_closes_at_end(expression, name_length)
+}
+
+/// Return the negated operand when the expression is a negation `(-x)` as created by `Neg`.
+///
+/// Declines for expressions like `(-a + b)` where the minus does not apply to the whole rest.
+fn strip_negation(expression: &str) -> Option<&str> {
+    if expression.starts_with("(-") && bracket_closes_at_end(expression, 0) {
+        let operand = &expression[2..expression.len() - 1];
+        if is_single_operand(operand) {
+            return Some(operand);
+        }
+    }
+    None
+}
+
+/// Default maximum length of symbolic expressions combined by arithmetic operations (1 MiB).
+pub const DEFAULT_MAX_SYMBOLIC_LENGTH: usize = 1 << 20;
+
+/// Maximum length of symbolic expressions combined by arithmetic operations.
+static MAX_SYMBOLIC_LENGTH: AtomicUsize = AtomicUsize::new(DEFAULT_MAX_SYMBOLIC_LENGTH);
+
+/// Set the maximum length of symbolic expressions combined by arithmetic operations.
+///
+/// Repeatedly combining a symbolic CalculatorFloat with itself (e.g. `x = x.clone() * x`)
+/// doubles the length of the expression string with every step. To fail fast instead of
+/// exhausting memory, the arithmetic operations check the combined length of the symbolic
+/// expressions of their operands before composing the new expression:
+///
+/// * The checked methods like [CalculatorFloat::checked_mul] return
+///   [CalculatorError::SymbolicExpressionTooLarge].
+/// * The operator traits (`+`, `-`, `*`, `/` and their assigning variants), `atan2` and `powf`
+///   can not return an error and panic with the same message.
+///
+/// Functions of a single symbolic expression like `sin` only add a constant length and are not checked.
+/// The limit is global for the process and defaults to [DEFAULT_MAX_SYMBOLIC_LENGTH] bytes.
+///
+/// # Arguments
+///
+/// * `limit` - Maximum combined length in bytes
+///
+pub fn set_max_symbolic_length(limit: usize) {
+    MAX_SYMBOLIC_LENGTH.store(limit, Ordering::Relaxed);
+}
+
+/// Return the maximum length of symbolic expressions combined by arithmetic operations.
+///
+/// See [set_max_symbolic_length].
+pub fn max_symbolic_length() -> usize {
+    MAX_SYMBOLIC_LENGTH.load(Ordering::Relaxed)
+}
+
+/// Check the combined length of the symbolic expressions of two operands against the limit.
+///
+/// # Arguments
+///
+/// * `first` - First operand
+/// * `second` - Second operand
+///
+pub(crate) fn check_symbolic_length(
+    first: &CalculatorFloat,
+    second: &CalculatorFloat,
+) -> Result<(), CalculatorError> {
+    let length_of = |value: &CalculatorFloat| match value {
+        CalculatorFloat::Float(_) => 0,
+        CalculatorFloat::Str(expression) => expression.len(),
+    };
+    check_combined_length(length_of(first) + length_of(second))
+}
+
+/// Check the length of a combined symbolic expression against the limit.
+fn check_combined_length(length: usize) -> Result<(), CalculatorError> {
+    let limit = max_symbolic_length();
+    if length > limit {
+        Err(CalculatorError::SymbolicExpressionTooLarge { length, limit })
+    } else {
+        Ok(())
+    }
+}
+
+/// Panic when the symbolic expressions of two operands are together longer than the limit.
+///
+/// # Panics
+///
+/// Panics with the message of [CalculatorError::SymbolicExpressionTooLarge].
+///
+fn assert_symbolic_length(first: &CalculatorFloat, second: &CalculatorFloat) {
+    if let Err(error) = check_symbolic_length(first, second) {
+        panic!("{error}")
+    }
+}
+
+/// Rounding of CalculatorFloat values converted to integers, see [CalculatorFloat::try_into_integer].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RoundingPolicy {
+    /// Only convert values that are integral within one ulp
+    #[default]
+    Exact,
+    /// Round to the closest integer, halfway values away from zero
+    Round,
+    /// Round towards negative infinity
+    Floor,
+    /// Round towards positive infinity
+    Ceil,
+}
+
+/// Implement PartialOrd trait for CalculatorFloat.
+///
+/// Float values are ordered like f64. Symbolic values can not be ordered and return None,
+/// apart from identical expressions which are equal. Use [CalculatorFloat::total_cmp] to
+/// sort collections containing symbolic values.
+///
+impl PartialOrd for CalculatorFloat {
+    fn partial_cmp(&self, other: &Self) -> Option<cmp::Ordering> {
+        match (self, other) {
+            (Self::Float(x), Self::Float(y)) => x.partial_cmp(y),
+            (Self::Str(x), Self::Str(y)) if x == y => Some(cmp::Ordering::Equal),
+            _ => None,
+        }
+    }
+}
+
+/// Implement Display trait for CalculatorFloat.
+///
+/// Allows use of simple text formating
+///
+impl fmt::Display for CalculatorFloat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CalculatorFloat::Float(x) => write!(f, "{}", format_float(*x)),
+            CalculatorFloat::Str(y) => write!(f, "{y}"),
+        }
+    }
+}
+
+impl CalculatorFloat {
+    /// Stream the Display of the CalculatorFloat into a writer, optionally truncated.
+    ///
+    /// Long symbolic expressions are written directly into the writer without creating
+    /// another String. With a maximal length only the start of longer values is written,
+    /// cut at a character boundary and followed by `...` and the full length,
+    /// e.g. `x + x + x... (1048576 bytes)`, like the expressions in error messages.
+    ///
+    /// # Arguments
+    ///
+    /// * `writer` - Writer receiving the displayed value
+    /// * `max_len` - Maximum number of bytes of the value before the `...`, None for the complete value
+    ///
+    pub fn write_display<W: fmt::Write>(
+        &self,
+        writer: &mut W,
+        max_len: Option<usize>,
+    ) -> fmt::Result {
+        match max_len {
+            None => write!(writer, "{self}"),
+            Some(limit) => write_truncated(writer, self, limit),
+        }
+    }
+
+    /// Constant zero for CalculatorFloat
+    pub const ZERO: CalculatorFloat = CalculatorFloat::Float(0.0);
+
+    /// Constant one for CalculatorFloat
+    pub const ONE: CalculatorFloat = CalculatorFloat::Float(1.0);
+
+    /// Constant pi for CalculatorFloat
+    pub const PI: CalculatorFloat = CalculatorFloat::Float(std::f64::consts::PI);
+
+    /// Constant Euler's number e for CalculatorFloat
+    pub const E: CalculatorFloat = CalculatorFloat::Float(std::f64::consts::E);
+
+    /// Constant 1/sqrt(2) e for CalculatorFloat
+    pub const FRAC_1_SQRT_2: CalculatorFloat =
+        CalculatorFloat::Float(std::f64::consts::FRAC_1_SQRT_2);
+
+    /// Constant pi / 2 e for CalculatorFloat
+    pub const FRAC_PI_2: CalculatorFloat = CalculatorFloat::Float(std::f64::consts::FRAC_PI_2);
+
+    /// Constant pi / 4 e for CalculatorFloat
+    pub const FRAC_PI_4: CalculatorFloat = CalculatorFloat::Float(std::f64::consts::FRAC_PI_4);
+
+    /// Constant sqrt(2) e for CalculatorFloat
+    pub const SQRT_2: CalculatorFloat = CalculatorFloat::Float(std::f64::consts::SQRT_2);
+
+    /// Return the fraction `numerator * pi / denominator` as CalculatorFloat.
+    ///
+    /// # Arguments
+    ///
+    /// * `numerator` - Multiple of pi
+    /// * `denominator` - Divisor of pi
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(CalculatorFloat::Float)` - The fraction of pi
+    /// * `Err(CalculatorError::DivisionByZero)` - The denominator is zero
+    ///
+    pub fn pi_fraction(
+        numerator: i64,
+        denominator: i64,
+    ) -> Result<CalculatorFloat, CalculatorError> {
+        if denominator == 0 {
+            return Err(CalculatorError::DivisionByZero);
+        }
+        Ok(CalculatorFloat::Float(
+            numerator as f64 * std::f64::consts::PI / denominator as f64,
+        ))
+    }
+
+    /// Return the CalculatorFloat as the real part of a CalculatorComplex with imaginary part zero.
+    ///
+    /// See [CalculatorComplex::demote] for the conversion back.
+    pub fn promote(&self) -> CalculatorComplex {
+        CalculatorComplex::new(self.clone(), 0.0)
+    }
+
+    /// Return the exact rational number `numerator / denominator` as CalculatorFloat.
+    ///
+    /// The fraction is stored as the symbolic rational literal `"(numerator / denominator)"`,
+    /// reduced by the greatest common divisor and with a positive denominator,
+    /// e.g. `rational(2, -6)` gives `"(-1 / 3)"`. Rational literals stay exact when combined
+    /// with each other or with integer valued floats by [CalculatorFloat::add_exact],
+    /// [CalculatorFloat::sub_exact], [CalculatorFloat::mul_exact] and [CalculatorFloat::div_exact].
+    /// All other operations treat them as any symbolic expression and the Calculator
+    /// evaluates them to floats as always.
+    ///
+    /// # Arguments
+    ///
+    /// * `numerator` - Numerator of the fraction
+    /// * `denominator` - Denominator of the fraction
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(CalculatorFloat::Str)` - The rational literal
+    /// * `Err(CalculatorError::DivisionByZero)` - The denominator is zero
+    /// * `Err(CalculatorError::RationalOverflow)` - The reduced fraction does not fit into i64, e.g. `i64::MIN / -1`
+    ///
+    pub fn rational(numerator: i64, denominator: i64) -> Result<CalculatorFloat, CalculatorError> {
+        let fraction = reduce_fraction(i128::from(numerator), i128::from(denominator))
+            .ok_or(CalculatorError::DivisionByZero)?;
+        rational_literal(fraction).ok_or(CalculatorError::RationalOverflow {
+            numerator: fraction.0,
+            denominator: fraction.1,
+        })
+    }
+
+    /// Return True when CalculatorFloat is a rational literal created by [CalculatorFloat::rational].
+    pub fn is_rational_literal(&self) -> bool {
+        match self {
+            Self::Float(_) => false,
+            Self::Str(x) => parse_rational_literal(x).is_some(),
+        }
+    }
+
+    /// Return a plain decimal literal like `"0.25"` as CalculatorFloat.
+    ///
+    /// The literal is stored verbatim in CalculatorFloat::Str and never normalized to a float,
+    /// so serialized values keep the human-written digits, also in human-readable formats
+    /// like JSON. Decimal literals stay exact when combined with each other or with integer
+    /// valued floats by [CalculatorFloat::add_exact], [CalculatorFloat::sub_exact],
+    /// [CalculatorFloat::mul_exact] and [CalculatorFloat::div_exact], e.g. `0.3` times `3`
+    /// gives `"0.9"`. The Calculator evaluates them to floats as always.
+    ///
+    /// # Arguments
+    ///
+    /// * `literal` - Decimal literal with optional minus sign, digits on both sides of the point
+    ///   and at most 18 digits, e.g. `"-0.25"`
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(CalculatorFloat::Str)` - The decimal literal
+    /// * `Err(CalculatorError::InvalidDecimalLiteral)` - The string is not a plain decimal literal, e.g. `"1e-3"` or `".5"`
+    ///
+    pub fn decimal(literal: &str) -> Result<CalculatorFloat, CalculatorError> {
+        match parse_decimal_literal(literal) {
+            Some(_) => Ok(CalculatorFloat::Str(literal.to_string())),
+            None => Err(CalculatorError::InvalidDecimalLiteral {
+                literal: literal.to_string(),
+            }),
+        }
+    }
+
+    /// Return True when CalculatorFloat is a decimal literal created by [CalculatorFloat::decimal].
+    pub fn is_decimal_literal(&self) -> bool {
+        match self {
+            Self::Float(_) => false,
+            Self::Str(x) => parse_decimal_literal(x).is_some(),
+        }
+    }
+
+    /// Return the exact fraction of a rational or decimal literal or an integer valued float.
+    fn exact_fraction(&self) -> Option<Fraction> {
+        match self {
+            Self::Float(x) if x.fract() == 0.0 && x.abs() <= MAX_EXACT_INTEGER => {
+                Some((*x as i128, 1))
+            }
+            Self::Float(_) => None,
+            Self::Str(x) => parse_rational_literal(x).or_else(|| parse_decimal_literal(x)),
+        }
+    }
+
+    /// Combine two operands exactly when both are exact literals or integer valued floats.
+    ///
+    /// Returns a rational literal when one operand is a rational literal, a decimal literal when
+    /// one operand is a decimal literal and the result has a finite decimal representation and
+    /// a rational literal for all other results that are not integers. Returns None when
+    /// the operation is not exact and falls back to the float operation.
+    fn exact_operation(
+        &self,
+        other: &CalculatorFloat,
+        combine: fn(Fraction, Fraction) -> Option<(i128, i128)>,
+    ) -> Option<CalculatorFloat> {
+        let (numerator, denominator) = combine(self.exact_fraction()?, other.exact_fraction()?)?;
+        let fraction = reduce_fraction(numerator, denominator)?;
+        if self.is_rational_literal() || other.is_rational_literal() {
+            rational_literal(fraction)
+        } else if self.is_decimal_literal() || other.is_decimal_literal() {
+            decimal_literal(fraction).or_else(|| rational_literal(fraction))
+        } else if fraction.1 != 1 {
+            rational_literal(fraction)
+        } else {
+            None
+        }
+    }
+
+    /// Add a value, keeping rational literals exact.
+    ///
+    /// When both operands are rational literals or integer valued floats and at least one is
+    /// a rational literal, the result is the reduced rational literal, e.g.
+    /// `(1 / 3) + (2 / 3)` gives `"(1 / 1)"`. Otherwise or when the result does not fit into
+    /// i64 the result of `+` is returned.
+    ///
+    /// # Arguments
+    ///
+    /// * `other` - Any type T for which CalculatorFloat::From<T> trait is implemented
+    ///
+    pub fn add_exact<T>(self, other: T) -> CalculatorFloat
+    where
+        T: Into<CalculatorFloat>,
+    {
+        let other_from: CalculatorFloat = other.into();
+        self.exact_operation(&other_from, |(a, b), (c, d)| {
+            Some((a.checked_mul(d)?.checked_add(c.checked_mul(b)?)?, b * d))
+        })
+        .unwrap_or_else(|| self + other_from)
+    }
+
+    /// Subtract a value, keeping rational literals exact.
+    ///
+    /// Rational literals stay exact like in [CalculatorFloat::add_exact], otherwise the result of `-` is returned.
+    ///
+    /// # Arguments
+    ///
+    /// * `other` - Any type T for which CalculatorFloat::From<T> trait is implemented
+    ///
+    pub fn sub_exact<T>(self, other: T) -> CalculatorFloat
+    where
+        T: Into<CalculatorFloat>,
+    {
+        let other_from: CalculatorFloat = other.into();
+        self.exact_operation(&other_from, |(a, b), (c, d)| {
+            Some((a.checked_mul(d)?.checked_sub(c.checked_mul(b)?)?, b * d))
+        })
+        .unwrap_or_else(|| self - other_from)
+    }
+
+    /// Multiply with a value, keeping rational literals exact.
+    ///
+    /// Rational literals stay exact like in [CalculatorFloat::add_exact], otherwise the result of `*` is returned.
+    ///
+    /// # Arguments
+    ///
+    /// * `other` - Any type T for which CalculatorFloat::From<T> trait is implemented
+    ///
+    pub fn mul_exact<T>(self, other: T) -> CalculatorFloat
+    where
+        T: Into<CalculatorFloat>,
+    {
+        let other_from: CalculatorFloat = other.into();
+        self.exact_operation(&other_from, |(a, b), (c, d)| Some((a * c, b * d)))
+            .unwrap_or_else(|| self * other_from)
+    }
+
+    /// Divide by a value, keeping rational literals exact.
+    ///
+    /// Rational literals stay exact like in [CalculatorFloat::add_exact]. The division of two
+    /// integer valued floats also gives a rational literal when the quotient is not an integer,
+    /// e.g. `1.0 / 3.0` gives `"(1 / 3)"`. Otherwise the result of `/` is returned,
+    /// which panics for a division by zero.
+    ///
+    /// # Arguments
+    ///
+    /// * `other` - Any type T for which CalculatorFloat::From<T> trait is implemented
+    ///
+    pub fn div_exact<T>(self, other: T) -> CalculatorFloat
+    where
+        T: Into<CalculatorFloat>,
+    {
+        let other_from: CalculatorFloat = other.into();
+        self.exact_operation(&other_from, |(a, b), (c, d)| Some((a * d, b * c)))
+            .unwrap_or_else(|| self / other_from)
+    }
+
+    /// Add a value, returning an error instead of panicking.
+    ///
+    /// # Arguments
+    ///
+    /// * `other` - Any type T for which CalculatorFloat::From<T> trait is implemented
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(CalculatorFloat)` - The sum
+    /// * `Err(CalculatorError::SymbolicExpressionTooLarge)` - The symbolic expressions are too long, see [set_max_symbolic_length]
+    ///
+    pub fn checked_add<T>(self, other: T) -> Result<CalculatorFloat, CalculatorError>
+    where
+        T: Into<CalculatorFloat>,
+    {
+        let other_from: CalculatorFloat = other.into();
+        check_symbolic_length(&self, &other_from)?;
+        Ok(self + other_from)
+    }
+
+    /// Subtract a value, returning an error instead of panicking.
+    ///
+    /// # Arguments
+    ///
+    /// * `other` - Any type T for which CalculatorFloat::From<T> trait is implemented
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(CalculatorFloat)` - The difference
+    /// * `Err(CalculatorError::SymbolicExpressionTooLarge)` - The symbolic expressions are too long, see [set_max_symbolic_length]
+    ///
+    pub fn checked_sub<T>(self, other: T) -> Result<CalculatorFloat, CalculatorError>
+    where
+        T: Into<CalculatorFloat>,
+    {
+        let other_from: CalculatorFloat = other.into();
+        check_symbolic_length(&self, &other_from)?;
+        Ok(self - other_from)
+    }
+
+    /// Multiply by a value, returning an error instead of panicking.
+    ///
+    /// # Arguments
+    ///
+    /// * `other` - Any type T for which CalculatorFloat::From<T> trait is implemented
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(CalculatorFloat)` - The product
+    /// * `Err(CalculatorError::SymbolicExpressionTooLarge)` - The symbolic expressions are too long, see [set_max_symbolic_length]
+    ///
+    pub fn checked_mul<T>(self, other: T) -> Result<CalculatorFloat, CalculatorError>
+    where
+        T: Into<CalculatorFloat>,
+    {
+        let other_from: CalculatorFloat = other.into();
+        check_symbolic_length(&self, &other_from)?;
+        Ok(self * other_from)
+    }
+
+    /// Divide by a value, returning an error instead of panicking.
+    ///
+    /// # Arguments
+    ///
+    /// * `other` - Any type T for which CalculatorFloat::From<T> trait is implemented
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(CalculatorFloat)` - The quotient
+    /// * `Err(CalculatorError::DivisionByZero)` - The divisor is the float zero
+    /// * `Err(CalculatorError::SymbolicExpressionTooLarge)` - The symbolic expressions are too long, see [set_max_symbolic_length]
+    ///
+    pub fn checked_div<T>(self, other: T) -> Result<CalculatorFloat, CalculatorError>
+    where
+        T: Into<CalculatorFloat>,
+    {
+        let other_from: CalculatorFloat = other.into();
+        if other_from == CalculatorFloat::Float(0.0) {
+            return Err(CalculatorError::DivisionByZero);
+        }
+        check_symbolic_length(&self, &other_from)?;
+        Ok(self / other_from)
+    }
+
+    /// Return the remainder x % y, returning an error instead of panicking.
+    ///
+    /// # Arguments
+    ///
+    /// * `other` - Any type T for which CalculatorFloat::From<T> trait is implemented
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(CalculatorFloat)` - The result of [CalculatorFloat::rem]
+    /// * `Err(CalculatorError::DivisionByZero)` - The divisor is the float zero
+    /// * `Err(CalculatorError::SymbolicExpressionTooLarge)` - The symbolic expressions are too long, see [set_max_symbolic_length]
+    ///
+    pub fn checked_rem<T>(&self, other: T) -> Result<CalculatorFloat, CalculatorError>
+    where
+        T: Into<CalculatorFloat>,
+    {
+        let other_from: CalculatorFloat = other.into();
+        if other_from == CalculatorFloat::Float(0.0) {
+            return Err(CalculatorError::DivisionByZero);
+        }
+        check_symbolic_length(self, &other_from)?;
+        Ok(CalculatorFloat::rem(self, other_from))
+    }
+
+    /// Return atan2 for CalculatorFloat and generic type `T`, returning an error instead of panicking.
+    ///
+    /// # Arguments
+    ///
+    /// * `other` - Any type T for which CalculatorFloat::From<T> trait is implemented
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(CalculatorFloat)` - The result of [CalculatorFloat::atan2]
+    /// * `Err(CalculatorError::SymbolicExpressionTooLarge)` - The symbolic expressions are too long, see [set_max_symbolic_length]
+    ///
+    pub fn checked_atan2<T>(&self, other: T) -> Result<CalculatorFloat, CalculatorError>
+    where
+        T: Into<CalculatorFloat>,
+    {
+        let other_from: CalculatorFloat = other.into();
+        check_symbolic_length(self, &other_from)?;
+        Ok(self.atan2(other_from))
+    }
+
+    /// Return Power for CalculatorFloat and generic type `T`, returning an error instead of panicking.
+    ///
+    /// # Arguments
+    ///
+    /// * `other` - Any type T for which CalculatorFloat::From<T> trait is implemented
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(CalculatorFloat)` - The result of [CalculatorFloat::powf]
+    /// * `Err(CalculatorError::SymbolicExpressionTooLarge)` - The symbolic expressions are too long, see [set_max_symbolic_length]
+    ///
+    pub fn checked_powf<T>(&self, other: T) -> Result<CalculatorFloat, CalculatorError>
+    where
+        T: Into<CalculatorFloat>,
+    {
+        let other_from: CalculatorFloat = other.into();
+        check_symbolic_length(self, &other_from)?;
+        Ok(self.powf(other_from))
+    }
+
+    /// Return the logarithm to an arbitrary base for CalculatorFloat and generic type `T`, returning an error instead of panicking.
+    ///
+    /// # Arguments
+    ///
+    /// * `base` - Any type T for which CalculatorFloat::From<T> trait is implemented
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(CalculatorFloat)` - The result of [CalculatorFloat::log]
+    /// * `Err(CalculatorError::SymbolicExpressionTooLarge)` - The symbolic expressions are too long, see [set_max_symbolic_length]
+    ///
+    pub fn checked_log<T>(&self, base: T) -> Result<CalculatorFloat, CalculatorError>
+    where
+        T: Into<CalculatorFloat>,
+    {
+        let base_from: CalculatorFloat = base.into();
+        check_symbolic_length(self, &base_from)?;
+        Ok(self.log(base_from))
+    }
+
+    /// Return True when CalculatorFloat does not contain symbolic expression.
+    pub fn is_float(&self) -> bool {
+        match self {
+            CalculatorFloat::Float(_) => true,
+            CalculatorFloat::Str(_) => false,
+        }
+    }
+
+    /// Return True when CalculatorFloat contains a symbolic expression, the inverse of [CalculatorFloat::is_float].
+    pub fn is_symbolic(&self) -> bool {
+        !self.is_float()
+    }
+
+    /// Return True when CalculatorFloat is the float 0.0 (or -0.0), False for symbolic expressions.
+    ///
+    /// The comparison is exact, the operators use the same condition to drop added zeros
+    /// and to replace products with zero by zero.
+    pub fn is_zero(&self) -> bool {
+        matches!(self, CalculatorFloat::Float(x) if is_zero_value(*x))
+    }
+
+    /// Return True when CalculatorFloat is the float 1.0, False for symbolic expressions.
+    ///
+    /// The comparison is exact, the operators use the same condition to drop factors of one.
+    pub fn is_one(&self) -> bool {
+        matches!(self, CalculatorFloat::Float(x) if is_one_value(*x))
+    }
+    /// Return square root of CalculatorFloat.
+    pub fn sqrt(&self) -> CalculatorFloat {
+        match self {
+            CalculatorFloat::Float(f) => CalculatorFloat::Float(f.sqrt()),
+            CalculatorFloat::Str(s) => CalculatorFloat::Str(format!("sqrt({s})")),
+        }
+    }
+    /// Return atan2 for CalculatorFloat and generic type `T`.
+    ///
+    /// # Arguments
+    ///
+    /// * `other` - Any type T for which CalculatorFloat::From<T> trait is implemented
+    ///
+    /// # Panics
+    ///
+    /// Panics when the symbolic expressions of the operands are together longer than [max_symbolic_length].
+    ///
+    pub fn atan2<T>(&self, other: T) -> CalculatorFloat
+    where
+        T: Into<CalculatorFloat>,
+    {
+        let other_from: CalculatorFloat = other.into();
+        assert_symbolic_length(self, &other_from);
+        match self {
+            Self::Float(x) => match other_from {
+                Self::Float(y) => CalculatorFloat::Float(math::atan2(*x, y)),
+                Self::Str(y) => Self::Str(format!("atan2({}, {y})", format_float(*x))),
+            },
+            Self::Str(x) => match other_from {
+                Self::Float(y) => Self::Str(format!("atan2({x}, {})", format_float(y))),
+                Self::Str(y) => Self::Str(format!("atan2({}, {})", x, &y)),
+            },
+        }
+    }
+
+    /// Return Power for CalculatorFloat and generic type `T`.
+    ///
+    /// # Arguments
+    ///
+    /// * `other` - Any type T for which CalculatorFloat::From<T> trait is implemented
+    ///
+    /// # Panics
+    ///
+    /// Panics when the symbolic expressions of the operands are together longer than [max_symbolic_length].
+    ///
+    pub fn powf<T>(&self, other: T) -> CalculatorFloat
+    where
+        T: Into<CalculatorFloat>,
+    {
+        let other_from: CalculatorFloat = other.into();
+        assert_symbolic_length(self, &other_from);
+        match self {
+            Self::Float(x) => match other_from {
+                Self::Float(y) => CalculatorFloat::Float(math::powf(*x, y)),
+                Self::Str(y) => Self::Str(format!("({} ^ {y})", format_float(*x))),
+            },
+            Self::Str(x) => match other_from {
+                Self::Float(y) => Self::Str(format!("({x} ^ {})", format_float(y))),
+                Self::Str(y) => Self::Str(format!("({} ^ {})", x, &y)),
+            },
+        }
+    }
+
+    /// Return the remainder x % y of the truncated division for CalculatorFloat and generic type `T`.
+    ///
+    /// Like `%` for f64 the remainder has the sign of the dividend, e.g. `-7 % 3 = -1`.
+    /// Symbolic operands give the expression `(x % y)`.
+    ///
+    /// # Arguments
+    ///
+    /// * `other` - Any type T for which CalculatorFloat::From<T> trait is implemented
+    ///
+    /// # Panics
+    ///
+    /// Panics on division by zero.
+    /// Division by zero is only detected when other is converted to CalculatorFloat::Float
+    /// Panics when the symbolic expressions of the operands are together longer than [max_symbolic_length].
+    ///
+    pub fn rem<T>(&self, other: T) -> CalculatorFloat
+    where
+        T: Into<CalculatorFloat>,
+    {
+        let other_from: CalculatorFloat = other.into();
+        assert_symbolic_length(self, &other_from);
+        match self {
+            Self::Float(x) => match other_from {
+                Self::Float(y) => {
+                    if y == 0.0 {
+                        panic!("Division by zero")
+                    } else {
+                        Self::Float(x % y)
+                    }
+                }
+                Self::Str(y) => {
+                    if is_zero_value(*x) {
+                        Self::Float(0.0)
+                    } else {
+                        Self::Str(format!("({} % {y})", format_float(*x)))
+                    }
+                }
+            },
+            Self::Str(x) => match other_from {
+                Self::Float(y) => {
+                    if y == 0.0 {
+                        panic!("Division by zero")
+                    } else {
+                        Self::Str(format!("({x} % {})", format_float(y)))
+                    }
+                }
+                Self::Str(y) => Self::Str(format!("({} % {})", x, &y)),
+            },
+        }
+    }
+
+    /// Return exponential function exp(x) for CalculatorFloat.
+    pub fn exp(&self) -> CalculatorFloat {
+        match self {
+            Self::Float(x) => CalculatorFloat::Float(math::exp(*x)),
+            Self::Str(y) => Self::Str(format!("exp({y})")),
+        }
+    }
+    /// Return sine function sin(x) for CalculatorFloat.
+    pub fn sin(&self) -> CalculatorFloat {
+        match self {
+            Self::Float(x) => CalculatorFloat::Float(math::sin(*x)),
+            Self::Str(y) => Self::Str(format!("sin({y})")),
+        }
+    }
+    /// Return cosine function cos(x) for CalculatorFloat.
+    pub fn cos(&self) -> CalculatorFloat {
+        match self {
+            Self::Float(x) => CalculatorFloat::Float(math::cos(*x)),
+            Self::Str(y) => Self::Str(format!("cos({y})")),
+        }
+    }
+    /// Return arccosine function acos(x) for CalculatorFloat.
+    pub fn acos(&self) -> CalculatorFloat {
+        match self {
+            Self::Float(x) => CalculatorFloat::Float(math::acos(*x)),
+            Self::Str(y) => Self::Str(format!("acos({y})")),
+        }
+    }
+    /// Return tangent function tan(x) for CalculatorFloat.
+    pub fn tan(&self) -> CalculatorFloat {
+        match self {
+            Self::Float(x) => CalculatorFloat::Float(math::tan(*x)),
+            Self::Str(y) => Self::Str(format!("tan({y})")),
+        }
+    }
+    /// Return arcsine function asin(x) for CalculatorFloat.
+    pub fn asin(&self) -> CalculatorFloat {
+        match self {
+            Self::Float(x) => CalculatorFloat::Float(math::asin(*x)),
+            Self::Str(y) => Self::Str(format!("asin({y})")),
+        }
+    }
+    /// Return arctangent function atan(x) for CalculatorFloat.
+    pub fn atan(&self) -> CalculatorFloat {
+        match self {
+            Self::Float(x) => CalculatorFloat::Float(math::atan(*x)),
+            Self::Str(y) => Self::Str(format!("atan({y})")),
+        }
+    }
+    /// Return hyperbolic sine function sinh(x) for CalculatorFloat.
+    pub fn sinh(&self) -> CalculatorFloat {
+        match self {
+            Self::Float(x) => CalculatorFloat::Float(math::sinh(*x)),
+            Self::Str(y) => Self::Str(format!("sinh({y})")),
+        }
+    }
+    /// Return hyperbolic cosine function cosh(x) for CalculatorFloat.
+    pub fn cosh(&self) -> CalculatorFloat {
+        match self {
+            Self::Float(x) => CalculatorFloat::Float(math::cosh(*x)),
+            Self::Str(y) => Self::Str(format!("cosh({y})")),
+        }
+    }
+    /// Return hyperbolic tangent function tanh(x) for CalculatorFloat.
+    pub fn tanh(&self) -> CalculatorFloat {
+        match self {
+            Self::Float(x) => CalculatorFloat::Float(math::tanh(*x)),
+            Self::Str(y) => Self::Str(format!("tanh({y})")),
+        }
+    }
+    /// Return power of two 2^x for CalculatorFloat.
+    pub fn exp2(&self) -> CalculatorFloat {
+        match self {
+            Self::Float(x) => CalculatorFloat::Float(math::exp2(*x)),
+            Self::Str(y) => Self::Str(format!("exp2({y})")),
+        }
+    }
+    /// Return natural logarithm ln(x), written `log(x)` in symbolic expressions for CalculatorFloat.
+    pub fn ln(&self) -> CalculatorFloat {
+        match self {
+            Self::Float(x) => CalculatorFloat::Float(math::ln(*x)),
+            Self::Str(y) => Self::Str(format!("log({y})")),
+        }
+    }
+    /// Return logarithm to base 2 log2(x) for CalculatorFloat.
+    pub fn log2(&self) -> CalculatorFloat {
+        match self {
+            Self::Float(x) => CalculatorFloat::Float(math::log2(*x)),
+            Self::Str(y) => Self::Str(format!("log2({y})")),
+        }
+    }
+    /// Return logarithm to base 10 log10(x) for CalculatorFloat.
+    pub fn log10(&self) -> CalculatorFloat {
+        match self {
+            Self::Float(x) => CalculatorFloat::Float(math::log10(*x)),
+            Self::Str(y) => Self::Str(format!("log10({y})")),
+        }
+    }
+    /// Return largest integer smaller than or equal to x floor(x) for CalculatorFloat.
+    pub fn floor(&self) -> CalculatorFloat {
+        match self {
+            Self::Float(x) => CalculatorFloat::Float(x.floor()),
+            Self::Str(y) => Self::Str(format!("floor({y})")),
+        }
+    }
+    /// Return smallest integer greater than or equal to x ceil(x) for CalculatorFloat.
+    pub fn ceil(&self) -> CalculatorFloat {
+        match self {
+            Self::Float(x) => CalculatorFloat::Float(x.ceil()),
+            Self::Str(y) => Self::Str(format!("ceil({y})")),
+        }
+    }
+    /// Return x rounded half away from zero round(x) for CalculatorFloat.
+    pub fn round(&self) -> CalculatorFloat {
+        match self {
+            Self::Float(x) => CalculatorFloat::Float(x.round()),
+            Self::Str(y) => Self::Str(format!("round({y})")),
+        }
+    }
+    /// Return the logarithm of x to an arbitrary base for CalculatorFloat and generic type `T`.
+    ///
+    /// Like `f64::log` the logarithm is the quotient of the natural logarithms,
+    /// symbolic operands give the expression `(log(x) / log(base))`.
+    ///
+    /// # Arguments
+    ///
+    /// * `base` - Any type T for which CalculatorFloat::From<T> trait is implemented
+    ///
+    /// # Panics
+    ///
+    /// Panics when the symbolic expressions of the operands are together longer than [max_symbolic_length].
+    ///
+    pub fn log<T>(&self, base: T) -> CalculatorFloat
+    where
+        T: Into<CalculatorFloat>,
+    {
+        let base_from: CalculatorFloat = base.into();
+        assert_symbolic_length(self, &base_from);
+        match (self, base_from) {
+            (Self::Float(x), Self::Float(b)) => CalculatorFloat::Float(math::ln(*x) / math::ln(b)),
+            (x, b) => Self::Str(format!("(log({x}) / log({b}))")),
+        }
+    }
+    /// Return absolute value abs(x) for CalculatorFloat.
+    ///
+    /// Symbolic expressions are simplified syntactically: `abs(abs(x))` gives `abs(x)`
+    /// and the absolute value of a negation `(-x)` gives `abs(x)`.
+    pub fn abs(&self) -> CalculatorFloat {
+        match self {
+            Self::Float(x) => CalculatorFloat::Float(x.abs()),
+            Self::Str(y) => {
+                let operand = strip_negation(y).unwrap_or(y);
+                if strip_call(operand, "abs").is_some() {
+                    Self::Str(operand.to_string())
+                } else {
+                    Self::Str(format!("abs({operand})"))
+                }
+            }
+        }
+    }
+    /// Return signum value sign(x) for CalculatorFloat.
+    ///
+    /// Symbolic expressions are simplified syntactically: `sign(sign(x))` gives `sign(x)`.
+    /// `sign(abs(x))` is kept, it is 1 for all numbers (`sign(0)` is 1 like `f64::signum`)
+    /// but NaN for NaN, which can not be decided from the expression.
+    pub fn signum(&self) -> CalculatorFloat {
+        match self {
+            Self::Float(x) => CalculatorFloat::Float(x.signum()),
+            Self::Str(y) => {
+                if strip_call(y, "sign").is_some() {
+                    Self::Str(y.clone())
+                } else {
+                    Self::Str(format!("sign({y})"))
+                }
+            }
+        }
+    }
+    /// Return factorial x! for CalculatorFloat.
     ///
-    pub fn atan2<T>(&self, other: T) -> CalculatorFloat
+    /// Uses the same implementation as the `factorial` function of the Calculator: exact for
+    /// integers up to 34 and the gamma function gamma(x + 1) otherwise.
+    /// Returns NaN for negative integers where the factorial is not defined.
+    pub fn factorial(&self) -> CalculatorFloat {
+        match self {
+            Self::Float(x) => {
+                CalculatorFloat::Float(special_functions::factorial(*x).unwrap_or(f64::NAN))
+            }
+            Self::Str(y) => Self::Str(format!("factorial({y})")),
+        }
+    }
+    /// Return the CalculatorFloat rounded to a number of decimal digits after the decimal point.
+    ///
+    /// Uses the same implementation as the `round_digits` function of the Calculator:
+    /// the shortest decimal representation of a float is rounded half away from zero,
+    /// e.g. 0.00005 rounded to 4 digits is 0.0001. Negative digits round to tens, hundreds, ...
+    ///
+    /// # Arguments
+    ///
+    /// * `digits` - Number of decimal digits after the decimal point
+    ///
+    pub fn round_to_digits(&self, digits: i32) -> CalculatorFloat {
+        match self {
+            Self::Float(x) => {
+                CalculatorFloat::Float(special_functions::round_to_digits(*x, digits))
+            }
+            Self::Str(y) => Self::Str(format!("round_digits({y}, {digits})")),
+        }
+    }
+    /// Return the CalculatorFloat rounded to the nearest multiple of a step.
+    ///
+    /// Uses the same implementation as the `round_step` function of the Calculator:
+    /// ties are rounded half away from zero and for steps like 1e-4 the result is the float
+    /// closest to the decimal multiple.
+    ///
+    /// # Arguments
+    ///
+    /// * `step` - Positive and finite step
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(CalculatorFloat)` - The rounded value
+    /// * `Err(CalculatorError::InvalidFunctionArgument)` - The step is not positive and finite
+    ///
+    pub fn round_to_multiple(&self, step: f64) -> Result<CalculatorFloat, CalculatorError> {
+        match self {
+            Self::Float(x) => Ok(CalculatorFloat::Float(special_functions::round_step(
+                *x, step,
+            )?)),
+            Self::Str(y) => {
+                special_functions::check_round_step(step)?;
+                Ok(Self::Str(format!(
+                    "round_step({y}, {})",
+                    format_float(step)
+                )))
+            }
+        }
+    }
+    /// Return Heaviside step function theta(x) for CalculatorFloat.
+    ///
+    /// Uses the same convention as the `theta` function of the Calculator:
+    /// 0.0 for negative, 0.5 for zero and 1.0 for positive values.
+    /// Zero is detected with the tolerance `f64::EPSILON`.
+    pub fn heaviside(&self) -> CalculatorFloat {
+        match self {
+            Self::Float(x) => CalculatorFloat::Float(heaviside(*x)),
+            Self::Str(y) => Self::Str(format!("theta({y})")),
+        }
+    }
+    /// Return Heaviside step function theta(x) for CalculatorFloat, alias of [CalculatorFloat::heaviside].
+    pub fn step(&self) -> CalculatorFloat {
+        self.heaviside()
+    }
+    /// Return Kronecker delta delta(x) for CalculatorFloat.
+    ///
+    /// Uses the same convention as the `delta` function of the Calculator:
+    /// 1.0 when the absolute value is smaller than `f64::EPSILON` and 0.0 otherwise.
+    pub fn delta(&self) -> CalculatorFloat {
+        match self {
+            Self::Float(x) => CalculatorFloat::Float(delta(*x)),
+            Self::Str(y) => Self::Str(format!("delta({y})")),
+        }
+    }
+    /// Return True if self value is close to other value.
+    ///
+    /// Two floats are close when `|x - y| <= ATOL + RTOL * |y|`. A symbolic string holding a
+    /// number, e.g. `Str("1e0")`, is compared numerically to a float in both directions.
+    /// Other symbolic strings are only close to identical strings and never to a float.
+    pub fn isclose<T>(&self, other: T) -> bool
     where
         T: Into<CalculatorFloat>,
     {
         let other_from: CalculatorFloat = other.into();
+        // Every formatted float can be parsed as float, so a string that can not be parsed
+        // never equals a formatted float and no formatted comparison is needed
+        match (self, &other_from) {
+            (Self::Float(x), Self::Float(y)) => floats_close(*x, *y),
+            (Self::Float(x), Self::Str(y)) => f64::from_str(y).is_ok_and(|y| floats_close(*x, y)),
+            (Self::Str(x), Self::Float(y)) => f64::from_str(x).is_ok_and(|x| floats_close(x, *y)),
+            // Comparing the lengths first exits early for most different expressions
+            (Self::Str(x), Self::Str(y)) => x.len() == y.len() && x == y,
+        }
+    }
+
+    /// Return Some(f64) when CalculatorFloat is a numeric value
+    pub fn float(&self) -> Result<&f64, CalculatorError> {
         match self {
-            Self::Float(x) => match other_from {
-                Self::Float(y) => CalculatorFloat::Float(x.atan2(y)),
-                Self::Str(y) => Self::Str(format!("atan2({:e}, {})", x, &y)),
-            },
-            Self::Str(x) => match other_from {
-                Self::Float(y) => Self::Str(format!("atan2({x}, {y:e})")),
-                Self::Str(y) => Self::Str(format!("atan2({}, {})", x, &y)),
-            },
+            Self::Float(x) => Ok(x),
+            Self::Str(x) => Err(CalculatorError::FloatSymbolicNotConvertable { val: x.clone() }),
         }
     }
 
-    /// Return Power for CalculatorFloat and generic type `T`.
+    /// Convert a numeric CalculatorFloat to an integer type, e.g. for gate indices.
     ///
     /// # Arguments
     ///
-    /// * `other` - Any type T for which CalculatorFloat::From<T> trait is implemented
+    /// * `policy` - Rounding of values that are not integral
     ///
-    pub fn powf<T>(&self, other: T) -> CalculatorFloat
-    where
-        T: Into<CalculatorFloat>,
-    {
-        let other_from: CalculatorFloat = other.into();
+    /// # Returns
+    ///
+    /// * `Ok(T)` - The rounded value
+    /// * `Err(CalculatorError::FloatSymbolicNotConvertable)` - The value is symbolic
+    /// * `Err(CalculatorError::IntegerConversionError)` - The value is not finite, not integral
+    ///   for [RoundingPolicy::Exact] or outside of the range of `T`
+    pub fn try_into_integer<T: TryFrom<i64>>(
+        &self,
+        policy: RoundingPolicy,
+    ) -> Result<T, CalculatorError> {
+        let value = *self.float()?;
+        let error = || CalculatorError::IntegerConversionError {
+            value,
+            target: std::any::type_name::<T>(),
+        };
+        if !value.is_finite() {
+            return Err(error());
+        }
+        let rounded = match policy {
+            RoundingPolicy::Exact => {
+                let rounded = value.round();
+                // The distance to the next larger float is the ulp of the value
+                let ulp = f64::from_bits(value.abs().to_bits() + 1) - value.abs();
+                if (value - rounded).abs() > ulp {
+                    return Err(error());
+                }
+                rounded
+            }
+            RoundingPolicy::Round => value.round(),
+            RoundingPolicy::Floor => value.floor(),
+            RoundingPolicy::Ceil => value.ceil(),
+        };
+        // i64::MIN is a power of two and exactly representable, i64::MAX rounds up to 2^63
+        if !(i64::MIN as f64..-(i64::MIN as f64)).contains(&rounded) {
+            return Err(error());
+        }
+        T::try_from(rounded as i64).map_err(|_| error())
+    }
+
+    /// Convert a numeric CalculatorFloat to usize, see [CalculatorFloat::try_into_integer].
+    pub fn try_into_usize(&self, policy: RoundingPolicy) -> Result<usize, CalculatorError> {
+        self.try_into_integer(policy)
+    }
+
+    /// Convert a numeric CalculatorFloat to u32, see [CalculatorFloat::try_into_integer].
+    pub fn try_into_u32(&self, policy: RoundingPolicy) -> Result<u32, CalculatorError> {
+        self.try_into_integer(policy)
+    }
+
+    /// Convert a numeric CalculatorFloat to i64, see [CalculatorFloat::try_into_integer].
+    pub fn try_into_i64(&self, policy: RoundingPolicy) -> Result<i64, CalculatorError> {
+        self.try_into_integer(policy)
+    }
+
+    /// Return inverse/reciprocal function (1/x) for CalculatorFloat.
+    pub fn recip(&self) -> CalculatorFloat {
         match self {
-            Self::Float(x) => match other_from {
-                Self::Float(y) => CalculatorFloat::Float(x.powf(y)),
-                Self::Str(y) => Self::Str(format!("({:e} ^ {})", x, &y)),
-            },
-            Self::Str(x) => match other_from {
-                Self::Float(y) => Self::Str(format!("({x} ^ {y:e})")),
-                Self::Str(y) => Self::Str(format!("({} ^ {})", x, &y)),
-            },
+            Self::Float(x) => Self::Float(x.recip()),
+            Self::Str(y) => Self::Str(format!("(1 / {y})")),
         }
     }
 
-    /// Return exponential function exp(x) for CalculatorFloat.
-    pub fn exp(&self) -> CalculatorFloat {
+    /// Return the CalculatorFloat with constant subexpressions evaluated.
+    ///
+    /// The expression is parsed into an expression tree and rebuilt from its leaves,
+    /// so purely numeric subexpressions are folded, e.g. `((2e0 * 3e0) * x)` becomes `(6e0 * x)`.
+    /// Added zeros and factors of one are removed, symbols are kept.
+    /// A numeric expression becomes CalculatorFloat::Float.
+    /// Expressions that can not be parsed into an expression tree (e.g. assignments) or fail
+    /// to evaluate are returned unchanged.
+    pub fn simplify(&self) -> CalculatorFloat {
         match self {
-            Self::Float(x) => CalculatorFloat::Float(x.exp()),
-            Self::Str(y) => Self::Str(format!("exp({y})")),
+            Self::Float(_) => self.clone(),
+            Self::Str(expression) => ExpressionTree::parse(expression)
+                .and_then(|tree| tree.value())
+                .unwrap_or_else(|_| self.clone()),
         }
     }
-    /// Return sine function sin(x) for CalculatorFloat.
-    pub fn sin(&self) -> CalculatorFloat {
+
+    /// Return the derivative of the CalculatorFloat with respect to a variable.
+    ///
+    /// The expression is differentiated symbolically. Piecewise constant functions
+    /// (e.g. `sign`, `floor`, `theta`) have a vanishing derivative.
+    ///
+    /// # Arguments
+    ///
+    /// * `variable` - Name of the variable
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(CalculatorFloat)` - The derivative, zero when the expression does not depend on the variable
+    /// * `Err(CalculatorError)` - The expression can not be parsed or contains a function without known derivative
+    ///
+    pub fn derivative(&self, variable: &str) -> Result<CalculatorFloat, CalculatorError> {
         match self {
-            Self::Float(x) => CalculatorFloat::Float(x.sin()),
-            Self::Str(y) => Self::Str(format!("sin({y})")),
+            Self::Float(_) => Ok(Self::ZERO),
+            Self::Str(expression) => ExpressionTree::parse(expression)?.derivative(variable),
+        }
+    }
+
+    /// Return the partial derivatives of the CalculatorFloat with respect to several variables.
+    ///
+    /// The expression is only parsed once for all variables.
+    ///
+    /// # Arguments
+    ///
+    /// * `variables` - Names of the variables
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Vec<CalculatorFloat>)` - The partial derivatives in the order of the variables
+    /// * `Err(CalculatorError)` - The expression can not be parsed or contains a function without known derivative
+    ///
+    pub fn gradient(&self, variables: &[&str]) -> Result<Vec<CalculatorFloat>, CalculatorError> {
+        match self {
+            Self::Float(_) => Ok(vec![Self::ZERO; variables.len()]),
+            Self::Str(expression) => {
+                let tree = ExpressionTree::parse(expression)?;
+                variables
+                    .iter()
+                    .map(|variable| tree.derivative(variable))
+                    .collect()
+            }
+        }
+    }
+
+    /// Return a stable 64 bit hash of the CalculatorFloat.
+    ///
+    /// Equivalent forms of an expression share a hash: sums and products are flattened and
+    /// their operands sorted, so `(a + b)` and `b+a` agree, and `Float(2.0)` agrees with `"2"`.
+    /// Numbers are hashed by their bits after mapping `-0.0` to `0.0` and all NaNs to one NaN.
+    ///
+    /// The hash is computed with FNV-1a over the documented canonical encoding and does not
+    /// depend on HashMap seeds, the platform or its endianness. It is stable across versions,
+    /// a change of the hash values is a breaking change announced in the changelog.
+    pub fn canonical_hash(&self) -> u64 {
+        canonical_hash::fnv1a_64(&canonical_hash::encode_float(self))
+    }
+
+    /// Return a stable 128 bit hash of the CalculatorFloat.
+    ///
+    /// Uses the same canonical encoding as [CalculatorFloat::canonical_hash] with 128 bit FNV-1a,
+    /// for deduplication of large numbers of expressions.
+    pub fn canonical_hash_128(&self) -> u128 {
+        canonical_hash::fnv1a_128(&canonical_hash::encode_float(self))
+    }
+
+    /// Return the names of the free variables in the order of their first occurrence.
+    ///
+    /// Function names are not included, each variable is listed once.
+    /// A float has no variables.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Vec<String>)` - Names of the variables
+    /// * `Err(CalculatorError::InvalidExpression)` - The expression can not be lexed, e.g. it
+    ///   contains an unrecognized element, see [crate::validate_expression]
+    ///
+    pub fn variables(&self) -> Result<Vec<String>, CalculatorError> {
+        match self {
+            Self::Float(_) => Ok(Vec::new()),
+            Self::Str(expression) => variables_in_expression(expression),
+        }
+    }
+
+    /// Return the names of the placeholders, e.g. `qubit` for `{qubit}`, in the order of their first occurrence.
+    pub fn placeholders(&self) -> Vec<String> {
+        match self {
+            Self::Float(_) => Vec::new(),
+            Self::Str(expression) => {
+                let tokens: Vec<Token> = TokenIterator {
+                    current_expression: expression,
+                }
+                .collect();
+                placeholders_in(tokens.iter())
+            }
+        }
+    }
+
+    /// Replace the placeholders of the CalculatorFloat, e.g. `{qubit}`, by values.
+    ///
+    /// Placeholders are filled in when an expression is constructed and are never evaluated,
+    /// unlike variables that are set in the [crate::Calculator]. Symbolic values that are not
+    /// a single operand are inserted in brackets, so `{a} * 2` bound to `x + 1` gives `(x + 1) * 2`.
+    /// Placeholders in the values are inserted as they are and can be bound in a later step.
+    ///
+    /// # Arguments
+    ///
+    /// * `values` - Values of the placeholders by name, can be numbers, variables or expressions
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(CalculatorFloat)` - The CalculatorFloat with the values inserted
+    /// * `Err(CalculatorError::UnboundPlaceholder)` - Names of all placeholders without a value
+    ///
+    pub fn bind_placeholders(
+        &self,
+        values: &HashMap<String, CalculatorFloat>,
+    ) -> Result<CalculatorFloat, CalculatorError> {
+        let expression = match self {
+            Self::Float(_) => return Ok(self.clone()),
+            Self::Str(expression) => expression,
+        };
+        let missing: Vec<String> = self
+            .placeholders()
+            .into_iter()
+            .filter(|name| !values.contains_key(name))
+            .collect();
+        if !missing.is_empty() {
+            return Err(CalculatorError::UnboundPlaceholder { names: missing });
+        }
+        let mut output = String::with_capacity(expression.len());
+        let mut copied = 0;
+        for (position, token) in positioned_tokens(expression) {
+            if let Token::Placeholder(name) = token {
+                output.push_str(&expression[copied..position]);
+                let value = values[&*name].to_string();
+                if is_single_operand(&value) {
+                    output.push_str(&value);
+                } else {
+                    output.push_str(&format!("({value})"));
+                }
+                // The placeholder is the name in braces
+                copied = position + name.len() + 2;
+            }
+        }
+        output.push_str(&expression[copied..]);
+        Ok(CalculatorFloat::from(output))
+    }
+
+    /// Replace a variable of the symbolic expression by a value.
+    ///
+    /// Unlike [crate::Calculator::parse_get] the other variables do not need to be set,
+    /// so a substitution can be partial. Only variables with exactly the given name are
+    /// replaced, `a` does not touch `a_1`, functions and placeholders are kept. Symbolic
+    /// values that are not a single operand are inserted in brackets, so `2*theta` with
+    /// `theta` replaced by `alpha/2` gives `2*(alpha/2)`. When no variables and placeholders
+    /// are left, the expression is evaluated and a CalculatorFloat::Float is returned.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - Name of the replaced variable
+    /// * `value` - Value inserted for the variable, a number, variable or expression
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(CalculatorFloat)` - The CalculatorFloat with the value inserted
+    /// * `Err(CalculatorError::InvalidExpression)` - The expression can not be lexed
+    /// * `Err(CalculatorError)` - The expression became numeric and can not be evaluated, e.g. `DivisionByZero`
+    ///
+    pub fn substitute(
+        &self,
+        name: &str,
+        value: &CalculatorFloat,
+    ) -> Result<CalculatorFloat, CalculatorError> {
+        let expression = match self {
+            Self::Float(_) => return Ok(self.clone()),
+            Self::Str(expression) => expression,
+        };
+        if !variables_in_expression(expression)?
+            .iter()
+            .any(|variable| variable == name)
+        {
+            return Ok(self.clone());
+        }
+        let mut replacement = value.to_string();
+        if !is_single_operand(&replacement) {
+            replacement = format!("({replacement})");
+        }
+        let mut output = String::with_capacity(expression.len() + replacement.len());
+        let mut copied = 0;
+        for (span, token) in spanned_tokens(expression) {
+            if matches!(token, Token::Variable(variable) if variable == name) {
+                output.push_str(&expression[copied..span.start]);
+                output.push_str(&replacement);
+                copied = span.end;
+            }
+        }
+        output.push_str(&expression[copied..]);
+        let substituted = Self::Str(output);
+        if substituted.variables()?.is_empty() && substituted.placeholders().is_empty() {
+            parse_str(&substituted.to_string()).map(Self::Float)
+        } else {
+            Ok(substituted)
+        }
+    }
+
+    /// Rename the variables of the symbolic expression to `v0`, `v1`, ...
+    ///
+    /// Allows sharing an expression without the original variable names. Variables are renamed
+    /// consistently in the order of their first occurrence, numbers, functions and the
+    /// structure of the expression are not changed.
+    ///
+    /// # Returns
+    ///
+    /// * `(CalculatorFloat, HashMap<String, String>)` - The renamed CalculatorFloat and the original names with their replacements
+    ///
+    pub fn anonymized(&self) -> (CalculatorFloat, HashMap<String, String>) {
+        let mut mapping: HashMap<String, String> = HashMap::new();
+        let anonymized = self.anonymized_with(&mut mapping);
+        (anonymized, mapping)
+    }
+
+    /// Rename the variables of the symbolic expression continuing an existing mapping.
+    ///
+    /// # Arguments
+    ///
+    /// * `mapping` - Original names and their replacements, extended by the new names
+    ///
+    pub(crate) fn anonymized_with(&self, mapping: &mut HashMap<String, String>) -> CalculatorFloat {
+        match self {
+            Self::Float(_) => self.clone(),
+            Self::Str(expression) => Self::Str(anonymize_variables(expression, mapping)),
+        }
+    }
+
+    /// Compare two CalculatorFloat values in a total order.
+    ///
+    /// Every CalculatorFloat::Float is smaller than every CalculatorFloat::Str. Floats are
+    /// ordered by [f64::total_cmp], so `-0.0 < 0.0` and NaNs are ordered by their sign and
+    /// payload, with a positive NaN above positive infinity. Strings are ordered
+    /// lexicographically by their bytes and not by their value, so `"2"` and `"2e0"` differ.
+    ///
+    /// Sorting with `sort_by(CalculatorFloat::total_cmp)` is deterministic. The type does not
+    /// implement [Ord] as the order disagrees with [PartialEq] for NaN and `-0.0`, use
+    /// [crate::TotalOrd] for keys of ordered collections.
+    ///
+    /// # Arguments
+    ///
+    /// * `other` - CalculatorFloat compared to self
+    ///
+    pub fn total_cmp(&self, other: &Self) -> cmp::Ordering {
+        match (self, other) {
+            (Self::Float(x), Self::Float(y)) => x.total_cmp(y),
+            (Self::Float(_), Self::Str(_)) => cmp::Ordering::Less,
+            (Self::Str(_), Self::Float(_)) => cmp::Ordering::Greater,
+            (Self::Str(x), Self::Str(y)) => x.cmp(y),
+        }
+    }
+
+    /// Return every number literal of the symbolic expression with its byte range.
+    ///
+    /// The literals are returned in the order of the expression, e.g. `1e-3` in scientific
+    /// notation or `0.5` inside of function arguments. A minus in front of a literal is an
+    /// operator and not part of the literal: `-2.5 * x` returns `(2.5, 1..4)`.
+    /// CalculatorFloat::Float has no embedded literals.
+    pub fn embedded_literals(&self) -> Vec<(f64, Range<usize>)> {
+        match self {
+            Self::Float(_) => Vec::new(),
+            Self::Str(expression) => spanned_tokens(expression)
+                .into_iter()
+                .filter_map(|(span, token)| match token {
+                    Token::Number(value) => Some((value, span)),
+                    _ => None,
+                })
+                .collect(),
+        }
+    }
+
+    /// Replace the number literal at a byte range of the symbolic expression by a new value.
+    ///
+    /// The new value is written like the numbers in composed expressions, negative values
+    /// are put in brackets so that they are not combined with a preceding operator.
+    ///
+    /// # Arguments
+    ///
+    /// * `span` - Byte range of the literal as returned by [CalculatorFloat::embedded_literals]
+    /// * `new_value` - Finite value replacing the literal
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(CalculatorFloat)` - The CalculatorFloat with the replaced literal
+    /// * `Err(CalculatorError::ParsingError)` - There is no literal at the span or the value is not finite
+    /// * `Err(CalculatorError::InvalidExpression)` - The resulting expression is not valid
+    ///
+    pub fn replace_literal(
+        &self,
+        span: Range<usize>,
+        new_value: f64,
+    ) -> Result<CalculatorFloat, CalculatorError> {
+        if !new_value.is_finite() {
+            return Err(CalculatorError::ParsingError {
+                msg: "Only finite values can replace a number literal",
+                position: None,
+            });
+        }
+        let has_literal = |(_, literal): &(f64, Range<usize>)| *literal == span;
+        let expression = match self {
+            Self::Str(expression) if self.embedded_literals().iter().any(has_literal) => expression,
+            _ => {
+                return Err(CalculatorError::ParsingError {
+                    msg: "No number literal at the given span, see embedded_literals",
+                    position: None,
+                })
+            }
+        };
+        let value = if new_value.is_sign_negative() {
+            format!("({})", format_float(new_value))
+        } else {
+            format_float(new_value)
+        };
+        let replaced = format!(
+            "{}{value}{}",
+            &expression[..span.start],
+            &expression[span.end..]
+        );
+        validate_expression(&replaced)?;
+        Ok(CalculatorFloat::Str(replaced))
+    }
+
+    /// Return the CalculatorFloat as a string that can be parsed by SymPy.
+    ///
+    /// Power operators are written as `**`, function names are mapped onto their
+    /// SymPy equivalents (e.g. `sign` to `sign`, `theta` to `Heaviside`) and
+    /// variables are wrapped in `Symbol('name')` so they can not collide with
+    /// names that SymPy interprets as functions or constants (e.g. `theta`, `E`).
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(String)` - The expression in SymPy syntax
+    /// * `Err(CalculatorError)` - The expression contains unsupported elements
+    ///
+    pub fn to_sympy_string(&self) -> Result<String, CalculatorError> {
+        let expression = match self {
+            Self::Float(x) => return Ok(format!("{x:?}")),
+            Self::Str(y) => y,
+        };
+        let mut output = String::with_capacity(expression.len());
+        // For each open bracket the separator used for commas and the closing string
+        let mut bracket_stack: Vec<(&'static str, &'static str)> = Vec::new();
+        let tokens: Vec<Token> = TokenIterator {
+            current_expression: expression,
+        }
+        .collect();
+        for (index, token) in tokens.iter().enumerate() {
+            match token {
+                Token::Number(x) => output.push_str(&format!("{x:?}")),
+                Token::ImaginaryNumber(x) => output.push_str(&format!("({x:?}*I)")),
+                Token::Variable(name) => output.push_str(&format!("Symbol('{name}')")),
+                Token::Placeholder(_) => {
+                    return Err(CalculatorError::UnboundPlaceholder {
+                        names: self.placeholders(),
+                    })
+                }
+                Token::Function(name) => {
+                    let (open, separator, close) = sympy_function(name)?;
+                    output.push_str(&open);
+                    bracket_stack.push((separator, close));
+                }
+                Token::Reduction(name) => {
+                    let number_values = number_call_arguments(&tokens[index + 1..]);
+                    let (open, separator, close) = sympy_reduction(name, number_values)?;
+                    output.push_str(&open);
+                    bracket_stack.push((separator, close));
+                }
+                Token::Plus => output.push_str(" + "),
+                Token::Minus => output.push_str(" - "),
+                Token::Multiply => output.push_str(" * "),
+                Token::Divide => output.push_str(" / "),
+                // The Mod of SymPy has the sign of the divisor, not of the dividend
+                Token::Modulo => {
+                    return Err(CalculatorError::NotImplementedError { fct: "Modulo" })
+                }
+                Token::Power => output.push_str("**"),
+                // Comparisons of SymPy are booleans and can not be used in arithmetic
+                Token::Comparison(_) => {
+                    return Err(CalculatorError::NotImplementedError { fct: "Comparison" })
+                }
+                Token::BracketOpen => {
+                    output.push('(');
+                    bracket_stack.push((", ", ")"));
+                }
+                Token::BracketClose | Token::ListClose => {
+                    let (_, close) = bracket_stack.pop().ok_or(CalculatorError::ParsingError {
+                        msg: "Unexpected closing bracket",
+                        position: None,
+                    })?;
+                    output.push_str(close);
+                }
+                Token::Comma => {
+                    let (separator, _) =
+                        bracket_stack.last().ok_or(CalculatorError::ParsingError {
+                            msg: "Unexpected comma outside of function arguments",
+                            position: None,
+                        })?;
+                    output.push_str(separator);
+                }
+                Token::EndOfString => (),
+                Token::VariableAssign(variable_name) | Token::CompoundAssign(variable_name, _) => {
+                    return Err(CalculatorError::NotParsableAssign {
+                        variable_name: variable_name.to_string(),
+                    })
+                }
+                Token::Assign => return Err(CalculatorError::NotParsableSingleAssign),
+                Token::Factorial => {
+                    return Err(CalculatorError::NotImplementedError { fct: "Factorial" })
+                }
+                Token::DoubleFactorial => {
+                    return Err(CalculatorError::NotImplementedError {
+                        fct: "DoubleFactorial",
+                    })
+                }
+                Token::EndOfExpression
+                | Token::StringLiteral(_)
+                | Token::ListOpen
+                | Token::Unrecognized => return Err(CalculatorError::NotParsableUnrecognized),
+            }
+        }
+        if !bracket_stack.is_empty() {
+            return Err(CalculatorError::ParsingError {
+                msg: "Expected Braket close",
+                position: None,
+            });
+        }
+        Ok(output)
+    }
+}
+
+/// Match name of function to the SymPy representation of the function.
+///
+/// Returns the opening string, the separator used in place of commas between
+/// arguments and the closing string.
+fn sympy_function(name: &str) -> Result<(String, &'static str, &'static str), CalculatorError> {
+    let sympy_name = match name {
+        "sin" | "cos" | "tan" | "acos" | "asin" | "atan" | "cosh" | "sinh" | "tanh" | "acosh"
+        | "asinh" | "atanh" | "exp" | "log" | "sqrt" | "cbrt" | "floor" | "erf" | "sign"
+        | "atan2" => name,
+        "abs" => "Abs",
+        "arcosh" => "acosh",
+        "arsinh" => "asinh",
+        "artanh" => "atanh",
+        "ceil" => "ceiling",
+        "fract" => "frac",
+        "tgamma" => "gamma",
+        "lgamma" => "loggamma",
+        "pow" => "Pow",
+        "max" => "Max",
+        "min" => "Min",
+        "sum" => "Add",
+        "log10" => return Ok(("log(".to_string(), ", ", ", 10)")),
+        "log2" => return Ok(("log(".to_string(), ", ", ", 2)")),
+        "exp2" => return Ok(("2**(".to_string(), ", ", ")")),
+        "expm1" => return Ok(("(exp(".to_string(), ", ", ") - 1)")),
+        "hypot" => return Ok(("sqrt((".to_string(), ")**2 + (", ")**2)")),
+        "delta" => return Ok(("KroneckerDelta(".to_string(), ", ", ", 0)")),
+        "theta" => return Ok(("Heaviside(".to_string(), ", ", ", 1/2)")),
+        "round" => return Err(CalculatorError::NotImplementedError { fct: "round" }),
+        "round_digits" => {
+            return Err(CalculatorError::NotImplementedError {
+                fct: "round_digits",
+            })
+        }
+        "round_step" => return Err(CalculatorError::NotImplementedError { fct: "round_step" }),
+        "factorial" | "gamma" => name,
+        "binom" => "binomial",
+        "parity" => return Ok(("(-1)**(".to_string(), ", ", ")")),
+        _ => {
+            return Err(CalculatorError::FunctionNotFound {
+                fct: name.to_string(),
+            })
+        }
+    };
+    Ok((format!("{sympy_name}("), ", ", ")"))
+}
+
+/// Match name of a reduction to its SymPy representation, see [sympy_function].
+///
+/// # Arguments
+///
+/// * `name` - Name of the reduction
+/// * `number_values` - Number of values in the square brackets
+///
+fn sympy_reduction(
+    name: &str,
+    number_values: usize,
+) -> Result<(String, &'static str, &'static str), CalculatorError> {
+    let (open, close) = match name {
+        "sum" => return Ok(("Add(".to_string(), ", ", ")")),
+        _ if number_values == 0 && REDUCTIONS.contains(&name) => {
+            return Err(CalculatorError::NotEnoughFunctionArguments)
+        }
+        "max" => ("Max(".to_string(), ")"),
+        "min" => ("Min(".to_string(), ")"),
+        "mean" => (format!("Mul(Rational(1, {number_values}), Add("), "))"),
+        _ => {
+            return Err(CalculatorError::FunctionNotFound {
+                fct: format!("{name}[]"),
+            })
+        }
+    };
+    Ok((open, ", ", close))
+}
+
+/// Return `(left operator right)` built in the buffer of the symbolic left operand.
+///
+/// The buffer grows geometrically, so the assigning operators do not allocate a new string
+/// for every operation. Inserting the opening bracket moves the expression, so a chain of
+/// assigning operators costs time quadratic in its length, see [SymbolicAccumulator].
+fn append_operation(mut left: String, operator: &str, right: &str) -> String {
+    left.reserve(right.len() + operator.len() + 4);
+    left.insert(0, '(');
+    left.push(' ');
+    left.push_str(operator);
+    left.push(' ');
+    left.push_str(right);
+    left.push(')');
+    left
+}
+
+/// Return `(left operator right)` built in the buffer of the symbolic right operand.
+fn prepend_operation(left: &str, operator: &str, mut right: String) -> String {
+    right.reserve(left.len() + operator.len() + 4);
+    right.insert_str(0, &format!("({left} {operator} "));
+    right.push(')');
+    right
+}
+
+/// Return `(left operator right)` for borrowed operands, allocating the result once.
+fn borrowed_operation(left: &str, operator: &str, right: &str) -> String {
+    let mut expression = String::with_capacity(left.len() + operator.len() + right.len() + 4);
+    expression.push('(');
+    expression.push_str(left);
+    expression.push(' ');
+    expression.push_str(operator);
+    expression.push(' ');
+    expression.push_str(right);
+    expression.push(')');
+    expression
+}
+
+/// Accumulator building a CalculatorFloat with `+=`, `-=`, `*=` and `/=` in linear time.
+///
+/// Every assigning operator of [CalculatorFloat] moves the accumulated expression to insert the
+/// opening bracket of its result, so the cost of a long chain grows quadratically with its
+/// length. The accumulator only counts the opening brackets and writes them once in
+/// [SymbolicAccumulator::finish]. Runs of `+=` and `-=` form one flat sum, e.g. `(a + b - c)`,
+/// which the parser evaluates like `((a + b) - c)`. Numeric operands and the shortcuts for
+/// zero and one are handled like by the operators of CalculatorFloat.
+///
+/// # Example
+///
+/// ```
+/// use qoqo_calculator::{CalculatorFloat, SymbolicAccumulator};
+///
+/// let mut accumulator = SymbolicAccumulator::default();
+/// accumulator += 2.0;
+/// accumulator += "a";
+/// accumulator -= "b";
+/// accumulator *= "c";
+/// assert_eq!(accumulator.finish(), CalculatorFloat::from("((2e0 + a - b) * c)"));
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct SymbolicAccumulator {
+    /// Accumulated value, symbolic values without the leading opening brackets
+    value: CalculatorFloat,
+    /// Number of opening brackets missing in front of the symbolic value
+    brackets: usize,
+    /// True when the innermost missing bracket is an unclosed sum that can be extended
+    open_sum: bool,
+}
+
+impl Default for SymbolicAccumulator {
+    fn default() -> Self {
+        SymbolicAccumulator::from(CalculatorFloat::Float(0.0))
+    }
+}
+
+impl From<CalculatorFloat> for SymbolicAccumulator {
+    fn from(value: CalculatorFloat) -> Self {
+        SymbolicAccumulator {
+            value,
+            brackets: 0,
+            open_sum: false,
         }
     }
-    /// Return cosine function cos(x) for CalculatorFloat.
-    pub fn cos(&self) -> CalculatorFloat {
-        match self {
-            Self::Float(x) => CalculatorFloat::Float(x.cos()),
-            Self::Str(y) => Self::Str(format!("cos({y})")),
+}
+
+impl SymbolicAccumulator {
+    /// Return the accumulated value.
+    ///
+    /// # Returns
+    ///
+    /// * `CalculatorFloat` - The value the same operators on CalculatorFloat give,
+    ///   with runs of `+` and `-` written as one flat sum
+    ///
+    pub fn finish(self) -> CalculatorFloat {
+        match self.value {
+            CalculatorFloat::Str(expression) if self.brackets > 0 => {
+                let mut result = String::with_capacity(
+                    self.brackets + expression.len() + usize::from(self.open_sum),
+                );
+                result.extend(std::iter::repeat('(').take(self.brackets));
+                result.push_str(&expression);
+                if self.open_sum {
+                    result.push(')');
+                }
+                CalculatorFloat::Str(result)
+            }
+            value => value,
         }
     }
-    /// Return arccosine function acos(x) for CalculatorFloat.
-    pub fn acos(&self) -> CalculatorFloat {
-        match self {
-            Self::Float(x) => CalculatorFloat::Float(x.acos()),
-            Self::Str(y) => Self::Str(format!("acos({y})")),
+
+    /// Apply an arithmetic operator with other as right operand.
+    ///
+    /// # Panics
+    ///
+    /// Panics on division by zero.
+    /// Panics when the accumulated expression gets longer than [max_symbolic_length].
+    ///
+    fn apply(&mut self, operator: &str, other: CalculatorFloat) {
+        let expression = match &mut self.value {
+            CalculatorFloat::Float(x) => {
+                let x = *x;
+                self.value = match (operator, other) {
+                    ("+" | "-", CalculatorFloat::Str(y)) if !is_zero_value(x) => {
+                        let expression = format!("{} {operator} {y}", format_float(x));
+                        if let Err(error) = check_combined_length(expression.len() + 2) {
+                            panic!("{error}")
+                        }
+                        self.brackets = 1;
+                        self.open_sum = true;
+                        CalculatorFloat::Str(expression)
+                    }
+                    ("+", other) => CalculatorFloat::Float(x) + other,
+                    ("-", other) => CalculatorFloat::Float(x) - other,
+                    ("*", other) => CalculatorFloat::Float(x) * other,
+                    (_, other) => CalculatorFloat::Float(x) / other,
+                };
+                return;
+            }
+            CalculatorFloat::Str(expression) => expression,
+        };
+        let operand = match other {
+            CalculatorFloat::Str(y) => y,
+            CalculatorFloat::Float(y) => match operator {
+                "/" if y == 0.0 => panic!("Division by zero"),
+                "+" | "-" if is_zero_value(y) => return,
+                "*" if is_zero_value(y) => {
+                    *self = SymbolicAccumulator::default();
+                    return;
+                }
+                "*" | "/" if is_one_value(y) => return,
+                _ => format_float(y),
+            },
+        };
+        let length = self.brackets + expression.len() + usize::from(self.open_sum) + operand.len();
+        if let Err(error) = check_combined_length(length) {
+            panic!("{error}")
         }
-    }
-    /// Return absolute value abs(x) for CalculatorFloat.
-    pub fn abs(&self) -> CalculatorFloat {
-        match self {
-            Self::Float(x) => CalculatorFloat::Float(x.abs()),
-            Self::Str(y) => Self::Str(format!("abs({y})")),
+        let sum = matches!(operator, "+" | "-");
+        expression.reserve(operand.len() + operator.len() + 3);
+        if self.open_sum && !sum {
+            expression.push(')');
+            self.open_sum = false;
         }
-    }
-    /// Return signum value sign(x) for CalculatorFloat.
-    pub fn signum(&self) -> CalculatorFloat {
-        match self {
-            Self::Float(x) => CalculatorFloat::Float(x.signum()),
-            Self::Str(y) => Self::Str(format!("sign({y})")),
+        if !self.open_sum {
+            self.brackets += 1;
         }
-    }
-    /// Return True if self value is close to other value.
-    pub fn isclose<T>(&self, other: T) -> bool
-    where
-        T: Into<CalculatorFloat>,
-    {
-        let other_from: CalculatorFloat = other.into();
-        match self {
-            Self::Float(x) => match other_from {
-                Self::Float(y) => (x - y).abs() <= (ATOL + RTOL * y.abs()),
-                Self::Str(y) => format!("{x:e}") == y,
-            },
-            Self::Str(x) => match other_from {
-                Self::Float(y) => x == &format!("{y:e}"),
-                Self::Str(y) => x == &y,
-            },
+        expression.push(' ');
+        expression.push_str(operator);
+        expression.push(' ');
+        expression.push_str(&operand);
+        if sum {
+            self.open_sum = true;
+        } else {
+            expression.push(')');
         }
     }
+}
 
-    /// Return Some(f64) when CalculatorFloat is a numeric value
-    pub fn float(&self) -> Result<&f64, CalculatorError> {
-        match self {
-            Self::Float(x) => Ok(x),
-            Self::Str(x) => Err(CalculatorError::FloatSymbolicNotConvertable { val: x.clone() }),
-        }
+/// Implement `+=` (add) for SymbolicAccumulator and generic type `T`.
+///
+/// # Arguments
+///
+/// * `other` - Any type T for which CalculatorFloat::From<T> trait is implemented
+///
+/// # Panics
+///
+/// Panics when the accumulated expression gets longer than [max_symbolic_length].
+///
+impl<T> ops::AddAssign<T> for SymbolicAccumulator
+where
+    T: Into<CalculatorFloat>,
+{
+    fn add_assign(&mut self, other: T) {
+        self.apply("+", other.into());
     }
+}
 
-    /// Return inverse/reciprocal function (1/x) for CalculatorFloat.
-    pub fn recip(&self) -> CalculatorFloat {
-        match self {
-            Self::Float(x) => Self::Float(x.recip()),
-            Self::Str(y) => Self::Str(format!("(1 / {y})")),
-        }
+/// Implement `-=` (subtract) for SymbolicAccumulator and generic type `T`.
+///
+/// # Arguments
+///
+/// * `other` - Any type T for which CalculatorFloat::From<T> trait is implemented
+///
+/// # Panics
+///
+/// Panics when the accumulated expression gets longer than [max_symbolic_length].
+///
+impl<T> ops::SubAssign<T> for SymbolicAccumulator
+where
+    T: Into<CalculatorFloat>,
+{
+    fn sub_assign(&mut self, other: T) {
+        self.apply("-", other.into());
+    }
+}
+
+/// Implement `*=` (multiply) for SymbolicAccumulator and generic type `T`.
+///
+/// # Arguments
+///
+/// * `other` - Any type T for which CalculatorFloat::From<T> trait is implemented
+///
+/// # Panics
+///
+/// Panics when the accumulated expression gets longer than [max_symbolic_length].
+///
+impl<T> ops::MulAssign<T> for SymbolicAccumulator
+where
+    T: Into<CalculatorFloat>,
+{
+    fn mul_assign(&mut self, other: T) {
+        self.apply("*", other.into());
+    }
+}
+
+/// Implement `/=` (divide) for SymbolicAccumulator and generic type `T`.
+///
+/// # Arguments
+///
+/// * `other` - Any type T for which CalculatorFloat::From<T> trait is implemented
+///
+/// # Panics
+///
+/// Panics on division by zero.
+/// Division by zero is only detected when other is converted to CalculatorFloat::Float
+/// Panics when the accumulated expression gets longer than [max_symbolic_length].
+///
+impl<T> ops::DivAssign<T> for SymbolicAccumulator
+where
+    T: Into<CalculatorFloat>,
+{
+    fn div_assign(&mut self, other: T) {
+        self.apply("/", other.into());
     }
 }
+
 /// Implement `+` (add) for CalculatorFloat and generic type `T`.
 ///
 /// # Arguments
 ///
 /// * `other` - Any type T for which CalculatorFloat::From<T> trait is implemented
 ///
+/// # Panics
+///
+/// Panics when the symbolic expressions of the operands are together longer than [max_symbolic_length].
+///
 impl<T> ops::Add<T> for CalculatorFloat
 where
     T: Into<CalculatorFloat>,
@@ -788,12 +2992,13 @@ where
     type Output = Self;
     fn add(self, other: T) -> Self {
         let other_from: CalculatorFloat = other.into();
+        assert_symbolic_length(&self, &other_from);
         match self {
             Self::Float(x) => match other_from {
                 Self::Float(y) => CalculatorFloat::Float(x + y),
                 Self::Str(y) => {
-                    if x != 0.0 {
-                        Self::Str(format!("({:e} + {})", x, &y))
+                    if !is_zero_value(x) {
+                        Self::Str(prepend_operation(&format_float(x), "+", y))
                     } else {
                         Self::Str(y)
                     }
@@ -801,13 +3006,13 @@ where
             },
             Self::Str(x) => match other_from {
                 Self::Float(y) => {
-                    if y != 0.0 {
-                        Self::Str(format!("({} + {:e})", &x, y))
+                    if !is_zero_value(y) {
+                        Self::Str(append_operation(x, "+", &format_float(y)))
                     } else {
                         Self::Str(x)
                     }
                 }
-                Self::Str(y) => Self::Str(format!("({} + {})", &x, &y)),
+                Self::Str(y) => Self::Str(append_operation(x, "+", &y)),
             },
         }
     }
@@ -815,61 +3020,44 @@ where
 
 /// Implements summing over an iterator of CalculatorFloat
 ///
+/// The summands are appended to one flat sum, see [SymbolicAccumulator],
+/// so the cost grows linearly with the number of summands.
+///
 /// # Arguments
 ///
 /// * `iter` - Any iterator over CalculatorFloat items
 ///
 impl std::iter::Sum for CalculatorFloat {
     fn sum<I: Iterator<Item = CalculatorFloat>>(iter: I) -> Self {
-        let mut sum = CalculatorFloat::from(0);
+        let mut sum = SymbolicAccumulator::default();
         for i in iter {
             sum += i;
         }
-        sum
+        sum.finish()
     }
 }
 
 /// Implement `+=` (add) for CalculatorFloat and generic type `T`.
 ///
+/// The symbolic expression is extended in place, see [CalculatorFloat::add](ops::Add::add).
+/// Each operation moves the expression to insert the opening bracket of `(a + b)`, so
+/// accumulating n terms costs time quadratic in n. Use [SymbolicAccumulator] or `Sum`
+/// for long sums, they write the terms to one flat sum in linear time.
+///
 /// # Arguments
 ///
 /// * `other` - Any type T for which CalculatorFloat::From<T> trait is implemented
 ///
+/// # Panics
+///
+/// Panics when the symbolic expressions of the operands are together longer than [max_symbolic_length].
+///
 impl<T> ops::AddAssign<T> for CalculatorFloat
 where
     T: Into<CalculatorFloat>,
 {
     fn add_assign(&mut self, other: T) {
-        let other_from: CalculatorFloat = other.into();
-
-        match self {
-            Self::Float(x) => match other_from {
-                Self::Float(y) => {
-                    *self = Self::Float(*x + y);
-                }
-                Self::Str(y) => {
-                    *self = {
-                        if (*x - 0.0).abs() > ATOL {
-                            Self::Str(format!("({:e} + {})", x, &y))
-                        } else {
-                            Self::Str(y)
-                        }
-                    }
-                }
-            },
-            Self::Str(x) => match other_from {
-                Self::Float(y) => {
-                    *self = {
-                        if y != 0.0 {
-                            Self::Str(format!("({x} + {y:e})"))
-                        } else {
-                            Self::Str(x.to_owned())
-                        }
-                    }
-                }
-                Self::Str(y) => *self = Self::Str(format!("({} + {})", x, &y)),
-            },
-        }
+        *self = std::mem::take(self) + other;
     }
 }
 
@@ -879,19 +3067,24 @@ where
 ///
 /// * `other` - Any type T for which CalculatorFloat::From<T> trait is implemented
 ///
-impl<'a, T> ops::Add<T> for &'a CalculatorFloat
+/// # Panics
+///
+/// Panics when the symbolic expressions of the operands are together longer than [max_symbolic_length].
+///
+impl<T> ops::Add<T> for &CalculatorFloat
 where
     CalculatorFloat: From<T>,
 {
     type Output = CalculatorFloat;
     fn add(self, other: T) -> CalculatorFloat {
         let other_from = CalculatorFloat::from(other);
+        assert_symbolic_length(self, &other_from);
         match self {
             CalculatorFloat::Float(x) => match other_from {
                 CalculatorFloat::Float(y) => CalculatorFloat::Float(x + y),
                 CalculatorFloat::Str(y) => {
-                    if (x - 0.0).abs() > ATOL {
-                        CalculatorFloat::Str(format!("({:e} + {})", x, &y))
+                    if !is_zero_value(*x) {
+                        CalculatorFloat::Str(prepend_operation(&format_float(*x), "+", y))
                     } else {
                         CalculatorFloat::Str(y)
                     }
@@ -899,13 +3092,13 @@ where
             },
             CalculatorFloat::Str(x) => match other_from {
                 CalculatorFloat::Float(y) => {
-                    if y != 0.0 {
-                        CalculatorFloat::Str(format!("({x} + {y:e})"))
+                    if !is_zero_value(y) {
+                        CalculatorFloat::Str(borrowed_operation(x, "+", &format_float(y)))
                     } else {
                         CalculatorFloat::Str(x.to_owned())
                     }
                 }
-                CalculatorFloat::Str(y) => CalculatorFloat::Str(format!("({} + {})", x, &y)),
+                CalculatorFloat::Str(y) => CalculatorFloat::Str(prepend_operation(x, "+", y)),
             },
         }
     }
@@ -921,6 +3114,7 @@ where
 ///
 /// Panics on division by zero.
 /// Division by zero is only detected when other is converted to CalculatorFloat::Float
+/// Panics when the symbolic expressions of the operands are together longer than [max_symbolic_length].
 ///
 impl<T> ops::Div<T> for CalculatorFloat
 where
@@ -929,6 +3123,7 @@ where
     type Output = Self;
     fn div(self, other: T) -> Self {
         let other_from: CalculatorFloat = other.into();
+        assert_symbolic_length(&self, &other_from);
         match self {
             Self::Float(x) => match other_from {
                 Self::Float(y) => {
@@ -939,10 +3134,10 @@ where
                     }
                 }
                 Self::Str(y) => {
-                    if x == 0.0 {
+                    if is_zero_value(x) {
                         Self::Float(0.0)
                     } else {
-                        Self::Str(format!("({:e} / {})", x, &y))
+                        Self::Str(prepend_operation(&format_float(x), "/", y))
                     }
                 }
             },
@@ -950,13 +3145,13 @@ where
                 Self::Float(y) => {
                     if y == 0.0 {
                         panic!("Division by zero")
-                    } else if (y - 1.0).abs() < ATOL {
+                    } else if is_one_value(y) {
                         Self::Str(x)
                     } else {
-                        Self::Str(format!("({} / {:e})", &x, y))
+                        Self::Str(append_operation(x, "/", &format_float(y)))
                     }
                 }
-                Self::Str(y) => Self::Str(format!("({} / {})", &x, &y)),
+                Self::Str(y) => Self::Str(append_operation(x, "/", &y)),
             },
         }
     }
@@ -964,6 +3159,9 @@ where
 
 /// Implement `/=` (divide) for CalculatorFloat and generic type `T`.
 ///
+/// The symbolic expression is extended in place, see [CalculatorFloat::div](ops::Div::div).
+/// Long chains cost time quadratic in their length, see [CalculatorFloat::add_assign](ops::AddAssign::add_assign).
+///
 /// # Arguments
 ///
 /// * `other` - Any type T for which CalculatorFloat::From<T> trait is implemented
@@ -972,49 +3170,57 @@ where
 ///
 /// Panics on division by zero.
 /// Division by zero is only detected when other is converted to CalculatorFloat::Float
+/// Panics when the symbolic expressions of the operands are together longer than [max_symbolic_length].
 ///
 impl<T> ops::DivAssign<T> for CalculatorFloat
 where
     T: Into<CalculatorFloat>,
 {
     fn div_assign(&mut self, other: T) {
-        let other_from: CalculatorFloat = other.into();
-        match self {
-            Self::Float(x) => match other_from {
-                Self::Float(y) => {
-                    *self = {
-                        if y == 0.0 {
-                            panic!("Division by zero")
-                        } else {
-                            Self::Float(*x / y)
-                        }
-                    }
-                }
-                Self::Str(y) => {
-                    *self = {
-                        if (*x - 0.0).abs() < ATOL {
-                            Self::Float(0.0)
-                        } else {
-                            Self::Str(format!("({:e} / {})", x, &y))
-                        }
-                    }
-                }
-            },
-            Self::Str(x) => match other_from {
-                Self::Float(y) => {
-                    *self = {
-                        if y == 0.0 {
-                            panic!("Division by zero")
-                        } else if (y - 1.0).abs() < ATOL {
-                            Self::Str(x.to_owned())
-                        } else {
-                            Self::Str(format!("({x} / {y:e})"))
-                        }
-                    }
-                }
-                Self::Str(y) => *self = Self::Str(format!("({} / {})", x, &y)),
-            },
-        }
+        *self = std::mem::take(self) / other;
+    }
+}
+
+/// Implement `%` (remainder) for CalculatorFloat and generic type `T`, see [CalculatorFloat::rem].
+///
+/// # Arguments
+///
+/// * `other` - Any type T for which CalculatorFloat::From<T> trait is implemented
+///
+/// # Panics
+///
+/// Panics on division by zero.
+/// Division by zero is only detected when other is converted to CalculatorFloat::Float
+/// Panics when the symbolic expressions of the operands are together longer than [max_symbolic_length].
+///
+impl<T> ops::Rem<T> for CalculatorFloat
+where
+    T: Into<CalculatorFloat>,
+{
+    type Output = Self;
+    fn rem(self, other: T) -> Self {
+        CalculatorFloat::rem(&self, other)
+    }
+}
+
+/// Implement `%=` (remainder) for CalculatorFloat and generic type `T`, see [CalculatorFloat::rem].
+///
+/// # Arguments
+///
+/// * `other` - Any type T for which CalculatorFloat::From<T> trait is implemented
+///
+/// # Panics
+///
+/// Panics on division by zero.
+/// Division by zero is only detected when other is converted to CalculatorFloat::Float
+/// Panics when the symbolic expressions of the operands are together longer than [max_symbolic_length].
+///
+impl<T> ops::RemAssign<T> for CalculatorFloat
+where
+    T: Into<CalculatorFloat>,
+{
+    fn rem_assign(&mut self, other: T) {
+        *self = CalculatorFloat::rem(self, other);
     }
 }
 
@@ -1024,6 +3230,10 @@ where
 ///
 /// * `other` - Any type T for which CalculatorFloat::From<T> trait is implemented
 ///
+/// # Panics
+///
+/// Panics when the symbolic expressions of the operands are together longer than [max_symbolic_length].
+///
 impl<T> ops::Mul<T> for CalculatorFloat
 where
     T: Into<CalculatorFloat>,
@@ -1031,30 +3241,31 @@ where
     type Output = Self;
     fn mul(self, other: T) -> Self {
         let other_from: CalculatorFloat = other.into();
+        assert_symbolic_length(&self, &other_from);
         match self {
             Self::Float(x) => match other_from {
                 Self::Float(y) => Self::Float(x * y),
                 Self::Str(y) => {
-                    if x == 0.0 {
+                    if is_zero_value(x) {
                         Self::Float(0.0)
-                    } else if (x - 1.0).abs() < ATOL {
+                    } else if is_one_value(x) {
                         Self::Str(y)
                     } else {
-                        Self::Str(format!("({:e} * {})", x, &y))
+                        Self::Str(prepend_operation(&format_float(x), "*", y))
                     }
                 }
             },
             Self::Str(x) => match other_from {
                 Self::Float(y) => {
-                    if y == 0.0 {
+                    if is_zero_value(y) {
                         Self::Float(0.0)
-                    } else if (y - 1.0).abs() < ATOL {
+                    } else if is_one_value(y) {
                         Self::Str(x)
                     } else {
-                        Self::Str(format!("({} * {:e})", &x, y))
+                        Self::Str(append_operation(x, "*", &format_float(y)))
                     }
                 }
-                Self::Str(y) => Self::Str(format!("({x} * {y})")),
+                Self::Str(y) => Self::Str(append_operation(x, "*", &y)),
             },
         }
     }
@@ -1066,6 +3277,10 @@ where
 ///
 /// * `other` - Any type T for which CalculatorFloat::From<T> trait is implemented
 ///
+/// # Panics
+///
+/// Panics when the symbolic expressions of the operands are together longer than [max_symbolic_length].
+///
 impl<T> ops::Mul<T> for &CalculatorFloat
 where
     T: Into<CalculatorFloat>,
@@ -1073,30 +3288,31 @@ where
     type Output = CalculatorFloat;
     fn mul(self, other: T) -> CalculatorFloat {
         let other_from: CalculatorFloat = other.into();
+        assert_symbolic_length(self, &other_from);
         match self {
             CalculatorFloat::Float(x) => match other_from {
                 CalculatorFloat::Float(y) => CalculatorFloat::Float(x * y),
                 CalculatorFloat::Str(y) => {
-                    if *x == 0.0 {
+                    if is_zero_value(*x) {
                         CalculatorFloat::Float(0.0)
-                    } else if (x - 1.0).abs() < ATOL {
+                    } else if is_one_value(*x) {
                         CalculatorFloat::Str(y)
                     } else {
-                        CalculatorFloat::Str(format!("({:e} * {})", x, &y))
+                        CalculatorFloat::Str(prepend_operation(&format_float(*x), "*", y))
                     }
                 }
             },
             CalculatorFloat::Str(x) => match other_from {
                 CalculatorFloat::Float(y) => {
-                    if y == 0.0 {
+                    if is_zero_value(y) {
                         CalculatorFloat::Float(0.0)
-                    } else if (y - 1.0).abs() < ATOL {
+                    } else if is_one_value(y) {
                         CalculatorFloat::Str(x.to_string())
                     } else {
-                        CalculatorFloat::Str(format!("({} * {:e})", &x, y))
+                        CalculatorFloat::Str(borrowed_operation(x, "*", &format_float(y)))
                     }
                 }
-                CalculatorFloat::Str(y) => CalculatorFloat::Str(format!("({x} * {y})")),
+                CalculatorFloat::Str(y) => CalculatorFloat::Str(prepend_operation(x, "*", y)),
             },
         }
     }
@@ -1104,48 +3320,23 @@ where
 
 /// Implement `*=` (multiply) for CalculatorFloat and generic type `T`.
 ///
+/// The symbolic expression is extended in place, see [CalculatorFloat::mul](ops::Mul::mul).
+/// Long chains cost time quadratic in their length, see [CalculatorFloat::add_assign](ops::AddAssign::add_assign).
+///
 /// # Arguments
 ///
 /// * `other` - Any type T for which CalculatorFloat::From<T> trait is implemented
 ///
+/// # Panics
+///
+/// Panics when the symbolic expressions of the operands are together longer than [max_symbolic_length].
+///
 impl<T> ops::MulAssign<T> for CalculatorFloat
 where
     T: Into<CalculatorFloat>,
 {
     fn mul_assign(&mut self, other: T) {
-        let other_from: CalculatorFloat = other.into();
-        match self {
-            Self::Float(x) => match other_from {
-                Self::Float(y) => {
-                    *self = Self::Float(*x * y);
-                }
-                Self::Str(y) => {
-                    *self = {
-                        if (*x - 0.0).abs() < ATOL {
-                            Self::Float(0.0)
-                        } else if (*x - 1.0).abs() < ATOL {
-                            Self::Str(y)
-                        } else {
-                            Self::Str(format!("({x:e} * {y})"))
-                        }
-                    }
-                }
-            },
-            Self::Str(x) => match other_from {
-                Self::Float(y) => {
-                    *self = {
-                        if y == 0.0 {
-                            Self::Float(0.0)
-                        } else if (y - 1.0).abs() < ATOL {
-                            Self::Str(x.to_string())
-                        } else {
-                            Self::Str(format!("({x} * {y:e})"))
-                        }
-                    }
-                }
-                Self::Str(y) => *self = Self::Str(format!("({x} * {y})")),
-            },
-        }
+        *self = std::mem::take(self) * other;
     }
 }
 
@@ -1155,6 +3346,10 @@ where
 ///
 /// * `other` - Any type T for which CalculatorFloat::From<T> trait is implemented
 ///
+/// # Panics
+///
+/// Panics when the symbolic expressions of the operands are together longer than [max_symbolic_length].
+///
 impl<T> ops::Sub<T> for CalculatorFloat
 where
     T: Into<CalculatorFloat>,
@@ -1162,26 +3357,27 @@ where
     type Output = Self;
     fn sub(self, other: T) -> Self {
         let other_from: CalculatorFloat = other.into();
+        assert_symbolic_length(&self, &other_from);
         match self {
             CalculatorFloat::Float(x) => match other_from {
                 CalculatorFloat::Float(y) => CalculatorFloat::Float(x - y),
                 CalculatorFloat::Str(y) => {
-                    if x != 0.0 {
-                        CalculatorFloat::Str(format!("({x:e} - {y})"))
+                    if !is_zero_value(x) {
+                        CalculatorFloat::Str(prepend_operation(&format_float(x), "-", y))
                     } else {
-                        CalculatorFloat::Str(format!("(-{})", &y))
+                        -CalculatorFloat::Str(y)
                     }
                 }
             },
             CalculatorFloat::Str(x) => match other_from {
                 CalculatorFloat::Float(y) => {
-                    if y != 0.0 {
-                        CalculatorFloat::Str(format!("({x} - {y:e})"))
+                    if !is_zero_value(y) {
+                        CalculatorFloat::Str(append_operation(x, "-", &format_float(y)))
                     } else {
                         CalculatorFloat::Str(x)
                     }
                 }
-                CalculatorFloat::Str(y) => CalculatorFloat::Str(format!("({x} - {y})")),
+                CalculatorFloat::Str(y) => CalculatorFloat::Str(append_operation(x, "-", &y)),
             },
         }
     }
@@ -1189,44 +3385,23 @@ where
 
 /// Implement `-=` (subtract) for CalculatorFloat and generic type `T`.
 ///
+/// The symbolic expression is extended in place, see [CalculatorFloat::sub](ops::Sub::sub).
+/// Long chains cost time quadratic in their length, see [CalculatorFloat::add_assign](ops::AddAssign::add_assign).
+///
 /// # Arguments
 ///
 /// * `other` - Any type T for which CalculatorFloat::From<T> trait is implemented
 ///
+/// # Panics
+///
+/// Panics when the symbolic expressions of the operands are together longer than [max_symbolic_length].
+///
 impl<T> ops::SubAssign<T> for CalculatorFloat
 where
     T: Into<CalculatorFloat>,
 {
     fn sub_assign(&mut self, other: T) {
-        let other_from: CalculatorFloat = other.into();
-        match self {
-            Self::Float(x) => match other_from {
-                Self::Float(y) => {
-                    *self = Self::Float(*x - y);
-                }
-                Self::Str(y) => {
-                    *self = {
-                        if (*x - 0.0).abs() > ATOL {
-                            Self::Str(format!("({x:e} - {y})"))
-                        } else {
-                            Self::Str(format!("(-{y})"))
-                        }
-                    }
-                }
-            },
-            Self::Str(x) => match other_from {
-                Self::Float(y) => {
-                    *self = {
-                        if y != 0.0 {
-                            Self::Str(format!("({x} - {y:e})"))
-                        } else {
-                            Self::Str(x.to_owned())
-                        }
-                    }
-                }
-                Self::Str(y) => *self = Self::Str(format!("({x} - {y})")),
-            },
-        }
+        *self = std::mem::take(self) - other;
     }
 }
 
@@ -1237,17 +3412,54 @@ impl ops::Neg for CalculatorFloat {
     fn neg(self) -> Self {
         match self {
             Self::Float(x) => Self::Float(-x),
-            Self::Str(y) => Self::Str(format!("(-{y})")),
+            Self::Str(mut y) => {
+                y.reserve(3);
+                y.insert_str(0, "(-");
+                y.push(')');
+                Self::Str(y)
+            }
         }
     }
 }
 
+/// Implement arithmetic operators with a scalar as left operand and CalculatorFloat as right operand.
+///
+/// The scalar is converted to CalculatorFloat and the operator of CalculatorFloat is applied,
+/// so symbolic results and the shortcuts for zero and one are the same as for `CalculatorFloat * T`.
+macro_rules! scalar_first_operations {
+    ($scalar:ty => $(($trait:ident, $method:ident)),*) => {
+        $(
+            impl ops::$trait<CalculatorFloat> for $scalar {
+                type Output = CalculatorFloat;
+                fn $method(self, other: CalculatorFloat) -> CalculatorFloat {
+                    ops::$trait::$method(CalculatorFloat::from(self), other)
+                }
+            }
+
+            impl ops::$trait<&CalculatorFloat> for $scalar {
+                type Output = CalculatorFloat;
+                fn $method(self, other: &CalculatorFloat) -> CalculatorFloat {
+                    ops::$trait::$method(CalculatorFloat::from(self), other)
+                }
+            }
+        )*
+    };
+}
+
+scalar_first_operations!(f64 => (Add, add), (Sub, sub), (Mul, mul), (Div, div));
+
 #[cfg(test)]
 mod tests {
-    use super::CalculatorFloat;
+    use super::{
+        max_symbolic_length, non_finite_from_str, CalculatorFloat, RoundingPolicy,
+        SymbolicAccumulator, DEFAULT_MAX_SYMBOLIC_LENGTH,
+    };
+    use crate::{math, Calculator, CalculatorError};
     #[cfg(feature = "json_schema")]
     use schemars::schema_for;
-    use serde_test::{assert_tokens, Configure, Token};
+    use serde_test::{assert_ser_tokens, assert_tokens, Configure, Token};
+    use std::collections::HashMap;
+    use std::fmt;
     use std::{convert::TryFrom, str::FromStr};
 
     // Test the serialization/deserialization of CalculatorFloat from string
@@ -1286,6 +3498,47 @@ mod tests {
         );
     }
 
+    // Test the serialization/deserialization of non-finite CalculatorFloat values
+    #[test]
+    fn ser_de_non_finite() {
+        let x = CalculatorFloat::from(f64::INFINITY);
+        assert_tokens(&x.readable(), &[Token::Str("inf")]);
+        let x = CalculatorFloat::from(f64::NEG_INFINITY);
+        assert_tokens(&x.readable(), &[Token::Str("-inf")]);
+        let x = CalculatorFloat::from(f64::NEG_INFINITY);
+        assert_tokens(
+            &x.compact(),
+            &[
+                Token::NewtypeVariant {
+                    name: "CalculatorFloat",
+                    variant: "Float",
+                },
+                Token::F64(f64::NEG_INFINITY),
+            ],
+        );
+        assert_ser_tokens(
+            &CalculatorFloat::from(f64::NAN).readable(),
+            &[Token::Str("NaN")],
+        );
+    }
+
+    // Test the spellings of non-finite floats
+    #[test]
+    fn non_finite_spellings() {
+        for spelling in ["inf", "+inf", "INF", "Infinity", "+infinity", "iNfInItY"] {
+            assert_eq!(non_finite_from_str(spelling), Some(f64::INFINITY));
+        }
+        for spelling in ["-inf", "-Inf", "-Infinity", "-INFINITY"] {
+            assert_eq!(non_finite_from_str(spelling), Some(f64::NEG_INFINITY));
+        }
+        for spelling in ["NaN", "nan", "NAN", "-nan", "+NaN"] {
+            assert!(non_finite_from_str(spelling).unwrap().is_nan());
+        }
+        for spelling in ["", "-", "info", "infinit", "nana", " inf", "1e400", "theta"] {
+            assert_eq!(non_finite_from_str(spelling), None);
+        }
+    }
+
     // Test the serialization/deserialization of CalculatorFloat from float
     #[test]
     fn ser_de_float_compact() {
@@ -1318,6 +3571,97 @@ mod tests {
         );
     }
 
+    // Test that invalid expressions are caught at the construction site in debug builds
+    #[cfg(any(debug_assertions, feature = "strict-construction"))]
+    #[test]
+    #[should_panic(expected = "Invalid expression \"a=2\" used to construct CalculatorFloat::Str")]
+    fn from_invalid_expression() {
+        let _ = CalculatorFloat::from("a=2");
+    }
+
+    // Test that invalid expressions are not checked in default release builds
+    #[cfg(not(any(debug_assertions, feature = "strict-construction")))]
+    #[test]
+    fn from_invalid_expression() {
+        let x = CalculatorFloat::from("a=2");
+        assert_eq!(x, CalculatorFloat::Str("a=2".to_string()));
+    }
+
+    // Test that deserializing an invalid expression panics with the expression in debug builds
+    #[cfg(all(debug_assertions, not(feature = "strict-construction")))]
+    #[test]
+    #[should_panic(expected = "Invalid expression \"a=2\" used to construct CalculatorFloat::Str")]
+    fn de_invalid_expression() {
+        let _: Result<CalculatorFloat, _> = serde_json::from_str("\"a=2\"");
+    }
+
+    // Test that deserializing an invalid expression returns an error with the expression
+    #[cfg(feature = "strict-construction")]
+    #[test]
+    fn de_invalid_expression() {
+        let error = serde_json::from_str::<CalculatorFloat>("\"a=2\"").unwrap_err();
+        assert!(error
+            .to_string()
+            .starts_with("Invalid expression \"a=2\" used to construct CalculatorFloat::Str"));
+
+        serde_test::assert_de_tokens_error::<serde_test::Compact<CalculatorFloat>>(
+            &[
+                Token::NewtypeVariant {
+                    name: "CalculatorFloat",
+                    variant: "Str",
+                },
+                Token::String("a=2"),
+            ],
+            "Invalid expression \"a=2\" used to construct CalculatorFloat::Str: Invalid expression at position 0: CalculatorFloat::Str is not a valid expression that can be parsed: Variable assignment to a",
+        );
+    }
+
+    // Test that deserializing an invalid expression is unchanged in default release builds
+    #[cfg(not(any(debug_assertions, feature = "strict-construction")))]
+    #[test]
+    fn de_invalid_expression() {
+        let x: CalculatorFloat = serde_json::from_str("\"a=2\"").unwrap();
+        assert_eq!(x, CalculatorFloat::Str("a=2".to_string()));
+    }
+
+    // Test the approx comparisons of float and symbolic values
+    #[cfg(feature = "approx")]
+    #[test]
+    fn test_approx() {
+        use approx::{
+            assert_abs_diff_eq, assert_abs_diff_ne, assert_relative_eq, assert_relative_ne,
+            assert_ulps_eq, AbsDiffEq, RelativeEq,
+        };
+        let x = CalculatorFloat::from(1.0);
+        assert_relative_eq!(x, CalculatorFloat::from(1.0 + 1e-9));
+        assert_relative_ne!(x, CalculatorFloat::from(1.0 + 1e-7));
+        assert_relative_eq!(x, CalculatorFloat::from(1.1), max_relative = 0.1);
+        assert_abs_diff_eq!(x, CalculatorFloat::from(1.0 + f64::EPSILON));
+        assert_abs_diff_ne!(x, CalculatorFloat::from(1.0 + 1e-9));
+        assert_abs_diff_eq!(x, CalculatorFloat::from(1.01), epsilon = 0.1);
+        assert_ulps_eq!(x, CalculatorFloat::from(1.0 + f64::EPSILON));
+        assert_eq!(CalculatorFloat::default_epsilon(), f64::EPSILON);
+        assert_eq!(CalculatorFloat::default_max_relative(), 1e-8);
+
+        // Symbolic values are only equal to identical symbolic values
+        let theta = CalculatorFloat::from("theta / 2");
+        assert_relative_eq!(theta, CalculatorFloat::from("theta / 2"));
+        assert_abs_diff_eq!(theta, CalculatorFloat::from("theta / 2"));
+        assert_ulps_eq!(theta, CalculatorFloat::from("theta / 2"));
+        assert_relative_ne!(theta, CalculatorFloat::from("theta/2"));
+        let one = CalculatorFloat::Str("1e0".to_string());
+        assert_relative_ne!(one, x, max_relative = 1.0);
+        assert_abs_diff_ne!(x, one, epsilon = 1.0);
+
+        // Failed assertions show both values
+        let message = std::panic::catch_unwind(|| assert_relative_eq!(x, theta.clone()))
+            .unwrap_err()
+            .downcast::<String>()
+            .unwrap();
+        assert!(message.contains("left  = Float(1.0)"), "{message}");
+        assert!(message.contains("right = Str(\"theta / 2\")"), "{message}");
+    }
+
     #[cfg(feature = "json_schema")]
     #[test]
     fn test_json_schema_support() {
@@ -1326,6 +3670,75 @@ mod tests {
         assert_eq!(serialized.as_str(), "{\"$schema\":\"http://json-schema.org/draft-07/schema#\",\"title\":\"CalculatorFloat\",\"oneOf\":[{\"type\":\"number\",\"format\":\"double\"},{\"type\":\"string\"}]}");
     }
 
+    // Test pi_fraction for exact fractions and division by zero
+    #[test]
+    fn pi_fraction() {
+        assert_eq!(
+            CalculatorFloat::pi_fraction(1, 2),
+            Ok(CalculatorFloat::FRAC_PI_2)
+        );
+        assert_eq!(
+            CalculatorFloat::pi_fraction(-2, 2),
+            Ok(-CalculatorFloat::PI)
+        );
+        assert_eq!(
+            CalculatorFloat::pi_fraction(0, 3),
+            Ok(CalculatorFloat::ZERO)
+        );
+        assert_eq!(
+            CalculatorFloat::pi_fraction(1, 0),
+            Err(CalculatorError::DivisionByZero)
+        );
+    }
+
+    // Test that composed strings embed numbers identically independent of their construction
+    #[test]
+    fn embedded_float_format() {
+        let symbol = CalculatorFloat::from("x");
+        for value in [0.5, 2.0, -3.0, 1e-20, 123456.789] {
+            let constructions = [
+                CalculatorFloat::from(value),
+                CalculatorFloat::from(&value),
+                CalculatorFloat::from(format!("{value}")),
+                CalculatorFloat::from(format!("{value:e}").as_str()),
+            ];
+            let formatted = format!("{}", CalculatorFloat::from(value));
+            for number in constructions {
+                let composed = [
+                    (number.clone() + &symbol, format!("({formatted} + x)")),
+                    (&symbol + number.clone(), format!("(x + {formatted})")),
+                    (number.clone() - &symbol, format!("({formatted} - x)")),
+                    (
+                        symbol.clone() - number.clone(),
+                        format!("(x - {formatted})"),
+                    ),
+                    (number.clone() * &symbol, format!("({formatted} * x)")),
+                    (&symbol * number.clone(), format!("(x * {formatted})")),
+                    (number.clone() / &symbol, format!("({formatted} / x)")),
+                    (
+                        symbol.clone() / number.clone(),
+                        format!("(x / {formatted})"),
+                    ),
+                    (number.powf(&symbol), format!("({formatted} ^ x)")),
+                    (symbol.powf(&number), format!("(x ^ {formatted})")),
+                    (number.atan2(&symbol), format!("atan2({formatted}, x)")),
+                    (symbol.atan2(&number), format!("atan2(x, {formatted})")),
+                ];
+                for (result, expected) in composed {
+                    assert_eq!(result, CalculatorFloat::Str(expected));
+                }
+            }
+        }
+        assert_eq!(
+            CalculatorFloat::from(0.5) + "x",
+            CalculatorFloat::from("0.5") + "x"
+        );
+        assert_eq!(
+            format!("{}", CalculatorFloat::from(0.5) + "x"),
+            "(5e-1 + x)"
+        );
+    }
+
     // Test the initialisation of CalculatorFloat from all possible input types
     #[test]
     fn from() {
@@ -1442,6 +3855,113 @@ mod tests {
         assert!(x2.is_float());
     }
 
+    // Test the exact predicates is_zero, is_one and is_symbolic
+    #[test]
+    fn predicates() {
+        assert!(CalculatorFloat::from(0.0).is_zero());
+        assert!(CalculatorFloat::from(-0.0).is_zero());
+        assert!(!CalculatorFloat::from(1e-300).is_zero());
+        assert!(!CalculatorFloat::from("0 * x").is_zero());
+        assert!(!CalculatorFloat::from("x").is_zero());
+        assert!(CalculatorFloat::from(1).is_one());
+        assert!(!CalculatorFloat::from(1.0 - f64::EPSILON / 2.0).is_one());
+        assert!(!CalculatorFloat::from("x").is_one());
+        assert!(CalculatorFloat::from("x").is_symbolic());
+        assert!(!CalculatorFloat::from(2).is_symbolic());
+    }
+
+    // Test that all forms of the operators shortcut zeros and ones the same way
+    #[test]
+    fn operator_shortcuts() {
+        let x = CalculatorFloat::from("x");
+        let expected: &[(f64, [&str; 8])] = &[
+            (0.0, ["x", "x", "(-x)", "x", "0e0", "0e0", "0e0", "raises"]),
+            (-0.0, ["x", "x", "(-x)", "x", "0e0", "0e0", "0e0", "raises"]),
+            (
+                1.0,
+                [
+                    "(1e0 + x)",
+                    "(x + 1e0)",
+                    "(1e0 - x)",
+                    "(x - 1e0)",
+                    "x",
+                    "x",
+                    "(1e0 / x)",
+                    "x",
+                ],
+            ),
+            (
+                2.0,
+                [
+                    "(2e0 + x)",
+                    "(x + 2e0)",
+                    "(2e0 - x)",
+                    "(x - 2e0)",
+                    "(2e0 * x)",
+                    "(x * 2e0)",
+                    "(2e0 / x)",
+                    "(x / 2e0)",
+                ],
+            ),
+            (
+                1e-20,
+                [
+                    "(1e-20 + x)",
+                    "(x + 1e-20)",
+                    "(1e-20 - x)",
+                    "(x - 1e-20)",
+                    "(1e-20 * x)",
+                    "(x * 1e-20)",
+                    "(1e-20 / x)",
+                    "(x / 1e-20)",
+                ],
+            ),
+        ];
+        for (value, strings) in expected {
+            let a = CalculatorFloat::from(*value);
+            let by_value = [
+                a.clone() + x.clone(),
+                x.clone() + a.clone(),
+                a.clone() - x.clone(),
+                x.clone() - a.clone(),
+                a.clone() * x.clone(),
+                x.clone() * a.clone(),
+                a.clone() / x.clone(),
+            ];
+            // Only addition and multiplication are implemented for references
+            let by_reference = [(0, &a + &x), (1, &x + &a), (4, &a * &x), (5, &x * &a)];
+            let mut assigned = [
+                a.clone(),
+                x.clone(),
+                a.clone(),
+                x.clone(),
+                a.clone(),
+                x.clone(),
+                a.clone(),
+            ];
+            assigned[0] += x.clone();
+            assigned[1] += a.clone();
+            assigned[2] -= x.clone();
+            assigned[3] -= a.clone();
+            assigned[4] *= x.clone();
+            assigned[5] *= a.clone();
+            assigned[6] /= x.clone();
+            for index in 0..7 {
+                assert_eq!(
+                    by_value[index].to_string(),
+                    strings[index],
+                    "{value} {index}"
+                );
+                assert_eq!(assigned[index], by_value[index], "{value} {index}");
+            }
+            for (index, result) in by_reference {
+                assert_eq!(result, by_value[index], "{value} {index}");
+            }
+            let divided = std::panic::catch_unwind(|| (x.clone() / a.clone()).to_string());
+            assert_eq!(divided.unwrap_or_else(|_| "raises".to_string()), strings[7]);
+        }
+    }
+
     // Test the reverse from functions: T::from(CalculatorFloat)
     #[test]
     fn from_reversed() {
@@ -1468,6 +3988,263 @@ mod tests {
         assert!((x - f64::try_from(x3).unwrap()).abs() < f64::EPSILON);
     }
 
+    // Test that accumulating 1000 terms with the operators and with Sum gives the numeric value
+    #[test]
+    fn accumulation_of_many_terms() {
+        let mut calculator = Calculator::new();
+        let mut expected = 0.0;
+        let mut terms: Vec<CalculatorFloat> = Vec::new();
+        for index in 0..1000 {
+            let value = (index as f64 * 0.37).sin();
+            calculator
+                .set_variable(&format!("x_{index}"), value)
+                .unwrap();
+            let term = CalculatorFloat::from(format!("x_{index}")) * (index % 7) as f64;
+            expected += value * (index % 7) as f64;
+            terms.push(term);
+        }
+        let summed: CalculatorFloat = std::iter::once(CalculatorFloat::from(0.5))
+            .chain(terms.iter().cloned())
+            .sum();
+        let value = calculator.parse_get_ref(&summed).unwrap();
+        assert!((value - expected - 0.5).abs() < 1e-10, "{value}");
+        // += nests one bracket per term like the formatted expressions did, deeply nested
+        // brackets exceed the stack of the recursive parser in debug builds
+        let mut accumulated = CalculatorFloat::from(0.5);
+        let mut formatted = CalculatorFloat::from(0.5).to_string();
+        for term in terms.iter() {
+            accumulated += term;
+            if term.is_symbolic() {
+                formatted = format!("({formatted} + {term})");
+            }
+        }
+        assert_eq!(accumulated, CalculatorFloat::from(formatted));
+        let parser = calculator.clone();
+        let value = std::thread::Builder::new()
+            .stack_size(256 * 1024 * 1024)
+            .spawn(move || parser.parse_get(accumulated).unwrap())
+            .unwrap()
+            .join()
+            .unwrap();
+        assert!((value - expected - 0.5).abs() < 1e-10, "{value}");
+        // The accumulator writes the same flat sum as Sum
+        let mut accumulator = SymbolicAccumulator::from(CalculatorFloat::from(0.5));
+        for term in terms.iter() {
+            accumulator += term;
+        }
+        assert_eq!(accumulator.finish(), summed);
+        // The other assigning operators extend the expression in place as well
+        let mut product = CalculatorFloat::from("x_1");
+        for term in terms.iter().skip(1).take(6) {
+            product -= term;
+            product *= "x_2";
+            product /= 2.0;
+        }
+        let mut numeric = calculator.get_variable("x_1").unwrap();
+        for term in terms.iter().skip(1).take(6) {
+            numeric = (numeric - calculator.parse_get_ref(term).unwrap())
+                * calculator.get_variable("x_2").unwrap()
+                / 2.0;
+        }
+        assert!((calculator.parse_get(product).unwrap() - numeric).abs() < 1e-12);
+    }
+
+    // Test that the operators give the same result for a value and its clone
+    #[test]
+    fn operators_ignore_clones() {
+        let values = [
+            CalculatorFloat::from("a"),
+            CalculatorFloat::from("a") + "b",
+            CalculatorFloat::from(2.0) - "b",
+            (CalculatorFloat::from("a") + "b") * "c",
+        ];
+        let others = [
+            CalculatorFloat::from("d"),
+            CalculatorFloat::from(2.0),
+            CalculatorFloat::from(1.0),
+        ];
+        for value in values.iter() {
+            for other in others.iter() {
+                let clone = value.clone();
+                assert_eq!(value.clone() + other, clone.clone() + other);
+                assert_eq!(value.clone() - other, clone.clone() - other);
+                assert_eq!(value.clone() * other, clone.clone() * other);
+                assert_eq!(value.clone() / other, clone.clone() / other);
+                assert_eq!(value + other, clone.clone() + other);
+                let mut assigned = value.clone();
+                assigned -= other;
+                assert_eq!(assigned, clone.clone() - other);
+                assert_eq!(&clone, value);
+            }
+        }
+        assert_eq!(
+            values[1].clone() + "c",
+            CalculatorFloat::from("((a + b) + c)")
+        );
+    }
+
+    // Test that an expression written into the buffer of a dropped sum is wrapped as a whole
+    #[test]
+    fn reused_buffer() {
+        let mut calculator = Calculator::new();
+        calculator.set_variable("xxxxxxxx", 6.0).unwrap();
+        calculator.set_variable("yyyyyyyy", 3.0).unwrap();
+        calculator.set_variable("z", 1.0).unwrap();
+        for _ in 0..100 {
+            let sum = CalculatorFloat::from("xxxxxxxx") + "yyyyyyyy";
+            let capacity = match &sum {
+                CalculatorFloat::Str(expression) => expression.capacity(),
+                CalculatorFloat::Float(_) => unreachable!(),
+            };
+            drop(sum);
+            let mut expression = String::with_capacity(capacity);
+            expression.push_str("(xxxxxxxx)/(yyyyyyyy)");
+            let difference = CalculatorFloat::Str(expression) - "z";
+            assert_eq!(
+                difference,
+                CalculatorFloat::from("((xxxxxxxx)/(yyyyyyyy) - z)")
+            );
+            assert_eq!(calculator.parse_get(difference).unwrap(), 1.0);
+        }
+    }
+
+    // Test that SymbolicAccumulator gives the values of the operators of CalculatorFloat
+    #[test]
+    fn accumulator_matches_operators() {
+        let mut calculator = Calculator::new();
+        calculator.set_variable("a", 0.7).unwrap();
+        calculator.set_variable("b", -1.3).unwrap();
+        calculator.set_variable("c", 2.9).unwrap();
+        let operands = [
+            CalculatorFloat::from(1.5),
+            CalculatorFloat::from("a"),
+            CalculatorFloat::from(1.0),
+            CalculatorFloat::from("b"),
+            CalculatorFloat::from(0.0),
+            CalculatorFloat::from("c"),
+            CalculatorFloat::from(-2.0),
+        ];
+        for start in [0.0, 2.0] {
+            for (offset, _) in operands.iter().enumerate() {
+                let mut accumulator = SymbolicAccumulator::from(CalculatorFloat::from(start));
+                let mut value = CalculatorFloat::from(start);
+                for (index, operand) in operands.iter().cycle().skip(offset).take(20).enumerate() {
+                    match index % 5 {
+                        0 | 3 => {
+                            accumulator += operand;
+                            value += operand;
+                        }
+                        1 => {
+                            accumulator -= operand;
+                            value -= operand;
+                        }
+                        2 => {
+                            accumulator *= operand;
+                            value *= operand;
+                        }
+                        _ => {
+                            if operand.is_symbolic() || operand.float().unwrap() != &0.0 {
+                                accumulator /= operand;
+                                value /= operand;
+                            }
+                        }
+                    }
+                }
+                let accumulated = accumulator.finish();
+                assert_eq!(accumulated.is_symbolic(), value.is_symbolic());
+                let expected = calculator.parse_get(value).unwrap();
+                let result = calculator.parse_get(accumulated.clone()).unwrap();
+                assert!((result - expected).abs() < 1e-10, "{accumulated}");
+            }
+        }
+        let mut accumulator = SymbolicAccumulator::default();
+        accumulator -= "a";
+        accumulator += "b";
+        accumulator /= 2.0;
+        accumulator *= 1.0;
+        accumulator -= 3.0;
+        assert_eq!(
+            accumulator.clone().finish(),
+            CalculatorFloat::from("((((-a) + b) / 2e0) - 3e0)")
+        );
+        accumulator *= 0.0;
+        assert_eq!(accumulator.finish(), CalculatorFloat::from(0.0));
+    }
+
+    // Test that accumulating 100k terms with SymbolicAccumulator takes time linear in the number of terms
+    #[test]
+    fn accumulation_is_linear() {
+        let terms: Vec<CalculatorFloat> = (0..100_000)
+            .map(|index| CalculatorFloat::from(format!("x{}", index % 100)))
+            .collect();
+        let accumulate = |number: usize| {
+            let mut sum = SymbolicAccumulator::default();
+            for (index, term) in terms[..number].iter().enumerate() {
+                if index % 10 == 9 {
+                    sum *= term;
+                } else {
+                    sum += term;
+                }
+            }
+            sum.finish()
+        };
+        let time = |number: usize| {
+            (0..3)
+                .map(|_| {
+                    let start = std::time::Instant::now();
+                    std::hint::black_box(accumulate(number));
+                    start.elapsed()
+                })
+                .min()
+                .unwrap()
+        };
+        let quarter = time(25_000);
+        let full = time(100_000);
+        // Linear growth gives a factor of 4, quadratic growth a factor of 16
+        assert!(full < quarter * 8, "{full:?} {quarter:?}");
+        let product = accumulate(100_000);
+        assert!(product.to_string().ends_with(" + x96 + x97 + x98) * x99)"));
+    }
+
+    // Test that SymbolicAccumulator panics on division by zero
+    #[test]
+    #[should_panic(expected = "Division by zero")]
+    fn accumulator_division_by_zero() {
+        let mut accumulator = SymbolicAccumulator::from(CalculatorFloat::from("a"));
+        accumulator /= 0.0;
+    }
+
+    // Test that Sum keeps the summands in order in one bracket and skips zero summands
+    #[test]
+    fn sum_is_flat() {
+        let values = [
+            CalculatorFloat::from(1.0),
+            CalculatorFloat::from(2.0),
+            CalculatorFloat::from("a"),
+            CalculatorFloat::from(0.0),
+            CalculatorFloat::from("b"),
+            CalculatorFloat::from(3.0),
+        ];
+        assert_eq!(
+            values.iter().cloned().sum::<CalculatorFloat>(),
+            CalculatorFloat::from("(3e0 + a + b + 3e0)")
+        );
+        assert_eq!(
+            values[2..3].iter().cloned().sum::<CalculatorFloat>(),
+            CalculatorFloat::from("a")
+        );
+        assert_eq!(
+            values[..2].iter().cloned().sum::<CalculatorFloat>(),
+            CalculatorFloat::from(3.0)
+        );
+        assert_eq!(
+            Vec::<CalculatorFloat>::new()
+                .into_iter()
+                .sum::<CalculatorFloat>(),
+            CalculatorFloat::from(0.0)
+        );
+    }
+
     // Test the add functionality of CalculatorFloat with all possible input types
     #[test]
     fn add() {
@@ -1643,6 +4420,87 @@ mod tests {
         x2 /= 0.0;
     }
 
+    // Test the remainder functionality of CalculatorFloat with all possible input types
+    #[test]
+    fn rem() {
+        // Float remainders follow % for f64, the sign is the sign of the dividend
+        for (x, y) in [
+            (7.0, 3.0),
+            (-7.0, 3.0),
+            (7.0, -3.0),
+            (-7.0, -3.0),
+            (5.5, 2.0),
+        ] {
+            assert_eq!(CalculatorFloat::from(x) % y, CalculatorFloat::Float(x % y));
+            assert_eq!(
+                CalculatorFloat::from(x).rem(y),
+                CalculatorFloat::Float(x % y)
+            );
+        }
+        assert_eq!(
+            CalculatorFloat::from(0.0) % "x",
+            CalculatorFloat::Float(0.0)
+        );
+        assert_eq!(
+            CalculatorFloat::from(7) % "x",
+            CalculatorFloat::from("(7e0 % x)")
+        );
+        assert_eq!(
+            CalculatorFloat::from("phi") % 2.0,
+            CalculatorFloat::from("(phi % 2e0)")
+        );
+        assert_eq!(
+            CalculatorFloat::from("phi").rem(CalculatorFloat::from("(2 * pi)")),
+            CalculatorFloat::from("(phi % (2 * pi))")
+        );
+        let mut x = CalculatorFloat::from("(a + b)");
+        x %= 3;
+        assert_eq!(x, CalculatorFloat::from("((a + b) % 3e0)"));
+        let mut y = CalculatorFloat::from(-7.0);
+        y %= 3;
+        assert_eq!(y, CalculatorFloat::Float(-1.0));
+
+        // The symbolic remainder evaluates to the float remainder
+        let mut calculator = Calculator::new();
+        for (a, b) in [(7.0, 3.0), (-7.0, 3.0), (7.0, -3.0), (-7.5, 2.0)] {
+            calculator.set_variable("a", a).unwrap();
+            calculator.set_variable("b", b).unwrap();
+            let symbolic = CalculatorFloat::from("a") % CalculatorFloat::from("b");
+            assert_eq!(calculator.parse_get(symbolic), Ok(a % b));
+            let symbolic = CalculatorFloat::from("a") % b;
+            assert_eq!(calculator.parse_get(symbolic), Ok(a % b));
+        }
+
+        assert_eq!(
+            CalculatorFloat::from("x").checked_rem(0.0),
+            Err(CalculatorError::DivisionByZero)
+        );
+        assert_eq!(
+            CalculatorFloat::from(-7).checked_rem(3),
+            Ok(CalculatorFloat::Float(-1.0))
+        );
+        assert_eq!(
+            CalculatorFloat::from("x % 2").derivative("x"),
+            Ok(CalculatorFloat::Float(1.0))
+        );
+        assert_eq!(
+            CalculatorFloat::from("x % 2").simplify(),
+            CalculatorFloat::from("(x % 2e0)")
+        );
+        assert_ne!(
+            CalculatorFloat::from("a % b").canonical_hash(),
+            CalculatorFloat::from("b % a").canonical_hash()
+        );
+    }
+
+    // Test the remainder of CalculatorFloat by zero (should panic)
+    #[test]
+    #[should_panic]
+    fn fail_rem_by_zero() {
+        let x = CalculatorFloat::from("x");
+        let _y = x % 0.0;
+    }
+
     // Test the multiply functionality of CalculatorFloat with all possible input types
     #[test]
     fn mult() {
@@ -1731,6 +4589,14 @@ mod tests {
         let expression = "?";
         let result = CalculatorFloat::from_str(expression);
         assert!(result.is_err());
+        // Unbalanced and mismatched brackets are rejected at construction
+        for expression in ["(a + 2", "sin(a))", "max[a, 2)", "a]"] {
+            let result = CalculatorFloat::from_str(expression);
+            assert!(
+                matches!(result, Err(CalculatorError::InvalidExpression { .. })),
+                "{expression}"
+            );
+        }
         let expression = "a+2";
         let result = CalculatorFloat::from_str(expression);
         assert!(result.is_ok());
@@ -1860,26 +4726,344 @@ mod tests {
         assert_eq!(x3s.sin(), CalculatorFloat::Str(String::from("sin(-3t)")));
     }
 
-    // Test the arctangent functionality of CalculatorFloat with all possible input types
+    // Test that the unary functions emit the function names of the parser and agree with it
+    #[test]
+    fn unary_functions_match_parser() {
+        type UnaryFunction = fn(&CalculatorFloat) -> CalculatorFloat;
+        let functions: &[(&str, UnaryFunction)] = &[
+            ("sqrt", CalculatorFloat::sqrt),
+            ("exp", CalculatorFloat::exp),
+            ("exp2", CalculatorFloat::exp2),
+            ("log", CalculatorFloat::ln),
+            ("log2", CalculatorFloat::log2),
+            ("log10", CalculatorFloat::log10),
+            ("sin", CalculatorFloat::sin),
+            ("cos", CalculatorFloat::cos),
+            ("tan", CalculatorFloat::tan),
+            ("asin", CalculatorFloat::asin),
+            ("acos", CalculatorFloat::acos),
+            ("atan", CalculatorFloat::atan),
+            ("sinh", CalculatorFloat::sinh),
+            ("cosh", CalculatorFloat::cosh),
+            ("tanh", CalculatorFloat::tanh),
+            ("floor", CalculatorFloat::floor),
+            ("ceil", CalculatorFloat::ceil),
+            ("round", CalculatorFloat::round),
+        ];
+        let mut calculator = Calculator::new();
+        for (name, function) in functions {
+            let symbolic = function(&CalculatorFloat::from("x"));
+            assert_eq!(symbolic, CalculatorFloat::Str(format!("{name}(x)")));
+            for value in [0.3, 0.5, 0.75, 0.999] {
+                calculator.set_variable("x", value).unwrap();
+                let expected = calculator.parse_str(&format!("{name}({value})")).unwrap();
+                assert_eq!(function(&CalculatorFloat::from(value)), expected.into());
+                assert_eq!(calculator.parse_get(symbolic.clone()).unwrap(), expected);
+            }
+        }
+    }
+
+    // Test the logarithm to an arbitrary base with all possible input types
+    #[test]
+    fn log() {
+        let x = CalculatorFloat::from(8.0);
+        assert_eq!(
+            x.log(2.0),
+            CalculatorFloat::Float(math::ln(8.0) / math::ln(2.0))
+        );
+        let xs = CalculatorFloat::from("x");
+        assert_eq!(xs.log(10.0), CalculatorFloat::from("(log(x) / log(1e1))"));
+        assert_eq!(x.log("b"), CalculatorFloat::from("(log(8e0) / log(b))"));
+        let mut calculator = Calculator::new();
+        calculator.set_variable("x", 8.0).unwrap();
+        calculator.set_variable("b", 3.0).unwrap();
+        assert_eq!(
+            calculator.parse_get(xs.log("b")).unwrap(),
+            math::ln(8.0) / math::ln(3.0)
+        );
+    }
+
+    // Test the arctangent functionality of CalculatorFloat with all possible input types
+    #[test]
+    fn atan2() {
+        // Test atan2
+        let x3 = CalculatorFloat::from(-3);
+        let x2: f64 = -3.0;
+        assert_eq!(CalculatorFloat::Float(x2.atan2(2.0)), x3.atan2(2.0));
+        let x3s = CalculatorFloat::from("-3t");
+        assert_eq!(
+            x3s.atan2("test"),
+            CalculatorFloat::Str(String::from("atan2(-3t, test)"))
+        );
+        assert_eq!(
+            x3s.atan2(1.0),
+            CalculatorFloat::Str(String::from("atan2(-3t, 1e0)"))
+        );
+        assert_eq!(
+            x3.atan2("test"),
+            CalculatorFloat::Str(String::from("atan2(-3e0, test)"))
+        );
+    }
+
+    // Test the factorial of CalculatorFloat against the factorial function of the Calculator
+    #[test]
+    fn factorial() {
+        assert_eq!(
+            CalculatorFloat::from(20).factorial(),
+            CalculatorFloat::Float(2432902008176640000.0)
+        );
+        assert!(CalculatorFloat::from(-2)
+            .factorial()
+            .float()
+            .unwrap()
+            .is_nan());
+        let x = CalculatorFloat::from("x");
+        assert_eq!(x.factorial(), CalculatorFloat::from("factorial(x)"));
+        let mut calculator = Calculator::new();
+        for value in [0.0, 5.0, 0.5, 35.0, -0.5] {
+            calculator.set_variable("x", value).unwrap();
+            assert_eq!(
+                calculator.parse_get(x.factorial()).unwrap(),
+                *CalculatorFloat::from(value).factorial().float().unwrap()
+            );
+        }
+    }
+
+    // Test the construction of rational literals with reduction and sign normalization
+    #[test]
+    fn rational_construction() {
+        let rational = |n, d| CalculatorFloat::rational(n, d).unwrap();
+        assert_eq!(rational(2, 6), CalculatorFloat::from("(1 / 3)"));
+        assert_eq!(rational(2, -6), CalculatorFloat::from("(-1 / 3)"));
+        assert_eq!(rational(-2, -6), CalculatorFloat::from("(1 / 3)"));
+        assert_eq!(rational(0, -5), CalculatorFloat::from("(0 / 1)"));
+        assert_eq!(rational(4, 2), CalculatorFloat::from("(2 / 1)"));
+        assert_eq!(
+            rational(i64::MIN, i64::MIN),
+            CalculatorFloat::from("(1 / 1)")
+        );
+        assert_eq!(
+            CalculatorFloat::rational(1, 0),
+            Err(CalculatorError::DivisionByZero)
+        );
+        assert_eq!(
+            CalculatorFloat::rational(i64::MIN, -1),
+            Err(CalculatorError::RationalOverflow {
+                numerator: 9223372036854775808,
+                denominator: 1
+            })
+        );
+        assert!(rational(-7, 3).is_rational_literal());
+        for not_literal in [
+            CalculatorFloat::from(0.5),
+            CalculatorFloat::from("(2 / 6)"),
+            CalculatorFloat::from("(1 / -3)"),
+            CalculatorFloat::from("(01 / 3)"),
+            CalculatorFloat::from("(+1 / 3)"),
+            CalculatorFloat::from("(1/3)"),
+            CalculatorFloat::from("(a / 3)"),
+        ] {
+            assert!(!not_literal.is_rational_literal(), "{not_literal}");
+        }
+        let calculator = Calculator::new();
+        assert_eq!(calculator.parse_get(rational(-1, 3)).unwrap(), -1.0 / 3.0);
+    }
+
+    // Test that chained exact arithmetic of rational literals stays exact
+    #[test]
+    fn rational_exact_arithmetic() {
+        let rational = |n, d| CalculatorFloat::rational(n, d).unwrap();
+        let third = rational(1, 3);
+        assert_eq!(third.clone().add_exact(rational(2, 3)), rational(1, 1));
+        assert_eq!(third.clone().sub_exact(rational(1, 2)), rational(-1, 6));
+        assert_eq!(third.clone().mul_exact(rational(3, 4)), rational(1, 4));
+        assert_eq!(third.clone().div_exact(rational(-2, 3)), rational(-1, 2));
+        assert_eq!(third.clone().add_exact(1.0), rational(4, 3));
+        assert_eq!(
+            CalculatorFloat::from(2).mul_exact(third.clone()),
+            rational(2, 3)
+        );
+        assert_eq!(CalculatorFloat::from(1.0).div_exact(3.0), third);
+        assert_eq!(
+            CalculatorFloat::from(6.0).div_exact(3.0),
+            CalculatorFloat::Float(2.0)
+        );
+        assert_eq!(
+            CalculatorFloat::from(1.0).add_exact(2.0),
+            CalculatorFloat::Float(3.0)
+        );
+        // Chained operations stay exact where floats accumulate rounding errors
+        let mut exact = CalculatorFloat::from(0);
+        let mut float = CalculatorFloat::from(0);
+        for _ in 0..10 {
+            exact = exact.add_exact(rational(1, 10));
+            float += 0.1;
+        }
+        assert_eq!(exact, rational(1, 1));
+        assert_ne!(float, CalculatorFloat::from(1.0));
+        let product = (1..=20).fold(CalculatorFloat::from(1), |product, n| {
+            product.mul_exact(rational(n, n + 1))
+        });
+        assert_eq!(product, rational(1, 21));
+        // Operands that are not exact fall back to the usual operations
+        assert_eq!(third.clone().add_exact(0.5), third.clone() + 0.5);
+        assert_eq!(
+            third.clone().mul_exact("x"),
+            third.clone() * CalculatorFloat::from("x")
+        );
+        let large = rational(i64::MAX, 1);
+        assert_eq!(
+            large.clone().add_exact(large.clone()),
+            large.clone() + large.clone()
+        );
+        let calculator = Calculator::new();
+        assert_eq!(
+            calculator
+                .parse_get(third.sub_exact(rational(1, 2)))
+                .unwrap(),
+            -1.0 / 6.0
+        );
+    }
+
+    // Test construction, exact arithmetic and serialization of decimal literals
+    #[test]
+    fn decimal_literals() {
+        let decimal = |literal| CalculatorFloat::decimal(literal).unwrap();
+        assert_eq!(decimal("0.3"), CalculatorFloat::Str(String::from("0.3")));
+        assert_eq!(
+            decimal("-0.30"),
+            CalculatorFloat::Str(String::from("-0.30"))
+        );
+        assert!(decimal("12.5").is_decimal_literal());
+        assert!(!CalculatorFloat::from(0.3).is_decimal_literal());
+        assert!(!CalculatorFloat::from("x").is_decimal_literal());
+        for literal in [
+            "1e-3",
+            ".5",
+            "5.",
+            "3",
+            "+0.5",
+            "0.5.1",
+            "x.5",
+            "inf",
+            "0.1234567890123456789",
+        ] {
+            assert_eq!(
+                CalculatorFloat::decimal(literal),
+                Err(CalculatorError::InvalidDecimalLiteral {
+                    literal: literal.to_string()
+                })
+            );
+        }
+        // Arithmetic with integers and decimals composes decimal literals
+        assert_eq!(decimal("0.3").mul_exact(3), decimal("0.9"));
+        assert_eq!(decimal("0.3").add_exact(decimal("0.6")), decimal("0.9"));
+        assert_eq!(decimal("0.5").mul_exact(2), decimal("1.0"));
+        assert_eq!(decimal("0.1").sub_exact(1), decimal("-0.9"));
+        assert_eq!(decimal("0.75").div_exact(decimal("-0.5")), decimal("-1.5"));
+        assert_eq!(
+            decimal("0.003").mul_exact(decimal("0.02")),
+            decimal("0.00006")
+        );
+        let mut steps = CalculatorFloat::from(0);
+        for _ in 0..10 {
+            steps = steps.add_exact(decimal("0.3"));
+        }
+        assert_eq!(steps, decimal("3.0"));
+        // Results without finite decimal representation are rational literals
+        assert_eq!(
+            decimal("1.0").div_exact(3),
+            CalculatorFloat::rational(1, 3).unwrap()
+        );
+        assert_eq!(
+            decimal("0.5").add_exact(CalculatorFloat::rational(1, 3).unwrap()),
+            CalculatorFloat::rational(5, 6).unwrap()
+        );
+        // Non-exact operands and the usual operators keep the symbolic behaviour
+        assert_eq!(decimal("0.3").mul_exact(0.5), decimal("0.3") * 0.5);
+        assert_eq!(decimal("0.3") * 3, CalculatorFloat::from("(0.3 * 3e0)"));
+        let calculator = Calculator::new();
+        assert_eq!(
+            calculator.parse_get(decimal("0.3").mul_exact(3)).unwrap(),
+            0.9
+        );
+        assert_eq!(calculator.parse_get(decimal("-0.30")).unwrap(), -0.3);
+        // Serialization keeps the literal in the untagged and tagged form
+        let value = decimal("0.30");
+        assert_tokens(&value.clone().readable(), &[Token::Str("0.30")]);
+        assert_tokens(
+            &value.clone().compact(),
+            &[
+                Token::NewtypeVariant {
+                    name: "CalculatorFloat",
+                    variant: "Str",
+                },
+                Token::Str("0.30"),
+            ],
+        );
+        let json = serde_json::to_string(&value).unwrap();
+        assert_eq!(json, "\"0.30\"");
+        assert_eq!(
+            serde_json::from_str::<CalculatorFloat>(&json).unwrap(),
+            value
+        );
+    }
+
+    // Test rounding of CalculatorFloat against the rounding functions of the Calculator
     #[test]
-    fn atan2() {
-        // Test atan2
-        let x3 = CalculatorFloat::from(-3);
-        let x2: f64 = -3.0;
-        assert_eq!(CalculatorFloat::Float(x2.atan2(2.0)), x3.atan2(2.0));
-        let x3s = CalculatorFloat::from("-3t");
+    fn rounding() {
         assert_eq!(
-            x3s.atan2("test"),
-            CalculatorFloat::Str(String::from("atan2(-3t, test)"))
+            CalculatorFloat::from(0.00005).round_to_digits(4),
+            CalculatorFloat::Float(0.0001)
         );
         assert_eq!(
-            x3s.atan2(1.0),
-            CalculatorFloat::Str(String::from("atan2(-3t, 1e0)"))
+            CalculatorFloat::from(-0.00005).round_to_multiple(1e-4),
+            Ok(CalculatorFloat::Float(-0.0001))
         );
+        let x = CalculatorFloat::from("x");
         assert_eq!(
-            x3.atan2("test"),
-            CalculatorFloat::Str(String::from("atan2(-3e0, test)"))
+            x.round_to_digits(-2),
+            CalculatorFloat::from("round_digits(x, -2)")
+        );
+        assert_eq!(
+            x.round_to_multiple(0.25),
+            Ok(CalculatorFloat::from("round_step(x, 2.5e-1)"))
         );
+        for step in [0.0, -1e-4, f64::NAN] {
+            assert!(matches!(
+                x.round_to_multiple(step),
+                Err(CalculatorError::InvalidFunctionArgument {
+                    fct: "round_step",
+                    ..
+                })
+            ));
+            assert!(CalculatorFloat::from(1.0).round_to_multiple(step).is_err());
+        }
+        let mut calculator = Calculator::new();
+        for value in [0.00005, -0.00005, 0.123456, -2.675, 1250.0, 0.0] {
+            calculator.set_variable("x", value).unwrap();
+            for digits in [-2, 0, 2, 4] {
+                assert_eq!(
+                    calculator.parse_get(x.round_to_digits(digits)).unwrap(),
+                    *CalculatorFloat::from(value)
+                        .round_to_digits(digits)
+                        .float()
+                        .unwrap()
+                );
+            }
+            for step in [1e-4, 0.25, 3.0] {
+                assert_eq!(
+                    calculator
+                        .parse_get(x.round_to_multiple(step).unwrap())
+                        .unwrap(),
+                    *CalculatorFloat::from(value)
+                        .round_to_multiple(step)
+                        .unwrap()
+                        .float()
+                        .unwrap()
+                );
+            }
+        }
     }
 
     // Test the sign functionality of CalculatorFloat with all possible input types
@@ -1891,6 +5075,138 @@ mod tests {
         assert_eq!(x3.signum(), CalculatorFloat::Str(String::from("sign(-3t)")));
     }
 
+    // Test the shapes of the simplified abs and signum expressions
+    #[test]
+    fn abs_signum_simplification() {
+        let x = CalculatorFloat::from("x");
+        assert_eq!(x.abs().abs(), CalculatorFloat::from("abs(x)"));
+        assert_eq!((-x.clone()).abs(), CalculatorFloat::from("abs(x)"));
+        assert_eq!((-x.abs()).abs(), CalculatorFloat::from("abs(x)"));
+        assert_eq!(x.signum().signum(), CalculatorFloat::from("sign(x)"));
+        assert_eq!(x.abs().signum(), CalculatorFloat::from("sign(abs(x))"));
+        assert_eq!(
+            (-CalculatorFloat::from("sin(x)")).abs(),
+            CalculatorFloat::from("abs(sin(x))")
+        );
+        assert_eq!(
+            (-(x.clone() + "y")).abs(),
+            CalculatorFloat::from("abs((x + y))")
+        );
+        // Conservative matcher declines when the outer call does not span the expression
+        for declined in [
+            "abs(x) * abs(y)",
+            "(-a + b)",
+            "(-x) * (-y)",
+            "-x",
+            "absx(y)",
+        ] {
+            assert_eq!(
+                CalculatorFloat::from(declined).abs(),
+                CalculatorFloat::Str(format!("abs({declined})"))
+            );
+        }
+        assert_eq!(
+            CalculatorFloat::from("sign(x) * sign(y)").signum(),
+            CalculatorFloat::from("sign(sign(x) * sign(y))")
+        );
+        assert_eq!(
+            CalculatorFloat::from(-2.0).abs().abs(),
+            CalculatorFloat::from(2.0)
+        );
+        assert_eq!(
+            CalculatorFloat::from(0.0).signum().signum(),
+            CalculatorFloat::from(1.0)
+        );
+    }
+
+    // Test that simplified abs and signum expressions evaluate like the unsimplified compositions
+    #[test]
+    fn abs_signum_simplification_numeric() {
+        let mut calculator = Calculator::new();
+        let operands = ["x", "(x + y)", "sin(x)", "(x * y)", "(-x)", "abs(x - y)"];
+        type Composition = (&'static str, fn(&CalculatorFloat) -> CalculatorFloat);
+        let compositions: [Composition; 5] = [
+            ("abs(abs({}))", |x| x.abs().abs()),
+            ("abs(-({}))", |x| (-x.clone()).abs()),
+            ("abs(-(abs({})))", |x| (-x.abs()).abs()),
+            ("sign(sign({}))", |x| x.signum().signum()),
+            ("sign(abs(-({})))", |x| (-x.clone()).abs().signum()),
+        ];
+        // Deterministic pseudo random values in [-5, 5)
+        let mut state: u64 = 0x2545_f491_4f6c_dd1d;
+        let mut next = || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            (state >> 11) as f64 / (1u64 << 53) as f64 * 10.0 - 5.0
+        };
+        for _ in 0..50 {
+            calculator.set_variable("x", next()).unwrap();
+            calculator.set_variable("y", next()).unwrap();
+            for operand in operands {
+                for (reference, composition) in compositions.iter() {
+                    let simplified = composition(&CalculatorFloat::from(operand));
+                    let expected = calculator
+                        .parse_str(&reference.replace("{}", operand))
+                        .unwrap();
+                    assert_eq!(calculator.parse_get(simplified).unwrap(), expected);
+                }
+            }
+        }
+    }
+
+    // Test heaviside, step and delta against the Calculator evaluating the emitted strings
+    #[test]
+    fn heaviside_delta() {
+        let calculator = Calculator::new();
+        let values = [
+            -2.0,
+            -f64::EPSILON,
+            -f64::EPSILON / 2.0,
+            -0.0,
+            0.0,
+            f64::EPSILON / 2.0,
+            f64::EPSILON,
+            1e-3,
+            3.5,
+        ];
+        for value in values {
+            let x = CalculatorFloat::from(value);
+            let symbolic = CalculatorFloat::Str(format!("({value:e})"));
+            for (float_result, symbolic_result) in [
+                (x.heaviside(), symbolic.heaviside()),
+                (x.step(), symbolic.step()),
+                (x.delta(), symbolic.delta()),
+            ] {
+                assert_eq!(
+                    float_result,
+                    CalculatorFloat::Float(calculator.parse_get(symbolic_result).unwrap())
+                );
+            }
+        }
+        assert_eq!(
+            CalculatorFloat::from(-0.0).heaviside(),
+            CalculatorFloat::Float(0.5)
+        );
+        assert_eq!(
+            CalculatorFloat::from(f64::EPSILON).heaviside(),
+            CalculatorFloat::Float(1.0)
+        );
+        assert_eq!(
+            CalculatorFloat::from(f64::EPSILON / 2.0).delta(),
+            CalculatorFloat::Float(1.0)
+        );
+        assert_eq!(
+            CalculatorFloat::from(-f64::EPSILON).delta(),
+            CalculatorFloat::Float(0.0)
+        );
+
+        let x = CalculatorFloat::from("a");
+        assert_eq!(x.heaviside(), CalculatorFloat::from("theta(a)"));
+        assert_eq!(x.step(), CalculatorFloat::from("theta(a)"));
+        assert_eq!(x.delta(), CalculatorFloat::from("delta(a)"));
+    }
+
     // Test the power functionality of CalculatorFloat with all possible input types
     #[test]
     fn powf() {
@@ -1922,6 +5238,63 @@ mod tests {
         assert_eq!(x1s_recip, CalculatorFloat::Str(String::from("(1 / 2x)")));
     }
 
+    // Test the conversion of CalculatorFloat to a SymPy compatible string
+    #[test]
+    fn to_sympy_string() {
+        let x = CalculatorFloat::from(0.1);
+        assert_eq!(x.to_sympy_string().unwrap(), "0.1");
+        let x = CalculatorFloat::from("theta(theta) ^ 2e0");
+        assert_eq!(
+            x.to_sympy_string().unwrap(),
+            "Heaviside(Symbol('theta'), 1/2)**2.0"
+        );
+        let x = CalculatorFloat::from("sign(a)*atan2(b, 1.5) - abs(-c)");
+        assert_eq!(
+            x.to_sympy_string().unwrap(),
+            "sign(Symbol('a')) * atan2(Symbol('b'), 1.5) - Abs( - Symbol('c'))"
+        );
+        let x = CalculatorFloat::from("hypot(a, (b+1))");
+        assert_eq!(
+            x.to_sympy_string().unwrap(),
+            "sqrt((Symbol('a'))**2 + ((Symbol('b') + 1.0))**2)"
+        );
+        let x = CalculatorFloat::from("log10(x) / exp2(y)");
+        assert_eq!(
+            x.to_sympy_string().unwrap(),
+            "log(Symbol('x'), 10) / 2**(Symbol('y'))"
+        );
+        let x = CalculatorFloat::from("binom(n, 2) * factorial(n) / gamma(x) + parity(n)");
+        assert_eq!(
+            x.to_sympy_string().unwrap(),
+            "binomial(Symbol('n'), 2.0) * factorial(Symbol('n')) / gamma(Symbol('x')) + (-1)**(Symbol('n'))"
+        );
+        let x = CalculatorFloat::from("max[a, 2, b] + sum[a, b] / mean[a, (b + 1)]");
+        assert_eq!(
+            x.to_sympy_string().unwrap(),
+            "Max(Symbol('a'), 2.0, Symbol('b')) + Add(Symbol('a'), Symbol('b')) / Mul(Rational(1, 2), Add(Symbol('a'), (Symbol('b') + 1.0)))"
+        );
+        let x = CalculatorFloat::from("max(a, 2, b) - sum(a, b, 1)");
+        assert_eq!(
+            x.to_sympy_string().unwrap(),
+            "Max(Symbol('a'), 2.0, Symbol('b')) - Add(Symbol('a'), Symbol('b'), 1.0)"
+        );
+        assert!(CalculatorFloat::from("min[]").to_sympy_string().is_err());
+        assert!(CalculatorFloat::Str("max[a, b".to_string())
+            .to_sympy_string()
+            .is_err());
+        assert!(CalculatorFloat::from("round(x)").to_sympy_string().is_err());
+        assert!(CalculatorFloat::from("unknown(x)")
+            .to_sympy_string()
+            .is_err());
+        assert!(CalculatorFloat::Str("sin(x".to_string())
+            .to_sympy_string()
+            .is_err());
+        assert!(CalculatorFloat::Str("x)".to_string())
+            .to_sympy_string()
+            .is_err());
+        assert!(CalculatorFloat::from("x!").to_sympy_string().is_err());
+    }
+
     // Test the Display functionality of CalculatorFloat with all possible input types
     #[test]
     fn display() {
@@ -1942,6 +5315,72 @@ mod tests {
         assert!(!x2.isclose("-3.000000001t"));
     }
 
+    // Test that numeric strings are compared numerically to floats in both directions
+    #[test]
+    fn isclose_numeric_string() {
+        let one = CalculatorFloat::Float(1.0);
+        let one_str = CalculatorFloat::Str("1e0".to_string());
+        assert!(one.isclose(one_str.clone()));
+        assert!(one_str.isclose(one.clone()));
+        // Formatted strings differ but the values are close
+        let almost_one = CalculatorFloat::Str("1.000000001".to_string());
+        assert!(one.isclose(almost_one.clone()));
+        assert!(almost_one.isclose(1.0));
+        assert!(!CalculatorFloat::Str("1.1".to_string()).isclose(1.0));
+        assert!(!one.isclose(CalculatorFloat::Str("1.1".to_string())));
+        // Symbolic strings are never close to floats
+        assert!(!CalculatorFloat::from("x").isclose(1.0));
+        assert!(!one.isclose("x"));
+        // Strings are compared as strings, also when both are numeric
+        assert!(one_str.isclose(CalculatorFloat::Str("1e0".to_string())));
+        assert!(!one_str.isclose(CalculatorFloat::Str("1.0".to_string())));
+        assert!(!CalculatorFloat::from("a + b").isclose("a + bc"));
+        assert!(!CalculatorFloat::from("a + b").isclose("a + c"));
+        assert!(CalculatorFloat::from("a + b").isclose("a + b"));
+    }
+
+    // Test the operators with f64 as left operand
+    #[test]
+    fn scalar_first_operations() {
+        let x = CalculatorFloat::from("x");
+        let three = CalculatorFloat::from(3.0);
+        // Add
+        assert_eq!(2.0 + three.clone(), CalculatorFloat::Float(5.0));
+        assert_eq!(2.0 + &three, CalculatorFloat::Float(5.0));
+        assert_eq!(2.0 + x.clone(), CalculatorFloat::from("(2e0 + x)"));
+        assert_eq!(2.0 + &x, CalculatorFloat::from("(2e0 + x)"));
+        assert_eq!(0.0 + x.clone(), x);
+        // Sub
+        assert_eq!(2.0 - three.clone(), CalculatorFloat::Float(-1.0));
+        assert_eq!(2.0 - &three, CalculatorFloat::Float(-1.0));
+        assert_eq!(2.0 - x.clone(), CalculatorFloat::from("(2e0 - x)"));
+        assert_eq!(2.0 - &x, CalculatorFloat::from("(2e0 - x)"));
+        assert_eq!(0.0 - x.clone(), CalculatorFloat::from("(-x)"));
+        // Mul
+        assert_eq!(2.0 * three.clone(), CalculatorFloat::Float(6.0));
+        assert_eq!(2.0 * &three, CalculatorFloat::Float(6.0));
+        assert_eq!(2.0 * x.clone(), CalculatorFloat::from("(2e0 * x)"));
+        assert_eq!(2.0 * &x, CalculatorFloat::from("(2e0 * x)"));
+        assert_eq!(0.0 * x.clone(), CalculatorFloat::Float(0.0));
+        assert_eq!(1.0 * &x, x);
+        // Div
+        assert_eq!(6.0 / three.clone(), CalculatorFloat::Float(2.0));
+        assert_eq!(6.0 / &three, CalculatorFloat::Float(2.0));
+        assert_eq!(2.0 / x.clone(), CalculatorFloat::from("(2e0 / x)"));
+        assert_eq!(2.0 / &x, CalculatorFloat::from("(2e0 / x)"));
+        assert_eq!(0.0 / x.clone(), CalculatorFloat::Float(0.0));
+        // The same results as with CalculatorFloat as left operand
+        assert_eq!(2.0 * &x, CalculatorFloat::from(2.0) * &x);
+        assert_eq!(2.0 / &x, CalculatorFloat::from(2.0) / &x);
+    }
+
+    // Test that f64 divided by a CalculatorFloat zero panics like CalculatorFloat / 0
+    #[test]
+    #[should_panic]
+    fn scalar_first_div_by_zero() {
+        let _ = 1.0 / CalculatorFloat::from(0.0);
+    }
+
     // Test the adding with reference input functionality of CalculatorFloat
     // with all possible input types
     #[test]
@@ -2008,5 +5447,613 @@ mod tests {
         assert!(x1s == x2s);
         assert!(x2s == x1s);
     }
+
+    // Test that a doubling chain hits the default limit of the symbolic length at a fixed step
+    #[test]
+    fn checked_mul_symbolic_length() {
+        let mut x = CalculatorFloat::from("x");
+        let mut steps = 0;
+        let error = loop {
+            match x.clone().checked_mul(x.clone()) {
+                Ok(product) => x = product,
+                Err(error) => break error,
+            }
+            steps += 1;
+        };
+        // Each step doubles the length and adds "(", " * " and ")"
+        assert_eq!(steps, 17);
+        assert_eq!(
+            error,
+            CalculatorError::SymbolicExpressionTooLarge {
+                length: 2 * (6 * (1 << 17) - 5),
+                limit: DEFAULT_MAX_SYMBOLIC_LENGTH
+            }
+        );
+        assert!(x.clone().checked_add(x.clone()).is_err());
+        assert!(x.clone().checked_sub(x.clone()).is_err());
+        assert!(x.clone().checked_div(x.clone()).is_err());
+        assert!(x.checked_powf(x.clone()).is_err());
+        assert!(x.checked_atan2(x.clone()).is_err());
+        assert!(x.clone().checked_add(1.0).is_ok());
+    }
+
+    // Test that the infallible operators panic when the symbolic length limit is hit
+    #[test]
+    #[should_panic(expected = "exceed the maximum length 1048576")]
+    fn mul_symbolic_length_panics() {
+        let mut x = CalculatorFloat::from("x");
+        for _ in 0..20 {
+            x *= x.clone();
+        }
+    }
+
+    // Test that checked operations agree with the operators and report division by zero
+    #[test]
+    fn checked_operations() {
+        let x = CalculatorFloat::from("x");
+        assert_eq!(x.clone().checked_add(2).unwrap(), x.clone() + 2);
+        assert_eq!(x.clone().checked_sub("y").unwrap(), x.clone() - "y");
+        assert_eq!(x.clone().checked_mul(2).unwrap(), x.clone() * 2);
+        assert_eq!(x.clone().checked_div("y").unwrap(), x.clone() / "y");
+        assert_eq!(x.checked_powf("y").unwrap(), x.powf("y"));
+        assert_eq!(x.checked_atan2(2).unwrap(), x.atan2(2));
+        assert_eq!(x.checked_div(0.0), Err(CalculatorError::DivisionByZero));
+    }
+
+    // Test that building large but ordinary expressions never hits the symbolic length limit
+    #[test]
+    fn ordinary_expressions_symbolic_length() {
+        let mut sum = CalculatorFloat::from(0.0);
+        for i in 0..2000 {
+            let term = CalculatorFloat::from(format!("theta_{i}")).sin() * 0.5;
+            sum = sum.checked_add(term.powf(2)).unwrap();
+        }
+        assert!(max_symbolic_length() == DEFAULT_MAX_SYMBOLIC_LENGTH);
+        assert!(sum.to_string().len() < DEFAULT_MAX_SYMBOLIC_LENGTH);
+    }
+
+    // Test the gradient of expressions in three variables against central finite differences
+    #[test]
+    fn gradient_finite_differences() {
+        let variables = ["x", "y", "z"];
+        let point = [0.4, -1.3, 2.1];
+        let step = 1e-6;
+        for expression in [
+            "x * y * z",
+            "sin(x * y) + cos(z) / (1 + x^2)",
+            "exp(-(x^2 + y^2)) * log(z) - atan2(y, x)",
+            "sqrt(x^2 + y^2 + z^2) * hypot(x, z) ^ y",
+            "max(x, y) * z - tanh(x - 2 * z) / y",
+        ] {
+            let cf = CalculatorFloat::from(expression);
+            let gradient = cf.gradient(&variables).unwrap();
+            let mut calculator = Calculator::new();
+            let set_point = |calculator: &mut Calculator, shift: (usize, f64)| {
+                for (index, (name, value)) in variables.iter().zip(point).enumerate() {
+                    let shifted = if index == shift.0 {
+                        value + shift.1
+                    } else {
+                        value
+                    };
+                    calculator.set_variable(name, shifted).unwrap();
+                }
+            };
+            for (index, partial) in gradient.iter().enumerate() {
+                set_point(&mut calculator, (index, 0.0));
+                let value = calculator.parse_get(partial.clone()).unwrap();
+                assert_eq!(partial, &cf.derivative(variables[index]).unwrap());
+                set_point(&mut calculator, (index, step));
+                let upper = calculator.parse_get(cf.clone()).unwrap();
+                set_point(&mut calculator, (index, -step));
+                let lower = calculator.parse_get(cf.clone()).unwrap();
+                let finite_difference = (upper - lower) / (2.0 * step);
+                assert!(
+                    (value - finite_difference).abs() < 1e-6 * (1.0 + value.abs()),
+                    "{expression} d{}: {value} != {finite_difference}",
+                    variables[index]
+                );
+            }
+        }
+    }
+
+    // Test that variables the expression does not depend on have a zero derivative
+    #[test]
+    fn derivative_unused_variable() {
+        let cf = CalculatorFloat::from("sin(x) * y");
+        assert_eq!(cf.derivative("z"), Ok(CalculatorFloat::ZERO));
+        assert_eq!(
+            cf.gradient(&["z", "x"]).unwrap(),
+            vec![CalculatorFloat::ZERO, CalculatorFloat::from("(cos(x) * y)")]
+        );
+        assert_eq!(
+            CalculatorFloat::from(2.0).gradient(&["x", "y"]),
+            Ok(vec![CalculatorFloat::ZERO; 2])
+        );
+        assert_eq!(cf.gradient(&[]), Ok(vec![]));
+    }
+
+    // Test that comparisons are piecewise constant and kept symbolic by simplify
+    #[test]
+    fn comparison() {
+        let cf = CalculatorFloat::from("(x > 0) * x^2");
+        assert_eq!(
+            cf.derivative("x"),
+            Ok(CalculatorFloat::from("((x > 0e0) * (2e0 * x))"))
+        );
+        assert_eq!(
+            CalculatorFloat::from("(1 + 1 == 2) * x").simplify(),
+            CalculatorFloat::from("x")
+        );
+        assert_eq!(
+            CalculatorFloat::from("x != 2 * 3").simplify(),
+            CalculatorFloat::from("(x != 6e0)")
+        );
+        assert_eq!(
+            cf.to_sympy_string(),
+            Err(CalculatorError::NotImplementedError { fct: "Comparison" })
+        );
+        assert_eq!(
+            CalculatorFloat::from("a < b").canonical_hash(),
+            CalculatorFloat::from("(a) < (b)").canonical_hash()
+        );
+        assert_ne!(
+            CalculatorFloat::from("a < b").canonical_hash(),
+            CalculatorFloat::from("b < a").canonical_hash()
+        );
+    }
+
+    // Test that invalid expressions return an error
+    #[test]
+    fn derivative_invalid() {
+        assert_eq!(
+            CalculatorFloat::Str("sin(x".to_string()).derivative("x"),
+            Err(CalculatorError::ParsingError {
+                msg: "Expected braket close.",
+                position: Some(5),
+            })
+        );
+        assert_eq!(
+            CalculatorFloat::from("lgamma(x)").gradient(&["y", "x"]),
+            Err(CalculatorError::NotImplementedError { fct: "lgamma" })
+        );
+    }
+
+    // Test that simplify folds constant subexpressions and keeps symbols
+    #[test]
+    fn simplify() {
+        for (expression, expected) in [
+            ("((2e0 * 3e0) * x)", "(6e0 * x)"),
+            ("(x + 0e0) * 1e0", "x"),
+            ("0 + 1 * sin(y / 1)", "sin(y)"),
+            ("x ^ (3 - 2) + 2 * 3", "(x + 6e0)"),
+            ("-x * (1 + 1)", "((-x) * 2e0)"),
+            ("max(1, x, 2) + cos(0)", "(max(max(1e0, x), 2e0) + 1e0)"),
+        ] {
+            let simplified = CalculatorFloat::Str(expression.to_string()).simplify();
+            assert_eq!(simplified, CalculatorFloat::Str(expected.to_string()));
+            assert_eq!(simplified.simplify(), simplified);
+        }
+        // Numeric expressions collapse to a float
+        assert_eq!(
+            CalculatorFloat::Str("(2e0 * 3e0) + 2 ^ 2".to_string()).simplify(),
+            CalculatorFloat::Float(10.0)
+        );
+        assert_eq!(
+            CalculatorFloat::from(1.5).simplify(),
+            CalculatorFloat::Float(1.5)
+        );
+        // Expressions without expression tree are returned unchanged
+        for expression in ["a = 1; a + 1", "sin(x", "1 / 0"] {
+            let cf = CalculatorFloat::Str(expression.to_string());
+            assert_eq!(cf.simplify(), cf);
+        }
+    }
+
+    // Test the conversion to integers with every rounding policy
+    #[test]
+    fn try_into_integer() {
+        assert_eq!(
+            CalculatorFloat::from(2.0).try_into_usize(RoundingPolicy::Exact),
+            Ok(2)
+        );
+        // One ulp below an integer is still integral
+        assert_eq!(
+            CalculatorFloat::from(f64::from_bits(3.0_f64.to_bits() - 1))
+                .try_into_u32(RoundingPolicy::Exact),
+            Ok(3)
+        );
+        let half = CalculatorFloat::from(2.5);
+        assert_eq!(
+            half.try_into_i64(RoundingPolicy::Exact),
+            Err(CalculatorError::IntegerConversionError {
+                value: 2.5,
+                target: "i64"
+            })
+        );
+        assert_eq!(half.try_into_i64(RoundingPolicy::Round), Ok(3));
+        assert_eq!(half.try_into_i64(RoundingPolicy::Floor), Ok(2));
+        assert_eq!(half.try_into_i64(RoundingPolicy::Ceil), Ok(3));
+        assert_eq!(
+            CalculatorFloat::from(-2.5).try_into_i64(RoundingPolicy::Round),
+            Ok(-3)
+        );
+        assert_eq!(
+            CalculatorFloat::from(-1.0).try_into_usize(RoundingPolicy::Exact),
+            Err(CalculatorError::IntegerConversionError {
+                value: -1.0,
+                target: "usize"
+            })
+        );
+        assert_eq!(
+            CalculatorFloat::from(1e20).try_into_u32(RoundingPolicy::Exact),
+            Err(CalculatorError::IntegerConversionError {
+                value: 1e20,
+                target: "u32"
+            })
+        );
+        assert_eq!(
+            CalculatorFloat::from(-9223372036854775808.0).try_into_i64(RoundingPolicy::Exact),
+            Ok(i64::MIN)
+        );
+        assert_eq!(
+            CalculatorFloat::from(9223372036854775808.0)
+                .try_into_i64(RoundingPolicy::Exact)
+                .map_err(|error| error.kind()),
+            Err("IntegerConversionError")
+        );
+        assert_eq!(
+            CalculatorFloat::from(f64::NAN)
+                .try_into_integer::<i16>(RoundingPolicy::Round)
+                .map_err(|error| error.kind()),
+            Err("IntegerConversionError")
+        );
+        assert_eq!(
+            CalculatorFloat::from("x").try_into_usize(RoundingPolicy::Round),
+            Err(CalculatorError::FloatSymbolicNotConvertable {
+                val: "x".to_string()
+            })
+        );
+        assert_eq!(RoundingPolicy::default(), RoundingPolicy::Exact);
+    }
+
+    // Test listing the variables of expressions without function names and duplicates
+    #[test]
+    fn variables() {
+        assert_eq!(
+            CalculatorFloat::from("b * sin(theta) + b / theta - a").variables(),
+            Ok(vec!["b".to_string(), "theta".to_string(), "a".to_string()])
+        );
+        assert_eq!(
+            CalculatorFloat::from("atan2(x, 2) + max[y, x]").variables(),
+            Ok(vec!["x".to_string(), "y".to_string()])
+        );
+        assert_eq!(
+            CalculatorFloat::Str("2 * 3 + sqrt(4)".to_string()).variables(),
+            Ok(vec![])
+        );
+        assert_eq!(CalculatorFloat::from(1.5).variables(), Ok(vec![]));
+        assert_eq!(
+            CalculatorFloat::Str("a $ b".to_string()).variables(),
+            Err(CalculatorError::InvalidExpression {
+                position: 2,
+                error: Box::new(CalculatorError::NotParsableUnrecognized)
+            })
+        );
+    }
+
+    // Test binding placeholders to numbers, variables and expressions
+    #[test]
+    fn bind_placeholders() {
+        let template = CalculatorFloat::from("{angle} * sin({phase}) + {angle}");
+        assert_eq!(template.placeholders(), vec!["angle", "phase"]);
+        let values: HashMap<String, CalculatorFloat> = [
+            ("angle".to_string(), CalculatorFloat::from("x + 1")),
+            ("phase".to_string(), CalculatorFloat::from(-0.5)),
+        ]
+        .into_iter()
+        .collect();
+        let bound = template.bind_placeholders(&values).unwrap();
+        assert_eq!(
+            bound,
+            CalculatorFloat::from("(x + 1) * sin((-5e-1)) + (x + 1)")
+        );
+        assert!(bound.placeholders().is_empty());
+        let mut calculator = Calculator::new();
+        calculator.set_variable("x", 2.0).unwrap();
+        assert_eq!(
+            calculator.parse_get(bound).unwrap(),
+            3.0 * (-0.5_f64).sin() + 3.0
+        );
+        // Binding a single placeholder to a number gives a float
+        let values: HashMap<String, CalculatorFloat> =
+            [("n".to_string(), CalculatorFloat::from(2))]
+                .into_iter()
+                .collect();
+        assert_eq!(
+            CalculatorFloat::from("{n}").bind_placeholders(&values),
+            Ok(CalculatorFloat::from(2.0))
+        );
+        // Placeholders in the values can be bound in a later step
+        let values: HashMap<String, CalculatorFloat> = [
+            ("angle".to_string(), CalculatorFloat::from("{scale} * y")),
+            ("phase".to_string(), CalculatorFloat::from("z")),
+        ]
+        .into_iter()
+        .collect();
+        let bound = template.bind_placeholders(&values).unwrap();
+        assert_eq!(
+            bound,
+            CalculatorFloat::from("({scale} * y) * sin(z) + ({scale} * y)")
+        );
+        assert_eq!(bound.placeholders(), vec!["scale"]);
+        let values: HashMap<String, CalculatorFloat> =
+            [("b".to_string(), CalculatorFloat::from(1))]
+                .into_iter()
+                .collect();
+        assert_eq!(
+            CalculatorFloat::from("{a} + {b} * {c} + {a}").bind_placeholders(&values),
+            Err(CalculatorError::UnboundPlaceholder {
+                names: vec!["a".to_string(), "c".to_string()]
+            })
+        );
+        assert_eq!(
+            CalculatorFloat::from(1.5).bind_placeholders(&HashMap::new()),
+            Ok(CalculatorFloat::from(1.5))
+        );
+        assert_eq!(
+            template.to_sympy_string(),
+            Err(CalculatorError::UnboundPlaceholder {
+                names: vec!["angle".to_string(), "phase".to_string()]
+            })
+        );
+        assert_eq!(
+            template.derivative("x"),
+            Err(CalculatorError::UnboundPlaceholder {
+                names: vec!["angle".to_string(), "phase".to_string()]
+            })
+        );
+        // Serialization keeps the braces
+        assert_tokens(
+            &template.readable(),
+            &[Token::String("{angle} * sin({phase}) + {angle}")],
+        );
+    }
+
+    /// Writer storing at most `capacity` bytes and failing when more are written.
+    struct LimitedWriter {
+        written: String,
+        capacity: usize,
+    }
+
+    impl fmt::Write for LimitedWriter {
+        fn write_str(&mut self, s: &str) -> fmt::Result {
+            if self.written.len() + s.len() > self.capacity {
+                return Err(fmt::Error);
+            }
+            self.written.push_str(s);
+            Ok(())
+        }
+    }
+
+    // Test streaming long expressions into writers with and without truncation
+    #[test]
+    fn write_display() {
+        let expression = "x + ".repeat(1 << 18) + "x";
+        let value = CalculatorFloat::from(expression.as_str());
+        // The truncated value is written without writing or storing the full expression
+        let mut writer = LimitedWriter {
+            written: String::new(),
+            capacity: 64,
+        };
+        value.write_display(&mut writer, Some(9)).unwrap();
+        assert_eq!(writer.written, "x + x + x... (1048577 bytes)");
+        let mut writer = LimitedWriter {
+            written: String::new(),
+            capacity: 64,
+        };
+        assert_eq!(value.write_display(&mut writer, None), Err(fmt::Error));
+        assert!(writer.written.is_empty());
+        // Without truncation the complete Display is written
+        let mut written = String::new();
+        value.write_display(&mut written, None).unwrap();
+        assert_eq!(written, expression);
+        let mut written = String::new();
+        value
+            .write_display(&mut written, Some(expression.len()))
+            .unwrap();
+        assert_eq!(written, expression);
+        let mut written = String::new();
+        CalculatorFloat::from(0.5)
+            .write_display(&mut written, Some(2))
+            .unwrap();
+        assert_eq!(written, "5e... (4 bytes)");
+        // Multi byte characters are not split
+        let mut written = String::new();
+        CalculatorFloat::from("ππ")
+            .write_display(&mut written, Some(3))
+            .unwrap();
+        assert_eq!(written, "π... (4 bytes)");
+    }
+
+    // Test replacing a variable by numbers and expressions
+    #[test]
+    fn substitute() {
+        let x = CalculatorFloat::from("2*theta + phi");
+        let half = CalculatorFloat::from(0.5);
+        assert_eq!(
+            x.substitute("theta", &half).unwrap(),
+            CalculatorFloat::from("2*(5e-1) + phi")
+        );
+        assert_eq!(
+            x.substitute("theta", &CalculatorFloat::from("alpha/2"))
+                .unwrap(),
+            CalculatorFloat::from("2*(alpha/2) + phi")
+        );
+        assert_eq!(
+            x.substitute("phi", &CalculatorFloat::from("beta")).unwrap(),
+            CalculatorFloat::from("2*theta + beta")
+        );
+        // Variables inside function arguments are replaced, function names are kept
+        let x = CalculatorFloat::from("sin(a) + atan2(a, sin_a) * a_1");
+        assert_eq!(
+            x.substitute("a", &CalculatorFloat::from("b + 1")).unwrap(),
+            CalculatorFloat::from("sin((b + 1)) + atan2((b + 1), sin_a) * a_1")
+        );
+        assert_eq!(
+            CalculatorFloat::from("sin(sin)")
+                .substitute("sin", &half)
+                .unwrap(),
+            CalculatorFloat::Float(0.5_f64.sin())
+        );
+        // Chained substitutions collapse to a float when no variables are left
+        let x = CalculatorFloat::from("2*theta + phi");
+        let chained = x
+            .substitute("theta", &CalculatorFloat::from("alpha/2"))
+            .and_then(|x| x.substitute("alpha", &CalculatorFloat::from(3.0)))
+            .unwrap();
+        assert_eq!(chained, CalculatorFloat::from("2*(3e0/2) + phi"));
+        let chained = chained.substitute("phi", &CalculatorFloat::from(-1.0));
+        assert_eq!(chained, Ok(CalculatorFloat::Float(2.0)));
+        // Unknown names, floats and placeholders are kept
+        assert_eq!(
+            CalculatorFloat::from("{q} * a")
+                .substitute("a", &half)
+                .unwrap(),
+            CalculatorFloat::from("{q} * (5e-1)")
+        );
+        assert_eq!(x.substitute("gamma", &half).unwrap(), x);
+        assert_eq!(
+            CalculatorFloat::from(1.5).substitute("a", &half),
+            Ok(CalculatorFloat::from(1.5))
+        );
+        // Numeric results are evaluated
+        assert_eq!(
+            CalculatorFloat::from("1 / a").substitute("a", &CalculatorFloat::ZERO),
+            Err(CalculatorError::DivisionByZero)
+        );
+    }
+
+    // Test renaming variables and evaluating the renamed expression
+    #[test]
+    fn anonymized() {
+        let expression = CalculatorFloat::from(
+            "device_amp * sin(omega_q1 * t) + device_amp^2 / 1.5e-3 - max[t, 0.5]",
+        );
+        let (anonymized, mapping) = expression.anonymized();
+        assert_eq!(
+            anonymized,
+            CalculatorFloat::from("v0 * sin(v1 * v2) + v0^2 / 1.5e-3 - max[v2, 0.5]")
+        );
+        let expected: HashMap<String, String> =
+            [("device_amp", "v0"), ("omega_q1", "v1"), ("t", "v2")]
+                .into_iter()
+                .map(|(name, replacement)| (name.to_string(), replacement.to_string()))
+                .collect();
+        assert_eq!(mapping, expected);
+        // Setting the variables according to the mapping gives the same value
+        let mut original = Calculator::new();
+        let mut renamed = Calculator::new();
+        for (name, value) in [("device_amp", 0.3), ("omega_q1", 2.1), ("t", 0.7)] {
+            original.set_variable(name, value).unwrap();
+            renamed.set_variable(&mapping[name], value).unwrap();
+        }
+        assert_eq!(
+            original.parse_get(expression).unwrap().to_bits(),
+            renamed.parse_get(anonymized).unwrap().to_bits()
+        );
+        // Names that look like replacements are renamed as well
+        let (anonymized, mapping) = CalculatorFloat::from("v1 + x * v1").anonymized();
+        assert_eq!(anonymized, CalculatorFloat::from("v0 + v1 * v0"));
+        assert_eq!(mapping["v1"], "v0");
+        assert_eq!(mapping["x"], "v1");
+        let (anonymized, mapping) = CalculatorFloat::from(0.5).anonymized();
+        assert_eq!(anonymized, CalculatorFloat::from(0.5));
+        assert!(mapping.is_empty());
+    }
+
+    // Test listing and replacing the number literals of expressions
+    #[test]
+    fn embedded_literals() {
+        assert!(CalculatorFloat::from(0.5).embedded_literals().is_empty());
+        assert!(CalculatorFloat::from("x * y")
+            .embedded_literals()
+            .is_empty());
+        // Scientific notation and literals inside of function arguments
+        let expression = CalculatorFloat::from("2.5e-3 * atan2(0.4999999, 1E+2) + x");
+        assert_eq!(
+            expression.embedded_literals(),
+            vec![(2.5e-3, 0..6), (0.4999999, 15..24), (1e2, 26..30)]
+        );
+        // The unary minus is not part of the literal
+        let negative = CalculatorFloat::from("-2 * x - (-3.0)");
+        let literals = negative.embedded_literals();
+        assert_eq!(literals, vec![(2.0, 1..2), (3.0, 11..14)]);
+        for (value, span) in literals {
+            assert_eq!(f64::from_str(&negative.to_string()[span]).unwrap(), value);
+        }
+
+        assert_eq!(
+            expression.replace_literal(15..24, 0.5),
+            Ok(CalculatorFloat::from("2.5e-3 * atan2(5e-1, 1E+2) + x"))
+        );
+        assert_eq!(
+            expression.replace_literal(26..30, -1.0),
+            Ok(CalculatorFloat::from(
+                "2.5e-3 * atan2(0.4999999, (-1e0)) + x"
+            ))
+        );
+        let replaced = negative.replace_literal(1..2, -4.0).unwrap();
+        assert_eq!(replaced, CalculatorFloat::from("-(-4e0) * x - (-3.0)"));
+        let mut calculator = Calculator::new();
+        calculator.set_variable("x", 2.0).unwrap();
+        assert_eq!(calculator.parse_get(replaced).unwrap(), 11.0);
+        // Spans must match a literal exactly
+        for span in [0..2, 16..24, 30..32] {
+            assert_eq!(
+                expression.replace_literal(span, 1.0),
+                Err(CalculatorError::ParsingError {
+                    msg: "No number literal at the given span, see embedded_literals",
+                    position: None,
+                })
+            );
+        }
+        assert!(CalculatorFloat::from(0.5)
+            .replace_literal(0..3, 1.0)
+            .is_err());
+        assert_eq!(
+            expression.replace_literal(0..6, f64::INFINITY),
+            Err(CalculatorError::ParsingError {
+                msg: "Only finite values can replace a number literal",
+                position: None,
+            })
+        );
+    }
+
+    // Test PartialOrd for float and symbolic values
+    #[test]
+    fn partial_ord() {
+        use std::cmp::Ordering;
+        let one = CalculatorFloat::from(1.0);
+        let two = CalculatorFloat::from(2.0);
+        let x = CalculatorFloat::from("x");
+        assert!(one < two);
+        assert!(two >= one);
+        assert!(one <= one.clone());
+        assert_eq!(one.partial_cmp(&two), Some(Ordering::Less));
+        assert_eq!(one.partial_cmp(&x), None);
+        assert_eq!(x.partial_cmp(&two), None);
+        assert_eq!(x.partial_cmp(&CalculatorFloat::from("y")), None);
+        assert_eq!(x.partial_cmp(&x.clone()), Some(Ordering::Equal));
+        assert_eq!(
+            (one < x, one > x, one <= x, one >= x),
+            (false, false, false, false)
+        );
+        let nan = CalculatorFloat::from(f64::NAN);
+        assert_eq!(nan.partial_cmp(&one), None);
+        assert_eq!(
+            CalculatorFloat::from(-0.0).partial_cmp(&CalculatorFloat::from(0.0)),
+            Some(Ordering::Equal)
+        );
+    }
 }
 // End of tests
@@ -0,0 +1,383 @@
+// Copyright © 2020-2021 HQS Quantum Simulations GmbH. All Rights Reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except
+// in compliance with the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the
+// License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either
+// express or implied. See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! calculator_vector module
+//!
+//! Provides CalculatorVector struct for elementwise math on vectors of CalculatorFloat.
+
+use crate::calculator_float::format_float;
+use crate::CalculatorError;
+use crate::CalculatorFloat;
+use serde::{Deserialize, Serialize};
+use std::ops;
+
+/// Vector of CalculatorFloat values supporting elementwise arithmetic.
+///
+/// Serialized as a plain sequence of CalculatorFloat.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "json_schema", derive(schemars::JsonSchema))]
+#[serde(transparent)]
+pub struct CalculatorVector(pub Vec<CalculatorFloat>);
+
+impl CalculatorVector {
+    /// Return the number of elements.
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Return true when the vector has no elements.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Return an iterator over the elements.
+    pub fn iter(&self) -> std::slice::Iter<'_, CalculatorFloat> {
+        self.0.iter()
+    }
+
+    /// Return the dot product with another CalculatorVector.
+    ///
+    /// Numerical products are summed directly, symbolic products are combined in a
+    /// single flat sum instead of nested additions.
+    ///
+    /// # Arguments
+    ///
+    /// * `other` - CalculatorVector with the same length
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(CalculatorFloat)` - The sum of the elementwise products
+    /// * `Err(CalculatorError::VectorLengthMismatch)` - The vectors have different lengths
+    ///
+    pub fn dot(&self, other: &CalculatorVector) -> Result<CalculatorFloat, CalculatorError> {
+        self.check_length(other)?;
+        Ok(flat_sum(
+            self.iter()
+                .zip(other.iter())
+                .map(|(left, right)| left.clone() * right),
+        ))
+    }
+
+    /// Return the sum of the squares of the elements.
+    pub fn norm_sqr(&self) -> CalculatorFloat {
+        flat_sum(self.iter().map(|value| value.clone() * value))
+    }
+
+    /// Return an error when the vectors have different lengths.
+    fn check_length(&self, other: &CalculatorVector) -> Result<(), CalculatorError> {
+        if self.len() == other.len() {
+            Ok(())
+        } else {
+            Err(CalculatorError::VectorLengthMismatch {
+                left: self.len(),
+                right: other.len(),
+            })
+        }
+    }
+}
+
+/// Sum CalculatorFloat values into a single flat sum.
+///
+/// Float values are added numerically, symbolic values are joined in one bracket
+/// with the numerical part as last summand.
+fn flat_sum<I: Iterator<Item = CalculatorFloat>>(values: I) -> CalculatorFloat {
+    let mut number: f64 = 0.0;
+    let mut summands: Vec<String> = Vec::new();
+    for value in values {
+        match value {
+            CalculatorFloat::Float(x) => number += x,
+            CalculatorFloat::Str(x) => summands.push(x),
+        }
+    }
+    if summands.is_empty() {
+        return CalculatorFloat::Float(number);
+    }
+    if number != 0.0 {
+        summands.push(format_float(number));
+    }
+    if summands.len() == 1 {
+        CalculatorFloat::Str(summands.remove(0))
+    } else {
+        CalculatorFloat::Str(format!("({})", summands.join(" + ")))
+    }
+}
+
+impl From<Vec<CalculatorFloat>> for CalculatorVector {
+    fn from(values: Vec<CalculatorFloat>) -> Self {
+        CalculatorVector(values)
+    }
+}
+
+impl<T> FromIterator<T> for CalculatorVector
+where
+    T: Into<CalculatorFloat>,
+{
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        CalculatorVector(iter.into_iter().map(|value| value.into()).collect())
+    }
+}
+
+impl ops::Index<usize> for CalculatorVector {
+    type Output = CalculatorFloat;
+    fn index(&self, index: usize) -> &CalculatorFloat {
+        &self.0[index]
+    }
+}
+
+impl IntoIterator for CalculatorVector {
+    type Item = CalculatorFloat;
+    type IntoIter = std::vec::IntoIter<CalculatorFloat>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a CalculatorVector {
+    type Item = &'a CalculatorFloat;
+    type IntoIter = std::slice::Iter<'a, CalculatorFloat>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.iter()
+    }
+}
+
+/// Implement elementwise `+` (add) for CalculatorVector.
+///
+/// # Arguments
+///
+/// * `other` - CalculatorVector with the same length
+///
+/// # Returns
+///
+/// * `Ok(CalculatorVector)` - The elementwise sum
+/// * `Err(CalculatorError::VectorLengthMismatch)` - The vectors have different lengths
+///
+impl ops::Add<CalculatorVector> for CalculatorVector {
+    type Output = Result<CalculatorVector, CalculatorError>;
+    fn add(self, other: CalculatorVector) -> Self::Output {
+        self.check_length(&other)?;
+        Ok(self
+            .into_iter()
+            .zip(other)
+            .map(|(left, right)| left + right)
+            .collect())
+    }
+}
+
+/// Implement elementwise `-` (subtract) for CalculatorVector.
+///
+/// # Arguments
+///
+/// * `other` - CalculatorVector with the same length
+///
+/// # Returns
+///
+/// * `Ok(CalculatorVector)` - The elementwise difference
+/// * `Err(CalculatorError::VectorLengthMismatch)` - The vectors have different lengths
+///
+impl ops::Sub<CalculatorVector> for CalculatorVector {
+    type Output = Result<CalculatorVector, CalculatorError>;
+    fn sub(self, other: CalculatorVector) -> Self::Output {
+        self.check_length(&other)?;
+        Ok(self
+            .into_iter()
+            .zip(other)
+            .map(|(left, right)| left - right)
+            .collect())
+    }
+}
+
+/// Implement `*` (multiply) for CalculatorVector and a scalar of generic type `T`.
+///
+/// # Arguments
+///
+/// * `other` - Any type T for which CalculatorFloat::From<T> trait is implemented
+///
+impl<T> ops::Mul<T> for CalculatorVector
+where
+    T: Into<CalculatorFloat>,
+{
+    type Output = Self;
+    fn mul(self, other: T) -> Self {
+        let other_from: CalculatorFloat = other.into();
+        self.into_iter().map(|value| value * &other_from).collect()
+    }
+}
+
+/// Implement `/` (divide) for CalculatorVector and a scalar of generic type `T`.
+///
+/// # Arguments
+///
+/// * `other` - Any type T for which CalculatorFloat::From<T> trait is implemented
+///
+/// # Panics
+///
+/// Panics on division by zero.
+/// Division by zero is only detected when other is converted to CalculatorFloat::Float
+///
+impl<T> ops::Div<T> for CalculatorVector
+where
+    T: Into<CalculatorFloat>,
+{
+    type Output = Self;
+    fn div(self, other: T) -> Self {
+        let other_from: CalculatorFloat = other.into();
+        self.into_iter().map(|value| value / &other_from).collect()
+    }
+}
+
+/// Implements summing over an iterator of CalculatorVector
+///
+/// Returns an error when the vectors have different lengths.
+/// Summing an empty iterator returns an empty CalculatorVector.
+///
+/// # Arguments
+///
+/// * `iter` - Any iterator over CalculatorVector items
+///
+impl std::iter::Sum<CalculatorVector> for Result<CalculatorVector, CalculatorError> {
+    fn sum<I: Iterator<Item = CalculatorVector>>(mut iter: I) -> Self {
+        let first = match iter.next() {
+            Some(first) => first,
+            None => return Ok(CalculatorVector::default()),
+        };
+        iter.try_fold(first, |sum, value| sum + value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::CalculatorVector;
+    use crate::{Calculator, CalculatorError, CalculatorFloat};
+    use serde_test::{assert_tokens, Configure, Token};
+
+    fn vector(values: &[&str]) -> CalculatorVector {
+        values
+            .iter()
+            .map(|value| match value.parse::<f64>() {
+                Ok(x) => CalculatorFloat::Float(x),
+                Err(_) => CalculatorFloat::from(*value),
+            })
+            .collect()
+    }
+
+    // Test elementwise add and subtract
+    #[test]
+    fn add_sub() {
+        let left = vector(&["1", "a", "2"]);
+        let right = vector(&["2", "1", "b"]);
+        assert_eq!(
+            (left.clone() + right.clone()).unwrap(),
+            vector(&["3", "(a + 1e0)", "(2e0 + b)"])
+        );
+        assert_eq!(
+            (left - right).unwrap(),
+            vector(&["-1", "(a - 1e0)", "(2e0 - b)"])
+        );
+    }
+
+    // Test that vectors of different length can not be added, subtracted or multiplied
+    #[test]
+    fn length_mismatch() {
+        let left = vector(&["1", "a"]);
+        let right = vector(&["1"]);
+        let error = || CalculatorError::VectorLengthMismatch { left: 2, right: 1 };
+        assert_eq!(left.clone() + right.clone(), Err(error()));
+        assert_eq!(left.clone() - right.clone(), Err(error()));
+        assert_eq!(left.dot(&right), Err(error()));
+        let sum: Result<CalculatorVector, CalculatorError> = vec![left, right].into_iter().sum();
+        assert_eq!(sum, Err(error()));
+    }
+
+    // Test scalar multiplication and division
+    #[test]
+    fn mul_div() {
+        let values = vector(&["2", "a"]);
+        assert_eq!(values.clone() * 2, vector(&["4", "(a * 2e0)"]));
+        assert_eq!(values.clone() * "b", vector(&["(2e0 * b)", "(a * b)"]));
+        assert_eq!(values / 2.0, vector(&["1", "(a / 2e0)"]));
+    }
+
+    // Test dot products of numerical and symbolic vectors
+    #[test]
+    fn dot() {
+        let numbers = vector(&["1", "2", "3"]);
+        assert_eq!(numbers.dot(&numbers).unwrap(), CalculatorFloat::Float(14.0));
+        assert_eq!(numbers.norm_sqr(), CalculatorFloat::Float(14.0));
+
+        let symbols = vector(&["a", "2", "b"]);
+        let dot = numbers.dot(&symbols).unwrap();
+        assert_eq!(dot, CalculatorFloat::from("(a + (3e0 * b) + 4e0)"));
+        let mut calculator = Calculator::new();
+        calculator.set_variable("a", 0.5).unwrap();
+        calculator.set_variable("b", -1.0).unwrap();
+        assert_eq!(calculator.parse_get(dot).unwrap(), 1.5);
+
+        assert_eq!(
+            vector(&["a", "0"]).dot(&vector(&["b", "1"])).unwrap(),
+            CalculatorFloat::from("(a * b)")
+        );
+        assert_eq!(
+            symbols.norm_sqr(),
+            CalculatorFloat::from("((a * a) + (b * b) + 4e0)")
+        );
+        assert_eq!(
+            CalculatorVector::default().dot(&CalculatorVector::default()),
+            Ok(CalculatorFloat::Float(0.0))
+        );
+    }
+
+    // Test summing vectors, indexing and iterating
+    #[test]
+    fn sum_index_iter() {
+        let sum: Result<CalculatorVector, CalculatorError> = vec![
+            vector(&["1", "a"]),
+            vector(&["2", "1"]),
+            vector(&["3", "0"]),
+        ]
+        .into_iter()
+        .sum();
+        let sum = sum.unwrap();
+        assert_eq!(sum, vector(&["6", "(a + 1e0)"]));
+        assert_eq!(sum[1], CalculatorFloat::from("(a + 1e0)"));
+        assert_eq!(sum.len(), 2);
+        assert_eq!((&sum).into_iter().count(), 2);
+        assert_eq!(
+            sum.into_iter().collect::<Vec<CalculatorFloat>>(),
+            vec![
+                CalculatorFloat::Float(6.0),
+                CalculatorFloat::from("(a + 1e0)")
+            ]
+        );
+        let empty: Result<CalculatorVector, CalculatorError> =
+            Vec::<CalculatorVector>::new().into_iter().sum();
+        assert!(empty.unwrap().is_empty());
+    }
+
+    // Test serialization as a plain sequence
+    #[test]
+    fn serde_readable() {
+        let values = vector(&["1", "a"]);
+        assert_tokens(
+            &values.clone().readable(),
+            &[
+                Token::Seq { len: Some(2) },
+                Token::F64(1.0),
+                Token::String("a"),
+                Token::SeqEnd,
+            ],
+        );
+        let serialized = serde_json::to_string(&values).unwrap();
+        assert_eq!(serialized, "[1.0,\"a\"]");
+        let deserialized: CalculatorVector = serde_json::from_str(&serialized).unwrap();
+        assert_eq!(deserialized, values);
+    }
+}
@@ -0,0 +1,299 @@
+// Copyright © 2020-2021 HQS Quantum Simulations GmbH. All Rights Reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except
+// in compliance with the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the
+// License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either
+// express or implied. See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! canonical_hash module
+//!
+//! Stable hashes of CalculatorFloat values that agree for equivalent forms of an expression.
+//!
+//! The value is encoded into a canonical byte sequence which is hashed with FNV-1a
+//! (64 or 128 bit). The encoding only depends on the value, not on HashMap seeds,
+//! the platform or its endianness.
+//!
+//! # Normalization
+//!
+//! A symbolic expression is parsed into its expression tree and normalized:
+//!
+//! * Sums and differences are flattened into one sum, `a - b` is the sum of `a` and `-b`
+//! * Products are flattened into one product
+//! * The operands of sums and products are sorted by their encoding
+//! * Double negations cancel and negated numbers are folded into the number
+//! * Numbers are normalized, `-0.0` is encoded as `0.0` and all NaNs as one quiet NaN
+//!
+//! So `(a + b)`, `b+a` and `-(-b) + a` share a hash, and `CalculatorFloat::Float(2.0)`
+//! shares the hash of `"2"`. No further algebraic simplification is performed.
+//! Expressions that can not be parsed are hashed by their raw string.
+//!
+//! # Encoding
+//!
+//! Every node starts with a tag byte, integers are written as u64 little endian:
+//!
+//! | Node       | Encoding                                              |
+//! |------------|-------------------------------------------------------|
+//! | number     | `0x01`, bits of the normalized f64                    |
+//! | variable   | `0x02`, length of the name, UTF-8 bytes of the name   |
+//! | negation   | `0x03`, operand                                       |
+//! | sum        | `0x04`, number of operands, sorted operands           |
+//! | product    | `0x05`, number of operands, sorted operands           |
+//! | quotient   | `0x06`, numerator, denominator                        |
+//! | power      | `0x07`, base, exponent                                |
+//! | function   | `0x08`, length and bytes of the name, number of arguments, arguments |
+//! | unparsable | `0x09`, length of the string, UTF-8 bytes of the string |
+//! | complex    | `0x0a`, real part, imaginary part                     |
+//! | remainder  | `0x0b`, dividend, divisor                             |
+//! | comparison | `0x0c`, length and bytes of the operator, left, right |
+//!
+//! # Compatibility
+//!
+//! The encoding and the hash functions are part of the public API. The hashes are
+//! stable across versions of qoqo_calculator; a change is a breaking change and is
+//! announced in the changelog.
+
+use crate::derivative::ExpressionTree;
+use crate::{CalculatorComplex, CalculatorFloat};
+
+/// Tag of a number node
+const NUMBER: u8 = 0x01;
+/// Tag of a variable node
+const VARIABLE: u8 = 0x02;
+/// Tag of a negation node
+const NEGATE: u8 = 0x03;
+/// Tag of a sum node
+const SUM: u8 = 0x04;
+/// Tag of a product node
+const PRODUCT: u8 = 0x05;
+/// Tag of a quotient node
+const QUOTIENT: u8 = 0x06;
+/// Tag of a power node
+const POWER: u8 = 0x07;
+/// Tag of a function node
+const FUNCTION: u8 = 0x08;
+/// Tag of an expression that can not be parsed
+const UNPARSABLE: u8 = 0x09;
+/// Tag of a complex value
+const COMPLEX: u8 = 0x0a;
+/// Tag of a remainder node
+const REMAINDER: u8 = 0x0b;
+/// Tag of a comparison node
+const COMPARISON: u8 = 0x0c;
+
+/// Bits of the canonical NaN
+const CANONICAL_NAN: u64 = 0x7ff8_0000_0000_0000;
+
+/// Return the canonical encoding of a CalculatorFloat.
+pub(crate) fn encode_float(value: &CalculatorFloat) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    match value {
+        CalculatorFloat::Float(x) => encode_number(*x, &mut bytes),
+        CalculatorFloat::Str(expression) => match ExpressionTree::parse(expression) {
+            Ok(tree) => encode_tree(&tree, &mut bytes),
+            Err(_) => {
+                bytes.push(UNPARSABLE);
+                encode_str(expression, &mut bytes);
+            }
+        },
+    }
+    bytes
+}
+
+/// Return the canonical encoding of a CalculatorComplex.
+pub(crate) fn encode_complex(value: &CalculatorComplex) -> Vec<u8> {
+    let mut bytes = vec![COMPLEX];
+    bytes.extend(encode_float(&value.re));
+    bytes.extend(encode_float(&value.im));
+    bytes
+}
+
+/// Return the 64 bit FNV-1a hash of the bytes.
+pub(crate) fn fnv1a_64(bytes: &[u8]) -> u64 {
+    const OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0000_0100_0000_01b3;
+    bytes.iter().fold(OFFSET, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(PRIME)
+    })
+}
+
+/// Return the 128 bit FNV-1a hash of the bytes.
+pub(crate) fn fnv1a_128(bytes: &[u8]) -> u128 {
+    const OFFSET: u128 = 0x6c62_272e_07bb_0142_62b8_2175_6295_c58d;
+    const PRIME: u128 = 0x0000_0000_0100_0000_0000_0000_0000_013b;
+    bytes.iter().fold(OFFSET, |hash, byte| {
+        (hash ^ u128::from(*byte)).wrapping_mul(PRIME)
+    })
+}
+
+/// Append the encoding of a number.
+fn encode_number(x: f64, bytes: &mut Vec<u8>) {
+    let bits = if x.is_nan() {
+        CANONICAL_NAN
+    } else if x == 0.0 {
+        0
+    } else {
+        x.to_bits()
+    };
+    bytes.push(NUMBER);
+    bytes.extend(bits.to_le_bytes());
+}
+
+/// Append the length and the UTF-8 bytes of a string.
+fn encode_str(string: &str, bytes: &mut Vec<u8>) {
+    bytes.extend((string.len() as u64).to_le_bytes());
+    bytes.extend(string.as_bytes());
+}
+
+/// Append a node with a tag and operands sorted by their encoding.
+fn encode_sorted(tag: u8, mut operands: Vec<Vec<u8>>, bytes: &mut Vec<u8>) {
+    operands.sort_unstable();
+    bytes.push(tag);
+    bytes.extend((operands.len() as u64).to_le_bytes());
+    for operand in operands {
+        bytes.extend(operand);
+    }
+}
+
+/// Append the normalized encoding of an expression tree.
+fn encode_tree(tree: &ExpressionTree, bytes: &mut Vec<u8>) {
+    match tree {
+        ExpressionTree::Number(x) => encode_number(*x, bytes),
+        ExpressionTree::Variable(name) => {
+            bytes.push(VARIABLE);
+            encode_str(name, bytes);
+        }
+        ExpressionTree::Negate(_) | ExpressionTree::Add(..) | ExpressionTree::Subtract(..) => {
+            let mut terms = Vec::new();
+            collect_terms(tree, false, &mut terms);
+            if terms.len() == 1 {
+                bytes.extend(terms.remove(0));
+            } else {
+                encode_sorted(SUM, terms, bytes);
+            }
+        }
+        ExpressionTree::Multiply(..) => {
+            let mut factors = Vec::new();
+            collect_factors(tree, &mut factors);
+            encode_sorted(PRODUCT, factors, bytes);
+        }
+        ExpressionTree::Divide(numerator, denominator) => {
+            bytes.push(QUOTIENT);
+            encode_tree(numerator, bytes);
+            encode_tree(denominator, bytes);
+        }
+        ExpressionTree::Remainder(dividend, divisor) => {
+            bytes.push(REMAINDER);
+            encode_tree(dividend, bytes);
+            encode_tree(divisor, bytes);
+        }
+        ExpressionTree::Compare(operator, left, right) => {
+            bytes.push(COMPARISON);
+            encode_str(operator.symbol(), bytes);
+            encode_tree(left, bytes);
+            encode_tree(right, bytes);
+        }
+        ExpressionTree::Power(base, exponent) => {
+            bytes.push(POWER);
+            encode_tree(base, bytes);
+            encode_tree(exponent, bytes);
+        }
+        ExpressionTree::Function(name, arguments) => {
+            bytes.push(FUNCTION);
+            encode_str(name, bytes);
+            bytes.extend((arguments.len() as u64).to_le_bytes());
+            for argument in arguments {
+                encode_tree(argument, bytes);
+            }
+        }
+    }
+}
+
+/// Collect the encoded terms of a sum, negating them when `negative` is set.
+fn collect_terms(tree: &ExpressionTree, negative: bool, terms: &mut Vec<Vec<u8>>) {
+    match tree {
+        ExpressionTree::Add(left, right) => {
+            collect_terms(left, negative, terms);
+            collect_terms(right, negative, terms);
+        }
+        ExpressionTree::Subtract(left, right) => {
+            collect_terms(left, negative, terms);
+            collect_terms(right, !negative, terms);
+        }
+        ExpressionTree::Negate(argument) => collect_terms(argument, !negative, terms),
+        ExpressionTree::Number(x) if negative => {
+            let mut term = Vec::new();
+            encode_number(-x, &mut term);
+            terms.push(term);
+        }
+        _ => {
+            let mut term = Vec::new();
+            if negative {
+                term.push(NEGATE);
+            }
+            encode_tree(tree, &mut term);
+            terms.push(term);
+        }
+    }
+}
+
+/// Collect the encoded factors of a product.
+fn collect_factors(tree: &ExpressionTree, factors: &mut Vec<Vec<u8>>) {
+    match tree {
+        ExpressionTree::Multiply(left, right) => {
+            collect_factors(left, factors);
+            collect_factors(right, factors);
+        }
+        _ => {
+            let mut factor = Vec::new();
+            encode_tree(tree, &mut factor);
+            factors.push(factor);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Test the FNV-1a hashes against the published test vectors
+    #[test]
+    fn fnv1a_test_vectors() {
+        assert_eq!(fnv1a_64(b""), 0xcbf29ce484222325);
+        assert_eq!(fnv1a_64(b"a"), 0xaf63dc4c8601ec8c);
+        assert_eq!(fnv1a_64(b"foobar"), 0x85944171f73967e8);
+        assert_eq!(fnv1a_128(b""), 0x6c62272e07bb014262b821756295c58d);
+        assert_eq!(fnv1a_128(b"a"), 0xd228cb696f1a8caf78912b704e4a8964);
+    }
+
+    // Test the encoding of numbers, variables and the normalized operators
+    #[test]
+    fn encoding() {
+        assert_eq!(
+            encode_float(&CalculatorFloat::from(1.0)),
+            [&[NUMBER][..], &1.0f64.to_bits().to_le_bytes()].concat()
+        );
+        assert_eq!(
+            encode_float(&CalculatorFloat::from("ab")),
+            [&[VARIABLE][..], &2u64.to_le_bytes(), b"ab"].concat()
+        );
+        let a = encode_float(&CalculatorFloat::from("a"));
+        let b = encode_float(&CalculatorFloat::from("b"));
+        let mut negative_b = vec![NEGATE];
+        negative_b.extend(&b);
+        let mut sum = vec![SUM];
+        sum.extend(2u64.to_le_bytes());
+        sum.extend(&a);
+        sum.extend(&negative_b);
+        assert_eq!(encode_float(&CalculatorFloat::from("-b + a")), sum);
+        assert_eq!(encode_float(&CalculatorFloat::from("a - b")), sum);
+        assert_eq!(
+            encode_float(&CalculatorFloat::Str("sin(".to_string())),
+            [&[UNPARSABLE][..], &4u64.to_le_bytes(), b"sin("].concat()
+        );
+    }
+}
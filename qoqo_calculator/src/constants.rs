@@ -0,0 +1,81 @@
+// Copyright © 2020-2021 HQS Quantum Simulations GmbH. All Rights Reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except
+// in compliance with the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the
+// License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either
+// express or implied. See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! constants module
+//!
+//! Provides physical constants with CODATA 2018 values in SI units.
+//! The constants can be loaded into a Calculator with
+//! [Calculator::load_physical_constants](crate::Calculator::load_physical_constants).
+
+/// Reduced Planck constant in J s
+pub const HBAR: f64 = 1.054_571_817e-34;
+/// Boltzmann constant in J/K
+pub const KB: f64 = 1.380_649e-23;
+/// Elementary charge in C
+pub const QE: f64 = 1.602_176_634e-19;
+/// Speed of light in vacuum in m/s
+pub const C: f64 = 299_792_458.0;
+/// Vacuum magnetic permeability in N/A^2
+pub const MU0: f64 = 1.256_637_062_12e-6;
+/// Vacuum electric permittivity in F/m
+pub const EPS0: f64 = 8.854_187_812_8e-12;
+
+/// Names of the physical constants as used in expressions and their values.
+pub const PHYSICAL_CONSTANTS: &[(&str, f64)] = &[
+    ("hbar", HBAR),
+    ("kb", KB),
+    ("qe", QE),
+    ("c", C),
+    ("mu0", MU0),
+    ("eps0", EPS0),
+];
+
+/// Physical constants as CalculatorFloat.
+pub mod calculator_float {
+    use crate::CalculatorFloat;
+
+    /// Reduced Planck constant in J s
+    pub const HBAR: CalculatorFloat = CalculatorFloat::Float(super::HBAR);
+    /// Boltzmann constant in J/K
+    pub const KB: CalculatorFloat = CalculatorFloat::Float(super::KB);
+    /// Elementary charge in C
+    pub const QE: CalculatorFloat = CalculatorFloat::Float(super::QE);
+    /// Speed of light in vacuum in m/s
+    pub const C: CalculatorFloat = CalculatorFloat::Float(super::C);
+    /// Vacuum magnetic permeability in N/A^2
+    pub const MU0: CalculatorFloat = CalculatorFloat::Float(super::MU0);
+    /// Vacuum electric permittivity in F/m
+    pub const EPS0: CalculatorFloat = CalculatorFloat::Float(super::EPS0);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CalculatorFloat;
+
+    // Test the exact CODATA 2018 values
+    #[test]
+    fn codata_values() {
+        assert_eq!(HBAR, 1.054571817e-34);
+        assert_eq!(KB, 1.380649e-23);
+        assert_eq!(QE, 1.602176634e-19);
+        assert_eq!(C, 299792458.0);
+        assert_eq!(calculator_float::HBAR, CalculatorFloat::Float(HBAR));
+        assert_eq!(
+            calculator_float::EPS0,
+            CalculatorFloat::Float(8.8541878128e-12)
+        );
+        assert_eq!(PHYSICAL_CONSTANTS.len(), 6);
+        // mu0 and eps0 are consistent with the speed of light
+        assert!((1.0 / (MU0 * EPS0 * C * C) - 1.0).abs() < 1e-9);
+    }
+}
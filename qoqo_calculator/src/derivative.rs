@@ -0,0 +1,856 @@
+// Copyright © 2020-2021 HQS Quantum Simulations GmbH. All Rights Reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except
+// in compliance with the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the
+// License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either
+// express or implied. See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! derivative module
+//!
+//! Provides the ExpressionTree used for the symbolic differentiation of CalculatorFloat
+//! expressions. An expression is parsed once into a tree that can be differentiated
+//! with respect to any number of variables.
+
+use crate::calculator::{
+    function_1_argument, function_2_arguments, function_argument_numbers, locate_parsing_error,
+    placeholders_in, ArgumentCount, ComparisonOperator, SpannedTokenIterator, Token, REDUCTIONS,
+};
+use crate::{CalculatorError, CalculatorFloat};
+use std::f64::consts::{FRAC_2_SQRT_PI, LN_10, LN_2};
+
+/// Expression tree of a symbolic expression.
+///
+/// Follows the grammar of the Calculator: a unary sign only applies to the following
+/// number, variable, function or bracket and powers are not chained.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum ExpressionTree {
+    /// A float or integer
+    Number(f64),
+    /// A variable
+    Variable(String),
+    /// Unary minus
+    Negate(Box<ExpressionTree>),
+    /// Sum of two expressions
+    Add(Box<ExpressionTree>, Box<ExpressionTree>),
+    /// Difference of two expressions
+    Subtract(Box<ExpressionTree>, Box<ExpressionTree>),
+    /// Product of two expressions
+    Multiply(Box<ExpressionTree>, Box<ExpressionTree>),
+    /// Quotient of two expressions
+    Divide(Box<ExpressionTree>, Box<ExpressionTree>),
+    /// Remainder of the truncated division of two expressions
+    Remainder(Box<ExpressionTree>, Box<ExpressionTree>),
+    /// First expression to the power of the second expression
+    Power(Box<ExpressionTree>, Box<ExpressionTree>),
+    /// A known function applied to its arguments
+    Function(String, Vec<ExpressionTree>),
+    /// Comparison of two expressions, 1.0 when it holds and 0.0 otherwise
+    Compare(ComparisonOperator, Box<ExpressionTree>, Box<ExpressionTree>),
+}
+
+impl ExpressionTree {
+    /// Parse a string expression into an expression tree.
+    ///
+    /// Expressions separated by `;` are accepted like in the Calculator, the last one
+    /// determines the tree. Assignments are rejected.
+    ///
+    /// # Arguments
+    ///
+    /// * `expression` - Expression that is parsed
+    ///
+    pub(crate) fn parse(expression: &str) -> Result<Self, CalculatorError> {
+        let (positions, tokens): (Vec<usize>, Vec<Token>) = SpannedTokenIterator::new(expression)
+            .map(|(span, token)| (span.start, token))
+            .unzip();
+        let mut parser = TreeParser { tokens, current: 0 };
+        let mut tree = None;
+        while parser.current_token() != &Token::EndOfString {
+            // Parsing errors are returned with the same positions as by the Calculator
+            tree = Some(parser.parse_statement().map_err(|error| {
+                locate_parsing_error(
+                    error,
+                    expression,
+                    parser.current_token(),
+                    positions.get(parser.current).copied().unwrap_or_default(),
+                )
+            })?);
+            while parser.current_token() == &Token::EndOfExpression {
+                parser.next_token();
+            }
+        }
+        tree.ok_or(CalculatorError::NoValueReturnedParsing)
+    }
+
+    /// Parse the tokens of an expression consisting of a single statement.
+    ///
+    /// Unlike [ExpressionTree::parse] expressions with several statements are rejected,
+    /// since the Calculator evaluates all of them and can fail in any of them.
+    ///
+    /// # Arguments
+    ///
+    /// * `tokens` - Tokens of the expression
+    ///
+    pub(crate) fn parse_single_statement(tokens: &[Token]) -> Result<Self, CalculatorError> {
+        let mut parser = TreeParser {
+            tokens: tokens.to_vec(),
+            current: 0,
+        };
+        let tree = parser.parse_statement()?;
+        while parser.current_token() == &Token::EndOfExpression {
+            parser.next_token();
+        }
+        match parser.current_token() {
+            Token::EndOfString => Ok(tree),
+            _ => Err(CalculatorError::ParsingError {
+                msg: "Expected a single statement",
+                position: None,
+            }),
+        }
+    }
+
+    /// Return the derivative of the expression with respect to a variable.
+    ///
+    /// # Arguments
+    ///
+    /// * `variable` - Name of the variable
+    ///
+    pub(crate) fn derivative(&self, variable: &str) -> Result<CalculatorFloat, CalculatorError> {
+        Ok(self.value_and_derivative(variable)?.1)
+    }
+
+    /// Return the value of the expression.
+    ///
+    /// Numeric subexpressions are evaluated, symbolic ones are combined with the arithmetic
+    /// of CalculatorFloat, which drops added zeros and factors of one.
+    pub(crate) fn value(&self) -> Result<CalculatorFloat, CalculatorError> {
+        match self {
+            ExpressionTree::Number(x) => Ok(CalculatorFloat::Float(*x)),
+            ExpressionTree::Variable(name) => Ok(CalculatorFloat::Str(name.clone())),
+            ExpressionTree::Negate(argument) => Ok(-argument.value()?),
+            ExpressionTree::Add(left, right) => left.value()?.checked_add(right.value()?),
+            ExpressionTree::Subtract(left, right) => left.value()?.checked_sub(right.value()?),
+            ExpressionTree::Multiply(left, right) => left.value()?.checked_mul(right.value()?),
+            ExpressionTree::Divide(left, right) => left.value()?.checked_div(right.value()?),
+            ExpressionTree::Remainder(left, right) => left.value()?.checked_rem(right.value()?),
+            ExpressionTree::Power(base, exponent) => power(base.value()?, exponent.value()?),
+            ExpressionTree::Function(name, arguments) => {
+                let values = arguments
+                    .iter()
+                    .map(|argument| argument.value())
+                    .collect::<Result<Vec<CalculatorFloat>, CalculatorError>>()?;
+                apply_function(name, &values)
+            }
+            ExpressionTree::Compare(operator, left, right) => {
+                compare(*operator, left.value()?, right.value()?)
+            }
+        }
+    }
+
+    /// Return the value of the expression and its derivative with respect to a variable.
+    fn value_and_derivative(
+        &self,
+        variable: &str,
+    ) -> Result<(CalculatorFloat, CalculatorFloat), CalculatorError> {
+        match self {
+            ExpressionTree::Number(x) => Ok((CalculatorFloat::Float(*x), CalculatorFloat::ZERO)),
+            ExpressionTree::Variable(name) => {
+                let derivative = if name == variable { 1.0 } else { 0.0 };
+                Ok((
+                    CalculatorFloat::Str(name.clone()),
+                    CalculatorFloat::Float(derivative),
+                ))
+            }
+            ExpressionTree::Negate(argument) => {
+                let (u, du) = argument.value_and_derivative(variable)?;
+                Ok((-u, -du))
+            }
+            ExpressionTree::Add(left, right) => {
+                let (u, du) = left.value_and_derivative(variable)?;
+                let (v, dv) = right.value_and_derivative(variable)?;
+                Ok((u.checked_add(v)?, du.checked_add(dv)?))
+            }
+            ExpressionTree::Subtract(left, right) => {
+                let (u, du) = left.value_and_derivative(variable)?;
+                let (v, dv) = right.value_and_derivative(variable)?;
+                Ok((u.checked_sub(v)?, du.checked_sub(dv)?))
+            }
+            ExpressionTree::Multiply(left, right) => {
+                let (u, du) = left.value_and_derivative(variable)?;
+                let (v, dv) = right.value_and_derivative(variable)?;
+                let derivative = du
+                    .checked_mul(v.clone())?
+                    .checked_add(u.clone().checked_mul(dv)?)?;
+                Ok((u.checked_mul(v)?, derivative))
+            }
+            ExpressionTree::Divide(left, right) => {
+                let (u, du) = left.value_and_derivative(variable)?;
+                let (v, dv) = right.value_and_derivative(variable)?;
+                let value = u.clone().checked_div(v.clone())?;
+                let derivative = if is_zero(&dv) {
+                    du.checked_div(v)?
+                } else {
+                    du.checked_mul(v.clone())?
+                        .checked_sub(u.checked_mul(dv)?)?
+                        .checked_div(power(v, 2.0.into())?)?
+                };
+                Ok((value, derivative))
+            }
+            ExpressionTree::Remainder(left, right) => {
+                let (u, du) = left.value_and_derivative(variable)?;
+                let (v, dv) = right.value_and_derivative(variable)?;
+                let value = u.checked_rem(v.clone())?;
+                // d(u % v) = du - trunc(u / v) * dv with the truncated quotient (u - u % v) / v
+                let derivative = if is_zero(&dv) {
+                    du
+                } else {
+                    let quotient = u.checked_sub(value.clone())?.checked_div(v)?;
+                    du.checked_sub(quotient.checked_mul(dv)?)?
+                };
+                Ok((value, derivative))
+            }
+            ExpressionTree::Power(base, exponent) => {
+                let (u, du) = base.value_and_derivative(variable)?;
+                let (v, dv) = exponent.value_and_derivative(variable)?;
+                power_value_and_derivative(u, du, v, dv)
+            }
+            ExpressionTree::Function(name, arguments) => {
+                let mut values = Vec::with_capacity(arguments.len());
+                let mut derivatives = Vec::with_capacity(arguments.len());
+                for argument in arguments {
+                    let (value, derivative) = argument.value_and_derivative(variable)?;
+                    values.push(value);
+                    derivatives.push(derivative);
+                }
+                let value = apply_function(name, &values)?;
+                if derivatives.iter().all(is_zero) {
+                    return Ok((value, CalculatorFloat::ZERO));
+                }
+                let derivative = function_derivative(name, &value, &values, &derivatives)?;
+                Ok((value, derivative))
+            }
+            // Comparisons are piecewise constant, like in the Calculator the derivative is zero
+            ExpressionTree::Compare(..) => Ok((self.value()?, CalculatorFloat::ZERO)),
+        }
+    }
+}
+
+/// Recursive descent parser building an ExpressionTree, mirroring the Calculator parser.
+struct TreeParser<'a> {
+    tokens: Vec<Token<'a>>,
+    current: usize,
+}
+
+impl<'a> TreeParser<'a> {
+    /// Return the current token.
+    fn current_token(&self) -> &Token<'a> {
+        self.tokens.get(self.current).unwrap_or(&Token::EndOfString)
+    }
+
+    /// Advance to the next token.
+    fn next_token(&mut self) {
+        self.current += 1;
+    }
+
+    /// Parse a single statement, rejecting assignments.
+    fn parse_statement(&mut self) -> Result<ExpressionTree, CalculatorError> {
+        match self.current_token() {
+            Token::EndOfExpression | Token::EndOfString => {
+                Err(CalculatorError::UnexpectedEndOfExpression)
+            }
+            Token::VariableAssign(variable_name) | Token::CompoundAssign(variable_name, _) => {
+                Err(CalculatorError::NotParsableAssign {
+                    variable_name: variable_name.to_string(),
+                })
+            }
+            Token::Assign => Err(CalculatorError::NotParsableSingleAssign),
+            _ => self.parse_binary_0(),
+        }
+    }
+
+    /// Parse least preference binary expression (<, >, <=, >=, ==, !=).
+    fn parse_binary_0(&mut self) -> Result<ExpressionTree, CalculatorError> {
+        let mut tree = self.parse_binary_1()?;
+        while let Token::Comparison(operator) = self.current_token() {
+            let operator = *operator;
+            self.next_token();
+            let right = Box::new(self.parse_binary_1()?);
+            tree = ExpressionTree::Compare(operator, Box::new(tree), right);
+        }
+        Ok(tree)
+    }
+
+    /// Parse low preference binary expression (+, -).
+    fn parse_binary_1(&mut self) -> Result<ExpressionTree, CalculatorError> {
+        let mut tree = self.parse_binary_2()?;
+        loop {
+            let add = match self.current_token() {
+                Token::Plus => true,
+                Token::Minus => false,
+                _ => return Ok(tree),
+            };
+            self.next_token();
+            let right = Box::new(self.parse_binary_2()?);
+            tree = if add {
+                ExpressionTree::Add(Box::new(tree), right)
+            } else {
+                ExpressionTree::Subtract(Box::new(tree), right)
+            };
+        }
+    }
+
+    /// Parse middle preference binary expression (*, /, %).
+    fn parse_binary_2(&mut self) -> Result<ExpressionTree, CalculatorError> {
+        let mut tree = self.parse_binary_3()?;
+        loop {
+            let operation = match self.current_token() {
+                Token::Multiply => ExpressionTree::Multiply,
+                Token::Divide => ExpressionTree::Divide,
+                Token::Modulo => ExpressionTree::Remainder,
+                _ => return Ok(tree),
+            };
+            self.next_token();
+            let right = Box::new(self.parse_binary_3()?);
+            tree = operation(Box::new(tree), right);
+        }
+    }
+
+    /// Parse highest preference binary expression (^, !).
+    fn parse_binary_3(&mut self) -> Result<ExpressionTree, CalculatorError> {
+        let tree = self.parse_unary()?;
+        match self.current_token() {
+            Token::DoubleFactorial => Err(CalculatorError::NotImplementedError {
+                fct: "DoubleFactorial",
+            }),
+            Token::Factorial => Err(CalculatorError::NotImplementedError { fct: "Factorial" }),
+            Token::Power => {
+                self.next_token();
+                let exponent = self.parse_unary()?;
+                Ok(ExpressionTree::Power(Box::new(tree), Box::new(exponent)))
+            }
+            _ => Ok(tree),
+        }
+    }
+
+    /// Handle any unary + or - signs.
+    fn parse_unary(&mut self) -> Result<ExpressionTree, CalculatorError> {
+        match self.current_token() {
+            Token::Minus => {
+                self.next_token();
+                Ok(ExpressionTree::Negate(Box::new(self.parse_atom()?)))
+            }
+            Token::Plus => {
+                self.next_token();
+                self.parse_atom()
+            }
+            _ => self.parse_atom(),
+        }
+    }
+
+    /// Handle numbers, variables, functions and parentheses.
+    fn parse_atom(&mut self) -> Result<ExpressionTree, CalculatorError> {
+        match self.current_token().clone() {
+            Token::BracketOpen => {
+                self.next_token();
+                let tree = self.parse_statement()?;
+                if self.current_token() != &Token::BracketClose {
+                    return Err(CalculatorError::ParsingError {
+                        msg: "Expected Braket close",
+                        position: None,
+                    });
+                }
+                self.next_token();
+                Ok(tree)
+            }
+            Token::Number(x) => {
+                self.next_token();
+                Ok(ExpressionTree::Number(x))
+            }
+            Token::Variable(name) => {
+                self.next_token();
+                Ok(ExpressionTree::Variable(name.into_owned()))
+            }
+            Token::Function(name) => {
+                self.next_token();
+                let number_arguments = match function_argument_numbers(&name)? {
+                    ArgumentCount::Fixed(number_arguments) => number_arguments,
+                    ArgumentCount::Variadic => return self.parse_variadic(&name),
+                };
+                let mut arguments = Vec::with_capacity(number_arguments);
+                for argument_number in 0..number_arguments {
+                    arguments.push(self.parse_statement()?);
+                    if argument_number < number_arguments - 1 {
+                        if self.current_token() != &Token::Comma {
+                            return Err(CalculatorError::ParsingError {
+                                msg: "expected comma in function arguments",
+                                position: None,
+                            });
+                        }
+                        self.next_token();
+                    }
+                }
+                if self.current_token() != &Token::BracketClose {
+                    return Err(CalculatorError::ParsingError {
+                        msg: "Expected braket close.",
+                        position: None,
+                    });
+                }
+                self.next_token();
+                Ok(ExpressionTree::Function(name.into_owned(), arguments))
+            }
+            Token::Reduction(name) => {
+                self.next_token();
+                self.parse_reduction(&name)
+            }
+            Token::Placeholder(_) => Err(CalculatorError::UnboundPlaceholder {
+                names: placeholders_in(self.tokens.iter()),
+            }),
+            _ => Err(CalculatorError::ParsingError {
+                msg: "Bad_Position",
+                position: None,
+            }),
+        }
+    }
+
+    /// Parse a variadic function like `max(a, b, c)` after the opening bracket.
+    ///
+    /// The arguments are combined like the values of a reduction, see [Self::parse_reduction].
+    fn parse_variadic(&mut self, name: &str) -> Result<ExpressionTree, CalculatorError> {
+        let mut values: Vec<ExpressionTree> = Vec::new();
+        if self.current_token() != &Token::BracketClose {
+            loop {
+                values.push(self.parse_statement()?);
+                match self.current_token() {
+                    Token::Comma => self.next_token(),
+                    Token::BracketClose => break,
+                    _ => {
+                        return Err(CalculatorError::ParsingError {
+                            msg: "Expected braket close.",
+                            position: None,
+                        })
+                    }
+                }
+            }
+        }
+        self.next_token();
+        if values.len() < 2 {
+            return Err(CalculatorError::NotEnoughFunctionArguments);
+        }
+        Ok(combine_values(name, values).expect("at least two values are combined"))
+    }
+
+    /// Parse a reduction like `max[a, b, c]` after the opening square bracket.
+    ///
+    /// Maxima and minima become nested calls of the functions with two arguments,
+    /// sums and means become chains of additions like in the Calculator.
+    fn parse_reduction(&mut self, name: &str) -> Result<ExpressionTree, CalculatorError> {
+        if !REDUCTIONS.contains(&name) {
+            return Err(CalculatorError::FunctionNotFound {
+                fct: format!("{name}[]"),
+            });
+        }
+        let mut values: Vec<ExpressionTree> = Vec::new();
+        if self.current_token() != &Token::ListClose {
+            loop {
+                values.push(self.parse_statement()?);
+                match self.current_token() {
+                    Token::Comma => self.next_token(),
+                    Token::ListClose => break,
+                    _ => {
+                        return Err(CalculatorError::ParsingError {
+                            msg: "Expected square braket close.",
+                            position: None,
+                        })
+                    }
+                }
+            }
+        }
+        self.next_token();
+        let number_values = values.len();
+        let combined = match combine_values(name, values) {
+            Some(combined) => combined,
+            None if name == "sum" => return Ok(ExpressionTree::Number(0.0)),
+            None => return Err(CalculatorError::NotEnoughFunctionArguments),
+        };
+        Ok(match name {
+            "mean" => ExpressionTree::Divide(
+                Box::new(combined),
+                Box::new(ExpressionTree::Number(number_values as f64)),
+            ),
+            _ => combined,
+        })
+    }
+}
+
+/// Combine values from left to right, None when there are no values.
+///
+/// Maxima and minima become nested calls of the functions with two arguments,
+/// all other values are added.
+fn combine_values(name: &str, values: Vec<ExpressionTree>) -> Option<ExpressionTree> {
+    let mut values = values.into_iter();
+    let first = values.next()?;
+    Some(values.fold(first, |previous, value| match name {
+        "max" | "min" => ExpressionTree::Function(name.to_string(), vec![previous, value]),
+        _ => ExpressionTree::Add(Box::new(previous), Box::new(value)),
+    }))
+}
+
+/// Return true when the value is the number zero.
+fn is_zero(value: &CalculatorFloat) -> bool {
+    matches!(value, CalculatorFloat::Float(x) if *x == 0.0)
+}
+
+/// Return base ^ exponent, simplifying the exponents zero and one.
+fn power(
+    base: CalculatorFloat,
+    exponent: CalculatorFloat,
+) -> Result<CalculatorFloat, CalculatorError> {
+    match exponent {
+        CalculatorFloat::Float(0.0) => Ok(CalculatorFloat::Float(1.0)),
+        CalculatorFloat::Float(1.0) => Ok(base),
+        _ => {
+            crate::calculator_float::check_symbolic_length(&base, &exponent)?;
+            Ok(base.powf(exponent))
+        }
+    }
+}
+
+/// Return the comparison of two values, a float when both values are floats.
+fn compare(
+    operator: ComparisonOperator,
+    left: CalculatorFloat,
+    right: CalculatorFloat,
+) -> Result<CalculatorFloat, CalculatorError> {
+    match (&left, &right) {
+        (CalculatorFloat::Float(x), CalculatorFloat::Float(y)) => {
+            Ok(CalculatorFloat::Float(operator.compare(*x, *y)))
+        }
+        _ => {
+            crate::calculator_float::check_symbolic_length(&left, &right)?;
+            Ok(CalculatorFloat::Str(format!(
+                "({left} {} {right})",
+                operator.symbol()
+            )))
+        }
+    }
+}
+
+/// Return the value and derivative of u ^ v from the values and derivatives of u and v.
+fn power_value_and_derivative(
+    u: CalculatorFloat,
+    du: CalculatorFloat,
+    v: CalculatorFloat,
+    dv: CalculatorFloat,
+) -> Result<(CalculatorFloat, CalculatorFloat), CalculatorError> {
+    let value = power(u.clone(), v.clone())?;
+    let derivative = if is_zero(&dv) {
+        // d(u^v) = v * u^(v - 1) * du for a constant exponent
+        if is_zero(&du) {
+            CalculatorFloat::ZERO
+        } else {
+            v.clone()
+                .checked_mul(power(u, v.checked_sub(1.0)?)?)?
+                .checked_mul(du)?
+        }
+    } else {
+        // d(u^v) = u^v * (dv * log(u) + v * du / u)
+        let log_term = dv.checked_mul(apply_function("log", std::slice::from_ref(&u))?)?;
+        let inner = if is_zero(&du) {
+            log_term
+        } else {
+            log_term.checked_add(v.checked_mul(du)?.checked_div(u)?)?
+        };
+        value.clone().checked_mul(inner)?
+    };
+    Ok((value, derivative))
+}
+
+/// Apply a known function to its arguments.
+///
+/// Numeric arguments are evaluated like in the Calculator, otherwise a symbolic
+/// function call is returned.
+fn apply_function(
+    name: &str,
+    arguments: &[CalculatorFloat],
+) -> Result<CalculatorFloat, CalculatorError> {
+    match arguments {
+        [CalculatorFloat::Float(x)] => Ok(CalculatorFloat::Float(function_1_argument(name, *x)?)),
+        [CalculatorFloat::Float(x), CalculatorFloat::Float(y)] => {
+            Ok(CalculatorFloat::Float(function_2_arguments(name, *x, *y)?))
+        }
+        _ => {
+            let arguments: Vec<String> = arguments.iter().map(|x| x.to_string()).collect();
+            Ok(CalculatorFloat::Str(format!(
+                "{name}({})",
+                arguments.join(", ")
+            )))
+        }
+    }
+}
+
+/// Return the derivative of a function call by the chain rule.
+///
+/// # Arguments
+///
+/// * `name` - Name of the function
+/// * `value` - Value of the function call
+/// * `arguments` - Values of the arguments
+/// * `derivatives` - Derivatives of the arguments
+///
+fn function_derivative(
+    name: &str,
+    value: &CalculatorFloat,
+    arguments: &[CalculatorFloat],
+    derivatives: &[CalculatorFloat],
+) -> Result<CalculatorFloat, CalculatorError> {
+    let one = CalculatorFloat::Float(1.0);
+    let apply = |function: &str, argument: &CalculatorFloat| {
+        apply_function(function, std::slice::from_ref(argument))
+    };
+    if let ([a, b], [da, db]) = (arguments, derivatives) {
+        let (a, b, da, db) = (a.clone(), b.clone(), da.clone(), db.clone());
+        return match name {
+            "atan2" => {
+                let numerator = b
+                    .clone()
+                    .checked_mul(da)?
+                    .checked_sub(a.clone().checked_mul(db)?)?;
+                numerator.checked_div(power(a, 2.0.into())?.checked_add(power(b, 2.0.into())?)?)
+            }
+            "hypot" => a
+                .checked_mul(da)?
+                .checked_add(b.checked_mul(db)?)?
+                .checked_div(value.clone()),
+            "pow" => Ok(power_value_and_derivative(a, da, b, db)?.1),
+            "binom" => Err(CalculatorError::NotImplementedError { fct: "binom" }),
+            // Piecewise constant like round
+            "round_digits" | "round_step" => Ok(CalculatorFloat::ZERO),
+            "max" | "min" => {
+                let (first, second) = if name == "max" {
+                    (a.clone().checked_sub(b.clone())?, b.checked_sub(a)?)
+                } else {
+                    (b.clone().checked_sub(a.clone())?, a.checked_sub(b)?)
+                };
+                apply("theta", &first)?
+                    .checked_mul(da)?
+                    .checked_add(apply("theta", &second)?.checked_mul(db)?)
+            }
+            _ => Err(CalculatorError::FunctionNotFound {
+                fct: name.to_string(),
+            }),
+        };
+    }
+    let (u, du) = match (arguments, derivatives) {
+        ([u], [du]) => (u.clone(), du.clone()),
+        _ => return Err(CalculatorError::NotEnoughFunctionArguments),
+    };
+    let square = power(u.clone(), 2.0.into())?;
+    let outer = match name {
+        "sin" => apply("cos", &u)?,
+        "cos" => -apply("sin", &u)?,
+        "tan" => one.checked_div(power(apply("cos", &u)?, 2.0.into())?)?,
+        "asin" => one.checked_div(apply(
+            "sqrt",
+            &CalculatorFloat::Float(1.0).checked_sub(square)?,
+        )?)?,
+        "acos" => {
+            -(one.checked_div(apply(
+                "sqrt",
+                &CalculatorFloat::Float(1.0).checked_sub(square)?,
+            )?)?)
+        }
+        "atan" => one.checked_div(CalculatorFloat::Float(1.0).checked_add(square)?)?,
+        "sinh" => apply("cosh", &u)?,
+        "cosh" => apply("sinh", &u)?,
+        "tanh" => one.checked_div(power(apply("cosh", &u)?, 2.0.into())?)?,
+        "asinh" | "arsinh" => one.checked_div(apply("sqrt", &square.checked_add(1.0)?)?)?,
+        "acosh" | "arcosh" => one.checked_div(apply("sqrt", &square.checked_sub(1.0)?)?)?,
+        "atanh" | "artanh" => one.checked_div(CalculatorFloat::Float(1.0).checked_sub(square)?)?,
+        "exp" | "expm1" => apply("exp", &u)?,
+        "exp2" => apply("exp2", &u)?.checked_mul(LN_2)?,
+        "log" => one.checked_div(u)?,
+        "log10" => one.checked_div(u.checked_mul(LN_10)?)?,
+        "log2" => one.checked_div(u.checked_mul(LN_2)?)?,
+        "sqrt" => one.checked_div(value.clone().checked_mul(2.0)?)?,
+        "cbrt" => one.checked_div(power(value.clone(), 2.0.into())?.checked_mul(3.0)?)?,
+        "abs" => apply("sign", &u)?,
+        "fract" => one,
+        "erf" => apply("exp", &(-square))?.checked_mul(FRAC_2_SQRT_PI)?,
+        // Piecewise constant functions have a vanishing derivative almost everywhere
+        "sign" | "ceil" | "floor" | "round" | "theta" | "delta" | "parity" => CalculatorFloat::ZERO,
+        "tgamma" => return Err(CalculatorError::NotImplementedError { fct: "tgamma" }),
+        "gamma" => return Err(CalculatorError::NotImplementedError { fct: "gamma" }),
+        "factorial" => return Err(CalculatorError::NotImplementedError { fct: "factorial" }),
+        "lgamma" => return Err(CalculatorError::NotImplementedError { fct: "lgamma" }),
+        _ => {
+            return Err(CalculatorError::FunctionNotFound {
+                fct: name.to_string(),
+            })
+        }
+    };
+    outer.checked_mul(du)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ExpressionTree;
+    use crate::{Calculator, CalculatorError, CalculatorFloat};
+
+    // Test that the tree follows the grammar of the Calculator
+    #[test]
+    fn test_parse() {
+        let number = |x: f64| Box::new(ExpressionTree::Number(x));
+        let variable = |name: &str| Box::new(ExpressionTree::Variable(name.to_string()));
+        assert_eq!(
+            ExpressionTree::parse("-x^2").unwrap(),
+            ExpressionTree::Power(Box::new(ExpressionTree::Negate(variable("x"))), number(2.0))
+        );
+        assert_eq!(
+            ExpressionTree::parse("1 - x - y").unwrap(),
+            ExpressionTree::Subtract(
+                Box::new(ExpressionTree::Subtract(number(1.0), variable("x"))),
+                variable("y")
+            )
+        );
+        assert_eq!(
+            ExpressionTree::parse("x; 2 * y").unwrap(),
+            ExpressionTree::Multiply(number(2.0), variable("y"))
+        );
+        assert_eq!(
+            ExpressionTree::parse("atan2(x, 1)").unwrap(),
+            ExpressionTree::Function("atan2".to_string(), vec![*variable("x"), *number(1.0)])
+        );
+    }
+
+    // Test that invalid expressions return the errors of the Calculator
+    #[test]
+    fn test_parse_errors() {
+        let calculator = Calculator::new();
+        for expression in ["(1", "sin(1", "atan2(1)", "1 + )", "1!", "", "unknown(1)"] {
+            let calculator_error = calculator.parse_str(expression).unwrap_err();
+            assert_eq!(
+                ExpressionTree::parse(expression).unwrap_err(),
+                calculator_error,
+                "{expression}"
+            );
+        }
+        assert_eq!(
+            ExpressionTree::parse("a=x"),
+            Err(CalculatorError::NotParsableAssign {
+                variable_name: "a".to_string()
+            })
+        );
+    }
+
+    // Test the derivatives of the basic operations
+    #[test]
+    fn test_derivative_operations() {
+        let derivative = |expression: &str| {
+            ExpressionTree::parse(expression)
+                .unwrap()
+                .derivative("x")
+                .unwrap()
+        };
+        assert_eq!(derivative("x"), CalculatorFloat::Float(1.0));
+        assert_eq!(derivative("y"), CalculatorFloat::Float(0.0));
+        assert_eq!(derivative("3 * x + y"), CalculatorFloat::Float(3.0));
+        assert_eq!(derivative("x * y"), CalculatorFloat::from("y"));
+        assert_eq!(derivative("x / y"), CalculatorFloat::from("(1e0 / y)"));
+        assert_eq!(derivative("x^2"), CalculatorFloat::from("(2e0 * x)"));
+        assert_eq!(derivative("-x"), CalculatorFloat::Float(-1.0));
+        assert_eq!(derivative("sin(x)"), CalculatorFloat::from("cos(x)"));
+        assert_eq!(derivative("sin(y)"), CalculatorFloat::Float(0.0));
+        assert_eq!(derivative("theta(x)"), CalculatorFloat::Float(0.0));
+    }
+
+    // Test the derivatives of all functions against finite differences
+    #[test]
+    fn test_derivative_functions() {
+        let mut calculator = Calculator::new();
+        let x = 0.35;
+        let step = 1e-6;
+        for expression in [
+            "sin(x)",
+            "cos(x)",
+            "tan(x)",
+            "asin(x)",
+            "acos(x)",
+            "atan(x)",
+            "sinh(x)",
+            "cosh(x)",
+            "tanh(x)",
+            "asinh(x)",
+            "arsinh(x)",
+            "acosh(x + 1)",
+            "arcosh(x + 1)",
+            "atanh(x)",
+            "artanh(x)",
+            "exp(x)",
+            "exp2(x)",
+            "expm1(x)",
+            "log(x)",
+            "log10(x)",
+            "sqrt(x)",
+            "cbrt(x)",
+            "abs(x)",
+            "fract(x + 1)",
+            "sign(x)",
+            "ceil(x)",
+            "floor(x)",
+            "round(x)",
+            "round_digits(x, 2)",
+            "round_step(x, 0.25)",
+            "theta(x)",
+            "delta(x)",
+            "atan2(x, 0.2)",
+            "atan2(0.2, x)",
+            "hypot(x, 0.2)",
+            "pow(x, 3)",
+            "pow(2, x)",
+            "pow(x, x)",
+            "x^x",
+            "max(x, 0.2)",
+            "max(0.2, x)",
+            "min(x, 0.2)",
+            "min(0.2, x)",
+            "max[0.2, x, 2 * x - 0.5]",
+            "min[x, 0.2, x^2]",
+            "sum[x, sin(x), 2]",
+            "mean[x, x^2, 1]",
+            "max(0.2, x, 2 * x - 0.5)",
+            "min(x, 0.2, x^2)",
+            "sum(x, sin(x), 2)",
+        ] {
+            let derivative = ExpressionTree::parse(expression)
+                .unwrap()
+                .derivative("x")
+                .unwrap();
+            calculator.set_variable("x", x).unwrap();
+            let value = calculator.parse_get(derivative).unwrap();
+            calculator.set_variable("x", x + step).unwrap();
+            let upper = calculator.parse_str(expression).unwrap();
+            calculator.set_variable("x", x - step).unwrap();
+            let lower = calculator.parse_str(expression).unwrap();
+            let finite_difference = (upper - lower) / (2.0 * step);
+            assert!(
+                (value - finite_difference).abs() < 1e-6 * (1.0 + value.abs()),
+                "{expression}: {value} != {finite_difference}"
+            );
+        }
+    }
+
+    // Test functions without implemented derivative
+    #[test]
+    fn test_derivative_not_implemented() {
+        let tree = ExpressionTree::parse("tgamma(x)").unwrap();
+        assert_eq!(
+            tree.derivative("x"),
+            Err(CalculatorError::NotImplementedError { fct: "tgamma" })
+        );
+        assert_eq!(tree.derivative("y"), Ok(CalculatorFloat::Float(0.0)));
+    }
+}
@@ -0,0 +1,374 @@
+// Copyright © 2020-2021 HQS Quantum Simulations GmbH. All Rights Reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except
+// in compliance with the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the
+// License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either
+// express or implied. See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! detokenize module
+//!
+//! Writes lexed Tokens back to a canonical expression string, so tools can transform
+//! token streams without building an expression tree.
+//!
+//! The canonical form has single spaces around binary operators and assignments, no spaces
+//! inside brackets and function calls, a space after commas and semicolons and no comments.
+//! Unary signs are written directly in front of their operand. Assignments of variables are
+//! written as `a=1`, as the lexer requires the `=` directly after the name.
+//!
+//! Numbers are written in plain decimal notation between 1e-4 and 1e16 and in scientific
+//! notation otherwise, in both cases with the shortest digits that give back the same f64.
+
+use crate::calculator::{CompoundOperator, Token, TokenIterator};
+use crate::CalculatorError;
+
+/// Operator used to write powers when detokenizing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PowerOperator {
+    /// `^`, as used by CalculatorFloat
+    #[default]
+    Caret,
+    /// `**`
+    DoubleStar,
+}
+
+/// Lex an expression into Tokens.
+///
+/// Whitespace and comments are skipped. Whitespace or a comment at the end of the
+/// expression gives a final [Token::EndOfString].
+///
+/// # Arguments
+///
+/// * `expression` - Expression that is lexed
+///
+pub fn tokenize(expression: &str) -> Vec<Token<'_>> {
+    TokenIterator {
+        current_expression: expression,
+    }
+    .collect()
+}
+
+/// Write Tokens back to a canonical expression string using `^` for powers.
+///
+/// See [detokenize_with].
+///
+/// # Arguments
+///
+/// * `tokens` - Tokens that are written
+///
+pub fn detokenize<'a>(
+    tokens: impl IntoIterator<Item = Token<'a>>,
+) -> Result<String, CalculatorError> {
+    detokenize_with(tokens, PowerOperator::default())
+}
+
+/// Write Tokens back to a canonical expression string.
+///
+/// The returned expression lexes to the same Tokens, apart from [Token::EndOfString]
+/// which is not written.
+///
+/// # Arguments
+///
+/// * `tokens` - Tokens that are written
+/// * `power` - Operator used to write [Token::Power]
+///
+/// # Returns
+///
+/// * `Ok(String)` - The canonical expression
+/// * `Err(CalculatorError::ParsingError)` - The Tokens contain [Token::Unrecognized] or can not
+///   be written so that they are lexed again, e.g. a negative number or a variable directly
+///   followed by a bracket, which is lexed as a function
+///
+pub fn detokenize_with<'a>(
+    tokens: impl IntoIterator<Item = Token<'a>>,
+    power: PowerOperator,
+) -> Result<String, CalculatorError> {
+    let tokens: Vec<Token<'a>> = tokens
+        .into_iter()
+        .filter(|token| token != &Token::EndOfString)
+        .collect();
+    let mut expression = String::new();
+    let mut previous: Option<&Token<'a>> = None;
+    let mut previous_is_sign = false;
+    for token in tokens.iter() {
+        if token == &Token::Unrecognized {
+            return Err(CalculatorError::ParsingError {
+                msg: "Unrecognized token can not be written to an expression",
+                position: None,
+            });
+        }
+        if !previous_is_sign && space_between(previous, token) {
+            expression.push(' ');
+        }
+        match token {
+            Token::Number(x) => expression.push_str(&format_number(*x)),
+            Token::ImaginaryNumber(x) => {
+                expression.push_str(&format_number(*x));
+                expression.push('i');
+            }
+            Token::Variable(name) => expression.push_str(name),
+            Token::Function(name) => {
+                expression.push_str(name);
+                expression.push('(');
+            }
+            Token::Reduction(name) => {
+                expression.push_str(name);
+                expression.push('[');
+            }
+            Token::Plus => expression.push('+'),
+            Token::Minus => expression.push('-'),
+            Token::Multiply => expression.push('*'),
+            Token::Divide => expression.push('/'),
+            Token::Modulo => expression.push('%'),
+            Token::Power => expression.push_str(match power {
+                PowerOperator::Caret => "^",
+                PowerOperator::DoubleStar => "**",
+            }),
+            Token::Factorial => expression.push('!'),
+            Token::DoubleFactorial => expression.push_str("!!"),
+            Token::BracketOpen => expression.push('('),
+            Token::BracketClose => expression.push(')'),
+            Token::ListOpen => expression.push('['),
+            Token::ListClose => expression.push(']'),
+            Token::Assign => expression.push('='),
+            Token::Comparison(operator) => expression.push_str(operator.symbol()),
+            Token::VariableAssign(name) => {
+                expression.push_str(name);
+                expression.push('=');
+            }
+            Token::CompoundAssign(name, operator) => {
+                expression.push_str(name);
+                expression.push_str(match operator {
+                    CompoundOperator::Plus => " +=",
+                    CompoundOperator::Minus => " -=",
+                    CompoundOperator::Multiply => " *=",
+                    CompoundOperator::Divide => " /=",
+                });
+            }
+            Token::Comma => expression.push(','),
+            Token::StringLiteral(literal) => {
+                expression.push('\'');
+                expression.push_str(literal);
+                expression.push('\'');
+            }
+            Token::Placeholder(name) => {
+                expression.push('{');
+                expression.push_str(name);
+                expression.push('}');
+            }
+            Token::EndOfExpression => expression.push(';'),
+            Token::EndOfString | Token::Unrecognized => (),
+        }
+        previous_is_sign =
+            matches!(token, Token::Plus | Token::Minus) && is_operand_expected(previous);
+        previous = Some(token);
+    }
+    // Names, literals and numbers are written as they are, check that they are lexed back
+    if !tokenize(&expression)
+        .into_iter()
+        .filter(|token| token != &Token::EndOfString)
+        .eq(tokens)
+    {
+        return Err(CalculatorError::ParsingError {
+            msg: "Tokens can not be written to an expression that is lexed to the same tokens",
+            position: None,
+        });
+    }
+    Ok(expression)
+}
+
+/// Lex an expression and write it in the canonical form of [detokenize], dropping comments.
+///
+/// # Arguments
+///
+/// * `expression` - Expression that is reformatted
+///
+pub fn reformat(expression: &str) -> Result<String, CalculatorError> {
+    detokenize(tokenize(expression))
+}
+
+/// Return true when a Plus or Minus after the token is a sign and not a binary operator.
+///
+/// Signs are written directly in front of their operand.
+fn is_operand_expected(previous: Option<&Token<'_>>) -> bool {
+    matches!(
+        previous,
+        None | Some(
+            Token::Plus
+                | Token::Minus
+                | Token::Multiply
+                | Token::Divide
+                | Token::Modulo
+                | Token::Power
+                | Token::Function(_)
+                | Token::Reduction(_)
+                | Token::BracketOpen
+                | Token::ListOpen
+                | Token::Assign
+                | Token::Comparison(_)
+                | Token::VariableAssign(_)
+                | Token::CompoundAssign(..)
+                | Token::Comma
+                | Token::EndOfExpression
+        )
+    )
+}
+
+/// Return true when a space is written between the previous and the current token,
+/// apart from signs which are handled by the caller.
+fn space_between(previous: Option<&Token<'_>>, token: &Token<'_>) -> bool {
+    let Some(previous_token) = previous else {
+        return false;
+    };
+    match previous_token {
+        Token::Function(_) | Token::Reduction(_) | Token::BracketOpen | Token::ListOpen => {
+            return false
+        }
+        // `x= =` must not be written as the comparison `x==`
+        Token::VariableAssign(_) => {
+            return matches!(token, Token::Assign | Token::Comparison(_));
+        }
+        _ => (),
+    }
+    match token {
+        Token::BracketClose | Token::ListClose | Token::Comma | Token::EndOfExpression => false,
+        // `! !` must not be written as a double factorial
+        Token::Factorial | Token::DoubleFactorial => {
+            matches!(previous_token, Token::Factorial | Token::DoubleFactorial)
+        }
+        _ => true,
+    }
+}
+
+/// Format a number with the shortest digits that give back the same f64.
+fn format_number(value: f64) -> String {
+    if value == 0.0 || (1e-4..1e16).contains(&value.abs()) {
+        format!("{value}")
+    } else {
+        format!("{value:e}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::borrow::Cow;
+
+    /// Expressions and scripts covering all tokens of the lexer.
+    const CORPUS: [&str; 17] = [
+        "a+b*c - d/e % f",
+        "2**3 ^ -x",
+        "sin(x)*cos( y )",
+        "-(-a) + +b",
+        "a=3; b += a*2;c-=1",
+        "x = 2; x",
+        "max[a, b, 3] + sum[]",
+        "sum_vars('a_') * {qubit}",
+        "5!! + 3! ! + 4!!!",
+        "1e-7 + 2.5E+3 + 1e300 + .5 + 0.1 + 123456789",
+        "a # comment with 2,5\n + 1 # trailing",
+        "atan2(a,-b)/ 2",
+        "b*=2; b/=4;b",
+        "[1, 2]",
+        "a<b>c <= d>=e==-f != g",
+        "x == 2; y=x != 1; 3! = 2; z= ==1",
+        "1 + 2i - 3.5e-1j * x",
+    ];
+
+    /// Return the tokens of the expression without EndOfString.
+    fn lexed(expression: &str) -> Vec<Token<'_>> {
+        tokenize(expression)
+            .into_iter()
+            .filter(|token| token != &Token::EndOfString)
+            .collect()
+    }
+
+    // Test that detokenized expressions are lexed to the same tokens with both power operators
+    #[test]
+    fn corpus_round_trip() {
+        for expression in CORPUS {
+            let tokens = tokenize(expression);
+            for power in [PowerOperator::Caret, PowerOperator::DoubleStar] {
+                let written = detokenize_with(tokens.clone(), power).unwrap();
+                assert_eq!(
+                    lexed(&written),
+                    lexed(expression),
+                    "{expression} -> {written}"
+                );
+                // The canonical form is a fixed point
+                assert_eq!(detokenize_with(tokenize(&written), power).unwrap(), written);
+            }
+        }
+    }
+
+    // Test the canonical form
+    #[test]
+    fn canonical_form() {
+        for (expression, expected) in [
+            ("a+b*c - d/e", "a + b * c - d / e"),
+            ("sin(x)*cos( y )", "sin(x) * cos(y)"),
+            ("-(-a) + +b", "-(-a) + +b"),
+            ("a=3; b += a*2;c-=1", "a=3; b += a * 2; c -= 1"),
+            ("max[a, b, 3] + sum[]", "max[a, b, 3] + sum[]"),
+            ("5!! + 3! ! + 4!!!", "5!! + 3! ! + 4!! !"),
+            ("a # comment with 2,5\n + 1 # trailing", "a + 1"),
+            ("atan2(a,-b)/ 2", "atan2(a, -b) / 2"),
+            ("sum_vars('a_') * {qubit}", "sum_vars('a_') * {qubit}"),
+        ] {
+            assert_eq!(reformat(expression).unwrap(), expected);
+        }
+        let tokens = tokenize("2**3 ^ -x");
+        assert_eq!(detokenize(tokens.clone()).unwrap(), "2 ^ 3 ^ -x");
+        assert_eq!(
+            detokenize_with(tokens, PowerOperator::DoubleStar).unwrap(),
+            "2 ** 3 ** -x"
+        );
+    }
+
+    // Test that numbers are written with the shortest digits in plain or scientific notation
+    #[test]
+    fn numbers() {
+        assert_eq!(
+            reformat("1e-7 + 2.5E+3 + 1e300 + .5 + 0.1 + 123456789 + 1e16 + 0.0001").unwrap(),
+            "1e-7 + 2500 + 1e300 + 0.5 + 0.1 + 123456789 + 1e16 + 0.0001"
+        );
+        assert_eq!(reformat("0.1e1").unwrap(), "1");
+        for x in [f64::MIN_POSITIVE, f64::MAX, 0.1 + 0.2, 1.0 / 3.0, 5e-324] {
+            let written = detokenize([Token::Number(x)]).unwrap();
+            assert_eq!(tokenize(&written), vec![Token::Number(x)]);
+        }
+    }
+
+    // Test tokens that can not be written back
+    #[test]
+    fn not_writable() {
+        for tokens in [
+            vec![Token::Number(-1.0)],
+            vec![Token::Number(f64::NAN)],
+            vec![Token::Number(f64::INFINITY)],
+            vec![Token::Variable(Cow::Borrowed("a")), Token::BracketOpen],
+            vec![Token::Variable(Cow::Borrowed("a")), Token::Assign],
+            vec![Token::Variable(Cow::Borrowed("a b"))],
+            vec![Token::StringLiteral(Cow::Borrowed("it's"))],
+        ] {
+            assert_eq!(
+                detokenize(tokens),
+                Err(CalculatorError::ParsingError {
+msg: "Tokens can not be written to an expression that is lexed to the same tokens",
+position: None,
+})
+            );
+        }
+        assert_eq!(
+            reformat("a $ b"),
+            Err(CalculatorError::ParsingError {
+                msg: "Unrecognized token can not be written to an expression",
+                position: None,
+            })
+        );
+    }
+}
@@ -0,0 +1,656 @@
+// Copyright © 2020-2021 HQS Quantum Simulations GmbH. All Rights Reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except
+// in compliance with the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the
+// License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either
+// express or implied. See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! dual module
+//!
+//! Provides the number types the Calculator parser evaluates expressions with:
+//! f64 for values, Dual for values together with their derivative with respect to one
+//! variable (forward-mode automatic differentiation), `Complex<f64>` for expressions with
+//! imaginary numbers and Unevaluated for checking expressions without computing them.
+//!
+//! The derivatives of the functions follow the rules of the symbolic derivative
+//! ([CalculatorFloat::derivative](crate::CalculatorFloat::derivative)):
+//!
+//! * Piecewise constant functions (`sign`, `ceil`, `floor`, `round`, `round_digits`, `round_step`,
+//!   `theta`, `delta`, `parity`) have a vanishing derivative, also at their discontinuities
+//! * `abs` has the derivative `sign(x)`, also at zero
+//! * `max` and `min` take the derivative of the selected argument, following `theta` for ties
+//! * `binom`, `factorial` and the gamma functions have no derivative rule and return an error,
+//!   unless the derivative of their arguments vanishes
+//! * Interpolation tables have the slope of the interpolated segment, the slope of the segment
+//!   to the right at a knot and zero outside of the table
+
+use crate::calculator::{function_1_argument, function_2_arguments};
+use crate::{math, CalculatorError, InterpolationTable};
+use num_complex::Complex;
+use std::f64::consts::{FRAC_2_SQRT_PI, LN_10, LN_2};
+use std::ops;
+
+/// Number type the parser evaluates expressions with.
+pub(crate) trait Scalar:
+    Copy
+    + ops::Add<Output = Self>
+    + ops::Sub<Output = Self>
+    + ops::Mul<Output = Self>
+    + ops::Div<Output = Self>
+    + ops::Rem<Output = Self>
+{
+    /// Return a number that does not depend on the variable.
+    fn constant(value: f64) -> Self;
+
+    /// Return the value of a variable, the seeded variable has the derivative one.
+    fn variable(value: f64, seeded: bool) -> Self;
+
+    /// Return an imaginary number, real number types return an error.
+    fn imaginary(_value: f64) -> Result<Self, CalculatorError> {
+        Err(CalculatorError::ParsingError {
+            msg: "Imaginary number in real expression, use parse_str_complex",
+            position: None,
+        })
+    }
+
+    /// Return the value of the number.
+    fn value(self) -> f64;
+
+    /// Return the value of a number that has to be real, e.g. an assigned value or a condition.
+    fn real(self) -> Result<f64, CalculatorError> {
+        Ok(self.value())
+    }
+
+    /// Return true when the number is zero, used to check divisions.
+    fn is_zero(self) -> bool {
+        self.value() == 0.0
+    }
+
+    /// Return the derivative of the number, zero for f64.
+    fn derivative(self) -> f64;
+
+    /// Return the power self ^ exponent.
+    fn powf(self, exponent: Self) -> Self;
+
+    /// Apply a known function with one argument.
+    fn function_1(name: &str, argument: Self) -> Result<Self, CalculatorError>;
+
+    /// Apply a known function with two arguments.
+    fn function_2(name: &str, first: Self, second: Self) -> Result<Self, CalculatorError>;
+
+    /// Interpolate a table at the argument.
+    fn interpolate(table: &InterpolationTable, argument: Self) -> Result<Self, CalculatorError>;
+}
+
+impl Scalar for f64 {
+    #[inline]
+    fn constant(value: f64) -> Self {
+        value
+    }
+
+    #[inline]
+    fn variable(value: f64, _seeded: bool) -> Self {
+        value
+    }
+
+    #[inline]
+    fn value(self) -> f64 {
+        self
+    }
+
+    #[inline]
+    fn derivative(self) -> f64 {
+        0.0
+    }
+
+    #[inline]
+    fn powf(self, exponent: Self) -> Self {
+        math::powf(self, exponent)
+    }
+
+    #[inline]
+    fn function_1(name: &str, argument: Self) -> Result<Self, CalculatorError> {
+        function_1_argument(name, argument)
+    }
+
+    #[inline]
+    fn function_2(name: &str, first: Self, second: Self) -> Result<Self, CalculatorError> {
+        function_2_arguments(name, first, second)
+    }
+
+    #[inline]
+    fn interpolate(table: &InterpolationTable, argument: Self) -> Result<Self, CalculatorError> {
+        table.interpolate(argument)
+    }
+}
+
+/// Dual number holding a value and its derivative with respect to one variable.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct Dual {
+    /// Value
+    pub(crate) value: f64,
+    /// Derivative of the value
+    pub(crate) derivative: f64,
+}
+
+impl ops::Add for Dual {
+    type Output = Self;
+    fn add(self, other: Self) -> Self {
+        Dual {
+            value: self.value + other.value,
+            derivative: self.derivative + other.derivative,
+        }
+    }
+}
+
+impl ops::Sub for Dual {
+    type Output = Self;
+    fn sub(self, other: Self) -> Self {
+        Dual {
+            value: self.value - other.value,
+            derivative: self.derivative - other.derivative,
+        }
+    }
+}
+
+impl ops::Mul for Dual {
+    type Output = Self;
+    fn mul(self, other: Self) -> Self {
+        Dual {
+            value: self.value * other.value,
+            derivative: self.derivative * other.value + self.value * other.derivative,
+        }
+    }
+}
+
+impl ops::Div for Dual {
+    type Output = Self;
+    fn div(self, other: Self) -> Self {
+        let value = self.value / other.value;
+        Dual {
+            value,
+            derivative: (self.derivative - value * other.derivative) / other.value,
+        }
+    }
+}
+
+impl ops::Rem for Dual {
+    type Output = Self;
+    fn rem(self, other: Self) -> Self {
+        // u % v = u - trunc(u / v) * v, the truncated quotient is piecewise constant
+        let quotient = (self.value / other.value).trunc();
+        Dual {
+            value: self.value % other.value,
+            derivative: self.derivative - quotient * other.derivative,
+        }
+    }
+}
+
+impl Scalar for Dual {
+    fn constant(value: f64) -> Self {
+        Dual {
+            value,
+            derivative: 0.0,
+        }
+    }
+
+    fn variable(value: f64, seeded: bool) -> Self {
+        Dual {
+            value,
+            derivative: if seeded { 1.0 } else { 0.0 },
+        }
+    }
+
+    fn value(self) -> f64 {
+        self.value
+    }
+
+    fn derivative(self) -> f64 {
+        self.derivative
+    }
+
+    fn powf(self, exponent: Self) -> Self {
+        let value = math::powf(self.value, exponent.value);
+        let derivative = if exponent.derivative == 0.0 {
+            // d(u^v) = v * u^(v - 1) * du for a constant exponent
+            if self.derivative == 0.0 {
+                0.0
+            } else {
+                exponent.value * math::powf(self.value, exponent.value - 1.0) * self.derivative
+            }
+        } else {
+            // d(u^v) = u^v * (dv * log(u) + v * du / u)
+            let log_term = exponent.derivative * math::ln(self.value);
+            let inner = if self.derivative == 0.0 {
+                log_term
+            } else {
+                log_term + exponent.value * self.derivative / self.value
+            };
+            value * inner
+        };
+        Dual { value, derivative }
+    }
+
+    fn function_1(name: &str, argument: Self) -> Result<Self, CalculatorError> {
+        let value = function_1_argument(name, argument.value)?;
+        if argument.derivative == 0.0 {
+            return Ok(Dual::constant(value));
+        }
+        let u = argument.value;
+        let outer = match name {
+            "sin" => math::cos(u),
+            "cos" => -math::sin(u),
+            "tan" => 1.0 / math::powf(math::cos(u), 2.0),
+            "asin" => 1.0 / (1.0 - u * u).sqrt(),
+            "acos" => -1.0 / (1.0 - u * u).sqrt(),
+            "atan" => 1.0 / (1.0 + u * u),
+            "sinh" => math::cosh(u),
+            "cosh" => math::sinh(u),
+            "tanh" => 1.0 / math::powf(math::cosh(u), 2.0),
+            "asinh" | "arsinh" => 1.0 / (u * u + 1.0).sqrt(),
+            "acosh" | "arcosh" => 1.0 / (u * u - 1.0).sqrt(),
+            "atanh" | "artanh" => 1.0 / (1.0 - u * u),
+            "exp" | "expm1" => math::exp(u),
+            "exp2" => math::exp2(u) * LN_2,
+            "log" => 1.0 / u,
+            "log10" => 1.0 / (u * LN_10),
+            "log2" => 1.0 / (u * LN_2),
+            "sqrt" => 1.0 / (2.0 * value),
+            "cbrt" => 1.0 / (3.0 * value * value),
+            "abs" => function_1_argument("sign", u)?,
+            "fract" => 1.0,
+            "erf" => math::exp(-(u * u)) * FRAC_2_SQRT_PI,
+            // Piecewise constant functions have a vanishing derivative almost everywhere
+            "sign" | "ceil" | "floor" | "round" | "theta" | "delta" | "parity" => 0.0,
+            "tgamma" => return Err(CalculatorError::NotImplementedError { fct: "tgamma" }),
+            "gamma" => return Err(CalculatorError::NotImplementedError { fct: "gamma" }),
+            "factorial" => return Err(CalculatorError::NotImplementedError { fct: "factorial" }),
+            "lgamma" => return Err(CalculatorError::NotImplementedError { fct: "lgamma" }),
+            _ => {
+                return Err(CalculatorError::FunctionNotFound {
+                    fct: name.to_string(),
+                })
+            }
+        };
+        Ok(Dual {
+            value,
+            derivative: outer * argument.derivative,
+        })
+    }
+
+    fn function_2(name: &str, first: Self, second: Self) -> Result<Self, CalculatorError> {
+        let value = function_2_arguments(name, first.value, second.value)?;
+        if first.derivative == 0.0 && second.derivative == 0.0 {
+            return Ok(Dual::constant(value));
+        }
+        let (a, da, b, db) = (
+            first.value,
+            first.derivative,
+            second.value,
+            second.derivative,
+        );
+        let derivative = match name {
+            "atan2" => (b * da - a * db) / (a * a + b * b),
+            "hypot" => (a * da + b * db) / value,
+            "pow" => first.powf(second).derivative,
+            "binom" => return Err(CalculatorError::NotImplementedError { fct: "binom" }),
+            // Piecewise constant like round
+            "round_digits" | "round_step" => 0.0,
+            "max" => {
+                function_1_argument("theta", a - b)? * da
+                    + function_1_argument("theta", b - a)? * db
+            }
+            "min" => {
+                function_1_argument("theta", b - a)? * da
+                    + function_1_argument("theta", a - b)? * db
+            }
+            _ => {
+                return Err(CalculatorError::FunctionNotFound {
+                    fct: name.to_string(),
+                })
+            }
+        };
+        Ok(Dual { value, derivative })
+    }
+
+    fn interpolate(table: &InterpolationTable, argument: Self) -> Result<Self, CalculatorError> {
+        let value = table.interpolate(argument.value)?;
+        if argument.derivative == 0.0 {
+            return Ok(Dual::constant(value));
+        }
+        let (xs, ys) = (table.xs(), table.ys());
+        // Number of x values smaller or equal to the argument, as in interpolate
+        let upper = xs.partition_point(|knot| *knot <= argument.value);
+        let slope = if upper == 0 || upper == xs.len() {
+            0.0
+        } else {
+            (ys[upper] - ys[upper - 1]) / (xs[upper] - xs[upper - 1])
+        };
+        Ok(Dual {
+            value,
+            derivative: slope * argument.derivative,
+        })
+    }
+}
+
+/// Number that is not computed, used to check expressions without evaluating them.
+///
+/// Every operation succeeds, so domain errors and divisions by zero are not reported.
+/// The value is one, which passes the checks of the parser for divisions and asserts.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct Unevaluated;
+
+impl ops::Add for Unevaluated {
+    type Output = Self;
+    fn add(self, _other: Self) -> Self {
+        Unevaluated
+    }
+}
+
+impl ops::Sub for Unevaluated {
+    type Output = Self;
+    fn sub(self, _other: Self) -> Self {
+        Unevaluated
+    }
+}
+
+impl ops::Mul for Unevaluated {
+    type Output = Self;
+    fn mul(self, _other: Self) -> Self {
+        Unevaluated
+    }
+}
+
+impl ops::Div for Unevaluated {
+    type Output = Self;
+    fn div(self, _other: Self) -> Self {
+        Unevaluated
+    }
+}
+
+impl ops::Rem for Unevaluated {
+    type Output = Self;
+    fn rem(self, _other: Self) -> Self {
+        Unevaluated
+    }
+}
+
+impl Scalar for Unevaluated {
+    fn constant(_value: f64) -> Self {
+        Unevaluated
+    }
+
+    fn variable(_value: f64, _seeded: bool) -> Self {
+        Unevaluated
+    }
+
+    fn value(self) -> f64 {
+        1.0
+    }
+
+    fn derivative(self) -> f64 {
+        0.0
+    }
+
+    fn powf(self, _exponent: Self) -> Self {
+        Unevaluated
+    }
+
+    fn function_1(_name: &str, _argument: Self) -> Result<Self, CalculatorError> {
+        Ok(Unevaluated)
+    }
+
+    fn function_2(_name: &str, _first: Self, _second: Self) -> Result<Self, CalculatorError> {
+        Ok(Unevaluated)
+    }
+
+    fn interpolate(_table: &InterpolationTable, _argument: Self) -> Result<Self, CalculatorError> {
+        Ok(Unevaluated)
+    }
+}
+
+/// Functions of a complex argument, the other functions require a real argument.
+///
+/// `sqrt` and `log` of negative real numbers are evaluated on the principal branch as well.
+fn complex_function(name: &str, argument: Complex<f64>) -> Option<Complex<f64>> {
+    Some(match name {
+        "abs" => Complex::new(argument.norm(), 0.0),
+        "exp" => argument.exp(),
+        "log" => argument.ln(),
+        "sqrt" => argument.sqrt(),
+        "sin" => argument.sin(),
+        "cos" => argument.cos(),
+        "tan" => argument.tan(),
+        "sinh" => argument.sinh(),
+        "cosh" => argument.cosh(),
+        "tanh" => argument.tanh(),
+        _ => return None,
+    })
+}
+
+/// Complex numbers evaluated by [Calculator::parse_str_complex](crate::Calculator::parse_str_complex).
+///
+/// Operations on real values give the same result as for f64, the imaginary part stays zero.
+/// Assigned values, comparisons, conditions and the arguments of functions without complex
+/// implementation have to be real.
+impl Scalar for Complex<f64> {
+    fn constant(value: f64) -> Self {
+        Complex::new(value, 0.0)
+    }
+
+    fn variable(value: f64, _seeded: bool) -> Self {
+        Complex::new(value, 0.0)
+    }
+
+    fn imaginary(value: f64) -> Result<Self, CalculatorError> {
+        Ok(Complex::new(0.0, value))
+    }
+
+    fn value(self) -> f64 {
+        self.re
+    }
+
+    fn real(self) -> Result<f64, CalculatorError> {
+        if self.im == 0.0 {
+            Ok(self.re)
+        } else {
+            Err(CalculatorError::ComplexCanNotBeConvertedToFloat { val: self.into() })
+        }
+    }
+
+    fn is_zero(self) -> bool {
+        self.re == 0.0 && self.im == 0.0
+    }
+
+    fn derivative(self) -> f64 {
+        0.0
+    }
+
+    fn powf(self, exponent: Self) -> Self {
+        if self.im == 0.0 && exponent.im == 0.0 {
+            let value = math::powf(self.re, exponent.re);
+            if !value.is_nan() || self.re.is_nan() || exponent.re.is_nan() {
+                return Complex::new(value, 0.0);
+            }
+        }
+        // Principal branch like CalculatorComplex::powf, zero to a positive power is zero
+        if Scalar::is_zero(self) && exponent.re > 0.0 {
+            return Complex::new(0.0, 0.0);
+        }
+        // Integer powers are multiplied out, so e.g. the square of 2i is exactly -4
+        if exponent.im == 0.0 && exponent.re.fract() == 0.0 && exponent.re.abs() <= 64.0 {
+            return self.powi(exponent.re as i32);
+        }
+        self.powc(exponent)
+    }
+
+    fn function_1(name: &str, argument: Self) -> Result<Self, CalculatorError> {
+        if argument.im == 0.0 && !(matches!(name, "sqrt" | "log") && argument.re < 0.0) {
+            return function_1_argument(name, argument.re).map(Scalar::constant);
+        }
+        match complex_function(name, argument) {
+            Some(value) => Ok(value),
+            None => function_1_argument(name, argument.real()?).map(Scalar::constant),
+        }
+    }
+
+    fn function_2(name: &str, first: Self, second: Self) -> Result<Self, CalculatorError> {
+        if name == "pow" {
+            return Ok(Scalar::powf(first, second));
+        }
+        function_2_arguments(name, first.real()?, second.real()?).map(Scalar::constant)
+    }
+
+    fn interpolate(table: &InterpolationTable, argument: Self) -> Result<Self, CalculatorError> {
+        table.interpolate(argument.real()?).map(Scalar::constant)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Calculator, CalculatorError, CalculatorFloat};
+
+    /// Return the central finite difference of an expression with respect to x.
+    fn central_difference(calculator: &Calculator, expression: &str, x: f64) -> f64 {
+        let step = 1e-6;
+        let mut shifted = calculator.clone();
+        shifted.set_variable("x", x + step).unwrap();
+        let upper = shifted.parse_str(expression).unwrap();
+        shifted.set_variable("x", x - step).unwrap();
+        let lower = shifted.parse_str(expression).unwrap();
+        (upper - lower) / (2.0 * step)
+    }
+
+    // Test the derivatives against central finite differences and the symbolic derivative
+    #[test]
+    fn derivatives_match_finite_differences() {
+        let mut calculator = Calculator::new();
+        calculator.set_variable("y", 0.7).unwrap();
+        for expression in [
+            "3 * x^2 - 2 * x + 1",
+            "sin(x) * cos(2 * x)",
+            "exp(-x^2 / y)",
+            "x / (1 + x)",
+            "-x^3",
+            "sqrt(x) + cbrt(x)",
+            "log(x) + log10(x) + exp2(x)",
+            "atan2(x, y) + hypot(x, y)",
+            "pow(x, y) + y^x + x^x",
+            "tanh(x) + asinh(x) + atan(x)",
+            "max(x, y) * min(x, 2 * y)",
+            "abs(x - y) + floor(x) + theta(x - 1)",
+            "x % 0.3 + 1.7 % x",
+        ] {
+            for x in [0.4, 1.3] {
+                calculator.set_variable("x", x).unwrap();
+                let (value, derivative) = calculator.parse_str_derivative(expression, "x").unwrap();
+                assert_eq!(
+                    value,
+                    calculator.parse_str(expression).unwrap(),
+                    "{expression}"
+                );
+                let expected = central_difference(&calculator, expression, x);
+                assert!(
+                    (derivative - expected).abs() < 1e-6 * (1.0 + expected.abs()),
+                    "{expression} at {x}: {derivative} != {expected}"
+                );
+                let symbolic = CalculatorFloat::from(expression).derivative("x").unwrap();
+                let symbolic = calculator.parse_get(symbolic).unwrap();
+                assert!(
+                    (derivative - symbolic).abs() < 1e-12 * (1.0 + symbolic.abs()),
+                    "{expression} at {x}: {derivative} != {symbolic}"
+                );
+            }
+        }
+    }
+
+    // Test variables, expression variables, aggregates and interpolation tables
+    #[test]
+    fn derivative_through_calculator_features() {
+        let mut calculator = Calculator::new();
+        calculator.set_variable("x", 2.0).unwrap();
+        calculator.set_variable("x_1", 3.0).unwrap();
+        calculator
+            .set_expression_variable("square", "x * x")
+            .unwrap();
+        calculator
+            .register_interpolation_table("table", vec![0.0, 1.0, 3.0], vec![0.0, 2.0, 3.0])
+            .unwrap();
+        assert_eq!(calculator.parse_str_derivative("x", "x"), Ok((2.0, 1.0)));
+        assert_eq!(calculator.parse_str_derivative("x", "y"), Ok((2.0, 0.0)));
+        assert_eq!(
+            calculator.parse_str_derivative("3 * x_1", "x"),
+            Ok((9.0, 0.0))
+        );
+        assert_eq!(
+            calculator.parse_str_derivative("square + 1", "x"),
+            Ok((5.0, 4.0))
+        );
+        assert_eq!(
+            calculator.parse_str_derivative("sum_vars('x') + count_vars('x')", "x"),
+            Ok((7.0, 1.0))
+        );
+        assert_eq!(
+            calculator.parse_str_derivative("table(x)", "x"),
+            Ok((2.5, 0.5))
+        );
+        assert_eq!(
+            calculator.parse_str_derivative("table(x / 2)", "x"),
+            Ok((2.0, 0.25))
+        );
+        assert_eq!(
+            calculator.parse_str_derivative("table(x + 5)", "x"),
+            Ok((3.0, 0.0))
+        );
+        assert_eq!(
+            calculator.parse_str_derivative("1; x^2; 2 * x", "x"),
+            Ok((4.0, 2.0))
+        );
+        assert_eq!(
+            calculator.parse_str_derivative("gamma(3) * x", "x"),
+            Ok((4.0, 2.0))
+        );
+        assert_eq!(
+            calculator.parse_str_derivative("gamma(x)", "x"),
+            Err(CalculatorError::NotImplementedError { fct: "gamma" })
+        );
+        assert_eq!(
+            calculator.parse_str_derivative("x / (x - 2)", "x"),
+            Err(CalculatorError::DivisionByZero)
+        );
+        assert!(matches!(
+            calculator.parse_str_derivative("a = x", "x"),
+            Err(CalculatorError::ForbiddenAssign { .. })
+        ));
+    }
+
+    // Test the documented conventions at discontinuities
+    #[test]
+    fn derivative_conventions() {
+        let mut calculator = Calculator::new();
+        calculator.set_variable("x", 0.0).unwrap();
+        for expression in ["sign(x)", "theta(x)", "floor(x)", "round(x)"] {
+            assert_eq!(
+                calculator.parse_str_derivative(expression, "x").unwrap().1,
+                0.0,
+                "{expression}"
+            );
+        }
+        let sign = calculator.parse_str("sign(0)").unwrap();
+        assert_eq!(
+            calculator.parse_str_derivative("abs(x)", "x"),
+            Ok((0.0, sign))
+        );
+        calculator.set_variable("y", 0.0).unwrap();
+        let theta = calculator.parse_str("theta(0)").unwrap();
+        assert_eq!(
+            calculator.parse_str_derivative("max(x, y)", "x"),
+            Ok((0.0, theta))
+        );
+    }
+}
@@ -0,0 +1,374 @@
+// Copyright © 2020-2021 HQS Quantum Simulations GmbH. All Rights Reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except
+// in compliance with the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the
+// License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either
+// express or implied. See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! evaluation_graph module
+//!
+//! Provides the EvaluationGraph struct re-evaluating only the registered expressions
+//! that depend on changed variables.
+
+use crate::calculator::{validate_expression, Token, VARIABLE_AGGREGATES};
+use crate::{Calculator, CalculatorError, CompiledExpression};
+use std::collections::{HashMap, HashSet};
+
+/// Handle of an expression registered in an [EvaluationGraph].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ExpressionHandle(usize);
+
+impl ExpressionHandle {
+    /// Return the position of the expression in the order of registration.
+    pub fn index(&self) -> usize {
+        self.0
+    }
+}
+
+/// Variables an expression depends on.
+#[derive(Debug, Clone, Default, PartialEq)]
+struct Dependencies {
+    /// Names of the variables used or assigned in the expression
+    variables: Vec<String>,
+    /// Prefixes of `sum_vars('prefix')` and `count_vars('prefix')` in the expression
+    prefixes: Vec<String>,
+}
+
+impl Dependencies {
+    /// Collect the dependencies from the tokens of a compiled expression.
+    fn new(compiled: &CompiledExpression) -> Self {
+        let mut dependencies = Dependencies::default();
+        let tokens = compiled.tokens();
+        for (index, token) in tokens.iter().enumerate() {
+            match token {
+                Token::Variable(name)
+                | Token::VariableAssign(name)
+                | Token::CompoundAssign(name, _)
+                    if !dependencies.variables.iter().any(|known| known == name) =>
+                {
+                    dependencies.variables.push(name.to_string());
+                }
+                Token::StringLiteral(prefix)
+                    if index > 0
+                        && matches!(
+                            &tokens[index - 1],
+                            Token::Function(name) if VARIABLE_AGGREGATES.contains(&&**name)
+                        ) =>
+                {
+                    dependencies.prefixes.push(prefix.to_string());
+                }
+                _ => (),
+            }
+        }
+        dependencies
+    }
+
+    /// Return true when one of the changed variables is used.
+    fn depends_on(&self, changed: &HashSet<String>) -> bool {
+        self.variables.iter().any(|name| changed.contains(name))
+            || changed.iter().any(|name| {
+                self.prefixes
+                    .iter()
+                    .any(|prefix| name.starts_with(prefix.as_str()))
+            })
+    }
+}
+
+/// Expressions that are evaluated together and re-evaluated when variables change.
+///
+/// When an expression is registered, the variables it references are added to a dependency
+/// index. After changing variables of the Calculator, [EvaluationGraph::update] only
+/// re-evaluates the expressions referencing one of the changed variables, directly,
+/// through `sum_vars` and `count_vars` or through variables defined by expressions
+/// (see [Calculator::set_expression_variable]). The definitions of expression variables
+/// are read from the Calculator at every update, so changing them is tracked by passing
+/// the name of the redefined variable.
+///
+/// Changes that are not variables, e.g. registering an interpolation table or setting
+/// the unknown function handler, require [EvaluationGraph::eval_all].
+#[derive(Debug, Clone, Default)]
+pub struct EvaluationGraph {
+    /// Registered expressions in order of registration
+    expressions: Vec<CompiledExpression>,
+    /// Dependencies of the registered expressions
+    dependencies: Vec<Dependencies>,
+    /// Indices of the expressions using a variable, keyed by the variable name
+    dependents: HashMap<String, Vec<usize>>,
+    /// Indices of the expressions using `sum_vars` or `count_vars`
+    aggregating: Vec<usize>,
+    /// Last value of every expression, None when it has not been evaluated successfully
+    values: Vec<Option<f64>>,
+}
+
+impl EvaluationGraph {
+    /// Create new empty EvaluationGraph.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register an expression and add it to the dependency index.
+    ///
+    /// The expression is evaluated by the next [EvaluationGraph::eval_all]
+    /// or [EvaluationGraph::update].
+    ///
+    /// # Arguments
+    ///
+    /// * `expression` - Expression that is registered
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(ExpressionHandle)` - Handle of the registered expression
+    /// * `Err(CalculatorError::InvalidExpression)` - The expression is not valid, see [validate_expression]
+    ///
+    pub fn register(&mut self, expression: &str) -> Result<ExpressionHandle, CalculatorError> {
+        validate_expression(expression)?;
+        let index = self.expressions.len();
+        let compiled = CompiledExpression::new(expression);
+        let dependencies = Dependencies::new(&compiled);
+        for name in dependencies.variables.iter() {
+            self.dependents.entry(name.clone()).or_default().push(index);
+        }
+        if !dependencies.prefixes.is_empty() {
+            self.aggregating.push(index);
+        }
+        self.expressions.push(compiled);
+        self.dependencies.push(dependencies);
+        self.values.push(None);
+        Ok(ExpressionHandle(index))
+    }
+
+    /// Return the number of registered expressions.
+    pub fn len(&self) -> usize {
+        self.expressions.len()
+    }
+
+    /// Return true when no expression is registered.
+    pub fn is_empty(&self) -> bool {
+        self.expressions.is_empty()
+    }
+
+    /// Return the last value of an expression.
+    ///
+    /// # Arguments
+    ///
+    /// * `handle` - Handle of the expression
+    ///
+    /// # Returns
+    ///
+    /// `Option<f64>` - None when the expression has not been evaluated successfully
+    ///
+    pub fn value(&self, handle: ExpressionHandle) -> Option<f64> {
+        self.values.get(handle.0).copied().flatten()
+    }
+
+    /// Evaluate all registered expressions.
+    ///
+    /// # Arguments
+    ///
+    /// * `calculator` - Calculator providing the variables
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Vec<f64>)` - Values of the expressions in order of registration
+    /// * `Err(CalculatorError)` - Evaluating an expression failed, the expressions
+    ///   that have not been evaluated are evaluated by the next update
+    ///
+    pub fn eval_all(&mut self, calculator: &Calculator) -> Result<Vec<f64>, CalculatorError> {
+        self.values.iter_mut().for_each(|value| *value = None);
+        let mut values = Vec::with_capacity(self.expressions.len());
+        for (compiled, value) in self.expressions.iter().zip(self.values.iter_mut()) {
+            let result = calculator.evaluate_compiled(compiled)?;
+            *value = Some(result);
+            values.push(result);
+        }
+        Ok(values)
+    }
+
+    /// Re-evaluate the expressions depending on changed variables.
+    ///
+    /// Expressions that have not been evaluated successfully before are evaluated as well.
+    ///
+    /// # Arguments
+    ///
+    /// * `calculator` - Calculator providing the variables
+    /// * `changed` - Names of the variables that have been set or redefined since the last evaluation
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Vec<(ExpressionHandle, f64)>)` - Re-evaluated expressions whose value changed,
+    ///   in order of registration
+    /// * `Err(CalculatorError)` - Evaluating an expression failed, the failing expression
+    ///   and the expressions that have not been evaluated are evaluated by the next update
+    ///
+    pub fn update(
+        &mut self,
+        calculator: &Calculator,
+        changed: &[&str],
+    ) -> Result<Vec<(ExpressionHandle, f64)>, CalculatorError> {
+        let changed = changed_with_expression_variables(calculator, changed);
+        let mut stale: Vec<usize> = changed
+            .iter()
+            .filter_map(|name| self.dependents.get(name))
+            .flatten()
+            .copied()
+            .chain(
+                self.aggregating
+                    .iter()
+                    .copied()
+                    .filter(|index| self.dependencies[*index].depends_on(&changed)),
+            )
+            .chain(
+                self.values
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(index, value)| value.is_none().then_some(index)),
+            )
+            .collect();
+        stale.sort_unstable();
+        stale.dedup();
+        let mut updated = Vec::new();
+        for index in stale {
+            let previous = self.values[index].take();
+            let result = calculator.evaluate_compiled(&self.expressions[index])?;
+            self.values[index] = Some(result);
+            // Bitwise comparison so NaN results are not reported at every update
+            if previous.map(f64::to_bits) != Some(result.to_bits()) {
+                updated.push((ExpressionHandle(index), result));
+            }
+        }
+        Ok(updated)
+    }
+}
+
+/// Add the variables defined by expressions that depend on changed variables.
+///
+/// # Arguments
+///
+/// * `calculator` - Calculator holding the expression variables
+/// * `changed` - Names of the changed variables
+///
+fn changed_with_expression_variables(calculator: &Calculator, changed: &[&str]) -> HashSet<String> {
+    let mut changed: HashSet<String> = changed.iter().map(|name| name.to_string()).collect();
+    let mut definitions: Vec<(&String, Dependencies)> = calculator
+        .expression_variables()
+        .iter()
+        .filter(|(name, _)| !changed.contains(*name))
+        .map(|(name, expression)| {
+            let compiled = calculator.expression_cache().get_or_compile(expression);
+            (name, Dependencies::new(&compiled))
+        })
+        .collect();
+    // Definitions can reference other expression variables, repeat until no variable is added
+    loop {
+        let (affected, unaffected): (Vec<_>, Vec<_>) = definitions
+            .into_iter()
+            .partition(|(_, dependencies)| dependencies.depends_on(&changed));
+        if affected.is_empty() {
+            return changed;
+        }
+        changed.extend(affected.into_iter().map(|(name, _)| name.clone()));
+        definitions = unaffected;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::EvaluationGraph;
+    use crate::{Calculator, CalculatorError};
+
+    // Test that updates agree with evaluating all expressions after single variable changes
+    #[test]
+    fn update_matches_eval_all() {
+        let mut calculator = Calculator::new();
+        for index in 0..5 {
+            calculator
+                .set_variable(&format!("x_{index}"), index as f64)
+                .unwrap();
+        }
+        calculator
+            .set_expression_variable("double", "2 * x_1")
+            .unwrap();
+        calculator
+            .set_expression_variable("quadruple", "2 * double")
+            .unwrap();
+        let mut graph = EvaluationGraph::new();
+        let expressions = [
+            "x_0 + x_1",
+            "sin(x_2) * x_3",
+            "quadruple - x_4",
+            "sum_vars('x_')",
+            "max[x_0, x_4, 1]",
+            "3",
+        ];
+        let handles: Vec<_> = expressions
+            .iter()
+            .map(|expression| graph.register(expression).unwrap())
+            .collect();
+        assert_eq!(graph.len(), 6);
+        let values = graph.eval_all(&calculator).unwrap();
+        assert_eq!(values, vec![1.0, 2.0f64.sin() * 3.0, 0.0, 10.0, 4.0, 3.0]);
+
+        let changed: Vec<_> = graph
+            .update(&calculator, &["x_1"])
+            .unwrap()
+            .into_iter()
+            .map(|(handle, _)| handle.index())
+            .collect();
+        assert!(changed.is_empty());
+        for (name, expected) in [
+            ("x_1", vec![0, 2, 3]),
+            ("x_4", vec![2, 3, 4]),
+            ("x_2", vec![1, 3]),
+            ("unused", vec![]),
+        ] {
+            calculator.set_variable(name, 7.5).unwrap();
+            let changed: Vec<_> = graph
+                .update(&calculator, &[name])
+                .unwrap()
+                .into_iter()
+                .map(|(handle, _)| handle.index())
+                .collect();
+            assert_eq!(changed, expected, "{name}");
+            for (handle, expression) in handles.iter().zip(expressions) {
+                assert_eq!(
+                    graph.value(*handle),
+                    Some(calculator.parse_str(expression).unwrap())
+                );
+            }
+        }
+        // Redefining an expression variable is tracked by its name
+        calculator
+            .set_expression_variable("double", "3 * x_1")
+            .unwrap();
+        let changed = graph.update(&calculator, &["double"]).unwrap();
+        assert_eq!(changed, vec![(handles[2], 37.5)]);
+    }
+
+    // Test that failing expressions are evaluated again by the next update
+    #[test]
+    fn update_after_error() {
+        let mut calculator = Calculator::new();
+        let mut graph = EvaluationGraph::new();
+        let handle = graph.register("1 / a").unwrap();
+        assert!(graph.register("a + $").is_err());
+        assert!(graph.update(&calculator, &[]).is_err());
+        assert_eq!(graph.value(handle), None);
+        calculator.set_variable("a", 0.0).unwrap();
+        assert_eq!(
+            graph.update(&calculator, &["a"]),
+            Err(CalculatorError::DivisionByZero)
+        );
+        calculator.set_variable("b", 2.0).unwrap();
+        calculator.set_variable("a", 4.0).unwrap();
+        assert_eq!(
+            graph.update(&calculator, &["b"]).unwrap(),
+            vec![(handle, 0.25)]
+        );
+        assert_eq!(graph.value(handle), Some(0.25));
+    }
+}
@@ -0,0 +1,201 @@
+// Copyright © 2020-2021 HQS Quantum Simulations GmbH. All Rights Reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except
+// in compliance with the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the
+// License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either
+// express or implied. See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! expr module
+//!
+//! Composes reductions over lists of values whose length is only known when the expression
+//! is generated, e.g. `max[amp_1, amp_2, amp_3]`. The values are joined into a single
+//! reduction in square brackets, so the expression grows linearly with the number of values
+//! instead of nesting calls of `max(a, b)`.
+//!
+//! When all values are floats the result is computed directly and agrees with the Calculator.
+//! Empty lists give zero for [sum_of] and an expression that returns
+//! `CalculatorError::NotEnoughFunctionArguments` when evaluated for the other reductions.
+//!
+//! # Example
+//!
+//! ```
+//! use qoqo_calculator::expr::max_of;
+//! use qoqo_calculator::{Calculator, CalculatorFloat};
+//!
+//! let amplitudes = (1..=3).map(|index| CalculatorFloat::from(format!("amp_{index}")));
+//! let maximum = max_of(amplitudes);
+//! assert_eq!(maximum, CalculatorFloat::from("max[amp_1, amp_2, amp_3]"));
+//!
+//! let mut calculator = Calculator::new();
+//! calculator.set_variable("amp_1", 0.5).unwrap();
+//! calculator.set_variable("amp_2", 2.0).unwrap();
+//! calculator.set_variable("amp_3", 1.0).unwrap();
+//! assert_eq!(calculator.parse_get(maximum).unwrap(), 2.0);
+//! ```
+
+use crate::CalculatorFloat;
+use std::fmt::Write;
+
+/// Return the maximum of the values as `max[...]`.
+///
+/// # Arguments
+///
+/// * `values` - Values of the reduction
+///
+pub fn max_of(values: impl IntoIterator<Item = CalculatorFloat>) -> CalculatorFloat {
+    reduction("max", values)
+}
+
+/// Return the minimum of the values as `min[...]`.
+///
+/// # Arguments
+///
+/// * `values` - Values of the reduction
+///
+pub fn min_of(values: impl IntoIterator<Item = CalculatorFloat>) -> CalculatorFloat {
+    reduction("min", values)
+}
+
+/// Return the sum of the values as `sum[...]`, zero for no values.
+///
+/// # Arguments
+///
+/// * `values` - Values of the reduction
+///
+pub fn sum_of(values: impl IntoIterator<Item = CalculatorFloat>) -> CalculatorFloat {
+    reduction("sum", values)
+}
+
+/// Return the arithmetic mean of the values as `mean[...]`.
+///
+/// # Arguments
+///
+/// * `values` - Values of the reduction
+///
+pub fn mean_of(values: impl IntoIterator<Item = CalculatorFloat>) -> CalculatorFloat {
+    reduction("mean", values)
+}
+
+/// Compose the reduction `name[...]` of the values or compute it for floats.
+fn reduction(name: &str, values: impl IntoIterator<Item = CalculatorFloat>) -> CalculatorFloat {
+    let values: Vec<CalculatorFloat> = values.into_iter().collect();
+    let floats: Option<Vec<f64>> = values
+        .iter()
+        .map(|value| match value {
+            CalculatorFloat::Float(x) => Some(*x),
+            CalculatorFloat::Str(_) => None,
+        })
+        .collect();
+    // Combined from left to right like in the Calculator
+    match floats.as_deref() {
+        Some([]) if name == "sum" => return CalculatorFloat::Float(0.0),
+        Some([first, rest @ ..]) => {
+            let number_values = rest.len() + 1;
+            let rest = rest.iter();
+            return CalculatorFloat::Float(match name {
+                "max" => rest.fold(*first, |maximum, x| maximum.max(*x)),
+                "min" => rest.fold(*first, |minimum, x| minimum.min(*x)),
+                "sum" => rest.fold(*first, |sum, x| sum + x),
+                _ => rest.fold(*first, |sum, x| sum + x) / number_values as f64,
+            });
+        }
+        _ => (),
+    }
+    let mut expression = format!("{name}[");
+    for (index, value) in values.iter().enumerate() {
+        if index > 0 {
+            expression.push_str(", ");
+        }
+        // Writing into a String can not fail
+        let _ = write!(expression, "{value}");
+    }
+    expression.push(']');
+    CalculatorFloat::Str(expression)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{max_of, mean_of, min_of, sum_of};
+    use crate::{Calculator, CalculatorError, CalculatorFloat};
+
+    /// Return n symbolic values a_0, a_1, ... and a Calculator with a_i = (i * 7) % 13 - 6.
+    fn values(n: usize) -> (Vec<CalculatorFloat>, Calculator, Vec<f64>) {
+        let mut calculator = Calculator::new();
+        let mut numbers = Vec::with_capacity(n);
+        let values = (0..n)
+            .map(|index| {
+                let name = format!("a_{index}");
+                let number = ((index * 7) % 13) as f64 - 6.0;
+                calculator.set_variable(&name, number).unwrap();
+                numbers.push(number);
+                CalculatorFloat::from(name)
+            })
+            .collect();
+        (values, calculator, numbers)
+    }
+
+    // Test reductions of 0, 1 and 100 values through serialization and parse_get
+    #[test]
+    fn reductions_round_trip() {
+        for n in [1, 100] {
+            let (symbols, calculator, numbers) = values(n);
+            let sum: f64 = numbers.iter().sum();
+            for (reduction, expected) in [
+                (
+                    max_of(symbols.clone()),
+                    numbers.iter().copied().fold(f64::MIN, f64::max),
+                ),
+                (
+                    min_of(symbols.clone()),
+                    numbers.iter().copied().fold(f64::MAX, f64::min),
+                ),
+                (sum_of(symbols.clone()), sum),
+                (mean_of(symbols.clone()), sum / n as f64),
+            ] {
+                // Flat single reduction, the length grows linearly with the number of values
+                let expression = reduction.to_string();
+                let names_length: usize = symbols.iter().map(|x| x.to_string().len()).sum();
+                let name_length = expression.find('[').unwrap();
+                assert_eq!(
+                    expression.len(),
+                    name_length + 2 + names_length + 2 * (n - 1)
+                );
+                assert_eq!(expression.matches('[').count(), 1);
+                let json = serde_json::to_string(&reduction).unwrap();
+                let deserialized: CalculatorFloat = serde_json::from_str(&json).unwrap();
+                assert_eq!(deserialized, reduction);
+                let value = calculator.parse_get(deserialized).unwrap();
+                assert!((value - expected).abs() < 1e-12, "{expression}: {value}");
+            }
+        }
+        // Empty lists
+        let calculator = Calculator::new();
+        assert_eq!(sum_of(Vec::new()), CalculatorFloat::Float(0.0));
+        for reduction in [max_of(Vec::new()), min_of(Vec::new()), mean_of(Vec::new())] {
+            assert_eq!(
+                calculator.parse_get(reduction),
+                Err(CalculatorError::NotEnoughFunctionArguments)
+            );
+        }
+    }
+
+    // Test that float values are reduced directly and mixed values are composed
+    #[test]
+    fn float_reductions() {
+        let floats = || [2.0, -1.0, 5.0].map(CalculatorFloat::from);
+        assert_eq!(max_of(floats()), CalculatorFloat::Float(5.0));
+        assert_eq!(min_of(floats()), CalculatorFloat::Float(-1.0));
+        assert_eq!(sum_of(floats()), CalculatorFloat::Float(6.0));
+        assert_eq!(mean_of(floats()), CalculatorFloat::Float(2.0));
+        let mixed = max_of([CalculatorFloat::from(2.0), CalculatorFloat::from("x / 2")]);
+        assert_eq!(mixed, CalculatorFloat::from("max[2e0, x / 2]"));
+        let mut calculator = Calculator::new();
+        calculator.set_variable("x", 6.0).unwrap();
+        assert_eq!(calculator.parse_get(mixed).unwrap(), 3.0);
+    }
+}
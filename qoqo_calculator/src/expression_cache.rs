@@ -0,0 +1,564 @@
+// Copyright © 2020-2021 HQS Quantum Simulations GmbH. All Rights Reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except
+// in compliance with the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the
+// License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either
+// express or implied. See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! expression_cache module
+//!
+//! Provides the ExpressionCache struct storing compiled expressions that are shared
+//! between Calculators.
+
+use crate::calculator::{lookup_function, positioned_tokens, Token};
+use crate::{kernel, Calculator, CalculatorError};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, RwLock};
+
+/// Expression that has been lexed once and can be evaluated repeatedly.
+///
+/// The compiled structure only depends on the expression string.
+/// Variable values and function names are resolved when the expression is evaluated.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CompiledExpression {
+    /// Tokens of the expression in order of appearance
+    tokens: Vec<Token<'static>>,
+    /// Byte positions of the tokens in the expression
+    positions: Vec<usize>,
+    /// Expression the tokens are lexed from, for the source text in error messages
+    source: String,
+    /// Parenthesized subexpressions without variables that occur several times, by opening bracket
+    repeated_constants: Vec<RepeatedConstant>,
+}
+
+/// Parenthesized subexpression without variables whose source occurs several times in an expression.
+///
+/// The value of the first occurrence is reused for the later occurrences when the expression
+/// is evaluated, e.g. for `(2 * 3.141592653589793 / 1024)` in generated scripts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct RepeatedConstant {
+    /// Index of the opening bracket token
+    pub(crate) open: usize,
+    /// Index of the closing bracket token
+    pub(crate) close: usize,
+    /// Index of the value shared by all occurrences of the same source
+    pub(crate) slot: usize,
+}
+
+impl CompiledExpression {
+    /// Compile a string expression.
+    ///
+    /// # Arguments
+    ///
+    /// * `expression` - Expression that is compiled
+    ///
+    pub fn new(expression: &str) -> Self {
+        let (positions, tokens): (Vec<usize>, Vec<Token<'static>>) = positioned_tokens(expression)
+            .into_iter()
+            .map(|(position, token)| (position, token.into_owned()))
+            .unzip();
+        let repeated_constants = repeated_constants(expression, &tokens, &positions);
+        CompiledExpression {
+            tokens,
+            positions,
+            source: expression.to_string(),
+            repeated_constants,
+        }
+    }
+
+    /// Evaluate the expression with the variables of a Calculator.
+    ///
+    /// The result and the errors, e.g. for a division by zero or an unset variable, are
+    /// identical to evaluating the expression string with [Calculator::parse_str],
+    /// without lexing the expression again.
+    ///
+    /// # Arguments
+    ///
+    /// * `calculator` - Calculator providing the variables and functions
+    ///
+    pub fn evaluate(&self, calculator: &Calculator) -> Result<f64, CalculatorError> {
+        calculator.evaluate_compiled(self)
+    }
+
+    /// Evaluate the expression for every value of a variable, e.g. for a time series.
+    ///
+    /// The values of the other variables are taken from the Calculator, the variable overlays
+    /// a set variable of the same name. The results are written to `out` and are identical to
+    /// evaluating the expression for each value with [Calculator::parse_str].
+    ///
+    /// Subexpressions that do not depend on the variable are evaluated once and the
+    /// remaining operations are applied to chunks of values without allocating per value.
+    /// Expressions that can not be evaluated this way, e.g. expressions with assignments,
+    /// interpolation tables or variables defined by expressions, are evaluated value by value.
+    ///
+    /// # Arguments
+    ///
+    /// * `variable` - Name of the variable taking the values
+    /// * `values` - Values of the variable
+    /// * `calculator` - Calculator providing the other variables
+    /// * `out` - Buffer for the results, must have the length of `values`
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` - The results have been written to `out`
+    /// * `Err(CalculatorError::OutputLengthMismatch)` - `out` does not have the length of `values`
+    /// * `Err(CalculatorError::SweepPointError)` - Evaluation failed, contains the index of the first
+    ///   failing value and the error, the contents of `out` are unspecified
+    ///
+    pub fn evaluate_over(
+        &self,
+        variable: &str,
+        values: &[f64],
+        calculator: &Calculator,
+        out: &mut [f64],
+    ) -> Result<(), CalculatorError> {
+        kernel::evaluate_over(self, variable, values, calculator, out)
+    }
+
+    /// Return the tokens of the compiled expression.
+    pub(crate) fn tokens(&self) -> &[Token<'static>] {
+        &self.tokens
+    }
+
+    /// Return the byte positions of the tokens in the expression.
+    pub(crate) fn positions(&self) -> &[usize] {
+        &self.positions
+    }
+
+    /// Return the expression the tokens are lexed from.
+    pub(crate) fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// Return the repeated constant subexpressions ordered by their opening bracket.
+    pub(crate) fn repeated_constants(&self) -> &[RepeatedConstant] {
+        &self.repeated_constants
+    }
+}
+
+/// Find the parenthesized subexpressions whose source occurs several times in the expression
+/// and that only contain numbers, operators, reductions and functions of the function table.
+///
+/// Their values do not change while the expression is evaluated. Function calls themselves
+/// are not reused, only subexpressions in brackets.
+fn repeated_constants(
+    expression: &str,
+    tokens: &[Token<'static>],
+    positions: &[usize],
+) -> Vec<RepeatedConstant> {
+    // Open brackets with their index, whether they belong to a function call and whether
+    // the subexpression is constant so far
+    let mut open_brackets: Vec<(usize, bool, bool)> = Vec::new();
+    let mut occurrences: HashMap<&str, Vec<(usize, usize)>> = HashMap::new();
+    for (index, token) in tokens.iter().enumerate() {
+        match token {
+            Token::BracketOpen => open_brackets.push((index, false, true)),
+            Token::Function(name) => {
+                open_brackets.push((index, true, lookup_function(name).is_ok()))
+            }
+            Token::BracketClose => {
+                let Some((open, is_function, constant)) = open_brackets.pop() else {
+                    continue;
+                };
+                if let Some(enclosing) = open_brackets.last_mut() {
+                    enclosing.2 &= constant;
+                }
+                if constant && !is_function {
+                    let source = &expression[positions[open]..positions[index] + 1];
+                    occurrences.entry(source).or_default().push((open, index));
+                }
+            }
+            Token::Number(_)
+            | Token::ImaginaryNumber(_)
+            | Token::Plus
+            | Token::Minus
+            | Token::Multiply
+            | Token::Divide
+            | Token::Modulo
+            | Token::Power
+            | Token::Comparison(_)
+            | Token::Factorial
+            | Token::DoubleFactorial
+            | Token::Comma
+            | Token::Reduction(_)
+            | Token::ListOpen
+            | Token::ListClose => (),
+            _ => {
+                if let Some(enclosing) = open_brackets.last_mut() {
+                    enclosing.2 = false;
+                }
+            }
+        }
+    }
+    // Slots are numbered by the first occurrence, so compiling is deterministic
+    let mut spans: Vec<Vec<(usize, usize)>> = occurrences
+        .into_values()
+        .filter(|spans| spans.len() > 1)
+        .collect();
+    spans.sort_unstable_by_key(|spans| spans[0].0);
+    let mut repeated: Vec<RepeatedConstant> = spans
+        .into_iter()
+        .enumerate()
+        .flat_map(|(slot, spans)| {
+            spans
+                .into_iter()
+                .map(move |(open, close)| RepeatedConstant { open, close, slot })
+        })
+        .collect();
+    repeated.sort_unstable_by_key(|constant| constant.open);
+    repeated
+}
+
+/// Default maximal number of compiled expressions stored in an [ExpressionCache].
+pub const DEFAULT_EXPRESSION_CACHE_CAPACITY: usize = 4096;
+
+/// Cache of compiled expressions keyed by the expression string.
+///
+/// Cloning an ExpressionCache (or a Calculator holding it) shares the cached
+/// compiled expressions, the capacity and the hit and miss counters between the clones.
+/// The cache can be used from several threads at the same time.
+///
+/// The number of cached expressions is bounded by the capacity
+/// ([DEFAULT_EXPRESSION_CACHE_CAPACITY] by default), so evaluating many generated expressions
+/// does not grow the cache without limit. When a new expression is compiled into a full cache,
+/// the least recently used half of the entries is removed. A capacity of zero disables caching,
+/// every expression is compiled when it is used.
+///
+/// Compiling an expression only lexes it and consults the fixed table of built-in functions.
+/// Functions registered on a Calculator, i.e. interpolation tables, libraries and the unknown
+/// function handler, are resolved when an expression is evaluated. Registering or removing
+/// them does not invalidate cached expressions and the cache never has to be cleared for it.
+#[derive(Clone)]
+pub struct ExpressionCache {
+    /// Compiled expressions keyed by the expression string
+    expressions: Arc<RwLock<HashMap<String, CacheEntry>>>,
+    /// Maximal number of cached expressions
+    capacity: Arc<AtomicUsize>,
+    /// Counter ordering the uses of the cached expressions
+    clock: Arc<AtomicU64>,
+    /// Number of lookups that found a compiled expression
+    hits: Arc<AtomicUsize>,
+    /// Number of lookups that had to compile the expression
+    misses: Arc<AtomicUsize>,
+}
+
+/// Compiled expression in an [ExpressionCache] with the time of its last use.
+struct CacheEntry {
+    /// Compiled expression
+    compiled: Arc<CompiledExpression>,
+    /// Value of the clock of the cache when the expression was last used
+    last_use: AtomicU64,
+}
+
+impl Default for ExpressionCache {
+    fn default() -> Self {
+        Self::with_capacity(DEFAULT_EXPRESSION_CACHE_CAPACITY)
+    }
+}
+
+impl fmt::Debug for ExpressionCache {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ExpressionCache")
+            .field("len", &self.len())
+            .field("capacity", &self.capacity())
+            .field("hits", &self.hits())
+            .field("misses", &self.misses())
+            .finish()
+    }
+}
+
+impl ExpressionCache {
+    /// Create new empty ExpressionCache with the default capacity.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create new empty ExpressionCache storing at most `capacity` compiled expressions.
+    ///
+    /// # Arguments
+    ///
+    /// * `capacity` - Maximal number of cached expressions, zero disables caching
+    ///
+    pub fn with_capacity(capacity: usize) -> Self {
+        ExpressionCache {
+            expressions: Arc::new(RwLock::new(HashMap::new())),
+            capacity: Arc::new(AtomicUsize::new(capacity)),
+            clock: Arc::new(AtomicU64::new(0)),
+            hits: Arc::new(AtomicUsize::new(0)),
+            misses: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    /// Return the compiled expression, compiling and caching it when it is not cached yet.
+    ///
+    /// # Arguments
+    ///
+    /// * `expression` - Expression that is looked up
+    ///
+    pub fn get_or_compile(&self, expression: &str) -> Arc<CompiledExpression> {
+        if let Some(entry) = self
+            .expressions
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .get(expression)
+        {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            entry.last_use.store(self.tick(), Ordering::Relaxed);
+            return entry.compiled.clone();
+        }
+        let compiled = Arc::new(CompiledExpression::new(expression));
+        let capacity = self.capacity();
+        if capacity == 0 {
+            self.misses.fetch_add(1, Ordering::Relaxed);
+            return compiled;
+        }
+        let mut expressions = self
+            .expressions
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        // Another thread may have compiled the same expression in the meantime
+        match expressions.get(expression) {
+            Some(existing) => {
+                self.hits.fetch_add(1, Ordering::Relaxed);
+                existing.last_use.store(self.tick(), Ordering::Relaxed);
+                existing.compiled.clone()
+            }
+            None => {
+                self.misses.fetch_add(1, Ordering::Relaxed);
+                if expressions.len() >= capacity {
+                    evict_least_recently_used(&mut expressions, capacity / 2);
+                }
+                expressions.insert(
+                    expression.to_string(),
+                    CacheEntry {
+                        compiled: compiled.clone(),
+                        last_use: AtomicU64::new(self.tick()),
+                    },
+                );
+                compiled
+            }
+        }
+    }
+
+    /// Return the next value of the clock ordering the uses of cached expressions.
+    fn tick(&self) -> u64 {
+        self.clock.fetch_add(1, Ordering::Relaxed)
+    }
+
+    /// Return the number of cached expressions.
+    pub fn len(&self) -> usize {
+        self.expressions
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .len()
+    }
+
+    /// Return true when no expression is cached.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Return the maximal number of cached expressions.
+    pub fn capacity(&self) -> usize {
+        self.capacity.load(Ordering::Relaxed)
+    }
+
+    /// Set the maximal number of cached expressions for every Calculator sharing the cache.
+    ///
+    /// When more expressions are cached, the least recently used ones are removed.
+    ///
+    /// # Arguments
+    ///
+    /// * `capacity` - Maximal number of cached expressions, zero disables caching
+    ///
+    pub fn set_capacity(&self, capacity: usize) {
+        self.capacity.store(capacity, Ordering::Relaxed);
+        let mut expressions = self
+            .expressions
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        if expressions.len() > capacity {
+            evict_least_recently_used(&mut expressions, capacity);
+        }
+    }
+
+    /// Return the number of lookups that found a cached expression.
+    pub fn hits(&self) -> usize {
+        self.hits.load(Ordering::Relaxed)
+    }
+
+    /// Return the number of lookups that compiled a new expression.
+    pub fn misses(&self) -> usize {
+        self.misses.load(Ordering::Relaxed)
+    }
+
+    /// Remove all cached expressions for every Calculator sharing the cache.
+    pub fn clear(&self) {
+        self.expressions
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .clear();
+    }
+}
+
+/// Remove cached expressions until only the `keep` most recently used ones are left.
+///
+/// Removing many entries at once keeps the cost of eviction constant per compiled expression
+/// on average.
+fn evict_least_recently_used(expressions: &mut HashMap<String, CacheEntry>, keep: usize) {
+    if keep == 0 {
+        expressions.clear();
+        return;
+    }
+    let mut uses: Vec<u64> = expressions
+        .values()
+        .map(|entry| entry.last_use.load(Ordering::Relaxed))
+        .collect();
+    if uses.len() <= keep {
+        return;
+    }
+    // The clock values are unique, so exactly `keep` entries are used at or after the threshold
+    let (_, threshold, _) = uses.select_nth_unstable_by(keep - 1, |a, b| b.cmp(a));
+    let threshold = *threshold;
+    expressions.retain(|_, entry| entry.last_use.load(Ordering::Relaxed) >= threshold);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CompiledExpression, ExpressionCache};
+    use crate::calculator::Token;
+    use crate::{Calculator, CalculatorError};
+    use std::sync::Arc;
+
+    // Test that compiled expressions evaluate like parse_str, including the errors
+    #[test]
+    fn evaluate_like_parse_str() {
+        let mut calculator = Calculator::new();
+        calculator.set_variable("a", 0.5).unwrap();
+        calculator.set_variable("b", -2.0).unwrap();
+        let expressions = [
+            "a + b * 3",
+            "sin(a) ^ 2 + cos(a) ^ 2",
+            "max(a, b, 1) - min[a, b]",
+            "(2 * 3.141592653589793 / 1024) * a + (2 * 3.141592653589793 / 1024)",
+            "1 / (a - 0.5)",
+            "a / 0",
+            "unknown + 1",
+            "c",
+            "unknown_function(a)",
+            "5! / 3!!",
+            "-b ^ 0.5",
+        ];
+        for expression in expressions {
+            let compiled = calculator.compile(expression).unwrap();
+            assert_eq!(
+                compiled.evaluate(&calculator),
+                calculator.parse_str(expression),
+                "{expression}"
+            );
+        }
+        // Changed variables are seen by the compiled expression
+        let compiled = calculator.compile("a * b").unwrap();
+        assert_eq!(compiled.evaluate(&calculator), Ok(-1.0));
+        calculator.set_variable("a", 2.0).unwrap();
+        assert_eq!(compiled.evaluate(&calculator), Ok(-4.0));
+        assert_eq!(
+            calculator.compile("1 / 0").unwrap().evaluate(&calculator),
+            Err(CalculatorError::DivisionByZero)
+        );
+        assert_eq!(
+            calculator.compile("c + 1").unwrap().evaluate(&calculator),
+            Err(CalculatorError::VariableNotSet {
+                name: "c".to_string()
+            })
+        );
+    }
+
+    // Test that invalid expressions are rejected when they are compiled
+    #[test]
+    fn compile_invalid() {
+        let calculator = Calculator::new();
+        for expression in ["a = 1; a", "(1 + 2", "1 $ 2", "a += 1"] {
+            assert!(matches!(
+                calculator.compile(expression),
+                Err(CalculatorError::InvalidExpression { .. })
+            ));
+        }
+    }
+
+    // Test that compiling an expression keeps all tokens
+    #[test]
+    fn compiled_expression() {
+        let compiled = CompiledExpression::new("a + 1");
+        assert_eq!(
+            compiled.tokens(),
+            &[Token::Variable("a".into()), Token::Plus, Token::Number(1.0)]
+        );
+        assert_eq!(compiled.positions(), &[0, 2, 4]);
+    }
+
+    // Test that a second lookup is a cache hit returning the same compiled expression
+    #[test]
+    fn get_or_compile() {
+        let cache = ExpressionCache::new();
+        assert!(cache.is_empty());
+        let first = cache.get_or_compile("a + 1");
+        assert_eq!((cache.hits(), cache.misses()), (0, 1));
+        let second = cache.get_or_compile("a + 1");
+        assert_eq!((cache.hits(), cache.misses()), (1, 1));
+        assert!(Arc::ptr_eq(&first, &second));
+        assert_eq!(cache.len(), 1);
+
+        let clone = cache.clone();
+        clone.clear();
+        assert!(cache.is_empty());
+        assert_eq!(
+            format!("{cache:?}"),
+            "ExpressionCache { len: 0, capacity: 4096, hits: 1, misses: 1 }"
+        );
+    }
+
+    // Test that the cache is bounded and keeps the recently used expressions
+    #[test]
+    fn bounded_cache() {
+        let cache = ExpressionCache::with_capacity(100);
+        let mut calculator = Calculator::with_shared_cache(cache.clone());
+        calculator.set_variable("a", 1.0).unwrap();
+        let frequent = cache.get_or_compile("a + 1");
+        for index in 0..100_000 {
+            assert_eq!(
+                calculator.parse_str(&format!("{index} + 1")),
+                Ok(index as f64 + 1.0)
+            );
+            assert!(cache.len() <= 100);
+            if index % 10 == 0 {
+                assert!(Arc::ptr_eq(&frequent, &cache.get_or_compile("a + 1")));
+            }
+        }
+        assert_eq!(cache.misses(), 100_001);
+
+        cache.set_capacity(10);
+        assert_eq!(cache.len(), 10);
+        assert!(Arc::ptr_eq(
+            &frequent,
+            &calculator.expression_cache().get_or_compile("a + 1")
+        ));
+
+        // A capacity of zero compiles every expression without caching it
+        calculator.expression_cache().set_capacity(0);
+        assert!(cache.is_empty());
+        assert_eq!(calculator.parse_str("a + 1"), Ok(2.0));
+        assert!(cache.is_empty());
+        assert_eq!(
+            ExpressionCache::new().capacity(),
+            crate::DEFAULT_EXPRESSION_CACHE_CAPACITY
+        );
+    }
+}
@@ -0,0 +1,203 @@
+// Copyright © 2020-2021 HQS Quantum Simulations GmbH. All Rights Reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except
+// in compliance with the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the
+// License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either
+// express or implied. See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! interpolation module
+//!
+//! Provides InterpolationTable struct for piecewise linear interpolation of sampled
+//! (x, y) tables that can be called by name in expressions parsed by the Calculator.
+
+use crate::CalculatorError;
+
+/// Behavior of an InterpolationTable for arguments outside the range of its x values.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OutOfRange {
+    /// Return the y value of the closest end of the table
+    #[default]
+    Clamp,
+    /// Return CalculatorError::InterpolationOutOfRange
+    Error,
+}
+
+/// Table of sampled (x, y) values evaluated by piecewise linear interpolation.
+///
+/// The x values are strictly increasing and the table contains at least two points.
+#[derive(Debug, Clone, PartialEq)]
+pub struct InterpolationTable {
+    /// Strictly increasing x values
+    xs: Vec<f64>,
+    /// y values at the x values
+    ys: Vec<f64>,
+    /// Behavior for arguments outside of the x values
+    out_of_range: OutOfRange,
+}
+
+impl InterpolationTable {
+    /// Create a new InterpolationTable.
+    ///
+    /// # Arguments
+    ///
+    /// * `xs` - Strictly increasing finite x values
+    /// * `ys` - Finite y values at the x values
+    /// * `out_of_range` - Behavior for arguments outside of the x values
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(InterpolationTable)` - The table
+    /// * `Err(CalculatorError::InvalidInterpolationTable)` - The values do not form a valid table
+    ///
+    pub fn new(
+        xs: Vec<f64>,
+        ys: Vec<f64>,
+        out_of_range: OutOfRange,
+    ) -> Result<Self, CalculatorError> {
+        let invalid = |msg: &'static str| Err(CalculatorError::InvalidInterpolationTable { msg });
+        if xs.len() != ys.len() {
+            return invalid("x and y values have different lengths");
+        }
+        if xs.len() < 2 {
+            return invalid("at least two points are needed");
+        }
+        if xs.iter().chain(ys.iter()).any(|value| !value.is_finite()) {
+            return invalid("all values must be finite");
+        }
+        if xs.windows(2).any(|pair| pair[0] >= pair[1]) {
+            return invalid("x values must be strictly increasing");
+        }
+        Ok(InterpolationTable {
+            xs,
+            ys,
+            out_of_range,
+        })
+    }
+
+    /// Return the x values of the table.
+    pub fn xs(&self) -> &[f64] {
+        &self.xs
+    }
+
+    /// Return the y values of the table.
+    pub fn ys(&self) -> &[f64] {
+        &self.ys
+    }
+
+    /// Return the behavior for arguments outside of the x values.
+    pub fn out_of_range(&self) -> OutOfRange {
+        self.out_of_range
+    }
+
+    /// Interpolate the table linearly at an argument.
+    ///
+    /// # Arguments
+    ///
+    /// * `x` - Argument at which the table is evaluated, NaN returns NaN
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(f64)` - The interpolated value, the y value of a knot is returned exactly
+    /// * `Err(CalculatorError::InterpolationOutOfRange)` - The argument is outside of the table and out_of_range is Error
+    ///
+    pub fn interpolate(&self, x: f64) -> Result<f64, CalculatorError> {
+        if x.is_nan() {
+            return Ok(f64::NAN);
+        }
+        let last = self.xs.len() - 1;
+        // Number of x values smaller or equal to the argument
+        let upper = self.xs.partition_point(|knot| *knot <= x);
+        if upper == 0 || (upper > last && x > self.xs[last]) {
+            return match self.out_of_range {
+                OutOfRange::Clamp if upper == 0 => Ok(self.ys[0]),
+                OutOfRange::Clamp => Ok(self.ys[last]),
+                OutOfRange::Error => Err(CalculatorError::InterpolationOutOfRange {
+                    value: x,
+                    lower: self.xs[0],
+                    upper: self.xs[last],
+                }),
+            };
+        }
+        if upper > last {
+            return Ok(self.ys[last]);
+        }
+        let (x0, x1) = (self.xs[upper - 1], self.xs[upper]);
+        let (y0, y1) = (self.ys[upper - 1], self.ys[upper]);
+        Ok(y0 + (x - x0) / (x1 - x0) * (y1 - y0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{InterpolationTable, OutOfRange};
+    use crate::CalculatorError;
+
+    fn table(out_of_range: OutOfRange) -> InterpolationTable {
+        InterpolationTable::new(vec![0.0, 1.0, 3.0], vec![2.0, 4.0, 0.0], out_of_range).unwrap()
+    }
+
+    // Test interpolation between and at the knots
+    #[test]
+    fn interpolate_interior() {
+        let table = table(OutOfRange::Error);
+        assert_eq!(table.interpolate(0.5), Ok(3.0));
+        assert_eq!(table.interpolate(2.0), Ok(2.0));
+        assert_eq!(table.interpolate(0.0), Ok(2.0));
+        assert_eq!(table.interpolate(1.0), Ok(4.0));
+        assert_eq!(table.interpolate(3.0), Ok(0.0));
+        assert!(table.interpolate(f64::NAN).unwrap().is_nan());
+    }
+
+    // Test both behaviors for arguments outside of the table
+    #[test]
+    fn interpolate_out_of_range() {
+        let table_clamp = table(OutOfRange::Clamp);
+        assert_eq!(table_clamp.interpolate(-1.0), Ok(2.0));
+        assert_eq!(table_clamp.interpolate(f64::INFINITY), Ok(0.0));
+        let table_error = table(OutOfRange::Error);
+        assert_eq!(
+            table_error.interpolate(-1.0),
+            Err(CalculatorError::InterpolationOutOfRange {
+                value: -1.0,
+                lower: 0.0,
+                upper: 3.0
+            })
+        );
+        assert!(table_error.interpolate(3.5).is_err());
+    }
+
+    // Test the validation of the table values
+    #[test]
+    fn invalid_tables() {
+        let invalid =
+            |xs: Vec<f64>, ys: Vec<f64>| match InterpolationTable::new(xs, ys, OutOfRange::Clamp) {
+                Err(CalculatorError::InvalidInterpolationTable { msg }) => msg,
+                other => panic!("unexpected {other:?}"),
+            };
+        assert_eq!(
+            invalid(vec![0.0, 1.0], vec![0.0]),
+            "x and y values have different lengths"
+        );
+        assert_eq!(
+            invalid(vec![0.0], vec![0.0]),
+            "at least two points are needed"
+        );
+        assert_eq!(
+            invalid(vec![0.0, f64::NAN], vec![0.0, 1.0]),
+            "all values must be finite"
+        );
+        assert_eq!(
+            invalid(vec![0.0, 0.0], vec![0.0, 1.0]),
+            "x values must be strictly increasing"
+        );
+        assert_eq!(
+            invalid(vec![1.0, 0.0], vec![0.0, 1.0]),
+            "x values must be strictly increasing"
+        );
+    }
+}
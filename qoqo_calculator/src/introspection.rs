@@ -0,0 +1,282 @@
+// Copyright © 2020-2021 HQS Quantum Simulations GmbH. All Rights Reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except
+// in compliance with the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the
+// License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either
+// express or implied. See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! introspection module
+//!
+//! Describes the functions, constants, operators and error kinds of the Calculator for tooling,
+//! e.g. documentation generators or autocompletion in expression editors.
+//! The function and constant lists are built from the tables used by the parser.
+
+use crate::calculator::{constant_descriptions, function_descriptions, ArgumentCount};
+use crate::ERROR_KINDS;
+use std::sync::OnceLock;
+
+/// Function that can be used in expressions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FunctionInfo {
+    /// Name of the function
+    pub name: &'static str,
+    /// Number of arguments, the minimal number for variadic functions
+    pub arity: usize,
+    /// True when the function takes more than `arity` arguments, e.g. `max(a, b, c)`
+    pub variadic: bool,
+    /// Short description
+    pub description: &'static str,
+}
+
+/// Constant that can be used in expressions without setting it as a variable.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ConstantInfo {
+    /// Name of the constant
+    pub name: &'static str,
+    /// Value of the constant
+    pub value: f64,
+    /// Short description
+    pub description: &'static str,
+}
+
+/// Associativity of an operator.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Associativity {
+    /// Chains are evaluated from left to right, `a - b - c` is `(a - b) - c`
+    Left,
+    /// The operator can not be chained, e.g. `a^b^c` is not valid
+    NonAssociative,
+}
+
+/// Operator that can be used in expressions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OperatorInfo {
+    /// Symbol of the operator
+    pub symbol: &'static str,
+    /// Binding strength, operators with higher precedence are applied first
+    pub precedence: u8,
+    /// Associativity of the operator
+    pub associativity: Associativity,
+    /// True for prefix operators with one operand
+    pub unary: bool,
+}
+
+/// Operators of the Calculator in order of increasing precedence.
+///
+/// Assignments are only allowed at the start of a statement. Statements are separated by `;`.
+static OPERATORS: [OperatorInfo; 20] = [
+    operator("=", 0, Associativity::NonAssociative, false),
+    operator("+=", 0, Associativity::NonAssociative, false),
+    operator("-=", 0, Associativity::NonAssociative, false),
+    operator("*=", 0, Associativity::NonAssociative, false),
+    operator("/=", 0, Associativity::NonAssociative, false),
+    operator("<", 1, Associativity::Left, false),
+    operator(">", 1, Associativity::Left, false),
+    operator("<=", 1, Associativity::Left, false),
+    operator(">=", 1, Associativity::Left, false),
+    operator("==", 1, Associativity::Left, false),
+    operator("!=", 1, Associativity::Left, false),
+    operator("+", 2, Associativity::Left, false),
+    operator("-", 2, Associativity::Left, false),
+    operator("*", 3, Associativity::Left, false),
+    operator("/", 3, Associativity::Left, false),
+    operator("%", 3, Associativity::Left, false),
+    operator("^", 4, Associativity::NonAssociative, false),
+    operator("**", 4, Associativity::NonAssociative, false),
+    operator("+", 5, Associativity::NonAssociative, true),
+    operator("-", 5, Associativity::NonAssociative, true),
+];
+
+/// Create an OperatorInfo in a static.
+const fn operator(
+    symbol: &'static str,
+    precedence: u8,
+    associativity: Associativity,
+    unary: bool,
+) -> OperatorInfo {
+    OperatorInfo {
+        symbol,
+        precedence,
+        associativity,
+        unary,
+    }
+}
+
+/// Return the functions that can be used in expressions, sorted by name.
+///
+/// Contains the functions of the parser's function table and the functions taking quoted
+/// strings (`assert`, `count_vars`, `sum_vars`). Registered interpolation tables and functions
+/// of an unknown function handler are not included.
+pub fn functions() -> &'static [FunctionInfo] {
+    static FUNCTIONS: OnceLock<Vec<FunctionInfo>> = OnceLock::new();
+    FUNCTIONS.get_or_init(|| {
+        let mut functions: Vec<FunctionInfo> = function_descriptions()
+            .map(|(name, argument_count, description)| {
+                let (arity, variadic) = match argument_count {
+                    ArgumentCount::Fixed(number) => (number, false),
+                    ArgumentCount::Variadic => (2, true),
+                };
+                FunctionInfo {
+                    name,
+                    arity,
+                    variadic,
+                    description,
+                }
+            })
+            .collect();
+        functions.sort_by_key(|function| function.name);
+        functions
+    })
+}
+
+/// Return the built-in constants, sorted by name.
+///
+/// Variables of a Calculator with the same name take precedence over the constants,
+/// the list can be used to check variable names for collisions.
+pub fn constants() -> &'static [ConstantInfo] {
+    static CONSTANTS: OnceLock<Vec<ConstantInfo>> = OnceLock::new();
+    CONSTANTS.get_or_init(|| {
+        constant_descriptions()
+            .iter()
+            .map(|(name, value, description)| ConstantInfo {
+                name,
+                value: *value,
+                description,
+            })
+            .collect()
+    })
+}
+
+/// Return the names of all kinds of [CalculatorError](crate::CalculatorError),
+/// as returned by [CalculatorError::kind](crate::CalculatorError::kind).
+pub fn error_kinds() -> &'static [&'static str] {
+    &ERROR_KINDS
+}
+
+/// Return the operators of the Calculator in order of increasing precedence.
+pub fn operators() -> &'static [OperatorInfo] {
+    &OPERATORS
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{math, Calculator, CalculatorError};
+
+    // Test that every listed function is known to the parser with the listed arity
+    #[test]
+    fn functions_are_parsed() {
+        let mut calculator = Calculator::new();
+        calculator.register_library("a", "x=1").unwrap();
+        assert!(functions().windows(2).all(|w| w[0].name < w[1].name));
+        for function in functions() {
+            let mut arguments: Vec<&str> = ["0.5", "0.25"][..function.arity].to_vec();
+            match function.name {
+                "count_vars" | "sum_vars" => arguments = vec!["'a'"],
+                "assert" => arguments = vec!["1", "'message'"],
+                _ => (),
+            }
+            let expression = format!("{}({})", function.name, arguments.join(", "));
+            // include is a statement and only allowed where variables can be assigned
+            let result = match function.name {
+                "include" => calculator.parse_str_assign("include('a')"),
+                _ => calculator.parse_str(&expression),
+            };
+            match result {
+                Ok(_) | Err(CalculatorError::InvalidFunctionArgument { .. }) => (),
+                Err(error) => panic!("{expression}: {error:?}"),
+            }
+            assert!(!function.description.is_empty());
+        }
+        assert!(functions().iter().any(|function| function.name == "assert"));
+        assert!(functions().iter().any(|function| function.name == "erf"));
+    }
+
+    // Test that every listed constant is resolved by the parser
+    #[test]
+    fn constants_are_parsed() {
+        let calculator = Calculator::new();
+        assert!(constants().windows(2).all(|w| w[0].name < w[1].name));
+        for constant in constants() {
+            assert_eq!(calculator.parse_str(constant.name), Ok(constant.value));
+            assert!(!constant.description.is_empty());
+        }
+        assert!(constants().iter().any(|constant| constant.name == "pi"));
+    }
+
+    // Test that the operator table matches the precedence and associativity of the parser
+    #[test]
+    fn operators_match_parser() {
+        let calculator = Calculator::new();
+        let apply = |symbol: &str, x: f64, y: f64| match symbol {
+            "+" => x + y,
+            "-" => x - y,
+            "*" => x * y,
+            "/" => x / y,
+            "%" => x % y,
+            "<" => f64::from(x < y),
+            ">" => f64::from(x > y),
+            "<=" => f64::from(x <= y),
+            ">=" => f64::from(x >= y),
+            "==" => f64::from(x == y),
+            "!=" => f64::from(x != y),
+            _ => math::powf(x, y),
+        };
+        let binary: Vec<&OperatorInfo> = operators()
+            .iter()
+            .filter(|operator| !operator.unary && operator.precedence > 0)
+            .collect();
+        for first in binary.iter() {
+            for second in binary.iter() {
+                let expression = format!("3 {} 2 {} 1.5", first.symbol, second.symbol);
+                let expected = if first.precedence > second.precedence {
+                    Some(apply(second.symbol, apply(first.symbol, 3.0, 2.0), 1.5))
+                } else if first.precedence < second.precedence {
+                    Some(apply(first.symbol, 3.0, apply(second.symbol, 2.0, 1.5)))
+                } else if first.associativity == Associativity::Left {
+                    Some(apply(second.symbol, apply(first.symbol, 3.0, 2.0), 1.5))
+                } else {
+                    None
+                };
+                match (calculator.parse_str(&expression), expected) {
+                    (Ok(value), Some(expected)) => assert_eq!(value, expected, "{expression}"),
+                    (Err(_), None) => (),
+                    (result, expected) => panic!("{expression}: {result:?} != {expected:?}"),
+                }
+            }
+        }
+        // Prefix operators bind stronger than powers and can not be chained
+        assert_eq!(calculator.parse_str("-2^2"), Ok(4.0));
+        assert_eq!(calculator.parse_str("2^-2"), Ok(0.25));
+        assert!(calculator.parse_str("--2").is_err());
+        // Assignments can not be chained
+        assert!(Calculator::new().parse_str_assign("a=b=1").is_err());
+    }
+
+    // Test that the error kinds are distinct and name the variants
+    #[test]
+    fn error_kinds_are_variants() {
+        let kinds = error_kinds();
+        for (index, kind) in kinds.iter().enumerate() {
+            assert!(!kinds[index + 1..].contains(kind), "{kind}");
+        }
+        for error in [
+            CalculatorError::DivisionByZero,
+            CalculatorError::FunctionNotFound {
+                fct: "f".to_string(),
+            },
+            CalculatorError::AssertionFailed {
+                message: "m".to_string(),
+                position: 0,
+            },
+        ] {
+            assert!(format!("{error:?}").starts_with(error.kind()));
+            assert!(kinds.contains(&error.kind()));
+        }
+    }
+}
@@ -0,0 +1,446 @@
+// Copyright © 2020-2021 HQS Quantum Simulations GmbH. All Rights Reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except
+// in compliance with the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the
+// License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either
+// express or implied. See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! kernel module
+//!
+//! Evaluates a compiled expression for many values of one variable, e.g. a time series.
+//!
+//! The expression is translated once into a kernel. Subexpressions that do not depend on the
+//! variable are evaluated to constants with the variables of the Calculator, the remaining
+//! operations are applied to chunks of values. The arithmetic operations are plain loops over
+//! slices that the compiler can vectorize, and no memory is allocated per value.
+//!
+//! The kernel performs the same floating point operations in the same order as the parser,
+//! so the results are identical to evaluating the expression value by value.
+//! Expressions the kernel does not cover (several statements, assignments, interpolation
+//! tables, expression variables, ...) and chunks in which an operation fails are evaluated
+//! value by value with the parser, so the same errors are reported.
+
+use crate::calculator::{lookup_function, FunctionImplementation};
+use crate::derivative::ExpressionTree;
+use crate::{math, Calculator, CalculatorError, ComparisonOperator, CompiledExpression};
+
+/// Number of values evaluated by one pass over the kernel
+const CHUNK: usize = 256;
+
+/// Argument of a kernel operation.
+#[derive(Debug, Clone, Copy)]
+enum Argument {
+    /// Value that does not depend on the variable
+    Constant(f64),
+    /// Values of the variable
+    Input,
+    /// Results of an earlier operation of the kernel
+    Register(usize),
+}
+
+/// Operation of the kernel, unary operations only use their first argument.
+#[derive(Clone, Copy)]
+enum Operation {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Remainder,
+    Power,
+    Compare(ComparisonOperator),
+    Function(FunctionImplementation),
+}
+
+impl Operation {
+    /// Apply the operation to two numbers as done by the parser.
+    fn apply(self, left: f64, right: f64) -> Result<f64, CalculatorError> {
+        match self {
+            Operation::Add => Ok(left + right),
+            Operation::Subtract => Ok(left - right),
+            Operation::Multiply => Ok(left * right),
+            Operation::Divide if right == 0.0 => Err(CalculatorError::DivisionByZero),
+            Operation::Divide => Ok(left / right),
+            Operation::Remainder if right == 0.0 => Err(CalculatorError::DivisionByZero),
+            Operation::Remainder => Ok(left % right),
+            Operation::Power => Ok(math::powf(left, right)),
+            Operation::Compare(operator) => Ok(operator.compare(left, right)),
+            Operation::Function(function) => match function {
+                FunctionImplementation::Unary(function) => Ok(function(left)),
+                FunctionImplementation::Binary(function)
+                | FunctionImplementation::Variadic(function) => Ok(function(left, right)),
+                FunctionImplementation::FallibleUnary(function) => function(left),
+                FunctionImplementation::FallibleBinary(function) => function(left, right),
+            },
+        }
+    }
+}
+
+/// Operands of a chunk.
+#[derive(Clone, Copy)]
+enum Operand<'a> {
+    Scalar(f64),
+    Values(&'a [f64]),
+}
+
+/// Expression translated into a sequence of operations on chunks of values.
+///
+/// Operation `i` writes the register `i` and only reads registers of earlier operations.
+struct Kernel {
+    operations: Vec<(Operation, [Argument; 2])>,
+    result: Argument,
+}
+
+impl Kernel {
+    /// Translate a compiled expression, returns None when the parser has to be used.
+    fn new(compiled: &CompiledExpression, variable: &str, calculator: &Calculator) -> Option<Self> {
+        let tree = ExpressionTree::parse_single_statement(compiled.tokens()).ok()?;
+        let mut kernel = Kernel {
+            operations: Vec::new(),
+            result: Argument::Input,
+        };
+        kernel.result = kernel.translate(&tree, variable, calculator)?;
+        Some(kernel)
+    }
+
+    /// Translate a subtree, folding operations on constants.
+    fn translate(
+        &mut self,
+        tree: &ExpressionTree,
+        variable: &str,
+        calculator: &Calculator,
+    ) -> Option<Argument> {
+        let mut translate = |tree: &ExpressionTree| self.translate(tree, variable, calculator);
+        let (operation, arguments) = match tree {
+            ExpressionTree::Number(x) => return Some(Argument::Constant(*x)),
+            ExpressionTree::Variable(name) if name == variable => return Some(Argument::Input),
+            // Expression variables and variables that are not set are left to the parser
+            ExpressionTree::Variable(name) => {
+                return calculator.get_variable(name).ok().map(Argument::Constant)
+            }
+            // The parser negates by multiplying with -1
+            ExpressionTree::Negate(argument) => (
+                Operation::Multiply,
+                [Argument::Constant(-1.0), translate(argument)?],
+            ),
+            ExpressionTree::Add(left, right) => {
+                (Operation::Add, [translate(left)?, translate(right)?])
+            }
+            ExpressionTree::Subtract(left, right) => {
+                (Operation::Subtract, [translate(left)?, translate(right)?])
+            }
+            ExpressionTree::Multiply(left, right) => {
+                (Operation::Multiply, [translate(left)?, translate(right)?])
+            }
+            ExpressionTree::Divide(left, right) => {
+                (Operation::Divide, [translate(left)?, translate(right)?])
+            }
+            ExpressionTree::Remainder(left, right) => {
+                (Operation::Remainder, [translate(left)?, translate(right)?])
+            }
+            ExpressionTree::Power(base, exponent) => {
+                (Operation::Power, [translate(base)?, translate(exponent)?])
+            }
+            ExpressionTree::Compare(operator, left, right) => (
+                Operation::Compare(*operator),
+                [translate(left)?, translate(right)?],
+            ),
+            ExpressionTree::Function(name, function_arguments) => {
+                let function = lookup_function(name).ok()?;
+                let arguments = match (function, function_arguments.as_slice()) {
+                    (
+                        FunctionImplementation::Unary(_) | FunctionImplementation::FallibleUnary(_),
+                        [argument],
+                    ) => [translate(argument)?, Argument::Constant(0.0)],
+                    (
+                        FunctionImplementation::Binary(_)
+                        | FunctionImplementation::FallibleBinary(_)
+                        | FunctionImplementation::Variadic(_),
+                        [left, right],
+                    ) => [translate(left)?, translate(right)?],
+                    _ => return None,
+                };
+                (Operation::Function(function), arguments)
+            }
+        };
+        match arguments {
+            [Argument::Constant(left), Argument::Constant(right)] => {
+                operation.apply(left, right).ok().map(Argument::Constant)
+            }
+            _ => {
+                self.operations.push((operation, arguments));
+                Some(Argument::Register(self.operations.len() - 1))
+            }
+        }
+    }
+
+    /// Evaluate the kernel for a chunk of at most CHUNK values.
+    ///
+    /// `registers` holds CHUNK values for every operation.
+    fn evaluate_chunk(
+        &self,
+        input: &[f64],
+        registers: &mut [f64],
+        out: &mut [f64],
+    ) -> Result<(), CalculatorError> {
+        let length = input.len();
+        for (index, (operation, arguments)) in self.operations.iter().enumerate() {
+            let (previous, current) = registers.split_at_mut(index * CHUNK);
+            let target = &mut current[..length];
+            let left = operand(arguments[0], input, previous);
+            let right = operand(arguments[1], input, previous);
+            match operation {
+                Operation::Add => map(target, left, right, |x, y| x + y),
+                Operation::Subtract => map(target, left, right, |x, y| x - y),
+                Operation::Multiply => map(target, left, right, |x, y| x * y),
+                Operation::Divide | Operation::Remainder => {
+                    let zero_divisor = match right {
+                        Operand::Scalar(y) => y == 0.0,
+                        Operand::Values(ys) => ys.contains(&0.0),
+                    };
+                    if zero_divisor {
+                        return Err(CalculatorError::DivisionByZero);
+                    }
+                    if matches!(operation, Operation::Divide) {
+                        map(target, left, right, |x, y| x / y)
+                    } else {
+                        map(target, left, right, |x, y| x % y)
+                    }
+                }
+                Operation::Power => map(target, left, right, math::powf),
+                Operation::Compare(operator) => {
+                    map(target, left, right, |x, y| operator.compare(x, y))
+                }
+                Operation::Function(FunctionImplementation::Unary(function)) => {
+                    map(target, left, right, |x, _| function(x))
+                }
+                Operation::Function(
+                    FunctionImplementation::Binary(function)
+                    | FunctionImplementation::Variadic(function),
+                ) => map(target, left, right, function),
+                Operation::Function(_) => {
+                    for (position, value) in target.iter_mut().enumerate() {
+                        *value = operation.apply(left.get(position), right.get(position))?;
+                    }
+                }
+            }
+        }
+        match operand(self.result, input, registers) {
+            Operand::Scalar(x) => out.fill(x),
+            Operand::Values(values) => out.copy_from_slice(values),
+        }
+        Ok(())
+    }
+}
+
+impl Operand<'_> {
+    /// Return the value at a position of the chunk.
+    fn get(self, position: usize) -> f64 {
+        match self {
+            Operand::Scalar(x) => x,
+            Operand::Values(values) => values[position],
+        }
+    }
+}
+
+/// Return the operand of an argument for the current chunk.
+fn operand<'a>(argument: Argument, input: &'a [f64], registers: &'a [f64]) -> Operand<'a> {
+    match argument {
+        Argument::Constant(x) => Operand::Scalar(x),
+        Argument::Input => Operand::Values(input),
+        Argument::Register(index) => {
+            Operand::Values(&registers[index * CHUNK..index * CHUNK + input.len()])
+        }
+    }
+}
+
+/// Apply a binary function elementwise, the loops are specialized for scalar operands.
+#[inline]
+fn map(target: &mut [f64], left: Operand, right: Operand, function: impl Fn(f64, f64) -> f64) {
+    match (left, right) {
+        (Operand::Values(xs), Operand::Values(ys)) => {
+            for (value, (x, y)) in target.iter_mut().zip(xs.iter().zip(ys)) {
+                *value = function(*x, *y);
+            }
+        }
+        (Operand::Values(xs), Operand::Scalar(y)) => {
+            for (value, x) in target.iter_mut().zip(xs) {
+                *value = function(*x, y);
+            }
+        }
+        (Operand::Scalar(x), Operand::Values(ys)) => {
+            for (value, y) in target.iter_mut().zip(ys) {
+                *value = function(x, *y);
+            }
+        }
+        (Operand::Scalar(x), Operand::Scalar(y)) => target.fill(function(x, y)),
+    }
+}
+
+/// Evaluate a compiled expression for every value of a variable, see
+/// [CompiledExpression::evaluate_over].
+pub(crate) fn evaluate_over(
+    compiled: &CompiledExpression,
+    variable: &str,
+    values: &[f64],
+    calculator: &Calculator,
+    out: &mut [f64],
+) -> Result<(), CalculatorError> {
+    if out.len() != values.len() {
+        return Err(CalculatorError::OutputLengthMismatch {
+            expected: values.len(),
+            found: out.len(),
+        });
+    }
+    let evaluate_values = |start: usize, out: &mut [f64]| -> Result<(), CalculatorError> {
+        for (index, value) in out.iter_mut().enumerate() {
+            *value = calculator
+                .evaluate_compiled_at(compiled, variable, values[start + index])
+                .map_err(|error| CalculatorError::SweepPointError {
+                    index: vec![start + index],
+                    error: Box::new(error),
+                })?;
+        }
+        Ok(())
+    };
+    let kernel = match Kernel::new(compiled, variable, calculator) {
+        Some(kernel) => kernel,
+        None => return evaluate_values(0, out),
+    };
+    let mut registers = vec![0.0; kernel.operations.len() * CHUNK];
+    for (chunk, (input, out)) in values.chunks(CHUNK).zip(out.chunks_mut(CHUNK)).enumerate() {
+        if kernel.evaluate_chunk(input, &mut registers, out).is_err() {
+            // The parser finds the first failing value and its error
+            evaluate_values(chunk * CHUNK, out)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Evaluate an expression with the kernel and value by value with parse_str.
+    fn compare(calculator: &Calculator, expression: &str, values: &[f64]) {
+        let compiled = CompiledExpression::new(expression);
+        let mut out = vec![0.0; values.len()];
+        let result = evaluate_over(&compiled, "t", values, calculator, &mut out);
+        let mut expected = Vec::with_capacity(values.len());
+        let mut expected_error = None;
+        for (index, value) in values.iter().enumerate() {
+            let mut point = calculator.clone();
+            point.set_variable("t", *value).unwrap();
+            match point.parse_str(expression) {
+                Ok(value) => expected.push(value),
+                Err(error) => {
+                    expected_error = Some(CalculatorError::SweepPointError {
+                        index: vec![index],
+                        error: Box::new(error),
+                    });
+                    break;
+                }
+            }
+        }
+        match expected_error {
+            Some(error) => assert_eq!(result, Err(error), "{expression}"),
+            None => {
+                assert_eq!(result, Ok(()), "{expression}");
+                for (value, expected) in out.iter().zip(expected) {
+                    assert!(
+                        value.to_bits() == expected.to_bits()
+                            || value.is_nan() && expected.is_nan(),
+                        "{expression}: {value} != {expected}"
+                    );
+                }
+            }
+        }
+    }
+
+    // Test that the kernel agrees with the parser bit for bit
+    #[test]
+    fn kernel_matches_parser() {
+        let mut calculator = Calculator::new();
+        calculator.set_variable("amp", 1.5).unwrap();
+        calculator.set_variable("t0", 0.3).unwrap();
+        calculator.set_variable("w", 0.7).unwrap();
+        calculator.set_variable("zero", 0.0).unwrap();
+        let gaussian = CompiledExpression::new("amp * exp(-((t-t0)/w)^2)");
+        let kernel = Kernel::new(&gaussian, "t", &calculator).unwrap();
+        assert_eq!(kernel.operations.len(), 6);
+        let values: Vec<f64> = (0..1000).map(|i| i as f64 * 0.003 - 1.0).collect();
+        for expression in [
+            "amp * exp(-((t-t0)/w)^2)",
+            "amp * exp(-((t-t0)/w)^2) + 0.1 * sin(2 * pi * t)",
+            "t",
+            "-t",
+            "+t",
+            "amp * w",
+            "t0 * amp * t",
+            "t * t0 * amp",
+            "2^t - t^2 + atan2(t, w) - atan2(w, t)",
+            "max(t, t0) + min(amp, t) + sqrt(abs(t))",
+            "max(t, t0, amp * t) + sum(t, w, 1) - min(t, 0.5, -t)",
+            "sqrt(t)",
+            "log(t)",
+            "acosh(t + 1.5)",
+            "1 / t",
+            "t / zero",
+            "t % w - 2 % (t + 1.5)",
+            "t % zero",
+            "(t > t0) * amp + (t <= 0) * t - (t == 0.5) + (w != t) * (t >= w) * (t < 1)",
+            "amp / zero + t",
+            "t;",
+            "a = 2; t * a",
+            "t + undefined",
+            "t!",
+            "sin(t",
+            "",
+        ] {
+            compare(&calculator, expression, &values);
+        }
+    }
+
+    // Test that expression variables, interpolation tables and a set variable of the same name
+    // are handled like in the parser
+    #[test]
+    fn kernel_fallback() {
+        let mut calculator = Calculator::new();
+        calculator.set_variable("t", 100.0).unwrap();
+        calculator
+            .set_expression_variable("shifted", "t - 1")
+            .unwrap();
+        calculator
+            .register_interpolation_table("table", vec![0.0, 1.0], vec![0.0, 2.0])
+            .unwrap();
+        let values: Vec<f64> = (0..600).map(|i| i as f64 / 600.0).collect();
+        for expression in ["t * shifted", "table(t) + t", "2 * t"] {
+            compare(&calculator, expression, &values);
+        }
+        let compiled = CompiledExpression::new("t * shifted");
+        assert!(Kernel::new(&compiled, "t", &calculator).is_none());
+    }
+
+    // Test that an output buffer of the wrong length is rejected
+    #[test]
+    fn output_length() {
+        let calculator = Calculator::new();
+        let compiled = CompiledExpression::new("t");
+        let mut out = vec![0.0; 2];
+        assert_eq!(
+            evaluate_over(&compiled, "t", &[1.0], &calculator, &mut out),
+            Err(CalculatorError::OutputLengthMismatch {
+                expected: 1,
+                found: 2
+            })
+        );
+        assert_eq!(
+            evaluate_over(&compiled, "t", &[], &calculator, &mut []),
+            Ok(())
+        );
+    }
+}
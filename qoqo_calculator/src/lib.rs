@@ -19,15 +19,66 @@
 //! qoqo_calculator module
 //!
 //! Provides CalculatorError enum for all custom errors relating to
-//! Calculator, CalculatorFloat and CalculatorComplex.
+//! Calculator, CalculatorFloat and CalculatorComplex
+//! and CalculatorWarning enum for non-fatal diagnostics of the Calculator.
 
 mod calculator_float;
 pub use calculator_float::CalculatorFloat;
+pub use calculator_float::RoundingPolicy;
+pub use calculator_float::SymbolicAccumulator;
+pub use calculator_float::{
+    max_deserialized_expression_length, max_symbolic_length,
+    set_max_deserialized_expression_length, set_max_symbolic_length,
+    DEFAULT_MAX_DESERIALIZED_EXPRESSION_LENGTH, DEFAULT_MAX_SYMBOLIC_LENGTH,
+};
 mod calculator;
 pub use calculator::Calculator;
+pub use calculator::CalculatorAccess;
+pub use calculator::CheckReport;
+pub use calculator::ComparisonOperator;
+pub use calculator::CompoundOperator;
+pub use calculator::EvaluationEvent;
+pub use calculator::EvaluationObserver;
+pub use calculator::SweepResult;
+pub use calculator::Token;
+pub use calculator::UnknownFunctionHandler;
+pub use calculator::{
+    parse_str, parse_str_assign, parse_str_complex, validate_expression, variables_in_expression,
+    MAX_EXPRESSION_VARIABLE_DEPTH, MAX_INCLUDE_DEPTH,
+};
+pub use calculator::{SpannedTokenIterator, TokenIterator};
 mod calculator_complex;
 pub use calculator_complex::CalculatorComplex;
+mod calculator_vector;
+pub use calculator_vector::CalculatorVector;
+mod canonical_hash;
+mod interpolation;
+mod kernel;
+mod math;
+mod special_functions;
+pub use interpolation::{InterpolationTable, OutOfRange};
+#[cfg(feature = "proptest")]
+pub mod arbitrary;
+#[cfg(feature = "physical-constants")]
+pub mod constants;
+mod derivative;
+mod detokenize;
+pub use detokenize::{detokenize, detokenize_with, reformat, tokenize, PowerOperator};
+mod dual;
+mod evaluation_graph;
+pub mod expr;
+mod expression_cache;
+pub mod introspection;
+pub mod serialize;
+mod total_ord;
+pub use evaluation_graph::{EvaluationGraph, ExpressionHandle};
+pub use expression_cache::{
+    CompiledExpression, ExpressionCache, DEFAULT_EXPRESSION_CACHE_CAPACITY,
+};
+use std::fmt;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use thiserror::Error;
+pub use total_ord::TotalOrd;
 
 /// Define custom errors for Calculator.
 #[derive(Error, Debug, PartialEq)]
@@ -36,28 +87,30 @@ pub enum CalculatorError {
     #[error("Input cannot be converted to CalculatorFloat")]
     NotConvertable,
     /// A symbolic input cannot be converted to CalculatorFloat
-    #[error("Symbolic value {val:?} can not be converted to float")]
+    #[error("Symbolic value {} can not be converted to float", Truncated(format_args!("{val:?}")))]
     FloatSymbolicNotConvertable {
         /// Value that can not be converted
         val: String,
     },
     /// A symbolic input cannot be converted to CalculatorComplex
-    #[error("Symbolic value {val:?} can not be converted to complex")]
+    #[error("Symbolic value {} can not be converted to complex", Truncated(format_args!("{val:?}")))]
     ComplexSymbolicNotConvertable {
         /// Value that cannot be converted
         val: CalculatorComplex,
     },
     /// A complex value cannot be converted to float because imaginary part is not zero
-    #[error("Imaginary part of CalculatorComplex {val:?} not zero")]
+    #[error("Imaginary part of CalculatorComplex {} not zero", Truncated(format_args!("{val:?}")))]
     ComplexCanNotBeConvertedToFloat {
         /// Value of the CalculatorComplex that cannot be converted
         val: CalculatorComplex,
     },
-    #[error("Parsing error: {msg:?}")]
+    #[error("Parsing error: {msg:?}{}", AtPosition(*.position))]
     /// Parsing error when using Calculator
     ParsingError {
         /// Parsing error
         msg: &'static str,
+        /// Byte position of the token where parsing failed, None when not known
+        position: Option<usize>,
     },
     /// Function not implemented in Calculator
     #[error("Function {fct:?} not implemented.")]
@@ -71,12 +124,26 @@ pub enum CalculatorError {
         /// Name of function that cannot be found
         fct: String,
     },
+    /// Function is not defined for the argument, e.g. the factorial of a negative integer
+    #[error("Function {fct:?} is not defined for the argument {value}.")]
+    InvalidFunctionArgument {
+        /// Name of the function
+        fct: &'static str,
+        /// Argument the function is not defined for
+        value: f64,
+    },
     /// A variable is not set
     #[error("Variable {name:?} not set.")]
     VariableNotSet {
         /// Name of the variable that is not set
         name: String,
     },
+    /// Several variables are not set
+    #[error("Variables {names:?} not set.")]
+    VariablesNotSet {
+        /// Names of the variables that are not set
+        names: Vec<String>,
+    },
     /// Parsed expression ended unexpectedly
     #[error("Parsing error: Unexpected end of expression")]
     UnexpectedEndOfExpression,
@@ -96,7 +163,7 @@ pub enum CalculatorError {
         variable_name: String,
     },
     /// Error raised when checking if a String-CalculatorFloat is valid and can be parsed
-    #[error("CalculatorFloat::Str is not a valid expression that can be parsed: Variable assignment to {variable_name}")]
+    #[error("CalculatorFloat::Str is not a valid expression that can be parsed: Variable assignment to {}", Truncated(variable_name))]
     NotParsableAssign {
         /// Name of the variable that is being assigned
         variable_name: String,
@@ -107,12 +174,502 @@ pub enum CalculatorError {
     /// Error raised when checking if a String-CalculatorFloat is valid and can be parsed
     #[error("CalculatorFloat::Str is not a valid expression that can be parsed: Assign operator `=` found in expression")]
     NotParsableSingleAssign,
+    /// Error raised when validating an expression finds an invalid element
+    #[error("Invalid expression at position {position}: {error}")]
+    InvalidExpression {
+        /// Byte position of the invalid element in the expression
+        position: usize,
+        /// Reason the element is invalid
+        error: Box<CalculatorError>,
+    },
+    /// Error raised when evaluating one point of a sweep fails
+    #[error("Sweep failed at index {index:?}: {error}")]
+    SweepPointError {
+        /// Multi-index of the failing point
+        index: Vec<usize>,
+        /// Reason the evaluation failed
+        error: Box<CalculatorError>,
+    },
+    /// Arithmetic operation would combine too long symbolic expressions
+    #[error("Symbolic expressions of length {length} exceed the maximum length {limit}, see set_max_symbolic_length")]
+    SymbolicExpressionTooLarge {
+        /// Combined length of the symbolic expressions of the operands
+        length: usize,
+        /// Maximum length at the time of the operation
+        limit: usize,
+    },
+    /// Variable defined by an expression depends on itself
+    #[error("Variable defined by expression depends on itself: {}", chain.join(" -> "))]
+    RecursiveExpressionVariable {
+        /// Variables from the outermost use to the repeated variable
+        chain: Vec<String>,
+    },
+    /// Variables defined by expressions are nested too deeply
+    #[error("Variable {name:?} defined by expression is nested deeper than {limit} levels")]
+    ExpressionVariableTooDeep {
+        /// Name of the variable exceeding the depth
+        name: String,
+        /// Maximal nesting depth
+        limit: usize,
+    },
+    /// Elementwise operation on CalculatorVectors of different length
+    #[error("CalculatorVectors have different lengths {left} and {right}")]
+    VectorLengthMismatch {
+        /// Length of the left vector
+        left: usize,
+        /// Length of the right vector
+        right: usize,
+    },
+    /// Values do not form a valid interpolation table
+    #[error("Invalid interpolation table: {msg}")]
+    InvalidInterpolationTable {
+        /// Reason the table is not valid
+        msg: &'static str,
+    },
+    /// Interpolation table evaluated outside of its x values
+    #[error("Value {value} is outside of the interpolation table range [{lower}, {upper}]")]
+    InterpolationOutOfRange {
+        /// Argument of the interpolation
+        value: f64,
+        /// Smallest x value of the table
+        lower: f64,
+        /// Largest x value of the table
+        upper: f64,
+    },
+    /// Reduced rational number does not fit into 64 bit integers
+    #[error("Rational number {numerator} / {denominator} does not fit into 64 bit integers")]
+    RationalOverflow {
+        /// Reduced numerator
+        numerator: i128,
+        /// Reduced positive denominator
+        denominator: i128,
+    },
+    /// Condition of an `assert` in an expression is not fulfilled
+    #[error("Assertion failed at position {position}: {message}")]
+    AssertionFailed {
+        /// Message of the assert
+        message: String,
+        /// Byte position of the assert in the evaluated expression
+        position: usize,
+    },
+    /// Phase of the complex zero, which is not defined
+    #[error("The phase of the complex zero is not defined")]
+    PhaseOfZero,
+    /// Output buffer does not have the length of the evaluated values
+    #[error("Output buffer has length {found}, expected {expected}")]
+    OutputLengthMismatch {
+        /// Number of evaluated values
+        expected: usize,
+        /// Length of the output buffer
+        found: usize,
+    },
+    /// Numeric value can not be converted to an integer type
+    #[error("Value {value} can not be converted to {target}")]
+    IntegerConversionError {
+        /// Value that can not be converted
+        value: f64,
+        /// Name of the integer type
+        target: &'static str,
+    },
+    /// Expression contains placeholders that are not bound
+    #[error("Placeholders {names:?} are not bound, see CalculatorFloat::bind_placeholders")]
+    UnboundPlaceholder {
+        /// Names of the placeholders that are not bound
+        names: Vec<String>,
+    },
+    /// String is not a plain decimal literal like `0.25`
+    #[error("{literal:?} is not a plain decimal literal with at most 18 digits like 0.25")]
+    InvalidDecimalLiteral {
+        /// The rejected string
+        literal: String,
+    },
+    /// Included library is not registered
+    #[error("Library {name:?} is not registered, see Calculator::register_library")]
+    LibraryNotFound {
+        /// Name of the included library
+        name: String,
+    },
+    /// Evaluating the statements of an included library failed
+    #[error("Error in library {library:?} at position {position}: {error}")]
+    LibraryError {
+        /// Name of the library
+        library: String,
+        /// Byte position in the library where the evaluation failed
+        position: usize,
+        /// Reason the evaluation failed
+        error: Box<CalculatorError>,
+    },
+    /// Library includes itself directly or through other libraries
+    #[error("Library includes itself: {}", chain.join(" -> "))]
+    RecursiveInclude {
+        /// Libraries from the outermost include to the repeated library
+        chain: Vec<String>,
+    },
+    /// Libraries are included too deeply
+    #[error("Library {name:?} is included deeper than {limit} levels")]
+    IncludeTooDeep {
+        /// Name of the library exceeding the depth
+        name: String,
+        /// Maximal nesting depth
+        limit: usize,
+    },
+    /// Error raised when evaluating an expression at a random sample fails
+    #[error("Evaluation failed at the sample {assignments:?}: {error}")]
+    SamplePointError {
+        /// Sampled values of the variables
+        assignments: Vec<(String, f64)>,
+        /// Reason the evaluation failed
+        error: Box<CalculatorError>,
+    },
+    /// Deserialized symbolic expression is too long
+    #[error("Deserialized symbolic expression of length {length} exceeds the maximum length {limit}, see set_max_deserialized_expression_length")]
+    DeserializedExpressionTooLong {
+        /// Length of the deserialized expression
+        length: usize,
+        /// Maximum length at the time of deserialization
+        limit: usize,
+    },
+    /// Calculator is frozen and can not be changed
+    #[error("Calculator is frozen, {operation} is not allowed, see Calculator::unfreeze")]
+    CalculatorFrozen {
+        /// Name of the rejected method
+        operation: &'static str,
+    },
+    /// Parsed expression ended unexpectedly at a known position
+    #[error("Parsing error: Unexpected end of expression at position {position}")]
+    UnexpectedEndOfExpressionAt {
+        /// Byte position where the expression ended
+        position: usize,
+    },
+    /// A token can not be parsed at its position, e.g. a closing bracket after an operator
+    #[error(
+        "Parsing error: Unexpected token '{}' at position {position}",
+        Truncated(token)
+    )]
+    UnexpectedToken {
+        /// Source text of the token
+        token: String,
+        /// Byte position of the token
+        position: usize,
+    },
+    /// An element of the expression is not recognized by the lexer
+    #[error(
+        "Parsing error: Unrecognized element '{}' at position {position}",
+        Truncated(token)
+    )]
+    UnrecognizedToken {
+        /// Source text of the unrecognized element
+        token: String,
+        /// Byte position of the unrecognized element
+        position: usize,
+    },
+    /// Serializing or deserializing a Calculator failed
+    #[error("Serialization error: {msg}")]
+    SerializationError {
+        /// Reason of the failure
+        msg: String,
+    },
+}
+
+/// Names of all CalculatorError variants, see [CalculatorError::kind].
+pub(crate) const ERROR_KINDS: [&str; 44] = [
+    "NotConvertable",
+    "FloatSymbolicNotConvertable",
+    "ComplexSymbolicNotConvertable",
+    "ComplexCanNotBeConvertedToFloat",
+    "ParsingError",
+    "NotImplementedError",
+    "FunctionNotFound",
+    "InvalidFunctionArgument",
+    "VariableNotSet",
+    "VariablesNotSet",
+    "UnexpectedEndOfExpression",
+    "DivisionByZero",
+    "NoValueReturnedParsing",
+    "NotEnoughFunctionArguments",
+    "ForbiddenAssign",
+    "NotParsableAssign",
+    "NotParsableUnrecognized",
+    "NotParsableSingleAssign",
+    "InvalidExpression",
+    "SweepPointError",
+    "SymbolicExpressionTooLarge",
+    "RecursiveExpressionVariable",
+    "ExpressionVariableTooDeep",
+    "VectorLengthMismatch",
+    "InvalidInterpolationTable",
+    "InterpolationOutOfRange",
+    "RationalOverflow",
+    "AssertionFailed",
+    "PhaseOfZero",
+    "OutputLengthMismatch",
+    "IntegerConversionError",
+    "UnboundPlaceholder",
+    "InvalidDecimalLiteral",
+    "LibraryNotFound",
+    "LibraryError",
+    "RecursiveInclude",
+    "IncludeTooDeep",
+    "SamplePointError",
+    "DeserializedExpressionTooLong",
+    "CalculatorFrozen",
+    "UnexpectedEndOfExpressionAt",
+    "UnexpectedToken",
+    "UnrecognizedToken",
+    "SerializationError",
+];
+
+impl CalculatorError {
+    /// Return the name of the variant, e.g. `"DivisionByZero"`.
+    ///
+    /// All names are listed by [introspection::error_kinds].
+    pub fn kind(&self) -> &'static str {
+        match self {
+            CalculatorError::NotConvertable => "NotConvertable",
+            CalculatorError::FloatSymbolicNotConvertable { .. } => "FloatSymbolicNotConvertable",
+            CalculatorError::ComplexSymbolicNotConvertable { .. } => {
+                "ComplexSymbolicNotConvertable"
+            }
+            CalculatorError::ComplexCanNotBeConvertedToFloat { .. } => {
+                "ComplexCanNotBeConvertedToFloat"
+            }
+            CalculatorError::ParsingError { .. } => "ParsingError",
+            CalculatorError::NotImplementedError { .. } => "NotImplementedError",
+            CalculatorError::FunctionNotFound { .. } => "FunctionNotFound",
+            CalculatorError::InvalidFunctionArgument { .. } => "InvalidFunctionArgument",
+            CalculatorError::VariableNotSet { .. } => "VariableNotSet",
+            CalculatorError::VariablesNotSet { .. } => "VariablesNotSet",
+            CalculatorError::UnexpectedEndOfExpression => "UnexpectedEndOfExpression",
+            CalculatorError::DivisionByZero => "DivisionByZero",
+            CalculatorError::NoValueReturnedParsing => "NoValueReturnedParsing",
+            CalculatorError::NotEnoughFunctionArguments => "NotEnoughFunctionArguments",
+            CalculatorError::ForbiddenAssign { .. } => "ForbiddenAssign",
+            CalculatorError::NotParsableAssign { .. } => "NotParsableAssign",
+            CalculatorError::NotParsableUnrecognized => "NotParsableUnrecognized",
+            CalculatorError::NotParsableSingleAssign => "NotParsableSingleAssign",
+            CalculatorError::InvalidExpression { .. } => "InvalidExpression",
+            CalculatorError::SweepPointError { .. } => "SweepPointError",
+            CalculatorError::SymbolicExpressionTooLarge { .. } => "SymbolicExpressionTooLarge",
+            CalculatorError::RecursiveExpressionVariable { .. } => "RecursiveExpressionVariable",
+            CalculatorError::ExpressionVariableTooDeep { .. } => "ExpressionVariableTooDeep",
+            CalculatorError::VectorLengthMismatch { .. } => "VectorLengthMismatch",
+            CalculatorError::InvalidInterpolationTable { .. } => "InvalidInterpolationTable",
+            CalculatorError::InterpolationOutOfRange { .. } => "InterpolationOutOfRange",
+            CalculatorError::RationalOverflow { .. } => "RationalOverflow",
+            CalculatorError::AssertionFailed { .. } => "AssertionFailed",
+            CalculatorError::PhaseOfZero => "PhaseOfZero",
+            CalculatorError::OutputLengthMismatch { .. } => "OutputLengthMismatch",
+            CalculatorError::IntegerConversionError { .. } => "IntegerConversionError",
+            CalculatorError::UnboundPlaceholder { .. } => "UnboundPlaceholder",
+            CalculatorError::InvalidDecimalLiteral { .. } => "InvalidDecimalLiteral",
+            CalculatorError::LibraryNotFound { .. } => "LibraryNotFound",
+            CalculatorError::LibraryError { .. } => "LibraryError",
+            CalculatorError::RecursiveInclude { .. } => "RecursiveInclude",
+            CalculatorError::IncludeTooDeep { .. } => "IncludeTooDeep",
+            CalculatorError::SamplePointError { .. } => "SamplePointError",
+            CalculatorError::DeserializedExpressionTooLong { .. } => {
+                "DeserializedExpressionTooLong"
+            }
+            CalculatorError::CalculatorFrozen { .. } => "CalculatorFrozen",
+            CalculatorError::UnexpectedEndOfExpressionAt { .. } => "UnexpectedEndOfExpressionAt",
+            CalculatorError::UnexpectedToken { .. } => "UnexpectedToken",
+            CalculatorError::UnrecognizedToken { .. } => "UnrecognizedToken",
+            CalculatorError::SerializationError { .. } => "SerializationError",
+        }
+    }
+}
+
+/// Default maximum length of expressions embedded in error messages (200 bytes).
+pub const DEFAULT_MAX_ERROR_EXPRESSION_LENGTH: usize = 200;
+
+/// Maximum length of expressions embedded in error messages.
+static MAX_ERROR_EXPRESSION_LENGTH: AtomicUsize =
+    AtomicUsize::new(DEFAULT_MAX_ERROR_EXPRESSION_LENGTH);
+
+/// Set the maximum length of expressions embedded in error messages.
+///
+/// The messages of [CalculatorError::FloatSymbolicNotConvertable],
+/// [CalculatorError::ComplexSymbolicNotConvertable],
+/// [CalculatorError::ComplexCanNotBeConvertedToFloat] and [CalculatorError::NotParsableAssign]
+/// only contain the start of long expressions followed by `...` and the full length,
+/// so logging an error for a generated expression of megabytes stays readable.
+/// The fields of the errors always hold the complete values.
+/// The limit is global for the process and defaults to [DEFAULT_MAX_ERROR_EXPRESSION_LENGTH] bytes.
+///
+/// # Arguments
+///
+/// * `limit` - Maximum length in bytes of an embedded expression
+///
+pub fn set_max_error_expression_length(limit: usize) {
+    MAX_ERROR_EXPRESSION_LENGTH.store(limit, Ordering::Relaxed);
+}
+
+/// Return the maximum length of expressions embedded in error messages.
+///
+/// See [set_max_error_expression_length].
+pub fn max_error_expression_length() -> usize {
+    MAX_ERROR_EXPRESSION_LENGTH.load(Ordering::Relaxed)
+}
+
+/// Displays ` at position {position}` for a known position and nothing otherwise.
+struct AtPosition(Option<usize>);
+
+impl fmt::Display for AtPosition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            Some(position) => write!(f, " at position {position}"),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Displays a value truncated to [max_error_expression_length] bytes.
+///
+/// Longer values are cut at a character boundary and followed by `...` and their full length,
+/// e.g. `"x + x + x... (1048576 bytes)`. Shorter values are displayed unchanged.
+/// The value is formatted only once and never held in memory as a whole.
+#[derive(Debug, Clone, Copy)]
+pub struct Truncated<T>(pub T);
+
+impl<T: fmt::Display> fmt::Display for Truncated<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_truncated(f, &self.0, max_error_expression_length())
+    }
+}
+
+/// Stream a value into a writer, cut after `limit` bytes and followed by `...` and the full length.
+///
+/// The value is formatted only once and never held in memory as a whole.
+///
+/// # Arguments
+///
+/// * `writer` - Writer receiving the start of the value
+/// * `value` - Value that is displayed
+/// * `limit` - Maximum number of bytes of the value that are written
+///
+pub(crate) fn write_truncated<W: fmt::Write + ?Sized>(
+    writer: &mut W,
+    value: impl fmt::Display,
+    limit: usize,
+) -> fmt::Result {
+    let mut truncating = TruncatingWriter {
+        writer,
+        limit,
+        length: 0,
+        truncated: false,
+    };
+    fmt::write(&mut truncating, format_args!("{value}"))?;
+    let (length, truncated) = (truncating.length, truncating.truncated);
+    if truncated {
+        write!(writer, "... ({length} bytes)")?;
+    }
+    Ok(())
+}
+
+/// Writer forwarding the first `limit` bytes to another writer and counting all written bytes.
+struct TruncatingWriter<'a, W: fmt::Write + ?Sized> {
+    /// Writer receiving the start of the output
+    writer: &'a mut W,
+    /// Maximum number of forwarded bytes
+    limit: usize,
+    /// Number of bytes written so far
+    length: usize,
+    /// Whether output has been cut off
+    truncated: bool,
+}
+
+impl<W: fmt::Write + ?Sized> fmt::Write for TruncatingWriter<'_, W> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        if !self.truncated {
+            let mut end = s.len().min(self.limit - self.length);
+            while !s.is_char_boundary(end) {
+                end -= 1;
+            }
+            self.writer.write_str(&s[..end])?;
+            self.truncated = end < s.len();
+        }
+        self.length += s.len();
+        Ok(())
+    }
+}
+
+/// Define non-fatal diagnostics for expressions parsed by the Calculator.
+///
+/// Warnings point out valid expressions that are most likely not what was intended.
+/// Positions are byte positions in the parsed expression.
+#[derive(Debug, Clone, PartialEq)]
+pub enum CalculatorWarning {
+    /// A comma between two digits, which separates two function arguments
+    /// but was probably meant as a decimal separator
+    DecimalComma {
+        /// Byte position of the comma
+        position: usize,
+    },
+    /// A variable used once in the expression that differs from another set variable
+    /// by a single character
+    SimilarVariable {
+        /// Byte position of the variable
+        position: usize,
+        /// Name of the used variable
+        name: String,
+        /// Name of the similar set variable
+        similar: String,
+    },
+    /// A variable assignment that is not read in the following statements of the expression
+    UnusedAssignment {
+        /// Byte position of the assigned variable
+        position: usize,
+        /// Name of the assigned variable
+        name: String,
+    },
+    /// A number literal that is parsed to a float with a different value,
+    /// only reported when enabled with [Calculator::warn_on_precision_loss]
+    PrecisionLoss {
+        /// Byte position of the literal
+        position: usize,
+        /// Source text of the literal
+        literal: String,
+        /// Value the literal is parsed to
+        parsed: f64,
+    },
+}
+
+impl fmt::Display for CalculatorWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CalculatorWarning::DecimalComma { position } => write!(
+                f,
+                "Comma between digits at position {position} separates two function arguments, use '.' as decimal separator"
+            ),
+            CalculatorWarning::SimilarVariable {
+                position,
+                name,
+                similar,
+            } => write!(
+                f,
+                "Variable {name:?} at position {position} is used once and is similar to variable {similar:?}"
+            ),
+            CalculatorWarning::UnusedAssignment { position, name } => write!(
+                f,
+                "Variable {name:?} assigned at position {position} is not read afterwards"
+            ),
+            CalculatorWarning::PrecisionLoss {
+                position,
+                literal,
+                parsed,
+            } => write!(
+                f,
+                "Number {literal} at position {position} is not exactly representable and is parsed as {parsed:e}"
+            ),
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::CalculatorComplex;
     use super::CalculatorError;
+    use super::CalculatorWarning;
 
     // Test all CalculatorErrors give the correct output (debug)
     #[test]
@@ -144,8 +701,27 @@ mod tests {
             "ComplexSymbolicNotConvertable { val: CalculatorComplex { re: Float(1.0), im: Float(3.0) } }"
         );
 
-        let parse = CalculatorError::ParsingError { msg: "test" };
-        assert_eq!(format!("{parse:?}"), "ParsingError { msg: \"test\" }");
+        let parse = CalculatorError::ParsingError {
+            msg: "test",
+            position: None,
+        };
+        assert_eq!(
+            format!("{parse:?}"),
+            "ParsingError { msg: \"test\", position: None }"
+        );
+        assert_eq!(format!("{parse}"), "Parsing error: \"test\"");
+        let parse_at = CalculatorError::ParsingError {
+            msg: "Expected Braket close",
+            position: Some(3),
+        };
+        assert_eq!(
+            format!("{parse_at:?}"),
+            "ParsingError { msg: \"Expected Braket close\", position: Some(3) }"
+        );
+        assert_eq!(
+            format!("{parse_at}"),
+            "Parsing error: \"Expected Braket close\" at position 3"
+        );
 
         let not_impl = CalculatorError::NotImplementedError { fct: "Test" };
         assert_eq!(
@@ -153,6 +729,19 @@ mod tests {
             "NotImplementedError { fct: \"Test\" }"
         );
 
+        let invalid_argument = CalculatorError::InvalidFunctionArgument {
+            fct: "factorial",
+            value: -1.0,
+        };
+        assert_eq!(
+            format!("{invalid_argument:?}"),
+            "InvalidFunctionArgument { fct: \"factorial\", value: -1.0 }"
+        );
+        assert_eq!(
+            format!("{invalid_argument}"),
+            "Function \"factorial\" is not defined for the argument -1."
+        );
+
         let func_not_found = CalculatorError::FunctionNotFound {
             fct: String::from("Test"),
         };
@@ -169,6 +758,18 @@ mod tests {
             "VariableNotSet { name: \"Test\" }"
         );
 
+        let vars_not_set = CalculatorError::VariablesNotSet {
+            names: vec![String::from("a"), String::from("b")],
+        };
+        assert_eq!(
+            format!("{vars_not_set:?}"),
+            "VariablesNotSet { names: [\"a\", \"b\"] }"
+        );
+        assert_eq!(
+            format!("{vars_not_set}"),
+            "Variables [\"a\", \"b\"] not set."
+        );
+
         let end_of_exp = CalculatorError::UnexpectedEndOfExpression;
         assert_eq!(format!("{end_of_exp:?}"), "UnexpectedEndOfExpression");
 
@@ -180,5 +781,367 @@ mod tests {
 
         let func_args = CalculatorError::NotEnoughFunctionArguments;
         assert_eq!(format!("{func_args:?}"), "NotEnoughFunctionArguments");
+
+        let invalid_expression = CalculatorError::InvalidExpression {
+            position: 2,
+            error: Box::new(CalculatorError::NotParsableSingleAssign),
+        };
+        assert_eq!(
+            format!("{invalid_expression:?}"),
+            "InvalidExpression { position: 2, error: NotParsableSingleAssign }"
+        );
+
+        let sweep_point = CalculatorError::SweepPointError {
+            index: vec![0, 1],
+            error: Box::new(CalculatorError::DivisionByZero),
+        };
+        assert_eq!(
+            format!("{sweep_point:?}"),
+            "SweepPointError { index: [0, 1], error: DivisionByZero }"
+        );
+
+        let too_large = CalculatorError::SymbolicExpressionTooLarge {
+            length: 12,
+            limit: 10,
+        };
+        assert_eq!(
+            format!("{too_large:?}"),
+            "SymbolicExpressionTooLarge { length: 12, limit: 10 }"
+        );
+
+        let length_mismatch = CalculatorError::VectorLengthMismatch { left: 2, right: 3 };
+        assert_eq!(
+            format!("{length_mismatch:?}"),
+            "VectorLengthMismatch { left: 2, right: 3 }"
+        );
+
+        let phase_of_zero = CalculatorError::PhaseOfZero;
+        assert_eq!(format!("{phase_of_zero:?}"), "PhaseOfZero");
+        assert_eq!(
+            format!("{phase_of_zero}"),
+            "The phase of the complex zero is not defined"
+        );
+
+        let output_mismatch = CalculatorError::OutputLengthMismatch {
+            expected: 2,
+            found: 3,
+        };
+        assert_eq!(
+            format!("{output_mismatch:?}"),
+            "OutputLengthMismatch { expected: 2, found: 3 }"
+        );
+        assert_eq!(
+            format!("{output_mismatch}"),
+            "Output buffer has length 3, expected 2"
+        );
+
+        let integer_conversion = CalculatorError::IntegerConversionError {
+            value: -1.0,
+            target: "usize",
+        };
+        assert_eq!(
+            format!("{integer_conversion:?}"),
+            "IntegerConversionError { value: -1.0, target: \"usize\" }"
+        );
+        assert_eq!(
+            format!("{integer_conversion}"),
+            "Value -1 can not be converted to usize"
+        );
+
+        let unbound = CalculatorError::UnboundPlaceholder {
+            names: vec![String::from("n"), String::from("m")],
+        };
+        assert_eq!(
+            format!("{unbound:?}"),
+            "UnboundPlaceholder { names: [\"n\", \"m\"] }"
+        );
+        assert_eq!(
+            format!("{unbound}"),
+            "Placeholders [\"n\", \"m\"] are not bound, see CalculatorFloat::bind_placeholders"
+        );
+
+        let invalid_decimal = CalculatorError::InvalidDecimalLiteral {
+            literal: String::from("1e-3"),
+        };
+        assert_eq!(
+            format!("{invalid_decimal:?}"),
+            "InvalidDecimalLiteral { literal: \"1e-3\" }"
+        );
+        assert_eq!(
+            format!("{invalid_decimal}"),
+            "\"1e-3\" is not a plain decimal literal with at most 18 digits like 0.25"
+        );
+
+        let library_not_found = CalculatorError::LibraryNotFound {
+            name: String::from("pulses"),
+        };
+        assert_eq!(
+            format!("{library_not_found:?}"),
+            "LibraryNotFound { name: \"pulses\" }"
+        );
+        assert_eq!(
+            format!("{library_not_found}"),
+            "Library \"pulses\" is not registered, see Calculator::register_library"
+        );
+
+        let library_error = CalculatorError::LibraryError {
+            library: String::from("pulses"),
+            position: 4,
+            error: Box::new(CalculatorError::DivisionByZero),
+        };
+        assert_eq!(
+            format!("{library_error:?}"),
+            "LibraryError { library: \"pulses\", position: 4, error: DivisionByZero }"
+        );
+        assert_eq!(
+            format!("{library_error}"),
+            "Error in library \"pulses\" at position 4: Division by zero error"
+        );
+
+        let recursive_include = CalculatorError::RecursiveInclude {
+            chain: vec![String::from("a"), String::from("b"), String::from("a")],
+        };
+        assert_eq!(
+            format!("{recursive_include:?}"),
+            "RecursiveInclude { chain: [\"a\", \"b\", \"a\"] }"
+        );
+        assert_eq!(
+            format!("{recursive_include}"),
+            "Library includes itself: a -> b -> a"
+        );
+
+        let include_too_deep = CalculatorError::IncludeTooDeep {
+            name: String::from("a"),
+            limit: 16,
+        };
+        assert_eq!(
+            format!("{include_too_deep:?}"),
+            "IncludeTooDeep { name: \"a\", limit: 16 }"
+        );
+        assert_eq!(
+            format!("{include_too_deep}"),
+            "Library \"a\" is included deeper than 16 levels"
+        );
+
+        let sample_point = CalculatorError::SamplePointError {
+            assignments: vec![(String::from("x"), 0.5)],
+            error: Box::new(CalculatorError::DivisionByZero),
+        };
+        assert_eq!(
+            format!("{sample_point:?}"),
+            "SamplePointError { assignments: [(\"x\", 0.5)], error: DivisionByZero }"
+        );
+        assert_eq!(
+            format!("{sample_point}"),
+            "Evaluation failed at the sample [(\"x\", 0.5)]: Division by zero error"
+        );
+
+        let too_long = CalculatorError::DeserializedExpressionTooLong {
+            length: 12,
+            limit: 10,
+        };
+        assert_eq!(
+            format!("{too_long:?}"),
+            "DeserializedExpressionTooLong { length: 12, limit: 10 }"
+        );
+        assert_eq!(
+            format!("{too_long}"),
+            "Deserialized symbolic expression of length 12 exceeds the maximum length 10, see set_max_deserialized_expression_length"
+        );
+
+        let end_at = CalculatorError::UnexpectedEndOfExpressionAt { position: 4 };
+        assert_eq!(
+            format!("{end_at:?}"),
+            "UnexpectedEndOfExpressionAt { position: 4 }"
+        );
+        assert_eq!(
+            format!("{end_at}"),
+            "Parsing error: Unexpected end of expression at position 4"
+        );
+
+        let unexpected = CalculatorError::UnexpectedToken {
+            token: ")".to_string(),
+            position: 14,
+        };
+        assert_eq!(
+            format!("{unexpected:?}"),
+            "UnexpectedToken { token: \")\", position: 14 }"
+        );
+        assert_eq!(
+            format!("{unexpected}"),
+            "Parsing error: Unexpected token ')' at position 14"
+        );
+
+        let unrecognized = CalculatorError::UnrecognizedToken {
+            token: "$".to_string(),
+            position: 2,
+        };
+        assert_eq!(
+            format!("{unrecognized:?}"),
+            "UnrecognizedToken { token: \"$\", position: 2 }"
+        );
+        assert_eq!(
+            format!("{unrecognized}"),
+            "Parsing error: Unrecognized element '$' at position 2"
+        );
+
+        let serialization = CalculatorError::SerializationError {
+            msg: "expected value at line 1 column 1".to_string(),
+        };
+        assert_eq!(
+            format!("{serialization:?}"),
+            "SerializationError { msg: \"expected value at line 1 column 1\" }"
+        );
+        assert_eq!(
+            format!("{serialization}"),
+            "Serialization error: expected value at line 1 column 1"
+        );
+
+        let frozen = CalculatorError::CalculatorFrozen {
+            operation: "set_variable",
+        };
+        assert_eq!(
+            format!("{frozen:?}"),
+            "CalculatorFrozen { operation: \"set_variable\" }"
+        );
+        assert_eq!(
+            format!("{frozen}"),
+            "Calculator is frozen, set_variable is not allowed, see Calculator::unfreeze"
+        );
+
+        let rational_overflow = CalculatorError::RationalOverflow {
+            numerator: 9223372036854775808,
+            denominator: 1,
+        };
+        assert_eq!(
+            format!("{rational_overflow:?}"),
+            "RationalOverflow { numerator: 9223372036854775808, denominator: 1 }"
+        );
+        assert_eq!(
+            format!("{rational_overflow}"),
+            "Rational number 9223372036854775808 / 1 does not fit into 64 bit integers"
+        );
+
+        let assertion_failed = CalculatorError::AssertionFailed {
+            message: "pulse too long".to_string(),
+            position: 4,
+        };
+        assert_eq!(
+            format!("{assertion_failed:?}"),
+            "AssertionFailed { message: \"pulse too long\", position: 4 }"
+        );
+        assert_eq!(
+            format!("{assertion_failed}"),
+            "Assertion failed at position 4: pulse too long"
+        );
+    }
+
+    // Test all CalculatorWarnings give the correct output (display)
+    #[test]
+    fn test_warning_display() {
+        let decimal_comma = CalculatorWarning::DecimalComma { position: 7 };
+        assert_eq!(
+            format!("{decimal_comma}"),
+            "Comma between digits at position 7 separates two function arguments, use '.' as decimal separator"
+        );
+
+        let similar = CalculatorWarning::SimilarVariable {
+            position: 0,
+            name: String::from("thet"),
+            similar: String::from("theta"),
+        };
+        assert_eq!(
+            format!("{similar}"),
+            "Variable \"thet\" at position 0 is used once and is similar to variable \"theta\""
+        );
+
+        let unused = CalculatorWarning::UnusedAssignment {
+            position: 3,
+            name: String::from("a"),
+        };
+        assert_eq!(
+            format!("{unused}"),
+            "Variable \"a\" assigned at position 3 is not read afterwards"
+        );
+
+        let precision_loss = CalculatorWarning::PrecisionLoss {
+            position: 1,
+            literal: String::from("0.1000000000000000001"),
+            parsed: 0.1,
+        };
+        assert_eq!(
+            format!("{precision_loss}"),
+            "Number 0.1000000000000000001 at position 1 is not exactly representable and is parsed as 1e-1"
+        );
+    }
+    // Test that messages embed long expressions truncated and the fields keep them completely
+    #[test]
+    fn test_truncated_messages() {
+        let expression = "x + ".repeat(1 << 18) + "x";
+        let long_variable = "é".repeat(1 << 19);
+        for (error, complete) in [
+            (
+                CalculatorError::FloatSymbolicNotConvertable {
+                    val: expression.clone(),
+                },
+                expression.len(),
+            ),
+            (
+                CalculatorError::ComplexSymbolicNotConvertable {
+                    val: CalculatorComplex::new(expression.as_str(), 1),
+                },
+                expression.len(),
+            ),
+            (
+                CalculatorError::ComplexCanNotBeConvertedToFloat {
+                    val: CalculatorComplex::new(1, expression.as_str()),
+                },
+                expression.len(),
+            ),
+            (
+                CalculatorError::NotParsableAssign {
+                    variable_name: long_variable.clone(),
+                },
+                long_variable.len(),
+            ),
+        ] {
+            let message = error.to_string();
+            assert!(message.len() < 400, "{message}");
+            assert!(message.contains("... ("), "{message}");
+            match error {
+                CalculatorError::FloatSymbolicNotConvertable { val } => assert_eq!(val, expression),
+                CalculatorError::ComplexSymbolicNotConvertable { val } => {
+                    assert_eq!(val.re.to_string(), expression)
+                }
+                CalculatorError::ComplexCanNotBeConvertedToFloat { val } => {
+                    assert_eq!(val.im.to_string(), expression)
+                }
+                CalculatorError::NotParsableAssign { variable_name } => {
+                    assert_eq!(variable_name.len(), complete)
+                }
+                _ => unreachable!(),
+            }
+        }
+        assert_eq!(
+            CalculatorError::FloatSymbolicNotConvertable { val: expression }
+                .to_string()
+                .split_once("...")
+                .unwrap()
+                .1,
+            " (1048579 bytes) can not be converted to float"
+        );
+        assert_eq!(
+            format!("{}", super::Truncated("a".repeat(200))),
+            "a".repeat(200)
+        );
+        assert_eq!(
+            format!("{}", super::Truncated("a".repeat(201))),
+            "a".repeat(200) + "... (201 bytes)"
+        );
+        // Characters are not cut
+        assert_eq!(
+            format!("{}", super::Truncated(format_args!("a{}", "é".repeat(100)))),
+            "a".to_string() + &"é".repeat(99) + "... (201 bytes)"
+        );
     }
 }
@@ -0,0 +1,116 @@
+// Copyright © 2020-2021 HQS Quantum Simulations GmbH. All Rights Reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except
+// in compliance with the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the
+// License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either
+// express or implied. See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! math module
+//!
+//! Elementary functions used by the Calculator, CalculatorFloat and the special functions.
+//!
+//! By default the functions of the Rust standard library are used. They call the libm of the
+//! platform, so the last bit of a result can differ between platforms. With the
+//! `deterministic-math` feature the pure Rust implementations of the libm crate are used,
+//! which return bit identical results on all platforms.
+//!
+//! Affected functions: sin, cos, tan, asin, acos, atan, atan2, sinh, cosh, tanh, asinh, acosh,
+//! atanh, exp, exp2, expm1, log, log2, log10, cbrt, hypot and the power (`^`, `pow`, `powf`),
+//! as well as the gamma based factorial, gamma and binom.
+//! Not affected are abs, sqrt, ceil, floor, round, fract, sign, min, max and the arithmetic
+//! operators, which IEEE 754 defines exactly on all platforms.
+
+/// Define functions of one argument forwarding to libm or the standard library.
+macro_rules! unary_functions {
+    ($($(#[$doc:meta])* $name:ident => $libm:ident;)*) => {
+        $(
+            $(#[$doc])*
+            #[inline]
+            pub(crate) fn $name(x: f64) -> f64 {
+                #[cfg(feature = "deterministic-math")]
+                {
+                    libm::$libm(x)
+                }
+                #[cfg(not(feature = "deterministic-math"))]
+                {
+                    x.$name()
+                }
+            }
+        )*
+    };
+}
+
+/// Define functions of two arguments forwarding to libm or the standard library.
+macro_rules! binary_functions {
+    ($($(#[$doc:meta])* $name:ident => $libm:ident;)*) => {
+        $(
+            $(#[$doc])*
+            #[inline]
+            pub(crate) fn $name(x: f64, y: f64) -> f64 {
+                #[cfg(feature = "deterministic-math")]
+                {
+                    libm::$libm(x, y)
+                }
+                #[cfg(not(feature = "deterministic-math"))]
+                {
+                    x.$name(y)
+                }
+            }
+        )*
+    };
+}
+
+unary_functions! {
+    /// Sine
+    sin => sin;
+    /// Cosine
+    cos => cos;
+    /// Tangent
+    tan => tan;
+    /// Arcsine
+    asin => asin;
+    /// Arccosine
+    acos => acos;
+    /// Arctangent
+    atan => atan;
+    /// Hyperbolic sine
+    sinh => sinh;
+    /// Hyperbolic cosine
+    cosh => cosh;
+    /// Hyperbolic tangent
+    tanh => tanh;
+    /// Inverse hyperbolic sine
+    asinh => asinh;
+    /// Inverse hyperbolic cosine
+    acosh => acosh;
+    /// Inverse hyperbolic tangent
+    atanh => atanh;
+    /// Exponential function
+    exp => exp;
+    /// Power of two
+    exp2 => exp2;
+    /// Exponential function minus one
+    exp_m1 => expm1;
+    /// Natural logarithm
+    ln => log;
+    /// Logarithm to base 2
+    log2 => log2;
+    /// Logarithm to base 10
+    log10 => log10;
+    /// Cubic root
+    cbrt => cbrt;
+}
+
+binary_functions! {
+    /// Arctangent of x / y using the signs of both arguments
+    atan2 => atan2;
+    /// Length of the hypotenuse
+    hypot => hypot;
+    /// Power x^y
+    powf => pow;
+}
@@ -0,0 +1,312 @@
+// Copyright © 2020-2021 HQS Quantum Simulations GmbH. All Rights Reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except
+// in compliance with the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the
+// License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either
+// express or implied. See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! serialize module
+//!
+//! Provides serde helper modules for alternative serialized forms that can be
+//! selected with `#[serde(with = "...")]`.
+
+/// Serialize CalculatorComplex as a map with `re` and `im` fields.
+///
+/// The default serialization of CalculatorComplex is a tuple `(re, im)`. Inside
+/// `#[serde(untagged)]` enums that tuple can not be distinguished from other variants
+/// holding a pair of values like `(f64, f64)`, and a tuple can not be used with
+/// `#[serde(flatten)]`. The map form `{"re": ..., "im": ...}` avoids both problems.
+///
+/// For JSON schemas, use [tagged_complex::TaggedComplex] as the schema of the field
+/// with `#[schemars(with = "TaggedComplex")]`.
+///
+/// # Example
+///
+/// ```
+/// use qoqo_calculator::serialize::tagged_complex;
+/// use qoqo_calculator::CalculatorComplex;
+/// use serde::{Deserialize, Serialize};
+///
+/// #[derive(Serialize, Deserialize)]
+/// #[serde(untagged)]
+/// enum Involved {
+///     Pair((f64, f64)),
+///     Complex(#[serde(with = "tagged_complex")] CalculatorComplex),
+/// }
+/// ```
+pub mod tagged_complex {
+    use crate::{CalculatorComplex, CalculatorFloat};
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    /// Map form of a CalculatorComplex.
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    #[cfg_attr(feature = "json_schema", derive(schemars::JsonSchema))]
+    pub struct TaggedComplex {
+        /// Real part
+        pub re: CalculatorFloat,
+        /// Imaginary part
+        pub im: CalculatorFloat,
+    }
+
+    /// Borrowed map form used for serialization without cloning.
+    #[derive(Serialize)]
+    #[serde(rename = "TaggedComplex")]
+    struct TaggedComplexRef<'a> {
+        re: &'a CalculatorFloat,
+        im: &'a CalculatorFloat,
+    }
+
+    impl From<CalculatorComplex> for TaggedComplex {
+        fn from(value: CalculatorComplex) -> Self {
+            TaggedComplex {
+                re: value.re,
+                im: value.im,
+            }
+        }
+    }
+
+    impl From<TaggedComplex> for CalculatorComplex {
+        fn from(value: TaggedComplex) -> Self {
+            CalculatorComplex {
+                re: value.re,
+                im: value.im,
+            }
+        }
+    }
+
+    /// Serialize a CalculatorComplex as a map with `re` and `im` fields.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - CalculatorComplex that is serialized
+    /// * `serializer` - Serializer used for serialization
+    ///
+    pub fn serialize<S>(value: &CalculatorComplex, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        TaggedComplexRef {
+            re: &value.re,
+            im: &value.im,
+        }
+        .serialize(serializer)
+    }
+
+    /// Deserialize a CalculatorComplex from a map with `re` and `im` fields.
+    ///
+    /// # Arguments
+    ///
+    /// * `deserializer` - Deserializer used for deserialization
+    ///
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<CalculatorComplex, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        TaggedComplex::deserialize(deserializer).map(CalculatorComplex::from)
+    }
+}
+
+/// Serialize CalculatorFloat in the enum-tagged form in every format.
+///
+/// By default CalculatorFloat is serialized as bare value (`0.5` or `"theta"`) in
+/// human-readable formats and in the enum-tagged form in other formats. With this module
+/// the field always uses the tagged form, `{"Float": 0.5}` or `{"Str": "theta"}` in JSON.
+/// Deserialization only accepts the tagged form.
+///
+/// # Example
+///
+/// ```
+/// use qoqo_calculator::serialize::always_tagged;
+/// use qoqo_calculator::CalculatorFloat;
+/// use serde::{Deserialize, Serialize};
+///
+/// #[derive(Serialize, Deserialize)]
+/// struct Gate {
+///     #[serde(with = "always_tagged")]
+///     theta: CalculatorFloat,
+/// }
+/// ```
+pub mod always_tagged {
+    use crate::calculator_float::{deserialize_tagged, serialize_tagged};
+    use crate::CalculatorFloat;
+    use serde::{Deserializer, Serializer};
+
+    /// Serialize a CalculatorFloat in the enum-tagged form.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - CalculatorFloat that is serialized
+    /// * `serializer` - Serializer used for serialization
+    ///
+    pub fn serialize<S>(value: &CalculatorFloat, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serialize_tagged(value, serializer)
+    }
+
+    /// Deserialize a CalculatorFloat from the enum-tagged form.
+    ///
+    /// # Arguments
+    ///
+    /// * `deserializer` - Deserializer used for deserialization
+    ///
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<CalculatorFloat, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserialize_tagged(deserializer)
+    }
+}
+
+/// Serialize CalculatorFloat as bare float or string in every format.
+///
+/// By default CalculatorFloat is serialized as bare value (`0.5` or `"theta"`) in
+/// human-readable formats and in the enum-tagged form in other formats. With this module
+/// the field always uses the bare value. Floats and symbolic expressions are never
+/// ambiguous, so serialization does not fail.
+///
+/// Deserialization accepts a float or a string and needs a self-describing format,
+/// it fails for formats like bincode. Strings that can be parsed as float,
+/// e.g. `"0.5"`, are deserialized to CalculatorFloat::Float.
+///
+/// # Example
+///
+/// ```
+/// use qoqo_calculator::serialize::always_untagged;
+/// use qoqo_calculator::CalculatorFloat;
+/// use serde::{Deserialize, Serialize};
+///
+/// #[derive(Serialize, Deserialize)]
+/// struct Gate {
+///     #[serde(with = "always_untagged")]
+///     theta: CalculatorFloat,
+/// }
+/// ```
+pub mod always_untagged {
+    use crate::calculator_float::{deserialize_untagged, serialize_untagged};
+    use crate::CalculatorFloat;
+    use serde::{Deserializer, Serializer};
+
+    /// Serialize a CalculatorFloat as bare float or string.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - CalculatorFloat that is serialized
+    /// * `serializer` - Serializer used for serialization
+    ///
+    pub fn serialize<S>(value: &CalculatorFloat, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serialize_untagged(value, serializer)
+    }
+
+    /// Deserialize a CalculatorFloat from a bare float or string.
+    ///
+    /// # Arguments
+    ///
+    /// * `deserializer` - Deserializer used for deserialization
+    ///
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<CalculatorFloat, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserialize_untagged(deserializer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{always_tagged, always_untagged, tagged_complex};
+    use crate::{CalculatorComplex, CalculatorFloat};
+    use serde::{Deserialize, Serialize};
+    use serde_test::{assert_tokens, Configure, Token};
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Wrapper(#[serde(with = "tagged_complex")] CalculatorComplex);
+
+    // Test the serialization/deserialization of CalculatorComplex as a map
+    #[test]
+    fn ser_de_tagged_complex() {
+        let x = Wrapper(CalculatorComplex::new(1, "a"));
+        assert_tokens(
+            &x.readable(),
+            &[
+                Token::NewtypeStruct { name: "Wrapper" },
+                Token::Struct {
+                    name: "TaggedComplex",
+                    len: 2,
+                },
+                Token::Str("re"),
+                Token::F64(1.0),
+                Token::Str("im"),
+                Token::String("a"),
+                Token::StructEnd,
+            ],
+        );
+    }
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    struct Tagged(#[serde(with = "always_tagged")] CalculatorFloat);
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    struct Untagged(#[serde(with = "always_untagged")] CalculatorFloat);
+
+    // Test that always_tagged uses the enum-tagged form in readable and compact formats
+    #[test]
+    fn ser_de_always_tagged() {
+        let float = Tagged(CalculatorFloat::from(0.5));
+        let tokens = [
+            Token::NewtypeStruct { name: "Tagged" },
+            Token::NewtypeVariant {
+                name: "CalculatorFloat",
+                variant: "Float",
+            },
+            Token::F64(0.5),
+        ];
+        assert_tokens(&float.clone().readable(), &tokens);
+        assert_tokens(&float.compact(), &tokens);
+        let symbolic = Tagged(CalculatorFloat::from("theta"));
+        let tokens = [
+            Token::NewtypeStruct { name: "Tagged" },
+            Token::NewtypeVariant {
+                name: "CalculatorFloat",
+                variant: "Str",
+            },
+            Token::String("theta"),
+        ];
+        assert_tokens(&symbolic.clone().readable(), &tokens);
+        assert_tokens(&symbolic.compact(), &tokens);
+    }
+
+    // Test that always_untagged uses the bare value in readable and compact formats
+    #[test]
+    fn ser_de_always_untagged() {
+        let float = Untagged(CalculatorFloat::from(0.5));
+        let tokens = [Token::NewtypeStruct { name: "Untagged" }, Token::F64(0.5)];
+        assert_tokens(&float.clone().readable(), &tokens);
+        assert_tokens(&float.compact(), &tokens);
+        let symbolic = Untagged(CalculatorFloat::from("theta"));
+        let tokens = [
+            Token::NewtypeStruct { name: "Untagged" },
+            Token::String("theta"),
+        ];
+        assert_tokens(&symbolic.clone().readable(), &tokens);
+        assert_tokens(&symbolic.compact(), &tokens);
+    }
+
+    // Test the JSON schema of the map form of CalculatorComplex
+    #[cfg(feature = "json_schema")]
+    #[test]
+    fn test_json_schema_support() {
+        let schema = schemars::schema_for!(tagged_complex::TaggedComplex);
+        let serialized = serde_json::to_string(&schema).unwrap();
+        assert_eq!(serialized.as_str(), "{\"$schema\":\"http://json-schema.org/draft-07/schema#\",\"title\":\"TaggedComplex\",\"description\":\"Map form of a CalculatorComplex.\",\"type\":\"object\",\"required\":[\"im\",\"re\"],\"properties\":{\"im\":{\"description\":\"Imaginary part\",\"allOf\":[{\"$ref\":\"#/definitions/CalculatorFloat\"}]},\"re\":{\"description\":\"Real part\",\"allOf\":[{\"$ref\":\"#/definitions/CalculatorFloat\"}]}},\"definitions\":{\"CalculatorFloat\":{\"oneOf\":[{\"type\":\"number\",\"format\":\"double\"},{\"type\":\"string\"}]}}}");
+    }
+}
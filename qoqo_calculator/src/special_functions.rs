@@ -0,0 +1,697 @@
+// Copyright © 2020-2021 HQS Quantum Simulations GmbH. All Rights Reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except
+// in compliance with the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the
+// License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either
+// express or implied. See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! special_functions module
+//!
+//! Provides the factorial, binomial coefficient, gamma, log-gamma, error and parity functions
+//! of the Calculator.
+//!
+//! Integer valued arguments are evaluated with exact integer arithmetic (u128) as long as
+//! the result fits, so results up to 2^53 are exact and larger results are correctly rounded.
+//! Beyond that and for non-integer arguments the continuous extension by the gamma function
+//! (Lanczos approximation) is used, which has a relative precision of about 1e-13.
+//!
+//! The error function is evaluated with a series of positive terms for small arguments and
+//! a continued fraction of the complementary error function for large arguments, with a
+//! relative precision of a few ulp.
+//!
+//! Also provides the rounding functions round_digits and round_step.
+
+use crate::math;
+use crate::CalculatorError;
+use std::f64::consts::{FRAC_2_SQRT_PI, PI};
+
+/// Largest n for which n! fits into u128.
+const MAX_EXACT_FACTORIAL: f64 = 34.0;
+
+/// Largest integer below which all integers are exactly representable by f64 (2^53).
+const MAX_SAFE_INTEGER: f64 = 9007199254740992.0;
+
+/// Parameter g of the Lanczos approximation.
+const LANCZOS_G: f64 = 7.0;
+
+/// Argument above which the error function is evaluated with the continued fraction.
+const ERF_CONTINUED_FRACTION_START: f64 = 2.0;
+
+/// Argument above which the error function is one in f64 precision.
+const ERF_ONE: f64 = 6.0;
+
+/// Number of terms of the continued fraction of the complementary error function.
+const ERFC_CONTINUED_FRACTION_TERMS: usize = 60;
+
+/// Coefficients of the Lanczos approximation for g = 7.
+const LANCZOS_COEFFICIENTS: [f64; 9] = [
+    0.999_999_999_999_809_9,
+    676.520_368_121_885_1,
+    -1_259.139_216_722_402_8,
+    771.323_428_777_653_1,
+    -176.615_029_162_140_6,
+    12.507_343_278_686_905,
+    -0.138_571_095_265_720_12,
+    9.984_369_578_019_572e-6,
+    1.505_632_735_149_311_6e-7,
+];
+
+/// Return the integer closest to a float when the float is integer valued.
+///
+/// The same relative tolerance of `f64::EPSILON` is used by all functions of this module,
+/// so e.g. `3.0000000000000004` counts as 3.
+///
+/// # Arguments
+///
+/// * `x` - Float value that is checked
+///
+pub(crate) fn integer_value(x: f64) -> Option<f64> {
+    let rounded = x.round();
+    if (x - rounded).abs() <= f64::EPSILON * rounded.abs().max(1.0) {
+        Some(rounded)
+    } else {
+        None
+    }
+}
+
+/// Return the error of a function at an argument where it is not defined.
+fn invalid(fct: &'static str, value: f64) -> CalculatorError {
+    CalculatorError::InvalidFunctionArgument { fct, value }
+}
+
+/// Return n! for n <= 34 with exact integer arithmetic.
+fn exact_factorial(n: f64) -> f64 {
+    (1..=n as u128).product::<u128>() as f64
+}
+
+/// Return the binomial coefficient with exact integer arithmetic, None when it does not fit into u128.
+fn exact_binomial(n: u64, k: u64) -> Option<u128> {
+    let mut coefficient: u128 = 1;
+    for i in 0..k {
+        // coefficient * (n - i) / (i + 1) is the next binomial coefficient, the division is exact
+        coefficient = coefficient.checked_mul(u128::from(n - i))? / u128::from(i + 1);
+    }
+    Some(coefficient)
+}
+
+/// Return the sum of the Lanczos series and the shifted argument t for x >= 0.5.
+fn lanczos_sum(x: f64) -> (f64, f64) {
+    let x = x - 1.0;
+    let sum = LANCZOS_COEFFICIENTS
+        .iter()
+        .enumerate()
+        .skip(1)
+        .fold(LANCZOS_COEFFICIENTS[0], |sum, (index, coefficient)| {
+            sum + coefficient / (x + index as f64)
+        });
+    (sum, x + LANCZOS_G + 0.5)
+}
+
+/// Return the gamma function of x with the Lanczos approximation, x is not a pole.
+fn lanczos_gamma(x: f64) -> f64 {
+    if x == f64::INFINITY {
+        x
+    } else if x < 0.5 {
+        // Reflection formula
+        PI / (math::sin(PI * x) * lanczos_gamma(1.0 - x))
+    } else {
+        let (sum, t) = lanczos_sum(x);
+        // The power is split in two halves so it does not overflow before the product
+        let half_power = math::powf(t, (x - 0.5) / 2.0);
+        (2.0 * PI).sqrt() * half_power * (half_power * math::exp(-t)) * sum
+    }
+}
+
+/// Return the natural logarithm of the gamma function of x for x >= 0.5.
+fn ln_gamma(x: f64) -> f64 {
+    let (sum, t) = lanczos_sum(x);
+    0.5 * math::ln(2.0 * PI) + (x - 0.5) * math::ln(t) - t + math::ln(sum)
+}
+
+/// Gamma function, as evaluated by the `gamma` function.
+///
+/// Exact for integer arguments up to 35, returns an error at the poles 0, -1, -2, ...
+///
+/// # Arguments
+///
+/// * `x` - Argument of the gamma function
+///
+pub(crate) fn gamma(x: f64) -> Result<f64, CalculatorError> {
+    match integer_value(x) {
+        Some(n) if n <= 0.0 => Err(invalid("gamma", x)),
+        Some(n) if n <= MAX_EXACT_FACTORIAL + 1.0 => Ok(exact_factorial(n - 1.0)),
+        _ => Ok(lanczos_gamma(x)),
+    }
+}
+
+/// Gamma function, as evaluated by the `tgamma` function.
+///
+/// Same as [gamma], the name follows the C standard library.
+///
+/// # Arguments
+///
+/// * `x` - Argument of the gamma function
+///
+pub(crate) fn tgamma(x: f64) -> Result<f64, CalculatorError> {
+    gamma(x).map_err(|_| invalid("tgamma", x))
+}
+
+/// Natural logarithm of the absolute value of the gamma function, as evaluated by `lgamma`.
+///
+/// Exact logarithms of integer factorials up to 34!, returns an error at the poles 0, -1, -2, ...
+/// Does not overflow for large arguments, unlike the logarithm of [gamma].
+///
+/// # Arguments
+///
+/// * `x` - Argument of the gamma function
+///
+pub(crate) fn lgamma(x: f64) -> Result<f64, CalculatorError> {
+    match integer_value(x) {
+        Some(n) if n <= 0.0 => Err(invalid("lgamma", x)),
+        Some(n) if n <= MAX_EXACT_FACTORIAL + 1.0 => Ok(math::ln(exact_factorial(n - 1.0))),
+        _ if x.is_nan() || x == f64::INFINITY => Ok(x),
+        _ if x < 0.5 => {
+            // Reflection formula, the gamma function of 1 - x is positive
+            Ok(math::ln(PI / math::sin(PI * x).abs()) - ln_gamma(1.0 - x))
+        }
+        _ => Ok(ln_gamma(x)),
+    }
+}
+
+/// Error function, as evaluated by the `erf` function.
+///
+/// # Arguments
+///
+/// * `x` - Argument of the error function
+///
+pub(crate) fn erf(x: f64) -> f64 {
+    if x.is_nan() {
+        return x;
+    }
+    let absolute = x.abs();
+    let value = if absolute >= ERF_ONE {
+        1.0
+    } else if absolute >= ERF_CONTINUED_FRACTION_START {
+        1.0 - erfc_continued_fraction(absolute)
+    } else {
+        // erf(x) = 2 / sqrt(pi) exp(-x^2) sum_n 2^n x^(2n+1) / (1 * 3 * ... * (2n+1)),
+        // all terms are positive so there is no cancellation
+        let square = absolute * absolute;
+        let mut term = absolute;
+        let mut sum = absolute;
+        let mut n = 0.0;
+        while term > f64::EPSILON * sum / 2.0 {
+            n += 1.0;
+            term *= 2.0 * square / (2.0 * n + 1.0);
+            sum += term;
+        }
+        FRAC_2_SQRT_PI * math::exp(-square) * sum
+    };
+    value.copysign(x)
+}
+
+/// Return the complementary error function of x >= ERF_CONTINUED_FRACTION_START.
+///
+/// erfc(x) = exp(-x^2) / sqrt(pi) / (x + (1/2) / (x + 1 / (x + (3/2) / (x + ...)))),
+/// evaluated from the innermost term.
+fn erfc_continued_fraction(x: f64) -> f64 {
+    let fraction = (1..=ERFC_CONTINUED_FRACTION_TERMS)
+        .rev()
+        .fold(x, |denominator, n| x + n as f64 / 2.0 / denominator);
+    FRAC_2_SQRT_PI / 2.0 * math::exp(-x * x) / fraction
+}
+
+/// Return 1 / gamma(x), which is zero at the poles of the gamma function.
+fn reciprocal_gamma(x: f64) -> f64 {
+    gamma(x).map_or(0.0, |value| 1.0 / value)
+}
+
+/// Factorial, as evaluated by the `factorial` function.
+///
+/// Exact for integer arguments up to 34, gamma(x + 1) for larger and non-integer arguments.
+/// Returns an error for negative integers.
+///
+/// # Arguments
+///
+/// * `x` - Argument of the factorial
+///
+pub(crate) fn factorial(x: f64) -> Result<f64, CalculatorError> {
+    match integer_value(x) {
+        Some(n) if n < 0.0 => Err(invalid("factorial", x)),
+        Some(n) if n <= MAX_EXACT_FACTORIAL => Ok(exact_factorial(n)),
+        Some(n) => Ok(lanczos_gamma(n + 1.0)),
+        None => Ok(lanczos_gamma(x + 1.0)),
+    }
+}
+
+/// Binomial coefficient, as evaluated by the `binom` function.
+///
+/// For integer arguments with n >= 0 the coefficient is zero for k < 0 and k > n and
+/// computed exactly while it fits into u128, above that via the logarithm of the gamma function.
+/// Non-integer arguments use gamma(n + 1) / (gamma(k + 1) gamma(n - k + 1)).
+/// Returns an error for negative integers n.
+///
+/// # Arguments
+///
+/// * `n` - Number of elements
+/// * `k` - Number of chosen elements
+///
+pub(crate) fn binom(n: f64, k: f64) -> Result<f64, CalculatorError> {
+    if n.is_nan() || k.is_nan() {
+        return Ok(f64::NAN);
+    }
+    match (integer_value(n), integer_value(k)) {
+        (Some(n_int), _) if n_int < 0.0 => Err(invalid("binom", n)),
+        (Some(n), Some(k)) => {
+            if k < 0.0 || k > n {
+                return Ok(0.0);
+            }
+            let k = k.min(n - k);
+            if n <= MAX_SAFE_INTEGER {
+                if let Some(exact) = exact_binomial(n as u64, k as u64) {
+                    return Ok(exact as f64);
+                }
+            }
+            Ok(math::exp(
+                ln_gamma(n + 1.0) - ln_gamma(k + 1.0) - ln_gamma(n - k + 1.0),
+            ))
+        }
+        _ => Ok(lanczos_gamma(n + 1.0) * reciprocal_gamma(k + 1.0) * reciprocal_gamma(n - k + 1.0)),
+    }
+}
+
+/// Parity of an integer, as evaluated by the `parity` function.
+///
+/// Returns 1.0 for even and -1.0 for odd integers, integers above 2^53 are even.
+/// Returns an error for arguments that are not integer valued.
+///
+/// # Arguments
+///
+/// * `x` - Integer valued argument
+///
+pub(crate) fn parity(x: f64) -> Result<f64, CalculatorError> {
+    if x.is_nan() {
+        return Ok(f64::NAN);
+    }
+    match integer_value(x) {
+        Some(n) if n % 2.0 == 0.0 => Ok(1.0),
+        Some(_) => Ok(-1.0),
+        None => Err(invalid("parity", x)),
+    }
+}
+
+/// Round a float to a number of decimal digits after the decimal point.
+///
+/// The shortest decimal representation of the float, the one its Display shows, is rounded
+/// half away from zero. So a value shown as a tie is rounded like the decimal number,
+/// e.g. 0.00005 to 4 digits gives 0.0001 and 2.675 to 2 digits gives 2.68,
+/// although the binary values of 0.00005 and 2.675 are slightly above and below the tie.
+/// Negative digits round to tens, hundreds, ... Non-finite values are returned unchanged.
+///
+/// # Arguments
+///
+/// * `x` - Value that is rounded
+/// * `digits` - Number of decimal digits after the decimal point
+///
+pub(crate) fn round_to_digits(x: f64, digits: i32) -> f64 {
+    if !x.is_finite() || x == 0.0 {
+        return x;
+    }
+    let representation = format!("{:e}", x.abs());
+    let (mantissa, exponent) = representation
+        .split_once('e')
+        .expect("Exponential format of a float contains an exponent");
+    let exponent: i64 = exponent.parse().expect("Exponent of a float is an integer");
+    let mut decimals: Vec<u8> = mantissa
+        .bytes()
+        .filter(|byte| *byte != b'.')
+        .map(|byte| byte - b'0')
+        .collect();
+    // Number of significant decimals before the rounding position
+    let kept = exponent + 1 + i64::from(digits);
+    if kept >= decimals.len() as i64 {
+        return x;
+    }
+    if kept < 0 {
+        return 0.0_f64.copysign(x);
+    }
+    let round_up = decimals[kept as usize] >= 5;
+    decimals.truncate(kept as usize);
+    if round_up {
+        match decimals.iter().rposition(|decimal| *decimal != 9) {
+            Some(index) => {
+                decimals[index] += 1;
+                decimals[index + 1..].fill(0);
+            }
+            None => {
+                decimals.fill(0);
+                decimals.insert(0, 1);
+            }
+        }
+    }
+    if decimals.is_empty() {
+        return 0.0_f64.copysign(x);
+    }
+    // The kept decimals are the rounded value in units of 10^-digits
+    let integer: String = decimals
+        .iter()
+        .map(|decimal| char::from(b'0' + decimal))
+        .collect();
+    let rounded: f64 = format!("{integer}e{}", -i64::from(digits))
+        .parse()
+        .expect("Rounded decimals form a float");
+    rounded.copysign(x)
+}
+
+/// Rounding to decimal digits, as evaluated by the `round_digits` function.
+///
+/// See [round_to_digits], returns an error when the number of digits is not integer valued.
+///
+/// # Arguments
+///
+/// * `x` - Value that is rounded
+/// * `digits` - Integer valued number of decimal digits after the decimal point
+///
+pub(crate) fn round_digits(x: f64, digits: f64) -> Result<f64, CalculatorError> {
+    match integer_value(digits) {
+        // More than 400 digits are beyond the range of f64 in both directions
+        Some(digits) => Ok(round_to_digits(x, digits.clamp(-400.0, 400.0) as i32)),
+        None => Err(invalid("round_digits", digits)),
+    }
+}
+
+/// Check that a float is a valid step of [round_step].
+///
+/// # Arguments
+///
+/// * `step` - Step that is checked, must be positive and finite
+///
+pub(crate) fn check_round_step(step: f64) -> Result<(), CalculatorError> {
+    if step > 0.0 && step.is_finite() {
+        Ok(())
+    } else {
+        Err(invalid("round_step", step))
+    }
+}
+
+/// Rounding to the nearest multiple of a step, as evaluated by the `round_step` function.
+///
+/// Ties are rounded half away from zero. The remainder of the division by the step is
+/// computed exactly, so ties are decided on the exact binary values of x and step.
+/// For steps that are reciprocals of integers, like 1e-4, the result is the float
+/// closest to the decimal multiple, e.g. 0.123456 with step 1e-4 gives 0.1235.
+/// Non-finite values are returned unchanged, returns an error when the step is not
+/// positive and finite.
+///
+/// # Arguments
+///
+/// * `x` - Value that is rounded
+/// * `step` - Positive step the value is rounded to a multiple of
+///
+pub(crate) fn round_step(x: f64, step: f64) -> Result<f64, CalculatorError> {
+    check_round_step(step)?;
+    if !x.is_finite() {
+        return Ok(x);
+    }
+    let remainder = x % step;
+    let mut multiples = ((x - remainder) / step).round();
+    if 2.0 * remainder.abs() >= step {
+        multiples += x.signum();
+    }
+    // For steps like 1e-4 the division by the integer 1e4 is the float closest to the
+    // multiple, the product with the binary value of the step can be one bit off
+    let inverse = step.recip();
+    let rounded = if inverse.fract() == 0.0 && inverse.recip() == step {
+        multiples / inverse
+    } else {
+        multiples * step
+    };
+    Ok(rounded.copysign(x))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        binom, erf, factorial, gamma, integer_value, lgamma, parity, round_digits, round_step,
+        round_to_digits, tgamma,
+    };
+    use crate::CalculatorError;
+    use std::f64::consts::{FRAC_2_SQRT_PI, PI};
+
+    /// Assert that two values agree within a relative tolerance.
+    fn assert_close(value: f64, expected: f64, tolerance: f64) {
+        assert!(
+            (value - expected).abs() <= tolerance * expected.abs(),
+            "{value} != {expected}"
+        );
+    }
+
+    // Test the detection of integer valued arguments
+    #[test]
+    fn integer_detection() {
+        assert_eq!(integer_value(3.0), Some(3.0));
+        assert_eq!(integer_value(3.0000000000000004), Some(3.0));
+        assert_eq!(integer_value(-2.0), Some(-2.0));
+        assert_eq!(integer_value(3.000001), None);
+        assert_eq!(integer_value(0.5), None);
+        assert_eq!(integer_value(f64::NAN), None);
+    }
+
+    // Test exact factorials and the gamma based continuation
+    #[test]
+    fn factorials() {
+        assert_eq!(factorial(0.0), Ok(1.0));
+        assert_eq!(factorial(20.0), Ok(2432902008176640000.0));
+        assert_eq!(factorial(18.0), Ok(6402373705728000.0));
+        // 34! is the correctly rounded value of the exact integer
+        assert_eq!(
+            factorial(34.0),
+            Ok(295232799039604140847618609643520000000_u128 as f64)
+        );
+        assert_close(factorial(35.0).unwrap(), 1.0333147966386145e40, 1e-13);
+        assert_close(factorial(100.0).unwrap(), 9.332621544394415e157, 1e-12);
+        assert_eq!(factorial(171.0), Ok(f64::INFINITY));
+        assert_close(factorial(0.5).unwrap(), PI.sqrt() / 2.0, 1e-14);
+        assert_close(factorial(-0.5).unwrap(), PI.sqrt(), 1e-14);
+        assert!(factorial(f64::NAN).unwrap().is_nan());
+        assert_eq!(
+            factorial(-1.0),
+            Err(CalculatorError::InvalidFunctionArgument {
+                fct: "factorial",
+                value: -1.0
+            })
+        );
+    }
+
+    // Test the gamma function against reference values
+    #[test]
+    fn gamma_function() {
+        assert_close(gamma(0.5).unwrap(), PI.sqrt(), 1e-14);
+        assert_close(gamma(-0.5).unwrap(), -2.0 * PI.sqrt(), 1e-14);
+        assert_close(gamma(1.5).unwrap(), PI.sqrt() / 2.0, 1e-14);
+        assert_close(gamma(0.1).unwrap(), 9.513507698668732, 1e-14);
+        assert_close(gamma(10.3).unwrap(), 716430.6890623765, 1e-13);
+        assert_close(gamma(171.5).unwrap(), 9.483367566824801e307, 1e-12);
+        assert_eq!(gamma(5.0), Ok(24.0));
+        assert_eq!(gamma(f64::INFINITY), Ok(f64::INFINITY));
+        for pole in [0.0, -1.0, -7.0] {
+            assert_eq!(
+                gamma(pole),
+                Err(CalculatorError::InvalidFunctionArgument {
+                    fct: "gamma",
+                    value: pole
+                })
+            );
+        }
+    }
+
+    // Test exact binomial coefficients and the continuous extension
+    #[test]
+    fn binomial_coefficients() {
+        assert_eq!(binom(52.0, 5.0), Ok(2598960.0));
+        assert_eq!(binom(5.0, 0.0), Ok(1.0));
+        assert_eq!(binom(5.0, 5.0), Ok(1.0));
+        assert_eq!(binom(5.0, 6.0), Ok(0.0));
+        assert_eq!(binom(5.0, -1.0), Ok(0.0));
+        assert_eq!(binom(0.0, 0.0), Ok(1.0));
+        assert_eq!(
+            binom(100.0, 50.0),
+            Ok(100891344545564193334812497256_u128 as f64)
+        );
+        assert_eq!(binom(1e15, 1.0), Ok(1e15));
+        // Too large for u128, evaluated via the logarithm of the gamma function
+        assert_close(binom(1000.0, 500.0).unwrap(), 2.7028824094543655e299, 1e-10);
+        assert_close(binom(2.5, 1.0).unwrap(), 2.5, 1e-14);
+        assert_close(binom(0.5, 2.0).unwrap(), -0.125, 1e-14);
+        assert_close(binom(4.0, 1.5).unwrap(), 768.0 / (45.0 * PI), 1e-13);
+        assert!(binom(f64::NAN, 1.0).unwrap().is_nan());
+        assert_eq!(
+            binom(-1.0, 2.0),
+            Err(CalculatorError::InvalidFunctionArgument {
+                fct: "binom",
+                value: -1.0
+            })
+        );
+        assert!(binom(-3.0, 0.5).is_err());
+    }
+
+    // Test the parity of integers
+    #[test]
+    fn parity_function() {
+        assert_eq!(parity(0.0), Ok(1.0));
+        assert_eq!(parity(3.0), Ok(-1.0));
+        assert_eq!(parity(-3.0), Ok(-1.0));
+        assert_eq!(parity(4.0000000000000001), Ok(1.0));
+        assert_eq!(parity(1e20), Ok(1.0));
+        assert!(parity(f64::NAN).unwrap().is_nan());
+        assert_eq!(
+            parity(0.5),
+            Err(CalculatorError::InvalidFunctionArgument {
+                fct: "parity",
+                value: 0.5
+            })
+        );
+    }
+
+    // Test rounding to decimal digits, ties are rounded away from zero
+    #[test]
+    fn rounding_to_digits() {
+        assert_eq!(round_to_digits(0.00005, 4), 0.0001);
+        assert_eq!(round_to_digits(-0.00005, 4), -0.0001);
+        assert_eq!(round_to_digits(0.000049999, 4), 0.0);
+        assert_eq!(round_to_digits(2.675, 2), 2.68);
+        assert_eq!(round_to_digits(-1.005, 2), -1.01);
+        assert_eq!(round_to_digits(0.12344, 4), 0.1234);
+        assert_eq!(round_to_digits(9.9996, 3), 10.0);
+        assert_eq!(round_to_digits(0.5, 0), 1.0);
+        assert_eq!(round_to_digits(0.4, 0), 0.0);
+        assert_eq!(round_to_digits(1250.0, -2), 1300.0);
+        assert_eq!(round_to_digits(49.0, -2), 0.0);
+        assert_eq!(round_to_digits(1.5e300, -300), 2e300);
+        assert_eq!(round_to_digits(0.1, 20), 0.1);
+        assert!(round_to_digits(-0.00001, 4).is_sign_negative());
+        assert!(round_to_digits(f64::NAN, 2).is_nan());
+        assert_eq!(round_to_digits(f64::INFINITY, 2), f64::INFINITY);
+        assert_eq!(round_digits(2.675, 2.0), Ok(2.68));
+        assert_eq!(round_digits(1.5, 1e10), Ok(1.5));
+        assert_eq!(
+            round_digits(2.675, 1.5),
+            Err(CalculatorError::InvalidFunctionArgument {
+                fct: "round_digits",
+                value: 1.5
+            })
+        );
+    }
+
+    // Test rounding to multiples of a step, ties are rounded away from zero
+    #[test]
+    fn rounding_to_step() {
+        assert_eq!(round_step(0.00005, 0.0001), Ok(0.0001));
+        assert_eq!(round_step(-0.00005, 0.0001), Ok(-0.0001));
+        assert_eq!(round_step(0.123456, 0.0001), Ok(0.1235));
+        assert_eq!(round_step(0.625, 0.25), Ok(0.75));
+        assert_eq!(round_step(-0.625, 0.25), Ok(-0.75));
+        assert_eq!(round_step(0.6, 0.25), Ok(0.5));
+        assert_eq!(round_step(7.0, 5.0), Ok(5.0));
+        assert_eq!(round_step(7.5, 5.0), Ok(10.0));
+        assert_eq!(round_step(0.0, 0.1), Ok(0.0));
+        assert!(round_step(-0.00001, 0.0001).unwrap().is_sign_negative());
+        assert!(round_step(f64::NAN, 0.1).unwrap().is_nan());
+        for step in [0.0, -0.1, f64::NAN, f64::INFINITY] {
+            assert!(matches!(
+                round_step(1.0, step),
+                Err(CalculatorError::InvalidFunctionArgument {
+                    fct: "round_step",
+                    ..
+                })
+            ));
+        }
+    }
+
+    // Test the error function against reference values of the C standard library
+    #[test]
+    fn error_function() {
+        for (x, expected) in [
+            (1e-10, 1.1283791670955126e-10),
+            (0.1, 0.1124629160182849),
+            (0.5, 0.5204998778130465),
+            (1.0, 0.8427007929497149),
+            (1.5, 0.9661051464753108),
+            (2.0, 0.9953222650189527),
+            (2.5, 0.999593047982555),
+            (2.9999, 0.9999778955735178),
+            (3.0, 0.9999779095030014),
+            (3.5, 0.9999992569016276),
+            (4.0, 0.9999999845827421),
+            (5.0, 0.9999999999984626),
+            (5.9, 0.9999999999999999),
+            (-0.7, -0.6778011938374184),
+            (7.0, 1.0),
+            (f64::INFINITY, 1.0),
+            (f64::NEG_INFINITY, -1.0),
+        ] {
+            let value = erf(x);
+            assert!(
+                (value - expected).abs() <= 8.0 * f64::EPSILON * expected.abs(),
+                "erf({x}) = {value} != {expected}"
+            );
+        }
+        assert!(erf(-0.0).is_sign_negative());
+        assert!(erf(f64::NAN).is_nan());
+        // Derivative at zero
+        assert_eq!(erf(1e-300) / 1e-300, FRAC_2_SQRT_PI);
+    }
+
+    // Test tgamma and lgamma against the gamma function and reference values
+    #[test]
+    fn gamma_variants() {
+        for x in [0.5, 3.0, 7.25, -1.5] {
+            assert_eq!(tgamma(x), gamma(x));
+        }
+        assert_eq!(
+            tgamma(-2.0),
+            Err(CalculatorError::InvalidFunctionArgument {
+                fct: "tgamma",
+                value: -2.0
+            })
+        );
+        for (x, expected) in [
+            (0.5, 0.5723649429247004),
+            (1.5, -0.12078223763524543),
+            (2.5, 0.2846828704729196),
+            (10.5, 13.940625219403763),
+            (100.0, 359.1342053695754),
+            (171.5, 709.1431630309282),
+            (1000.3, 5907.29264478588),
+            (1e6, 12815504.569147611),
+            (-0.5, 1.265512123484645),
+            (-2.5, -0.05624371649767457),
+            (1e-5, 11.512919692895824),
+        ] {
+            let value = lgamma(x).unwrap();
+            assert!(
+                (value - expected).abs() <= 1e-13 * expected.abs().max(1.0),
+                "lgamma({x}) = {value} != {expected}"
+            );
+        }
+        // Exact for integers
+        assert_eq!(lgamma(1.0), Ok(0.0));
+        assert_eq!(lgamma(2.0), Ok(0.0));
+        assert_eq!(lgamma(3.0), Ok(2.0_f64.ln()));
+        assert_eq!(lgamma(21.0), Ok(2432902008176640000.0_f64.ln()));
+        assert_eq!(lgamma(f64::INFINITY), Ok(f64::INFINITY));
+        assert!(lgamma(f64::NAN).unwrap().is_nan());
+        assert_eq!(
+            lgamma(0.0),
+            Err(CalculatorError::InvalidFunctionArgument {
+                fct: "lgamma",
+                value: 0.0
+            })
+        );
+    }
+}
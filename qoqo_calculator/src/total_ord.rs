@@ -0,0 +1,176 @@
+// Copyright © 2020-2021 HQS Quantum Simulations GmbH. All Rights Reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except
+// in compliance with the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the
+// License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either
+// express or implied. See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! total_ord module
+//!
+//! Provides the TotalOrd wrapper ordering CalculatorFloat and CalculatorComplex values by
+//! their `total_cmp` methods, e.g. for keys of a BTreeMap.
+
+use crate::{CalculatorComplex, CalculatorFloat};
+use std::cmp::Ordering;
+
+/// Wrapper implementing [Ord] by [CalculatorFloat::total_cmp] or [CalculatorComplex::total_cmp].
+///
+/// Two wrapped values are equal when they are equal in the total order, so `-0.0` and `0.0`
+/// are different keys and NaNs with the same bits are the same key.
+///
+/// # Example
+///
+/// ```
+/// use qoqo_calculator::{CalculatorFloat, TotalOrd};
+/// use std::collections::BTreeMap;
+///
+/// let mut map: BTreeMap<TotalOrd<CalculatorFloat>, usize> = BTreeMap::new();
+/// map.insert(TotalOrd(CalculatorFloat::from("x")), 0);
+/// map.insert(TotalOrd(CalculatorFloat::from(f64::NAN)), 1);
+/// map.insert(TotalOrd(CalculatorFloat::from(1.0)), 2);
+/// let values: Vec<usize> = map.into_values().collect();
+/// assert_eq!(values, vec![2, 1, 0]);
+/// ```
+#[derive(Debug, Clone)]
+pub struct TotalOrd<T>(pub T);
+
+impl PartialEq for TotalOrd<CalculatorFloat> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for TotalOrd<CalculatorFloat> {}
+
+impl PartialOrd for TotalOrd<CalculatorFloat> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for TotalOrd<CalculatorFloat> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.total_cmp(&other.0)
+    }
+}
+
+impl PartialEq for TotalOrd<CalculatorComplex> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for TotalOrd<CalculatorComplex> {}
+
+impl PartialOrd for TotalOrd<CalculatorComplex> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for TotalOrd<CalculatorComplex> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.total_cmp(&other.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TotalOrd;
+    use crate::{CalculatorComplex, CalculatorFloat};
+    use std::collections::BTreeMap;
+
+    // Test that sorting shuffled mixed vectors gives the same documented order
+    #[test]
+    fn sort_shuffled() {
+        let nan = f64::NAN;
+        let sorted = vec![
+            CalculatorFloat::Float(-nan.abs()),
+            CalculatorFloat::Float(f64::NEG_INFINITY),
+            CalculatorFloat::Float(-1.5),
+            CalculatorFloat::Float(-0.0),
+            CalculatorFloat::Float(0.0),
+            CalculatorFloat::Float(2.0),
+            CalculatorFloat::Float(f64::INFINITY),
+            CalculatorFloat::Float(nan.abs()),
+            CalculatorFloat::Str("2".to_string()),
+            CalculatorFloat::Str("2e0".to_string()),
+            CalculatorFloat::Str("a".to_string()),
+            CalculatorFloat::Str("a + b".to_string()),
+            CalculatorFloat::Str("b".to_string()),
+        ];
+        // Shuffle with a fixed linear congruential generator
+        let mut state: u64 = 12345;
+        for _ in 0..20 {
+            let mut shuffled = sorted.clone();
+            for index in (1..shuffled.len()).rev() {
+                state = state
+                    .wrapping_mul(6364136223846793005)
+                    .wrapping_add(1442695040888963407);
+                shuffled.swap(index, (state >> 33) as usize % (index + 1));
+            }
+            shuffled.sort_by(CalculatorFloat::total_cmp);
+            // PartialEq fails for NaN, compare the bits of floats
+            assert_eq!(format!("{shuffled:?}"), format!("{sorted:?}"));
+            let bits: Vec<Option<u64>> = shuffled
+                .iter()
+                .map(|value| match value {
+                    CalculatorFloat::Float(x) => Some(x.to_bits()),
+                    CalculatorFloat::Str(_) => None,
+                })
+                .collect();
+            let expected: Vec<Option<u64>> = sorted
+                .iter()
+                .map(|value| match value {
+                    CalculatorFloat::Float(x) => Some(x.to_bits()),
+                    CalculatorFloat::Str(_) => None,
+                })
+                .collect();
+            assert_eq!(bits, expected);
+        }
+        let mut complex = vec![
+            CalculatorComplex::new("x", 0.0),
+            CalculatorComplex::new(1.0, "y"),
+            CalculatorComplex::new(1.0, -1.0),
+            CalculatorComplex::new(-0.0, 3.0),
+        ];
+        complex.sort_by(CalculatorComplex::total_cmp);
+        assert_eq!(
+            complex,
+            vec![
+                CalculatorComplex::new(-0.0, 3.0),
+                CalculatorComplex::new(1.0, -1.0),
+                CalculatorComplex::new(1.0, "y"),
+                CalculatorComplex::new("x", 0.0),
+            ]
+        );
+    }
+
+    // Test the wrapper as keys of a BTreeMap
+    #[test]
+    fn btree_map_keys() {
+        let mut map: BTreeMap<TotalOrd<CalculatorFloat>, &str> = BTreeMap::new();
+        map.insert(TotalOrd(CalculatorFloat::from("x")), "x");
+        map.insert(TotalOrd(CalculatorFloat::Float(0.0)), "zero");
+        map.insert(TotalOrd(CalculatorFloat::Float(-0.0)), "negative zero");
+        map.insert(TotalOrd(CalculatorFloat::Float(f64::NAN)), "nan");
+        map.insert(TotalOrd(CalculatorFloat::Float(f64::NAN)), "same nan");
+        assert_eq!(map.len(), 4);
+        assert_eq!(map[&TotalOrd(CalculatorFloat::Float(f64::NAN))], "same nan");
+        let values: Vec<&str> = map.values().copied().collect();
+        assert_eq!(values, vec!["negative zero", "zero", "same nan", "x"]);
+
+        let mut map: BTreeMap<TotalOrd<CalculatorComplex>, usize> = BTreeMap::new();
+        map.insert(TotalOrd(CalculatorComplex::new(1.0, "y")), 0);
+        map.insert(TotalOrd(CalculatorComplex::new(1.0, 2.0)), 1);
+        map.insert(TotalOrd(CalculatorComplex::new(1.0, 2.0)), 2);
+        assert_eq!(map.len(), 2);
+        let values: Vec<usize> = map.into_values().collect();
+        assert_eq!(values, vec![2, 0]);
+    }
+}
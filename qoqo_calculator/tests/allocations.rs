@@ -0,0 +1,71 @@
+// Copyright © 2020-2021 HQS Quantum Simulations GmbH. All Rights Reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except
+// in compliance with the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the
+// License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either
+// express or implied. See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Counts the heap allocations of parsing expressions in the steady state,
+//! after the expression has been compiled once.
+
+use qoqo_calculator::{Calculator, CalculatorFloat};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+
+/// Allocator counting the allocations of the current thread.
+struct CountingAllocator;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.with(|allocations| allocations.set(allocations.get() + 1));
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+/// Return the number of allocations of the current thread while running a closure.
+fn count_allocations<T>(run: impl FnOnce() -> T) -> (T, usize) {
+    let start = ALLOCATIONS.with(|allocations| allocations.get());
+    let result = run();
+    let end = ALLOCATIONS.with(|allocations| allocations.get());
+    (result, end - start)
+}
+
+// Test that parsing a numeric expression does not allocate once it is compiled
+#[test]
+fn numeric_expression_no_allocations() {
+    let calculator = Calculator::new();
+    let expression = "0.5*2 + sin(1e-1) ^ 2 - (3 / 4)";
+    let expected = calculator.parse_str(expression).unwrap();
+    let (value, allocations) = count_allocations(|| calculator.parse_str(expression));
+    assert_eq!(value, Ok(expected));
+    assert_eq!(allocations, 0);
+}
+
+// Test that reading variables and calling functions does not allocate once compiled
+#[test]
+fn symbolic_expression_no_allocations() {
+    let mut calculator = Calculator::new();
+    calculator.set_variable("theta", 0.3).unwrap();
+    calculator.set_variable("phi", -1.2).unwrap();
+    let expression = CalculatorFloat::from("theta * 2 + atan2(phi, theta) / max(theta, 1)");
+    let expected = calculator.parse_get_ref(&expression).unwrap();
+    let (value, allocations) = count_allocations(|| calculator.parse_get_ref(&expression));
+    assert_eq!(value, Ok(expected));
+    assert_eq!(allocations, 0);
+}
@@ -0,0 +1,118 @@
+// Copyright © 2020-2021 HQS Quantum Simulations GmbH. All Rights Reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except
+// in compliance with the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the
+// License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either
+// express or implied. See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Property tests of CalculatorFloat and CalculatorComplex using the generators of the
+//! `proptest` feature.
+
+#![cfg(feature = "proptest")]
+
+use proptest::prelude::*;
+use qoqo_calculator::arbitrary::{finite_float, symbolic_expression};
+use qoqo_calculator::{Calculator, CalculatorComplex, CalculatorError, CalculatorFloat};
+use std::str::FromStr;
+
+/// Return a Calculator with all variables of the generated expressions set.
+fn calculator() -> Calculator {
+    let mut calculator = Calculator::new();
+    for (name, value) in [
+        ("x", 0.5),
+        ("y", -1.25),
+        ("theta", 2.0),
+        ("phi", 0.1),
+        ("a_1", 3.0),
+        ("t0", -0.7),
+    ] {
+        calculator.set_variable(name, value).unwrap();
+    }
+    calculator
+}
+
+/// Return true when both values are the same float or both are NaN.
+fn same_value(left: f64, right: f64) -> bool {
+    left == right || (left.is_nan() && right.is_nan())
+}
+
+proptest! {
+    // Test that serialization to JSON and bincode round-trips CalculatorFloat
+    #[test]
+    fn float_serialization_round_trip(x in any::<CalculatorFloat>()) {
+        let json = serde_json::to_string(&x).unwrap();
+        prop_assert_eq!(serde_json::from_str::<CalculatorFloat>(&json).unwrap(), x.clone());
+        let bytes = bincode::serialize(&x).unwrap();
+        prop_assert_eq!(bincode::deserialize::<CalculatorFloat>(&bytes).unwrap(), x);
+    }
+
+    // Test that serialization to JSON and bincode round-trips CalculatorComplex
+    #[test]
+    fn complex_serialization_round_trip(x in any::<CalculatorComplex>()) {
+        let json = serde_json::to_string(&x).unwrap();
+        prop_assert_eq!(serde_json::from_str::<CalculatorComplex>(&json).unwrap(), x.clone());
+        let bytes = bincode::serialize(&x).unwrap();
+        prop_assert_eq!(bincode::deserialize::<CalculatorComplex>(&bytes).unwrap(), x);
+    }
+
+    // Test that adding zero and multiplying with one keep the value
+    #[test]
+    fn neutral_elements(x in any::<CalculatorFloat>(), z in any::<CalculatorComplex>()) {
+        prop_assert_eq!(x.clone() + 0.0, x.clone());
+        prop_assert_eq!(x.clone() * 1.0, x);
+        prop_assert_eq!(z.clone() + CalculatorComplex::ZERO, z.clone());
+        prop_assert_eq!(z.clone() * CalculatorComplex::ONE, z);
+    }
+
+    // Test that (a + b) - b is a up to the rounding of the sum for numeric values
+    #[test]
+    fn add_then_subtract(a in finite_float(), b in finite_float()) {
+        let sum = CalculatorFloat::from(a) + b;
+        prop_assume!(sum.float().unwrap().is_finite());
+        let difference = *(sum - b).float().unwrap();
+        prop_assert!((difference - a).abs() <= f64::EPSILON * (a.abs() + b.abs()));
+    }
+
+    // Test that generated expressions are accepted, displayed unchanged and can be evaluated
+    #[test]
+    fn symbolic_expressions_are_valid(expression in symbolic_expression(4)) {
+        let x = CalculatorFloat::from_str(&expression).unwrap();
+        prop_assert!(x.is_symbolic());
+        prop_assert_eq!(x.to_string(), expression);
+        prop_assert!(!x.variables().unwrap().is_empty());
+        let value = calculator().parse_get(x);
+        prop_assert!(
+            matches!(value, Ok(_) | Err(CalculatorError::DivisionByZero)),
+            "{:?}",
+            value
+        );
+    }
+
+    // Test that symbolic operations evaluate to the operation of the numeric values
+    #[test]
+    fn symbolic_arithmetic_matches_numeric(
+        x in symbolic_expression(3),
+        y in any::<CalculatorFloat>(),
+    ) {
+        let calculator = calculator();
+        let x = CalculatorFloat::from(x.as_str());
+        let (Ok(x_value), Ok(y_value)) = (
+            calculator.parse_get(x.clone()),
+            calculator.parse_get(y.clone()),
+        ) else {
+            return Ok(());
+        };
+        let sum = calculator.parse_get(x.clone() + y.clone()).unwrap();
+        prop_assert!(same_value(sum, x_value + y_value));
+        // Multiplying with the float zero gives zero also for a NaN value of x
+        if !y.is_zero() {
+            let product = calculator.parse_get(x * y).unwrap();
+            prop_assert!(same_value(product, x_value * y_value));
+        }
+    }
+}
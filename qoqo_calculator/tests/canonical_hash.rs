@@ -0,0 +1,151 @@
+// Copyright © 2020-2021 HQS Quantum Simulations GmbH. All Rights Reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except
+// in compliance with the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the
+// License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either
+// express or implied. See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Golden values of the canonical hashes.
+//!
+//! The canonical hashes are stable across versions and platforms. A failing entry means
+//! the hash of a value changed, which is a breaking change that has to be announced.
+
+use qoqo_calculator::{CalculatorComplex, CalculatorFloat};
+
+/// Expressions and their 64 and 128 bit canonical hashes.
+const GOLDEN_EXPRESSIONS: &[(&str, u64, u128)] = &[
+    ("a", 0x07b2d57592fc095f, 0x00b5d756c99e7572077f72577fe731d7),
+    (
+        "a + b",
+        0xaef410871afd95b8,
+        0x880d20371dfa901a98ac82f1a00156e0,
+    ),
+    (
+        "2 * x * sin(theta) - 1e-3",
+        0xa85ae850c43bc2cf,
+        0x044c700d1f1d3f21dceb89f24a6c24bf,
+    ),
+    (
+        "(a + b)^2 / c",
+        0xaa760c5beeeaf10c,
+        0x62c6be127b7020dcd4890ef5c8ef4c34,
+    ),
+    (
+        "pow(x, 2.5) - -3",
+        0x8923fb2f2f0d4e53,
+        0x93d0ea4937f70818d0340b7d10609ec3,
+    ),
+    (
+        "sin(",
+        0x3e637f0a1e7368e6,
+        0x308aa15f5b8ef43894de378cbe15abb6,
+    ),
+];
+
+/// Numbers and their 64 and 128 bit canonical hashes.
+const GOLDEN_NUMBERS: &[(f64, u64, u128)] = &[
+    (0.0, 0x529a2cdc8ff533ac, 0x426d5674ed03ad9adb79f299c7307ac4),
+    (1.5, 0x5095a3dc8e3e5f39, 0x426d56fe9403ad9adb79f299c78511c9),
+    (
+        -2.25,
+        0x52a1b8dc8ffc403e,
+        0x426d56799903ad9adb79f299c7333336,
+    ),
+    (
+        f64::NAN,
+        0x509563dc8e3df279,
+        0x426d56fe5403ad9adb79f299c784c309,
+    ),
+];
+
+// Test that the canonical hashes of expressions and numbers match the golden values
+#[test]
+fn golden_hashes() {
+    for (expression, hash, hash_128) in GOLDEN_EXPRESSIONS {
+        let value = CalculatorFloat::Str(expression.to_string());
+        assert_eq!(value.canonical_hash(), *hash, "{expression}");
+        assert_eq!(value.canonical_hash_128(), *hash_128, "{expression}");
+    }
+    for (number, hash, hash_128) in GOLDEN_NUMBERS {
+        let value = CalculatorFloat::from(*number);
+        assert_eq!(value.canonical_hash(), *hash, "{number}");
+        assert_eq!(value.canonical_hash_128(), *hash_128, "{number}");
+    }
+    let complex = CalculatorComplex::new(1.0, "x");
+    assert_eq!(complex.canonical_hash(), 0xf1a2b67ec71ce738);
+    assert_eq!(
+        complex.canonical_hash_128(),
+        0x380f205d9bb5c7bfa3bdd446025b5cd8
+    );
+}
+
+// Test that equivalent forms of an expression share the canonical hash
+#[test]
+fn equivalent_forms() {
+    let equivalent: &[&[&str]] = &[
+        &["(a + b)", "b+a", "-(-b) + a", "(b) + (a)"],
+        &["a - b", "-b + a", "a + (-b)", "-(b - a)"],
+        &["a + b + c", "c + (b + a)", "(c + a) + b"],
+        &["x * y * 2", "2 * (y * x)", "(y * 2) * x"],
+        &["sin(a + b) / c", "sin(b + a) / c"],
+        &["-3", "(-3)", "-(3)"],
+    ];
+    for forms in equivalent {
+        let hash = CalculatorFloat::from(forms[0]).canonical_hash();
+        for form in forms.iter() {
+            assert_eq!(
+                CalculatorFloat::from(*form).canonical_hash(),
+                hash,
+                "{form}"
+            );
+        }
+    }
+    assert_eq!(
+        CalculatorFloat::from(-3.0).canonical_hash(),
+        CalculatorFloat::from("-3").canonical_hash()
+    );
+    assert_eq!(
+        CalculatorFloat::from(-0.0).canonical_hash(),
+        CalculatorFloat::from(0.0).canonical_hash()
+    );
+    assert_eq!(
+        CalculatorFloat::from(-f64::NAN).canonical_hash_128(),
+        CalculatorFloat::from(f64::NAN).canonical_hash_128()
+    );
+}
+
+// Test that different expressions have different canonical hashes
+#[test]
+fn distinct_forms() {
+    let distinct = [
+        "a - b",
+        "b - a",
+        "a / b",
+        "b / a",
+        "a^b",
+        "b^a",
+        "a * b",
+        "a + b",
+        "ab",
+        "sin(a)",
+        "cos(a)",
+        "atan2(a, b)",
+        "atan2(b, a)",
+        "1",
+        "2",
+    ];
+    for (index, first) in distinct.iter().enumerate() {
+        for second in distinct.iter().skip(index + 1) {
+            assert_ne!(
+                CalculatorFloat::from(*first).canonical_hash(),
+                CalculatorFloat::from(*second).canonical_hash(),
+                "{first} {second}"
+            );
+        }
+    }
+}
@@ -0,0 +1,90 @@
+// Copyright © 2020-2021 HQS Quantum Simulations GmbH. All Rights Reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except
+// in compliance with the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the
+// License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either
+// express or implied. See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Test of the global limit of the length of deserialized expressions.
+//!
+//! The limit is global for the process, so it is changed in a separate test binary
+//! with a single test to not interfere with other tests.
+
+use qoqo_calculator::{
+    max_deserialized_expression_length, set_max_deserialized_expression_length, CalculatorComplex,
+    CalculatorError, CalculatorFloat, DEFAULT_MAX_DESERIALIZED_EXPRESSION_LENGTH,
+};
+
+// Test that overlong expressions are rejected by JSON and bincode deserialization
+#[test]
+fn set_max_deserialized_expression_length_rejects_long_expressions() {
+    assert_eq!(
+        max_deserialized_expression_length(),
+        DEFAULT_MAX_DESERIALIZED_EXPRESSION_LENGTH
+    );
+    let long = CalculatorFloat::from("a * ".repeat(1 << 16) + "a");
+    let normal = CalculatorFloat::from("theta / 2");
+    let long_json = serde_json::to_string(&long).unwrap();
+    let long_bincode = bincode::serialize(&long).unwrap();
+    // Unlimited by default
+    assert_eq!(
+        serde_json::from_str::<CalculatorFloat>(&long_json).unwrap(),
+        long
+    );
+    assert_eq!(
+        bincode::deserialize::<CalculatorFloat>(&long_bincode).unwrap(),
+        long
+    );
+
+    set_max_deserialized_expression_length(1024);
+    assert_eq!(max_deserialized_expression_length(), 1024);
+    let message = CalculatorError::DeserializedExpressionTooLong {
+        length: 262145,
+        limit: 1024,
+    }
+    .to_string();
+    let error = serde_json::from_str::<CalculatorFloat>(&long_json).unwrap_err();
+    assert!(error.is_data());
+    assert!(error.to_string().starts_with(&message), "{error}");
+    let error = bincode::deserialize::<CalculatorFloat>(&long_bincode).unwrap_err();
+    assert!(matches!(*error, bincode::ErrorKind::Custom(ref custom) if *custom == message));
+    // Also inside of other types
+    let complex = CalculatorComplex::new(long.clone(), 1.0);
+    let error =
+        serde_json::from_str::<CalculatorComplex>(&serde_json::to_string(&complex).unwrap())
+            .unwrap_err();
+    assert!(
+        error
+            .to_string()
+            .starts_with(&format!("Invalid real part: {message}")),
+        "{error}"
+    );
+    assert!(
+        bincode::deserialize::<CalculatorComplex>(&bincode::serialize(&complex).unwrap()).is_err()
+    );
+
+    // Normal expressions and floats are not affected
+    for value in [normal.clone(), CalculatorFloat::from(0.5)] {
+        let json = serde_json::to_string(&value).unwrap();
+        assert_eq!(
+            serde_json::from_str::<CalculatorFloat>(&json).unwrap(),
+            value
+        );
+        let encoded = bincode::serialize(&value).unwrap();
+        assert_eq!(
+            bincode::deserialize::<CalculatorFloat>(&encoded).unwrap(),
+            value
+        );
+    }
+
+    set_max_deserialized_expression_length(DEFAULT_MAX_DESERIALIZED_EXPRESSION_LENGTH);
+    assert_eq!(
+        serde_json::from_str::<CalculatorFloat>(&long_json).unwrap(),
+        long
+    );
+}
@@ -0,0 +1,89 @@
+// Copyright © 2020-2021 HQS Quantum Simulations GmbH. All Rights Reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except
+// in compliance with the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the
+// License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either
+// express or implied. See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Bit exact results of the platform dependent functions with the `deterministic-math` feature.
+//!
+//! The golden bit patterns were recorded once and must be identical on every platform.
+//! A failing entry means a result changed in the last bit, which breaks archived results.
+
+#![cfg(feature = "deterministic-math")]
+
+use qoqo_calculator::{Calculator, CalculatorFloat};
+
+/// Expressions and the bit patterns of their values.
+const GOLDEN: &[(&str, u64)] = &[
+    ("sin(0.7)", 0x3fe49d6e694619b8),
+    ("cos(0.7)", 0x3fe87996529f9d93),
+    ("tan(0.7)", 0x3feaf406c2fc78ae),
+    ("asin(0.3)", 0x3fd380159e14f6ff),
+    ("acos(0.3)", 0x3ff441f5ecbeef59),
+    ("atan(2.5)", 0x3ff30b6d796a4da8),
+    ("atan2(0.3, -1.7)", 0x4007bc40ca9216fe),
+    ("sinh(1.3)", 0x3ffb2c9310045c64),
+    ("cosh(1.3)", 0x3fff88dd5c279504),
+    ("tanh(0.45)", 0x3fdb0064af70e0bb),
+    ("asinh(2.2)", 0x3ff8797d488657e0),
+    ("acosh(2.2)", 0x3ff6ce81ff1a70f3),
+    ("atanh(0.45)", 0x3fdf0554513b1b4c),
+    ("exp(1.7)", 0x4015e552770df8a6),
+    ("exp2(0.37)", 0x3ff4ad7a2967b268),
+    ("expm1(1e-5)", 0x3ee4f8bc681cdfb6),
+    ("log(3.3)", 0x3ff31a4e7240c777),
+    ("log10(3.3)", 0x3fe097aa8bc975db),
+    ("cbrt(17.0)", 0x400491fc152578ca),
+    ("hypot(3.1, 4.7)", 0x40168566e3aa6849),
+    ("pow(1.7, 2.3)", 0x400b1c0c46dcb4ea),
+    ("2.9^0.41", 0x3ff8c1ddf94666ab),
+    ("gamma(4.3)", 0x4021b5ef90a00067),
+    ("factorial(5.5)", 0x4071fe2a1911f7e4),
+    ("binom(7.5, 2.5)", 0x4041988000000008),
+    ("binom(300, 150)", 0x526790eb1a4ab776),
+];
+
+// Test that the parsed expressions return the golden bit patterns
+#[test]
+fn golden_bit_patterns() {
+    let calculator = Calculator::new();
+    for (expression, bits) in GOLDEN {
+        let value = calculator.parse_str(expression).unwrap();
+        assert_eq!(
+            value.to_bits(),
+            *bits,
+            "{expression} evaluates to {value:e} with bits {:#018x}",
+            value.to_bits()
+        );
+    }
+}
+
+// Test that the CalculatorFloat methods return the same bits as the parsed expressions
+#[test]
+fn calculator_float_methods() {
+    let calculator = Calculator::new();
+    let x = CalculatorFloat::from(0.7);
+    let y = CalculatorFloat::from(-1.7);
+    let typed: [(&str, CalculatorFloat); 6] = [
+        ("sin(0.7)", x.sin()),
+        ("cos(0.7)", x.cos()),
+        ("acos(0.7)", x.acos()),
+        ("exp(0.7)", x.exp()),
+        ("atan2(0.7, -1.7)", x.atan2(&y)),
+        ("0.7^(-1.7)", x.powf(&y)),
+    ];
+    for (expression, value) in typed {
+        let parsed = calculator.parse_str(expression).unwrap();
+        assert_eq!(
+            value.float().unwrap().to_bits(),
+            parsed.to_bits(),
+            "{expression}"
+        );
+    }
+}
@@ -0,0 +1,467 @@
+// Copyright © 2020-2021 HQS Quantum Simulations GmbH. All Rights Reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except
+// in compliance with the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the
+// License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either
+// express or implied. See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Round-trip invariant of the symbolic expressions emitted by CalculatorFloat and CalculatorComplex.
+//!
+//! The expression grammar of the Calculator (see `grammar_spec.rs`) is the only grammar
+//! of symbolic values. Every string emitted by an operation on symbolic operands must be
+//! parsed by the Calculator to the value the same operation returns for numeric operands.
+//!
+//! Each operation is applied to every combination of numeric and symbolic operands.
+//! The symbolic operands are placeholder variables, alone or inside expressions emitted
+//! by other operations, and the Calculator substitutes the numeric values for them.
+//! New emitting operations are covered by adding one line to one of the tables below.
+
+use qoqo_calculator::{Calculator, CalculatorComplex, CalculatorFloat};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
+type FloatUnary = fn(CalculatorFloat) -> CalculatorFloat;
+type FloatBinary = fn(CalculatorFloat, CalculatorFloat) -> CalculatorFloat;
+type ComplexToFloat = fn(CalculatorComplex) -> CalculatorFloat;
+type ComplexUnary = fn(CalculatorComplex) -> CalculatorComplex;
+type ComplexBinary = fn(CalculatorComplex, CalculatorComplex) -> CalculatorComplex;
+type SymbolicOperand = (fn(&str) -> CalculatorFloat, fn(f64) -> f64);
+
+/// Operations with one CalculatorFloat operand returning a CalculatorFloat.
+const FLOAT_UNARY: &[(&str, FloatUnary)] = &[
+    ("neg", |a| -a),
+    ("sqrt", |a| a.sqrt()),
+    ("exp", |a| a.exp()),
+    ("sin", |a| a.sin()),
+    ("cos", |a| a.cos()),
+    ("acos", |a| a.acos()),
+    ("tan", |a| a.tan()),
+    ("asin", |a| a.asin()),
+    ("atan", |a| a.atan()),
+    ("sinh", |a| a.sinh()),
+    ("cosh", |a| a.cosh()),
+    ("tanh", |a| a.tanh()),
+    ("exp2", |a| a.exp2()),
+    ("ln", |a| a.ln()),
+    ("log2", |a| a.log2()),
+    ("log10", |a| a.log10()),
+    ("floor", |a| a.floor()),
+    ("ceil", |a| a.ceil()),
+    ("round", |a| a.round()),
+    ("abs", |a| a.abs()),
+    ("signum", |a| a.signum()),
+    ("heaviside", |a| a.heaviside()),
+    ("step", |a| a.step()),
+    ("delta", |a| a.delta()),
+    ("recip", |a| a.recip()),
+    ("round_to_digits", |a| a.round_to_digits(1)),
+    ("round_to_multiple", |a| a.round_to_multiple(0.25).unwrap()),
+];
+
+/// Operations with two CalculatorFloat operands returning a CalculatorFloat.
+const FLOAT_BINARY: &[(&str, FloatBinary)] = &[
+    ("add", |a, b| a + b),
+    ("sub", |a, b| a - b),
+    ("mul", |a, b| a * b),
+    ("div", |a, b| a / b),
+    ("add_ref", |a, b| &a + &b),
+    ("mul_ref", |a, b| &a * &b),
+    ("add_assign", |mut a, b| {
+        a += b;
+        a
+    }),
+    ("sub_assign", |mut a, b| {
+        a -= b;
+        a
+    }),
+    ("mul_assign", |mut a, b| {
+        a *= b;
+        a
+    }),
+    ("div_assign", |mut a, b| {
+        a /= b;
+        a
+    }),
+    ("checked_add", |a, b| a.checked_add(b).unwrap()),
+    ("checked_sub", |a, b| a.checked_sub(b).unwrap()),
+    ("checked_mul", |a, b| a.checked_mul(b).unwrap()),
+    ("checked_div", |a, b| a.checked_div(b).unwrap()),
+    ("sum", |a, b| [a, b].into_iter().sum()),
+    ("atan2", |a, b| a.atan2(b)),
+    ("powf", |a, b| a.powf(b)),
+    ("log", |a, b| a.log(b)),
+];
+
+/// Operations with one CalculatorComplex operand returning a CalculatorFloat.
+const COMPLEX_TO_FLOAT: &[(&str, ComplexToFloat)] = &[
+    ("arg", |a| a.arg()),
+    ("norm_sqr", |a| a.norm_sqr()),
+    ("norm", |a| a.norm()),
+    ("abs", |a| a.abs()),
+    ("recip_norm", |a| a.recip().norm()),
+    ("recip_arg", |a| a.recip().arg()),
+    ("conj_arg", |a| a.conj().arg()),
+];
+
+/// Operations with one CalculatorComplex operand returning a CalculatorComplex.
+const COMPLEX_UNARY: &[(&str, ComplexUnary)] = &[
+    ("neg", |a| -a),
+    ("conj", |a| a.conj()),
+    ("recip", |a| a.recip()),
+    ("recip_recip", |a| a.recip().recip()),
+];
+
+/// Operations with two CalculatorComplex operands returning a CalculatorComplex.
+const COMPLEX_BINARY: &[(&str, ComplexBinary)] = &[
+    ("add", |a, b| a + b),
+    ("sub", |a, b| a - b),
+    ("mul", |a, b| a * b),
+    ("div", |a, b| a / b),
+    ("add_assign", |mut a, b| {
+        a += b;
+        a
+    }),
+    ("sub_assign", |mut a, b| {
+        a -= b;
+        a
+    }),
+    ("mul_assign", |mut a, b| {
+        a *= b;
+        a
+    }),
+    ("div_assign", |mut a, b| {
+        a /= b;
+        a
+    }),
+    ("sum", |a, b| [a, b].into_iter().sum()),
+    ("mul_float", |a, b| a * b.re),
+    ("div_float", |a, b| a / b.im),
+];
+
+/// Values of the first operand, including zero to cover the simplifications of the emitters.
+const FIRST_VALUES: &[f64] = &[0.0, 0.3, -0.7, 1.0, 2.5];
+
+/// Values of the second operand, without zero to avoid divisions by zero.
+const SECOND_VALUES: &[f64] = &[1.0, 0.4, -1.5, 3.0];
+
+/// Symbolic operands built from a placeholder variable.
+///
+/// Besides the bare variable the placeholder is used inside expressions emitted by
+/// other operations, so the emitted strings are also checked as operands.
+/// Every entry returns the symbolic operand and the function computing its value.
+const SYMBOLIC_OPERANDS: &[SymbolicOperand] = &[
+    (|name| CalculatorFloat::from(name), |x| x),
+    (|name| CalculatorFloat::from(name) + 0.5, |x| x + 0.5),
+    (|name| -CalculatorFloat::from(name), |x| -x),
+    (|name| CalculatorFloat::from(name) * 2.0, |x| x * 2.0),
+];
+
+/// Outcome of an operation compared between the numeric and the symbolic evaluation.
+///
+/// Non-finite values, panics and parsing errors are all undefined, e.g. the reciprocal
+/// of zero is infinite for numeric values and a division by zero error when parsed.
+#[derive(Debug, Clone, Copy)]
+enum Outcome {
+    Value(f64),
+    Undefined,
+}
+
+impl Outcome {
+    fn from_value(value: f64) -> Self {
+        if value.is_finite() {
+            Outcome::Value(value)
+        } else {
+            Outcome::Undefined
+        }
+    }
+
+    fn matches(self, expected: Self) -> bool {
+        match (self, expected) {
+            (Outcome::Value(x), Outcome::Value(y)) => (x - y).abs() <= 1e-12 * y.abs().max(1.0),
+            (Outcome::Undefined, Outcome::Undefined) => true,
+            _ => false,
+        }
+    }
+}
+
+/// Run an operation on numeric operands.
+fn numeric<T>(operation: impl FnOnce() -> T) -> Option<T> {
+    catch_unwind(AssertUnwindSafe(operation)).ok()
+}
+
+/// Evaluate the result of an operation with the Calculator.
+fn evaluate(calculator: &Calculator, value: &CalculatorFloat) -> Outcome {
+    match calculator.parse_get_ref(value) {
+        Ok(x) => Outcome::from_value(x),
+        Err(_) => Outcome::Undefined,
+    }
+}
+
+/// Return the numeric outcome of a CalculatorFloat returned for numeric operands.
+fn expected(value: Option<CalculatorFloat>) -> Outcome {
+    match value {
+        Some(CalculatorFloat::Float(x)) => Outcome::from_value(x),
+        Some(CalculatorFloat::Str(expression)) => {
+            panic!("numeric operands returned symbolic value {expression}")
+        }
+        None => Outcome::Undefined,
+    }
+}
+
+/// Return the numeric and all symbolic versions of an operand.
+///
+/// Each version is the operand and the value it evaluates to when the placeholder has the value x.
+fn operands(name: &str, x: f64) -> Vec<(CalculatorFloat, f64)> {
+    let mut operands = vec![(CalculatorFloat::from(x), x)];
+    for (symbolic, value) in SYMBOLIC_OPERANDS {
+        operands.push((symbolic(name), value(x)));
+    }
+    operands
+}
+
+/// Assert that a symbolic result evaluates to the numeric result.
+fn assert_roundtrip(
+    calculator: &Calculator,
+    description: &str,
+    result: Option<CalculatorFloat>,
+    expected_outcome: Outcome,
+) {
+    let outcome = match &result {
+        Some(value) => evaluate(calculator, value),
+        // Operations only panic on symbolic operands when the numeric operation is undefined
+        None => Outcome::Undefined,
+    };
+    assert!(
+        outcome.matches(expected_outcome),
+        "{description}: emitted {result:?} evaluates to {outcome:?}, numeric result is {expected_outcome:?}"
+    );
+    // Emitted expressions are operands of further operations, the exponent of a power
+    // binds tightest and fails for expressions that are not bracketed
+    if let (Some(CalculatorFloat::Str(_)), Outcome::Value(value)) = (&result, expected_outcome) {
+        let power = CalculatorFloat::from(2.0).powf(result.clone().unwrap());
+        let power_outcome = evaluate(calculator, &power);
+        let expected_power = Outcome::from_value(2.0_f64.powf(value));
+        assert!(
+            power_outcome.matches(expected_power),
+            "{description}: emitted {power:?} evaluates to {power_outcome:?} as operand, numeric result is {expected_power:?}"
+        );
+    }
+}
+
+// Test that every unary CalculatorFloat operation emits an expression evaluating to the numeric result
+#[test]
+fn float_unary_roundtrip() {
+    let mut calculator = Calculator::new();
+    for (name, operation) in FLOAT_UNARY {
+        for &x in FIRST_VALUES {
+            calculator.set_variable("x", x).unwrap();
+            for (operand, value) in operands("x", x) {
+                let expected_outcome = expected(numeric(|| operation(value.into())));
+                let result = numeric(|| operation(operand.clone()));
+                let description = format!("{name}({operand}) with x = {x}");
+                assert_roundtrip(&calculator, &description, result, expected_outcome);
+            }
+        }
+    }
+}
+
+// Test that every binary CalculatorFloat operation emits an expression evaluating to the numeric result
+#[test]
+fn float_binary_roundtrip() {
+    let mut calculator = Calculator::new();
+    for (name, operation) in FLOAT_BINARY {
+        for &x in FIRST_VALUES {
+            for &y in SECOND_VALUES {
+                calculator.set_variable("x", x).unwrap();
+                calculator.set_variable("y", y).unwrap();
+                for (a, a_value) in operands("x", x) {
+                    for (b, b_value) in operands("y", y) {
+                        let expected_outcome =
+                            expected(numeric(|| operation(a_value.into(), b_value.into())));
+                        let result = numeric(|| operation(a.clone(), b.clone()));
+                        let description = format!("{name}({a}, {b}) with x = {x}, y = {y}");
+                        assert_roundtrip(&calculator, &description, result, expected_outcome);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Return the numeric and symbolic versions of a complex operand with placeholders for both parts.
+///
+/// Each version is the operand and its value when the placeholders have the values re and im.
+fn complex_operands(
+    re_name: &str,
+    im_name: &str,
+    re: f64,
+    im: f64,
+) -> Vec<(CalculatorComplex, (f64, f64))> {
+    let mut complex_operands = Vec::new();
+    for (re_operand, re_value) in operands(re_name, re).into_iter().take(3) {
+        for (im_operand, im_value) in operands(im_name, im).into_iter().take(3) {
+            complex_operands.push((
+                CalculatorComplex::new(re_operand.clone(), im_operand),
+                (re_value, im_value),
+            ));
+        }
+    }
+    complex_operands
+}
+
+/// Assert that both parts of a symbolic complex result evaluate to the numeric result.
+fn assert_complex_roundtrip(
+    calculator: &Calculator,
+    description: &str,
+    result: Option<CalculatorComplex>,
+    expected_result: Option<CalculatorComplex>,
+) {
+    let (result_re, result_im) = match result {
+        Some(value) => (Some(value.re), Some(value.im)),
+        None => (None, None),
+    };
+    let (expected_re, expected_im) = match expected_result {
+        Some(value) => (Some(value.re), Some(value.im)),
+        None => (None, None),
+    };
+    assert_roundtrip(
+        calculator,
+        &format!("re of {description}"),
+        result_re,
+        expected(expected_re),
+    );
+    assert_roundtrip(
+        calculator,
+        &format!("im of {description}"),
+        result_im,
+        expected(expected_im),
+    );
+}
+
+// Test that every CalculatorComplex operation returning a CalculatorFloat emits an expression evaluating to the numeric result
+#[test]
+fn complex_to_float_roundtrip() {
+    let mut calculator = Calculator::new();
+    for (name, operation) in COMPLEX_TO_FLOAT {
+        for &re in FIRST_VALUES {
+            for &im in SECOND_VALUES {
+                calculator.set_variable("xr", re).unwrap();
+                calculator.set_variable("xi", im).unwrap();
+                for (a, (re_value, im_value)) in complex_operands("xr", "xi", re, im) {
+                    let expected_outcome = expected(numeric(|| {
+                        operation(CalculatorComplex::new(re_value, im_value))
+                    }));
+                    let result = numeric(|| operation(a.clone()));
+                    let description = format!("{name}({a}) with xr = {re}, xi = {im}");
+                    assert_roundtrip(&calculator, &description, result, expected_outcome);
+                }
+            }
+        }
+    }
+}
+
+// Test that every unary CalculatorComplex operation emits expressions evaluating to the numeric result
+#[test]
+fn complex_unary_roundtrip() {
+    let mut calculator = Calculator::new();
+    for (name, operation) in COMPLEX_UNARY {
+        for &re in FIRST_VALUES {
+            for &im in SECOND_VALUES {
+                calculator.set_variable("xr", re).unwrap();
+                calculator.set_variable("xi", im).unwrap();
+                for (a, (re_value, im_value)) in complex_operands("xr", "xi", re, im) {
+                    let expected_result =
+                        numeric(|| operation(CalculatorComplex::new(re_value, im_value)));
+                    let result = numeric(|| operation(a.clone()));
+                    let description = format!("{name}({a}) with xr = {re}, xi = {im}");
+                    assert_complex_roundtrip(&calculator, &description, result, expected_result);
+                }
+            }
+        }
+    }
+}
+
+// Test that every binary CalculatorComplex operation emits expressions evaluating to the numeric result
+#[test]
+fn complex_binary_roundtrip() {
+    let mut calculator = Calculator::new();
+    for (name, operation) in COMPLEX_BINARY {
+        for (&x, &y) in FIRST_VALUES.iter().zip(SECOND_VALUES.iter().cycle()) {
+            calculator.set_variable("xr", x).unwrap();
+            calculator.set_variable("xi", y).unwrap();
+            calculator.set_variable("yr", y).unwrap();
+            calculator.set_variable("yi", -y).unwrap();
+            for (a, (ar, ai)) in complex_operands("xr", "xi", x, y) {
+                for (b, (br, bi)) in complex_operands("yr", "yi", y, -y) {
+                    let expected_result = numeric(|| {
+                        operation(
+                            CalculatorComplex::new(ar, ai),
+                            CalculatorComplex::new(br, bi),
+                        )
+                    });
+                    let result = numeric(|| operation(a.clone(), b.clone()));
+                    let description = format!("{name}({a}, {b}) with xr = {x}, xi = yr = {y}");
+                    assert_complex_roundtrip(&calculator, &description, result, expected_result);
+                }
+            }
+        }
+    }
+}
+
+/// Constants that need all 17 significant digits or lie at the limits of the f64 range.
+const AWKWARD_CONSTANTS: &[f64] = &[
+    1.0 / 3.0,
+    -2.0 / 3.0,
+    0.1,
+    0.1 + 0.2,
+    std::f64::consts::PI,
+    9007199254740993.0,
+    1e-300,
+    -1.7976931348623157e300,
+    f64::MIN_POSITIVE,
+    5e-324,
+    f64::MAX,
+    f64::EPSILON,
+];
+
+// Test that the Display of every awkward constant is parsed back to the bit-identical float
+#[test]
+fn awkward_constants_display_roundtrip() {
+    let calculator = Calculator::new();
+    for &constant in AWKWARD_CONSTANTS.iter().chain([0.0, -0.0].iter()) {
+        let formatted = CalculatorFloat::from(constant).to_string();
+        let parsed = calculator.parse_str(&formatted).unwrap();
+        assert_eq!(parsed.to_bits(), constant.to_bits(), "{formatted}");
+    }
+}
+
+// Test that chains of operations with awkward constants evaluate bit-identical to the direct f64 result
+#[test]
+fn awkward_constants_chain_roundtrip() {
+    let mut calculator = Calculator::new();
+    for &x in &[1.0 / 7.0, -0.3, 1e-10, 12345.678] {
+        calculator.set_variable("x", x).unwrap();
+        for &a in AWKWARD_CONSTANTS {
+            for &b in AWKWARD_CONSTANTS {
+                let symbolic = ((CalculatorFloat::from("x") + a) * b - a) / b;
+                let direct = ((x + a) * b - a) / b;
+                let scalar_first = a - b / (b * CalculatorFloat::from("x") + a);
+                let scalar_first_direct = a - b / (b * x + a);
+                for (expression, expected) in
+                    [(symbolic, direct), (scalar_first, scalar_first_direct)]
+                {
+                    let result = calculator.parse_get_ref(&expression).unwrap();
+                    assert!(
+                        result.to_bits() == expected.to_bits()
+                            || (result.is_nan() && expected.is_nan()),
+                        "{expression} with x = {x}: {result:e} != {expected:e}"
+                    );
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,58 @@
+// Copyright © 2020-2021 HQS Quantum Simulations GmbH. All Rights Reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except
+// in compliance with the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the
+// License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either
+// express or implied. See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Test of the global limit of the length of expressions in error messages.
+//!
+//! The limit is global for the process, so it is changed in a separate test binary
+//! with a single test to not interfere with other tests.
+
+use qoqo_calculator::{
+    max_error_expression_length, set_max_error_expression_length, CalculatorError, CalculatorFloat,
+    DEFAULT_MAX_ERROR_EXPRESSION_LENGTH,
+};
+
+// Test that the configured limit is used by the error messages
+#[test]
+fn set_max_error_expression_length_limits_messages() {
+    assert_eq!(
+        max_error_expression_length(),
+        DEFAULT_MAX_ERROR_EXPRESSION_LENGTH
+    );
+    let expression = "a * ".repeat(1 << 18) + "a";
+    let error = CalculatorFloat::from(expression.as_str())
+        .float()
+        .unwrap_err();
+    assert_eq!(
+        error,
+        CalculatorError::FloatSymbolicNotConvertable {
+            val: expression.clone()
+        }
+    );
+    assert_eq!(
+        error.to_string().len(),
+        DEFAULT_MAX_ERROR_EXPRESSION_LENGTH + 64
+    );
+
+    set_max_error_expression_length(8);
+    assert_eq!(max_error_expression_length(), 8);
+    assert_eq!(
+        error.to_string(),
+        "Symbolic value \"a * a *... (1048579 bytes) can not be converted to float"
+    );
+
+    set_max_error_expression_length(usize::MAX);
+    assert_eq!(
+        error.to_string(),
+        format!("Symbolic value {expression:?} can not be converted to float")
+    );
+    set_max_error_expression_length(DEFAULT_MAX_ERROR_EXPRESSION_LENGTH);
+}
@@ -0,0 +1,445 @@
+// Copyright © 2020-2021 HQS Quantum Simulations GmbH. All Rights Reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except
+// in compliance with the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the
+// License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either
+// express or implied. See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Specification of the expression grammar accepted by the Calculator.
+//!
+//! Every entry of [SPEC] pins the result of parsing one expression, including the
+//! errors of malformed expressions. Changes of the grammar have to update the table.
+
+use qoqo_calculator::{Calculator, CalculatorFloat};
+use std::f64::consts::{FRAC_PI_3, FRAC_PI_6, LN_2, SQRT_2};
+use std::str::FromStr;
+
+/// Expected outcome of parsing an expression.
+#[derive(Debug, Clone, Copy)]
+enum Expected {
+    /// Expression evaluates to the value
+    Value(f64),
+    /// Expression evaluates to NaN
+    NaN,
+    /// Parsing fails with the error given by its Debug representation
+    Error(&'static str),
+}
+use Expected::{Error, NaN, Value};
+
+/// Relative tolerance when comparing finite values.
+const RTOL: f64 = 1e-12;
+
+/// Entry of the grammar spec: expression, variables set before parsing and expected outcome.
+type SpecEntry = (&'static str, &'static [(&'static str, f64)], Expected);
+
+/// Grammar specification of the Calculator.
+#[rustfmt::skip]
+const SPEC: &[SpecEntry] = &[
+    // Numbers and scientific notation
+    ("0", &[], Value(0.0)),
+    ("42", &[], Value(42.0)),
+    ("3.25", &[], Value(3.25)),
+    (".5", &[], Value(0.5)),
+    ("5.", &[], Value(5.0)),
+    ("007", &[], Value(7.0)),
+    ("1e3", &[], Value(1000.0)),
+    ("1E3", &[], Value(1000.0)),
+    ("1e-3", &[], Value(0.001)),
+    ("1.5e+2", &[], Value(150.0)),
+    ("1.e2", &[], Value(100.0)),
+    ("2.5E-1", &[], Value(0.25)),
+    ("1e308", &[], Value(1e+308)),
+    ("1e-320", &[], Value(1e-320)),
+    ("1e308*10", &[], Value(f64::INFINITY)),
+    ("123456789012345678", &[], Value(1.2345678901234568e+17)),
+    ("1e", &[], Error("UnrecognizedToken { token: \"1e\", position: 0 }")),
+    ("1e+", &[], Error("UnrecognizedToken { token: \"1e+\", position: 0 }")),
+    ("1.2.3", &[], Error("UnrecognizedToken { token: \"1.2.3\", position: 0 }")),
+
+    // Addition and subtraction, left associative
+    ("1+2", &[], Value(3.0)),
+    ("1 + 2", &[], Value(3.0)),
+    ("1-2", &[], Value(-1.0)),
+    ("1-1-1", &[], Value(-1.0)),
+    ("1-2+3", &[], Value(2.0)),
+    ("10 - 4 - 3", &[], Value(3.0)),
+    ("0.1+0.2", &[], Value(0.30000000000000004)),
+    ("1+2+3+4", &[], Value(10.0)),
+
+    // Multiplication and division, left associative
+    ("2*3", &[], Value(6.0)),
+    ("8/2/2", &[], Value(2.0)),
+    ("8/(2/2)", &[], Value(8.0)),
+    ("2*3/4", &[], Value(1.5)),
+    ("1/3", &[], Value(0.3333333333333333)),
+    ("6/4*2", &[], Value(3.0)),
+    ("1/0", &[], Error("DivisionByZero")),
+    ("1/(2-2)", &[], Error("DivisionByZero")),
+    ("0/0", &[], Error("DivisionByZero")),
+    ("0/1", &[], Value(0.0)),
+    ("0*1e308", &[], Value(0.0)),
+
+    // Power binds tighter than multiplication
+    ("2^3", &[], Value(8.0)),
+    ("2^2*3", &[], Value(12.0)),
+    ("3*2^2", &[], Value(12.0)),
+    ("2^-1", &[], Value(0.5)),
+    ("4^0.5", &[], Value(2.0)),
+    ("2^(1/2)", &[], Value(SQRT_2)),
+    ("(2^3)^2", &[], Value(64.0)),
+    ("2^(3^2)", &[], Value(512.0)),
+    ("2^3^2", &[], Error("UnexpectedToken { token: \"^\", position: 3 }")),
+    ("2^0", &[], Value(1.0)),
+    ("0^0", &[], Value(1.0)),
+    ("(-8)^(1/3)", &[], NaN),
+    ("(-2)^3", &[], Value(-8.0)),
+    ("(-2)^2", &[], Value(4.0)),
+
+    // Precedence
+    ("1+2*3", &[], Value(7.0)),
+    ("(1+2)*3", &[], Value(9.0)),
+    ("1+2^2*3", &[], Value(13.0)),
+    ("2*3+4*5", &[], Value(26.0)),
+    ("1-2*3", &[], Value(-5.0)),
+    ("12/4-1", &[], Value(2.0)),
+    ("2*(3+4)", &[], Value(14.0)),
+    ("((1))", &[], Value(1.0)),
+    ("((1+2)*(3+4))", &[], Value(21.0)),
+    ("(((2)))^2", &[], Value(4.0)),
+
+    // Comparisons give 1 or 0 and bind weaker than all arithmetic, chains are left associative
+    ("1 < 2", &[], Value(1.0)),
+    ("2 < 1", &[], Value(0.0)),
+    ("1 > 2", &[], Value(0.0)),
+    ("2 <= 2", &[], Value(1.0)),
+    ("2 >= 3", &[], Value(0.0)),
+    ("2 == 2", &[], Value(1.0)),
+    ("2 != 2", &[], Value(0.0)),
+    ("1+1 == 2", &[], Value(1.0)),
+    ("2*(1 < 2)", &[], Value(2.0)),
+    ("3 > 2 > 1", &[], Value(0.0)),
+    ("-1 < 0", &[], Value(1.0)),
+    ("x != x", &[("x", f64::NAN)], Value(1.0)),
+    ("x == x", &[("x", f64::NAN)], Value(0.0)),
+    ("1 <", &[], Error("UnexpectedEndOfExpressionAt { position: 3 }")),
+    ("1 < = 2", &[], Error("UnexpectedToken { token: \"=\", position: 4 }")),
+    ("1 => 2", &[], Error("UnexpectedToken { token: \"=\", position: 2 }")),
+
+    // Unary signs bind tighter than power and can not be stacked
+    ("-1", &[], Value(-1.0)),
+    ("+1", &[], Value(1.0)),
+    ("-(-1)", &[], Value(1.0)),
+    ("-(1+2)", &[], Value(-3.0)),
+    ("2*-3", &[], Value(-6.0)),
+    ("2/-4", &[], Value(-0.5)),
+    ("-2^2", &[], Value(4.0)),
+    ("-4^0.5", &[], NaN),
+    ("2^-2", &[], Value(0.25)),
+    ("1--1", &[], Value(2.0)),
+    ("1+-1", &[], Value(0.0)),
+    ("1-+1", &[], Value(0.0)),
+    ("--1", &[], Error("UnexpectedToken { token: \"-\", position: 1 }")),
+    ("+-1", &[], Error("UnexpectedToken { token: \"-\", position: 1 }")),
+    ("-+1", &[], Error("UnexpectedToken { token: \"+\", position: 1 }")),
+    ("1---1", &[], Error("UnexpectedToken { token: \"-\", position: 3 }")),
+
+    // Variables
+    ("x", &[("x", 0.5)], Value(0.5)),
+    ("-x", &[("x", 0.5)], Value(-0.5)),
+    ("x2", &[("x2", 4.0)], Value(4.0)),
+    ("a_b", &[("a_b", 3.0)], Value(3.0)),
+    ("x*y", &[("x", 0.5), ("y", -2.0)], Value(-1.0)),
+    ("x^-x", &[("x", 0.5)], Value(SQRT_2)),
+    ("theta", &[("theta", 0.1)], Value(0.1)),
+    ("sin(theta)", &[("theta", 0.1)], Value(0.09983341664682815)),
+    ("x + x", &[("x", 0.5)], Value(1.0)),
+    ("_x", &[], Error("UnrecognizedToken { token: \"_\", position: 0 }")),
+    ("z", &[], Error("VariableNotSet { name: \"z\" }")),
+    ("0x10", &[], Error("VariableNotSet { name: \"x10\" }")),
+    ("inf", &[], Error("VariableNotSet { name: \"inf\" }")),
+
+    // Built-in constants are used for names that are not set as variables
+    ("pi", &[], Value(std::f64::consts::PI)),
+    ("PI", &[], Value(std::f64::consts::PI)),
+    ("e", &[], Value(std::f64::consts::E)),
+    ("E", &[], Value(std::f64::consts::E)),
+    ("tau", &[], Value(std::f64::consts::TAU)),
+    ("sin(pi/2)", &[], Value(1.0)),
+    ("2*pi", &[], Value(std::f64::consts::TAU)),
+    ("e^2", &[], Value(7.38905609893065)),
+    ("pi", &[("pi", 3.0)], Value(3.0)),
+    ("2*e", &[("e", 0.5)], Value(1.0)),
+    ("Pi", &[], Error("VariableNotSet { name: \"Pi\" }")),
+
+    // Juxtaposition is parsed as separate statements, the last value is returned
+    ("1 2", &[], Value(2.0)),
+    ("2x", &[("x", 0.5)], Value(0.5)),
+    ("x y", &[("x", 0.5), ("y", -2.0)], Value(-2.0)),
+
+    // One argument functions
+    ("sin(0.5)", &[], Value(0.479425538604203)),
+    ("cos(0.5)", &[], Value(0.8775825618903728)),
+    ("tan(0.5)", &[], Value(0.5463024898437905)),
+    ("abs(-2.5)", &[], Value(2.5)),
+    ("acos(0.5)", &[], Value(FRAC_PI_3)),
+    ("asin(0.5)", &[], Value(FRAC_PI_6)),
+    ("atan(0.5)", &[], Value(0.4636476090008061)),
+    ("cosh(0.5)", &[], Value(1.1276259652063807)),
+    ("sinh(0.5)", &[], Value(0.5210953054937474)),
+    ("tanh(0.5)", &[], Value(0.46211715726000974)),
+    ("acosh(1.5)", &[], Value(0.9624236501192069)),
+    ("asinh(0.5)", &[], Value(0.48121182505960347)),
+    ("atanh(0.5)", &[], Value(0.5493061443340548)),
+    ("arcosh(1.5)", &[], Value(0.9624236501192069)),
+    ("arsinh(0.5)", &[], Value(0.48121182505960347)),
+    ("artanh(0.5)", &[], Value(0.5493061443340548)),
+    ("exp(0.5)", &[], Value(1.6487212707001282)),
+    ("exp2(0.5)", &[], Value(SQRT_2)),
+    ("expm1(1e-10)", &[], Value(1.00000000005e-10)),
+    ("log(2.0)", &[], Value(LN_2)),
+    ("log10(1000.0)", &[], Value(3.0)),
+    ("sqrt(2.0)", &[], Value(SQRT_2)),
+    ("cbrt(27.0)", &[], Value(3.0)),
+    ("ceil(1.2)", &[], Value(2.0)),
+    ("floor(-1.2)", &[], Value(-2.0)),
+    ("fract(1.25)", &[], Value(0.25)),
+    ("round(2.5)", &[], Value(3.0)),
+    ("round(-2.5)", &[], Value(-3.0)),
+    ("sign(-3.0)", &[], Value(-1.0)),
+    ("fract(-1.5)", &[], Value(-0.5)),
+    ("sign(0)", &[], Value(1.0)),
+    ("sign(-0.0)", &[], Value(-1.0)),
+    ("abs(-0)", &[], Value(0.0)),
+    ("delta(0)", &[], Value(1.0)),
+    ("delta(1e-17)", &[], Value(1.0)),
+    ("delta(0.1)", &[], Value(0.0)),
+    ("theta(0)", &[], Value(0.5)),
+    ("theta(-1)", &[], Value(0.0)),
+    ("theta(2)", &[], Value(1.0)),
+    ("sqrt(-1)", &[], NaN),
+    ("log(0)", &[], Value(f64::NEG_INFINITY)),
+    ("exp(1000)", &[], Value(f64::INFINITY)),
+    ("acos(2)", &[], NaN),
+    ("erf(1)", &[], Value(0.8427007929497149)),
+    ("erf(-7)", &[], Value(-1.0)),
+    ("tgamma(5)", &[], Value(24.0)),
+    ("tgamma(0)", &[], Error("InvalidFunctionArgument { fct: \"tgamma\", value: 0.0 }")),
+    ("lgamma(1)", &[], Value(0.0)),
+    ("lgamma(-1)", &[], Error("InvalidFunctionArgument { fct: \"lgamma\", value: -1.0 }")),
+    ("parity(1)", &[], Value(-1.0)),
+    ("parity(-4)", &[], Value(1.0)),
+    ("parity(0.5)", &[], Error("InvalidFunctionArgument { fct: \"parity\", value: 0.5 }")),
+    ("factorial(20)", &[], Value(2432902008176640000.0)),
+    ("factorial(0)", &[], Value(1.0)),
+    ("factorial(0.5)", &[], Value(0.886226925452758)),
+    ("factorial(-1)", &[], Error("InvalidFunctionArgument { fct: \"factorial\", value: -1.0 }")),
+    ("gamma(0.5)^2", &[], Value(std::f64::consts::PI)),
+    ("gamma(6)", &[], Value(120.0)),
+    ("gamma(0)", &[], Error("InvalidFunctionArgument { fct: \"gamma\", value: 0.0 }")),
+
+    // Two argument functions
+    ("atan2(1, -1)", &[], Value(2.356194490192345)),
+    ("atan2(0,1)", &[], Value(0.0)),
+    ("hypot(3,4)", &[], Value(5.0)),
+    ("pow(2,3)", &[], Value(8.0)),
+    ("pow(2, -1)", &[], Value(0.5)),
+    ("max(1,2)", &[], Value(2.0)),
+    ("min(1,2)", &[], Value(1.0)),
+    ("max(x,y)", &[("x", 0.5), ("y", -2.0)], Value(0.5)),
+    ("min(x, y)", &[("x", 0.5), ("y", -2.0)], Value(-2.0)),
+    ("binom(52, 5)", &[], Value(2598960.0)),
+    ("binom(5, 7)", &[], Value(0.0)),
+    ("binom(2.5, 1)", &[], Value(2.5)),
+    ("binom(-1, 1)", &[], Error("InvalidFunctionArgument { fct: \"binom\", value: -1.0 }")),
+    ("round_digits(0.00005, 4)", &[], Value(0.0001)),
+    ("round_digits(-2.675, 2)", &[], Value(-2.68)),
+    ("round_digits(1250, -2)", &[], Value(1300.0)),
+    ("round_digits(1, 0.5)", &[], Error("InvalidFunctionArgument { fct: \"round_digits\", value: 0.5 }")),
+    ("round_step(0.123456, 1e-4)", &[], Value(0.1235)),
+    ("round_step(-0.625, 0.25)", &[], Value(-0.75)),
+    ("round_step(1, 0)", &[], Error("InvalidFunctionArgument { fct: \"round_step\", value: 0.0 }")),
+
+    // Nesting and expressions as arguments
+    ("sin(cos(0))", &[], Value(0.8414709848078965)),
+    ("max(1+2, 2*2)", &[], Value(4.0)),
+    ("sqrt(hypot(3, 4)^2)", &[], Value(5.0)),
+    ("2*sin(0.5)^2", &[], Value(0.4596976941318603)),
+    ("-sin(0.5)", &[], Value(-0.479425538604203)),
+    ("exp(log(3))", &[], Value(3.0000000000000004)),
+    ("pow(max(1,2), min(3,4))", &[], Value(8.0)),
+    ("max(1, 2, 3)", &[], Value(3.0)),
+    ("min(4, x, 2, 3)", &[("x", -1.0)], Value(-1.0)),
+    ("sum(1, 2, 3, 4)", &[], Value(10.0)),
+    ("max(1, min(2, 3), 4)", &[], Value(4.0)),
+    ("sum(max(1, 5, 2), min(3, sum(1, 1)), -1) * 2", &[], Value(12.0)),
+    ("atan2(sin(1), cos(1))", &[], Value(1.0)),
+    ("abs(-2)*3", &[], Value(6.0)),
+    ("1 + abs(-1)", &[], Value(2.0)),
+
+    // Function call errors
+    ("foo(1)", &[], Error("FunctionNotFound { fct: \"foo\" }")),
+    ("sin()", &[], Error("UnexpectedToken { token: \")\", position: 4 }")),
+    ("sin(1,2)", &[], Error("ParsingError { msg: \"Expected braket close.\", position: Some(5) }")),
+    ("atan2(1)", &[], Error("ParsingError { msg: \"expected comma in function arguments\", position: Some(7) }")),
+    ("hypot(1, 2, 3)", &[], Error("ParsingError { msg: \"Expected braket close.\", position: Some(10) }")),
+    ("max()", &[], Error("NotEnoughFunctionArguments")),
+    ("max(1)", &[], Error("NotEnoughFunctionArguments")),
+    ("sum(1)", &[], Error("NotEnoughFunctionArguments")),
+    ("max(1, 2", &[], Error("ParsingError { msg: \"Expected braket close.\", position: Some(8) }")),
+    ("max(1, 2;", &[], Error("ParsingError { msg: \"Expected braket close.\", position: Some(8) }")),
+    ("max(1, )", &[], Error("UnexpectedToken { token: \")\", position: 7 }")),
+    ("sin (1)", &[], Error("ParsingError { msg: \"Expected braket close.\", position: Some(7) }")),
+    ("sin(1", &[], Error("ParsingError { msg: \"Expected braket close.\", position: Some(5) }")),
+
+    // Whitespace and comments
+    ("  1  +  2  ", &[], Value(3.0)),
+    ("1 # comment", &[], Value(1.0)),
+    ("1 #c\n+2", &[], Value(3.0)),
+    ("# only comment\n5", &[], Value(5.0)),
+    ("1 # a\n# b\n+1", &[], Value(2.0)),
+    ("", &[], Error("NoValueReturnedParsing")),
+    (" ", &[], Error("NoValueReturnedParsing")),
+    ("# c", &[], Error("NoValueReturnedParsing")),
+
+    // Multiple statements
+    ("1;2", &[], Value(2.0)),
+    ("1; 2", &[], Value(2.0)),
+    ("1;;2", &[], Value(2.0)),
+    ("1;", &[], Value(1.0)),
+    ("1+1; 2*3", &[], Value(6.0)),
+    ("x; x*2", &[("x", 0.5)], Value(1.0)),
+    (";1", &[], Error("UnexpectedEndOfExpressionAt { position: 0 }")),
+
+    // Brackets
+    ("(1", &[], Error("ParsingError { msg: \"Expected Braket close\", position: Some(2) }")),
+    ("1)", &[], Error("UnexpectedToken { token: \")\", position: 1 }")),
+    ("()", &[], Error("UnexpectedToken { token: \")\", position: 1 }")),
+    ("(1+2", &[], Error("ParsingError { msg: \"Expected Braket close\", position: Some(4) }")),
+
+    // Factorials and assignments
+    ("1!", &[], Error("NotImplementedError { fct: \"Factorial\" }")),
+    ("1!!", &[], Error("NotImplementedError { fct: \"DoubleFactorial\" }")),
+    ("a=1", &[], Error("ForbiddenAssign { variable_name: \"a\" }")),
+    ("1; b = 2", &[], Error("ForbiddenAssign { variable_name: \"b\" }")),
+    ("1 = 2", &[], Error("UnexpectedToken { token: \"=\", position: 2 }")),
+    ("a+=1", &[("a", 1.0)], Error("ForbiddenAssign { variable_name: \"a\" }")),
+    ("a /= 0", &[("a", 1.0)], Error("ForbiddenAssign { variable_name: \"a\" }")),
+    ("a+ =1", &[("a", 1.0)], Error("UnexpectedToken { token: \"=\", position: 3 }")),
+    ("a + = 1", &[("a", 1.0)], Error("UnexpectedToken { token: \"=\", position: 4 }")),
+
+    // Aggregates of variables selected by a single-quoted prefix
+    ("sum_vars('a_')", &[("a_1", 1.5), ("a_2", 2.0), ("b_1", 4.0)], Value(3.5)),
+    ("count_vars('a_')", &[("a_1", 1.5), ("a_2", 2.0), ("b_1", 4.0)], Value(2.0)),
+    ("sum_vars('')", &[("a", 1.0), ("b", 2.0)], Value(3.0)),
+    ("count_vars('x')", &[], Value(0.0)),
+    ("sum_vars(a_)", &[("a_", 1.0)], Error("ParsingError { msg: \"Expected string literal argument.\", position: Some(9) }")),
+    ("sum_vars('a_'", &[], Error("ParsingError { msg: \"Expected braket close.\", position: Some(13) }")),
+    ("sum_vars('a_)", &[], Error("ParsingError { msg: \"Expected string literal argument.\", position: Some(9) }")),
+    ("'a_'", &[], Error("UnexpectedToken { token: \"'a_'\", position: 0 }")),
+
+    // Asserts evaluating a condition, the message is optional
+    ("assert(a - 1, 'a is one')", &[("a", 2.0)], Value(1.0)),
+    ("assert(theta(b - a))", &[("a", 1.0), ("b", 2.0)], Value(1.0)),
+    ("assert(a - 1, 'a is one')", &[("a", 1.0)], Error("AssertionFailed { message: \"a is one\", position: 0 }")),
+    ("2 * assert(0)", &[], Error("AssertionFailed { message: \"assertion failed\", position: 4 }")),
+    ("assert(1, a)", &[("a", 1.0)], Error("ParsingError { msg: \"Expected string literal argument.\", position: Some(10) }")),
+    ("assert(1, 'a', 'b')", &[], Error("ParsingError { msg: \"Expected braket close.\", position: Some(13) }")),
+
+    // Reductions over any number of values in square brackets
+    ("max[a, 3, b]", &[("a", 1.0), ("b", 2.0)], Value(3.0)),
+    ("min[a, 3, -b]", &[("a", 1.0), ("b", 2.0)], Value(-2.0)),
+    ("sum[1, 2, 3, 4]", &[], Value(10.0)),
+    ("mean[1, 2, 3, 4]", &[], Value(2.5)),
+    ("max[4]", &[], Value(4.0)),
+    ("sum[]", &[], Value(0.0)),
+    ("2 * max[1, 2]", &[], Value(4.0)),
+    ("sum[max[1, 2], min(3, 4), (1 + 1)] ^ 2", &[], Value(49.0)),
+    ("max[]", &[], Error("NotEnoughFunctionArguments")),
+    ("mean[]", &[], Error("NotEnoughFunctionArguments")),
+    ("median[1, 2]", &[], Error("FunctionNotFound { fct: \"median[]\" }")),
+    ("max[1, 2", &[], Error("ParsingError { msg: \"Expected square braket close.\", position: Some(8) }")),
+    ("max[1, 2)", &[], Error("ParsingError { msg: \"Expected square braket close.\", position: Some(8) }")),
+    ("max(1, 2]", &[], Error("ParsingError { msg: \"Expected braket close.\", position: Some(8) }")),
+    ("[1, 2]", &[], Error("UnexpectedToken { token: \"[\", position: 0 }")),
+
+    // Placeholders are never evaluated, the error lists every following placeholder
+    ("{n} + 1", &[("n", 1.0)], Error("UnboundPlaceholder { names: [\"n\"] }")),
+    ("a * {n} + {m} - {n}", &[("a", 1.0)], Error("UnboundPlaceholder { names: [\"n\", \"m\"] }")),
+    ("sin({q_1})", &[], Error("UnboundPlaceholder { names: [\"q_1\"] }")),
+    ("{ n }", &[], Error("UnrecognizedToken { token: \"{ n }\", position: 0 }")),
+    ("{1n}", &[], Error("UnrecognizedToken { token: \"{1n}\", position: 0 }")),
+    ("{n", &[], Error("UnrecognizedToken { token: \"{n\", position: 0 }")),
+
+    // Unrecognized elements and incomplete expressions
+    ("$", &[], Error("UnrecognizedToken { token: \"$\", position: 0 }")),
+    ("1 + * 2", &[], Error("UnexpectedToken { token: \"*\", position: 4 }")),
+    ("1 +", &[], Error("UnexpectedEndOfExpressionAt { position: 3 }")),
+    ("*2", &[], Error("UnexpectedToken { token: \"*\", position: 0 }")),
+    ("1 + $", &[], Error("UnrecognizedToken { token: \"$\", position: 4 }")),
+];
+
+/// Return a Calculator with the variables of a spec entry set.
+fn calculator_with(variables: &[(&str, f64)]) -> Calculator {
+    let mut calculator = Calculator::new();
+    for (name, value) in variables {
+        calculator.set_variable(name, *value).unwrap();
+    }
+    calculator
+}
+
+/// Return true when the parsed value matches the expected value.
+fn matches(value: f64, expected: f64) -> bool {
+    if expected.is_infinite() {
+        value == expected
+    } else {
+        (value - expected).abs() <= RTOL * expected.abs().max(f64::MIN_POSITIVE)
+    }
+}
+
+// Test that every expression of the grammar spec parses to the expected outcome
+#[test]
+fn grammar_spec() {
+    let mut failures: Vec<String> = Vec::new();
+    for (expression, variables, expected) in SPEC {
+        let result = calculator_with(variables).parse_str(expression);
+        let ok = match (&result, expected) {
+            (Ok(value), Value(expected)) => matches(*value, *expected),
+            (Ok(value), NaN) => value.is_nan(),
+            (Err(error), Error(expected)) => format!("{error:?}") == *expected,
+            _ => false,
+        };
+        if !ok {
+            failures.push(format!(
+                "{expression:?}: expected {expected:?}, got {result:?}"
+            ));
+        }
+    }
+    assert!(
+        failures.is_empty(),
+        "Grammar spec mismatches:\n{}",
+        failures.join("\n")
+    );
+}
+
+// Test that parsing a CalculatorFloat agrees with parsing the string for valid expressions
+#[test]
+fn grammar_spec_calculator_float() {
+    for (expression, variables, expected) in SPEC {
+        if matches!(expected, Error(_)) {
+            continue;
+        }
+        let calculator = calculator_with(variables);
+        let value = calculator.parse_str(expression).unwrap();
+        let float = CalculatorFloat::from_str(expression).unwrap();
+        let float_value = calculator.parse_get(float).unwrap();
+        assert!(
+            float_value == value || (value.is_nan() && float_value.is_nan()),
+            "{expression:?}: parse_get returned {float_value} instead of {value}"
+        );
+    }
+}
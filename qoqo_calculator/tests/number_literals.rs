@@ -0,0 +1,179 @@
+// Copyright © 2020-2021 HQS Quantum Simulations GmbH. All Rights Reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except
+// in compliance with the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the
+// License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either
+// express or implied. See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Differential test of the number literals of the lexer against `f64::from_str`.
+//!
+//! The lexer isolates number literals on bytes and converts them with the locale independent
+//! `f64::from_str`. Every literal has to give a bit-identical Token::Number, including literals
+//! at the boundaries of the exact fast path, subnormals, huge exponents and halfway cases.
+
+use qoqo_calculator::{tokenize, Token};
+use std::str::FromStr;
+
+/// Literals at the boundaries of the fast path and known hard cases of float conversion.
+const TRICKY_LITERALS: &[&str] = &[
+    // 2^53 and the halfway cases around it
+    "9007199254740992",
+    "9007199254740993",
+    "9007199254740994",
+    "9007199254740995",
+    "9007199254740993e0",
+    "900719925474099.3e1",
+    // Powers of ten at the end of the exactly representable range
+    "1e22",
+    "1e23",
+    "1e-22",
+    "1e-23",
+    "123456789e22",
+    "9007199254740991e22",
+    "9007199254740991e-22",
+    // Halfway cases between neighbouring floats
+    "0.1",
+    "0.3",
+    "2.5e-1",
+    "5e-324",
+    "2.4703282292062327e-324",
+    "2.4703282292062328e-324",
+    "2.2250738585072011e-308",
+    "2.2250738585072014e-308",
+    "4.9406564584124654e-324",
+    "1.7976931348623157e308",
+    "1.7976931348623158e308",
+    "1.7976931348623159e308",
+    "8.98846567431158e307",
+    "7.038531e-26",
+    "9214843084008499",
+    "30078505129381147446200",
+    "1777820000000000000001",
+    "0.500000000000000166533453693773481063544750213623046875",
+    "3.518437208883201171875e13",
+    "62.5364939768271845828",
+    "8.10109172351e-10",
+    "1.00000005960464477550",
+    "1.00000000000000011102230246251565404236316680908203125",
+    "1.00000000000000011102230246251565404236316680908203124",
+    "1.00000000000000011102230246251565404236316680908203126",
+    // Overflow, underflow and long exponents
+    "1e309",
+    "1e-400",
+    "0e999999999999999999999",
+    "1e-999999999999999999999",
+    "1e999999999999999999999",
+    // Leading and trailing zeros
+    "0000000000000000000000001",
+    "1.0000000000000000000000000",
+    "0.0000000000000000000000001",
+    ".5",
+    "5.",
+];
+
+/// Return the float of the first Token of a literal, None when it is not a Token::Number.
+fn lexed_number(literal: &str) -> Option<f64> {
+    match tokenize(literal).first() {
+        Some(Token::Number(x)) => Some(*x),
+        _ => None,
+    }
+}
+
+/// Assert that the lexer gives the bit-identical float of `f64::from_str` for a literal.
+fn assert_bit_identical(literal: &str) {
+    let expected = f64::from_str(literal).unwrap();
+    let lexed = lexed_number(literal).unwrap_or_else(|| panic!("{literal} is not lexed"));
+    assert_eq!(lexed.to_bits(), expected.to_bits(), "{literal}");
+}
+
+/// Deterministic xorshift generator, the test does not depend on a random number crate.
+struct XorShift(u64);
+
+impl XorShift {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn below(&mut self, bound: u64) -> u64 {
+        self.next() % bound
+    }
+}
+
+/// Generate a literal with up to 25 mantissa digits, an optional point and an optional exponent.
+fn generated_literal(generator: &mut XorShift) -> String {
+    let digits = 1 + generator.below(25) as usize;
+    let mut literal: String = (0..digits)
+        .map(|_| char::from(b'0' + generator.below(10) as u8))
+        .collect();
+    if generator.below(2) == 0 {
+        literal.insert(generator.below(digits as u64 + 1) as usize, '.');
+    }
+    if generator.below(4) != 0 {
+        let exponent = generator.below(700) as i64 - 350;
+        let marker = if generator.below(2) == 0 { 'e' } else { 'E' };
+        let sign = if exponent >= 0 && generator.below(2) == 0 {
+            "+"
+        } else {
+            ""
+        };
+        literal.push_str(&format!("{marker}{sign}{exponent}"));
+    }
+    literal
+}
+
+// Test the literals at the boundaries of the fast path and known hard cases
+#[test]
+fn tricky_literals() {
+    for literal in TRICKY_LITERALS {
+        assert_bit_identical(literal);
+    }
+}
+
+// Test generated literals with many digits and exponents over the whole range
+#[test]
+fn generated_literals() {
+    let mut generator = XorShift(0x9e3779b97f4a7c15);
+    for _ in 0..200_000 {
+        assert_bit_identical(&generated_literal(&mut generator));
+    }
+}
+
+// Test all short literals with small exponents
+#[test]
+fn short_literals() {
+    for mantissa in (0..1000_u64).chain([9007199254740991, 9007199254740992, 9007199254740993]) {
+        for exponent in -30..=40 {
+            assert_bit_identical(&format!("{mantissa}e{exponent}"));
+            assert_bit_identical(&format!("{mantissa}.5e{exponent}"));
+        }
+    }
+}
+
+// Test that the shortest representation of every float is lexed back to the same float
+#[test]
+fn round_trip_of_floats() {
+    let mut generator = XorShift(0x2545f4914f6cdd1d);
+    for _ in 0..100_000 {
+        let value = f64::from_bits(generator.next() & !(1 << 63));
+        if value.is_finite() {
+            assert_bit_identical(&format!("{value:e}"));
+            assert_eq!(lexed_number(&format!("{value:e}")), Some(value));
+        }
+    }
+}
+
+// Test that invalid literals are not lexed as numbers
+#[test]
+fn invalid_literals() {
+    for literal in ["1.2.3", "1e", "1e+", "."] {
+        assert_eq!(tokenize(literal).first(), Some(&Token::Unrecognized));
+    }
+}
@@ -0,0 +1,520 @@
+// Copyright © 2020-2021 HQS Quantum Simulations GmbH. All Rights Reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except
+// in compliance with the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the
+// License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either
+// express or implied. See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Compatibility of the CalculatorComplex serialization with serde container attributes.
+//!
+//! The default serialization of CalculatorComplex is the tuple `(re, im)`.
+//! The tests document which `#[serde(untagged)]` and `#[serde(flatten)]` patterns work with
+//! the tuple form and where [tagged_complex] is needed to serialize as `{"re": ..., "im": ...}`.
+//! Besides the tuple form, the map form `{"re": ..., "im": ...}` is read in JSON, also with the
+//! keys `real` and `imag`.
+//! The JSON fixtures of [always_tagged] and [always_untagged] pin both forms of CalculatorFloat.
+
+use qoqo_calculator::serialize::{always_tagged, always_untagged, tagged_complex};
+use qoqo_calculator::{Calculator, CalculatorComplex, CalculatorFloat};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Untagged enum with a pair of floats next to a CalculatorComplex in tuple form.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+enum PairFirst {
+    Pair((f64, f64)),
+    Complex(CalculatorComplex),
+}
+
+/// Untagged enum trying the CalculatorComplex in tuple form before the pair of floats.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+enum ComplexFirst {
+    Complex(CalculatorComplex),
+    Pair((f64, f64)),
+}
+
+/// Untagged enum with a CalculatorComplex in map form.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+enum Tagged {
+    Pair((f64, f64)),
+    Complex(#[serde(with = "tagged_complex")] CalculatorComplex),
+}
+
+/// Struct flattening a CalculatorComplex in tuple form.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct FlattenTuple {
+    name: String,
+    #[serde(flatten)]
+    value: CalculatorComplex,
+}
+
+/// Struct flattening a CalculatorComplex in map form.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct FlattenTagged {
+    name: String,
+    #[serde(flatten, with = "tagged_complex")]
+    value: CalculatorComplex,
+}
+
+/// Struct holding a CalculatorComplex field next to other fields inside an untagged enum.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Named {
+    name: String,
+    value: CalculatorComplex,
+}
+
+/// Untagged enum with a struct variant holding a CalculatorComplex.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+enum NamedOrFloat {
+    Float(f64),
+    Named(Named),
+}
+
+// A numeric CalculatorComplex in tuple form is indistinguishable from a pair of floats:
+// the first variant that matches wins
+#[test]
+fn untagged_numeric_tuple_is_ambiguous() {
+    let complex = PairFirst::Complex(CalculatorComplex::new(1.0, 2.0));
+    let serialized = serde_json::to_string(&complex).unwrap();
+    assert_eq!(serialized, "[1.0,2.0]");
+    let deserialized: PairFirst = serde_json::from_str(&serialized).unwrap();
+    assert_eq!(deserialized, PairFirst::Pair((1.0, 2.0)));
+
+    let pair = ComplexFirst::Pair((1.0, 2.0));
+    let serialized = serde_json::to_string(&pair).unwrap();
+    let deserialized: ComplexFirst = serde_json::from_str(&serialized).unwrap();
+    assert_eq!(
+        deserialized,
+        ComplexFirst::Complex(CalculatorComplex::new(1.0, 2.0))
+    );
+}
+
+// A symbolic CalculatorComplex in tuple form does not match a pair of floats and round-trips
+#[test]
+fn untagged_symbolic_tuple_works() {
+    let complex = PairFirst::Complex(CalculatorComplex::new("a", 2.0));
+    let serialized = serde_json::to_string(&complex).unwrap();
+    assert_eq!(serialized, "[\"a\",2.0]");
+    let deserialized: PairFirst = serde_json::from_str(&serialized).unwrap();
+    assert_eq!(deserialized, complex);
+}
+
+// The map form of tagged_complex can not be confused with a pair of floats
+#[test]
+fn untagged_tagged_complex_works() {
+    for value in [
+        Tagged::Complex(CalculatorComplex::new(1.0, 2.0)),
+        Tagged::Complex(CalculatorComplex::new("a", "b")),
+        Tagged::Pair((1.0, 2.0)),
+    ] {
+        let serialized = serde_json::to_string(&value).unwrap();
+        let deserialized: Tagged = serde_json::from_str(&serialized).unwrap();
+        assert_eq!(deserialized, value);
+    }
+    let serialized =
+        serde_json::to_string(&Tagged::Complex(CalculatorComplex::new(1.0, "b"))).unwrap();
+    assert_eq!(serialized, "{\"re\":1.0,\"im\":\"b\"}");
+}
+
+// A tuple can not be flattened into the fields of a struct,
+// the legacy map form is still read from the flattened fields
+#[test]
+fn flatten_tuple_fails() {
+    let value = FlattenTuple {
+        name: "x".to_string(),
+        value: CalculatorComplex::new(1.0, 2.0),
+    };
+    assert!(serde_json::to_string(&value).is_err());
+    assert_eq!(
+        serde_json::from_str::<FlattenTuple>("{\"name\":\"x\",\"re\":1.0,\"im\":2.0}").unwrap(),
+        value
+    );
+}
+
+// The map form of tagged_complex can be flattened next to other fields
+#[test]
+fn flatten_tagged_complex_works() {
+    let value = FlattenTagged {
+        name: "x".to_string(),
+        value: CalculatorComplex::new(1.0, "b"),
+    };
+    let serialized = serde_json::to_string(&value).unwrap();
+    assert_eq!(serialized, "{\"name\":\"x\",\"re\":1.0,\"im\":\"b\"}");
+    let deserialized: FlattenTagged = serde_json::from_str(&serialized).unwrap();
+    assert_eq!(deserialized, value);
+}
+
+/// JSON attributes written by different versions, with CalculatorComplex in tuple and map form.
+const LEGACY_SEQUENCE: &str = r#"[
+    [0.5, -1.0],
+    {"re": "theta", "im": 0.0},
+    {"im": 2.0, "re": 1e-3},
+    ["2 * phi", "phi"]
+]"#;
+const LEGACY_MAP: &str = r#"{
+    "amplitude": {"re": 0.25, "im": "-theta"},
+    "offset": [0.0, 1.0]
+}"#;
+
+// Legacy map form with keys re and im is read next to the tuple form
+#[test]
+fn legacy_map_form_json() {
+    let expected = vec![
+        CalculatorComplex::new(0.5, -1.0),
+        CalculatorComplex::new("theta", 0.0),
+        CalculatorComplex::new(1e-3, 2.0),
+        CalculatorComplex::new("2 * phi", "phi"),
+    ];
+    let values: Vec<CalculatorComplex> = serde_json::from_str(LEGACY_SEQUENCE).unwrap();
+    assert_eq!(values, expected);
+    // Written back in tuple form
+    let serialized = serde_json::to_string(&values).unwrap();
+    assert_eq!(
+        serialized,
+        "[[0.5,-1.0],[\"theta\",0.0],[0.001,2.0],[\"2 * phi\",\"phi\"]]"
+    );
+    assert_eq!(
+        serde_json::from_str::<Vec<CalculatorComplex>>(&serialized).unwrap(),
+        expected
+    );
+
+    let values: HashMap<String, CalculatorComplex> = serde_json::from_str(LEGACY_MAP).unwrap();
+    assert_eq!(
+        values,
+        HashMap::from([
+            (
+                "amplitude".to_string(),
+                CalculatorComplex::new(0.25, "-theta")
+            ),
+            ("offset".to_string(), CalculatorComplex::new(0.0, 1.0)),
+        ])
+    );
+
+    // The map form is not written by bincode and not read from it
+    let bytes = bincode::serialize(&expected).unwrap();
+    assert_eq!(
+        bincode::deserialize::<Vec<CalculatorComplex>>(&bytes).unwrap(),
+        expected
+    );
+}
+
+// The map form accepts the keys real and imag of the Python to_dict and mixed parts
+#[test]
+fn map_form_aliases_json() {
+    for (fixture, expected) in [
+        (
+            r#"{"real": 0.5, "imag": "theta"}"#,
+            CalculatorComplex::new(0.5, "theta"),
+        ),
+        (
+            r#"{"imag": -1.0, "re": "2 * phi"}"#,
+            CalculatorComplex::new("2 * phi", -1.0),
+        ),
+        (
+            r#"{"is_calculator_complex": true, "real": "a", "imag": "b"}"#,
+            CalculatorComplex::new("a", "b"),
+        ),
+        (r#"{"re": 1, "im": 2}"#, CalculatorComplex::new(1.0, 2.0)),
+    ] {
+        let value: CalculatorComplex = serde_json::from_str(fixture).unwrap();
+        assert_eq!(value, expected, "{fixture}");
+        // The tuple form stays the canonical serialization and reads back to the same value
+        let serialized = serde_json::to_string(&value).unwrap();
+        assert!(serialized.starts_with('['), "{serialized}");
+        assert_eq!(
+            serde_json::from_str::<CalculatorComplex>(&serialized).unwrap(),
+            expected
+        );
+    }
+}
+
+// Errors name the malformed part and the position of the element
+#[test]
+fn legacy_map_form_errors() {
+    let error = serde_json::from_str::<Vec<CalculatorComplex>>(
+        "[[0.5, -1.0], {\"re\": 1.0, \"im\": [2.0]}, [1.0, 0.0]]",
+    )
+    .unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "Invalid imaginary part: invalid type: sequence, expected float or string at line 1 column 33"
+    );
+    let error = serde_json::from_str::<HashMap<String, CalculatorComplex>>(
+        "{\"a\": [1.0, 2.0], \"b\": [1.0, null], \"c\": [0.0, 0.0]}",
+    )
+    .unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "Invalid imaginary part: invalid type: null, expected float or string at line 1 column 33"
+    );
+    for (fixture, message) in [
+        (
+            "{\"re\": 1.0}",
+            "Missing imaginary part at line 1 column 11",
+        ),
+        ("{\"im\": 1.0}", "Missing real part at line 1 column 11"),
+        (
+            "{\"re\": 1.0, \"re\": 2.0, \"im\": 0.0}",
+            "Duplicate real part at line 1 column 16",
+        ),
+        (
+            "{\"re\": 1.0, \"real\": 2.0, \"im\": 0.0}",
+            "Duplicate real part at line 1 column 18",
+        ),
+        (
+            "{\"re\": 1.0, \"imaginary\": 2.0}",
+            "unknown field `imaginary`, expected one of `re`, `im`, `real`, `imag` at line 1 column 23",
+        ),
+        ("[1.0]", "Missing imaginary part at line 1 column 5"),
+        (
+            "1.0",
+            "invalid type: floating point `1.0`, expected Tuple of two CalculatorFloat values \
+             (float or string) or map with keys re and im at line 1 column 3",
+        ),
+    ] {
+        let error = serde_json::from_str::<CalculatorComplex>(fixture).unwrap_err();
+        assert_eq!(error.to_string(), message, "{fixture}");
+    }
+}
+
+// A CalculatorComplex field of a struct variant is unambiguous in tuple form
+#[test]
+fn untagged_struct_variant_works() {
+    for value in [
+        NamedOrFloat::Float(0.5),
+        NamedOrFloat::Named(Named {
+            name: "x".to_string(),
+            value: CalculatorComplex::new(1.0, 2.0),
+        }),
+    ] {
+        let serialized = serde_json::to_string(&value).unwrap();
+        let deserialized: NamedOrFloat = serde_json::from_str(&serialized).unwrap();
+        assert_eq!(deserialized, value);
+    }
+}
+
+// A CalculatorFloat holding a float is indistinguishable from a float in an untagged enum
+#[test]
+fn untagged_calculator_float_is_ambiguous() {
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    #[serde(untagged)]
+    enum FloatOrCalculatorFloat {
+        Float(f64),
+        CalculatorFloat(CalculatorFloat),
+    }
+    let value = FloatOrCalculatorFloat::CalculatorFloat(CalculatorFloat::from(1.5));
+    let serialized = serde_json::to_string(&value).unwrap();
+    let deserialized: FloatOrCalculatorFloat = serde_json::from_str(&serialized).unwrap();
+    assert_eq!(deserialized, FloatOrCalculatorFloat::Float(1.5));
+
+    let value = FloatOrCalculatorFloat::CalculatorFloat(CalculatorFloat::from("a"));
+    let serialized = serde_json::to_string(&value).unwrap();
+    let deserialized: FloatOrCalculatorFloat = serde_json::from_str(&serialized).unwrap();
+    assert_eq!(deserialized, value);
+}
+
+/// Struct using both forms of CalculatorFloat side by side.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Mixed {
+    #[serde(with = "always_tagged")]
+    tagged: CalculatorFloat,
+    #[serde(with = "always_untagged")]
+    untagged: CalculatorFloat,
+    default: CalculatorFloat,
+}
+
+// JSON fixtures of always_tagged
+#[test]
+fn always_tagged_json() {
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Wrapper(#[serde(with = "always_tagged")] CalculatorFloat);
+
+    for (value, fixture) in [
+        (CalculatorFloat::from(0.5), "{\"Float\":0.5}"),
+        (CalculatorFloat::from("theta"), "{\"Str\":\"theta\"}"),
+        (
+            CalculatorFloat::from("2 * theta"),
+            "{\"Str\":\"2 * theta\"}",
+        ),
+    ] {
+        let wrapped = Wrapper(value);
+        assert_eq!(serde_json::to_string(&wrapped).unwrap(), fixture);
+        assert_eq!(serde_json::from_str::<Wrapper>(fixture).unwrap(), wrapped);
+    }
+    assert!(serde_json::from_str::<Wrapper>("0.5").is_err());
+    assert!(serde_json::from_str::<Wrapper>("\"theta\"").is_err());
+    assert!(serde_json::from_str::<Wrapper>("{\"Int\":1}").is_err());
+}
+
+// JSON fixtures of always_untagged
+#[test]
+fn always_untagged_json() {
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Wrapper(#[serde(with = "always_untagged")] CalculatorFloat);
+
+    for (value, fixture) in [
+        (CalculatorFloat::from(0.5), "0.5"),
+        (CalculatorFloat::from("theta"), "\"theta\""),
+        (CalculatorFloat::from("2 * theta"), "\"2 * theta\""),
+    ] {
+        let wrapped = Wrapper(value);
+        assert_eq!(serde_json::to_string(&wrapped).unwrap(), fixture);
+        assert_eq!(serde_json::from_str::<Wrapper>(fixture).unwrap(), wrapped);
+    }
+    assert_eq!(
+        serde_json::from_str::<Wrapper>("2").unwrap(),
+        Wrapper(CalculatorFloat::from(2.0))
+    );
+    assert_eq!(
+        serde_json::from_str::<Wrapper>("\"5e-1\"").unwrap(),
+        Wrapper(CalculatorFloat::from(0.5))
+    );
+    // Plain decimal literals in strings keep their digits
+    assert_eq!(
+        serde_json::from_str::<Wrapper>("\"0.50\"").unwrap(),
+        Wrapper(CalculatorFloat::decimal("0.50").unwrap())
+    );
+    assert!(serde_json::from_str::<Wrapper>("{\"Float\":0.5}").is_err());
+}
+
+// Non-finite floats are written as strings in JSON and all common spellings are read back
+#[test]
+fn non_finite_json() {
+    for (value, fixture) in [(f64::INFINITY, "\"inf\""), (f64::NEG_INFINITY, "\"-inf\"")] {
+        let value = CalculatorFloat::from(value);
+        assert_eq!(serde_json::to_string(&value).unwrap(), fixture);
+        assert_eq!(
+            serde_json::from_str::<CalculatorFloat>(fixture).unwrap(),
+            value
+        );
+    }
+    let nan = serde_json::to_string(&CalculatorFloat::from(f64::NAN)).unwrap();
+    assert_eq!(nan, "\"NaN\"");
+    assert!(serde_json::from_str::<CalculatorFloat>(&nan)
+        .unwrap()
+        .float()
+        .unwrap()
+        .is_nan());
+
+    for (spelling, expected) in [
+        ("inf", f64::INFINITY),
+        ("+inf", f64::INFINITY),
+        ("Inf", f64::INFINITY),
+        ("Infinity", f64::INFINITY),
+        ("+Infinity", f64::INFINITY),
+        ("INFINITY", f64::INFINITY),
+        ("-inf", f64::NEG_INFINITY),
+        ("-Infinity", f64::NEG_INFINITY),
+        ("-INF", f64::NEG_INFINITY),
+    ] {
+        let fixture = format!("\"{spelling}\"");
+        let value = serde_json::from_str::<CalculatorFloat>(&fixture).unwrap();
+        assert_eq!(value, CalculatorFloat::from(expected), "{spelling}");
+        // Round trip through the canonical spelling
+        let serialized = serde_json::to_string(&value).unwrap();
+        assert_eq!(
+            serde_json::from_str::<CalculatorFloat>(&serialized).unwrap(),
+            value
+        );
+    }
+    for spelling in ["NaN", "nan", "NAN", "-NaN"] {
+        let value = serde_json::from_str::<CalculatorFloat>(&format!("\"{spelling}\"")).unwrap();
+        assert!(value.float().unwrap().is_nan(), "{spelling}");
+        assert_eq!(serde_json::to_string(&value).unwrap(), "\"NaN\"");
+    }
+
+    let complex = CalculatorComplex::new(f64::NEG_INFINITY, "x");
+    let fixture = "[\"-inf\",\"x\"]";
+    assert_eq!(serde_json::to_string(&complex).unwrap(), fixture);
+    assert_eq!(
+        serde_json::from_str::<CalculatorComplex>(fixture).unwrap(),
+        complex
+    );
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Wrapper(#[serde(with = "always_untagged")] CalculatorFloat);
+    let wrapped = Wrapper(CalculatorFloat::from(f64::INFINITY));
+    assert_eq!(serde_json::to_string(&wrapped).unwrap(), "\"inf\"");
+    assert_eq!(
+        serde_json::from_str::<Wrapper>("\"Infinity\"").unwrap(),
+        wrapped
+    );
+}
+
+// Deserialized non-finite values are floats: they evaluate to the value instead of failing
+// as unknown variables, and can be rejected explicitly before evaluation
+#[test]
+fn non_finite_evaluation() {
+    let calculator = Calculator::new();
+    let value: CalculatorFloat = serde_json::from_str("\"-Infinity\"").unwrap();
+    assert_eq!(calculator.parse_get_ref(&value), Ok(f64::NEG_INFINITY));
+    assert!(!value.float().unwrap().is_finite());
+
+    let finite_only = |value: &CalculatorFloat| -> Result<f64, String> {
+        let result = calculator
+            .parse_get_ref(value)
+            .map_err(|err| err.to_string())?;
+        if result.is_finite() {
+            Ok(result)
+        } else {
+            Err(format!("non-finite value {result}"))
+        }
+    };
+    assert_eq!(finite_only(&CalculatorFloat::from(0.5)), Ok(0.5));
+    assert_eq!(
+        finite_only(&value),
+        Err("non-finite value -inf".to_string())
+    );
+    let nan: CalculatorFloat = serde_json::from_str("\"nan\"").unwrap();
+    assert!(finite_only(&nan).is_err());
+}
+
+// Fields with different forms of CalculatorFloat side by side in one struct
+#[test]
+fn mixed_forms_json() {
+    let value = Mixed {
+        tagged: CalculatorFloat::from("theta"),
+        untagged: CalculatorFloat::from("phi"),
+        default: CalculatorFloat::from(1.5),
+    };
+    let fixture = "{\"tagged\":{\"Str\":\"theta\"},\"untagged\":\"phi\",\"default\":1.5}";
+    assert_eq!(serde_json::to_string(&value).unwrap(), fixture);
+    assert_eq!(serde_json::from_str::<Mixed>(fixture).unwrap(), value);
+
+    let value = Mixed {
+        tagged: CalculatorFloat::from(0.25),
+        untagged: CalculatorFloat::from(-1.0),
+        default: CalculatorFloat::from("x"),
+    };
+    let fixture = "{\"tagged\":{\"Float\":0.25},\"untagged\":-1.0,\"default\":\"x\"}";
+    assert_eq!(serde_json::to_string(&value).unwrap(), fixture);
+    assert_eq!(serde_json::from_str::<Mixed>(fixture).unwrap(), value);
+}
+
+// The schema of the map form is selected with schemars(with)
+#[cfg(feature = "json_schema")]
+#[test]
+fn tagged_complex_schema() {
+    use qoqo_calculator::serialize::tagged_complex::TaggedComplex;
+
+    #[derive(Serialize, Deserialize, schemars::JsonSchema)]
+    struct WithSchema {
+        #[serde(with = "tagged_complex")]
+        #[schemars(with = "TaggedComplex")]
+        value: CalculatorComplex,
+    }
+    let schema = serde_json::to_value(schemars::schema_for!(WithSchema)).unwrap();
+    assert_eq!(
+        schema["properties"]["value"]["$ref"],
+        "#/definitions/TaggedComplex"
+    );
+}
@@ -0,0 +1,51 @@
+// Copyright © 2020-2021 HQS Quantum Simulations GmbH. All Rights Reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except
+// in compliance with the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the
+// License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either
+// express or implied. See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Test of the global limit of the symbolic length.
+//!
+//! The limit is global for the process, so it is changed in a separate test binary
+//! with a single test to not interfere with other tests.
+
+use qoqo_calculator::{
+    max_symbolic_length, set_max_symbolic_length, CalculatorError, CalculatorFloat,
+    DEFAULT_MAX_SYMBOLIC_LENGTH,
+};
+use std::panic;
+
+// Test that the configured limit is used by checked and infallible operations
+#[test]
+fn set_max_symbolic_length_limits_operations() {
+    assert_eq!(max_symbolic_length(), DEFAULT_MAX_SYMBOLIC_LENGTH);
+    set_max_symbolic_length(10);
+    assert_eq!(max_symbolic_length(), 10);
+
+    let x = CalculatorFloat::from("abcdef");
+    assert_eq!(
+        x.clone().checked_mul(x.clone()),
+        Err(CalculatorError::SymbolicExpressionTooLarge {
+            length: 12,
+            limit: 10
+        })
+    );
+    assert_eq!(
+        x.clone().checked_mul(2.0),
+        Ok(CalculatorFloat::from("(abcdef * 2e0)"))
+    );
+    let result = panic::catch_unwind(|| x.atan2(x.clone()));
+    assert!(result.is_err());
+
+    set_max_symbolic_length(DEFAULT_MAX_SYMBOLIC_LENGTH);
+    assert_eq!(
+        x.clone().checked_mul(x),
+        Ok(CalculatorFloat::from("(abcdef * abcdef)"))
+    );
+}
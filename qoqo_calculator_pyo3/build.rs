@@ -1,3 +1,15 @@
 fn main() {
+    // Expose the Py_3_* and Py_LIMITED_API cfgs used by the interpreter check in lib.rs
+    pyo3_build_config::use_pyo3_cfgs();
     pyo3_build_config::add_extension_module_link_args();
+    // create_exception! of PyO3 0.22 checks the gil-refs feature in the calling crate
+    println!("cargo:rustc-check-cfg=cfg(feature, values(\"gil-refs\"))");
+    // The arithmetic methods do not catch panics, but PyO3 converts unexpected panics into
+    // PanicException only by unwinding, with panic=abort they terminate the interpreter
+    if std::env::var("CARGO_CFG_PANIC").as_deref() == Ok("abort") {
+        println!(
+            "cargo:warning=qoqo_calculator_pyo3 is built with panic=abort, \
+             an unexpected panic terminates the Python interpreter instead of raising PanicException"
+        );
+    }
 }
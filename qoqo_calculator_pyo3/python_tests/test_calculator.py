@@ -14,14 +14,515 @@ import sys
 import numpy as np
 import numpy.testing as npt
 import os
-from qoqo_calculator_pyo3 import Calculator
+import qoqo_calculator_pyo3
+from qoqo_calculator_pyo3 import Calculator, CalculatorFloat, CalculatorComplex, CalculatorAssertionError, CalculatorFrozenError
+from qoqo_calculator_pyo3 import parse, validate, variables_in, simplify, pi_fraction, dot, norm_sqr
+from qoqo_calculator_pyo3 import functions, error_kinds, operators, constants
 import math
+import copy
+import pickle
 
 def test_calculator_parse():
     c = Calculator() 
     c.set("a",1)
     assert c.parse_get("sin(a+1)") == math.sin(2)
 
+def test_calculator_variables_from_calculator_float():
+    """Numeric CalculatorFloats are accepted as values, symbolic ones raise ValueError."""
+    c = Calculator()
+    c.set("a", CalculatorFloat(1.5))
+    c.set_variable("b", CalculatorFloat(2))
+    c.set_variable("c", 3)
+    assert c.get_variable("a") == 1.5
+    assert c.get_variable("b") == 2.0
+    assert c.get_variable("c") == 3.0
+    assert c.parse_get(CalculatorFloat("a * b")) == 3.0
+    assert c.parse_get("a + c") == 4.5
+    assert c.parse_get(0.5) == 0.5
+    for value in [CalculatorFloat("x"), "x + 1"]:
+        with pytest.raises(ValueError):
+            c.set_variable("d", value)
+        with pytest.raises(ValueError):
+            c.parse_get(value)
+    with pytest.raises(TypeError):
+        c.set_variable("d", [1.0])
+    try:
+        c.get_variable("d")
+        assert False
+    except KeyError as err:
+        assert "d" in str(err)
+    c.set_variable("d", c.get_variable("a"))
+    assert c.get_variable("d") == c.get_variable("a")
+
+def test_calculator_sweep():
+    c = Calculator()
+    c.set("c", 0.5)
+    axes, values = c.sweep("x * c + y", [("x", [1, 2, 3]), ("y", [0.0, 0.25])])
+    assert axes == [("x", [1.0, 2.0, 3.0]), ("y", [0.0, 0.25])]
+    assert values == [x * 0.5 + y for x in [1, 2, 3] for y in [0.0, 0.25]]
+    with pytest.raises(ValueError, match=r"index: \[1, 1\]"):
+        c.sweep("1 / (x - y)", [("x", [0, 1]), ("y", [2, 1])])
+
+def test_calculator_parse_str_derivative():
+    c = Calculator()
+    c.set("x", 2.0)
+    c.set("y", 3.0)
+    assert c.parse_str_derivative("x^2 * y", "x") == (12.0, 12.0)
+    value, derivative = c.parse_str_derivative("sin(x)", "x")
+    assert value == math.sin(2.0)
+    assert derivative == math.cos(2.0)
+    with pytest.raises(ValueError):
+        c.parse_str_derivative("gamma(x)", "x")
+
+def test_calculator_complex_parsing():
+    c = Calculator()
+    c.set("x", 2.0)
+    assert c.parse_str_complex("1.5") == 1.5
+    assert c.parse_str_complex("3j") == 3j
+    assert c.parse_str_complex("(1 + 2i) * (x - 1i)") == (1 + 2j) * (2 - 1j)
+    assert c.parse_str_complex("sqrt(-4)") == 2j
+    assert c.parse_get_complex(CalculatorComplex.from_pair("x", "x^2")) == 2 + 4j
+    assert c.parse_get_complex(1 - 0.5j) == 1 - 0.5j
+    with pytest.raises(ValueError):
+        c.parse_str("1 + 2i")
+    with pytest.raises(ValueError):
+        c.parse_str_complex("1i < 2")
+
+def test_calculator_parse_str_with_warnings():
+    c = Calculator()
+    c.set("theta", 0.5)
+    c.set("thetta", 0.25)
+    assert c.parse_str_with_warnings("sin(theta) + thetta") == (math.sin(0.5) + 0.25, [])
+    value, warnings = c.parse_str_with_warnings("max(2,5) * thetta")
+    assert value == 1.25
+    assert len(warnings) == 2
+    assert "position 5" in warnings[0]
+    assert "\"thetta\"" in warnings[1]
+    value, warnings = c.parse_str_assign_with_warnings("a=1; b=2; a + 1")
+    assert value == 2
+    assert warnings == ["Variable \"b\" assigned at position 5 is not read afterwards"]
+    with pytest.raises(ValueError):
+        c.parse_str_with_warnings("1 / 0")
+    assert c.parse_str_with_warnings("0.1000000000000000001 + 0.50000000000000000000")[1] == []
+    c.warn_on_precision_loss(True)
+    value, warnings = c.parse_str_with_warnings("0.1000000000000000001 + 0.50000000000000000000")
+    assert value == 0.6
+    assert warnings == ["Number 0.1000000000000000001 at position 0 is not exactly representable and is parsed as 1e-1"]
+
+@pytest.mark.parametrize("initial", [
+    ("sin(a+1)", {"a": 1}, math.sin(2)),
+    ("2 * 3", None, 6),
+    ("a / b", {"a": 1, "b": 4}, 0.25),
+])
+def test_parse(initial):
+    assert math.isclose(parse(initial[0], initial[1]), initial[2])
+
+@pytest.mark.parametrize("expression", [
+    "a + 1",
+    "a = 1",
+    "1 / 0",
+])
+def test_parse_fail(expression):
+    with pytest.raises(ValueError):
+        parse(expression)
+
+def test_validate():
+    assert validate("sin(a) + (b * 2) ^ c") is None
+    with pytest.raises(ValueError, match="position: 4"):
+        validate("a + $")
+    with pytest.raises(ValueError, match="position: 7"):
+        validate("(a + 1))")
+    with pytest.raises(ValueError, match="NotParsableAssign"):
+        validate("1 + b = 2")
+
+def test_variables_in():
+    assert variables_in("b * sin(a) + b / atan2(c, 2)") == ["b", "a", "c"]
+    assert variables_in("1 + 2") == []
+    with pytest.raises(ValueError):
+        variables_in("a = 1")
+
+def test_simplify():
+    assert simplify("(2 * 3) * x + 0") == "(6e0 * x)"
+    assert simplify("1 * y + 0") == "y"
+    assert simplify("2 ^ 3 - 1") == "7e0"
+    assert simplify("x / 0") == "x / 0"
+    assert simplify("(2 * 3) * x") == str(CalculatorFloat("(2 * 3) * x").simplify())
+    with pytest.raises(ValueError, match="position: 4"):
+        simplify("a + $")
+    with pytest.raises(ValueError):
+        simplify("(a + 1")
+
+@pytest.mark.parametrize("initial", [
+    (1, 2, np.pi / 2),
+    (-3, 4, -3 * np.pi / 4),
+    (0, 1, 0),
+])
+def test_pi_fraction(initial):
+    value = pi_fraction(initial[0], initial[1])
+    assert isinstance(value, CalculatorFloat)
+    assert value.isclose(initial[2])
+
+def test_pi_fraction_fail():
+    with pytest.raises(ZeroDivisionError):
+        pi_fraction(1, 0)
+
+def test_dot():
+    assert dot([1, 2], [3, 4]) == CalculatorFloat(11)
+    value = dot([1, 2, CalculatorFloat("b")], ["a", 3, 2])
+    assert value == CalculatorFloat("(a + (b * 2e0) + 6e0)")
+    assert parse(value.value, {"a": 1, "b": 0.5}) == 8
+    assert dot([], []) == CalculatorFloat(0)
+    with pytest.raises(ValueError):
+        dot([1, 2], [1])
+    with pytest.raises(TypeError):
+        dot([dict()], [1])
+
+def test_norm_sqr():
+    assert norm_sqr([3, 4]) == CalculatorFloat(25)
+    assert norm_sqr([3, "a"]) == CalculatorFloat("((a * a) + 9e0)")
+
+def test_expression_variable():
+    calculator = Calculator()
+    calculator.set("alpha", 2.0)
+    calculator.set_expression_variable("beta", "2*alpha")
+    calculator.set_expression_variable("gamma", "beta + alpha")
+    assert calculator.parse_str("gamma") == 6
+    assert calculator.parse_get(CalculatorFloat("beta")) == 4
+    assert calculator.get_expression_variable("beta") == "2*alpha"
+    assert calculator.get_expression_variable("alpha") is None
+    assert calculator.expression_variables() == {"beta": "2*alpha", "gamma": "beta + alpha"}
+    calculator.set("beta", 1.0)
+    assert calculator.parse_str("gamma") == 3
+    calculator.set_expression_variable("delta_var", "delta_var + 1")
+    with pytest.raises(ValueError):
+        calculator.parse_str("delta_var")
+    with pytest.raises(ValueError):
+        calculator.set_expression_variable("epsilon", "a = 1")
+
+def test_bulk_variables():
+    calculator = Calculator()
+    calculator.set_variables({"alpha": 1.0, "beta": 2.5})
+    calculator.set_variables({"gamma": -1})
+    assert calculator.get_variables(["gamma", "alpha", "beta"]) == [-1.0, 1.0, 2.5]
+    assert calculator.parse_str("alpha + beta") == 3.5
+    try:
+        calculator.get_variables(["alpha", "delta", "epsilon"])
+        assert False
+    except ValueError as error:
+        assert "delta" in str(error)
+        assert "epsilon" in str(error)
+
+def test_interpolation_table():
+    calculator = Calculator()
+    calculator.register_interpolation_table("flux", [0.0, 1.0, 2.0], np.array([0.0, 10.0, 30.0]))
+    calculator.register_interpolation_table("strict", (0, 1), [1.0, 2.0], out_of_range="error")
+    calculator.set("x", 1.5)
+    assert calculator.parse_str("flux(x)") == 20
+    assert calculator.parse_str("flux(1)") == 10
+    assert calculator.parse_str("flux(-1)") == 0
+    assert calculator.parse_str("strict(0.25)") == 1.25
+    with pytest.raises(ValueError):
+        calculator.parse_str("strict(x)")
+    with pytest.raises(ValueError):
+        calculator.register_interpolation_table("bad", [1.0, 0.0], [0.0, 1.0])
+    with pytest.raises(ValueError):
+        calculator.register_interpolation_table("bad", [0.0], [0.0])
+    with pytest.raises(ValueError):
+        calculator.register_interpolation_table("sin", [0.0, 1.0], [0.0, 1.0])
+    with pytest.raises(ValueError):
+        calculator.register_interpolation_table("bad", [0.0, 1.0], [0.0, 1.0], out_of_range="wrap")
+    with pytest.raises(TypeError):
+        calculator.register_interpolation_table("bad", 1.0, [0.0, 1.0])
+
+def test_gil_released_during_evaluation():
+    """Other Python threads keep running while a long evaluation runs in a thread."""
+    import threading
+    calculator = Calculator()
+    calculator.set("c", 0.5)
+    axis = [0.001 * i for i in range(600)]
+    started = threading.Event()
+    result = []
+
+    def evaluate():
+        started.set()
+        result.append(calculator.sweep("sin(x) * cos(y) + c", [("x", axis), ("y", axis)]))
+
+    worker = threading.Thread(target=evaluate)
+    worker.start()
+    started.wait()
+    counter = 0
+    while worker.is_alive():
+        counter += 1
+    worker.join()
+    assert counter > 1000
+    _, values = result[0]
+    assert len(values) == 600 * 600
+    assert values[601] == math.sin(0.001) * math.cos(0.001) + 0.5
+
+def test_results_unchanged_without_gil():
+    calculator = Calculator()
+    calculator.set("a", 0.25)
+    assert calculator.parse_str("exp(a) * 2") == math.exp(0.25) * 2
+    assert calculator.parse_get("a + 1") == 1.25
+    assert calculator.parse_get(CalculatorFloat("a * 4")) == 1.0
+    assert calculator.parse_get(3) == 3.0
+    assert calculator.parse_str_with_warnings("a + 1") == (1.25, [])
+    with pytest.raises(ValueError):
+        calculator.parse_str("b + 1")
+    with pytest.raises(ValueError):
+        calculator.parse_get(CalculatorFloat("b"))
+
+def test_assert():
+    """A failing assert raises CalculatorAssertionError and aborts the script."""
+    calculator = Calculator()
+    calculator.set("t_rise", 1.0)
+    calculator.set("t_fall", 2.0)
+    calculator.set("t_total", 5.0)
+    script = "assert(theta(t_total - t_rise - t_fall), 'pulse too long'); t_total"
+    assert calculator.parse_str(script) == 5.0
+    calculator.set("t_total", 2.0)
+    try:
+        calculator.parse_str(script)
+        assert False
+    except CalculatorAssertionError as err:
+        assert "pulse too long (position 0)" in str(err)
+    assert issubclass(CalculatorAssertionError, ValueError)
+    with pytest.raises(CalculatorAssertionError):
+        calculator.parse_str_assign("a=1; assert(0); b=2")
+    with pytest.raises(ValueError):
+        calculator.get_variables(["a"])
+    with pytest.raises(CalculatorAssertionError):
+        calculator.sweep("assert(x)", [("x", [1.0, 0.0])])
+    with pytest.raises(CalculatorAssertionError):
+        calculator.parse_get("assert(0, 'fails')")
+    with pytest.raises(CalculatorAssertionError):
+        parse("assert(a - 1)", {"a": 1.0})
+
+def test_calculator_aliasing():
+    """A Calculator is shared by all names referring to it, copies are independent."""
+    calculator = Calculator()
+    alias = calculator
+    alias.set("a", 1.0)
+    assert calculator.parse_str("a") == 1.0
+    copied = copy.copy(calculator)
+    deep_copied = copy.deepcopy(calculator)
+    copied.set("a", 2.0)
+    deep_copied.set("a", 3.0)
+    assert calculator.parse_str("a") == 1.0
+    assert copied.parse_str("a") == 2.0
+    assert deep_copied.parse_str("a") == 3.0
+
+def test_freeze():
+    """A frozen Calculator raises CalculatorFrozenError on changes and keeps evaluating."""
+    calculator = Calculator()
+    calculator.set("a", 2.0)
+    calculator.set_expression_variable("b", "a * 3")
+    assert not calculator.is_frozen()
+    calculator.freeze()
+    assert calculator.is_frozen()
+    try:
+        calculator.set("a", 5.0)
+        assert False
+    except CalculatorFrozenError as err:
+        assert "set_variable" in str(err)
+    assert issubclass(CalculatorFrozenError, ValueError)
+    with pytest.raises(CalculatorFrozenError):
+        calculator.set_variables({"a": 5.0})
+    with pytest.raises(CalculatorFrozenError):
+        calculator.remove_variable("a")
+    with pytest.raises(CalculatorFrozenError):
+        calculator.parse_str_assign("a=5")
+    with pytest.raises(CalculatorFrozenError):
+        calculator.parse_str_assign_with_warnings("a=5")
+    with pytest.raises(CalculatorFrozenError):
+        calculator.set_expression_variable("b", "a")
+    with pytest.raises(CalculatorFrozenError):
+        calculator.register_interpolation_table("flux", [0.0, 1.0], [0.0, 1.0])
+    assert calculator.get_variables(["a"]) == [2.0]
+    assert calculator.parse_str("a + b") == 8.0
+    assert calculator.parse_get(CalculatorFloat("a * 2")) == 4.0
+    calculator.unfreeze()
+    assert not calculator.is_frozen()
+    calculator.set("a", 5.0)
+    assert calculator.remove_variable("a") == 5.0
+    assert calculator.remove_variable("a") is None
+
+def test_introspection():
+    """Functions, operators and error kinds are listed for tooling."""
+    listed = functions()
+    assert listed[0] == {"name": "abs", "arity": 1, "variadic": False, "description": "Absolute value"}
+    maximum = [function for function in listed if function["name"] == "max"][0]
+    assert (maximum["arity"], maximum["variadic"]) == (2, True)
+    names = [function["name"] for function in listed]
+    assert names == sorted(names)
+    assert "sum_vars" in names
+    for function in listed:
+        if function["name"] in ("assert", "count_vars", "include", "sum_vars"):
+            continue
+        arguments = ", ".join(["0.5"] * function["arity"])
+        try:
+            parse("{}({})".format(function["name"], arguments))
+        except ValueError as err:
+            assert "InvalidFunctionArgument" in str(err)
+    assert "DivisionByZero" in error_kinds()
+    assert "AssertionFailed" in error_kinds()
+    power = [operator for operator in operators() if operator["symbol"] == "^"][0]
+    assert power == {"symbol": "^", "precedence": 4, "associativity": "none", "unary": False}
+    minus = [operator for operator in operators() if operator["symbol"] == "-"]
+    assert [operator["unary"] for operator in minus] == [False, True]
+
+def test_comparisons():
+    """Comparisons evaluate to 1.0 or 0.0, == is not an assignment."""
+    calculator = Calculator()
+    calculator.set("x", 0.5)
+    calculator.set("y", 3)
+    assert calculator.parse_str("max(x, (x > 0) * y)") == 3
+    assert calculator.parse_str("abs(x - 1) < 1e-6") == 0
+    assert calculator.parse_str_assign("x == y") == 0
+    assert calculator.get_variable("x") == 0.5
+    assert calculator.parse_str_derivative("(x >= 0) * x", "x") == (0.5, 1.0)
+    assert str(CalculatorFloat("x >= 2 * 3").simplify()) == "(x >= 6e0)"
+    symbols = [operator["symbol"] for operator in operators() if operator["precedence"] == 1]
+    assert symbols == ["<", ">", "<=", ">=", "==", "!="]
+
+def test_built_in_constants():
+    """Constants are used for names that are not set, variables take precedence."""
+    calculator = Calculator()
+    assert calculator.parse_str("2*pi") == 2 * math.pi
+    assert CalculatorFloat(calculator.parse_str("pi")) == CalculatorFloat(math.pi)
+    calculator.set("pi", 3)
+    assert calculator.parse_str("2*pi") == 6
+    for constant in constants():
+        if constant["name"] != "pi":
+            assert calculator.parse_str(constant["name"]) == constant["value"]
+
+def test_evaluate_over():
+    import array
+    c = Calculator()
+    c.set("amp", 0.5)
+    c.set("t0", 0.2)
+    times = [0.01 * i for i in range(600)]
+    result = c.evaluate_over("amp * exp(-((t-t0)/0.1)^2)", "t", times)
+    for time, value in zip(times, result):
+        c.set("t", time)
+        assert value == c.parse_str("amp * exp(-((t-t0)/0.1)^2)")
+    out = array.array("d", [0.0] * len(times))
+    assert c.evaluate_over("t * amp", "t", times, out) is out
+    assert list(out) == [0.5 * time for time in times]
+    with pytest.raises(ValueError):
+        c.evaluate_over("t", "t", times, array.array("d", [0.0]))
+    try:
+        c.evaluate_over("1 / (t - 1)", "t", [0.0, 1.0])
+        assert False
+    except ValueError as err:
+        assert "SweepPointError { index: [1]" in str(err)
+
+@pytest.mark.skipif(not hasattr(Calculator, "evaluate_over_ndarray"), reason="built without numpy feature")
+def test_ndarray():
+    c = Calculator()
+    c.set("amp", 0.5)
+    times = np.linspace(0.0, 1.0, 300)
+    result = c.evaluate_over_ndarray("amp * sin(t)", "t", times)
+    assert isinstance(result, np.ndarray)
+    assert result.dtype == np.float64
+    assert result.shape == (300,)
+    assert list(result) == c.evaluate_over("amp * sin(t)", "t", list(times))
+    axes = [("x", np.array([0.0, 1.0, 2.0])), ("y", np.array([1.0, 2.0]))]
+    result = c.sweep_ndarray("x * y + amp", axes)
+    assert result.dtype == np.float64
+    assert result.shape == (3, 2)
+    _, values = c.sweep("x * y + amp", [(name, list(values)) for name, values in axes])
+    assert list(result.ravel()) == values
+    with pytest.raises(ValueError):
+        c.evaluate_over_ndarray("t", "t", times[::2])
+    with pytest.raises(ValueError):
+        c.evaluate_over_ndarray("t", "t", np.zeros((2, 3)))
+    with pytest.raises(TypeError):
+        c.evaluate_over_ndarray("t", "t", np.arange(3))
+    with pytest.raises(TypeError):
+        c.evaluate_over_ndarray("t", "t", [0.0, 1.0])
+
+def test_check_str():
+    c = Calculator()
+    report = c.check_str("y=b; x=a / 0; log(-x) + max(b,1)", {"a", "b"})
+    assert report["variables"] == ["b", "a", "x"]
+    assert report["assigned"] == ["y", "x"]
+    assert report["functions"] == [("log", 1), ("max", 2)]
+    assert len(report["warnings"]) == 1
+    try:
+        c.check_str("a + b + c", {"a"})
+        assert False
+    except ValueError as err:
+        assert "[\"b\", \"c\"]" in str(err)
+    with pytest.raises(ValueError):
+        c.check_str("sin(a, a)", {"a"})
+
+def test_calculator_serialization():
+    """Pickling and JSON keep the variables and the variables defined by expressions."""
+    empty = Calculator()
+    assert pickle.loads(pickle.dumps(empty)).parse_str("1 + 1") == 2.0
+    assert Calculator.from_json(empty.to_json()).to_json() == empty.to_json()
+    c = Calculator()
+    for index in range(1000):
+        c.set_variable(f"v_{index}", math.sqrt(index) / 7)
+    c.set_expression_variable("total", "v_1 + v_999")
+    for restored in [pickle.loads(pickle.dumps(c)), Calculator.from_json(c.to_json())]:
+        for index in range(1000):
+            assert restored.get_variable(f"v_{index}") == math.sqrt(index) / 7
+        assert restored.get_expression_variable("total") == "v_1 + v_999"
+        assert restored.parse_str("total") == c.parse_str("total")
+    c.set_variable("inf", math.inf)
+    assert pickle.loads(pickle.dumps(c)).get_variable("inf") == math.inf
+    with pytest.raises(ValueError):
+        c.to_json()
+    for json in ["", "[1, 2]", '{"variables": {"a": "x"}}', '{"expression_variables": {"a": "b=1"}}']:
+        with pytest.raises(ValueError):
+            Calculator.from_json(json)
+
+def test_parse_error_position():
+    c = Calculator()
+    try:
+        c.parse_str("  # comment\n 1 + 2)")
+        assert False
+    except ValueError as err:
+        assert 'UnexpectedToken { token: ")", position: 18 }' in str(err)
+
+def test_validate_expression():
+    c = Calculator()
+    assert c.validate_expression("a * sin(b) + max(a, c, 2)") == ["a", "b", "c"]
+    assert c.validate_expression("2 * 3") == []
+    for expression in ["(a + b", "a + b)", "foo(a)", "atan2(a)", "a=2; a", "a $ b"]:
+        with pytest.raises(ValueError):
+            c.validate_expression(expression)
+
+def test_parse_string_assign():
+    """One-shot evaluation allows assignments, variables are not kept between calls."""
+    assert qoqo_calculator_pyo3.parse_string_assign("a=3; # first\nb=a+1; a*b") == 12.0
+    with pytest.raises(ValueError):
+        qoqo_calculator_pyo3.parse_string_assign("a")
+    with pytest.raises(ValueError):
+        parse("a=3; a*2")
+
+def test_all():
+    for name in [
+        "parse", "validate", "variables_in", "simplify", "pi_fraction", "parse_string_assign"
+    ]:
+        assert name in qoqo_calculator_pyo3.__all__
+        assert callable(getattr(qoqo_calculator_pyo3, name))
+
+def test_reload():
+    """Reloading creates no second module: the classes keep their identity.
+
+    Values created before the reload are instances of the classes after the reload.
+    """
+    import importlib
+    value = CalculatorFloat("a")
+    extension = importlib.reload(qoqo_calculator_pyo3.qoqo_calculator_pyo3)
+    package = importlib.reload(qoqo_calculator_pyo3)
+    assert extension.CalculatorFloat is CalculatorFloat
+    assert package.Calculator is Calculator
+    assert isinstance(value, package.CalculatorFloat)
+    assert package.parse("a * 2", {"a": 1.5}) == 3.0
 
 if __name__ == '__main__':
     pytest.main(sys.argv)
\ No newline at end of file
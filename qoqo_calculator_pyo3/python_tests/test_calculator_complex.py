@@ -14,8 +14,10 @@ import sys
 import numpy as np
 import numpy.testing as npt
 import os
-from qoqo_calculator_pyo3 import CalculatorFloat, CalculatorComplex
+from qoqo_calculator_pyo3 import CalculatorFloat, CalculatorComplex, Calculator
 import math
+import cmath
+import numbers
 
 @pytest.mark.parametrize("start_value", [
     0,
@@ -51,6 +53,25 @@ def test_str_init():
     assert cf.imag.value == 0
 
 
+def test_predicates():
+    """is_zero needs both parts to be the float zero, is_real only the imaginary part."""
+    assert CalculatorComplex(0).is_zero
+    assert not CalculatorComplex.from_pair(0, 1e-300).is_zero
+    assert CalculatorComplex.from_pair("x", 0).is_real
+    assert not CalculatorComplex.from_pair("x", 0).is_zero
+    assert not CalculatorComplex.from_pair(1, "y").is_real
+
+
+def test_substitute():
+    """A variable is replaced in both parts, a float part stays a float."""
+    z = CalculatorComplex.from_pair("x * y", "sin(x)")
+    assert z.substitute("x", "z") == CalculatorComplex.from_pair("z * y", "sin(z)")
+    assert z.substitute("y", 2).real == CalculatorFloat("x * 2e0")
+    assert CalculatorComplex.from_pair("x", 1).substitute("x", 3) == CalculatorComplex.from_pair(3, 1)
+    with pytest.raises(TypeError):
+        z.substitute("x", dict())
+
+
 def test_failed_init():
     with pytest.raises(TypeError):
         cf = CalculatorComplex(dict())
@@ -117,6 +138,24 @@ def test_div(init):
     cf = CalculatorComplex(init[1])
     assert (init[0] / cf) == CalculatorComplex(init[2])
 
+def test_augmented_assignment_aliasing():
+    """Augmented assignments bind a new object and leave other names unchanged."""
+    original = CalculatorComplex.from_pair("x", 1)
+    a = CalculatorComplex.from_pair("x", 1)
+    b = a
+    b += 1
+    assert b == CalculatorComplex.from_pair("(x + 1e0)", 1)
+    assert a == original
+    b = a
+    b -= 1
+    b *= 2
+    b /= 4
+    assert a == original
+    assert b is not a
+    assert +a == a
+    for method in ["__iadd__", "__isub__", "__imul__", "__itruediv__"]:
+        assert not hasattr(a, method)
+
 
 @pytest.mark.parametrize("initial", [
     ((1, 0), (1, 0), True),
@@ -155,6 +194,17 @@ def test_complex_arg(initial):
     arg = CalculatorComplex.from_pair(*initial[0]).arg()
     assert arg.isclose(initial[1])
 
+def test_complex_zero_value():
+    zero = CalculatorComplex.from_pair(0, 0)
+    with pytest.raises(ValueError):
+        zero.arg_checked()
+    with pytest.raises(ZeroDivisionError):
+        ~zero
+    small = CalculatorComplex.from_pair(1e-100, 0)
+    assert small.arg_checked().isclose(0)
+    assert (~small).isclose(CalculatorComplex.from_pair(1e100, 0))
+    assert CalculatorComplex.from_pair('a', 0).arg_checked() == CalculatorFloat('atan2(0e0, a)')
+
 @pytest.mark.parametrize("initial", [
     ((1, 0), 1),
     ((0, 2), 2),
@@ -179,5 +229,116 @@ def test_complex_cast_fail():
     with pytest.raises(ValueError):
         assert complex(cc)
 
+def test_complex_to_sympy():
+    sympy = pytest.importorskip("sympy")
+    cc = CalculatorComplex.from_pair("x ^ 2", "sign(x)")
+    sympy_expression = cc.to_sympy().subs(sympy.Symbol("x"), -0.5)
+    assert complex(sympy_expression) == complex(0.25, -1)
+
+def test_complex_is_complex():
+    assert isinstance(CalculatorComplex(1 + 1j), numbers.Complex)
+    assert isinstance(CalculatorComplex.from_pair("a", "b"), numbers.Complex)
+    assert not isinstance(CalculatorComplex(1 + 1j), numbers.Real)
+
+def test_complex_complex_interface():
+    """Use a CalculatorComplex the way libraries use a generic numbers.Complex."""
+    cc = CalculatorComplex(1 + 2j)
+    assert +cc == cc
+    assert cc.conjugate() == CalculatorComplex(1 - 2j)
+    assert complex(cc ** 2).real == pytest.approx(((1 + 2j) ** 2).real)
+    assert complex(cc ** 2).imag == pytest.approx(((1 + 2j) ** 2).imag)
+    assert complex(2 ** cc) == pytest.approx(2 ** (1 + 2j))
+    assert complex(cc ** CalculatorComplex(0.5j)) == pytest.approx((1 + 2j) ** 0.5j)
+    symbolic = CalculatorComplex.from_pair("a", "b")
+    assert +symbolic == symbolic
+    assert symbolic.conjugate() == CalculatorComplex.from_pair("a", "(-b)")
+
+def test_complex_pow_fail():
+    with pytest.raises(ValueError):
+        CalculatorComplex.from_pair("a", "b") ** 2
+    with pytest.raises(ValueError):
+        2 ** CalculatorComplex.from_pair("a", 0)
+    with pytest.raises(ZeroDivisionError):
+        CalculatorComplex(0) ** -1
+
+def test_complex_canonical_hash():
+    """Equivalent real and imaginary parts share the stable canonical hash."""
+    first = CalculatorComplex.from_pair("a + b", 1)
+    second = CalculatorComplex.from_pair("b+a", 1.0)
+    assert first.canonical_hash() == second.canonical_hash()
+    assert first.canonical_hash_128() == second.canonical_hash_128()
+    assert CalculatorComplex.from_pair(1, "x").canonical_hash() == 0xf1a2b67ec71ce738
+
+def test_complex_exp_i():
+    """The phase factor agrees with cmath and contains a symbolic phase in both parts."""
+    for theta in [0.0, 0.3, -1.2, math.pi]:
+        phase = CalculatorComplex.exp_i(theta)
+        assert phase.isclose(cmath.exp(1j * theta))
+        assert phase.is_on_unit_circle()
+    phase = CalculatorComplex.exp_i(CalculatorFloat("theta"))
+    assert phase.real == "cos(theta)"
+    assert phase.imag == "sin(theta)"
+    assert not phase.is_on_unit_circle()
+    with pytest.raises(TypeError):
+        CalculatorComplex.exp_i([1])
+
+def test_complex_polar_form():
+    """from_polar agrees with cmath.rect and to_polar inverts it, symbolic parts evaluate correctly."""
+    for r, theta in [(1.0, 0.0), (2.0, 0.7), (0.5, -2.5)]:
+        value = CalculatorComplex.from_polar(r, theta)
+        assert value.isclose(cmath.rect(r, theta))
+        norm, arg = value.to_polar()
+        assert math.isclose(float(norm), r)
+        assert math.isclose(float(arg), theta, abs_tol=1e-15)
+    value = CalculatorComplex.from_polar("r", CalculatorFloat("theta"))
+    calculator = Calculator()
+    calculator.set("r", 1.5)
+    calculator.set("theta", 0.4)
+    assert math.isclose(calculator.parse_get(value.real), cmath.rect(1.5, 0.4).real)
+    assert math.isclose(calculator.parse_get(value.imag), cmath.rect(1.5, 0.4).imag)
+    assert CalculatorComplex.I == CalculatorComplex.from_pair(0, 1)
+    assert CalculatorComplex.ZERO.is_zero
+    assert CalculatorComplex.ONE == 1
+    with pytest.raises(TypeError):
+        CalculatorComplex.from_polar([1], 0)
+
+def test_complex_hyperbolic_and_tan():
+    """sinh, cosh and tan agree with cmath."""
+    for value in [0j, 0.5 - 1.5j, -2 + 0.3j]:
+        x = CalculatorComplex(value)
+        assert x.sinh().isclose(cmath.sinh(value))
+        assert x.cosh().isclose(cmath.cosh(value))
+        assert x.tan().isclose(cmath.tan(value))
+
+def test_complex_elementary_functions():
+    """exp, sin, cos, sqrt and powf agree with cmath, also after evaluating symbolic results."""
+    calculator = Calculator()
+    for value in [0.5 - 1.5j, -2 + 0.3j, -4 + 0j]:
+        x = CalculatorComplex(value)
+        assert x.exp().isclose(cmath.exp(value))
+        assert x.sin().isclose(cmath.sin(value))
+        assert x.cos().isclose(cmath.cos(value))
+        assert x.sqrt().isclose(cmath.sqrt(value))
+        assert x.powf(2.5).isclose(value ** 2.5)
+        calculator.set("a", value.real)
+        calculator.set("b", value.imag)
+        symbolic = CalculatorComplex.from_pair("a", "b").exp()
+        evaluated = complex(calculator.parse_get(symbolic.real), calculator.parse_get(symbolic.imag))
+        assert cmath.isclose(evaluated, cmath.exp(value))
+    with pytest.raises(TypeError):
+        CalculatorComplex(1).powf([1])
+
+def test_promote_demote():
+    """Explicit conversions do not drop imaginary parts silently."""
+    assert CalculatorComplex.from_pair("x", 0).demote() == CalculatorFloat("x")
+    with pytest.raises(ValueError):
+        CalculatorComplex.from_pair(1, 1e-20).demote()
+    with pytest.raises(ValueError):
+        CalculatorComplex.from_pair(1, "y").demote()
+    value, dropped = CalculatorComplex.from_pair(1, 1e-20).demote_lossy()
+    assert value == CalculatorFloat(1) and dropped
+    assert CalculatorComplex(2).demote_lossy() == (CalculatorFloat(2), False)
+    assert CalculatorFloat("x").promote() == CalculatorComplex.from_pair("x", 0)
+
 if __name__ == '__main__':
     pytest.main(sys.argv)
\ No newline at end of file
@@ -14,8 +14,9 @@ import sys
 import numpy as np
 import numpy.testing as npt
 import os
-from qoqo_calculator_pyo3 import CalculatorFloat
+from qoqo_calculator_pyo3 import CalculatorFloat, CalculatorComplex, Calculator
 import math
+import numbers
 
 def test_number():
     c = CalculatorFloat(1)
@@ -53,6 +54,15 @@ def test_str_init():
     assert not cf.is_float
 
 
+def test_predicates():
+    """is_zero and is_one compare exactly, symbolic values are neither."""
+    assert CalculatorFloat(0).is_zero and CalculatorFloat(-0.0).is_zero
+    assert not CalculatorFloat(1e-300).is_zero
+    assert CalculatorFloat(1).is_one and not CalculatorFloat(1 - 1e-16).is_one
+    assert CalculatorFloat("x").is_symbolic and not CalculatorFloat(2).is_symbolic
+    assert not CalculatorFloat("x").is_zero and not CalculatorFloat("x").is_one
+
+
 def test_failed_init():
     with pytest.raises(TypeError):
         cf = CalculatorFloat(dict())
@@ -131,6 +141,23 @@ def test_div(init):
     cf = CalculatorFloat(init[1])
     assert (init[0] / cf) == CalculatorFloat(init[2])
 
+def test_augmented_assignment_aliasing():
+    """Augmented assignments bind a new object and leave other names unchanged."""
+    a = CalculatorFloat("x")
+    b = a
+    b += 1
+    assert b == "(x + 1e0)"
+    assert a == "x"
+    b = a
+    b -= 1
+    b *= 2
+    b /= 4
+    assert a == "x"
+    assert b is not a
+    assert +a == a
+    for method in ["__iadd__", "__isub__", "__imul__", "__itruediv__"]:
+        assert not hasattr(a, method)
+
 
 @pytest.mark.parametrize("init", [
     (1, 0, 0),
@@ -147,6 +174,18 @@ def test_div_fail(init):
         (init[0] / cf)
 
 
+def test_symbolic_length_fail():
+    long = CalculatorFloat("x" * (2**19 + 1))
+    with pytest.raises(ValueError):
+        long * long
+    with pytest.raises(ValueError):
+        long / long
+    with pytest.raises(ValueError):
+        long ** long
+    with pytest.raises(ValueError):
+        long.atan2(long)
+
+
 @pytest.mark.parametrize("initial", [
     (1, 1),
     (-1, 1),
@@ -181,6 +220,47 @@ def test_float_sin(initial):
     assert t.isclose(initial[1])
 
 
+@pytest.mark.parametrize("value", [-2.0, -1e-17, -0.0, 0.0, 1e-17, 1e-3, 3.5])
+def test_float_heaviside_delta(value):
+    calculator = Calculator()
+    cf = CalculatorFloat(value)
+    symbolic = CalculatorFloat("x")
+    calculator.set("x", value)
+    assert cf.heaviside().value == calculator.parse_get(symbolic.heaviside())
+    assert cf.step().value == calculator.parse_get(symbolic.step())
+    assert cf.delta().value == calculator.parse_get(symbolic.delta())
+    assert symbolic.heaviside() == CalculatorFloat("theta(x)")
+    assert symbolic.step() == CalculatorFloat("theta(x)")
+    assert symbolic.delta() == CalculatorFloat("delta(x)")
+
+
+def test_float_factorial():
+    assert CalculatorFloat(20).factorial() == CalculatorFloat(2432902008176640000.0)
+    assert math.isnan(CalculatorFloat(-1).factorial().value)
+    assert CalculatorFloat("n").factorial() == CalculatorFloat("factorial(n)")
+    calculator = Calculator()
+    calculator.set("n", 5)
+    assert calculator.parse_get(CalculatorFloat("n").factorial()) == 120.0
+    assert calculator.parse_str("binom(52, 5)") == 2598960.0
+
+
+def test_float_rounding():
+    assert CalculatorFloat(0.00005).round_to_digits(4) == CalculatorFloat(0.0001)
+    assert CalculatorFloat(-2.675).round_to_digits(2) == CalculatorFloat(-2.68)
+    assert CalculatorFloat(0.123456).round_to_multiple(1e-4) == CalculatorFloat(0.1235)
+    symbolic = CalculatorFloat("x")
+    assert symbolic.round_to_digits(2) == CalculatorFloat("round_digits(x, 2)")
+    calculator = Calculator()
+    calculator.set("x", -0.00005)
+    assert calculator.parse_get(symbolic.round_to_multiple(1e-4)) == -0.0001
+    assert calculator.parse_get(symbolic.round_to_digits(4)) == -0.0001
+    for step in [0, -1e-4]:
+        with pytest.raises(ValueError):
+            CalculatorFloat(1).round_to_multiple(step)
+        with pytest.raises(ValueError):
+            symbolic.round_to_multiple(step)
+
+
 @pytest.mark.parametrize("initial", [
     (1, np.arccos(1)),
     (-1, np.arccos(-1)),
@@ -192,6 +272,42 @@ def test_float_acos(initial):
     assert t.isclose(initial[1])
 
 
+@pytest.mark.parametrize("method, function", [
+    ("tan", "tan"),
+    ("asin", "asin"),
+    ("atan", "atan"),
+    ("sinh", "sinh"),
+    ("cosh", "cosh"),
+    ("tanh", "tanh"),
+    ("exp2", "exp2"),
+    ("ln", "log"),
+    ("log2", "log2"),
+    ("log10", "log10"),
+    ("floor", "floor"),
+    ("ceil", "ceil"),
+    ("round", "round"),
+])
+def test_float_unary_functions(method, function):
+    """Unary functions emit the function names of the Calculator and agree with it."""
+    calculator = Calculator()
+    calculator.set("x", 0.75)
+    symbolic = getattr(CalculatorFloat("x"), method)()
+    assert symbolic == CalculatorFloat(function + "(x)")
+    expected = calculator.parse_str(function + "(0.75)")
+    assert getattr(CalculatorFloat(0.75), method)() == CalculatorFloat(expected)
+    assert calculator.parse_get(symbolic) == expected
+
+
+def test_float_log():
+    """The logarithm to an arbitrary base is the quotient of natural logarithms."""
+    calculator = Calculator()
+    calculator.set("b", 3)
+    assert CalculatorFloat(8).log(2) == CalculatorFloat(math.log(8) / math.log(2))
+    assert calculator.parse_get(CalculatorFloat(9).log("b")) == math.log(9) / math.log(3)
+    with pytest.raises(TypeError):
+        CalculatorFloat(8).log([2])
+
+
 @pytest.mark.parametrize("initial", [
     (1, 1, True),
     ('a', 'a', True),
@@ -217,5 +333,298 @@ def test_float_cast_fail():
     with pytest.raises(ValueError):
         assert float(cc)
 
+@pytest.mark.parametrize("expression", [
+    "sin(x) + cos(y)",
+    "sign(y) * abs(y) ^ 2",
+    "atan2(x, y) - hypot(x, y)",
+    "theta(theta) + theta(y) * theta",
+    "log10(x) + exp2(y) - expm1(x)",
+    "max(x, y) / min(x, 3e-1) + delta(x - 3e-1)",
+])
+def test_float_to_sympy(expression):
+    sympy = pytest.importorskip("sympy")
+    values = {"x": 0.3, "y": -1.2, "theta": 0.4}
+    calculator = Calculator()
+    for name, value in values.items():
+        calculator.set(name, value)
+    cf = CalculatorFloat(expression)
+    sympy_expression = cf.to_sympy()
+    substituted = sympy_expression.subs(
+        {sympy.Symbol(name): value for name, value in values.items()})
+    assert math.isclose(float(substituted), calculator.parse_get(cf), rel_tol=1e-12)
+
+def test_float_to_sympy_number():
+    sympy = pytest.importorskip("sympy")
+    assert CalculatorFloat(1.5).to_sympy() == sympy.Float(1.5)
+
+def test_float_to_sympy_fail():
+    pytest.importorskip("sympy")
+    with pytest.raises(ValueError):
+        CalculatorFloat("round(x)").to_sympy()
+
+def test_float_is_real():
+    assert isinstance(CalculatorFloat(1.5), numbers.Real)
+    assert isinstance(CalculatorFloat("a"), numbers.Real)
+    assert isinstance(CalculatorFloat(1.5), numbers.Complex)
+    assert not isinstance(CalculatorFloat(1.5), numbers.Integral)
+
+
+@pytest.mark.parametrize("left, right", [
+    (7.5, 2), (-7.5, 2), (7.5, -2), (-7.5, -2), (3, 0.5),
+])
+def test_float_real_operators(left, right):
+    cf = CalculatorFloat(left)
+    assert (cf // right) == CalculatorFloat(left // right)
+    assert (left // CalculatorFloat(right)) == CalculatorFloat(left // right)
+    assert (cf % right) == CalculatorFloat(left % right)
+    assert (left % CalculatorFloat(right)) == CalculatorFloat(left % right)
+    assert divmod(cf, right) == (CalculatorFloat(left // right), CalculatorFloat(left % right))
+    assert divmod(left, CalculatorFloat(right)) == divmod(cf, right)
+    assert (cf < right) == (left < right)
+    assert (cf <= right) == (left <= right)
+    assert (cf > right) == (left > right)
+    assert (cf >= right) == (left >= right)
+
+
+@pytest.mark.parametrize("value", [2.5, -2.5, 3.5, 0.4, -0.6])
+def test_float_real_rounding(value):
+    cf = CalculatorFloat(value)
+    assert math.trunc(cf) == math.trunc(value)
+    assert math.floor(cf) == math.floor(value)
+    assert math.ceil(cf) == math.ceil(value)
+    assert round(cf) == round(value)
+    assert isinstance(round(cf), int)
+    assert round(cf, 1) == CalculatorFloat(round(value, 1))
+    assert int(cf) == int(value)
+    assert isinstance(int(cf), int)
+
+
+@pytest.mark.parametrize("operation", [int, math.trunc, math.floor, math.ceil, round, lambda x: round(x, 1)])
+def test_float_symbolic_rounding(operation):
+    """The integral conversions raise for symbolic values, the methods keep them symbolic."""
+    with pytest.raises(ValueError, match="Symbolic Value"):
+        operation(CalculatorFloat("x + 1"))
+    x = CalculatorFloat("x + 1")
+    assert (x.floor(), x.ceil(), x.round()) == ("floor(x + 1)", "ceil(x + 1)", "round(x + 1)")
+    assert int(CalculatorFloat("2.7")) == 2
+    with pytest.raises(OverflowError):
+        int(CalculatorFloat(math.inf))
+
+
+def test_float_real_interface():
+    """Use a CalculatorFloat the way libraries use a generic numbers.Real."""
+    cf = CalculatorFloat(1.5)
+    assert cf.real == cf
+    assert cf.imag == CalculatorFloat(0)
+    assert cf.conjugate() == cf
+    assert +cf == cf
+    assert complex(cf) == complex(1.5)
+    assert (2 ** cf) == CalculatorFloat(2 ** 1.5)
+    assert CalculatorFloat("a").real == CalculatorFloat("a")
+    assert +CalculatorFloat("a") == CalculatorFloat("a")
+
+
+@pytest.mark.parametrize("left, right", [
+    (7.5, 2), (-7.5, 2), (7.5, -2), (-7.5, -2), (3, 0.5),
+])
+def test_float_rem(left, right):
+    """rem is the truncated remainder of the Calculator, also for symbolic values."""
+    assert CalculatorFloat(left).rem(right) == CalculatorFloat(math.fmod(left, right))
+    calculator = Calculator()
+    calculator.set("a", left)
+    calculator.set("b", right)
+    symbolic = CalculatorFloat("a").rem(CalculatorFloat("b"))
+    assert symbolic == "(a % b)"
+    assert calculator.parse_get(symbolic) == math.fmod(left, right)
+    assert calculator.parse_str("a % b") == math.fmod(left, right)
+
+
+def test_float_real_zero_division():
+    with pytest.raises(ZeroDivisionError):
+        CalculatorFloat(1) // 0
+    with pytest.raises(ZeroDivisionError):
+        CalculatorFloat(1) % 0
+    with pytest.raises(ZeroDivisionError):
+        CalculatorFloat("a").rem(0)
+    with pytest.raises(ZeroDivisionError):
+        divmod(1, CalculatorFloat(0))
+
+
+@pytest.mark.parametrize("operation", [
+    lambda x: x // 2,
+    lambda x: 2 // x,
+    lambda x: x % 2,
+    lambda x: 2 % x,
+    lambda x: divmod(x, 2),
+    lambda x: divmod(2, x),
+    lambda x: x < 2,
+    lambda x: x >= 2,
+    lambda x: CalculatorFloat(1) < x,
+    math.trunc,
+    math.floor,
+    math.ceil,
+    round,
+    lambda x: round(x, 2),
+])
+def test_float_real_symbolic_fail(operation):
+    with pytest.raises(ValueError):
+        operation(CalculatorFloat("a"))
+
+def test_float_canonical_hash():
+    """Equivalent expressions share the stable canonical hash."""
+    assert CalculatorFloat("(a + b)").canonical_hash() == CalculatorFloat("b+a").canonical_hash()
+    assert CalculatorFloat("a - b").canonical_hash() != CalculatorFloat("b - a").canonical_hash()
+    assert CalculatorFloat(2).canonical_hash() == CalculatorFloat("2").canonical_hash()
+    assert CalculatorFloat(-0.0).canonical_hash() == CalculatorFloat(0.0).canonical_hash()
+    assert CalculatorFloat("a").canonical_hash() == 0x07b2d57592fc095f
+    assert CalculatorFloat("a").canonical_hash_128() == 0x00b5d756c99e7572077f72577fe731d7
+
+def test_float_to_int():
+    """Conversion to integers with the rounding policies."""
+    assert CalculatorFloat(2.0).to_int() == 2
+    assert CalculatorFloat(2.5).to_int("round") == 3
+    assert CalculatorFloat(2.5).to_int("floor") == 2
+    assert CalculatorFloat(2.5).to_int(policy="ceil") == 3
+    for value, policy in [(2.5, "exact"), (1e20, "round"), (float("nan"), "round"), ("x", "round"), (2.0, "truncate")]:
+        with pytest.raises(ValueError):
+            CalculatorFloat(value).to_int(policy)
+
+def test_float_bind_placeholders():
+    """Placeholders are bound before evaluation and never evaluated."""
+    template = CalculatorFloat("{angle} * sin({phase})")
+    assert template.placeholders() == ["angle", "phase"]
+    bound = template.bind_placeholders({"angle": "x + 1", "phase": 0.5})
+    assert str(bound) == "(x + 1) * sin((5e-1))"
+    assert CalculatorFloat("{n}").bind_placeholders({"n": CalculatorFloat(2)}) == 2
+    with pytest.raises(ValueError):
+        template.bind_placeholders({"angle": 1})
+    with pytest.raises(ValueError):
+        Calculator().parse_str("{n} + 1")
+
+def test_float_substitute():
+    """Only whole variable names are replaced, the result is evaluated when no variables are left."""
+    x = CalculatorFloat("2*theta + phi")
+    assert str(x.substitute("theta", 0.5)) == "2*(5e-1) + phi"
+    assert str(x.substitute("theta", "alpha/2")) == "2*(alpha/2) + phi"
+    assert str(x.substitute("gamma", 1)) == "2*theta + phi"
+    assert str(CalculatorFloat("sin(a) * a_1").substitute("a", "b")) == "sin(b) * a_1"
+    chained = x.substitute("theta", "alpha/2").substitute("alpha", 3).substitute("phi", -1)
+    assert chained.is_float and chained == 2
+    with pytest.raises(ZeroDivisionError):
+        CalculatorFloat("1 / x").substitute("x", 0)
+    with pytest.raises(TypeError):
+        x.substitute("theta", dict())
+
+
+def test_float_embedded_literals():
+    """Number literals are listed with their byte positions, a leading minus is not included."""
+    expression = CalculatorFloat("-2.5e-3 * atan2(0.4999999, x)")
+    assert expression.embedded_literals() == [(2.5e-3, 1, 7), (0.4999999, 16, 25)]
+    for value, start, end in expression.embedded_literals():
+        assert float(str(expression)[start:end]) == value
+    assert CalculatorFloat(0.5).embedded_literals() == []
+
+def test_float_variables():
+    """Free variables are listed once in order of occurrence, without function names."""
+    assert CalculatorFloat("b * sin(theta) + b / theta").variables == ["b", "theta"]
+    assert CalculatorFloat("2 * 3 + sqrt(4)").variables == []
+    assert CalculatorFloat(1.5).variables == []
+
+def test_float_simplify():
+    """Constant subexpressions are evaluated, symbols are kept."""
+    simplified = CalculatorFloat("((2e0 * 3e0) * x)").simplify()
+    assert simplified == "(6e0 * x)"
+    assert simplified.simplify() == simplified
+    assert CalculatorFloat("1 * y + 0").simplify() == "y"
+    assert CalculatorFloat("2 ^ 3 - 1").simplify() == 7
+    assert CalculatorFloat("2 ^ 3 - 1").simplify().is_float
+    assert CalculatorFloat("x / 0").simplify() == "x / 0"
+
+# Math module compatibility: math functions convert their arguments with __float__ (or use
+# __trunc__, __floor__ and __ceil__), so every function works for float CalculatorFloats.
+# Symbolic values raise a ValueError, for __float__ the message contains the expression.
+MATH_FUNCTIONS = [
+    ("sin", lambda x: math.sin(x)),
+    ("cos", lambda x: math.cos(x)),
+    ("tan", lambda x: math.tan(x)),
+    ("asin", lambda x: math.asin(x)),
+    ("acos", lambda x: math.acos(x)),
+    ("atan", lambda x: math.atan(x)),
+    ("atan2", lambda x: math.atan2(x, 2)),
+    ("sinh", lambda x: math.sinh(x)),
+    ("cosh", lambda x: math.cosh(x)),
+    ("tanh", lambda x: math.tanh(x)),
+    ("exp", lambda x: math.exp(x)),
+    ("expm1", lambda x: math.expm1(x)),
+    ("log", lambda x: math.log(x)),
+    ("log1p", lambda x: math.log1p(x)),
+    ("log2", lambda x: math.log2(x)),
+    ("log10", lambda x: math.log10(x)),
+    ("sqrt", lambda x: math.sqrt(x)),
+    ("pow", lambda x: math.pow(x, 3)),
+    ("fabs", lambda x: math.fabs(x)),
+    ("fmod", lambda x: math.fmod(x, 0.2)),
+    ("copysign", lambda x: math.copysign(1, x)),
+    ("hypot", lambda x: math.hypot(x, 1)),
+    ("degrees", lambda x: math.degrees(x)),
+    ("radians", lambda x: math.radians(x)),
+    ("erf", lambda x: math.erf(x)),
+    ("gamma", lambda x: math.gamma(x)),
+    ("modf", lambda x: math.modf(x)),
+    ("frexp", lambda x: math.frexp(x)),
+    ("isfinite", lambda x: math.isfinite(x)),
+    ("isnan", lambda x: math.isnan(x)),
+    ("isclose", lambda x: math.isclose(x, 0.5)),
+    ("fsum", lambda x: math.fsum([1, x, 2])),
+    ("dist", lambda x: math.dist([x], [0])),
+    ("trunc", lambda x: math.trunc(x)),
+    ("floor", lambda x: math.floor(x)),
+    ("ceil", lambda x: math.ceil(x)),
+]
+
+@pytest.mark.parametrize("name, function", MATH_FUNCTIONS)
+def test_math_compatibility(name, function):
+    """Math module functions accept float CalculatorFloats and reject symbolic ones."""
+    assert function(CalculatorFloat(0.5)) == function(0.5), name
+    try:
+        function(CalculatorFloat("x + 1"))
+        assert False, name
+    except ValueError as error:
+        assert str(error) == 'Symbolic Value "x + 1" can not be cast to float.'
+
+def test_isclose_math():
+    """isclose_math uses the tolerances of math.isclose instead of the ones of isclose."""
+    for a, b in [(1.0, 1.0 + 1e-10), (1e-9, 0), (0.0, 0), (1e10, 1e10 + 1), (math.inf, math.inf)]:
+        assert CalculatorFloat.isclose_math(a, b) == math.isclose(a, b)
+        assert CalculatorFloat.isclose_math(CalculatorFloat(a), CalculatorFloat(b)) == math.isclose(a, b)
+    # No absolute tolerance by default, isclose uses the machine epsilon
+    assert CalculatorFloat(1e-17).isclose(0)
+    assert not CalculatorFloat.isclose_math(1e-17, 0)
+    # Relative to the larger value, isclose uses a relative tolerance of 1e-8 to the argument
+    assert not CalculatorFloat(1.0).isclose(1.0 + 1e-7)
+    assert CalculatorFloat.isclose_math(1.0, 1.0 + 1e-7, rel_tol=1e-7)
+    assert CalculatorFloat.isclose_math(1e-9, 0, abs_tol=1e-8)
+    assert CalculatorFloat.isclose_math(1.0, 1.1, rel_tol=0.2)
+    with pytest.raises(ValueError):
+        CalculatorFloat.isclose_math(CalculatorFloat("x"), 1)
+    with pytest.raises(TypeError):
+        CalculatorFloat.isclose_math(1, [1])
+
+def test_long_expression_error_messages():
+    """Error messages contain long expressions truncated."""
+    expression = "x + " * (1 << 18) + "x"
+    for function in [
+        lambda: float(CalculatorFloat(expression)),
+        lambda: CalculatorComplex.from_pair(1, expression).demote(),
+        lambda: Calculator().parse_str(expression),
+    ]:
+        try:
+            function()
+            assert False
+        except ValueError as error:
+            message = str(error)
+            assert len(message) < 1000
+            assert "... (104" in message and " bytes)" in message
+
 if __name__ == '__main__':
     pytest.main(sys.argv)
@@ -0,0 +1,125 @@
+# Copyright © 2019-2021 HQS Quantum Simulations GmbH. All Rights Reserved.
+#
+# Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except
+# in compliance with the License. You may obtain a copy of the License at
+#
+#     http://www.apache.org/licenses/LICENSE-2.0
+#
+# Unless required by applicable law or agreed to in writing, software distributed under the License
+# is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express
+# or implied. See the License for the specific language governing permissions and limitations under
+# the License.
+"""Equality and ordering of CalculatorFloat and CalculatorComplex with other types.
+
+Equality is supported for all objects that can be converted to CalculatorComplex, both sides are
+compared as CalculatorComplex. Other types compare unequal by identity, as the comparison returns
+NotImplemented. Ordering is only supported between float values of CalculatorFloat and real
+numbers. Both wrappers can be changed by the in-place operators and are not hashable.
+"""
+import pytest
+import sys
+import decimal
+import numpy as np
+from qoqo_calculator_pyo3 import CalculatorFloat, CalculatorComplex
+
+HALF = CalculatorFloat(0.5)
+ONE = CalculatorFloat(1)
+X = CalculatorFloat("x")
+HALF_COMPLEX = CalculatorComplex(0.5)
+HALF_I = CalculatorComplex(0.5 + 1j)
+X_COMPLEX = CalculatorComplex("x")
+
+# (left, right, equal) for every supported and unsupported pair of types
+EQUALITY_MATRIX = [
+    # int and float
+    (HALF, 0.5, True),
+    (HALF, 1, False),
+    (ONE, 1, True),
+    (ONE, 1.0, True),
+    (HALF_COMPLEX, 0.5, True),
+    (HALF_I, 0.5, False),
+    # str is converted to CalculatorFloat
+    (X, "x", True),
+    (X, "y", False),
+    (HALF, "x", False),
+    (HALF, "0.5", True),
+    (X_COMPLEX, "x", True),
+    (HALF_I, "x", False),
+    # complex equals when the imaginary part is zero
+    (HALF, 0.5 + 0j, True),
+    (HALF, 0.5 + 1j, False),
+    (HALF_COMPLEX, 0.5 + 0j, True),
+    (HALF_I, 0.5 + 1j, True),
+    # CalculatorFloat and CalculatorComplex
+    (HALF, CalculatorFloat(0.5), True),
+    (X, CalculatorFloat("x"), True),
+    (X, HALF, False),
+    (HALF, HALF_COMPLEX, True),
+    (HALF, HALF_I, False),
+    (X, X_COMPLEX, True),
+    (HALF_COMPLEX, CalculatorComplex(0.5), True),
+    (HALF_COMPLEX, HALF_I, False),
+    # Other numeric types
+    (HALF, decimal.Decimal("0.5"), True),
+    (HALF_COMPLEX, decimal.Decimal("0.5"), True),
+    (HALF, decimal.Decimal("0.25"), False),
+    # numpy scalars
+    (HALF, np.float64(0.5), True),
+    (ONE, np.int64(1), True),
+    (HALF, np.int64(1), False),
+    (HALF, np.complex128(0.5 + 1j), False),
+    (HALF_COMPLEX, np.complex128(0.5), True),
+    (HALF_I, np.complex128(0.5 + 1j), True),
+    # Unsupported types are never equal
+    (HALF, None, False),
+    (HALF_COMPLEX, None, False),
+    (HALF, [0.5], False),
+    (HALF_COMPLEX, [0.5], False),
+    (X, {"x": 1}, False),
+]
+
+
+@pytest.mark.parametrize("left, right, equal", EQUALITY_MATRIX)
+def test_equality_matrix(left, right, equal):
+    """Equality is symmetric and != is its negation."""
+    assert bool(left == right) == equal
+    assert bool(right == left) == equal
+    assert bool(left != right) != equal
+    assert bool(right != left) != equal
+
+
+def test_ordering():
+    """Float values are ordered with real numbers, all other orderings raise."""
+    assert HALF < 1
+    assert HALF <= np.float64(0.5)
+    assert not HALF > decimal.Decimal("0.5")
+    assert 1 > HALF
+    assert HALF >= CalculatorFloat(0.25)
+    for left, right in [
+        (HALF, None),
+        (None, HALF),
+        (HALF, 0.5 + 0j),
+        (HALF, [1]),
+        (HALF_COMPLEX, HALF_COMPLEX),
+        (HALF_COMPLEX, 0.5),
+        (0.5, HALF_COMPLEX),
+    ]:
+        with pytest.raises(TypeError):
+            left < right
+    nan = CalculatorFloat(float("nan"))
+    assert not (nan < HALF or nan <= HALF or nan > HALF or nan >= HALF)
+    assert sorted([CalculatorFloat(2), HALF, CalculatorFloat(-1)]) == [-1, 0.5, 2]
+    for left, right in [(X, 1), (1, X), (HALF, "x"), (X, X)]:
+        with pytest.raises(ValueError):
+            left < right
+
+
+def test_not_hashable():
+    """The in-place operators change the wrappers, so they are not hashable."""
+    for value in [HALF, X, HALF_COMPLEX]:
+        with pytest.raises(TypeError):
+            hash(value)
+
+
+if __name__ == '__main__':
+    pytest.main(sys.argv)
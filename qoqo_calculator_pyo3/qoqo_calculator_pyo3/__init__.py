@@ -10,4 +10,24 @@
 # or implied. See the License for the specific language governing permissions and limitations under
 # the License.
 from .qoqo_calculator_pyo3 import *
+
+__all__ = [
+    "Calculator",
+    "CalculatorFloat",
+    "CalculatorComplex",
+    "CalculatorAssertionError",
+    "CalculatorFrozenError",
+    "parse_string_assign",
+    "parse",
+    "validate",
+    "variables_in",
+    "simplify",
+    "pi_fraction",
+    "dot",
+    "norm_sqr",
+    "functions",
+    "error_kinds",
+    "constants",
+    "operators",
+]
 __license__ = "Apache-2.0 for linked dependencies see qoqo_calculator_pyo3/LICSENSE_FOR_BINARY_DISTRIBUTION"
\ No newline at end of file
@@ -15,11 +15,85 @@
 //! Converts the qoqo_calculator Calculator struct for parsing string expressions to floats
 //! into a Python class.
 
-use crate::convert_into_calculator_float;
-use pyo3::exceptions::{PyTypeError, PyValueError};
+#[cfg(all(feature = "numpy", not(Py_LIMITED_API)))]
+use crate::ndarray::{new_ndarray, ContiguousValues};
+use crate::{
+    convert_into_calculator_complex, convert_into_calculator_float, error_message,
+    CalculatorFloatWrapper,
+};
+use num_complex::Complex;
+use pyo3::create_exception;
+use pyo3::exceptions::{PyKeyError, PyTypeError, PyValueError};
 use pyo3::prelude::*;
-use qoqo_calculator::Calculator;
+use pyo3::types::PyDict;
+use qoqo_calculator::{Calculator, CalculatorError, CalculatorFloat, OutOfRange, Truncated};
+use std::collections::{HashMap, HashSet};
 
+create_exception!(
+    qoqo_calculator_pyo3,
+    CalculatorAssertionError,
+    PyValueError,
+    "Raised when the condition of an `assert(condition, 'message')` in an expression is zero."
+);
+
+create_exception!(
+    qoqo_calculator_pyo3,
+    CalculatorFrozenError,
+    PyValueError,
+    "Raised when a frozen Calculator would be changed, see `Calculator.freeze`."
+);
+
+/// Convert the error of evaluating an expression into a Python exception.
+///
+/// Failed asserts raise CalculatorAssertionError, a subclass of ValueError, with the message
+/// of the assert. Changing a frozen Calculator raises CalculatorFrozenError, also a subclass of
+/// ValueError. All other errors raise ValueError.
+///
+/// # Arguments
+///
+/// * `error` - Error returned by the Calculator
+/// * `context` - Appended to the message, e.g. the expression
+///
+pub(crate) fn evaluation_error(error: CalculatorError, context: &str) -> PyErr {
+    let assertion = match &error {
+        CalculatorError::SweepPointError { error, .. } => error.as_ref(),
+        error => error,
+    };
+    match assertion {
+        CalculatorError::AssertionFailed { message, position } => {
+            CalculatorAssertionError::new_err(format!("{message} (position {position}){context}"))
+        }
+        CalculatorError::CalculatorFrozen { .. } => {
+            CalculatorFrozenError::new_err(format!("{}{context}", error_message(&error)))
+        }
+        _ => PyValueError::new_err(format!("{}{context}", error_message(&error))),
+    }
+}
+
+/// Names of swept variables and the values of each variable.
+type SweepAxes = Vec<(String, Vec<f64>)>;
+
+/// Extract floats from any iterable, e.g. a list or a numpy array.
+fn extract_values(values: &Bound<PyAny>) -> PyResult<Vec<f64>> {
+    values
+        .iter()
+        .map_err(|_| PyTypeError::new_err("Values must be an iterable of floats"))?
+        .map(|value| value?.extract::<f64>())
+        .collect()
+}
+
+/// Python wrapper of the Calculator.
+///
+/// The evaluating methods that do not assign variables (parse_str, parse_str_with_warnings,
+/// parse_get and sweep) release the GIL while the expression is evaluated, so other Python
+/// threads keep running during long evaluations. The arguments are converted to Rust types
+/// before and the results to Python objects after the GIL is released.
+/// The assigning methods keep the GIL, releasing it while the Calculator is mutably borrowed
+/// would make every concurrent call on the same Calculator from another thread fail.
+///
+/// Unlike CalculatorFloat and CalculatorComplex the Calculator is mutable: setting variables,
+/// registering functions or freezing changes the object, and every name referring to it sees
+/// the change. copy.copy and copy.deepcopy return an independent Calculator.
 #[pyclass(name = "Calculator", module = "qoqo_calculator_pyo3")]
 pub struct CalculatorWrapper {
     pub r_calculator: Calculator,
@@ -38,15 +112,311 @@ impl CalculatorWrapper {
         CalculatorWrapper { r_calculator }
     }
 
+    /// Create Python copy of CalculatorWrapper.
+    ///
+    /// # Returns
+    ///
+    /// `CalculatorWrapper` - independent clone of the Calculator, including its frozen state
+    ///
+    fn __copy__(&self) -> CalculatorWrapper {
+        CalculatorWrapper {
+            r_calculator: self.r_calculator.clone(),
+        }
+    }
+
+    /// Create Python deep copy of CalculatorWrapper.
+    ///
+    /// # Returns
+    ///
+    /// `CalculatorWrapper` - independent clone of the Calculator, including its frozen state
+    ///
+    fn __deepcopy__(&self, _memodict: Py<PyAny>) -> CalculatorWrapper {
+        self.__copy__()
+    }
+
+    /// Get the variables of the Calculator for pickling.
+    ///
+    /// Interpolation tables, libraries, handlers and settings are not pickled.
+    ///
+    /// # Returns
+    ///
+    /// `(HashMap<String, f64>, HashMap<String, String>)` - variables and variables defined by expressions
+    ///
+    fn __getstate__(&self) -> (HashMap<String, f64>, HashMap<String, String>) {
+        (
+            self.r_calculator.variables.clone(),
+            self.r_calculator.expression_variables().clone(),
+        )
+    }
+
+    /// Set the variables of the Calculator when unpickling.
+    ///
+    /// # Arguments
+    ///
+    /// * `state` - variables and variables defined by expressions returned by __getstate__
+    ///
+    fn __setstate__(
+        &mut self,
+        state: (HashMap<String, f64>, HashMap<String, String>),
+    ) -> PyResult<()> {
+        let (variables, expression_variables) = state;
+        let mut r_calculator = Calculator::new();
+        r_calculator.variables = variables;
+        for (name, expression) in expression_variables {
+            r_calculator
+                .set_expression_variable(&name, &expression)
+                .map_err(|x| {
+                    evaluation_error(x, &format!("; expression: {}", Truncated(&expression)))
+                })?;
+        }
+        self.r_calculator = r_calculator;
+        Ok(())
+    }
+
+    /// Serialize the variables of the Calculator to JSON.
+    ///
+    /// The variables and the variables defined by expressions are written sorted by name.
+    /// Interpolation tables, libraries, handlers and settings are not serialized.
+    ///
+    /// # Returns
+    ///
+    /// `PyResult<String>` - JSON of the variables, ValueError when a variable is not finite
+    ///
+    fn to_json(&self) -> PyResult<String> {
+        self.r_calculator
+            .to_json()
+            .map_err(|x| PyValueError::new_err(error_message(&x)))
+    }
+
+    /// Create a Calculator with the variables serialized by to_json.
+    ///
+    /// # Arguments
+    ///
+    /// * `json` - JSON of the variables
+    ///
+    /// # Returns
+    ///
+    /// `PyResult<CalculatorWrapper>` - new Calculator, ValueError when the JSON can not be read
+    ///
+    #[staticmethod]
+    fn from_json(json: &str) -> PyResult<CalculatorWrapper> {
+        let r_calculator =
+            Calculator::from_json(json).map_err(|x| PyValueError::new_err(error_message(&x)))?;
+        Ok(CalculatorWrapper { r_calculator })
+    }
+
     /// Set variable for Calculator.
     ///
     /// # Arguments
     ///
     /// * `variable_string` - string of the variable name
-    /// * `val` - Float value of the variable
+    /// * `val` - Value of the variable, a float or a numeric CalculatorFloat
+    ///
+    /// # Raises
+    ///
+    /// `TypeError` - The value can not be converted to CalculatorFloat
+    /// `ValueError` - The value is symbolic
+    /// `CalculatorFrozenError` - The Calculator is frozen
+    ///
+    fn set(&mut self, variable_string: &str, val: &Bound<PyAny>) -> PyResult<()> {
+        let converted = convert_into_calculator_float(val)
+            .map_err(|_| PyTypeError::new_err("Value can not be converted to Calculator Float"))?;
+        let value = match converted {
+            CalculatorFloat::Float(x) => x,
+            CalculatorFloat::Str(x) => {
+                return Err(PyValueError::new_err(format!(
+                    "Symbolic value {} can not be set as variable {variable_string}",
+                    Truncated(&x)
+                )))
+            }
+        };
+        self.r_calculator
+            .set_variable(variable_string, value)
+            .map_err(|x| evaluation_error(x, ""))
+    }
+
+    /// Set variable for Calculator, same as `set`.
+    ///
+    /// # Arguments
     ///
-    fn set(&mut self, variable_string: &str, val: f64) {
-        self.r_calculator.set_variable(variable_string, val);
+    /// * `name` - string of the variable name
+    /// * `value` - Value of the variable, a float or a numeric CalculatorFloat
+    ///
+    /// # Raises
+    ///
+    /// `TypeError` - The value can not be converted to CalculatorFloat
+    /// `ValueError` - The value is symbolic
+    /// `CalculatorFrozenError` - The Calculator is frozen
+    ///
+    fn set_variable(&mut self, name: &str, value: &Bound<PyAny>) -> PyResult<()> {
+        self.set(name, value)
+    }
+
+    /// Get the value of a variable.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - string of the variable name
+    ///
+    /// # Returns
+    ///
+    /// `float` - Value of the variable
+    ///
+    /// # Raises
+    ///
+    /// `KeyError` - The variable is not set
+    ///
+    fn get_variable(&self, name: &str) -> PyResult<f64> {
+        self.r_calculator
+            .get_variable(name)
+            .map_err(|_| PyKeyError::new_err(name.to_string()))
+    }
+
+    /// Set several variables for Calculator.
+    ///
+    /// # Arguments
+    ///
+    /// * `variables` - dict of variable names and float values
+    ///
+    /// # Raises
+    ///
+    /// `CalculatorFrozenError` - The Calculator is frozen
+    ///
+    fn set_variables(&mut self, variables: HashMap<String, f64>) -> PyResult<()> {
+        self.r_calculator
+            .set_variables(variables)
+            .map_err(|x| evaluation_error(x, ""))
+    }
+
+    /// Remove a variable from the Calculator.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - string of the variable name
+    ///
+    /// # Returns
+    ///
+    /// `Optional[float]` - The value of the removed variable, None when it was not set
+    ///
+    /// # Raises
+    ///
+    /// `CalculatorFrozenError` - The Calculator is frozen
+    ///
+    fn remove_variable(&mut self, name: &str) -> PyResult<Option<f64>> {
+        self.r_calculator
+            .remove_variable(name)
+            .map_err(|x| evaluation_error(x, ""))
+    }
+
+    /// Freeze the Calculator, all further changes raise CalculatorFrozenError.
+    ///
+    /// Setting, removing or assigning variables, defining variables by expressions and
+    /// registering interpolation tables raise CalculatorFrozenError until `unfreeze` is called.
+    /// Evaluating expressions without assignments, e.g. with parse_str, is not affected.
+    fn freeze(&mut self) {
+        self.r_calculator.freeze();
+    }
+
+    /// Allow changes of a frozen Calculator again.
+    fn unfreeze(&mut self) {
+        self.r_calculator.unfreeze();
+    }
+
+    /// Return True when the Calculator is frozen.
+    fn is_frozen(&self) -> bool {
+        self.r_calculator.is_frozen()
+    }
+
+    /// Get the values of several variables.
+    ///
+    /// # Arguments
+    ///
+    /// * `names` - list of variable names
+    ///
+    /// # Returns
+    ///
+    /// `list[float]` - Values of the variables in the order of the names
+    ///
+    /// # Raises
+    ///
+    /// `ValueError` - Listing all names of variables that are not set
+    ///
+    fn get_variables(&self, names: Vec<String>) -> PyResult<Vec<f64>> {
+        let names: Vec<&str> = names.iter().map(|name| name.as_str()).collect();
+        self.r_calculator
+            .get_variables(&names)
+            .map_err(|x| PyValueError::new_err(error_message(&x)))
+    }
+
+    /// Define a variable by an expression that is evaluated whenever the variable is used.
+    ///
+    /// A numeric variable set with `set` takes precedence over the expression.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - string of the variable name
+    /// * `expression` - Expression defining the variable, must not contain assignments
+    ///
+    fn set_expression_variable(&mut self, name: &str, expression: &str) -> PyResult<()> {
+        self.r_calculator
+            .set_expression_variable(name, expression)
+            .map_err(|x| evaluation_error(x, &format!("; expression: {}", Truncated(expression))))
+    }
+
+    /// Get the expression defining a variable, None when the variable is not defined by an expression.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - string of the variable name
+    ///
+    fn get_expression_variable(&self, name: &str) -> Option<String> {
+        self.r_calculator
+            .get_expression_variable(name)
+            .map(|x| x.to_string())
+    }
+
+    /// Return all variables defined by expressions as a dict of name and expression.
+    fn expression_variables(&self) -> HashMap<String, String> {
+        self.r_calculator.expression_variables().clone()
+    }
+
+    /// Register a table of sampled values that can be called like a function, e.g. `flux(x)`.
+    ///
+    /// The table is evaluated by piecewise linear interpolation.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - Name of the table used in expressions, must not be a built-in function
+    /// * `xs` - Strictly increasing x values as a list or numpy array
+    /// * `ys` - y values at the x values as a list or numpy array
+    /// * `out_of_range` - "clamp" to return the closest end of the table or "error" to raise a ValueError
+    ///   for arguments outside of the x values
+    ///
+    #[pyo3(signature = (name, xs, ys, out_of_range="clamp"))]
+    fn register_interpolation_table(
+        &mut self,
+        name: &str,
+        xs: &Bound<PyAny>,
+        ys: &Bound<PyAny>,
+        out_of_range: &str,
+    ) -> PyResult<()> {
+        let out_of_range = match out_of_range {
+            "clamp" => OutOfRange::Clamp,
+            "error" => OutOfRange::Error,
+            _ => {
+                return Err(PyValueError::new_err(format!(
+                    "out_of_range must be 'clamp' or 'error', not {out_of_range:?}"
+                )))
+            }
+        };
+        self.r_calculator
+            .register_interpolation_table_with_out_of_range(
+                name,
+                extract_values(xs)?,
+                extract_values(ys)?,
+                out_of_range,
+            )
+            .map_err(|x| evaluation_error(x, &format!("; table: {name}")))
     }
 
     ///  Parse a string expression.
@@ -58,7 +428,10 @@ impl CalculatorWrapper {
     pub fn parse_str_assign(&mut self, input: &str) -> PyResult<f64> {
         match self.r_calculator.parse_str_assign(input) {
             Ok(x) => Ok(x),
-            Err(x) => Err(PyValueError::new_err(format!("{x:?}; expression: {input}"))),
+            Err(x) => Err(evaluation_error(
+                x,
+                &format!("; expression: {}", Truncated(input)),
+            )),
         }
     }
 
@@ -68,13 +441,326 @@ impl CalculatorWrapper {
     ///
     /// * `input` - Expression that is parsed
     ///
-    pub fn parse_str(&self, input: &str) -> PyResult<f64> {
-        match self.r_calculator.parse_str(input) {
+    pub fn parse_str(&self, py: Python, input: &str) -> PyResult<f64> {
+        match py.allow_threads(|| self.r_calculator.parse_str(input)) {
+            Ok(x) => Ok(x),
+            Err(x) => Err(evaluation_error(
+                x,
+                &format!("; expression: {}", Truncated(input)),
+            )),
+        }
+    }
+
+    /// Parse a string expression and return its value and its derivative with respect to a variable.
+    ///
+    /// The derivative is computed exactly with dual numbers, not by finite differences.
+    ///
+    /// # Arguments
+    ///
+    /// * `input` - Expression that is parsed
+    /// * `wrt` - Name of the variable the derivative is taken with respect to
+    ///
+    /// # Returns
+    ///
+    /// `PyResult<(f64, f64)>` - the value and the derivative
+    ///
+    pub fn parse_str_derivative(&self, py: Python, input: &str, wrt: &str) -> PyResult<(f64, f64)> {
+        match py.allow_threads(|| self.r_calculator.parse_str_derivative(input, wrt)) {
             Ok(x) => Ok(x),
-            Err(x) => Err(PyValueError::new_err(format!("{x:?}; expression: {input}"))),
+            Err(x) => Err(evaluation_error(
+                x,
+                &format!("; expression: {}", Truncated(input)),
+            )),
         }
     }
 
+    /// Parse a string expression with imaginary numbers like `1 + 2i` to a complex number.
+    ///
+    /// # Arguments
+    ///
+    /// * `input` - Expression that is parsed, numbers followed by `i` or `j` are imaginary
+    ///
+    /// # Returns
+    ///
+    /// `PyResult<Complex<f64>>` - the value of the expression
+    ///
+    pub fn parse_str_complex(&self, py: Python, input: &str) -> PyResult<Complex<f64>> {
+        match py.allow_threads(|| self.r_calculator.parse_str_complex(input)) {
+            Ok(x) => Ok(x),
+            Err(x) => Err(evaluation_error(
+                x,
+                &format!("; expression: {}", Truncated(input)),
+            )),
+        }
+    }
+
+    /// Report number literals that snap to a different value when parsed as warnings.
+    ///
+    /// # Arguments
+    ///
+    /// * `enabled` - Whether parse_str_with_warnings and parse_str_assign_with_warnings report
+    ///   literals like 0.1000000000000000001, disabled by default
+    ///
+    fn warn_on_precision_loss(&mut self, enabled: bool) {
+        self.r_calculator.warn_on_precision_loss(enabled);
+    }
+
+    ///  Parse a string expression and report suspicious patterns.
+    ///
+    /// # Arguments
+    ///
+    /// * `input` - Expression that is parsed
+    ///
+    /// # Returns
+    ///
+    /// `PyResult<(f64, Vec<String>)>` - the value and the messages of non-fatal warnings
+    ///
+    pub fn parse_str_with_warnings(&self, py: Python, input: &str) -> PyResult<(f64, Vec<String>)> {
+        match py.allow_threads(|| self.r_calculator.parse_str_with_warnings(input)) {
+            Ok((x, warnings)) => Ok((x, warnings.iter().map(|w| w.to_string()).collect())),
+            Err(x) => Err(evaluation_error(
+                x,
+                &format!("; expression: {}", Truncated(input)),
+            )),
+        }
+    }
+
+    /// Check an expression against declared variables without computing it.
+    ///
+    /// Function names, numbers of arguments and brackets are checked and every read variable
+    /// has to be declared or assigned before in the expression. Domain errors and divisions
+    /// by zero are not reported and the Calculator is not changed.
+    ///
+    /// # Arguments
+    ///
+    /// * `input` - Expression that is checked, may contain assignments
+    /// * `declared` - Names of the variables that are set when the expression is evaluated
+    ///
+    /// # Returns
+    ///
+    /// `PyResult<PyObject>` - dict with the read "variables", the "assigned" variables,
+    ///     the called "functions" as (name, number of arguments) and the messages of the "warnings",
+    ///     or a ValueError listing all variables that are not declared
+    ///
+    pub fn check_str(
+        &self,
+        py: Python,
+        input: &str,
+        declared: HashSet<String>,
+    ) -> PyResult<PyObject> {
+        let report = py
+            .allow_threads(|| self.r_calculator.check_str(input, &declared))
+            .map_err(|x| evaluation_error(x, &format!("; expression: {}", Truncated(input))))?;
+        let result = PyDict::new_bound(py);
+        result.set_item("variables", report.variables)?;
+        result.set_item("assigned", report.assigned)?;
+        result.set_item("functions", report.functions)?;
+        let warnings: Vec<String> = report.warnings.iter().map(|w| w.to_string()).collect();
+        result.set_item("warnings", warnings)?;
+        Ok(result.into_any().unbind())
+    }
+
+    /// Validate an expression before any variables are set, without computing it.
+    ///
+    /// Brackets, function names and numbers of arguments are checked, unrecognized elements
+    /// and assignments are rejected. Variables do not have to be set.
+    ///
+    /// # Arguments
+    ///
+    /// * `input` - Expression that is validated
+    ///
+    /// # Returns
+    ///
+    /// `PyResult<Vec<String>>` - Names of the variables of the expression in the order of their first use,
+    ///     or a ValueError when the expression is not valid
+    ///
+    pub fn validate_expression(&self, py: Python, input: &str) -> PyResult<Vec<String>> {
+        py.allow_threads(|| self.r_calculator.validate_expression(input))
+            .map_err(|x| evaluation_error(x, &format!("; expression: {}", Truncated(input))))
+    }
+
+    ///  Parse a string expression allowing variable assignments and report suspicious patterns.
+    ///
+    /// # Arguments
+    ///
+    /// * `input` - Expression that is parsed
+    ///
+    /// # Returns
+    ///
+    /// `PyResult<(f64, Vec<String>)>` - the value and the messages of non-fatal warnings
+    ///
+    pub fn parse_str_assign_with_warnings(&mut self, input: &str) -> PyResult<(f64, Vec<String>)> {
+        match self.r_calculator.parse_str_assign_with_warnings(input) {
+            Ok((x, warnings)) => Ok((x, warnings.iter().map(|w| w.to_string()).collect())),
+            Err(x) => Err(evaluation_error(
+                x,
+                &format!("; expression: {}", Truncated(input)),
+            )),
+        }
+    }
+
+    /// Evaluate an expression on the Cartesian product of variable values.
+    ///
+    /// # Arguments
+    ///
+    /// * `expression` - Expression that is evaluated
+    /// * `axes` - List of (name, values) pairs of the swept variables
+    ///
+    /// # Returns
+    ///
+    /// `PyResult<(SweepAxes, Vec<f64>)>` - the axes and the values in row-major order
+    ///     that can be reshaped with numpy to the lengths of the axes, or a ValueError
+    ///     containing the multi-index of the failing point
+    ///
+    pub fn sweep(
+        &self,
+        py: Python,
+        expression: &str,
+        axes: SweepAxes,
+    ) -> PyResult<(SweepAxes, Vec<f64>)> {
+        let axes: Vec<(&str, Vec<f64>)> = axes
+            .iter()
+            .map(|(name, values)| (name.as_str(), values.clone()))
+            .collect();
+        match py.allow_threads(|| self.r_calculator.sweep(expression, &axes)) {
+            Ok(result) => Ok((result.axes, result.values)),
+            Err(x) => Err(evaluation_error(
+                x,
+                &format!("; expression: {}", Truncated(expression)),
+            )),
+        }
+    }
+
+    /// Evaluate an expression for every value of a variable, e.g. for a time series.
+    ///
+    /// Subexpressions that do not depend on the variable are evaluated once,
+    /// the results are identical to calling parse_str for every value.
+    ///
+    /// # Arguments
+    ///
+    /// * `expression` - Expression that is evaluated
+    /// * `variable` - Name of the variable taking the values
+    /// * `values` - Values of the variable as a list or numpy array
+    /// * `out` - Optional preallocated output, e.g. a numpy array of the length of values,
+    ///   the results are written into it and it is returned
+    ///
+    /// # Returns
+    ///
+    /// `PyResult<PyObject>` - out or a new list with the results, or a ValueError
+    ///     containing the index of the first failing value
+    ///
+    #[pyo3(signature = (expression, variable, values, out=None))]
+    pub fn evaluate_over(
+        &self,
+        py: Python,
+        expression: &str,
+        variable: &str,
+        values: &Bound<PyAny>,
+        out: Option<&Bound<PyAny>>,
+    ) -> PyResult<PyObject> {
+        let values = extract_values(values)?;
+        if let Some(out) = out {
+            if out.len()? != values.len() {
+                return Err(PyValueError::new_err(format!(
+                    "out has length {}, expected {}",
+                    out.len()?,
+                    values.len()
+                )));
+            }
+        }
+        let mut results = vec![0.0; values.len()];
+        py.allow_threads(|| {
+            self.r_calculator
+                .expression_cache()
+                .get_or_compile(expression)
+                .evaluate_over(variable, &values, &self.r_calculator, &mut results)
+        })
+        .map_err(|x| evaluation_error(x, &format!("; expression: {}", Truncated(expression))))?;
+        match out {
+            Some(out) => {
+                for (index, value) in results.into_iter().enumerate() {
+                    out.set_item(index, value)?;
+                }
+                Ok(out.clone().unbind())
+            }
+            None => Ok(results.into_py(py)),
+        }
+    }
+
+    /// Evaluate an expression on the Cartesian product of variable values into a numpy array.
+    ///
+    /// Requires the numpy feature. Unlike sweep, the GIL is kept while the expression is evaluated.
+    ///
+    /// # Arguments
+    ///
+    /// * `expression` - Expression that is evaluated
+    /// * `axes` - List of (name, values) pairs of the swept variables,
+    ///   the values are contiguous one-dimensional float64 numpy arrays
+    ///
+    /// # Returns
+    ///
+    /// `PyResult<PyObject>` - float64 numpy array with one axis per swept variable, a TypeError
+    ///     or ValueError for other arrays, or a ValueError containing the multi-index of the failing point
+    ///
+    #[cfg(all(feature = "numpy", not(Py_LIMITED_API)))]
+    pub fn sweep_ndarray(
+        &self,
+        py: Python,
+        expression: &str,
+        axes: Vec<(String, Bound<PyAny>)>,
+    ) -> PyResult<PyObject> {
+        let mut borrowed = Vec::with_capacity(axes.len());
+        for (name, values) in axes.iter() {
+            borrowed.push((name.as_str(), ContiguousValues::borrow(values)?));
+        }
+        // The Calculator owns the values of the axes in the result, they are copied once
+        let axes: Vec<(&str, Vec<f64>)> = borrowed
+            .iter()
+            .map(|(name, values)| (*name, values.as_slice(py).to_vec()))
+            .collect();
+        let result = self.r_calculator.sweep(expression, &axes).map_err(|x| {
+            evaluation_error(x, &format!("; expression: {}", Truncated(expression)))
+        })?;
+        new_ndarray(py, &result.values, &result.shape())
+    }
+
+    /// Evaluate an expression for every value of a variable into a numpy array.
+    ///
+    /// Requires the numpy feature. The values are read from the array without copying,
+    /// therefore the GIL is kept while the expression is evaluated.
+    ///
+    /// # Arguments
+    ///
+    /// * `expression` - Expression that is evaluated
+    /// * `variable` - Name of the variable taking the values
+    /// * `values` - Values of the variable as a contiguous one-dimensional float64 numpy array
+    ///
+    /// # Returns
+    ///
+    /// `PyResult<PyObject>` - float64 numpy array with the results, a TypeError or ValueError
+    ///     for other arrays, or a ValueError containing the index of the first failing value
+    ///
+    #[cfg(all(feature = "numpy", not(Py_LIMITED_API)))]
+    pub fn evaluate_over_ndarray(
+        &self,
+        py: Python,
+        expression: &str,
+        variable: &str,
+        values: &Bound<PyAny>,
+    ) -> PyResult<PyObject> {
+        let values = ContiguousValues::borrow(values)?;
+        let values = values.as_slice(py);
+        let mut results = vec![0.0; values.len()];
+        self.r_calculator
+            .expression_cache()
+            .get_or_compile(expression)
+            .evaluate_over(variable, values, &self.r_calculator, &mut results)
+            .map_err(|x| {
+                evaluation_error(x, &format!("; expression: {}", Truncated(expression)))
+            })?;
+        new_ndarray(py, &results, &[results.len()])
+    }
+
     /// Parse an input to float.
     ///
     /// # Arguments
@@ -82,28 +768,49 @@ impl CalculatorWrapper {
     /// * `input` - Parsed string CalculatorFloat or returns float value
     ///
     pub fn parse_get(&self, input: &Bound<PyAny>) -> PyResult<f64> {
-        let converted = convert_into_calculator_float(input)
-            .map_err(|_| PyTypeError::new_err("Input can not be converted to Calculator Float"))?;
-        let out = self.r_calculator.parse_get(converted);
-        match out {
-            Ok(x) => Ok(x),
-            Err(x) => Err(PyValueError::new_err(format!("{x:?}"))),
-        }
+        let py = input.py();
+        // CalculatorFloat inputs are borrowed instead of converted
+        let out = match input.downcast::<CalculatorFloatWrapper>() {
+            Ok(wrapper) => {
+                let wrapper = wrapper.borrow();
+                let internal = &wrapper.internal;
+                py.allow_threads(|| self.r_calculator.parse_get_ref(internal))
+            }
+            Err(_) => {
+                let converted = convert_into_calculator_float(input).map_err(|_| {
+                    PyTypeError::new_err("Input can not be converted to Calculator Float")
+                })?;
+                py.allow_threads(|| self.r_calculator.parse_get(converted))
+            }
+        };
+        out.map_err(|x| evaluation_error(x, ""))
+    }
+
+    /// Parse an input to complex, evaluating the real and imaginary part.
+    ///
+    /// # Arguments
+    ///
+    /// * `input` - CalculatorComplex or value that can be converted to CalculatorComplex
+    ///
+    pub fn parse_get_complex(&self, input: &Bound<PyAny>) -> PyResult<Complex<f64>> {
+        let py = input.py();
+        let converted = convert_into_calculator_complex(input).map_err(|_| {
+            PyTypeError::new_err("Input can not be converted to Calculator Complex")
+        })?;
+        py.allow_threads(|| self.r_calculator.parse_get_complex(converted))
+            .map_err(|x| evaluation_error(x, ""))
     }
 }
 
-///  Parse a string expression.
+///  Parse a string expression allowing variable assignments in a temporary Calculator.
+///
+/// Wraps [qoqo_calculator::parse_str_assign].
 ///
 /// # Arguments
 ///
 /// * `expression` - Expression that is parsed
 ///
 pub fn parse_str_assign(expression: &str) -> PyResult<f64> {
-    let mut calculator = Calculator::new();
-    match calculator.parse_str_assign(expression) {
-        Ok(x) => Ok(x),
-        Err(x) => Err(PyValueError::new_err(format!(
-            "{x:?}; expression {expression}"
-        ))),
-    }
+    qoqo_calculator::parse_str_assign(expression)
+        .map_err(|x| evaluation_error(x, &format!("; expression {}", Truncated(expression))))
 }
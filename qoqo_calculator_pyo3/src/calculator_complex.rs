@@ -15,17 +15,17 @@
 //! Converts the qoqo_calculator CalculatorComplex struct and methods for parsing and evaluating
 //! mathematical expressions in string form to complex into a Python class.
 
-use crate::{convert_into_calculator_float, CalculatorFloatWrapper};
+use crate::calculator_float::{arithmetic_error, parse_sympy_expression};
+use crate::{convert_into_calculator_float, error_message, CalculatorFloatWrapper};
 use num_complex::Complex;
 use pyo3::class::basic::CompareOp;
-use pyo3::exceptions::{PyNotImplementedError, PyTypeError, PyValueError, PyZeroDivisionError};
+use pyo3::exceptions::{PyNotImplementedError, PyTypeError, PyValueError};
 use pyo3::prelude::*;
-use pyo3::types::PyTuple;
+use pyo3::types::{PyComplex, PyTuple};
 use pyo3::ToPyObject;
 use qoqo_calculator::{CalculatorComplex, CalculatorError, CalculatorFloat};
 use std::collections::HashMap;
 use std::convert::TryInto;
-use std::panic::catch_unwind;
 
 /// Convert an f64 float (or any input that can be cast to float) or a string to CalculatorComplex.
 ///
@@ -64,6 +64,11 @@ pub fn convert_into_calculator_complex(
     }
 }
 
+/// Symbolic or numeric complex value.
+///
+/// CalculatorComplex is an immutable value type like Python's complex: no method changes the
+/// object. Augmented assignments like `a += 1` fall back to the binary operators and bind
+/// the name to a new object, other names referring to the original object are unchanged.
 #[pyclass(name = "CalculatorComplex", module = "qoqo_calculator_pyo3")]
 #[derive(Clone, Debug)]
 pub struct CalculatorComplexWrapper {
@@ -210,6 +215,45 @@ impl CalculatorComplexWrapper {
         }
     }
 
+    /// Return True when both parts of CalculatorComplex are exactly the float zero.
+    #[getter]
+    fn is_zero(&self) -> bool {
+        self.internal.is_zero()
+    }
+
+    /// Return True when the imaginary part of CalculatorComplex is exactly the float zero.
+    #[getter]
+    fn is_real(&self) -> bool {
+        self.internal.is_real()
+    }
+
+    /// Constant zero.
+    #[classattr]
+    #[pyo3(name = "ZERO")]
+    fn zero() -> CalculatorComplexWrapper {
+        CalculatorComplexWrapper {
+            internal: CalculatorComplex::ZERO,
+        }
+    }
+
+    /// Constant one.
+    #[classattr]
+    #[pyo3(name = "ONE")]
+    fn one() -> CalculatorComplexWrapper {
+        CalculatorComplexWrapper {
+            internal: CalculatorComplex::ONE,
+        }
+    }
+
+    /// Constant imaginary unit.
+    #[classattr]
+    #[pyo3(name = "I")]
+    fn imaginary_unit() -> CalculatorComplexWrapper {
+        CalculatorComplexWrapper {
+            internal: CalculatorComplex::I,
+        }
+    }
+
     /// Create a new instance of CalculatorComplex from a pair of values.
     #[staticmethod]
     fn from_pair(re: &Bound<PyAny>, im: &Bound<PyAny>) -> PyResult<CalculatorComplexWrapper> {
@@ -224,6 +268,106 @@ impl CalculatorComplexWrapper {
         })
     }
 
+    /// Create the phase factor e^(i*theta) = cos(theta) + i*sin(theta).
+    ///
+    /// A symbolic theta is contained in both parts.
+    #[staticmethod]
+    fn exp_i(theta: &Bound<PyAny>) -> PyResult<CalculatorComplexWrapper> {
+        let theta_cf = convert_into_calculator_float(theta)
+            .map_err(|_| PyTypeError::new_err("Input can not be converted to Calculator Float"))?;
+        Ok(CalculatorComplexWrapper {
+            internal: CalculatorComplex::exp_i(theta_cf),
+        })
+    }
+
+    /// Create the complex number r * e^(i*theta) from its polar form.
+    ///
+    /// Symbolic arguments give the parts (r * cos(theta)) and (r * sin(theta)).
+    #[staticmethod]
+    fn from_polar(r: &Bound<PyAny>, theta: &Bound<PyAny>) -> PyResult<CalculatorComplexWrapper> {
+        let r_cf = convert_into_calculator_float(r)
+            .map_err(|_| PyTypeError::new_err("Norm can not be converted to Calculator Float"))?;
+        let theta_cf = convert_into_calculator_float(theta)
+            .map_err(|_| PyTypeError::new_err("Phase can not be converted to Calculator Float"))?;
+        Ok(CalculatorComplexWrapper {
+            internal: CalculatorComplex::from_polar(r_cf, theta_cf),
+        })
+    }
+
+    /// Return the polar form (norm, arg) of x.
+    fn to_polar(&self) -> (CalculatorFloatWrapper, CalculatorFloatWrapper) {
+        let (norm, arg) = self.internal.to_polar();
+        (
+            CalculatorFloatWrapper { internal: norm },
+            CalculatorFloatWrapper { internal: arg },
+        )
+    }
+
+    /// Return True when x is a number with |x| = 1 up to the absolute tolerance.
+    #[pyo3(signature = (tolerance=1e-12))]
+    fn is_on_unit_circle(&self, tolerance: f64) -> bool {
+        self.internal.is_on_unit_circle(tolerance)
+    }
+
+    /// Return hyperbolic sine of x.
+    fn sinh(&self) -> CalculatorComplexWrapper {
+        Self {
+            internal: self.internal.sinh(),
+        }
+    }
+
+    /// Return hyperbolic cosine of x.
+    fn cosh(&self) -> CalculatorComplexWrapper {
+        Self {
+            internal: self.internal.cosh(),
+        }
+    }
+
+    /// Return tangent of x.
+    fn tan(&self) -> CalculatorComplexWrapper {
+        Self {
+            internal: self.internal.tan(),
+        }
+    }
+
+    /// Return exponential function of x.
+    fn exp(&self) -> CalculatorComplexWrapper {
+        Self {
+            internal: self.internal.exp(),
+        }
+    }
+
+    /// Return sine of x.
+    fn sin(&self) -> CalculatorComplexWrapper {
+        Self {
+            internal: self.internal.sin(),
+        }
+    }
+
+    /// Return cosine of x.
+    fn cos(&self) -> CalculatorComplexWrapper {
+        Self {
+            internal: self.internal.cos(),
+        }
+    }
+
+    /// Return principal square root of x, the branch cut is the negative real axis.
+    fn sqrt(&self) -> CalculatorComplexWrapper {
+        Self {
+            internal: self.internal.sqrt(),
+        }
+    }
+
+    /// Return x to the power of a real exponent, which can be symbolic.
+    fn powf(&self, exponent: &Bound<PyAny>) -> PyResult<CalculatorComplexWrapper> {
+        let exponent_cf = convert_into_calculator_float(exponent).map_err(|_| {
+            PyTypeError::new_err("Exponent can not be converted to Calculator Float")
+        })?;
+        Ok(Self {
+            internal: self.internal.powf(exponent_cf),
+        })
+    }
+
     /// Return complex conjugate of x: x*=x.re-i*x.im.
     fn conj(&self) -> CalculatorComplexWrapper {
         Self {
@@ -231,6 +375,21 @@ impl CalculatorComplexWrapper {
         }
     }
 
+    /// Return the real part, raising a ValueError unless the imaginary part is exactly zero.
+    ///
+    /// Unlike `real`, a tiny or symbolic imaginary part is not dropped silently.
+    fn demote(&self) -> PyResult<CalculatorFloatWrapper> {
+        Ok(CalculatorFloatWrapper {
+            internal: self.internal.demote().map_err(arithmetic_error)?,
+        })
+    }
+
+    /// Return the real part and whether a nonzero or symbolic imaginary part was dropped.
+    fn demote_lossy(&self) -> (CalculatorFloatWrapper, bool) {
+        let (real, dropped) = self.internal.demote_lossy();
+        (CalculatorFloatWrapper { internal: real }, dropped)
+    }
+
     /// Return phase of complex number x: arg(x).
     fn arg(&self) -> CalculatorFloatWrapper {
         CalculatorFloatWrapper {
@@ -238,6 +397,25 @@ impl CalculatorComplexWrapper {
         }
     }
 
+    /// Return phase of complex number x: arg(x), raising a ValueError for the zero value.
+    fn arg_checked(&self) -> PyResult<CalculatorFloatWrapper> {
+        Ok(CalculatorFloatWrapper {
+            internal: self.internal.arg_checked().map_err(arithmetic_error)?,
+        })
+    }
+
+    /// Replace a variable in the real and imaginary part by a number, variable or expression.
+    fn substitute(&self, name: &str, value: &Bound<PyAny>) -> PyResult<CalculatorComplexWrapper> {
+        let value = convert_into_calculator_float(value)
+            .map_err(|_| PyTypeError::new_err("Value can not be converted to CalculatorFloat"))?;
+        Ok(Self {
+            internal: self
+                .internal
+                .substitute(name, &value)
+                .map_err(arithmetic_error)?,
+        })
+    }
+
     /// Return true when x is close to y.
     fn isclose(&self, other: &Bound<PyAny>) -> PyResult<bool> {
         let other_cc = convert_into_calculator_complex(other).map_err(|_| {
@@ -253,6 +431,55 @@ impl CalculatorComplexWrapper {
         }
     }
 
+    /// Return the CalculatorComplex as a string that can be parsed by SymPy.
+    ///
+    /// # Returns
+    ///
+    /// `PyResult<String>` - the expression in SymPy syntax or a ValueError
+    ///
+    fn to_sympy_string(&self) -> PyResult<String> {
+        self.internal
+            .to_sympy_string()
+            .map_err(|x| PyValueError::new_err(error_message(&x)))
+    }
+
+    /// Convert the CalculatorComplex to a SymPy expression.
+    ///
+    /// SymPy is imported when the method is called and is not a dependency of qoqo_calculator_pyo3.
+    ///
+    /// # Returns
+    ///
+    /// `PyResult<PyObject>` - the SymPy expression, an ImportError when sympy is not installed
+    ///                        or a ValueError when the expression can not be converted
+    ///
+    fn to_sympy(&self) -> PyResult<PyObject> {
+        let sympy_string = self.to_sympy_string()?;
+        Python::with_gil(|py| parse_sympy_expression(py, &sympy_string))
+    }
+
+    /// Return a stable 64 bit hash of the CalculatorComplex.
+    ///
+    /// Equivalent forms of the real and imaginary parts share a hash.
+    /// Unlike `hash()`, the value is the same in every process and on every platform.
+    ///
+    /// # Returns
+    ///
+    /// `u64` - the canonical hash
+    ///
+    fn canonical_hash(&self) -> u64 {
+        self.internal.canonical_hash()
+    }
+
+    /// Return a stable 128 bit hash of the CalculatorComplex.
+    ///
+    /// # Returns
+    ///
+    /// `u128` - the canonical hash with 128 bits
+    ///
+    fn canonical_hash_128(&self) -> u128 {
+        self.internal.canonical_hash_128()
+    }
+
     /// Implement the x.__float__() (float(x)) Python magic method to convert a CalculatorComplex
     /// into a float.
     ///
@@ -267,7 +494,7 @@ impl CalculatorComplexWrapper {
         let fl: Result<f64, CalculatorError> = CalculatorComplex::try_into(self.internal.clone());
         match fl {
             Ok(x) => Ok(x),
-            Err(x) => Err(PyValueError::new_err(format!("{x:?}"))),
+            Err(x) => Err(PyValueError::new_err(error_message(&x))),
         }
     }
 
@@ -286,13 +513,21 @@ impl CalculatorComplexWrapper {
             CalculatorComplex::try_into(self.internal.clone());
         match com {
             Ok(x) => Ok(x),
-            Err(x) => Err(PyValueError::new_err(format!("{x:?}"))),
+            Err(x) => Err(PyValueError::new_err(error_message(&x))),
         }
     }
 
     /// Return the __richcmp__ magic method to perform rich comparison.
     /// operations on CalculatorComplex.
     ///
+    /// Equality is supported for every object that can be converted to CalculatorComplex:
+    /// int, float, str, complex, CalculatorFloat, CalculatorComplex and numeric types like
+    /// decimal.Decimal and numpy scalars, see CalculatorFloat.__richcmp__.
+    /// Ordering and other types return NotImplemented, so Python falls back to the reflected
+    /// comparison, `==` and `!=` compare by identity and ordering raises TypeError like for complex.
+    ///
+    /// CalculatorComplex is not hashable because the in-place operators change it.
+    ///
     /// # Arguments
     ///
     /// * `&self` - the CalculatorComplexWrapper object
@@ -301,19 +536,17 @@ impl CalculatorComplexWrapper {
     ///
     /// # Returns
     ///
-    /// `PyResult<bool>` - whether the two operations compared evaluated to True or False
+    /// `PyResult<PyObject>` - whether the two operations compared evaluated to True or False,
+    ///     NotImplemented for ordering and unsupported types
     ///
-    fn __richcmp__(&self, other: &Bound<PyAny>, op: CompareOp) -> PyResult<bool> {
-        let other_cc = convert_into_calculator_complex(other).map_err(|_| {
-            PyTypeError::new_err("Right hand side can not be converted to Calculator Complex")
-        })?;
-        match op {
-            CompareOp::Eq => Ok(self.internal == other_cc),
-            CompareOp::Ne => Ok(self.internal != other_cc),
-            _ => Err(PyNotImplementedError::new_err(
-                "Other comparison not implemented.",
-            )),
-        }
+    fn __richcmp__(&self, other: &Bound<PyAny>, op: CompareOp) -> PyResult<PyObject> {
+        let py = other.py();
+        let other_cc = match (op, convert_into_calculator_complex(other)) {
+            (CompareOp::Eq | CompareOp::Ne, Ok(other_cc)) => other_cc,
+            _ => return Ok(py.NotImplemented()),
+        };
+        let equal = self.internal == other_cc;
+        Ok((equal == matches!(op, CompareOp::Eq)).into_py(py))
     }
 
     /// Implement the `+` (__add__) magic method to add two CalculatorComplexes.
@@ -333,7 +566,7 @@ impl CalculatorComplexWrapper {
             PyTypeError::new_err("Right hand side can not be converted to Calculator Complex")
         })?;
         Ok(CalculatorComplexWrapper {
-            internal: (self_cc + other_cc),
+            internal: self_cc.checked_add(other_cc).map_err(arithmetic_error)?,
         })
     }
 
@@ -354,26 +587,10 @@ impl CalculatorComplexWrapper {
             PyTypeError::new_err("Right hand side can not be converted to Calculator Complex")
         })?;
         Ok(CalculatorComplexWrapper {
-            internal: (other_cc + self_cc),
+            internal: other_cc.checked_add(self_cc).map_err(arithmetic_error)?,
         })
     }
 
-    /// Implement the `+=` (__iadd__) magic method to add a CalculatorComplex
-    /// to another CalculatorComplex.
-    ///
-    /// # Arguments
-    ///
-    /// * `self` - the CalculatorComplexWrapper object
-    /// * `other` - the CalculatorComplexWrapper object to be added to self
-    ///
-    fn __iadd__(&mut self, other: &Bound<PyAny>) -> PyResult<()> {
-        let other_cc = convert_into_calculator_complex(other).map_err(|_| {
-            PyTypeError::new_err("Right hand side can not be converted to Calculator Complex")
-        })?;
-        self.internal += other_cc;
-        Ok(())
-    }
-
     /// Implement the `-` (__sub__) magic method to subtract two CalculatorComplexes.
     ///
     /// # Arguments
@@ -391,7 +608,7 @@ impl CalculatorComplexWrapper {
             PyTypeError::new_err("Right hand side can not be converted to Calculator Complex")
         })?;
         Ok(CalculatorComplexWrapper {
-            internal: (self_cc - other_cc),
+            internal: self_cc.checked_sub(other_cc).map_err(arithmetic_error)?,
         })
     }
 
@@ -412,26 +629,10 @@ impl CalculatorComplexWrapper {
             PyTypeError::new_err("Right hand side can not be converted to Calculator Complex")
         })?;
         Ok(CalculatorComplexWrapper {
-            internal: (other_cc - self_cc),
+            internal: other_cc.checked_sub(self_cc).map_err(arithmetic_error)?,
         })
     }
 
-    /// Implement the `-=` (__isub__) magic method to subtract a CalculatorComplex
-    /// from another CalculatorComplex.
-    ///
-    /// # Arguments
-    ///
-    /// * `self` - the CalculatorComplexWrapper object
-    /// * `other` - the CalculatorComplexWrapper object to be subtracted from self
-    ///
-    fn __isub__(&mut self, other: &Bound<PyAny>) -> PyResult<()> {
-        let other_cc = convert_into_calculator_complex(other).map_err(|_| {
-            PyTypeError::new_err("Right hand side can not be converted to Calculator Complex")
-        })?;
-        self.internal -= other_cc;
-        Ok(())
-    }
-
     /// Implement the `*` (__mul__) magic method to multiply two CalculatorComplexes.
     ///
     /// # Arguments
@@ -449,7 +650,7 @@ impl CalculatorComplexWrapper {
             PyTypeError::new_err("Right hand side can not be converted to Calculator Complex")
         })?;
         Ok(CalculatorComplexWrapper {
-            internal: (self_cc * other_cc),
+            internal: self_cc.checked_mul(other_cc).map_err(arithmetic_error)?,
         })
     }
 
@@ -470,26 +671,10 @@ impl CalculatorComplexWrapper {
             PyTypeError::new_err("Right hand side can not be converted to Calculator Complex")
         })?;
         Ok(CalculatorComplexWrapper {
-            internal: (other_cc * self_cc),
+            internal: other_cc.checked_mul(self_cc).map_err(arithmetic_error)?,
         })
     }
 
-    /// Implement the `*=` (__imul__) magic method to multiply a CalculatorComplex
-    /// by another CalculatorComplex.
-    ///
-    /// # Arguments
-    ///
-    /// * `self` - the CalculatorComplexWrapper object
-    /// * `other` - the CalculatorComplexWrapper object to multiply self by
-    ///
-    fn __imul__(&mut self, other: &Bound<PyAny>) -> PyResult<()> {
-        let other_cc = convert_into_calculator_complex(other).map_err(|_| {
-            PyTypeError::new_err("Right hand side can not be converted to Calculator Complex")
-        })?;
-        self.internal *= other_cc;
-        Ok(())
-    }
-
     /// Implement the `/` (__truediv__) magic method to divide two CalculatorComplexes.
     ///
     /// # Arguments
@@ -507,11 +692,9 @@ impl CalculatorComplexWrapper {
         let other_cc = convert_into_calculator_complex(rhs).map_err(|_| {
             PyTypeError::new_err("Right hand side can not be converted to Calculator Complex")
         })?;
-        let res = catch_unwind(|| self_cc / other_cc);
-        match res {
-            Ok(x) => Ok(CalculatorComplexWrapper { internal: x }),
-            Err(_) => Err(PyZeroDivisionError::new_err("Division by zero!")),
-        }
+        Ok(CalculatorComplexWrapper {
+            internal: self_cc.checked_div(other_cc).map_err(arithmetic_error)?,
+        })
     }
 
     /// Implement the `/` (__rtruediv__) magic method to divide two CalculatorComplexes.
@@ -531,32 +714,9 @@ impl CalculatorComplexWrapper {
         let other_cc = convert_into_calculator_complex(other).map_err(|_| {
             PyTypeError::new_err("Right hand side can not be converted to Calculator Complex")
         })?;
-        let res = catch_unwind(|| other_cc / self_cc);
-        match res {
-            Ok(x) => Ok(CalculatorComplexWrapper { internal: x }),
-            Err(_) => Err(PyZeroDivisionError::new_err("Division by zero!")),
-        }
-    }
-
-    /// Implement the `/=` (__itruediv__) magic method to divide a CalculatorComplex
-    /// by another CalculatorComplex.
-    ///
-    /// # Arguments
-    ///
-    /// * `self` - the CalculatorComplexWrapper object
-    /// * `other` - the CalculatorComplexWrapper object to divide self by
-    ///
-    fn __itruediv__(&mut self, other: &Bound<PyAny>) -> PyResult<()> {
-        let other_cc = convert_into_calculator_complex(other).map_err(|_| {
-            PyTypeError::new_err("Right hand side can not be converted to Calculator Complex")
-        })?;
-        if let CalculatorFloat::Float(x) = other_cc.norm() {
-            if x == 0.0 {
-                return Err(PyZeroDivisionError::new_err("Division by zero!"));
-            }
-        }
-        self.internal /= other_cc;
-        Ok(())
+        Ok(CalculatorComplexWrapper {
+            internal: other_cc.checked_div(self_cc).map_err(arithmetic_error)?,
+        })
     }
 
     /// Implement Python minus sign for CalculatorComplex.
@@ -574,17 +734,96 @@ impl CalculatorComplexWrapper {
     }
 
     /// Implement Python Inverse `1/x` for CalculatorComplex.
+    ///
+    /// Raises ZeroDivisionError for the zero value.
     fn __invert__(&self) -> PyResult<CalculatorComplexWrapper> {
         Ok(CalculatorComplexWrapper {
-            internal: self.internal.recip(),
+            internal: self.internal.checked_recip().map_err(arithmetic_error)?,
         })
     }
+
+    /// Implement Python unary plus for CalculatorComplex.
+    fn __pos__(&self) -> CalculatorComplexWrapper {
+        self.clone()
+    }
+
+    /// Return the complex conjugate of the CalculatorComplex (numbers.Complex interface).
+    fn conjugate(&self) -> CalculatorComplexWrapper {
+        self.conj()
+    }
+
+    /// Implement the `**` (__pow__) magic method for two CalculatorComplexes.
+    ///
+    /// # Arguments
+    ///
+    /// * `rhs` - Any Python object that can be converted to CalculatorComplex
+    /// * `modulo` - Not supported, must be None
+    ///
+    /// # Returns
+    ///
+    /// `PyResult<CalculatorComplexWrapper>` - self ** rhs
+    ///
+    /// Raises ValueError when one of the values is symbolic.
+    ///
+    fn __pow__(
+        &self,
+        rhs: &Bound<PyAny>,
+        modulo: Option<CalculatorComplexWrapper>,
+    ) -> PyResult<CalculatorComplexWrapper> {
+        if let Some(_x) = modulo {
+            return Err(PyNotImplementedError::new_err("Modulo is not implemented"));
+        }
+        let other_cc = convert_into_calculator_complex(rhs).map_err(|_| {
+            PyTypeError::new_err("Right hand side can not be converted to Calculator Complex")
+        })?;
+        complex_power(rhs.py(), &self.internal, &other_cc)
+    }
+
+    /// Implement the `**` (__rpow__) magic method with a CalculatorComplex as exponent.
+    fn __rpow__(
+        &self,
+        other: &Bound<PyAny>,
+        modulo: Option<CalculatorComplexWrapper>,
+    ) -> PyResult<CalculatorComplexWrapper> {
+        if let Some(_x) = modulo {
+            return Err(PyNotImplementedError::new_err("Modulo is not implemented"));
+        }
+        let other_cc = convert_into_calculator_complex(other).map_err(|_| {
+            PyTypeError::new_err("Right hand side can not be converted to Calculator Complex")
+        })?;
+        complex_power(other.py(), &other_cc, &self.internal)
+    }
+}
+
+/// Raise a numeric CalculatorComplex to a numeric power using Python complex semantics.
+fn complex_power(
+    py: Python,
+    base: &CalculatorComplex,
+    exponent: &CalculatorComplex,
+) -> PyResult<CalculatorComplexWrapper> {
+    let to_complex = |value: &CalculatorComplex| -> PyResult<Complex<f64>> {
+        value
+            .clone()
+            .try_into()
+            .map_err(|_| PyValueError::new_err("Operation is not supported for symbolic values."))
+    };
+    let base = to_complex(base)?;
+    let exponent = to_complex(exponent)?;
+    let result: Complex<f64> = PyComplex::from_doubles_bound(py, base.re, base.im)
+        .call_method1("__pow__", (exponent,))?
+        .extract()?;
+    Ok(CalculatorComplexWrapper {
+        internal: CalculatorComplex::from(result),
+    })
 }
 
 impl CalculatorComplexWrapper {
     pub fn from_pyany(input: &Bound<PyAny>) -> PyResult<CalculatorComplex> {
         convert_into_calculator_complex(input).map_err(|err| {
-            PyValueError::new_err(format!("Error in convert_to_calculator_complex: {err:?}"))
+            PyValueError::new_err(format!(
+                "Error in convert_to_calculator_complex: {}",
+                error_message(&err)
+            ))
         })
     }
 }
@@ -15,14 +15,37 @@
 //! Converts the qoqo_calculator CalculatorFloat enum and methods for parsing and evaluating
 //! mathematical expressions in string form to float into a Python class.
 
+use crate::{convert_into_calculator_complex, error_message, CalculatorComplexWrapper};
 use num_complex::Complex;
 use pyo3::class::basic::CompareOp;
-use pyo3::exceptions::{PyNotImplementedError, PyTypeError, PyValueError, PyZeroDivisionError};
+use pyo3::exceptions::{
+    PyImportError, PyNotImplementedError, PyTypeError, PyValueError, PyZeroDivisionError,
+};
 use pyo3::prelude::*;
-use qoqo_calculator::{CalculatorError, CalculatorFloat};
+use pyo3::types::{IntoPyDict, PyComplex, PyFloat};
+use qoqo_calculator::{
+    CalculatorComplex, CalculatorError, CalculatorFloat, RoundingPolicy, Truncated,
+};
 use std::collections::HashMap;
 use std::convert::From;
-use std::panic::catch_unwind;
+
+/// Convert the error of a checked arithmetic operation into a Python exception.
+///
+/// The arithmetic methods use the checked operations of qoqo_calculator instead of catching
+/// panics, so they raise Python exceptions also when the extension is built with panic=abort.
+/// A division by zero raises ZeroDivisionError, symbolic expressions exceeding the maximum
+/// length raise ValueError.
+///
+/// # Arguments
+///
+/// * `error` - Error returned by the checked operation
+///
+pub(crate) fn arithmetic_error(error: CalculatorError) -> PyErr {
+    match error {
+        CalculatorError::DivisionByZero => PyZeroDivisionError::new_err("Division by zero!"),
+        error => PyValueError::new_err(error_message(&error)),
+    }
+}
 
 /// Convert an f64 float (or any input that can be cast to float) or a string to CalculatorFloat.
 ///
@@ -48,7 +71,7 @@ pub fn convert_into_calculator_float(
                 .get_type()
                 .name()
                 .map_err(|_| CalculatorError::NotConvertable)?;
-            match try_str_conversion.to_str() {
+            match try_str_conversion.to_cow().as_deref() {
                 Ok("str") => Ok(CalculatorFloat::from(
                     String::extract_bound(input).map_err(|_| CalculatorError::NotConvertable)?,
                 )),
@@ -67,6 +90,11 @@ pub fn convert_into_calculator_float(
     }
 }
 
+/// Symbolic or numeric float value.
+///
+/// CalculatorFloat is an immutable value type like Python's float: no method changes the
+/// object. Augmented assignments like `a += 1` fall back to the binary operators and bind
+/// the name to a new object, other names referring to the original object are unchanged.
 #[pyclass(name = "CalculatorFloat", module = "qoqo_calculator_pyo3")]
 #[derive(Clone, Debug)]
 pub struct CalculatorFloatWrapper {
@@ -142,6 +170,27 @@ impl CalculatorFloatWrapper {
         self.internal.is_float()
     }
 
+    /// Python getter function which returns True when
+    /// CalculatorFloat contains a symbolic expression.
+    #[getter]
+    fn is_symbolic(&self) -> bool {
+        self.internal.is_symbolic()
+    }
+
+    /// Python getter function which returns True when
+    /// CalculatorFloat is exactly the float zero.
+    #[getter]
+    fn is_zero(&self) -> bool {
+        self.internal.is_zero()
+    }
+
+    /// Python getter function which returns True when
+    /// CalculatorFloat is exactly the float one.
+    #[getter]
+    fn is_one(&self) -> bool {
+        self.internal.is_one()
+    }
+
     /// Python getter function which returns True when
     /// CalculatorFloat does not contain symbolic expression.
     fn float(&self) -> PyResult<f64> {
@@ -158,6 +207,16 @@ impl CalculatorFloatWrapper {
         }
     }
 
+    /// Returns CalculatorFloat with constant subexpressions evaluated.
+    ///
+    /// Added zeros and factors of one are removed, symbols are kept.
+    /// Expressions that can not be simplified are returned unchanged.
+    fn simplify(&self) -> CalculatorFloatWrapper {
+        CalculatorFloatWrapper {
+            internal: self.internal.simplify(),
+        }
+    }
+
     /// Return atan2 for CalculatorFloat and generic type `Py<PyAny>`.
     ///
     /// # Arguments
@@ -169,7 +228,31 @@ impl CalculatorFloatWrapper {
             PyTypeError::new_err("Right hand side can not be converted to Calculator Float")
         })?;
         Ok(CalculatorFloatWrapper {
-            internal: self.internal.atan2(other_cf),
+            internal: self
+                .internal
+                .checked_atan2(other_cf)
+                .map_err(arithmetic_error)?,
+        })
+    }
+
+    /// Return the remainder of the truncated division, with the sign of self like math.fmod.
+    ///
+    /// Symbolic values give the expression `(x % y)` of the Calculator. Unlike the `%` operator,
+    /// which follows Python float semantics and has the sign of the divisor.
+    ///
+    /// # Arguments
+    ///
+    /// * `other` - Any Python object that can be converted to CalculatorFloat
+    ///
+    fn rem(&self, other: &Bound<PyAny>) -> PyResult<CalculatorFloatWrapper> {
+        let other_cf = convert_into_calculator_float(other).map_err(|_| {
+            PyTypeError::new_err("Right hand side can not be converted to Calculator Float")
+        })?;
+        Ok(CalculatorFloatWrapper {
+            internal: self
+                .internal
+                .checked_rem(other_cf)
+                .map_err(arithmetic_error)?,
         })
     }
 
@@ -181,6 +264,47 @@ impl CalculatorFloatWrapper {
         Ok(self.internal.isclose(other_cf))
     }
 
+    /// Return math.isclose(a, b, rel_tol=rel_tol, abs_tol=abs_tol) for two values.
+    ///
+    /// Bridges to the semantics of Python's math module: the tolerance is relative to the larger
+    /// absolute value and no absolute tolerance is used by default, so values close to zero are
+    /// only close to an exact zero. The method `isclose` instead uses a fixed relative tolerance
+    /// of 1e-8 to its argument and an absolute tolerance of the machine epsilon, and also compares
+    /// symbolic expressions.
+    ///
+    /// # Arguments
+    ///
+    /// * `a` - Any Python object that can be converted to a float CalculatorFloat
+    /// * `b` - Any Python object that can be converted to a float CalculatorFloat
+    /// * `rel_tol` - Relative tolerance passed to math.isclose
+    /// * `abs_tol` - Absolute tolerance passed to math.isclose
+    ///
+    /// # Returns
+    ///
+    /// * `PyResult<bool>` - The result of math.isclose, or a ValueError naming the symbolic value
+    ///
+    #[staticmethod]
+    #[pyo3(signature = (a, b, rel_tol=1e-9, abs_tol=0.0))]
+    fn isclose_math(
+        a: &Bound<PyAny>,
+        b: &Bound<PyAny>,
+        rel_tol: f64,
+        abs_tol: f64,
+    ) -> PyResult<bool> {
+        let mut values = [0.0; 2];
+        for (value, input) in values.iter_mut().zip([a, b]) {
+            let internal = convert_into_calculator_float(input).map_err(|_| {
+                PyTypeError::new_err("Input can not be converted to Calculator Float")
+            })?;
+            *value = CalculatorFloatWrapper { internal }.__float__()?;
+        }
+        let kwargs = [("rel_tol", rel_tol), ("abs_tol", abs_tol)].into_py_dict_bound(a.py());
+        a.py()
+            .import_bound("math")?
+            .call_method("isclose", (values[0], values[1]), Some(&kwargs))?
+            .extract()
+    }
+
     /// Return exponential function exp(x) for CalculatorFloat.
     fn exp(&self) -> CalculatorFloatWrapper {
         CalculatorFloatWrapper {
@@ -209,6 +333,114 @@ impl CalculatorFloatWrapper {
         }
     }
 
+    /// Return tangent function tan(x) for CalculatorFloat.
+    fn tan(&self) -> CalculatorFloatWrapper {
+        CalculatorFloatWrapper {
+            internal: self.internal.tan(),
+        }
+    }
+
+    /// Return arcsine function asin(x) for CalculatorFloat.
+    fn asin(&self) -> CalculatorFloatWrapper {
+        CalculatorFloatWrapper {
+            internal: self.internal.asin(),
+        }
+    }
+
+    /// Return arctangent function atan(x) for CalculatorFloat.
+    fn atan(&self) -> CalculatorFloatWrapper {
+        CalculatorFloatWrapper {
+            internal: self.internal.atan(),
+        }
+    }
+
+    /// Return hyperbolic sine function sinh(x) for CalculatorFloat.
+    fn sinh(&self) -> CalculatorFloatWrapper {
+        CalculatorFloatWrapper {
+            internal: self.internal.sinh(),
+        }
+    }
+
+    /// Return hyperbolic cosine function cosh(x) for CalculatorFloat.
+    fn cosh(&self) -> CalculatorFloatWrapper {
+        CalculatorFloatWrapper {
+            internal: self.internal.cosh(),
+        }
+    }
+
+    /// Return hyperbolic tangent function tanh(x) for CalculatorFloat.
+    fn tanh(&self) -> CalculatorFloatWrapper {
+        CalculatorFloatWrapper {
+            internal: self.internal.tanh(),
+        }
+    }
+
+    /// Return power of two 2^x for CalculatorFloat.
+    fn exp2(&self) -> CalculatorFloatWrapper {
+        CalculatorFloatWrapper {
+            internal: self.internal.exp2(),
+        }
+    }
+
+    /// Return natural logarithm ln(x), written `log(x)` in symbolic expressions for CalculatorFloat.
+    fn ln(&self) -> CalculatorFloatWrapper {
+        CalculatorFloatWrapper {
+            internal: self.internal.ln(),
+        }
+    }
+
+    /// Return logarithm to base 2 log2(x) for CalculatorFloat.
+    fn log2(&self) -> CalculatorFloatWrapper {
+        CalculatorFloatWrapper {
+            internal: self.internal.log2(),
+        }
+    }
+
+    /// Return logarithm to base 10 log10(x) for CalculatorFloat.
+    fn log10(&self) -> CalculatorFloatWrapper {
+        CalculatorFloatWrapper {
+            internal: self.internal.log10(),
+        }
+    }
+
+    /// Return largest integer smaller than or equal to x floor(x) for CalculatorFloat.
+    fn floor(&self) -> CalculatorFloatWrapper {
+        CalculatorFloatWrapper {
+            internal: self.internal.floor(),
+        }
+    }
+
+    /// Return smallest integer greater than or equal to x ceil(x) for CalculatorFloat.
+    fn ceil(&self) -> CalculatorFloatWrapper {
+        CalculatorFloatWrapper {
+            internal: self.internal.ceil(),
+        }
+    }
+
+    /// Return x rounded half away from zero round(x) for CalculatorFloat.
+    fn round(&self) -> CalculatorFloatWrapper {
+        CalculatorFloatWrapper {
+            internal: self.internal.round(),
+        }
+    }
+
+    /// Return the logarithm to an arbitrary base, the quotient `(log(x) / log(base))`.
+    ///
+    /// # Arguments
+    ///
+    /// * `base` - Any Python object that can be converted to CalculatorFloat
+    ///
+    fn log(&self, base: &Bound<PyAny>) -> PyResult<CalculatorFloatWrapper> {
+        let base_cf = convert_into_calculator_float(base)
+            .map_err(|_| PyTypeError::new_err("Base can not be converted to Calculator Float"))?;
+        Ok(CalculatorFloatWrapper {
+            internal: self
+                .internal
+                .checked_log(base_cf)
+                .map_err(arithmetic_error)?,
+        })
+    }
+
     /// Return absolute value abs(x) for CalculatorFloat.
     fn abs(&self) -> CalculatorFloatWrapper {
         CalculatorFloatWrapper {
@@ -230,6 +462,215 @@ impl CalculatorFloatWrapper {
         }
     }
 
+    /// Return factorial x! for CalculatorFloat.
+    ///
+    /// Returns NaN for negative integers.
+    fn factorial(&self) -> CalculatorFloatWrapper {
+        CalculatorFloatWrapper {
+            internal: self.internal.factorial(),
+        }
+    }
+
+    /// Return the CalculatorFloat rounded to a number of decimal digits after the decimal point.
+    ///
+    /// The decimal representation is rounded half away from zero, e.g. 0.00005 rounded to
+    /// 4 digits is 0.0001. Symbolic values return the expression `round_digits(x, digits)`.
+    ///
+    /// # Arguments
+    ///
+    /// * `digits` - Number of decimal digits after the decimal point, negative for tens, hundreds, ...
+    ///
+    fn round_to_digits(&self, digits: i32) -> CalculatorFloatWrapper {
+        CalculatorFloatWrapper {
+            internal: self.internal.round_to_digits(digits),
+        }
+    }
+
+    /// Return the CalculatorFloat rounded to the nearest multiple of a step.
+    ///
+    /// Ties are rounded half away from zero. Symbolic values return the expression
+    /// `round_step(x, step)`.
+    ///
+    /// # Arguments
+    ///
+    /// * `step` - Positive and finite step
+    ///
+    /// # Returns
+    ///
+    /// `PyResult<CalculatorFloatWrapper>` - the rounded value or a ValueError for an invalid step
+    ///
+    fn round_to_multiple(&self, step: f64) -> PyResult<CalculatorFloatWrapper> {
+        self.internal
+            .round_to_multiple(step)
+            .map(|internal| CalculatorFloatWrapper { internal })
+            .map_err(|x| PyValueError::new_err(error_message(&x)))
+    }
+
+    /// Return Heaviside step function theta(x) for CalculatorFloat.
+    ///
+    /// Returns 0.0 for negative, 0.5 for zero and 1.0 for positive values.
+    fn heaviside(&self) -> CalculatorFloatWrapper {
+        CalculatorFloatWrapper {
+            internal: self.internal.heaviside(),
+        }
+    }
+
+    /// Return Heaviside step function theta(x) for CalculatorFloat, alias of heaviside.
+    fn step(&self) -> CalculatorFloatWrapper {
+        CalculatorFloatWrapper {
+            internal: self.internal.step(),
+        }
+    }
+
+    /// Return Kronecker delta delta(x) for CalculatorFloat.
+    ///
+    /// Returns 1.0 for zero and 0.0 otherwise.
+    fn delta(&self) -> CalculatorFloatWrapper {
+        CalculatorFloatWrapper {
+            internal: self.internal.delta(),
+        }
+    }
+
+    /// Python getter returning the names of the free variables in the order of their first occurrence.
+    ///
+    /// Function names are not included, a float has no variables.
+    ///
+    /// # Raises
+    ///
+    /// `ValueError` - The expression can not be lexed
+    ///
+    #[getter]
+    fn variables(&self) -> PyResult<Vec<String>> {
+        self.internal
+            .variables()
+            .map_err(|x| PyValueError::new_err(error_message(&x)))
+    }
+
+    /// Return the names of the placeholders, e.g. qubit for {qubit}, in the order of their first occurrence.
+    fn placeholders(&self) -> Vec<String> {
+        self.internal.placeholders()
+    }
+
+    /// Return the number literals of the symbolic expression as (value, start, end) tuples.
+    ///
+    /// start and end are the byte positions of the literal in the expression. A minus in front
+    /// of a literal is an operator and not part of the literal. A float has no literals.
+    fn embedded_literals(&self) -> Vec<(f64, usize, usize)> {
+        self.internal
+            .embedded_literals()
+            .into_iter()
+            .map(|(value, span)| (value, span.start, span.end))
+            .collect()
+    }
+
+    /// Replace the placeholders of the CalculatorFloat, e.g. {qubit}, by values.
+    ///
+    /// # Arguments
+    ///
+    /// * `values` - dict of placeholder names and values (float, str or CalculatorFloat)
+    ///
+    /// # Returns
+    ///
+    /// `PyResult<CalculatorFloatWrapper>` - the CalculatorFloat with the values inserted,
+    ///     a ValueError listing all placeholders without a value or a TypeError for values
+    ///     that can not be converted to CalculatorFloat
+    ///
+    fn bind_placeholders(
+        &self,
+        values: HashMap<String, Bound<PyAny>>,
+    ) -> PyResult<CalculatorFloatWrapper> {
+        let values = values
+            .into_iter()
+            .map(|(name, value)| {
+                convert_into_calculator_float(&value)
+                    .map(|value| (name, value))
+                    .map_err(|_| {
+                        PyTypeError::new_err(
+                            "Value of placeholder can not be converted to CalculatorFloat",
+                        )
+                    })
+            })
+            .collect::<PyResult<HashMap<String, CalculatorFloat>>>()?;
+        Ok(CalculatorFloatWrapper {
+            internal: self
+                .internal
+                .bind_placeholders(&values)
+                .map_err(|x| PyValueError::new_err(error_message(&x)))?,
+        })
+    }
+
+    /// Replace a variable of the CalculatorFloat by a number, variable or expression.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - name of the replaced variable
+    /// * `value` - value inserted for the variable (float, str or CalculatorFloat)
+    ///
+    /// # Returns
+    ///
+    /// `PyResult<CalculatorFloatWrapper>` - the CalculatorFloat with the value inserted,
+    ///     evaluated to a float when no variables are left
+    ///
+    fn substitute(&self, name: &str, value: &Bound<PyAny>) -> PyResult<CalculatorFloatWrapper> {
+        let value = convert_into_calculator_float(value)
+            .map_err(|_| PyTypeError::new_err("Value can not be converted to CalculatorFloat"))?;
+        Ok(CalculatorFloatWrapper {
+            internal: self
+                .internal
+                .substitute(name, &value)
+                .map_err(arithmetic_error)?,
+        })
+    }
+
+    /// Return the CalculatorFloat as a string that can be parsed by SymPy.
+    ///
+    /// # Returns
+    ///
+    /// `PyResult<String>` - the expression in SymPy syntax or a ValueError
+    ///
+    fn to_sympy_string(&self) -> PyResult<String> {
+        self.internal
+            .to_sympy_string()
+            .map_err(|x| PyValueError::new_err(error_message(&x)))
+    }
+
+    /// Convert the CalculatorFloat to a SymPy expression.
+    ///
+    /// SymPy is imported when the method is called and is not a dependency of qoqo_calculator_pyo3.
+    ///
+    /// # Returns
+    ///
+    /// `PyResult<PyObject>` - the SymPy expression, an ImportError when sympy is not installed
+    ///                        or a ValueError when the expression can not be converted
+    ///
+    fn to_sympy(&self) -> PyResult<PyObject> {
+        let sympy_string = self.to_sympy_string()?;
+        Python::with_gil(|py| parse_sympy_expression(py, &sympy_string))
+    }
+
+    /// Return a stable 64 bit hash of the CalculatorFloat.
+    ///
+    /// Equivalent forms of an expression, e.g. `(a + b)` and `b+a`, share a hash.
+    /// Unlike `hash()`, the value is the same in every process and on every platform.
+    ///
+    /// # Returns
+    ///
+    /// `u64` - the canonical hash
+    ///
+    fn canonical_hash(&self) -> u64 {
+        self.internal.canonical_hash()
+    }
+
+    /// Return a stable 128 bit hash of the CalculatorFloat.
+    ///
+    /// # Returns
+    ///
+    /// `u128` - the canonical hash with 128 bits
+    ///
+    fn canonical_hash_128(&self) -> u128 {
+        self.internal.canonical_hash_128()
+    }
+
     /// Python getter function which returns the value stored in CalculatorFloat.
     #[getter]
     fn value(&self) -> PyObject {
@@ -261,27 +702,56 @@ impl CalculatorFloatWrapper {
     /// Return the __richcmp__ magic method to perform rich comparison
     /// operations on CalculatorFloat.
     ///
+    /// Equality is supported for every object that can be converted to CalculatorComplex:
+    /// int, float, str, complex, CalculatorFloat, CalculatorComplex and numeric types like
+    /// decimal.Decimal and numpy scalars. Both sides are compared as CalculatorComplex, so a
+    /// CalculatorFloat equals a complex number with a zero imaginary part and a str equals the
+    /// CalculatorFloat created from it. Ordering is supported for float values and objects that
+    /// can be converted to CalculatorFloat except complex numbers.
+    /// Other types return NotImplemented, so Python falls back to the reflected comparison,
+    /// `==` and `!=` compare by identity and ordering raises TypeError.
+    ///
+    /// CalculatorFloat is not hashable because the in-place operators change it.
+    ///
     /// # Arguments
     ///
     /// * `&self` - the CalculatorFloatWrapper object
     /// * `other` - the object to compare self to
-    /// * `op` - equal or not equal
+    /// * `op` - the comparison operator, ordering is only supported for float values
     ///
     /// # Returns
     ///
-    /// `PyResult<bool>` - whether the two operations compared evaluated to True or False
+    /// `PyResult<PyObject>` - whether the two operations compared evaluated to True or False,
+    ///     NotImplemented for unsupported types
     ///
-    fn __richcmp__(&self, other: &Bound<PyAny>, op: CompareOp) -> PyResult<bool> {
-        let other_cf = convert_into_calculator_float(other).map_err(|_| {
-            PyTypeError::new_err("Right hand side can not be converted to Calculator Float")
-        })?;
-        match op {
-            CompareOp::Eq => Ok(self.internal == other_cf),
-            CompareOp::Ne => Ok(self.internal != other_cf),
-            _ => Err(PyNotImplementedError::new_err(
-                "Other comparison not implemented.",
-            )),
+    fn __richcmp__(&self, other: &Bound<PyAny>, op: CompareOp) -> PyResult<PyObject> {
+        let py = other.py();
+        if let CompareOp::Eq | CompareOp::Ne = op {
+            let other_cc = match convert_into_calculator_complex(other) {
+                Ok(other_cc) => other_cc,
+                Err(_) => return Ok(py.NotImplemented()),
+            };
+            let equal = CalculatorComplex::from(self.internal.clone()) == other_cc;
+            return Ok((equal == matches!(op, CompareOp::Eq)).into_py(py));
+        }
+        if other.is_instance_of::<PyComplex>() {
+            return Ok(py.NotImplemented());
         }
+        let other_cf = match convert_into_calculator_float(other) {
+            Ok(other_cf) => other_cf,
+            Err(_) => return Ok(py.NotImplemented()),
+        };
+        if !(self.internal.is_float() && other_cf.is_float()) {
+            return Err(PyValueError::new_err("Symbolic values can not be ordered."));
+        }
+        // PartialOrd of CalculatorFloat, comparisons with NaN are false
+        let ordered = match op {
+            CompareOp::Lt => self.internal < other_cf,
+            CompareOp::Le => self.internal <= other_cf,
+            CompareOp::Gt => self.internal > other_cf,
+            _ => self.internal >= other_cf,
+        };
+        Ok(ordered.into_py(py))
     }
 
     /// Return the __repr__ magic method to represent objects in Python of CalculatorFloat.
@@ -306,7 +776,7 @@ impl CalculatorFloatWrapper {
             PyTypeError::new_err("Right hand side can not be converted to Calculator Float")
         })?;
         Ok(CalculatorFloatWrapper {
-            internal: (self_cf + other_cf),
+            internal: self_cf.checked_add(other_cf).map_err(arithmetic_error)?,
         })
     }
 
@@ -327,26 +797,10 @@ impl CalculatorFloatWrapper {
             PyTypeError::new_err("Right hand side can not be converted to Calculator Float")
         })?;
         Ok(CalculatorFloatWrapper {
-            internal: (other_cf + self_cf),
+            internal: other_cf.checked_add(self_cf).map_err(arithmetic_error)?,
         })
     }
 
-    /// Implement the `+=` (__iadd__) magic method to add a CalculatorFloat
-    /// to another CalculatorFloat.
-    ///
-    /// # Arguments
-    ///
-    /// * `self` - the CalculatorFloatWrapper object
-    /// * `other` - the CalculatorFloatWrapper object to be added to self
-    ///
-    fn __iadd__(&mut self, other: &Bound<PyAny>) -> PyResult<()> {
-        let other_cf = convert_into_calculator_float(other).map_err(|_| {
-            PyTypeError::new_err("Right hand side can not be converted to Calculator Float")
-        })?;
-        self.internal += other_cf;
-        Ok(())
-    }
-
     /// Implement the `-` (__sub__) magic method to subtract two CalculatorFloats.
     ///
     /// # Arguments
@@ -364,7 +818,7 @@ impl CalculatorFloatWrapper {
             PyTypeError::new_err("Right hand side can not be converted to Calculator Float")
         })?;
         Ok(CalculatorFloatWrapper {
-            internal: (self_cf - other_cf),
+            internal: self_cf.checked_sub(other_cf).map_err(arithmetic_error)?,
         })
     }
 
@@ -385,26 +839,10 @@ impl CalculatorFloatWrapper {
             PyTypeError::new_err("Right hand side can not be converted to Calculator Float")
         })?;
         Ok(CalculatorFloatWrapper {
-            internal: (other_cf - self_cf),
+            internal: other_cf.checked_sub(self_cf).map_err(arithmetic_error)?,
         })
     }
 
-    /// Implement the `-=` (__isub__) magic method to subtract a CalculatorFloat
-    /// from another CalculatorFloat.
-    ///
-    /// # Arguments
-    ///
-    /// * `self` - the CalculatorFloatWrapper object
-    /// * `other` - the CalculatorFloatWrapper object to be subtracted from self
-    ///
-    fn __isub__(&mut self, other: &Bound<PyAny>) -> PyResult<()> {
-        let other_cf = convert_into_calculator_float(other).map_err(|_| {
-            PyTypeError::new_err("Right hand side can not be converted to Calculator Float")
-        })?;
-        self.internal -= other_cf;
-        Ok(())
-    }
-
     /// Implement the `*` (__mul__) magic method to multiply two CalculatorFloats.
     ///
     /// # Arguments
@@ -422,7 +860,7 @@ impl CalculatorFloatWrapper {
             PyTypeError::new_err("Right hand side can not be converted to Calculator Float")
         })?;
         Ok(CalculatorFloatWrapper {
-            internal: (self_cf * other_cf),
+            internal: self_cf.checked_mul(other_cf).map_err(arithmetic_error)?,
         })
     }
 
@@ -443,26 +881,10 @@ impl CalculatorFloatWrapper {
             PyTypeError::new_err("Right hand side can not be converted to Calculator Float")
         })?;
         Ok(CalculatorFloatWrapper {
-            internal: (other_cf * self_cf),
+            internal: other_cf.checked_mul(self_cf).map_err(arithmetic_error)?,
         })
     }
 
-    /// Implement the `*=` (__imul__) magic method to multiply a CalculatorFloat
-    /// by another CalculatorFloat.
-    ///
-    /// # Arguments
-    ///
-    /// * `self` - the CalculatorFloatWrapper object
-    /// * `other` - the CalculatorFloatWrapper object to multiply self by
-    ///
-    fn __imul__(&mut self, other: &Bound<PyAny>) -> PyResult<()> {
-        let other_cf = convert_into_calculator_float(other).map_err(|_| {
-            PyTypeError::new_err("Right hand side can not be converted to Calculator Float")
-        })?;
-        self.internal *= other_cf;
-        Ok(())
-    }
-
     /// Return __pow__ (power) for CalculatorFloat and generic type `Py<PyAny>`.
     ///
     /// # Arguments
@@ -482,7 +904,7 @@ impl CalculatorFloatWrapper {
             PyTypeError::new_err("Right hand side can not be converted to Calculator Float")
         })?;
         Ok(CalculatorFloatWrapper {
-            internal: (self_cf.powf(other_cf)),
+            internal: self_cf.checked_powf(other_cf).map_err(arithmetic_error)?,
         })
     }
 
@@ -502,11 +924,9 @@ impl CalculatorFloatWrapper {
         let other_cf = convert_into_calculator_float(rhs).map_err(|_| {
             PyTypeError::new_err("Right hand side can not be converted to Calculator Float")
         })?;
-        let res = catch_unwind(|| self_cf / other_cf);
-        match res {
-            Ok(x) => Ok(CalculatorFloatWrapper { internal: x }),
-            Err(_) => Err(PyZeroDivisionError::new_err("Division by zero!")),
-        }
+        Ok(CalculatorFloatWrapper {
+            internal: self_cf.checked_div(other_cf).map_err(arithmetic_error)?,
+        })
     }
 
     /// Implement the `/` (__truediv__) magic method to divide two CalculatorFloats.
@@ -525,32 +945,9 @@ impl CalculatorFloatWrapper {
         let other_cf = convert_into_calculator_float(other).map_err(|_| {
             PyTypeError::new_err("Right hand side can not be converted to Calculator Float")
         })?;
-        let res = catch_unwind(|| other_cf / self_cf);
-        match res {
-            Ok(x) => Ok(CalculatorFloatWrapper { internal: x }),
-            Err(_) => Err(PyZeroDivisionError::new_err("Division by zero!")),
-        }
-    }
-
-    /// Implement the `/=` (__itruediv__) magic method to divide a CalculatorFloat
-    /// by another CalculatorFloat.
-    ///
-    /// # Arguments
-    ///
-    /// * `self` - the CalculatorFloatWrapper object
-    /// * `other` - the CalculatorFloatWrapper object to divide self by
-    ///
-    fn __itruediv__(&mut self, other: &Bound<PyAny>) -> PyResult<()> {
-        let other_cf = convert_into_calculator_float(other).map_err(|_| {
-            PyTypeError::new_err("Right hand side can not be converted to Calculator Float")
-        })?;
-        if let CalculatorFloat::Float(x) = other_cf {
-            if x == 0.0 {
-                return Err(PyZeroDivisionError::new_err("Division by zero!"));
-            }
-        }
-        self.internal /= other_cf;
-        Ok(())
+        Ok(CalculatorFloatWrapper {
+            internal: other_cf.checked_div(self_cf).map_err(arithmetic_error)?,
+        })
     }
 
     /// Implement Python minus sign for CalculatorFloat.
@@ -583,20 +980,278 @@ impl CalculatorFloatWrapper {
     /// Converts the Rust Panic when CalculatorFloat contains symbolic string value
     /// into a Python error
     ///
+    /// The error message contains the symbolic expression, truncated when it is long, so failing
+    /// calls of math module functions like math.fsum show which value was symbolic.
+    ///
     fn __float__(&self) -> PyResult<f64> {
-        match self.internal {
-            CalculatorFloat::Float(x) => Ok(x),
-            CalculatorFloat::Str(_) => Err(PyValueError::new_err(
-                "Symbolic Value can not be cast to float.",
-            )),
+        match &self.internal {
+            CalculatorFloat::Float(x) => Ok(*x),
+            CalculatorFloat::Str(expression) => Err(PyValueError::new_err(format!(
+                "Symbolic Value {} can not be cast to float.",
+                Truncated(format_args!("{expression:?}"))
+            ))),
+        }
+    }
+
+    /// Convert the CalculatorFloat to an integer.
+    ///
+    /// # Arguments
+    ///
+    /// * `policy` - "exact" to only convert values that are integral within one ulp,
+    ///   or "round", "floor" or "ceil" to round the value
+    ///
+    /// # Returns
+    ///
+    /// * `PyResult<i64>` - The rounded value, or a ValueError for symbolic values, non-finite values,
+    ///   values that are not integral with policy "exact" and values outside of the 64 bit integers
+    ///
+    #[pyo3(signature = (policy="exact"))]
+    fn to_int(&self, policy: &str) -> PyResult<i64> {
+        let policy = match policy {
+            "exact" => RoundingPolicy::Exact,
+            "round" => RoundingPolicy::Round,
+            "floor" => RoundingPolicy::Floor,
+            "ceil" => RoundingPolicy::Ceil,
+            _ => {
+                return Err(PyValueError::new_err(format!(
+                    "policy must be 'exact', 'round', 'floor' or 'ceil', not {policy:?}"
+                )))
+            }
+        };
+        self.internal
+            .try_into_i64(policy)
+            .map_err(|x| PyValueError::new_err(error_message(&x)))
+    }
+
+    /// Return the real part of the CalculatorFloat (part of the numbers.Real interface).
+    #[getter]
+    fn real(&self) -> CalculatorFloatWrapper {
+        self.clone()
+    }
+
+    /// Return the imaginary part of the CalculatorFloat, which is always zero.
+    #[getter]
+    fn imag(&self) -> CalculatorFloatWrapper {
+        CalculatorFloatWrapper {
+            internal: CalculatorFloat::Float(0.0),
+        }
+    }
+
+    /// Return the complex conjugate of the CalculatorFloat, which is the value itself.
+    fn conjugate(&self) -> CalculatorFloatWrapper {
+        self.clone()
+    }
+
+    /// Return the CalculatorFloat as CalculatorComplex with imaginary part zero.
+    fn promote(&self) -> CalculatorComplexWrapper {
+        CalculatorComplexWrapper {
+            internal: self.internal.promote(),
+        }
+    }
+
+    /// Implement Python unary plus for CalculatorFloat.
+    fn __pos__(&self) -> CalculatorFloatWrapper {
+        self.clone()
+    }
+
+    /// Implement the `**` (__rpow__) magic method with a CalculatorFloat as exponent.
+    ///
+    /// # Arguments
+    ///
+    /// * `other` - Any Python object that can be converted to CalculatorFloat
+    /// * `modulo` - Not supported, must be None
+    ///
+    /// # Returns
+    ///
+    /// `PyResult<CalculatorFloatWrapper>` - other ** self
+    ///
+    fn __rpow__(
+        &self,
+        other: &Bound<PyAny>,
+        modulo: Option<CalculatorFloatWrapper>,
+    ) -> PyResult<CalculatorFloatWrapper> {
+        if let Some(_x) = modulo {
+            return Err(PyNotImplementedError::new_err("Modulo is not implemented"));
+        }
+        let other_cf = convert_into_calculator_float(other).map_err(|_| {
+            PyTypeError::new_err("Right hand side can not be converted to Calculator Float")
+        })?;
+        Ok(CalculatorFloatWrapper {
+            internal: other_cf
+                .checked_powf(&self.internal)
+                .map_err(arithmetic_error)?,
+        })
+    }
+
+    /// Implement the `//` (__floordiv__) magic method for two CalculatorFloats.
+    ///
+    /// # Returns
+    ///
+    /// `PyResult<CalculatorFloatWrapper>` - self // other
+    ///
+    /// Raises ValueError when one of the values is symbolic.
+    ///
+    fn __floordiv__(&self, other: &Bound<PyAny>) -> PyResult<CalculatorFloatWrapper> {
+        let (lhs, rhs) = self.real_operands(other, false)?;
+        float_operation(other.py(), lhs, "__floordiv__", rhs)
+    }
+
+    /// Implement the `//` (__rfloordiv__) magic method for two CalculatorFloats.
+    fn __rfloordiv__(&self, other: &Bound<PyAny>) -> PyResult<CalculatorFloatWrapper> {
+        let (lhs, rhs) = self.real_operands(other, true)?;
+        float_operation(other.py(), lhs, "__floordiv__", rhs)
+    }
+
+    /// Implement the `%` (__mod__) magic method for two CalculatorFloats.
+    ///
+    /// The result has the sign of the divisor, following Python float semantics.
+    /// Raises ValueError when one of the values is symbolic, see `rem` for the remainder
+    /// of the Calculator.
+    ///
+    fn __mod__(&self, other: &Bound<PyAny>) -> PyResult<CalculatorFloatWrapper> {
+        let (lhs, rhs) = self.real_operands(other, false)?;
+        float_operation(other.py(), lhs, "__mod__", rhs)
+    }
+
+    /// Implement the `%` (__rmod__) magic method for two CalculatorFloats.
+    fn __rmod__(&self, other: &Bound<PyAny>) -> PyResult<CalculatorFloatWrapper> {
+        let (lhs, rhs) = self.real_operands(other, true)?;
+        float_operation(other.py(), lhs, "__mod__", rhs)
+    }
+
+    /// Implement the divmod(x, y) (__divmod__) Python magic method for two CalculatorFloats.
+    ///
+    /// # Returns
+    ///
+    /// `PyResult<(CalculatorFloatWrapper, CalculatorFloatWrapper)>` - (self // other, self % other)
+    ///
+    fn __divmod__(
+        &self,
+        other: &Bound<PyAny>,
+    ) -> PyResult<(CalculatorFloatWrapper, CalculatorFloatWrapper)> {
+        let (lhs, rhs) = self.real_operands(other, false)?;
+        Ok((
+            float_operation(other.py(), lhs, "__floordiv__", rhs)?,
+            float_operation(other.py(), lhs, "__mod__", rhs)?,
+        ))
+    }
+
+    /// Implement the divmod(y, x) (__rdivmod__) Python magic method for two CalculatorFloats.
+    fn __rdivmod__(
+        &self,
+        other: &Bound<PyAny>,
+    ) -> PyResult<(CalculatorFloatWrapper, CalculatorFloatWrapper)> {
+        let (lhs, rhs) = self.real_operands(other, true)?;
+        Ok((
+            float_operation(other.py(), lhs, "__floordiv__", rhs)?,
+            float_operation(other.py(), lhs, "__mod__", rhs)?,
+        ))
+    }
+
+    // The integral conversions behave like the ones of float and raise the ValueError of
+    // __float__ for symbolic values. The methods floor, ceil and round keep symbolic values.
+
+    /// Implement int(x) (__int__) for CalculatorFloat, truncating like int(float).
+    fn __int__(&self, py: Python) -> PyResult<PyObject> {
+        integral_operation(py, self.__float__()?, "__int__")
+    }
+
+    /// Implement math.trunc(x) (__trunc__) for CalculatorFloat, returning a Python int.
+    fn __trunc__(&self, py: Python) -> PyResult<PyObject> {
+        integral_operation(py, self.__float__()?, "__trunc__")
+    }
+
+    /// Implement math.floor(x) (__floor__) for CalculatorFloat, returning a Python int.
+    fn __floor__(&self, py: Python) -> PyResult<PyObject> {
+        integral_operation(py, self.__float__()?, "__floor__")
+    }
+
+    /// Implement math.ceil(x) (__ceil__) for CalculatorFloat, returning a Python int.
+    fn __ceil__(&self, py: Python) -> PyResult<PyObject> {
+        integral_operation(py, self.__float__()?, "__ceil__")
+    }
+
+    /// Implement round(x, ndigits) (__round__) for CalculatorFloat.
+    ///
+    /// # Arguments
+    ///
+    /// * `ndigits` - Number of decimal digits to keep, None rounds to a Python int
+    ///
+    /// # Returns
+    ///
+    /// A Python int when `ndigits` is None, otherwise a rounded CalculatorFloat.
+    ///
+    #[pyo3(signature = (ndigits=None))]
+    fn __round__(&self, py: Python, ndigits: Option<i64>) -> PyResult<PyObject> {
+        let value = self.__float__()?;
+        match ndigits {
+            None => integral_operation(py, value, "__round__"),
+            Some(digits) => Ok(float_operation(py, value, "__round__", digits)?.into_py(py)),
         }
     }
 }
 
+/// Parse a string in SymPy syntax to a SymPy expression, importing sympy lazily.
+pub(crate) fn parse_sympy_expression(py: Python, sympy_string: &str) -> PyResult<PyObject> {
+    let sympy = py.import_bound("sympy").map_err(|_| {
+        PyImportError::new_err(
+            "Converting to a SymPy expression requires the sympy package. Install it with `pip install sympy`.",
+        )
+    })?;
+    Ok(sympy.call_method1("parse_expr", (sympy_string,))?.unbind())
+}
+
+/// Apply a binary Python float method to a numeric value and wrap the result.
+fn float_operation(
+    py: Python,
+    value: f64,
+    method: &str,
+    argument: impl IntoPy<PyObject>,
+) -> PyResult<CalculatorFloatWrapper> {
+    let result: f64 = PyFloat::new_bound(py, value)
+        .call_method1(method, (argument,))?
+        .extract()?;
+    Ok(CalculatorFloatWrapper {
+        internal: CalculatorFloat::Float(result),
+    })
+}
+
+/// Apply a Python float method that returns an int (e.g. __floor__) to a numeric value.
+fn integral_operation(py: Python, value: f64, method: &str) -> PyResult<PyObject> {
+    Ok(PyFloat::new_bound(py, value).call_method0(method)?.unbind())
+}
+
 impl CalculatorFloatWrapper {
     pub fn from_pyany(input: &Bound<PyAny>) -> PyResult<CalculatorFloat> {
         convert_into_calculator_float(input).map_err(|err| {
-            PyValueError::new_err(format!("Error in convert_to_calculator_float: {err:?}"))
+            PyValueError::new_err(format!(
+                "Error in convert_to_calculator_float: {}",
+                error_message(&err)
+            ))
         })
     }
+
+    /// Return the float value, raising ValueError for symbolic values.
+    fn real_value(&self) -> PyResult<f64> {
+        match self.internal {
+            CalculatorFloat::Float(x) => Ok(x),
+            CalculatorFloat::Str(_) => Err(PyValueError::new_err(
+                "Operation is not supported for symbolic values.",
+            )),
+        }
+    }
+
+    /// Return (self, other) as floats, or (other, self) when `reflected` is set.
+    fn real_operands(&self, other: &Bound<PyAny>, reflected: bool) -> PyResult<(f64, f64)> {
+        let other_cf = convert_into_calculator_float(other).map_err(|_| {
+            PyTypeError::new_err("Right hand side can not be converted to Calculator Float")
+        })?;
+        let other_value = CalculatorFloatWrapper { internal: other_cf }.real_value()?;
+        let self_value = self.real_value()?;
+        if reflected {
+            Ok((other_value, self_value))
+        } else {
+            Ok((self_value, other_value))
+        }
+    }
 }
@@ -14,8 +14,19 @@
 //!
 //! qoqo_calculator_pyo3 module bringing the qoqo_calculator rust library to Python.
 
+// The pyo3 macros produce PyErr to PyErr conversions that newer clippy versions flag.
+#![allow(clippy::useless_conversion)]
+
+use pyo3::exceptions::{PyImportError, PyTypeError, PyValueError, PyZeroDivisionError};
 use pyo3::prelude::*;
+use pyo3::types::PyDict;
 use pyo3::wrap_pyfunction;
+use qoqo_calculator::introspection::{self, Associativity};
+use qoqo_calculator::{
+    validate_expression, variables_in_expression, Calculator, CalculatorError, CalculatorFloat,
+    CalculatorVector, Truncated,
+};
+use std::collections::HashMap;
 mod calculator_float;
 pub use calculator_float::convert_into_calculator_float;
 pub use calculator_float::CalculatorFloatWrapper;
@@ -23,14 +34,413 @@ mod calculator_complex;
 pub use calculator_complex::convert_into_calculator_complex;
 pub use calculator_complex::CalculatorComplexWrapper;
 mod calculator;
+use calculator::evaluation_error;
 pub use calculator::parse_str_assign;
+pub use calculator::CalculatorAssertionError;
+pub use calculator::CalculatorFrozenError;
 pub use calculator::CalculatorWrapper;
+#[cfg(all(feature = "numpy", not(Py_LIMITED_API)))]
+mod ndarray;
+
+// The arrays are read with the buffer protocol, which is not part of the stable ABI of Python 3.9
+#[cfg(all(feature = "numpy", Py_LIMITED_API))]
+compile_error!("The numpy feature can not be combined with the abi3 feature");
+
+/// Format a CalculatorError for a Python exception.
+///
+/// The message is the Debug representation of the error, with long expressions truncated
+/// like in the Display message, see [qoqo_calculator::set_max_error_expression_length].
+///
+/// # Arguments
+///
+/// * `error` - Error returned by qoqo_calculator
+///
+pub(crate) fn error_message(error: &CalculatorError) -> String {
+    match error {
+        CalculatorError::FloatSymbolicNotConvertable { val } => format!(
+            "FloatSymbolicNotConvertable {{ val: {} }}",
+            Truncated(format_args!("{val:?}"))
+        ),
+        CalculatorError::ComplexSymbolicNotConvertable { val } => format!(
+            "ComplexSymbolicNotConvertable {{ val: {} }}",
+            Truncated(format_args!("{val:?}"))
+        ),
+        CalculatorError::ComplexCanNotBeConvertedToFloat { val } => format!(
+            "ComplexCanNotBeConvertedToFloat {{ val: {} }}",
+            Truncated(format_args!("{val:?}"))
+        ),
+        CalculatorError::NotParsableAssign { variable_name } => format!(
+            "NotParsableAssign {{ variable_name: {} }}",
+            Truncated(format_args!("{variable_name:?}"))
+        ),
+        CalculatorError::UnexpectedToken { token, position } => format!(
+            "UnexpectedToken {{ token: {}, position: {position} }}",
+            Truncated(format_args!("{token:?}"))
+        ),
+        CalculatorError::UnrecognizedToken { token, position } => format!(
+            "UnrecognizedToken {{ token: {}, position: {position} }}",
+            Truncated(format_args!("{token:?}"))
+        ),
+        CalculatorError::InvalidExpression { position, error } => format!(
+            "InvalidExpression {{ position: {position}, error: {} }}",
+            error_message(error)
+        ),
+        CalculatorError::SweepPointError { index, error } => format!(
+            "SweepPointError {{ index: {index:?}, error: {} }}",
+            error_message(error)
+        ),
+        error => format!("{error:?}"),
+    }
+}
 
 #[pyfunction]
 fn parse_string_assign(expression: &str) -> PyResult<f64> {
     parse_str_assign(expression)
 }
 
+/// Parse a string expression to float without assigning variables.
+///
+/// # Arguments
+///
+/// * `expression` - Expression that is parsed
+/// * `variables` - Optional dictionary of variable names and values used in the expression
+///
+/// # Returns
+///
+/// `PyResult<f64>` - the value of the expression or a ValueError
+///
+/// # Example
+///
+/// ```python
+/// >>> parse("a * 2", {"a": 1.5})
+/// 3.0
+/// ```
+///
+#[pyfunction]
+#[pyo3(signature = (expression, variables=None))]
+fn parse(expression: &str, variables: Option<HashMap<String, f64>>) -> PyResult<f64> {
+    let mut calculator = Calculator::new();
+    calculator
+        .set_variables(variables.unwrap_or_default())
+        .map_err(|x| evaluation_error(x, ""))?;
+    calculator
+        .parse_str(expression)
+        .map_err(|x| evaluation_error(x, &format!("; expression: {}", Truncated(expression))))
+}
+
+/// Check that an expression only contains elements that can be parsed.
+///
+/// # Arguments
+///
+/// * `expression` - Expression that is checked
+///
+/// # Returns
+///
+/// `PyResult<()>` - None for a valid expression or a ValueError with the position of the first
+///                  invalid element
+///
+/// # Example
+///
+/// ```python
+/// >>> validate("sin(a) + 1")
+/// >>> validate("a + $")
+/// ValueError: InvalidExpression { position: 4, error: NotParsableUnrecognized }; expression: a + $
+/// ```
+///
+#[pyfunction]
+fn validate(expression: &str) -> PyResult<()> {
+    validate_expression(expression).map_err(|x| {
+        PyValueError::new_err(format!(
+            "{}; expression: {}",
+            error_message(&x),
+            Truncated(expression)
+        ))
+    })
+}
+
+/// Return the names of all variables referenced in an expression.
+///
+/// # Arguments
+///
+/// * `expression` - Expression that is searched for variables
+///
+/// # Returns
+///
+/// `PyResult<Vec<String>>` - the variable names in order of first occurrence or a ValueError
+///
+/// # Example
+///
+/// ```python
+/// >>> variables_in("b * sin(a) + b")
+/// ['b', 'a']
+/// ```
+///
+#[pyfunction]
+fn variables_in(expression: &str) -> PyResult<Vec<String>> {
+    variables_in_expression(expression).map_err(|x| {
+        PyValueError::new_err(format!(
+            "{}; expression: {}",
+            error_message(&x),
+            Truncated(expression)
+        ))
+    })
+}
+
+/// Return an expression with its constant subexpressions evaluated.
+///
+/// Added zeros and factors of one are removed, symbols are kept. A numeric expression
+/// becomes a number. Expressions that can not be simplified are returned unchanged.
+///
+/// # Arguments
+///
+/// * `expression` - Expression that is simplified
+///
+/// # Returns
+///
+/// `PyResult<String>` - the simplified expression or a ValueError for an invalid expression
+///
+/// # Example
+///
+/// ```python
+/// >>> simplify("(2 * 3) * x + 0")
+/// '(6e0 * x)'
+/// >>> simplify("2 ^ 3 - 1")
+/// '7e0'
+/// ```
+///
+#[pyfunction]
+fn simplify(expression: &str) -> PyResult<String> {
+    validate_expression(expression).map_err(|x| {
+        PyValueError::new_err(format!(
+            "{}; expression: {}",
+            error_message(&x),
+            Truncated(expression)
+        ))
+    })?;
+    Ok(CalculatorFloat::Str(expression.to_string())
+        .simplify()
+        .to_string())
+}
+
+/// Return the fraction k * pi / n as CalculatorFloat.
+///
+/// # Arguments
+///
+/// * `k` - Multiple of pi
+/// * `n` - Divisor of pi
+///
+/// # Returns
+///
+/// `PyResult<CalculatorFloatWrapper>` - the fraction of pi or a ZeroDivisionError
+///
+/// # Example
+///
+/// ```python
+/// >>> pi_fraction(1, 2)
+/// 1.5707963267948966e0
+/// ```
+///
+#[pyfunction]
+fn pi_fraction(k: i64, n: i64) -> PyResult<CalculatorFloatWrapper> {
+    match CalculatorFloat::pi_fraction(k, n) {
+        Ok(internal) => Ok(CalculatorFloatWrapper { internal }),
+        Err(_) => Err(PyZeroDivisionError::new_err("Division by zero!")),
+    }
+}
+
+/// Convert a list of Python values to a CalculatorVector.
+fn convert_into_calculator_vector(values: Vec<Bound<PyAny>>) -> PyResult<CalculatorVector> {
+    values
+        .iter()
+        .map(|value| {
+            convert_into_calculator_float(value)
+                .map_err(|_| PyTypeError::new_err("Input can not be converted to Calculator Float"))
+        })
+        .collect::<PyResult<Vec<CalculatorFloat>>>()
+        .map(CalculatorVector::from)
+}
+
+/// Return the dot product of two lists of values.
+///
+/// # Arguments
+///
+/// * `left` - List of floats, strings or CalculatorFloats
+/// * `right` - List of floats, strings or CalculatorFloats with the same length
+///
+/// # Returns
+///
+/// `PyResult<CalculatorFloatWrapper>` - the sum of the elementwise products or a ValueError
+///                                      when the lists have different lengths
+///
+/// # Example
+///
+/// ```python
+/// >>> dot([1, 2], ["a", 3])
+/// (a + 6e0)
+/// ```
+///
+#[pyfunction]
+fn dot(left: Vec<Bound<PyAny>>, right: Vec<Bound<PyAny>>) -> PyResult<CalculatorFloatWrapper> {
+    let left = convert_into_calculator_vector(left)?;
+    let right = convert_into_calculator_vector(right)?;
+    match left.dot(&right) {
+        Ok(internal) => Ok(CalculatorFloatWrapper { internal }),
+        Err(x) => Err(PyValueError::new_err(error_message(&x))),
+    }
+}
+
+/// Return the sum of the squares of a list of values.
+///
+/// # Arguments
+///
+/// * `values` - List of floats, strings or CalculatorFloats
+///
+/// # Returns
+///
+/// `PyResult<CalculatorFloatWrapper>` - the sum of the squares
+///
+/// # Example
+///
+/// ```python
+/// >>> norm_sqr([3, "a"])
+/// ((a * a) + 9e0)
+/// ```
+///
+#[pyfunction]
+fn norm_sqr(values: Vec<Bound<PyAny>>) -> PyResult<CalculatorFloatWrapper> {
+    Ok(CalculatorFloatWrapper {
+        internal: convert_into_calculator_vector(values)?.norm_sqr(),
+    })
+}
+
+/// Return the functions that can be used in expressions, sorted by name.
+///
+/// # Returns
+///
+/// `PyResult<Vec<Bound<PyDict>>>` - one dict per function with the keys `name`, `arity`, `variadic` and
+///     `description`, `arity` is the minimal number of arguments of variadic functions
+///
+/// # Example
+///
+/// ```python
+/// >>> functions()[0]
+/// {'name': 'abs', 'arity': 1, 'variadic': False, 'description': 'Absolute value'}
+/// ```
+///
+#[pyfunction]
+fn functions(py: Python) -> PyResult<Vec<Bound<PyDict>>> {
+    introspection::functions()
+        .iter()
+        .map(|function| {
+            let dict = PyDict::new_bound(py);
+            dict.set_item("name", function.name)?;
+            dict.set_item("arity", function.arity)?;
+            dict.set_item("variadic", function.variadic)?;
+            dict.set_item("description", function.description)?;
+            Ok(dict)
+        })
+        .collect()
+}
+
+/// Return the names of all kinds of errors of the Calculator.
+///
+/// # Returns
+///
+/// `Vec<&str>` - the names of the error kinds, e.g. `DivisionByZero`
+///
+#[pyfunction]
+fn error_kinds() -> Vec<&'static str> {
+    introspection::error_kinds().to_vec()
+}
+
+/// Return the built-in constants that can be used in expressions, sorted by name.
+///
+/// Variables set in a Calculator take precedence over constants with the same name.
+///
+/// # Returns
+///
+/// `PyResult<Vec<Bound<PyDict>>>` - one dict per constant with the keys `name`, `value` and `description`
+///
+#[pyfunction]
+fn constants(py: Python) -> PyResult<Vec<Bound<PyDict>>> {
+    introspection::constants()
+        .iter()
+        .map(|constant| {
+            let dict = PyDict::new_bound(py);
+            dict.set_item("name", constant.name)?;
+            dict.set_item("value", constant.value)?;
+            dict.set_item("description", constant.description)?;
+            Ok(dict)
+        })
+        .collect()
+}
+
+/// Return the operators of the Calculator in order of increasing precedence.
+///
+/// # Returns
+///
+/// `PyResult<Vec<Bound<PyDict>>>` - one dict per operator with the keys `symbol`, `precedence`,
+///                                  `associativity` (`"left"` or `"none"`) and `unary`
+///
+#[pyfunction]
+fn operators(py: Python) -> PyResult<Vec<Bound<PyDict>>> {
+    introspection::operators()
+        .iter()
+        .map(|operator| {
+            let dict = PyDict::new_bound(py);
+            dict.set_item("symbol", operator.symbol)?;
+            dict.set_item("precedence", operator.precedence)?;
+            let associativity = match operator.associativity {
+                Associativity::Left => "left",
+                Associativity::NonAssociative => "none",
+            };
+            dict.set_item("associativity", associativity)?;
+            dict.set_item("unary", operator.unary)?;
+            Ok(dict)
+        })
+        .collect()
+}
+
+/// Raise ImportError when the module is imported into a sub-interpreter.
+///
+/// The Python types of the module are created once per process, so they can not be shared
+/// with sub-interpreters. PyO3 already refuses to initialize the module in a second interpreter
+/// after it has been initialized in the first one. This check also refuses the first
+/// initialization when it happens in a sub-interpreter, with a message naming the module.
+/// PyO3 records the interpreter before this check runs, so the module has to be imported
+/// in the main interpreter before any sub-interpreter tries to import it.
+///
+/// CPython before 3.9 (and the stable ABI of 3.9 on Windows) does not provide the interpreter ID,
+/// there PyO3 only allows a single initialization per process.
+#[cfg(all(
+    Py_3_9,
+    not(any(PyPy, GraalPy)),
+    not(all(windows, Py_LIMITED_API, not(Py_3_10)))
+))]
+fn check_main_interpreter(py: Python) -> PyResult<()> {
+    // The main interpreter always has the ID 0
+    let interpreter_id =
+        unsafe { pyo3::ffi::PyInterpreterState_GetID(pyo3::ffi::PyInterpreterState_Get()) };
+    match interpreter_id {
+        0 => Ok(()),
+        -1 => Err(PyErr::fetch(py)),
+        _ => Err(PyImportError::new_err(
+            "qoqo_calculator_pyo3 can only be imported in the main Python interpreter, \
+             sub-interpreters are not supported",
+        )),
+    }
+}
+
+/// Without the interpreter ID PyO3 guards against sub-interpreters by allowing a single initialization.
+#[cfg(not(all(
+    Py_3_9,
+    not(any(PyPy, GraalPy)),
+    not(all(windows, Py_LIMITED_API, not(Py_3_10)))
+)))]
+fn check_main_interpreter(_py: Python) -> PyResult<()> {
+    Ok(())
+}
+
 /// qoqo_calculator_pyo3 module bringing the qoqo_calculator rust library to Python.
 ///
 /// qoqo_calculator is a rust library implementing:
@@ -42,12 +452,47 @@ fn parse_string_assign(expression: &str) -> PyResult<f64> {
 ///
 /// Uses the pyo3 rust crate to create the Python bindings.
 ///
+/// The module is created once per process: initializing it again (e.g. with `importlib.reload`)
+/// returns the same module object, so the classes keep their identity. The only process-global
+/// state of qoqo_calculator is the limit of the symbolic expression length, an atomic value.
+/// Importing the module into a sub-interpreter raises ImportError.
+///
+/// Building with the `abi3` feature creates an extension for the stable ABI of CPython 3.9 and later.
+///
 #[pymodule]
-fn qoqo_calculator_pyo3(_py: Python, m: &Bound<PyModule>) -> PyResult<()> {
+pub fn qoqo_calculator_pyo3(py: Python, m: &Bound<PyModule>) -> PyResult<()> {
+    check_main_interpreter(py)?;
     m.add_class::<CalculatorWrapper>()?;
     m.add_class::<CalculatorFloatWrapper>()?;
     m.add_class::<CalculatorComplexWrapper>()?;
+    m.add(
+        "CalculatorAssertionError",
+        py.get_type_bound::<CalculatorAssertionError>(),
+    )?;
+    m.add(
+        "CalculatorFrozenError",
+        py.get_type_bound::<CalculatorFrozenError>(),
+    )?;
     m.add_function(wrap_pyfunction!(parse_string_assign, m)?)
         .unwrap();
+    m.add_function(wrap_pyfunction!(parse, m)?)?;
+    m.add_function(wrap_pyfunction!(validate, m)?)?;
+    m.add_function(wrap_pyfunction!(variables_in, m)?)?;
+    m.add_function(wrap_pyfunction!(simplify, m)?)?;
+    m.add_function(wrap_pyfunction!(pi_fraction, m)?)?;
+    m.add_function(wrap_pyfunction!(dot, m)?)?;
+    m.add_function(wrap_pyfunction!(norm_sqr, m)?)?;
+    m.add_function(wrap_pyfunction!(functions, m)?)?;
+    m.add_function(wrap_pyfunction!(error_kinds, m)?)?;
+    m.add_function(wrap_pyfunction!(constants, m)?)?;
+    m.add_function(wrap_pyfunction!(operators, m)?)?;
+    // Register as virtual subclasses so isinstance checks against the numbers ABCs succeed
+    let numbers = py.import_bound("numbers")?;
+    numbers
+        .getattr("Real")?
+        .call_method1("register", (m.getattr("CalculatorFloat")?,))?;
+    numbers
+        .getattr("Complex")?
+        .call_method1("register", (m.getattr("CalculatorComplex")?,))?;
     Ok(())
 }
@@ -0,0 +1,96 @@
+// Copyright © 2020-2021 HQS Quantum Simulations GmbH. All Rights Reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except
+// in compliance with the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the
+// License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either
+// express or implied. See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! ndarray module
+//!
+//! Exchanges float64 numpy arrays with the Calculator without Python lists in between.
+//! Inputs are read with the buffer protocol, outputs are written into a bytearray
+//! that numpy wraps without copying. numpy is imported when the first array is created,
+//! the extension itself does not link against it.
+
+use pyo3::buffer::PyBuffer;
+use pyo3::exceptions::{PyTypeError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::{PyByteArray, PyTuple};
+
+/// Float64 values of a contiguous one-dimensional array, borrowed from the array.
+pub(crate) struct ContiguousValues(PyBuffer<f64>);
+
+impl ContiguousValues {
+    /// Borrow the values of a contiguous one-dimensional float64 array.
+    ///
+    /// # Arguments
+    ///
+    /// * `values` - numpy array or any other object exporting float64 values with the buffer protocol
+    ///
+    /// # Returns
+    ///
+    /// `PyResult<ContiguousValues>` - the borrowed values, a TypeError for other dtypes
+    ///     or a ValueError for arrays that are not contiguous or not one-dimensional
+    ///
+    pub(crate) fn borrow(values: &Bound<PyAny>) -> PyResult<Self> {
+        let buffer = PyBuffer::<f64>::get_bound(values).map_err(|_| {
+            PyTypeError::new_err(format!(
+                "Values must be a numpy array of dtype float64, found {}",
+                values.get_type()
+            ))
+        })?;
+        if buffer.dimensions() != 1 || !buffer.is_c_contiguous() {
+            return Err(PyValueError::new_err(
+                "Values must be a one-dimensional contiguous numpy array, \
+                 use numpy.ascontiguousarray to create a contiguous copy",
+            ));
+        }
+        Ok(Self(buffer))
+    }
+
+    /// Return the borrowed values.
+    ///
+    /// The GIL token ensures no Python code, which could write to the array, runs while
+    /// the slice is alive.
+    pub(crate) fn as_slice<'py>(&'py self, _py: Python<'py>) -> &'py [f64] {
+        let length = self.0.item_count();
+        if length == 0 {
+            return &[];
+        }
+        // SAFETY: get_bound checked the item size, format and alignment and borrow checked that
+        // the buffer is contiguous. The buffer is held until self is dropped, so the memory
+        // stays valid, and it is only written by Python code, which needs the GIL.
+        unsafe { std::slice::from_raw_parts(self.0.buf_ptr() as *const f64, length) }
+    }
+}
+
+/// Create a float64 numpy array from values in row-major order.
+///
+/// # Arguments
+///
+/// * `py` - GIL token
+/// * `values` - Values of the array, the last axis changes fastest
+/// * `shape` - Lengths of the axes, their product is the number of values
+///
+/// # Returns
+///
+/// `PyResult<PyObject>` - the writeable numpy array, or an ImportError when numpy is not installed
+///
+pub(crate) fn new_ndarray(py: Python, values: &[f64], shape: &[usize]) -> PyResult<PyObject> {
+    let numpy = PyModule::import_bound(py, "numpy")?;
+    let bytes = PyByteArray::new_bound_with(py, std::mem::size_of_val(values), |bytes| {
+        for (chunk, value) in bytes.chunks_exact_mut(8).zip(values) {
+            chunk.copy_from_slice(&value.to_ne_bytes());
+        }
+        Ok(())
+    })?;
+    let array = numpy.call_method1("frombuffer", (bytes, "float64"))?;
+    Ok(array
+        .call_method1("reshape", (PyTuple::new_bound(py, shape),))?
+        .unbind())
+}
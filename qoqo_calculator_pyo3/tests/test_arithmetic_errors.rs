@@ -0,0 +1,117 @@
+// Copyright © 2020-2021 HQS Quantum Simulations GmbH. All Rights Reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except
+// in compliance with the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the
+// License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either
+// express or implied. See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The arithmetic methods raise Python exceptions without relying on unwinding.
+//!
+//! The inputs that made the operators of qoqo_calculator panic, a division by zero and
+//! symbolic expressions exceeding the maximum length, go through the checked operations.
+//! The tests also run when built with panic=abort, e.g. with
+//! `RUSTFLAGS="-C panic=abort" cargo +nightly test -Z panic-abort-tests`,
+//! where any remaining panic aborts the test process.
+
+use pyo3::exceptions::{PyValueError, PyZeroDivisionError};
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+use pyo3::PyTypeInfo;
+use qoqo_calculator::max_symbolic_length;
+use qoqo_calculator_pyo3::{CalculatorComplexWrapper, CalculatorFloatWrapper};
+
+/// Assert that a method call raised the Python exception E.
+fn assert_raises<E: PyTypeInfo>(py: Python, result: PyResult<Bound<PyAny>>) {
+    match result {
+        Ok(value) => panic!("expected exception, returned {value}"),
+        Err(error) => assert!(error.is_instance_of::<E>(py), "unexpected {error}"),
+    }
+}
+
+/// Run an augmented assignment `value op= other` and return the new value.
+///
+/// The wrappers have no in-place methods, the assignment uses the binary operator.
+fn augmented_assignment<'py>(
+    py: Python<'py>,
+    value: &Bound<'py, PyAny>,
+    operator: &str,
+    other: impl ToPyObject,
+) -> PyResult<Bound<'py, PyAny>> {
+    let locals = PyDict::new_bound(py);
+    locals.set_item("value", value)?;
+    locals.set_item("other", other)?;
+    py.run_bound(&format!("value {operator} other"), None, Some(&locals))?;
+    Ok(locals.get_item("value")?.unwrap())
+}
+
+/// Call all arithmetic methods with inputs the operators of qoqo_calculator panic on.
+fn assert_arithmetic_errors(py: Python) {
+    let float_type = py.get_type_bound::<CalculatorFloatWrapper>();
+    let complex_type = py.get_type_bound::<CalculatorComplexWrapper>();
+    let number = float_type.call1((1.0,)).unwrap();
+    let zero = float_type.call1((0.0,)).unwrap();
+    let symbolic = float_type.call1(("x",)).unwrap();
+    for (value, method, argument) in [
+        (&number, "__truediv__", 0.0.into_py(py)),
+        (&symbolic, "__truediv__", 0.0.into_py(py)),
+        (&zero, "__rtruediv__", 1.0.into_py(py)),
+    ] {
+        assert_raises::<PyZeroDivisionError>(py, value.call_method1(method, (argument,)));
+    }
+    assert_raises::<PyZeroDivisionError>(py, augmented_assignment(py, &symbolic, "/=", 0.0));
+    let complex = complex_type.call1((1.0,)).unwrap();
+    let complex_zero = complex_type.call1((0.0,)).unwrap();
+    assert_raises::<PyZeroDivisionError>(py, complex.call_method1("__truediv__", (0.0,)));
+    assert_raises::<PyZeroDivisionError>(py, complex_zero.call_method1("__rtruediv__", (1.0,)));
+    assert_raises::<PyZeroDivisionError>(py, augmented_assignment(py, &complex, "/=", 0.0));
+
+    let name = "x".repeat(max_symbolic_length() / 2 + 1);
+    let long = float_type.call1((name.as_str(),)).unwrap();
+    for method in [
+        "__add__",
+        "__radd__",
+        "__sub__",
+        "__rsub__",
+        "__mul__",
+        "__rmul__",
+        "__truediv__",
+        "__rtruediv__",
+        "__pow__",
+        "__rpow__",
+        "atan2",
+    ] {
+        let result = if method.ends_with("pow__") {
+            long.call_method1(method, (name.as_str(), py.None()))
+        } else {
+            long.call_method1(method, (name.as_str(),))
+        };
+        assert_raises::<PyValueError>(py, result);
+    }
+    for operator in ["+=", "-=", "*=", "/="] {
+        assert_raises::<PyValueError>(py, augmented_assignment(py, &long, operator, &name));
+    }
+    let long_complex = complex_type.call1((name.as_str(),)).unwrap();
+    for method in ["__add__", "__sub__", "__mul__", "__truediv__"] {
+        assert_raises::<PyValueError>(py, long_complex.call_method1(method, (name.as_str(),)));
+    }
+}
+
+// Test that division by zero and too long symbolic expressions raise Python exceptions
+#[test]
+fn arithmetic_errors_raise_exceptions() {
+    pyo3::prepare_freethreaded_python();
+    Python::with_gil(assert_arithmetic_errors);
+}
+
+// Test that the arithmetic errors do not depend on unwinding in a panic=abort build
+#[cfg(panic = "abort")]
+#[test]
+fn arithmetic_errors_without_unwinding() {
+    pyo3::prepare_freethreaded_python();
+    Python::with_gil(assert_arithmetic_errors);
+}
@@ -0,0 +1,37 @@
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+use pyo3::wrap_pymodule;
+
+// Test that creating the module twice returns the same module with identical classes
+#[test]
+fn test_module_created_twice() {
+    pyo3::prepare_freethreaded_python();
+    Python::with_gil(|py| {
+        let first = wrap_pymodule!(qoqo_calculator_pyo3::qoqo_calculator_pyo3)(py);
+        let second = wrap_pymodule!(qoqo_calculator_pyo3::qoqo_calculator_pyo3)(py);
+        assert!(first.bind(py).is(second.bind(py)));
+        let first_class = first.bind(py).getattr("CalculatorFloat").unwrap();
+        let second_class = second.bind(py).getattr("CalculatorFloat").unwrap();
+        assert!(first_class.is(&second_class));
+
+        // The numbers registration of the classes is not repeated or broken by a second init
+        let locals = PyDict::new_bound(py);
+        locals.set_item("module", second.bind(py)).unwrap();
+        let is_real: bool = py
+            .eval_bound(
+                "__import__('numbers').Real.__subclasscheck__(module.CalculatorFloat)",
+                None,
+                Some(&locals),
+            )
+            .unwrap()
+            .extract()
+            .unwrap();
+        assert!(is_real);
+        let value: f64 = py
+            .eval_bound("module.parse('a * 2', {'a': 1.5})", None, Some(&locals))
+            .unwrap()
+            .extract()
+            .unwrap();
+        assert_eq!(value, 3.0);
+    })
+}
@@ -0,0 +1,456 @@
+// Copyright © 2020-2021 HQS Quantum Simulations GmbH. All Rights Reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except
+// in compliance with the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the
+// License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either
+// express or implied. See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The Python entry points of the module called from Python code.
+//!
+//! Every snippet is evaluated with the module created in the test process and the repr of
+//! its result is compared with the expected string, so binding regressions like a reflected
+//! operator that is not reached fail with `cargo test` without running pytest.
+//! Covering a new method is one entry in [SNIPPETS].
+
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+use pyo3::wrap_pymodule;
+
+/// Python code run before the snippets, defining the names the snippets use.
+const PRELUDE: &str = r#"
+import copy
+import math
+import pickle
+import sys
+
+sys.modules["qoqo_calculator_pyo3"] = module
+from qoqo_calculator_pyo3 import *
+
+def calculator(**variables):
+    calculator = Calculator()
+    calculator.set_variables(variables)
+    return calculator
+
+def frozen(**variables):
+    frozen = calculator(**variables)
+    frozen.freeze()
+    return frozen
+"#;
+
+/// Python expressions and the repr of their results.
+///
+/// An expected result `raises <name>` means the expression raises the exception `<name>`.
+const SNIPPETS: &[(&str, &str)] = &[
+    // CalculatorFloat arithmetic with mixed operand types
+    ("CalculatorFloat(1) + 2", "3e0"),
+    ("2 + CalculatorFloat(1)", "3e0"),
+    ("CalculatorFloat(\"x\") + 1.5", "(x + 1.5e0)"),
+    ("CalculatorFloat(3) - 1", "2e0"),
+    ("1 - CalculatorFloat(\"x\")", "(1e0 - x)"),
+    ("CalculatorFloat(3) * 2", "6e0"),
+    ("2 * CalculatorFloat(\"x\")", "(2e0 * x)"),
+    ("CalculatorFloat(3) / 2", "1.5e0"),
+    ("3 / CalculatorFloat(2)", "1.5e0"),
+    ("CalculatorFloat(2) ** 3", "8e0"),
+    ("2 ** CalculatorFloat(3)", "8e0"),
+    ("CalculatorFloat(7) // 2", "3e0"),
+    ("7 // CalculatorFloat(2)", "3e0"),
+    ("CalculatorFloat(7) % 2", "1e0"),
+    ("7 % CalculatorFloat(3)", "1e0"),
+    ("divmod(CalculatorFloat(7), 2)", "(3e0, 1e0)"),
+    ("divmod(7, CalculatorFloat(2))", "(3e0, 1e0)"),
+    ("CalculatorFloat(\"x\") + CalculatorFloat(\"y\")", "(x + y)"),
+    ("-CalculatorFloat(\"x\")", "(-x)"),
+    ("+CalculatorFloat(2)", "2e0"),
+    ("abs(CalculatorFloat(-2))", "2e0"),
+    ("~CalculatorFloat(2)", "5e-1"),
+    ("CalculatorFloat(1) / 0", "raises ZeroDivisionError"),
+    ("CalculatorFloat(1) + [1]", "raises TypeError"),
+    // CalculatorFloat casts, comparisons and copies
+    ("float(CalculatorFloat(2.5))", "2.5"),
+    ("complex(CalculatorFloat(2.5))", "(2.5+0j)"),
+    ("math.trunc(CalculatorFloat(-2.7))", "-2"),
+    ("math.floor(CalculatorFloat(-2.7))", "-3"),
+    ("math.ceil(CalculatorFloat(-2.7))", "-2"),
+    ("round(CalculatorFloat(2.567), 2)", "2.57e0"),
+    ("round(CalculatorFloat(2.5))", "2"),
+    ("int(CalculatorFloat(-2.7))", "-2"),
+    ("format(CalculatorFloat(2.5), \"\")", "'2.5e0'"),
+    ("float(CalculatorFloat(\"x\"))", "raises ValueError"),
+    ("CalculatorFloat(2) == 2", "True"),
+    ("CalculatorFloat(\"x\") == \"x\"", "True"),
+    ("CalculatorFloat(2) != CalculatorFloat(3)", "True"),
+    ("CalculatorFloat(2) < 3", "True"),
+    ("copy.copy(CalculatorFloat(\"x\"))", "x"),
+    ("copy.deepcopy(CalculatorFloat(1.5))", "1.5e0"),
+    (
+        "pickle.loads(pickle.dumps(CalculatorFloat(\"2 * x\")))",
+        "2 * x",
+    ),
+    ("pickle.loads(pickle.dumps(CalculatorFloat(1.5)))", "1.5e0"),
+    ("CalculatorFloat([1])", "raises TypeError"),
+    // CalculatorFloat methods
+    ("CalculatorFloat(2).is_float", "True"),
+    ("CalculatorFloat(\"x\").is_float", "False"),
+    ("CalculatorFloat(\"x\").is_symbolic", "True"),
+    ("CalculatorFloat(0).is_zero", "True"),
+    ("CalculatorFloat(1).is_one", "True"),
+    ("CalculatorFloat(2).float()", "2.0"),
+    ("CalculatorFloat(\"x\").float()", "raises TypeError"),
+    ("CalculatorFloat(4).sqrt()", "2e0"),
+    (
+        "CalculatorFloat(\"b * sin(theta) + b\").variables",
+        "['b', 'theta']",
+    ),
+    ("CalculatorFloat(2).variables", "[]"),
+    (
+        "CalculatorFloat(\"(2 * 3) * x + 0\").simplify()",
+        "(6e0 * x)",
+    ),
+    ("CalculatorFloat(\"2 * 3\").simplify().is_float", "True"),
+    ("CalculatorFloat(1).atan2(1)", "7.853981633974483e-1"),
+    ("CalculatorFloat(-7).rem(3)", "-1e0"),
+    ("CalculatorFloat(\"phi\").rem(2)", "(phi % 2e0)"),
+    ("CalculatorFloat(1).rem(0)", "raises ZeroDivisionError"),
+    ("CalculatorFloat(1).isclose(1.0 + 1e-12)", "True"),
+    (
+        "CalculatorFloat.isclose_math(CalculatorFloat(1e-17), 0)",
+        "False",
+    ),
+    (
+        "CalculatorFloat.isclose_math(1, 1.1, rel_tol=0.2, abs_tol=0)",
+        "True",
+    ),
+    (
+        "CalculatorFloat.isclose_math(\"x\", 1)",
+        "raises ValueError",
+    ),
+    ("CalculatorFloat(0).exp()", "1e0"),
+    ("CalculatorFloat(0).sin()", "0e0"),
+    ("CalculatorFloat(0).cos()", "1e0"),
+    ("CalculatorFloat(1).acos()", "0e0"),
+    ("CalculatorFloat(0).tan()", "0e0"),
+    ("CalculatorFloat(0).asin()", "0e0"),
+    ("CalculatorFloat(0).atan()", "0e0"),
+    ("CalculatorFloat(0).sinh()", "0e0"),
+    ("CalculatorFloat(0).cosh()", "1e0"),
+    ("CalculatorFloat(0).tanh()", "0e0"),
+    ("CalculatorFloat(3).exp2()", "8e0"),
+    ("CalculatorFloat(\"x\").ln()", "log(x)"),
+    ("CalculatorFloat(8).log2()", "3e0"),
+    ("CalculatorFloat(100).log10()", "2e0"),
+    ("CalculatorFloat(\"x\").log(2)", "(log(x) / log(2e0))"),
+    ("CalculatorFloat(-1.5).floor()", "-2e0"),
+    ("CalculatorFloat(-1.5).ceil()", "-1e0"),
+    ("CalculatorFloat(-1.5).round()", "-2e0"),
+    ("CalculatorFloat(-3).abs()", "3e0"),
+    ("CalculatorFloat(-3).signum()", "-1e0"),
+    ("CalculatorFloat(-3).sign()", "-1e0"),
+    ("CalculatorFloat(4).factorial()", "2.4e1"),
+    ("CalculatorFloat(1.23456).round_to_digits(3)", "1.235e0"),
+    ("CalculatorFloat(1.26).round_to_multiple(0.5)", "1.5e0"),
+    ("CalculatorFloat(1).heaviside()", "1e0"),
+    ("CalculatorFloat(-1).step()", "0e0"),
+    ("CalculatorFloat(0).delta()", "1e0"),
+    ("CalculatorFloat(\"{n} * x\").placeholders()", "['n']"),
+    (
+        "CalculatorFloat(\"0.5 * x\").embedded_literals()",
+        "[(0.5, 0, 3)]",
+    ),
+    (
+        "CalculatorFloat(\"{n} * x\").bind_placeholders({\"n\": 2})",
+        "2e0 * x",
+    ),
+    (
+        "CalculatorFloat(\"2*theta + phi\").substitute(\"theta\", 0.5)",
+        "2*(5e-1) + phi",
+    ),
+    (
+        "CalculatorFloat(\"x**2\").to_sympy_string()",
+        "\"Symbol('x')**2.0\"",
+    ),
+    ("CalculatorFloat(1.5).value", "1.5"),
+    ("CalculatorFloat(2).to_int()", "2"),
+    ("CalculatorFloat(2.5).to_int(\"floor\")", "2"),
+    ("CalculatorFloat(2.5).to_int()", "raises ValueError"),
+    ("CalculatorFloat(2).real", "2e0"),
+    ("CalculatorFloat(2).imag", "0e0"),
+    ("CalculatorFloat(2).conjugate()", "2e0"),
+    ("CalculatorFloat(\"x\").promote()", "(x + i * 0e0)"),
+    (
+        "CalculatorFloat(2).canonical_hash() == CalculatorFloat(2.0).canonical_hash()",
+        "True",
+    ),
+    (
+        "CalculatorFloat(2).canonical_hash_128() == CalculatorFloat(2.0).canonical_hash_128()",
+        "True",
+    ),
+    // CalculatorComplex arithmetic with mixed operand types
+    ("CalculatorComplex(1) + 2", "(3e0 + i * 0e0)"),
+    ("2 + CalculatorComplex(1)", "(3e0 + i * 0e0)"),
+    ("CalculatorComplex.from_pair(1, 2) - 1", "(0e0 + i * 2e0)"),
+    ("1 - CalculatorComplex.from_pair(1, 2)", "(0e0 + i * -2e0)"),
+    ("CalculatorComplex.from_pair(1, 2) * 2", "(2e0 + i * 4e0)"),
+    ("2j * CalculatorComplex(1)", "(0e0 + i * 2e0)"),
+    (
+        "CalculatorComplex(1) * CalculatorFloat(\"x\")",
+        "(x + i * 0e0)",
+    ),
+    ("CalculatorComplex(4) / 2", "(2e0 + i * 0e0)"),
+    ("2 / CalculatorComplex(4)", "(5e-1 + i * 0e0)"),
+    ("CalculatorComplex(2) ** 2", "(4e0 + i * 0e0)"),
+    ("2 ** CalculatorComplex(2)", "(4e0 + i * 0e0)"),
+    ("-CalculatorComplex.from_pair(1, 2)", "(-1e0 + i * -2e0)"),
+    ("+CalculatorComplex.from_pair(1, 2)", "(1e0 + i * 2e0)"),
+    ("abs(CalculatorComplex.from_pair(3, 4))", "5e0"),
+    ("~CalculatorComplex.from_pair(1, 2)", "(2e-1 + i * -4e-1)"),
+    ("CalculatorComplex(1) / 0", "raises ZeroDivisionError"),
+    // CalculatorComplex casts, comparisons, copies and methods
+    ("complex(CalculatorComplex.from_pair(1, 2))", "(1+2j)"),
+    ("float(CalculatorComplex(2))", "2.0"),
+    (
+        "float(CalculatorComplex.from_pair(1, 2))",
+        "raises ValueError",
+    ),
+    (
+        "format(CalculatorComplex.from_pair(1, 2), \"\")",
+        "'(1e0 + i * 2e0)'",
+    ),
+    ("CalculatorComplex.from_pair(1, 2) == complex(1, 2)", "True"),
+    ("CalculatorComplex(1) != 2", "True"),
+    (
+        "copy.copy(CalculatorComplex.from_pair(\"x\", 1))",
+        "(x + i * 1e0)",
+    ),
+    (
+        "copy.deepcopy(CalculatorComplex.from_pair(1, \"y\"))",
+        "(1e0 + i * y)",
+    ),
+    (
+        "pickle.loads(pickle.dumps(CalculatorComplex.from_pair(\"x\", 2)))",
+        "(x + i * 2e0)",
+    ),
+    (
+        "CalculatorComplex.from_pair(1, 2).__getstate__()",
+        "(1.0, 2.0)",
+    ),
+    (
+        "sorted(CalculatorComplex.from_pair(1, 2).to_dict().items())",
+        "[('imag', 2.0), ('is_calculator_complex', True), ('real', 1.0)]",
+    ),
+    ("CalculatorComplex.from_pair(1, 2).real", "1e0"),
+    ("CalculatorComplex.from_pair(1, 2).imag", "2e0"),
+    ("CalculatorComplex(0).is_zero", "True"),
+    ("CalculatorComplex.from_pair(\"x\", 0).is_real", "True"),
+    (
+        "CalculatorComplex.from_pair(1, 2).conj()",
+        "(1e0 + i * -2e0)",
+    ),
+    (
+        "CalculatorComplex.from_pair(1, 2).conjugate()",
+        "(1e0 + i * -2e0)",
+    ),
+    (
+        "CalculatorComplex.exp_i(\"theta\")",
+        "(cos(theta) + i * sin(theta))",
+    ),
+    ("CalculatorComplex.exp_i(0.5).is_on_unit_circle()", "True"),
+    (
+        "CalculatorComplex.from_polar(\"r\", \"theta\")",
+        "((r * cos(theta)) + i * (r * sin(theta)))",
+    ),
+    (
+        "CalculatorComplex.from_polar(2, 0).to_polar()",
+        "(2e0, 0e0)",
+    ),
+    ("CalculatorComplex.I", "(0e0 + i * 1e0)"),
+    (
+        "CalculatorComplex.ONE + CalculatorComplex.ZERO",
+        "(1e0 + i * 0e0)",
+    ),
+    (
+        "CalculatorComplex(2).is_on_unit_circle(tolerance=0.5)",
+        "False",
+    ),
+    ("CalculatorComplex(0).sinh()", "(0e0 + i * 0e0)"),
+    ("CalculatorComplex(0).cosh()", "(1e0 + i * 0e0)"),
+    ("CalculatorComplex(0).tan()", "(0e0 + i * 0e0)"),
+    ("CalculatorComplex(0).exp()", "(1e0 + i * 0e0)"),
+    ("CalculatorComplex(0).sin()", "(0e0 + i * 0e0)"),
+    ("CalculatorComplex(0).cos()", "(1e0 + i * -0e0)"),
+    ("CalculatorComplex(4).sqrt()", "(2e0 + i * 0e0)"),
+    ("CalculatorComplex(2).powf(3)", "(8e0 + i * 0e0)"),
+    ("CalculatorComplex(2).powf([1])", "raises TypeError"),
+    ("CalculatorComplex(-1).arg()", "3.141592653589793e0"),
+    ("CalculatorComplex(-1).arg_checked()", "3.141592653589793e0"),
+    ("CalculatorComplex(0).arg_checked()", "raises ValueError"),
+    ("CalculatorComplex.from_pair(\"x\", 0).demote()", "x"),
+    (
+        "CalculatorComplex.from_pair(1, 1e-20).demote()",
+        "raises ValueError",
+    ),
+    (
+        "CalculatorComplex.from_pair(1, 1e-20).demote_lossy()",
+        "(1e0, True)",
+    ),
+    ("CalculatorComplex(1).demote_lossy()", "(1e0, False)"),
+    (
+        "CalculatorComplex.from_pair(\"x * y\", \"sin(x)\").substitute(\"x\", \"z\")",
+        "(z * y + i * sin(z))",
+    ),
+    (
+        "CalculatorComplex.from_pair(1, 2).isclose(complex(1, 2))",
+        "True",
+    ),
+    ("CalculatorComplex.from_pair(3, 4).abs()", "5e0"),
+    (
+        "CalculatorComplex.from_pair(\"x\", 1).to_sympy_string()",
+        "\"(Symbol('x')) + I*(1.0)\"",
+    ),
+    (
+        "CalculatorComplex(1).canonical_hash() == CalculatorComplex(1.0).canonical_hash()",
+        "True",
+    ),
+    // Calculator
+    ("calculator(a=2).parse_str(\"a + 1\")", "3.0"),
+    ("calculator(a=2).get_variables([\"a\"])", "[2.0]"),
+    ("calculator().parse_str_assign(\"a=3; a + 1\")", "4.0"),
+    (
+        "calculator(x=2).parse_str_derivative(\"x^2\", \"x\")",
+        "(4.0, 4.0)",
+    ),
+    (
+        "calculator(a=1).parse_str_with_warnings(\"a + 1\")",
+        "(2.0, [])",
+    ),
+    (
+        "calculator(a=1).check_str(\"a + b\", {\"a\", \"b\"})[\"variables\"]",
+        "['a', 'b']",
+    ),
+    (
+        "calculator().validate_expression(\"a * sin(b)\")",
+        "['a', 'b']",
+    ),
+    (
+        "calculator(b=2, a=1).to_json()",
+        "'{\"variables\":{\"a\":1.0,\"b\":2.0},\"expression_variables\":{}}'",
+    ),
+    (
+        "Calculator.from_json(calculator(a=1).to_json()).get_variable(\"a\")",
+        "1.0",
+    ),
+    (
+        "calculator().parse_str_assign_with_warnings(\"a=1; 2\")",
+        "(2.0, ['Variable \"a\" assigned at position 0 is not read afterwards'])",
+    ),
+    (
+        "calculator(c=1).sweep(\"x + c\", [(\"x\", [1, 2])])",
+        "([('x', [1.0, 2.0])], [2.0, 3.0])",
+    ),
+    (
+        "calculator().evaluate_over(\"x^2\", \"x\", [1, 2, 3])",
+        "[1.0, 4.0, 9.0]",
+    ),
+    (
+        "calculator(x=3).parse_get(CalculatorFloat(\"x * 2\"))",
+        "6.0",
+    ),
+    ("calculator(x=3).parse_get(2.5)", "2.5"),
+    ("calculator(x=2).parse_str_complex(\"x + 3i\")", "(2+3j)"),
+    (
+        "calculator(x=3).parse_get_complex(CalculatorComplex.from_pair(\"x\", 1))",
+        "(3+1j)",
+    ),
+    ("calculator(a=2).remove_variable(\"a\")", "2.0"),
+    ("calculator(a=2).get_variable(\"a\")", "2.0"),
+    ("calculator().get_variable(\"a\")", "raises KeyError"),
+    (
+        "calculator().set_variable(\"a\", CalculatorFloat(2))",
+        "None",
+    ),
+    (
+        "calculator().set_variable(\"a\", CalculatorFloat(\"x\"))",
+        "raises ValueError",
+    ),
+    ("copy.copy(calculator(x=2)).parse_str(\"x\")", "2.0"),
+    ("copy.deepcopy(frozen()).is_frozen()", "True"),
+    ("calculator().is_frozen()", "False"),
+    ("frozen().is_frozen()", "True"),
+    ("frozen(a=2).parse_str(\"a + 1\")", "3.0"),
+    ("frozen().set(\"a\", 1)", "raises CalculatorFrozenError"),
+    (
+        "frozen().parse_str_assign(\"a=1\")",
+        "raises CalculatorFrozenError",
+    ),
+    ("calculator(a=2).parse_str(\"max[a, 3, 1] + sum[]\")", "3.0"),
+    ("calculator().parse_str(\"max[]\")", "raises ValueError"),
+    ("calculator().parse_str(\"1 / 0\")", "raises ValueError"),
+    (
+        "calculator().parse_str(\"unknown + 1\")",
+        "raises ValueError",
+    ),
+    // Module functions
+    ("parse(\"a * 2\", {\"a\": 1.5})", "3.0"),
+    ("parse_string_assign(\"a=2; a * 3\")", "6.0"),
+    ("validate(\"a + 1\")", "None"),
+    ("validate(\"a = 1\")", "raises ValueError"),
+    ("variables_in(\"a + b * a\")", "['a', 'b']"),
+    ("simplify(\"(2 * 3) * x + 0\")", "'(6e0 * x)'"),
+    ("simplify(\"a + $\")", "raises ValueError"),
+    ("pi_fraction(1, 2)", "1.5707963267948966e0"),
+    ("dot([1, 2], [3, \"x\"])", "((2e0 * x) + 3e0)"),
+    ("norm_sqr([3, 4])", "2.5e1"),
+    (
+        "\"sin\" in [function[\"name\"] for function in functions()]",
+        "True",
+    ),
+    ("\"DivisionByZero\" in error_kinds()", "True"),
+    (
+        "[constant[\"name\"] for constant in constants()]",
+        "['E', 'PI', 'e', 'pi', 'tau']",
+    ),
+    (
+        "[operator[\"symbol\"] for operator in operators()][:3]",
+        "['=', '+=', '-=']",
+    ),
+];
+
+/// Evaluate a snippet and return the repr of its result or the name of the raised exception.
+fn evaluate(py: Python, snippet: &str, globals: &Bound<PyDict>) -> String {
+    match py.eval_bound(snippet, Some(globals), None) {
+        Ok(result) => result.repr().unwrap().to_string(),
+        Err(error) => format!("raises {}", error.get_type_bound(py).name().unwrap()),
+    }
+}
+
+// Test all snippets and report every snippet with an unexpected result
+#[test]
+fn test_python_snippets() {
+    pyo3::prepare_freethreaded_python();
+    Python::with_gil(|py| {
+        let module = wrap_pymodule!(qoqo_calculator_pyo3::qoqo_calculator_pyo3)(py);
+        let globals = PyDict::new_bound(py);
+        globals.set_item("module", module.bind(py)).unwrap();
+        py.run_bound(PRELUDE, Some(&globals), None).unwrap();
+        let failures: Vec<String> = SNIPPETS
+            .iter()
+            .filter_map(|(snippet, expected)| {
+                let result = evaluate(py, snippet, &globals);
+                (result != *expected)
+                    .then(|| format!("{snippet}\n    expected: {expected}\n    found:    {result}"))
+            })
+            .collect();
+        assert!(
+            failures.is_empty(),
+            "{} of {} snippets failed:\n{}",
+            failures.len(),
+            SNIPPETS.len(),
+            failures.join("\n")
+        );
+    })
+}